@@ -1,8 +1,11 @@
 import axios from 'axios'
-import type { MediaFile, PaginatedResponse, DateInfo, GpsInfo } from '@/types'
+import type { MediaFile, PaginatedResponse, DateInfo, GpsInfo, TimelineGroup, TimelineGranularity } from '@/types'
 
 const API_BASE = '/api'
 
+// 单次请求的最大条数，与后端 LATTE_API_MAX_PAGE_SIZE 的默认值一致，超出会返回 400
+export const MAX_PAGE_SIZE = 200
+
 // 创建axios实例
 const apiClient = axios.create({
   baseURL: API_BASE,
@@ -78,6 +81,11 @@ export const fileApi = {
     return apiClient.get<DateInfo[]>('/files/dates', { params })
   },
 
+  // 按日/周/月/年分组的时间线（周起始日由服务端配置决定）
+  getTimeline: (params: { granularity?: TimelineGranularity; filterType?: string }) => {
+    return apiClient.get<TimelineGroup[]>('/files/timeline', { params })
+  },
+
   // 按需获取照片的 GPS 经纬度（敏感信息端点，仅在用户主动展开位置信息折叠区时调用）
   getFileGps: (id: string) => {
     return apiClient.get<GpsInfo>(`/files/${id}/gps`)
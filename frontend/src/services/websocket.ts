@@ -1,4 +1,5 @@
 export interface ScanProgressMessage {
+  type?: 'scan'
   scanning: boolean
   phase?: string          // 当前阶段: collecting, counting, processing, writing, deleting, completed, error, cancelled
   totalFiles: number
@@ -11,14 +12,31 @@ export interface ScanProgressMessage {
   filesToAdd?: number
   filesToUpdate?: number
   filesToDelete?: number
+  // 读取量与最近的读取速率（字节/秒）
+  bytesRead?: number
+  throughputBytesPerSec?: number
+}
+
+// 缩略图预生成进度（同一连接上 type 为 thumbnails 的消息）
+export interface ThumbnailProgressMessage {
+  type: 'thumbnails'
+  running: boolean
+  status: 'idle' | 'started' | 'progress' | 'completed'
+  total: number
+  successCount: number
+  failureCount: number
+  progressPercentage: string
+  startTime?: string
 }
 
 type ProgressCallback = (progress: ScanProgressMessage) => void
+type ThumbnailProgressCallback = (progress: ThumbnailProgressMessage) => void
 
 class ScanProgressWebSocketService {
   private ws: WebSocket | null = null
   private isConnected = false
   private progressCallback: ProgressCallback | null = null
+  private thumbnailProgressCallback: ThumbnailProgressCallback | null = null
   private reconnectTimer: number | null = null
 
   /**
@@ -54,10 +72,12 @@ class ScanProgressWebSocketService {
 
         this.ws.onmessage = (event) => {
           try {
-            const progress: ScanProgressMessage = JSON.parse(event.data)
-            console.log('[WebSocket] 收到进度更新:', progress)
-            if (this.progressCallback) {
-              this.progressCallback(progress)
+            const message = JSON.parse(event.data)
+            console.log('[WebSocket] 收到进度更新:', message)
+            if (message.type === 'thumbnails') {
+              this.thumbnailProgressCallback?.(message as ThumbnailProgressMessage)
+            } else if (this.progressCallback) {
+              this.progressCallback(message as ScanProgressMessage)
             }
           } catch (e) {
             console.error('[WebSocket] 解析进度消息失败:', e)
@@ -120,6 +140,20 @@ class ScanProgressWebSocketService {
     this.progressCallback = null
   }
 
+  /**
+   * 设置缩略图预生成进度回调
+   */
+  onThumbnailProgress(callback: ThumbnailProgressCallback): void {
+    this.thumbnailProgressCallback = callback
+  }
+
+  /**
+   * 移除缩略图预生成进度回调
+   */
+  offThumbnailProgress(): void {
+    this.thumbnailProgressCallback = null
+  }
+
 }
 
 // 导出单例
@@ -20,6 +20,10 @@ export interface MediaFile {
   focalLength?: string
   duration?: number
   videoCodec?: string
+  hdrFormat?: 'hlg' | 'pq' | 'dolby_vision'
+  bitDepth?: number
+  colorPrimaries?: string
+  colorProfile?: string
 }
 
 export interface DateInfo {
@@ -27,6 +31,17 @@ export interface DateInfo {
   count: number
 }
 
+export type TimelineGranularity = 'day' | 'week' | 'month' | 'year'
+
+export interface TimelineGroup {
+  start: string
+  end: string
+  label: string
+  count: number
+  firstId: string
+  lastId: string
+}
+
 export interface Directory {
   id: number
   path: string
@@ -14,6 +14,20 @@ export const formatDuration = (seconds: number): string => {
   return `${minutes}:${remainingSeconds.toString().padStart(2, '0')}`
 }
 
+/**
+ * 格式化视频 HDR 格式
+ * @param format 后端返回的 hdrFormat（hlg / pq / dolby_vision）
+ * @returns 显示名称，如 "HDR10 (PQ)"
+ */
+export const formatHdrFormat = (format: string): string => {
+  switch (format) {
+    case 'hlg': return 'HLG'
+    case 'pq': return 'HDR10 (PQ)'
+    case 'dolby_vision': return 'Dolby Vision'
+    default: return format
+  }
+}
+
 /**
  * 格式化文件大小
  * @param bytes 字节数
@@ -0,0 +1,114 @@
+//! Benchmarks over a seeded synthetic library
+//!
+//! Run with: `cargo bench --bench library`
+//! Covers the list query, batch upserts and the scan skip-list check, the paths that
+//! dominate large libraries.
+
+use criterion::{criterion_group, criterion_main, BatchSize, Criterion};
+use latte_album::config::Config;
+use latte_album::db::{DatabasePool, FileQuery, MediaFileRepository, SortField, SortOrder};
+use latte_album::fixtures::SyntheticLibrary;
+use latte_album::processors::ProcessorRegistry;
+use latte_album::services::ScanService;
+use latte_album::websocket::ScanStateManager;
+use std::sync::Arc;
+use tempfile::TempDir;
+use tokio::runtime::Runtime;
+
+const SEED: u64 = 42;
+const LIBRARY_SIZE: usize = 20_000;
+const UPSERT_BATCH: usize = 500;
+const SCAN_FILES: usize = 2_000;
+
+async fn seeded_db(dir: &TempDir, count: usize) -> DatabasePool {
+    let db = DatabasePool::new(&dir.path().join("bench.db")).await.expect("Failed to create database pool");
+    db.migrate(std::path::Path::new("./src/db/migrations")).await.expect("Failed to run migrations");
+    SyntheticLibrary::new(SEED).seed_database(&db, count).await.expect("Failed to seed database");
+    db
+}
+
+fn bench_find_all(c: &mut Criterion) {
+    let rt = Runtime::new().unwrap();
+    let dir = TempDir::new().unwrap();
+    let db = rt.block_on(seeded_db(&dir, LIBRARY_SIZE));
+    let repo = MediaFileRepository::new(&db);
+
+    let mut group = c.benchmark_group("find_all");
+    let first_page = FileQuery::new();
+    group.bench_function("first_page", |b| b.to_async(&rt).iter(|| repo.find_all(&first_page)));
+    let deep_page = FileQuery::new().page(LIBRARY_SIZE as i32 / 50 - 1, 50);
+    group.bench_function("deep_page", |b| b.to_async(&rt).iter(|| repo.find_all(&deep_page)));
+    let filtered = FileQuery::new()
+        .camera_model(Some("X100V"))
+        .date(Some("2020"))
+        .sort(SortField::FileName, SortOrder::Asc);
+    group.bench_function("filtered", |b| b.to_async(&rt).iter(|| repo.find_all(&filtered)));
+    group.bench_function("count", |b| b.to_async(&rt).iter(|| repo.count(&filtered)));
+    group.finish();
+}
+
+fn bench_batch_upsert(c: &mut Criterion) {
+    let rt = Runtime::new().unwrap();
+    let dir = TempDir::new().unwrap();
+    let db = rt.block_on(seeded_db(&dir, LIBRARY_SIZE));
+    let repo = MediaFileRepository::new(&db);
+
+    // 更新已有行（重扫的常见情形）与插入新行分开测量
+    let existing = SyntheticLibrary::new(SEED).media_files(UPSERT_BATCH);
+    c.bench_function("batch_upsert/update_500", |b| {
+        b.to_async(&rt).iter(|| repo.batch_upsert(&existing))
+    });
+
+    // 每次迭代生成新编号的文件，保证都是插入
+    let mut fresh = SyntheticLibrary::with_root(SEED, "/synthetic-new");
+    let repo = &repo;
+    c.bench_function("batch_upsert/insert_500", |b| {
+        b.to_async(&rt).iter_batched(
+            || fresh.media_files(UPSERT_BATCH),
+            |files| async move { repo.batch_upsert(&files).await },
+            BatchSize::SmallInput,
+        )
+    });
+}
+
+/// The scan's skip list: one bulk lookup per chunk plus an mtime comparison per file
+fn bench_scan_skip_list(c: &mut Criterion) {
+    let rt = Runtime::new().unwrap();
+    let dir = TempDir::new().unwrap();
+    let photos = dir.path().join("photos");
+    let paths = SyntheticLibrary::with_root(SEED, &photos).write_jpegs(SCAN_FILES).unwrap();
+    let db = rt.block_on(seeded_db(&dir, 0));
+
+    // Rows for half of the files, with the real mtimes so they land in the skip list
+    let mut files = SyntheticLibrary::with_root(SEED, &photos).media_files(SCAN_FILES / 2);
+    for file in &mut files {
+        let modified = std::fs::metadata(&file.file_path).and_then(|m| m.modified()).unwrap();
+        let secs = modified.duration_since(std::time::UNIX_EPOCH).unwrap().as_secs() as i64;
+        file.modify_time = chrono::DateTime::from_timestamp(secs, 0).map(|t| t.naive_utc());
+    }
+    rt.block_on(MediaFileRepository::new(&db).batch_upsert(&files)).unwrap();
+
+    let config = Config {
+        base_path: photos.clone(),
+        db_path: dir.path().join("bench.db"),
+        ..Config::default()
+    };
+    let (tx, _rx) = tokio::sync::broadcast::channel(100);
+    let scan_service = ScanService::new(
+        config,
+        db.clone(),
+        Arc::new(ProcessorRegistry::new(None)),
+        Arc::new(ScanStateManager::new(tx)),
+    );
+
+    c.bench_function("scan/batch_check_exists_2000", |b| {
+        b.to_async(&rt).iter(|| scan_service.batch_check_exists(&paths))
+    });
+}
+
+criterion_group! {
+    name = benches;
+    config = Criterion::default().sample_size(20);
+    targets = bench_find_all, bench_batch_upsert, bench_scan_skip_list
+}
+criterion_main!(benches);
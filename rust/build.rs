@@ -12,11 +12,28 @@ fn main() {
         link_system_libraries();
     }
 
+    embed_git_hash();
+
     // Link against C++ standard library in Linux, or there would be compile error
     #[cfg(target_os = "linux")]
     println!("cargo:rustc-link-lib=dylib=stdc++");
 }
 
+/// Expose the short commit hash as LATTE_GIT_HASH for /api/system/info
+fn embed_git_hash() {
+    println!("cargo:rerun-if-changed=../.git/HEAD");
+    println!("cargo:rerun-if-changed=../.git/refs/heads");
+
+    let hash = std::process::Command::new("git")
+        .args(["rev-parse", "--short", "HEAD"])
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .map(|output| String::from_utf8_lossy(&output.stdout).trim().to_string())
+        .unwrap_or_else(|| "unknown".to_string());
+    println!("cargo:rustc-env=LATTE_GIT_HASH={}", hash);
+}
+
 /// Link against system libraries using pkg-config
 fn link_system_libraries() {
     use pkg_config::Config;
@@ -0,0 +1,57 @@
+use crate::{
+    api::{
+        files::{self, PaginatedResponse},
+        i18n::{self, Locale},
+        AppState,
+    },
+    app::State,
+    db::ActivityRepository,
+};
+use axum::{debug_handler, extract::Query, http::StatusCode, response::IntoResponse, Json};
+use serde::Deserialize;
+use tracing::warn;
+
+/// Query parameters for the activity feed
+#[derive(Debug, Deserialize)]
+pub struct ActivityParams {
+    pub page: Option<i32>,
+    pub size: Option<i32>,
+}
+
+/// 首页“最近动态”：扫描新增的文件（按天合并）、备注与标题/描述编辑，按时间倒序分页返回
+#[debug_handler]
+pub async fn list_activity(
+    State(state): State<AppState>,
+    locale: Locale,
+    Query(params): Query<ActivityParams>,
+) -> impl IntoResponse {
+    let (page, size) = match files::validate_pagination(params.page, params.size, &state.config) {
+        Ok(pagination) => pagination,
+        Err((message, detail)) => return i18n::error_with(StatusCode::BAD_REQUEST, locale, message, &detail),
+    };
+
+    let repo = ActivityRepository::new(&state.db);
+    let items = match repo.find_page(page, size).await {
+        Ok(items) => items,
+        Err(e) => {
+            warn!("Failed to load activity: {}", e);
+            return (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response();
+        }
+    };
+    let total = match repo.count().await {
+        Ok(total) => total,
+        Err(e) => {
+            warn!("Failed to count activity: {}", e);
+            return (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response();
+        }
+    };
+
+    Json(PaginatedResponse {
+        items,
+        total,
+        page,
+        size,
+        total_pages: ((total as f64) / (size as f64)).ceil() as i32,
+    })
+    .into_response()
+}
@@ -0,0 +1,33 @@
+use crate::{
+    api::{
+        i18n::{self, Locale, Message},
+        AppState,
+    },
+    app::State,
+    services::config_check,
+};
+use axum::{debug_handler, http::StatusCode, response::IntoResponse, Json};
+use tracing::warn;
+
+/// 校验当前生效的配置（含热重载后的值），返回与启动时日志相同的摘要与问题列表，供设置页展示
+#[debug_handler]
+pub async fn validate_config(State(state): State<AppState>) -> impl IntoResponse {
+    let config = state.config_reloader.current().await;
+    // 服务已占用端口，这里不检查端口
+    match tokio::task::spawn_blocking(move || config_check::validate(&config, false)).await {
+        Ok(report) => Json(report).into_response(),
+        Err(e) => {
+            warn!("Config validation failed: {}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response()
+        }
+    }
+}
+
+/// 重新读取配置并应用可在运行中修改的设置（与 SIGHUP 相同），返回已生效与需重启的配置项
+#[debug_handler]
+pub async fn reload_config(State(state): State<AppState>, locale: Locale) -> impl IntoResponse {
+    match state.config_reloader.reload().await {
+        Ok(outcome) => Json(outcome).into_response(),
+        Err(e) => i18n::error_with(StatusCode::BAD_REQUEST, locale, Message::ConfigReloadFailed, &e.to_string()),
+    }
+}
@@ -0,0 +1,141 @@
+use crate::{
+    api::{
+        i18n::{self, Locale, Message},
+        AppState,
+    },
+    app::State,
+    db::{AttributeRepository, MediaAttribute, MediaFileRepository},
+};
+use axum::{
+    debug_handler,
+    extract::{Path, Query},
+    http::StatusCode,
+    response::IntoResponse,
+    Json,
+};
+use serde::Deserialize;
+use tracing::warn;
+
+/// Source recorded when a request does not name one
+const DEFAULT_SOURCE: &str = "api";
+
+/// Longest accepted attribute value
+const MAX_VALUE_LEN: usize = 4096;
+
+/// Request body for setting an attribute
+#[derive(Debug, Deserialize)]
+pub struct SetAttributeRequest {
+    pub value: String,
+    /// Producer of the value (e.g. a classifier name); defaults to "api"
+    pub source: Option<String>,
+}
+
+/// Query parameters for deleting an attribute
+#[derive(Debug, Deserialize)]
+pub struct DeleteAttributeParams {
+    /// Source whose value is removed; defaults to "api"
+    pub source: Option<String>,
+}
+
+/// Keys and sources: 1-64 characters of letters, digits and `_ . : -`
+fn is_valid_name(name: &str) -> bool {
+    !name.is_empty()
+        && name.len() <= 64
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '.' | ':' | '-'))
+}
+
+/// 列出文件的全部扩展属性（按键排序）
+#[debug_handler]
+pub async fn list_attributes(
+    State(state): State<AppState>,
+    locale: Locale,
+    Path(id): Path<String>,
+) -> impl IntoResponse {
+    match MediaFileRepository::new(&state.db).find_by_id(&id).await {
+        Ok(Some(_)) => {}
+        Ok(None) => return i18n::error(StatusCode::NOT_FOUND, locale, Message::FileNotFound),
+        Err(e) => {
+            warn!("Failed to get file {}: {}", id, e);
+            return (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response();
+        }
+    }
+
+    match AttributeRepository::new(&state.db).find_by_file(&id).await {
+        Ok(attributes) => Json(attributes).into_response(),
+        Err(e) => {
+            warn!("Failed to list attributes of {}: {}", id, e);
+            (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response()
+        }
+    }
+}
+
+/// 设置（新增或覆盖）一个扩展属性，供外部分类器等写入；扫描只替换自身来源（exif）的属性
+#[debug_handler]
+pub async fn set_attribute(
+    State(state): State<AppState>,
+    locale: Locale,
+    Path((id, key)): Path<(String, String)>,
+    Json(request): Json<SetAttributeRequest>,
+) -> impl IntoResponse {
+    let source = request.source.as_deref().map(str::trim).unwrap_or(DEFAULT_SOURCE);
+    let value = request.value.trim();
+    if !is_valid_name(&key) || !is_valid_name(source) || value.is_empty() || value.len() > MAX_VALUE_LEN {
+        return i18n::error(StatusCode::BAD_REQUEST, locale, Message::InvalidAttribute);
+    }
+
+    match MediaFileRepository::new(&state.db).find_by_id(&id).await {
+        Ok(Some(_)) => {}
+        Ok(None) => return i18n::error(StatusCode::NOT_FOUND, locale, Message::FileNotFound),
+        Err(e) => {
+            warn!("Failed to get file {}: {}", id, e);
+            return (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response();
+        }
+    }
+
+    let attribute = MediaAttribute {
+        key,
+        value: value.to_string(),
+        source: source.to_string(),
+    };
+    match AttributeRepository::new(&state.db).set(&id, &attribute).await {
+        Ok(()) => Json(attribute).into_response(),
+        Err(e) => {
+            warn!("Failed to set attribute {} of {}: {}", attribute.key, id, e);
+            (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response()
+        }
+    }
+}
+
+/// 删除一个来源的扩展属性，其他来源的同名属性保留
+#[debug_handler]
+pub async fn delete_attribute(
+    State(state): State<AppState>,
+    locale: Locale,
+    Path((id, key)): Path<(String, String)>,
+    Query(params): Query<DeleteAttributeParams>,
+) -> impl IntoResponse {
+    let source = params.source.as_deref().unwrap_or(DEFAULT_SOURCE);
+    match AttributeRepository::new(&state.db).delete(&id, source, &key).await {
+        Ok(false) => i18n::error(StatusCode::NOT_FOUND, locale, Message::AttributeNotFound),
+        Ok(true) => StatusCode::NO_CONTENT.into_response(),
+        Err(e) => {
+            warn!("Failed to delete attribute {} of {}: {}", key, id, e);
+            (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_valid_name() {
+        for name in ["shot_mode", "xmp:Rating", "classifier.scene-v2"] {
+            assert!(is_valid_name(name), "{name}");
+        }
+        for name in ["", "has space", "中文", &"k".repeat(65)] {
+            assert!(!is_valid_name(name), "{name}");
+        }
+    }
+}
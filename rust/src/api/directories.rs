@@ -1,9 +1,18 @@
 use crate::{
-    api::AppState,
+    api::{
+        i18n::{self, Locale, Message},
+        AppState,
+    },
     app::State,
     db::DirectoryRepository,
+    services::{
+        library_move::{self, MoveError},
+        scan_filter::ScanFilter,
+    },
 };
-use axum::{debug_handler, response::IntoResponse, Json};
+use axum::{debug_handler, http::StatusCode, response::IntoResponse, Json};
+use serde::{Deserialize, Serialize};
+use tracing::warn;
 
 #[debug_handler]
 pub async fn list_directories(
@@ -16,3 +25,69 @@ pub async fn list_directories(
         Err(e) => (axum::http::StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
     }
 }
+
+/// Body of POST /api/directories/move; both paths relative to base_path
+#[derive(Debug, Deserialize)]
+pub struct MoveDirectoryRequest {
+    pub from: String,
+    pub to: String,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MoveDirectoryResponse {
+    pub from: String,
+    pub to: String,
+    /// Media files whose stored path was rewritten
+    pub files_moved: u64,
+}
+
+/// 重命名/移动目录：磁盘上 rename 后在一个事务中改写其下所有路径，文件 id 与缩略图缓存不变
+#[debug_handler]
+pub async fn move_directory(
+    State(state): State<AppState>,
+    locale: Locale,
+    Json(request): Json<MoveDirectoryRequest>,
+) -> impl IntoResponse {
+    if !state.capabilities.modify_originals {
+        return i18n::error(StatusCode::FORBIDDEN, locale, Message::LibraryReadOnly);
+    }
+    // 扫描中途移动会让扫描删除旧路径的记录
+    if state.scan_service.is_scanning() {
+        return i18n::error(StatusCode::CONFLICT, locale, Message::ScanInProgress);
+    }
+
+    let base = &state.config.base_path;
+    let filter = ScanFilter::new(state.config.scan_min_file_size, &state.config.scan_ignore_patterns);
+    let result = match (
+        library_move::resolve_target(base, &request.from, &filter, None),
+        library_move::resolve_target(base, &request.to, &filter, None),
+    ) {
+        (Ok(source), Ok(target)) => {
+            library_move::move_directory(&state.db, state.file_service.library_roots(), base, &source, &target).await
+        }
+        (Err(e), _) | (_, Err(e)) => Err(e),
+    };
+
+    match result {
+        Ok(files_moved) => Json(MoveDirectoryResponse {
+            from: request.from,
+            to: request.to,
+            files_moved,
+        })
+        .into_response(),
+        Err(MoveError::SourceNotFound(_)) => {
+            i18n::error_with(StatusCode::NOT_FOUND, locale, Message::DirectoryNotFound, &request.from)
+        }
+        Err(MoveError::InvalidTarget(detail)) => {
+            i18n::error_with(StatusCode::BAD_REQUEST, locale, Message::InvalidTargetPath, &detail)
+        }
+        Err(MoveError::TargetExists(_)) => {
+            i18n::error_with(StatusCode::CONFLICT, locale, Message::TargetExists, &request.to)
+        }
+        Err(e) => {
+            warn!("Failed to move directory {} -> {}: {}", request.from, request.to, e);
+            (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response()
+        }
+    }
+}
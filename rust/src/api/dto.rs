@@ -0,0 +1,235 @@
+//! API 响应 DTO
+//! `MediaFile` 是数据库行，这里的结构体才是对外的 JSON 契约：新增数据库列不会自动出现在响应中，
+//! 需要在此显式添加字段。GPS 不在 DTO 中，只通过 GET /api/files/{id}/gps 返回。
+
+use crate::db::models::{date_serialization, utc_date_serialization};
+use crate::db::MediaFile;
+use chrono::NaiveDateTime;
+use serde::Serialize;
+
+/// A media file as returned by the file APIs
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FileDetail {
+    pub id: String,
+    pub file_path: String,
+    pub file_name: String,
+    pub file_type: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mime_type: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub file_size: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub width: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub height: Option<i32>,
+    /// Local wall clock of the capture (no "Z")
+    #[serde(skip_serializing_if = "Option::is_none", serialize_with = "date_serialization::serialize")]
+    pub exif_timestamp: Option<NaiveDateTime>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub exif_timezone_offset: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none", serialize_with = "date_serialization::serialize")]
+    pub filename_timestamp: Option<NaiveDateTime>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub date_source: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none", serialize_with = "utc_date_serialization::serialize")]
+    pub create_time: Option<NaiveDateTime>,
+    #[serde(skip_serializing_if = "Option::is_none", serialize_with = "utc_date_serialization::serialize")]
+    pub modify_time: Option<NaiveDateTime>,
+    #[serde(skip_serializing_if = "Option::is_none", serialize_with = "utc_date_serialization::serialize")]
+    pub last_scanned: Option<NaiveDateTime>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub camera_make: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub camera_model: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub lens_model: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub exposure_time: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub aperture: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub iso: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub focal_length: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub duration: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub video_codec: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hdr_format: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bit_depth: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub color_primaries: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub raw_path: Option<String>,
+    pub thumbnail_generated: bool,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub has_depth_map: bool,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub has_portrait_matte: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub projection: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub color_profile: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub content_hash: Option<String>,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub pending_extraction: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub blur_score: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mean_luminance: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub clipped_highlights: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub clipped_shadows: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// Edit version for PATCH /api/files/{id}
+    pub version: i64,
+    /// Time of the last edit (see MediaFile::updated_at)
+    #[serde(skip_serializing_if = "Option::is_none", serialize_with = "utc_date_serialization::serialize")]
+    pub updated_at: Option<NaiveDateTime>,
+}
+
+impl From<MediaFile> for FileDetail {
+    fn from(file: MediaFile) -> Self {
+        Self {
+            id: file.id,
+            file_path: file.file_path,
+            file_name: file.file_name,
+            file_type: file.file_type,
+            mime_type: file.mime_type,
+            file_size: file.file_size,
+            width: file.width,
+            height: file.height,
+            exif_timestamp: file.exif_timestamp,
+            exif_timezone_offset: file.exif_timezone_offset,
+            filename_timestamp: file.filename_timestamp,
+            date_source: file.date_source,
+            create_time: file.create_time,
+            modify_time: file.modify_time,
+            last_scanned: file.last_scanned,
+            camera_make: file.camera_make,
+            camera_model: file.camera_model,
+            lens_model: file.lens_model,
+            exposure_time: file.exposure_time,
+            aperture: file.aperture,
+            iso: file.iso,
+            focal_length: file.focal_length,
+            duration: file.duration,
+            video_codec: file.video_codec,
+            hdr_format: file.hdr_format,
+            bit_depth: file.bit_depth,
+            color_primaries: file.color_primaries,
+            raw_path: file.raw_path,
+            thumbnail_generated: file.thumbnail_generated,
+            has_depth_map: file.has_depth_map,
+            has_portrait_matte: file.has_portrait_matte,
+            projection: file.projection,
+            color_profile: file.color_profile,
+            content_hash: file.content_hash,
+            pending_extraction: file.pending_extraction,
+            blur_score: file.blur_score,
+            mean_luminance: file.mean_luminance,
+            clipped_highlights: file.clipped_highlights,
+            clipped_shadows: file.clipped_shadows,
+            title: file.title,
+            description: file.description,
+            version: file.version,
+            updated_at: file.updated_at,
+        }
+    }
+}
+
+/// Item of GET /api/files: the file plus fields the grid needs before images load
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ListItem {
+    #[serde(flatten)]
+    pub file: FileDetail,
+    /// Thumbnail URL, versioned when built by `with_thumbnail_version`; clients append `size`
+    pub thumbnail_url: String,
+    /// Displayed width / height: the stored dimensions swapped for EXIF orientations 5-8
+    /// (rotated by 90 or 270 degrees); None without dimensions
+    pub aspect_ratio: Option<f64>,
+    /// Thumbnail sizes already generated, served without decoding the original
+    pub available_thumb_sizes: Vec<&'static str>,
+}
+
+impl ListItem {
+    /// Item whose thumbnail URL carries `version` (see utils::thumbnail::url_version)
+    pub fn with_thumbnail_version(file: MediaFile, version: Option<&str>) -> Self {
+        Self {
+            thumbnail_url: file.thumbnail_url(version),
+            aspect_ratio: aspect_ratio(file.width, file.height, file.orientation),
+            available_thumb_sizes: Vec::new(),
+            file: file.into(),
+        }
+    }
+
+    pub fn with_available_thumb_sizes(mut self, sizes: Vec<&'static str>) -> Self {
+        self.available_thumb_sizes = sizes;
+        self
+    }
+}
+
+impl From<MediaFile> for ListItem {
+    fn from(file: MediaFile) -> Self {
+        Self::with_thumbnail_version(file, None)
+    }
+}
+
+/// Displayed aspect ratio rounded to 4 decimals; None when a dimension is missing or zero
+fn aspect_ratio(width: Option<i32>, height: Option<i32>, orientation: Option<i32>) -> Option<f64> {
+    // 5-8 旋转 90/270 度，显示时宽高互换
+    let (width, height) = match orientation {
+        Some(5..=8) => (height, width),
+        _ => (width, height),
+    };
+    match (width, height) {
+        (Some(w), Some(h)) if w > 0 && h > 0 => Some((w as f64 / h as f64 * 10_000.0).round() / 10_000.0),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// DTO 与数据库模型的序列化保持一致，且不输出 GPS
+    #[test]
+    fn test_file_detail_matches_model_json() {
+        let mut file = MediaFile::new("/p/a.jpg".to_string(), "a.jpg".to_string(), "image".to_string());
+        file.width = Some(4000);
+        file.height = Some(3000);
+        file.exif_timestamp = chrono::NaiveDate::from_ymd_opt(2024, 5, 1).and_then(|d| d.and_hms_opt(8, 30, 0));
+        file.modify_time = file.exif_timestamp;
+        file.has_depth_map = true;
+        file.gps_latitude = Some(1.0);
+        file.gps_longitude = Some(2.0);
+
+        let model = serde_json::to_value(&file).unwrap();
+        let detail = serde_json::to_value(FileDetail::from(file.clone())).unwrap();
+        assert_eq!(model, detail);
+        assert!(detail.get("gpsLatitude").is_none());
+
+        let item = serde_json::to_value(ListItem::from(file)).unwrap();
+        assert_eq!(item["aspectRatio"], 1.3333);
+        assert_eq!(item["exifTimestamp"], "2024-05-01T08:30:00");
+        assert_eq!(item["modifyTime"], "2024-05-01T08:30:00Z");
+    }
+
+    #[test]
+    fn test_aspect_ratio() {
+        assert_eq!(aspect_ratio(Some(1920), Some(1080), None), Some(1.7778));
+        assert_eq!(aspect_ratio(Some(1920), Some(1080), Some(3)), Some(1.7778));
+        assert_eq!(aspect_ratio(Some(1920), Some(1080), Some(6)), Some(0.5625));
+        assert_eq!(aspect_ratio(Some(100), Some(0), None), None);
+        assert_eq!(aspect_ratio(None, Some(10), Some(8)), None);
+    }
+}
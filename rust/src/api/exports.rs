@@ -0,0 +1,63 @@
+use crate::{
+    api::{
+        i18n::{self, Locale, Message},
+        AppState,
+    },
+    app::State,
+    services::static_export::{ExportRequest, ExportStartError},
+};
+use axum::{debug_handler, http::StatusCode, response::IntoResponse, Json};
+use serde::Serialize;
+
+/// Response for export start/cancel
+#[derive(Debug, Serialize)]
+pub struct ExportResponse {
+    pub success: bool,
+    /// Stable message key (see api::i18n)
+    pub code: &'static str,
+    pub message: String,
+}
+
+/// 在后台把选定文件导出为静态相册（缩略图 + index.json + index.html），
+/// 立即返回；进度通过 GET /api/exports/progress 查询
+#[debug_handler]
+pub async fn start_export(
+    State(state): State<AppState>,
+    locale: Locale,
+    Json(request): Json<ExportRequest>,
+) -> impl IntoResponse {
+    let name = request.name.clone();
+    match state.exporter.start(request) {
+        Ok(()) => (
+            StatusCode::ACCEPTED,
+            Json(ExportResponse {
+                success: true,
+                code: Message::ExportStarted.code(),
+                message: Message::ExportStarted.text_with(locale, &name),
+            }),
+        )
+            .into_response(),
+        Err(ExportStartError::AlreadyRunning) => i18n::error(StatusCode::CONFLICT, locale, Message::ExportInProgress),
+        Err(ExportStartError::InvalidName) => {
+            i18n::error_with(StatusCode::BAD_REQUEST, locale, Message::InvalidExportName, &name)
+        }
+    }
+}
+
+/// 当前或上一次导出的进度
+#[debug_handler]
+pub async fn get_export_progress(State(state): State<AppState>) -> impl IntoResponse {
+    Json(state.exporter.progress())
+}
+
+/// 取消正在进行的导出；已导出的部分仍写出索引，可以浏览
+#[debug_handler]
+pub async fn cancel_export(State(state): State<AppState>, locale: Locale) -> impl IntoResponse {
+    let cancelled = state.exporter.cancel();
+    let message = if cancelled { Message::ExportCancelled } else { Message::NoExportInProgress };
+    Json(ExportResponse {
+        success: cancelled,
+        code: message.code(),
+        message: message.text(locale).to_string(),
+    })
+}
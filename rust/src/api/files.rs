@@ -1,17 +1,44 @@
 use crate::{
-    api::AppState,
+    api::{
+        dto::{FileDetail, ListItem},
+        i18n::{self, Locale, Message},
+        range::{parse_range, RangeRequest},
+        remote, AppState,
+    },
     app::State,
-    db::{MediaFile, MediaFileRepository},
+    config::Config,
+    db::{
+        AttributeRepository, EditOutcome, ExposureFilter, FileQuery, MediaAttribute, MediaFileEdit, MediaFileRepository, NoteRepository, SortField, SortOrder,
+        TaggingRepository,
+    },
+    processors::{
+        heif_processor,
+        thumbnail_crop::CropMode,
+        video_timeline::{self, VideoTimeline},
+    },
+    services::{
+        disk_guard::find_low_disk_space,
+        file_service::{fit_within, resized_label},
+        library_move::{self, MoveError},
+        remote_library::RemoteLibrary,
+        scan_filter::ScanFilter,
+        RescanError,
+    },
+    utils::{
+        calendar::Granularity, library_path::PathCheckError, placeholder::PLACEHOLDER_MIME,
+        thumbnail::{self, ThumbnailOptions},
+    },
 };
 use axum::{
     body::Body,
     debug_handler,
-    extract::{Path, Query},
-    http::HeaderMap,
+    extract::{Path, Query, RawQuery},
+    http::{HeaderMap, Method},
     response::IntoResponse,
     Json,
 };
 use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
 use tokio::fs::File;
 use tracing::warn;
 use tokio_util::io::ReaderStream;
@@ -31,6 +58,68 @@ pub struct FileQueryParams {
     #[serde(rename = "cameraModel")]
     pub camera_model: Option<String>,
     pub date: Option<String>,
+    /// Only images whose blur score is below LATTE_BLUR_THRESHOLD (unscored files are excluded)
+    #[serde(default)]
+    pub blurry: bool,
+    /// "underexposed" (alias "lowLight") or "overexposed", from thumbnail exposure statistics
+    pub exposure: Option<String>,
+    /// Comma-separated field projection, e.g. "id,width,height,thumbnailGenerated"
+    pub fields: Option<String>,
+}
+
+/// 校验分页参数：负页码、超出 [1, LATTE_API_MAX_PAGE_SIZE] 的 size 返回 400，
+/// 避免 size=100000 之类的请求一次性加载整个图库。返回 (page, size)
+pub(crate) fn validate_pagination(page: Option<i32>, size: Option<i32>, config: &Config) -> Result<(i32, i32), (Message, String)> {
+    let max_size = config.api_max_page_size.min(i32::MAX as usize) as i32;
+    let default_size = (config.api_default_page_size as i32).clamp(1, max_size);
+
+    let page = page.unwrap_or(0);
+    if page < 0 {
+        return Err((Message::InvalidPage, page.to_string()));
+    }
+    let size = size.unwrap_or(default_size);
+    if !(1..=max_size).contains(&size) {
+        return Err((Message::InvalidPageSize, format!("1-{}", max_size)));
+    }
+    // OFFSET = page * size 不能溢出
+    if page.checked_mul(size).is_none() {
+        return Err((Message::InvalidPage, page.to_string()));
+    }
+    Ok((page, size))
+}
+
+/// 校验列表参数并转换为 FileQuery：未知排序字段/方向、曝光筛选返回 400。
+/// blurry 映射为 LATTE_BLUR_THRESHOLD 以下的清晰度分数
+fn validate_list_query(params: &FileQueryParams, config: &Config) -> Result<FileQuery, (Message, String)> {
+    let (page, size) = validate_pagination(params.page, params.size, config)?;
+
+    let sort = match params.sort_by.as_deref() {
+        None => SortField::default(),
+        Some(name) => SortField::from_name(name).ok_or_else(|| {
+            let names: Vec<&str> = SortField::ALL.iter().map(|field| field.as_str()).collect();
+            (Message::InvalidSortField, names.join(", "))
+        })?,
+    };
+    let order = match params.order.as_deref() {
+        None => SortOrder::default(),
+        Some(name) => SortOrder::from_name(name).ok_or_else(|| (Message::InvalidSortOrder, name.to_string()))?,
+    };
+    let exposure = match params.exposure.as_deref() {
+        None => None,
+        Some(name) => {
+            Some(ExposureFilter::from_name(name).ok_or_else(|| (Message::InvalidExposureFilter, name.to_string()))?)
+        }
+    };
+
+    Ok(FileQuery::new()
+        .path(params.path.as_deref())
+        .file_type(params.filter_type.as_deref())
+        .camera_model(params.camera_model.as_deref())
+        .date(params.date.as_deref())
+        .max_blur_score(params.blurry.then_some(config.blur_threshold))
+        .exposure(exposure)
+        .sort(sort, order)
+        .page(page, size))
 }
 
 /// Pagination response
@@ -44,6 +133,20 @@ pub struct PaginatedResponse<T> {
     pub total_pages: i32,
 }
 
+/// Thumbnail sizes reported in `availableThumbSizes` ("full" is served from the original)
+const GRID_THUMBNAIL_SIZES: [&str; 3] = ["small", "medium", "large"];
+
+/// File detail: the media file plus counts of related records
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FileDetailResponse {
+    #[serde(flatten)]
+    pub file: FileDetail,
+    pub note_count: i64,
+    /// Extended key/value metadata (see /api/files/{id}/attributes)
+    pub attributes: Vec<MediaAttribute>,
+}
+
 /// Date with count response
 #[derive(Debug, Serialize)]
 pub struct DateResponse {
@@ -54,12 +157,12 @@ pub struct DateResponse {
 /// Neighbor response for navigation
 #[derive(Debug, Serialize)]
 pub struct NeighborResponse {
-    pub previous: Option<MediaFile>,
-    pub next: Option<MediaFile>,
+    pub previous: Option<FileDetail>,
+    pub next: Option<FileDetail>,
 }
 
 /// GPS info response for the sensitive-data endpoint.
-/// MediaFile skips GPS on default serialization; this is the only way to fetch it.
+/// File DTOs never include GPS; this is the only way to fetch it.
 #[derive(Debug, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct GpsInfo {
@@ -74,11 +177,64 @@ pub struct GpsInfo {
 #[derive(Debug, Deserialize)]
 pub struct ThumbnailSize {
     pub size: Option<String>,
+    /// Top-level image of a multi-image file (HEIC burst) to thumbnail instead of the primary image
+    pub item: Option<u32>,
+    /// Square crop for grid layouts: "square" (centered) or "smart" (most detailed region)
+    pub crop: Option<String>,
+    /// Content version from `thumbnailUrl`; when it matches the file the response is cached as immutable
+    pub v: Option<String>,
+}
+
+/// Query parameters for a video frame
+#[derive(Debug, Deserialize)]
+pub struct FrameParams {
+    /// Timestamp in seconds
+    pub t: Option<f64>,
+    /// Frame width, capped at LATTE_VIDEO_FRAME_MAX_WIDTH (default: the cap)
+    pub width: Option<u32>,
+}
+
+/// Frames narrower than this are not useful as previews
+const MIN_FRAME_WIDTH: u32 = 16;
+
+/// Query parameters for original file download
+#[derive(Debug, Deserialize)]
+pub struct OriginalParams {
+    /// Set Content-Disposition: attachment so browsers save with the original file name
+    #[serde(default)]
+    pub download: bool,
+    /// Resize images server-side to fit this width before sending (cached like thumbnails)
+    #[serde(rename = "maxWidth")]
+    pub max_width: Option<u32>,
+    /// Resize images server-side to fit this height before sending
+    #[serde(rename = "maxHeight")]
+    pub max_height: Option<u32>,
+}
+
+/// Build an attachment Content-Disposition value.
+/// `filename` carries an ASCII fallback, `filename*` the RFC 5987 UTF-8 encoded name.
+pub(crate) fn content_disposition(file_name: &str) -> String {
+    let fallback: String = file_name
+        .chars()
+        .map(|c| if c.is_ascii() && !c.is_ascii_control() && c != '"' && c != '\\' { c } else { '_' })
+        .collect();
+
+    let mut encoded = String::with_capacity(file_name.len() * 3);
+    for byte in file_name.bytes() {
+        // RFC 5987 attr-char
+        if byte.is_ascii_alphanumeric() || b"!#$&+-.^_`|~".contains(&byte) {
+            encoded.push(byte as char);
+        } else {
+            encoded.push_str(&format!("%{:02X}", byte));
+        }
+    }
+
+    format!("attachment; filename=\"{}\"; filename*=UTF-8''{}", fallback, encoded)
 }
 
 /// Get size label from size string
 /// This is used to determine the cache key and which thumbnail size to generate
-fn get_size_label(size_str: &str) -> &'static str {
+pub(crate) fn get_size_label(size_str: &str) -> &'static str {
     match size_str {
         "small" => "small",
         "medium" => "medium",
@@ -88,30 +244,72 @@ fn get_size_label(size_str: &str) -> &'static str {
     }
 }
 
+/// Options of the main thumbnail at `size_str` under the current settings; its cache entry,
+/// Content-Type and ETag follow from them
+fn thumbnail_options(state: &AppState, size_str: &str) -> ThumbnailOptions {
+    let size_label = get_size_label(size_str);
+    // large 尺寸固定高度
+    ThumbnailOptions::new(state.config.get_thumbnail_size(size_str), state.file_service.thumbnail_quality(), size_label == "large")
+        .with_format(state.config.thumbnail_formats.for_size(size_label))
+}
+
+/// `?v=` of thumbnail URLs of a file at `source_version` under the current settings
+pub(crate) fn thumbnail_url_version(state: &AppState, source_version: &str) -> String {
+    let sizes = ["small", "medium", "large", "full"].map(|label| (label, thumbnail_options(state, label)));
+    thumbnail::url_version(source_version, &sizes)
+}
+
+/// Append a raw query string to a path
+fn with_query(path: String, raw_query: Option<&str>) -> String {
+    match raw_query {
+        Some(q) if !q.is_empty() => format!("{}?{}", path, q),
+        _ => path,
+    }
+}
+
+/// Parse the `fields` projection parameter into a set of (camelCase) field names.
+/// `id` is always kept so items remain addressable.
+fn parse_fields(fields: &str) -> HashSet<String> {
+    let mut set: HashSet<String> = fields
+        .split(',')
+        .map(|f| f.trim())
+        .filter(|f| !f.is_empty())
+        .map(|f| f.to_string())
+        .collect();
+    set.insert("id".to_string());
+    set
+}
+
+/// Serialize a file keeping only the requested fields.
+/// Projection runs on the serialized form, so fields hidden by serde (GPS) can never be selected.
+fn project_fields<T: Serialize>(file: &T, fields: &HashSet<String>) -> serde_json::Value {
+    match serde_json::to_value(file) {
+        Ok(serde_json::Value::Object(map)) => serde_json::Value::Object(
+            map.into_iter().filter(|(key, _)| fields.contains(key)).collect(),
+        ),
+        Ok(other) => other,
+        Err(_) => serde_json::Value::Null,
+    }
+}
+
 #[debug_handler]
 pub async fn list_files(
     State(state): State<AppState>,
+    locale: Locale,
     Query(params): Query<FileQueryParams>,
+    RawQuery(raw_query): RawQuery,
+    headers: HeaderMap,
 ) -> impl IntoResponse {
-    let page = params.page.unwrap_or(0).max(0);
-    let size = params.size.unwrap_or(50).clamp(1, 200);
-    let sort_by = params.sort_by.as_deref().unwrap_or("exifTimestamp");
-    let order = params.order.as_deref().unwrap_or("desc");
+    let query = match validate_list_query(&params, &state.config) {
+        Ok(query) => query,
+        Err((message, detail)) => {
+            return i18n::error_with(axum::http::StatusCode::BAD_REQUEST, locale, message, &detail);
+        }
+    };
+    let (page, size) = (query.page, query.page_size);
 
     let repo = MediaFileRepository::new(&state.db);
-
-    let files = match repo
-        .find_all(
-            params.path.as_deref(),
-            params.filter_type.as_deref(),
-            params.camera_model.as_deref(),
-            params.date.as_deref(),
-            sort_by,
-            order,
-            page,
-            size,
-        )
-        .await {
+    let files = match repo.find_all(&query).await {
         Ok(files) => files,
         Err(e) => {
             warn!("Failed to query files: {}", e);
@@ -119,9 +317,7 @@ pub async fn list_files(
         }
     };
 
-    let total = match repo
-        .count(params.path.as_deref(), params.filter_type.as_deref())
-        .await {
+    let total = match repo.count(&query).await {
         Ok(total) => total,
         Err(e) => {
             warn!("Failed to count files: {}", e);
@@ -130,6 +326,56 @@ pub async fn list_files(
     };
 
     let total_pages = ((total as f64) / (size as f64)).ceil() as i32;
+    let fields = params.fields.as_deref().filter(|f| !f.trim().is_empty()).map(parse_fields);
+    // 每个文件要查多个缓存文件，整页并发检查，避免 200 条时串行等待数百次文件系统调用
+    let available = futures_util::future::join_all(
+        files.iter().map(|file| state.cache_service.cached_sizes(&file.id, &GRID_THUMBNAIL_SIZES)),
+    )
+    .await;
+    let files: Vec<ListItem> = files
+        .into_iter()
+        .zip(available)
+        .map(|(file, available)| {
+            let version = file.content_hash.as_deref().map(|hash| thumbnail_url_version(&state, hash));
+            ListItem::with_thumbnail_version(file, version.as_deref()).with_available_thumb_sizes(available)
+        })
+        .collect();
+
+    // 合并远程图库：本机与远程的同一页合并后按排序字段重排
+    if let Some(remote_library) = remote::merge_target(&state, &headers) {
+        let mut items: Vec<serde_json::Value> = match &fields {
+            Some(fields) => files.iter().map(|f| project_fields(f, fields)).collect(),
+            None => files.iter().filter_map(|f| serde_json::to_value(f).ok()).collect(),
+        };
+        let mut total = total;
+        let mut total_pages = total_pages;
+
+        if let Some(remote_page) = remote::fetch_file_page(remote_library, raw_query.as_deref()).await {
+            items.extend(remote_page.items);
+            total += remote_page.total;
+            total_pages = total_pages.max(remote_page.total_pages);
+            remote::sort_merged(&mut items, query.sort, query.order);
+        }
+
+        return Json(PaginatedResponse {
+            items,
+            total,
+            page,
+            size,
+            total_pages,
+        }).into_response();
+    }
+
+    // 网格视图只需要少数字段，按 fields 参数裁剪响应体
+    if let Some(fields) = fields {
+        return Json(PaginatedResponse {
+            items: files.iter().map(|f| project_fields(f, &fields)).collect(),
+            total,
+            page,
+            size,
+            total_pages,
+        }).into_response();
+    }
 
     Json(PaginatedResponse {
         items: files,
@@ -143,87 +389,341 @@ pub async fn list_files(
 #[debug_handler]
 pub async fn get_file(
     State(state): State<AppState>,
+    locale: Locale,
     Path(id): Path<String>,
 ) -> impl IntoResponse {
+    if let (Some(remote_library), Some(remote_id)) = (&state.remote_library, RemoteLibrary::remote_id(&id)) {
+        return remote::get_remote_file(remote_library, remote_id, locale).await;
+    }
+
     let repo = MediaFileRepository::new(&state.db);
 
-    match repo.find_by_id(&id).await {
-        Ok(Some(file)) => Json(file).into_response(),
-        Ok(None) => (axum::http::StatusCode::NOT_FOUND, "File not found").into_response(),
+    let file = match repo.find_by_id(&id).await {
+        Ok(Some(file)) => file,
+        Ok(None) => return i18n::error(axum::http::StatusCode::NOT_FOUND, locale, Message::FileNotFound),
         Err(e) => {
             warn!("Failed to get file {}: {}", id, e);
+            return (axum::http::StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response();
+        }
+    };
+
+    let note_count = match NoteRepository::new(&state.db).count_by_file(&id).await {
+        Ok(note_count) => note_count,
+        Err(e) => {
+            warn!("Failed to count notes of {}: {}", id, e);
+            return (axum::http::StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response();
+        }
+    };
+
+    match AttributeRepository::new(&state.db).find_by_file(&id).await {
+        Ok(attributes) => Json(FileDetailResponse { file: file.into(), note_count, attributes }).into_response(),
+        Err(e) => {
+            warn!("Failed to list attributes of {}: {}", id, e);
             (axum::http::StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response()
         }
     }
 }
 
+/// Body of PATCH /api/files/{id}. A field that is absent stays unchanged, null clears it.
+#[derive(Debug, Deserialize)]
+pub struct UpdateFileRequest {
+    #[serde(default, deserialize_with = "present")]
+    pub title: Option<Option<String>>,
+    #[serde(default, deserialize_with = "present")]
+    pub description: Option<Option<String>>,
+    /// Expected current version; alternatively sent as `If-Match: "<version>"`
+    pub version: Option<i64>,
+}
+
+/// Distinguishes an explicit null (Some(None)) from an absent field (None, via `default`)
+fn present<'de, D>(deserializer: D) -> Result<Option<Option<String>>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    Option::<String>::deserialize(deserializer).map(Some)
+}
+
+/// Version from an `If-Match` header: `"3"`, `W/"3"` or `3`
+fn parse_if_match(headers: &HeaderMap) -> Option<i64> {
+    let value = headers.get(axum::http::header::IF_MATCH)?.to_str().ok()?.trim();
+    value.trim_start_matches("W/").trim_matches('"').parse().ok()
+}
+
+/// Edit user metadata with optimistic concurrency: the expected version must match
+/// the stored one, otherwise 409 is returned with the current state.
+#[debug_handler]
+pub async fn update_file(
+    State(state): State<AppState>,
+    locale: Locale,
+    Path(id): Path<String>,
+    headers: HeaderMap,
+    Json(request): Json<UpdateFileRequest>,
+) -> impl IntoResponse {
+    use axum::http::{header, StatusCode};
+
+    let Some(expected_version) = parse_if_match(&headers).or(request.version) else {
+        return i18n::error(StatusCode::PRECONDITION_REQUIRED, locale, Message::MissingVersion);
+    };
+
+    let edit = MediaFileEdit {
+        title: request.title,
+        description: request.description,
+    };
+    let repo = MediaFileRepository::new(&state.db);
+    match repo.update_edits(&id, expected_version, &edit).await {
+        Ok(EditOutcome::Updated(file)) => {
+            let etag = format!("\"{}\"", file.version);
+            ([(header::ETAG, etag)], Json(FileDetail::from(file))).into_response()
+        }
+        Ok(EditOutcome::Conflict(current)) => {
+            let etag = format!("\"{}\"", current.version);
+            (StatusCode::CONFLICT, [(header::ETAG, etag)], Json(FileDetail::from(current))).into_response()
+        }
+        Ok(EditOutcome::NotFound) => i18n::error(StatusCode::NOT_FOUND, locale, Message::FileNotFound),
+        Err(e) => {
+            warn!("Failed to update file {}: {}", id, e);
+            (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response()
+        }
+    }
+}
+
+/// 立即重新提取单个文件的元数据并清除其缩略图缓存（不经过扫描流程），
+/// 用于外部修改了某张照片的 EXIF 之后单独刷新
+#[debug_handler]
+pub async fn rescan_file(
+    State(state): State<AppState>,
+    locale: Locale,
+    Path(id): Path<String>,
+) -> impl IntoResponse {
+    use axum::http::StatusCode;
+
+    let file = match MediaFileRepository::new(&state.db).find_by_id(&id).await {
+        Ok(Some(file)) => file,
+        Ok(None) => return i18n::error(StatusCode::NOT_FOUND, locale, Message::FileNotFound),
+        Err(e) => {
+            warn!("Failed to get file {}: {}", id, e);
+            return (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response();
+        }
+    };
+    if let Err(response) = resolve_source(&state, locale, &id, &file.file_path).await {
+        return response;
+    }
+
+    // 按库中记录的路径提取，保证更新的是同一行
+    let file = match state.scan_service.rescan_file(std::path::Path::new(&file.file_path)).await {
+        Ok(file) => file,
+        Err(RescanError::Extraction(detail)) => {
+            return i18n::error_with(StatusCode::UNPROCESSABLE_ENTITY, locale, Message::MetadataExtractionFailed, &detail);
+        }
+        Err(e) => {
+            warn!("Failed to rescan file {}: {}", id, e);
+            return (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response();
+        }
+    };
+
+    if let Err(e) = state.file_service.invalidate_thumbnails(&id).await {
+        warn!("Failed to invalidate thumbnails of {}: {}", id, e);
+    }
+    Json(FileDetail::from(file)).into_response()
+}
+
+/// Body of PATCH /api/files/{id}/path
+#[derive(Debug, Deserialize)]
+pub struct MoveFileRequest {
+    /// New location relative to base_path, e.g. "2024/trip/IMG_0001.jpg"
+    pub path: String,
+}
+
+/// 在 base_path 内移动/重命名文件：磁盘上 rename 后更新数据库路径，id 与缩略图缓存保持不变
+#[debug_handler]
+pub async fn move_file(
+    State(state): State<AppState>,
+    locale: Locale,
+    Path(id): Path<String>,
+    Json(request): Json<MoveFileRequest>,
+) -> impl IntoResponse {
+    use axum::http::StatusCode;
+
+    if !state.capabilities.modify_originals {
+        return i18n::error(StatusCode::FORBIDDEN, locale, Message::LibraryReadOnly);
+    }
+    // 扫描中途移动会让扫描删除旧路径的记录
+    if state.scan_service.is_scanning() {
+        return i18n::error(StatusCode::CONFLICT, locale, Message::ScanInProgress);
+    }
+
+    let file = match MediaFileRepository::new(&state.db).find_by_id(&id).await {
+        Ok(Some(file)) => file,
+        Ok(None) => return i18n::error(StatusCode::NOT_FOUND, locale, Message::FileNotFound),
+        Err(e) => {
+            warn!("Failed to get file {}: {}", id, e);
+            return (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response();
+        }
+    };
+    if let Err(response) = resolve_source(&state, locale, &id, &file.file_path).await {
+        return response;
+    }
+    // 只移动 base_path 下的文件（额外的库根目录可能是其他挂载点）
+    let source = std::path::Path::new(&file.file_path);
+    if !source.starts_with(&state.config.base_path) {
+        return i18n::error(StatusCode::FORBIDDEN, locale, Message::FileOutsideLibrary);
+    }
+
+    let filter = ScanFilter::new(state.config.scan_min_file_size, &state.config.scan_ignore_patterns);
+    let result = match library_move::resolve_target(&state.config.base_path, &request.path, &filter, Some(source)) {
+        Ok(target) => library_move::move_file(&state.db, state.file_service.library_roots(), &file, &target).await,
+        Err(e) => Err(e),
+    };
+    match result {
+        Ok(file) => Json(FileDetail::from(file)).into_response(),
+        Err(MoveError::InvalidTarget(detail)) => {
+            i18n::error_with(StatusCode::BAD_REQUEST, locale, Message::InvalidTargetPath, &detail)
+        }
+        Err(MoveError::TargetExists(_)) => {
+            i18n::error_with(StatusCode::CONFLICT, locale, Message::TargetExists, &request.path)
+        }
+        Err(e) => {
+            warn!("Failed to move file {}: {}", id, e);
+            (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response()
+        }
+    }
+}
+
 #[debug_handler]
 pub async fn get_thumbnail(
     State(state): State<AppState>,
+    locale: Locale,
     Path(id): Path<String>,
     Query(size): Query<ThumbnailSize>,
+    RawQuery(raw_query): RawQuery,
+    method: Method,
+    headers: HeaderMap,
 ) -> impl IntoResponse {
     use axum::body::Body;
     use axum::http::StatusCode;
     use axum::response::Response;
-    use std::fmt::Write;
     use tokio::fs::File;
     use tokio_util::io::ReaderStream;
 
+    if let (Some(remote_library), Some(remote_id)) = (&state.remote_library, RemoteLibrary::remote_id(&id)) {
+        let path = with_query(format!("/api/files/{}/thumbnail", remote_id), raw_query.as_deref());
+        return remote::proxy_media(remote_library, method, &path, &headers, locale).await;
+    }
+
     let size_str = size.size.as_deref().unwrap_or("medium");
     let thumbnail_size = state.config.get_thumbnail_size(size_str);
     let fit_to_height = size_str == "large";  // large size uses fixed height
     let size_label = get_size_label(size_str);
+    // 输出格式按尺寸配置，同时决定缓存键和 Content-Type
+    let format = state.config.thumbnail_formats.for_size(size_label);
+    let content_type = axum::http::HeaderValue::from_static(format.mime_type());
+
+    // 连拍等多图文件的指定子图：单独缓存，不走下面的主图缓存流程
+    if let Some(item) = size.item {
+        let result = state.file_service.get_item_thumbnail(&id, item, size_label, thumbnail_size, fit_to_height).await;
+        return variant_response(result, content_type, locale, Message::SubImageNotFound, || {
+            format!("thumbnail of item {} for {}", item, id)
+        });
+    }
+
+    // 方形裁剪缩略图同样单独缓存
+    if let Some(crop) = size.crop.as_deref() {
+        let Some(crop) = CropMode::parse(crop) else {
+            return i18n::error(StatusCode::BAD_REQUEST, locale, Message::InvalidCrop);
+        };
+        let result = state.file_service.get_cropped_thumbnail(&id, size_label, thumbnail_size, crop).await;
+        return variant_response(result, content_type, locale, Message::ThumbnailNotFound, || {
+            format!("{} thumbnail for {}", crop.as_str(), id)
+        });
+    }
+
+    // ETag 由源文件内容版本与生成参数派生：文件被修改或缩略图参数变化后客户端会重新获取
+    let source_version = match MediaFileRepository::new(&state.db).find_source_version(&id).await {
+        Ok(version) => version,
+        Err(e) => {
+            warn!("Failed to look up source version of {}: {}", id, e);
+            None
+        }
+    };
+    let etag = source_version.as_deref().and_then(|version| {
+        axum::http::HeaderValue::from_str(&thumbnail_options(&state, size_str).etag(version, size_label)).ok()
+    });
+    // 带版本的 URL（列表返回的 thumbnailUrl）内容永不变化，浏览器无需再验证：版本须与当前源文件和参数一致，
+    // 且缓存条目正按同一内容哈希寻址；否则按普通缓存返回
+    let immutable = match (size.v.as_deref(), source_version.as_deref()) {
+        (Some(v), Some(version)) => {
+            v == thumbnail_url_version(&state, version) && state.cache_service.is_keyed_by(&id, version).await
+        }
+        _ => false,
+    };
+    let cache_control = if immutable {
+        "public, max-age=31536000, immutable"
+    } else {
+        "public, max-age=86400"
+    };
+    let validators = |headers: &mut HeaderMap| {
+        headers.insert(
+            axum::http::header::CACHE_CONTROL,
+            axum::http::HeaderValue::from_static(cache_control),
+        );
+        if let Some(etag) = &etag {
+            headers.insert(axum::http::header::ETAG, etag.clone());
+        }
+    };
+
+    if let Some(etag) = &etag {
+        if if_none_match(&headers, etag) {
+            let mut response = StatusCode::NOT_MODIFIED.into_response();
+            validators(response.headers_mut());
+            return response;
+        }
+    }
 
     // 1. Check memory cache first - return directly if hit (already in memory)
     if let Some(data) = state.cache_service.get_thumbnail(&id, size_label).await {
-        let mut etag = String::with_capacity(64);
-        write!(&mut etag, "\"{}-{}\"", id, size_label).unwrap();
-
         let mut response = Response::new(Body::from(data));
         response.headers_mut().insert(
             axum::http::header::CONTENT_TYPE,
-            axum::http::HeaderValue::from_static("image/jpeg"),
-        );
-        response.headers_mut().insert(
-            axum::http::header::CACHE_CONTROL,
-            axum::http::HeaderValue::from_static("public, max-age=86400"),
-        );
-        response.headers_mut().insert(
-            axum::http::header::ETAG,
-            axum::http::HeaderValue::from_str(&etag).unwrap(),
+            content_type.clone(),
         );
+        validators(response.headers_mut());
         return response;
     }
 
     // 2. Check disk cache - stream from file if exists
-    if let Some(disk_path) = state.cache_service.get_thumbnail_disk_path(&id, size_label) {
+    if let Some(disk_path) = state.cache_service.get_thumbnail_disk_path(&id, size_label).await {
+        // nginx 直接发送缓存文件，Rust 侧只返回响应头
+        if let Some(prefix) = &state.config.thumbnail_accel_redirect {
+            if let Some(relative) = state.cache_service.relative_url_path(&disk_path) {
+                let mut response_headers = HeaderMap::new();
+                response_headers.insert(
+                    axum::http::header::CONTENT_TYPE,
+                    content_type.clone(),
+                );
+                validators(&mut response_headers);
+                if let Ok(location) = axum::http::HeaderValue::from_str(&format!("{}{}", prefix, relative)) {
+                    response_headers.insert("X-Accel-Redirect", location);
+                    return (StatusCode::OK, response_headers).into_response();
+                }
+            }
+        }
+
         match File::open(&disk_path).await {
             Ok(file) => {
                 let file_size = tokio::fs::metadata(&disk_path).await.map(|m| m.len()).unwrap_or(0);
 
-                let mut etag = String::with_capacity(64);
-                write!(&mut etag, "\"{}-{}\"", id, size_label).unwrap();
-
                 let stream = ReaderStream::with_capacity(file, 32 * 1024);
 
                 let mut response_headers = HeaderMap::new();
                 response_headers.insert(
                     axum::http::header::CONTENT_TYPE,
-                    axum::http::HeaderValue::from_static("image/jpeg"),
+                    content_type.clone(),
                 );
                 response_headers.insert(
                     axum::http::header::CONTENT_LENGTH,
                     file_size.to_string().parse().unwrap(),
                 );
-                response_headers.insert(
-                    axum::http::header::CACHE_CONTROL,
-                    axum::http::HeaderValue::from_static("public, max-age=86400"),
-                );
-                response_headers.insert(
-                    axum::http::header::ETAG,
-                    axum::http::HeaderValue::from_str(&etag).unwrap(),
-                );
+                validators(&mut response_headers);
 
                 return (StatusCode::OK, response_headers, Body::from_stream(stream)).into_response();
             }
@@ -235,11 +735,16 @@ pub async fn get_thumbnail(
     }
 
     // 3. Not in cache - generate thumbnail
-    match state.file_service.get_thumbnail(&id, size_label, thumbnail_size, fit_to_height).await {
-        Ok(Some((data, mime_type))) => {
-            let mut etag = String::with_capacity(64);
-            write!(&mut etag, "\"{}-{}\"", id, size_label).unwrap();
-
+    // 错误先转换为响应，Box<dyn Error> 不能跨越下面的 await
+    let generated = match state.file_service.get_thumbnail(&id, size_label, thumbnail_size, fit_to_height).await {
+        Ok(generated) => generated,
+        Err(e) => {
+            warn!("Failed to get thumbnail for {}: {}", id, e);
+            return generation_error(locale, e.as_ref());
+        }
+    };
+    match generated {
+        Some((data, mime_type)) => {
             let mut response = Response::new(Body::from(data));
             response.headers_mut().insert(
                 axum::http::header::CONTENT_TYPE,
@@ -247,41 +752,142 @@ pub async fn get_thumbnail(
                     axum::http::HeaderValue::from_static("image/jpeg")
                 }),
             );
-            response.headers_mut().insert(
-                axum::http::header::CACHE_CONTROL,
-                axum::http::HeaderValue::from_static("public, max-age=86400"),
-            );
-            response.headers_mut().insert(
-                axum::http::header::ETAG,
-                axum::http::HeaderValue::from_str(&etag).unwrap(),
-            );
+            validators(response.headers_mut());
             response
         }
-        Ok(None) => (StatusCode::NOT_FOUND, "Thumbnail not found").into_response(),
+        // 文件缺失或无法解码：返回占位图；不缓存，以便文件修复后浏览器重新请求
+        None => match state.file_service.thumbnail_placeholder(&id).await {
+            Ok(Some(svg)) => {
+                let mut headers = HeaderMap::new();
+                headers.insert("Content-Type", PLACEHOLDER_MIME.parse().unwrap());
+                headers.insert("Cache-Control", "no-cache".parse().unwrap());
+                headers.insert("X-Latte-Placeholder", "1".parse().unwrap());
+                (StatusCode::OK, headers, svg).into_response()
+            }
+            Ok(None) => i18n::error(StatusCode::NOT_FOUND, locale, Message::ThumbnailNotFound),
+            Err(e) => {
+                warn!("Failed to look up {} for a placeholder: {}", id, e);
+                (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response()
+            }
+        },
+    }
+}
+
+/// Response of a separately cached thumbnail variant (burst item, square crop);
+/// `describe` names the variant in the log when generation fails
+fn variant_response(
+    result: Result<Option<Vec<u8>>, Box<dyn std::error::Error>>,
+    content_type: axum::http::HeaderValue,
+    locale: Locale,
+    not_found: Message,
+    describe: impl FnOnce() -> String,
+) -> axum::response::Response {
+    use axum::http::{header, StatusCode};
+
+    match result {
+        Ok(Some(data)) => (
+            StatusCode::OK,
+            [
+                (header::CONTENT_TYPE, content_type),
+                (header::CACHE_CONTROL, axum::http::HeaderValue::from_static("public, max-age=86400")),
+            ],
+            data,
+        )
+            .into_response(),
+        Ok(None) => i18n::error(StatusCode::NOT_FOUND, locale, not_found),
         Err(e) => {
-            warn!("Failed to get thumbnail for {}: {}", id, e);
-            (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response()
+            warn!("Failed to get {}: {}", describe(), e);
+            generation_error(locale, e.as_ref())
         }
     }
 }
 
+/// 507 when generation was refused because the cache volume is nearly full, 500 otherwise
+fn generation_error(locale: Locale, error: &(dyn std::error::Error + 'static)) -> axum::response::Response {
+    use axum::http::StatusCode;
+
+    match find_low_disk_space(error) {
+        Some(low) => i18n::error_with(StatusCode::INSUFFICIENT_STORAGE, locale, Message::DiskSpaceLow, &low.to_string()),
+        None => (StatusCode::INTERNAL_SERVER_ERROR, error.to_string()).into_response(),
+    }
+}
+
+/// Whether an `If-None-Match` header (a list of ETags or `*`) matches `etag`
+fn if_none_match(headers: &HeaderMap, etag: &axum::http::HeaderValue) -> bool {
+    let Some(value) = headers.get(axum::http::header::IF_NONE_MATCH).and_then(|v| v.to_str().ok()) else {
+        return false;
+    };
+    let Ok(etag) = etag.to_str() else {
+        return false;
+    };
+    value
+        .split(',')
+        .map(|candidate| candidate.trim().trim_start_matches("W/"))
+        .any(|candidate| candidate == "*" || candidate == etag)
+}
+
+/// Resolve a media file for reading: 404 if it is gone, 403 if it resolves outside the library
+async fn resolve_source(
+    state: &AppState,
+    locale: Locale,
+    id: &str,
+    file_path: &str,
+) -> Result<std::path::PathBuf, axum::response::Response> {
+    use axum::http::StatusCode;
+
+    state.file_service.resolve_source(id, file_path).await.map_err(|e| match e {
+        PathCheckError::NotFound => i18n::error(StatusCode::NOT_FOUND, locale, Message::FileNotFound),
+        PathCheckError::OutsideLibrary => i18n::error(StatusCode::FORBIDDEN, locale, Message::FileOutsideLibrary),
+    })
+}
+
 #[debug_handler]
 pub async fn get_original(
     State(state): State<AppState>,
+    locale: Locale,
     Path(id): Path<String>,
+    Query(params): Query<OriginalParams>,
+    RawQuery(raw_query): RawQuery,
+    method: Method,
     headers: HeaderMap,
 ) -> impl IntoResponse {
     use axum::http::StatusCode;
     use std::io::SeekFrom;
-    use tokio::io::AsyncSeekExt;
+    use tokio::io::{AsyncReadExt, AsyncSeekExt};
+
+    if let (Some(remote_library), Some(remote_id)) = (&state.remote_library, RemoteLibrary::remote_id(&id)) {
+        let path = with_query(format!("/api/files/{}/original", remote_id), raw_query.as_deref());
+        return remote::proxy_media(remote_library, method, &path, &headers, locale).await;
+    }
 
     let repo = MediaFileRepository::new(&state.db);
 
     match repo.find_by_id(&id).await {
         Ok(Some(file)) => {
-            let path = std::path::Path::new(&file.file_path);
-            if !path.exists() {
-                return (StatusCode::NOT_FOUND, "File not found").into_response();
+            let path = match resolve_source(&state, locale, &id, &file.file_path).await {
+                Ok(path) => path,
+                Err(response) => return response,
+            };
+            let path = path.as_path();
+
+            // ?maxWidth=/?maxHeight=: 服务端缩小后发送（结果写入缩略图缓存），原图已足够小时直接发送原图
+            if !params.download && file.file_type == "image" {
+                if let Some((target_size, fit_to_height)) =
+                    fit_within(file.width, file.height, params.max_width, params.max_height)
+                {
+                    let label = resized_label(target_size, fit_to_height);
+                    match state.file_service.get_thumbnail(&id, &label, target_size, fit_to_height).await {
+                        Ok(Some((data, mime_type))) => {
+                            let mut response_headers = HeaderMap::new();
+                            response_headers.insert("Content-Type", mime_type.parse().unwrap());
+                            response_headers.insert("Content-Length", data.len().to_string().parse().unwrap());
+                            response_headers.insert("Cache-Control", "public, max-age=86400".parse().unwrap());
+                            return (StatusCode::OK, response_headers, data).into_response();
+                        }
+                        Ok(None) => {}
+                        Err(e) => warn!("Failed to resize {} to {}, serving original: {}", id, label, e),
+                    }
+                }
             }
 
             let mime_type = file.mime_type.unwrap_or_else(|| {
@@ -306,60 +912,71 @@ pub async fn get_original(
                 .unwrap_or(0);
 
             if file_size == 0 {
-                return (StatusCode::NOT_FOUND, "Empty file").into_response();
+                return i18n::error(StatusCode::NOT_FOUND, locale, Message::EmptyFile);
+            }
+
+            let disposition: Option<axum::http::HeaderValue> = if params.download {
+                content_disposition(&file.file_name).parse().ok()
+            } else {
+                None
+            };
+
+            // HEAD: headers only, don't open or read the file
+            if method == Method::HEAD {
+                let mut response_headers = HeaderMap::new();
+                response_headers.insert("Content-Type", mime_type.parse().unwrap());
+                response_headers.insert("Content-Length", file_size.to_string().parse().unwrap());
+                response_headers.insert("Accept-Ranges", "bytes".parse().unwrap());
+                if let Some(value) = disposition {
+                    response_headers.insert("Content-Disposition", value);
+                }
+                return (StatusCode::OK, response_headers).into_response();
             }
 
             // Check for Range header (video streaming)
-            let range_header = headers.get("range");
-
-            if let Some(range_value) = range_header {
-                // Parse Range header: "bytes=start-end"
-                let range_str = range_value.to_str().unwrap_or("");
-                if let Some(range_values) = range_str.strip_prefix("bytes=") {
-                    let ranges: Vec<&str> = range_values.split(',').collect();
-                    if let Some(range_part) = ranges.first() {
-                        let parts: Vec<&str> = range_part.trim().split('-').collect();
-                        if parts.len() == 2 {
-                            let start: u64 = parts[0].parse().unwrap_or(0);
-                            let end: u64 = parts[1].parse().unwrap_or(file_size.saturating_sub(1));
-
-                            // Clamp to file size
-                            let start = start.min(file_size.saturating_sub(1));
-                            let end = end.min(file_size.saturating_sub(1));
-                            if start > end {
-                                return (StatusCode::RANGE_NOT_SATISFIABLE, "Invalid range").into_response();
-                            }
-
-                            let content_length: u64 = end.saturating_sub(start).saturating_add(1);
-
-                            // Open file and seek to start position
-                            let mut file = match File::open(path).await {
-                                Ok(f) => f,
-                                Err(e) => {
-                                    warn!("Failed to open file {}: {}", path.display(), e);
-                                    return (StatusCode::NOT_FOUND, "Cannot open file").into_response();
-                                }
-                            };
-
-                            if start > 0 {
-                                if let Err(e) = file.seek(SeekFrom::Start(start)).await {
-                                    warn!("Failed to seek in file {}: {}", path.display(), e);
-                                    return (StatusCode::INTERNAL_SERVER_ERROR, "Seek failed").into_response();
-                                }
-                            }
-
-                            // Create streaming response
-                            let stream = ReaderStream::with_capacity(file, 64 * 1024);
+            let range_request = headers
+                .get("range")
+                .and_then(|v| v.to_str().ok())
+                .map(|v| parse_range(v, file_size))
+                .unwrap_or(RangeRequest::Full);
 
-                            let mut response_headers = HeaderMap::new();
-                            response_headers.insert("Content-Type", mime_type.parse().unwrap());
-                            response_headers.insert("Content-Length", content_length.to_string().parse().unwrap());
-                            response_headers.insert("Content-Range", format!("bytes {}-{}/{}", start, end, file_size).parse().unwrap());
-                            response_headers.insert("Accept-Ranges", "bytes".parse().unwrap());
+            match range_request {
+                RangeRequest::Full => {}
+                RangeRequest::Unsatisfiable => {
+                    let mut response_headers = HeaderMap::new();
+                    response_headers.insert("Content-Range", format!("bytes */{}", file_size).parse().unwrap());
+                    return (StatusCode::RANGE_NOT_SATISFIABLE, response_headers, "Invalid range").into_response();
+                }
+                RangeRequest::Partial(range) => {
+                    // Open file and seek to start position
+                    let mut file = match File::open(path).await {
+                        Ok(f) => f,
+                        Err(e) => {
+                            warn!("Failed to open file {}: {}", path.display(), e);
+                            return i18n::error(StatusCode::NOT_FOUND, locale, Message::CannotOpenFile);
+                        }
+                    };
 
-                            return (StatusCode::PARTIAL_CONTENT, response_headers, Body::from_stream(stream)).into_response();
+                    if range.start > 0 {
+                        if let Err(e) = file.seek(SeekFrom::Start(range.start)).await {
+                            warn!("Failed to seek in file {}: {}", path.display(), e);
+                            return i18n::error(StatusCode::INTERNAL_SERVER_ERROR, locale, Message::SeekFailed);
                         }
                     }
+
+                    // Stream exactly the requested window
+                    let stream = ReaderStream::with_capacity(file.take(range.content_length()), 64 * 1024);
+
+                    let mut response_headers = HeaderMap::new();
+                    response_headers.insert("Content-Type", mime_type.parse().unwrap());
+                    response_headers.insert("Content-Length", range.content_length().to_string().parse().unwrap());
+                    response_headers.insert("Content-Range", range.content_range(file_size).parse().unwrap());
+                    response_headers.insert("Accept-Ranges", "bytes".parse().unwrap());
+                    if let Some(value) = disposition {
+                        response_headers.insert("Content-Disposition", value);
+                    }
+
+                    return (StatusCode::PARTIAL_CONTENT, response_headers, Body::from_stream(stream)).into_response();
                 }
             }
 
@@ -371,7 +988,7 @@ pub async fn get_original(
                     Ok(f) => f,
                     Err(e) => {
                         warn!("Failed to open large file {}: {}", path.display(), e);
-                        return (StatusCode::NOT_FOUND, "Cannot open file").into_response();
+                        return i18n::error(StatusCode::NOT_FOUND, locale, Message::CannotOpenFile);
                     }
                 };
                 let stream = ReaderStream::with_capacity(file, 64 * 1024 * 1024);
@@ -380,6 +997,9 @@ pub async fn get_original(
                 headers.insert("Content-Type", mime_type.parse().unwrap());
                 headers.insert("Content-Length", file_size.to_string().parse().unwrap());
                 headers.insert("Accept-Ranges", "bytes".parse().unwrap());
+                if let Some(value) = disposition {
+                    headers.insert("Content-Disposition", value);
+                }
 
                 (StatusCode::OK, headers, Body::from_stream(stream)).into_response()
             } else {
@@ -390,17 +1010,20 @@ pub async fn get_original(
                         headers.insert("Content-Type", mime_type.parse().unwrap());
                         headers.insert("Content-Length", data.len().to_string().parse().unwrap());
                         headers.insert("Accept-Ranges", "bytes".parse().unwrap());
+                        if let Some(value) = disposition {
+                            headers.insert("Content-Disposition", value);
+                        }
 
                         (StatusCode::OK, headers, data).into_response()
                     }
                     Err(e) => {
                         warn!("Failed to read file {}: {}", path.display(), e);
-                        (StatusCode::NOT_FOUND, "Cannot read file").into_response()
+                        i18n::error(StatusCode::NOT_FOUND, locale, Message::CannotReadFile)
                     }
                 }
             }
         }
-        Ok(None) => (StatusCode::NOT_FOUND, "File not found").into_response(),
+        Ok(None) => i18n::error(StatusCode::NOT_FOUND, locale, Message::FileNotFound),
         Err(e) => {
             warn!("Failed to get original file {}: {}", id, e);
             (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response()
@@ -412,6 +1035,7 @@ pub async fn get_original(
 pub async fn list_dates(
     State(state): State<AppState>,
     Query(params): Query<FileQueryParams>,
+    headers: HeaderMap,
 ) -> impl IntoResponse {
     let repo = MediaFileRepository::new(&state.db);
 
@@ -419,7 +1043,10 @@ pub async fn list_dates(
         .find_dates_with_files(params.path.as_deref(), params.filter_type.as_deref())
         .await
     {
-        Ok(dates) => Json(dates).into_response(),
+        Ok(dates) => match remote::merge_target(&state, &headers) {
+            Some(remote_library) => Json(remote::merge_dates(remote_library, dates).await).into_response(),
+            None => Json(dates).into_response(),
+        },
         Err(e) => {
             warn!("Failed to query dates: {}", e);
             (axum::http::StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response()
@@ -427,9 +1054,76 @@ pub async fn list_dates(
     }
 }
 
+/// Query parameters of GET /api/files/timeline
+#[derive(Debug, Deserialize)]
+pub struct TimelineParams {
+    /// "day" (default), "week", "month" or "year"
+    pub granularity: Option<String>,
+    #[serde(rename = "filterType")]
+    pub filter_type: Option<String>,
+}
+
+/// One bucket of the timeline
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TimelineGroup {
+    /// First day of the bucket (YYYY-MM-DD); weeks start on LATTE_WEEK_START
+    pub start: String,
+    /// Last day of the bucket (YYYY-MM-DD)
+    pub end: String,
+    /// Localized label, e.g. "2024年3月4日–10日"
+    pub label: String,
+    pub count: i64,
+    /// Earliest / latest file of the bucket, for jumping into the gallery
+    pub first_id: String,
+    pub last_id: String,
+}
+
+/// 按日/周/月/年分组的时间线（最新在前），分组在 SQL 中完成。
+/// 标签语言取 LATTE_DATE_LOCALE，未配置时跟随 Accept-Language
+#[debug_handler]
+pub async fn list_timeline(
+    State(state): State<AppState>,
+    locale: Locale,
+    Query(params): Query<TimelineParams>,
+) -> impl IntoResponse {
+    let Some(granularity) = Granularity::parse(params.granularity.as_deref().unwrap_or("day")) else {
+        return i18n::error(axum::http::StatusCode::BAD_REQUEST, locale, Message::InvalidGranularity);
+    };
+    let repo = MediaFileRepository::new(&state.db);
+    let label_locale = state.config.date_locale.unwrap_or(locale);
+
+    let query = FileQuery::new().file_type(params.filter_type.as_deref());
+
+    match repo.timeline(granularity, &query, state.config.week_start).await {
+        Ok(buckets) => {
+            let groups: Vec<TimelineGroup> = buckets
+                .into_iter()
+                .filter_map(|bucket| {
+                    let start = chrono::NaiveDate::parse_from_str(&bucket.start, "%Y-%m-%d").ok()?;
+                    Some(TimelineGroup {
+                        start: bucket.start,
+                        end: granularity.end_of(start).to_string(),
+                        label: granularity.label(start, label_locale),
+                        count: bucket.count,
+                        first_id: bucket.first_id,
+                        last_id: bucket.last_id,
+                    })
+                })
+                .collect();
+            Json(groups).into_response()
+        }
+        Err(e) => {
+            warn!("Failed to query timeline: {}", e);
+            (axum::http::StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response()
+        }
+    }
+}
+
 #[debug_handler]
 pub async fn get_neighbors(
     State(state): State<AppState>,
+    locale: Locale,
     Path(id): Path<String>,
 ) -> impl IntoResponse {
     let repo = MediaFileRepository::new(&state.db);
@@ -440,7 +1134,10 @@ pub async fn get_neighbors(
                 let previous = repo.find_neighbors(&id, sort_time, true).await.unwrap_or(None);
                 let next = repo.find_neighbors(&id, sort_time, false).await.unwrap_or(None);
 
-                NeighborResponse { previous, next }
+                NeighborResponse {
+                    previous: previous.map(FileDetail::from),
+                    next: next.map(FileDetail::from),
+                }
             } else {
                 NeighborResponse {
                     previous: None,
@@ -449,7 +1146,7 @@ pub async fn get_neighbors(
             };
             Json(response).into_response()
         }
-        Ok(None) => (axum::http::StatusCode::NOT_FOUND, "File not found").into_response(),
+        Ok(None) => i18n::error(axum::http::StatusCode::NOT_FOUND, locale, Message::FileNotFound),
         Err(e) => {
             warn!("Failed to get neighbors for {}: {}", id, e);
             (axum::http::StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response()
@@ -458,10 +1155,11 @@ pub async fn get_neighbors(
 }
 
 /// 按需返回照片的 GPS 经纬度（敏感信息端点）。
-/// MediaFile 默认序列化已跳过 GPS；前端在用户手动展开详情面板时才会调用此端点。
+/// 文件 DTO 不包含 GPS；前端在用户手动展开详情面板时才会调用此端点。
 #[debug_handler]
 pub async fn get_file_gps(
     State(state): State<AppState>,
+    locale: Locale,
     Path(id): Path<String>,
 ) -> impl IntoResponse {
     let repo = MediaFileRepository::new(&state.db);
@@ -476,10 +1174,268 @@ pub async fn get_file_gps(
             })
             .into_response()
         }
-        Ok(None) => (axum::http::StatusCode::NOT_FOUND, "File not found").into_response(),
+        Ok(None) => i18n::error(axum::http::StatusCode::NOT_FOUND, locale, Message::FileNotFound),
         Err(e) => {
             warn!("Failed to get GPS for {}: {}", id, e);
             (axum::http::StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response()
         }
     }
 }
+
+/// 视频的章节与关键帧时间线，首次请求时用 ffprobe 提取并存入数据库
+#[debug_handler]
+pub async fn get_file_timeline(
+    State(state): State<AppState>,
+    locale: Locale,
+    Path(id): Path<String>,
+) -> impl IntoResponse {
+    use axum::http::StatusCode;
+
+    let repo = MediaFileRepository::new(&state.db);
+    let file = match repo.find_by_id(&id).await {
+        Ok(Some(file)) => file,
+        Ok(None) => return i18n::error(StatusCode::NOT_FOUND, locale, Message::FileNotFound),
+        Err(e) => {
+            warn!("Failed to get file {}: {}", id, e);
+            return (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response();
+        }
+    };
+    if file.file_type != "video" {
+        return i18n::error(StatusCode::NOT_FOUND, locale, Message::NotAVideo);
+    }
+
+    match repo.find_timeline(&id, file.modify_time).await {
+        Ok(Some(timeline)) => {
+            if let Ok(timeline) = serde_json::from_str::<VideoTimeline>(&timeline) {
+                return Json(timeline).into_response();
+            }
+        }
+        Ok(None) => {}
+        Err(e) => warn!("Failed to read timeline of {}: {}", id, e),
+    }
+
+    let ffprobe_path = state.config.ffprobe_path.clone();
+    let max_keyframes = state.config.video_timeline_max_keyframes;
+    let path = match resolve_source(&state, locale, &id, &file.file_path).await {
+        Ok(path) => path,
+        Err(response) => return response,
+    };
+    let timeline = match tokio::task::spawn_blocking(move || {
+        video_timeline::extract_timeline(&ffprobe_path, &path, max_keyframes)
+    })
+    .await
+    {
+        Ok(Ok(timeline)) => timeline,
+        Ok(Err(e)) => {
+            warn!("Failed to extract timeline of {}: {}", file.file_path, e);
+            return (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response();
+        }
+        Err(e) => return (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+    };
+
+    match serde_json::to_string(&timeline) {
+        Ok(json) => {
+            if let Err(e) = repo.save_timeline(&id, file.modify_time, &json).await {
+                warn!("Failed to store timeline of {}: {}", id, e);
+            }
+        }
+        Err(e) => warn!("Failed to serialize timeline of {}: {}", id, e),
+    }
+
+    Json(timeline).into_response()
+}
+
+/// 外部打标签服务给出的标签（按置信度降序），未打标签时为空列表
+#[debug_handler]
+pub async fn get_file_labels(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> impl IntoResponse {
+    match TaggingRepository::new(&state.db).find_labels(&id).await {
+        Ok(labels) => Json(labels).into_response(),
+        Err(e) => {
+            warn!("Failed to get labels for {}: {}", id, e);
+            (axum::http::StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response()
+        }
+    }
+}
+
+/// 多图 HEIC（连拍等）包含的顶层子图，单图文件为空列表；子图缩略图通过 `thumbnail?item=` 获取
+#[debug_handler]
+pub async fn get_file_items(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> impl IntoResponse {
+    match MediaFileRepository::new(&state.db).find_sub_images(&id).await {
+        Ok(items) => Json(items).into_response(),
+        Err(e) => {
+            warn!("Failed to get sub-images for {}: {}", id, e);
+            (axum::http::StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response()
+        }
+    }
+}
+
+/// 视频任意时间点的单帧（JPEG），供前端进度条悬停预览；时间按 0.5 秒取整后缓存，
+/// 提取并发受 LATTE_VIDEO_FRAME_CONCURRENCY 限制，超出时返回 429
+#[debug_handler]
+pub async fn get_video_frame(
+    State(state): State<AppState>,
+    locale: Locale,
+    Path(id): Path<String>,
+    Query(params): Query<FrameParams>,
+) -> impl IntoResponse {
+    use axum::http::StatusCode;
+
+    let repo = MediaFileRepository::new(&state.db);
+    let file = match repo.find_by_id(&id).await {
+        Ok(Some(file)) => file,
+        Ok(None) => return i18n::error(StatusCode::NOT_FOUND, locale, Message::FileNotFound),
+        Err(e) => {
+            warn!("Failed to get file {}: {}", id, e);
+            return (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response();
+        }
+    };
+    if file.file_type != "video" {
+        return i18n::error(StatusCode::NOT_FOUND, locale, Message::NotAVideo);
+    }
+
+    let seconds = match params.t {
+        Some(t) if t.is_finite() && t >= 0.0 && file.duration.is_none_or(|duration| t <= duration) => t,
+        t => {
+            let detail = t.map(|t| t.to_string()).unwrap_or_default();
+            return i18n::error_with(StatusCode::BAD_REQUEST, locale, Message::InvalidFrameTime, &detail);
+        }
+    };
+    let max_width = state.config.video_frame_max_width.max(MIN_FRAME_WIDTH);
+    let width = params.width.unwrap_or(max_width).clamp(MIN_FRAME_WIDTH, max_width);
+
+    let path = match resolve_source(&state, locale, &id, &file.file_path).await {
+        Ok(path) => path,
+        Err(response) => return response,
+    };
+    match state.file_service.get_video_frame(&id, &path, seconds, width).await {
+        Ok(Some(jpeg)) => {
+            let mut headers = HeaderMap::new();
+            headers.insert("Content-Type", "image/jpeg".parse().unwrap());
+            headers.insert("Cache-Control", "public, max-age=86400".parse().unwrap());
+            (StatusCode::OK, headers, jpeg).into_response()
+        }
+        Ok(None) => {
+            let mut response = i18n::error(StatusCode::TOO_MANY_REQUESTS, locale, Message::FrameExtractionBusy);
+            response.headers_mut().insert("Retry-After", "1".parse().unwrap());
+            response
+        }
+        Err(e) => {
+            warn!("Failed to extract frame of {} at {}s: {}", file.file_path, seconds, e);
+            generation_error(locale, &e)
+        }
+    }
+}
+
+/// HEIC 人像照片的深度图（灰度 PNG），供高级前端实现景深/3D 效果
+#[debug_handler]
+pub async fn get_depth_map(
+    State(state): State<AppState>,
+    locale: Locale,
+    Path(id): Path<String>,
+) -> impl IntoResponse {
+    use axum::http::StatusCode;
+
+    let repo = MediaFileRepository::new(&state.db);
+    let file = match repo.find_by_id(&id).await {
+        Ok(Some(file)) => file,
+        Ok(None) => return i18n::error(StatusCode::NOT_FOUND, locale, Message::FileNotFound),
+        Err(e) => {
+            warn!("Failed to get file {}: {}", id, e);
+            return (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response();
+        }
+    };
+    if !file.has_depth_map {
+        return i18n::error(StatusCode::NOT_FOUND, locale, Message::NoDepthMap);
+    }
+
+    let path = match resolve_source(&state, locale, &id, &file.file_path).await {
+        Ok(path) => path,
+        Err(response) => return response,
+    };
+    match tokio::task::spawn_blocking(move || heif_processor::extract_depth_map_png(&path)).await {
+        Ok(Ok(Some(png))) => {
+            let mut headers = HeaderMap::new();
+            headers.insert("Content-Type", "image/png".parse().unwrap());
+            headers.insert("Cache-Control", "public, max-age=86400".parse().unwrap());
+            (StatusCode::OK, headers, png).into_response()
+        }
+        Ok(Ok(None)) => i18n::error(StatusCode::NOT_FOUND, locale, Message::NoDepthMap),
+        Ok(Err(e)) => {
+            warn!("Failed to extract depth map of {}: {}", file.file_path, e);
+            (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response()
+        }
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+    }
+}
+
+/// 下载与展示文件配对的 RAW 原片（RAW+JPEG 配对开启时由扫描器关联）
+#[debug_handler]
+pub async fn get_raw(
+    State(state): State<AppState>,
+    locale: Locale,
+    Path(id): Path<String>,
+) -> impl IntoResponse {
+    use axum::http::StatusCode;
+
+    let repo = MediaFileRepository::new(&state.db);
+
+    match repo.find_by_id(&id).await {
+        Ok(Some(file)) => {
+            let Some(raw_path) = file.raw_path else {
+                return i18n::error(StatusCode::NOT_FOUND, locale, Message::NoRawLinked);
+            };
+
+            let raw_path = match state.file_service.library_roots().resolve(std::path::Path::new(&raw_path)).await {
+                Ok(path) => path,
+                Err(PathCheckError::OutsideLibrary) => {
+                    return i18n::error(StatusCode::FORBIDDEN, locale, Message::FileOutsideLibrary);
+                }
+                Err(PathCheckError::NotFound) => {
+                    return i18n::error(StatusCode::NOT_FOUND, locale, Message::CannotOpenRaw);
+                }
+            };
+            let raw_file = match File::open(&raw_path).await {
+                Ok(f) => f,
+                Err(e) => {
+                    warn!("Failed to open RAW file {}: {}", raw_path.display(), e);
+                    return i18n::error(StatusCode::NOT_FOUND, locale, Message::CannotOpenRaw);
+                }
+            };
+            let file_size = match raw_file.metadata().await {
+                Ok(m) => m.len(),
+                Err(e) => {
+                    warn!("Failed to stat RAW file {}: {}", raw_path.display(), e);
+                    return i18n::error(StatusCode::NOT_FOUND, locale, Message::CannotOpenRaw);
+                }
+            };
+
+            let file_name = raw_path
+                .file_name()
+                .and_then(|n| n.to_str())
+                .unwrap_or("raw")
+                .to_string();
+
+            let stream = ReaderStream::with_capacity(raw_file, 64 * 1024);
+
+            let mut headers = HeaderMap::new();
+            headers.insert("Content-Type", "application/octet-stream".parse().unwrap());
+            headers.insert("Content-Length", file_size.to_string().parse().unwrap());
+            if let Ok(value) = content_disposition(&file_name).parse() {
+                headers.insert("Content-Disposition", value);
+            }
+
+            (StatusCode::OK, headers, Body::from_stream(stream)).into_response()
+        }
+        Ok(None) => i18n::error(StatusCode::NOT_FOUND, locale, Message::FileNotFound),
+        Err(e) => {
+            warn!("Failed to get RAW file for {}: {}", id, e);
+            (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response()
+        }
+    }
+}
@@ -0,0 +1,467 @@
+//! API 消息本地化
+//! 面向用户的状态/错误消息统一在这里定义：每条消息有一个稳定的 `code`（前端据此判断，
+//! 不依赖文案），`message` 按请求的 Accept-Language 选择语言（目前 en、zh-CN，默认 en）。
+//! 错误响应体为 `{"code": "file_not_found", "message": "文件不存在"}`。
+
+use axum::{
+    extract::FromRequestParts,
+    http::{header, request::Parts, HeaderValue, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde::Serialize;
+use std::convert::Infallible;
+
+/// Response language negotiated from Accept-Language
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Locale {
+    #[default]
+    En,
+    ZhCn,
+}
+
+impl Locale {
+    /// BCP 47 tag, also sent back as Content-Language
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::En => "en",
+            Self::ZhCn => "zh-CN",
+        }
+    }
+
+    /// Supported locale of a language tag ("zh-TW" -> zh-CN, "en-GB" -> en)
+    pub fn from_tag(tag: &str) -> Option<Self> {
+        let language = tag.split(['-', '_']).next().unwrap_or("").to_ascii_lowercase();
+        match language.as_str() {
+            "en" => Some(Self::En),
+            "zh" => Some(Self::ZhCn),
+            _ => None,
+        }
+    }
+
+    /// Pick the supported locale with the highest q-value; ties keep header order.
+    /// Unsupported languages and q=0 entries are skipped; falls back to English.
+    pub fn negotiate(accept_language: &str) -> Self {
+        let mut best: Option<(Self, f32)> = None;
+        for entry in accept_language.split(',') {
+            let mut parts = entry.split(';');
+            let Some(locale) = Self::from_tag(parts.next().unwrap_or("").trim()) else {
+                continue;
+            };
+            let quality = parts
+                .find_map(|p| p.trim().strip_prefix("q="))
+                .and_then(|q| q.trim().parse::<f32>().ok())
+                .unwrap_or(1.0);
+            if quality > 0.0 && !matches!(best, Some((_, q)) if q >= quality) {
+                best = Some((locale, quality));
+            }
+        }
+        best.map(|(locale, _)| locale).unwrap_or_default()
+    }
+}
+
+impl<S: Send + Sync> FromRequestParts<S> for Locale {
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        Ok(parts
+            .headers
+            .get(header::ACCEPT_LANGUAGE)
+            .and_then(|v| v.to_str().ok())
+            .map(Self::negotiate)
+            .unwrap_or_default())
+    }
+}
+
+/// API-facing messages. `code()` is part of the API contract and must not change.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Message {
+    FileNotFound,
+    FileOutsideLibrary,
+    SubImageNotFound,
+    ThumbnailNotFound,
+    EmptyFile,
+    CannotOpenFile,
+    CannotReadFile,
+    SeekFailed,
+    NotAVideo,
+    NoDepthMap,
+    NoRawLinked,
+    CannotOpenRaw,
+    InvalidCrop,
+    InvalidGranularity,
+    InvalidPage,
+    InvalidPageSize,
+    InvalidSortField,
+    InvalidSortOrder,
+    MissingVersion,
+    MissingSearchQuery,
+    SemanticSearchDisabled,
+    RemoteUnavailable,
+    WebhookNotFound,
+    InvalidWebhookUrl,
+    UnsupportedWebhookEvent,
+    WebhookTestDelivered,
+    WebhookDeliveryFailed,
+    ScanStarted,
+    ScanQueued,
+    ForceScanStarted,
+    ForceScanQueued,
+    ScanCancelled,
+    NoScanInProgress,
+    ScanInProgress,
+    NoScanReport,
+    UnsupportedField,
+    BackfillStarted,
+    BackupCreated,
+    BackupNotFound,
+    InvalidBackup,
+    RestoreStaged,
+    FailuresCleared,
+    ExportStarted,
+    ExportInProgress,
+    ExportCancelled,
+    NoExportInProgress,
+    InvalidExportName,
+    BlurDetectionDisabled,
+    InvalidExposureFilter,
+    InvalidFrameTime,
+    FrameExtractionBusy,
+    NoteNotFound,
+    EmptyNote,
+    InvalidParentNote,
+    MetadataExtractionFailed,
+    LibraryReadOnly,
+    InvalidTargetPath,
+    TargetExists,
+    DirectoryNotFound,
+    DiskSpaceLow,
+    AttributeNotFound,
+    InvalidAttribute,
+    ConfigReloadFailed,
+    InvalidSyncCursor,
+    TooManyIds,
+    InvalidThumbnailSize,
+}
+
+impl Message {
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::FileNotFound => "file_not_found",
+            Self::FileOutsideLibrary => "file_outside_library",
+            Self::SubImageNotFound => "sub_image_not_found",
+            Self::ThumbnailNotFound => "thumbnail_not_found",
+            Self::EmptyFile => "empty_file",
+            Self::CannotOpenFile => "cannot_open_file",
+            Self::CannotReadFile => "cannot_read_file",
+            Self::SeekFailed => "seek_failed",
+            Self::NotAVideo => "not_a_video",
+            Self::NoDepthMap => "no_depth_map",
+            Self::NoRawLinked => "no_raw_linked",
+            Self::CannotOpenRaw => "cannot_open_raw",
+            Self::InvalidCrop => "invalid_crop",
+            Self::InvalidGranularity => "invalid_granularity",
+            Self::InvalidPage => "invalid_page",
+            Self::InvalidPageSize => "invalid_page_size",
+            Self::InvalidSortField => "invalid_sort_field",
+            Self::InvalidSortOrder => "invalid_sort_order",
+            Self::MissingVersion => "missing_version",
+            Self::MissingSearchQuery => "missing_search_query",
+            Self::SemanticSearchDisabled => "semantic_search_disabled",
+            Self::RemoteUnavailable => "remote_unavailable",
+            Self::WebhookNotFound => "webhook_not_found",
+            Self::InvalidWebhookUrl => "invalid_webhook_url",
+            Self::UnsupportedWebhookEvent => "unsupported_webhook_event",
+            Self::WebhookTestDelivered => "webhook_test_delivered",
+            Self::WebhookDeliveryFailed => "webhook_delivery_failed",
+            Self::ScanStarted => "scan_started",
+            Self::ScanQueued => "scan_queued",
+            Self::ForceScanStarted => "force_scan_started",
+            Self::ForceScanQueued => "force_scan_queued",
+            Self::ScanCancelled => "scan_cancelled",
+            Self::NoScanInProgress => "no_scan_in_progress",
+            Self::ScanInProgress => "scan_in_progress",
+            Self::NoScanReport => "no_scan_report",
+            Self::UnsupportedField => "unsupported_field",
+            Self::BackfillStarted => "backfill_started",
+            Self::BackupCreated => "backup_created",
+            Self::BackupNotFound => "backup_not_found",
+            Self::InvalidBackup => "invalid_backup",
+            Self::RestoreStaged => "restore_staged",
+            Self::FailuresCleared => "failures_cleared",
+            Self::ExportStarted => "export_started",
+            Self::ExportInProgress => "export_in_progress",
+            Self::ExportCancelled => "export_cancelled",
+            Self::NoExportInProgress => "no_export_in_progress",
+            Self::InvalidExportName => "invalid_export_name",
+            Self::BlurDetectionDisabled => "blur_detection_disabled",
+            Self::InvalidExposureFilter => "invalid_exposure_filter",
+            Self::InvalidFrameTime => "invalid_frame_time",
+            Self::FrameExtractionBusy => "frame_extraction_busy",
+            Self::NoteNotFound => "note_not_found",
+            Self::EmptyNote => "empty_note",
+            Self::InvalidParentNote => "invalid_parent_note",
+            Self::MetadataExtractionFailed => "metadata_extraction_failed",
+            Self::LibraryReadOnly => "library_read_only",
+            Self::InvalidTargetPath => "invalid_target_path",
+            Self::TargetExists => "target_exists",
+            Self::DirectoryNotFound => "directory_not_found",
+            Self::DiskSpaceLow => "disk_space_low",
+            Self::AttributeNotFound => "attribute_not_found",
+            Self::InvalidAttribute => "invalid_attribute",
+            Self::ConfigReloadFailed => "CONFIG_RELOAD_FAILED",
+            Self::InvalidSyncCursor => "INVALID_SYNC_CURSOR",
+            Self::TooManyIds => "too_many_ids",
+            Self::InvalidThumbnailSize => "invalid_thumbnail_size",
+        }
+    }
+
+    pub fn text(&self, locale: Locale) -> &'static str {
+        match locale {
+            Locale::En => self.en(),
+            Locale::ZhCn => self.zh_cn(),
+        }
+    }
+
+    fn en(&self) -> &'static str {
+        match self {
+            Self::FileNotFound => "File not found",
+            Self::FileOutsideLibrary => "Access to this file is denied",
+            Self::SubImageNotFound => "Sub-image not found",
+            Self::ThumbnailNotFound => "Thumbnail not found",
+            Self::EmptyFile => "Empty file",
+            Self::CannotOpenFile => "Cannot open file",
+            Self::CannotReadFile => "Cannot read file",
+            Self::SeekFailed => "Seek failed",
+            Self::NotAVideo => "Not a video",
+            Self::NoDepthMap => "No depth map",
+            Self::NoRawLinked => "No RAW file linked",
+            Self::CannotOpenRaw => "Cannot open RAW file",
+            Self::InvalidCrop => "crop must be 'square' or 'smart'",
+            Self::InvalidGranularity => "granularity must be 'day', 'week', 'month' or 'year'",
+            Self::InvalidPage => "page must not be negative",
+            Self::InvalidPageSize => "Page size out of range",
+            Self::InvalidSortField => "Unsupported sortBy",
+            Self::InvalidSortOrder => "order must be 'asc' or 'desc'",
+            Self::MissingVersion => "Missing version (If-Match header or version field)",
+            Self::MissingSearchQuery => "Missing query parameter q",
+            Self::SemanticSearchDisabled => "Semantic search is not configured",
+            Self::RemoteUnavailable => "Remote library unavailable",
+            Self::WebhookNotFound => "Webhook not found",
+            Self::InvalidWebhookUrl => "Invalid webhook URL",
+            Self::UnsupportedWebhookEvent => "Unsupported event",
+            Self::WebhookTestDelivered => "Test event delivered",
+            Self::WebhookDeliveryFailed => "Delivery failed",
+            Self::ScanStarted => "Scan started",
+            Self::ScanQueued => "Scan queued",
+            Self::ForceScanStarted => "Force scan started",
+            Self::ForceScanQueued => "Force scan queued",
+            Self::ScanCancelled => "Scan cancelled",
+            Self::NoScanInProgress => "No scan in progress",
+            Self::ScanInProgress => "Scan already in progress",
+            Self::NoScanReport => "No scan has finished yet",
+            Self::UnsupportedField => "Unsupported field",
+            Self::BackfillStarted => "Backfill started",
+            Self::BackupCreated => "Backup created",
+            Self::BackupNotFound => "Backup not found",
+            Self::InvalidBackup => "Invalid backup",
+            Self::RestoreStaged => "Restore staged, restart the server to apply",
+            Self::FailuresCleared => "Failures cleared, files will be retried",
+            Self::ExportStarted => "Export started",
+            Self::ExportInProgress => "An export is already running",
+            Self::ExportCancelled => "Export cancelled",
+            Self::NoExportInProgress => "No export in progress",
+            Self::InvalidExportName => "Invalid export name",
+            Self::BlurDetectionDisabled => "Blur detection is disabled (LATTE_BLUR_DETECTION)",
+            Self::InvalidExposureFilter => "Invalid exposure filter (underexposed, overexposed)",
+            Self::InvalidFrameTime => "Frame time must be between 0 and the video duration",
+            Self::FrameExtractionBusy => "Too many frame requests, retry later",
+            Self::NoteNotFound => "Note not found",
+            Self::EmptyNote => "Note body must not be empty",
+            Self::InvalidParentNote => "Parent note does not belong to this file",
+            Self::MetadataExtractionFailed => "Metadata extraction failed",
+            Self::LibraryReadOnly => "The library is read-only",
+            Self::InvalidTargetPath => "Invalid target path",
+            Self::TargetExists => "Target already exists",
+            Self::DirectoryNotFound => "Directory not found",
+            Self::DiskSpaceLow => "Not enough free disk space for the cache",
+            Self::AttributeNotFound => "Attribute not found",
+            Self::InvalidAttribute => "Invalid attribute key, value or source",
+            Self::ConfigReloadFailed => "Failed to reload the configuration",
+            Self::InvalidSyncCursor => "Invalid sync cursor",
+            Self::TooManyIds => "Too many ids",
+            Self::InvalidThumbnailSize => "Invalid size",
+        }
+    }
+
+    fn zh_cn(&self) -> &'static str {
+        match self {
+            Self::FileNotFound => "文件不存在",
+            Self::FileOutsideLibrary => "禁止访问该文件",
+            Self::SubImageNotFound => "子图不存在",
+            Self::ThumbnailNotFound => "缩略图不存在",
+            Self::EmptyFile => "文件为空",
+            Self::CannotOpenFile => "无法打开文件",
+            Self::CannotReadFile => "无法读取文件",
+            Self::SeekFailed => "文件定位失败",
+            Self::NotAVideo => "不是视频文件",
+            Self::NoDepthMap => "没有深度图",
+            Self::NoRawLinked => "没有关联的 RAW 文件",
+            Self::CannotOpenRaw => "无法打开 RAW 文件",
+            Self::InvalidCrop => "crop 只能是 'square' 或 'smart'",
+            Self::InvalidGranularity => "granularity 只能是 'day'、'week'、'month' 或 'year'",
+            Self::InvalidPage => "page 不能为负数",
+            Self::InvalidPageSize => "分页大小超出范围",
+            Self::InvalidSortField => "不支持的 sortBy",
+            Self::InvalidSortOrder => "order 只能是 'asc' 或 'desc'",
+            Self::MissingVersion => "缺少版本号（If-Match 请求头或 version 字段）",
+            Self::MissingSearchQuery => "缺少查询参数 q",
+            Self::SemanticSearchDisabled => "未配置语义搜索",
+            Self::RemoteUnavailable => "远程图库不可用",
+            Self::WebhookNotFound => "Webhook 不存在",
+            Self::InvalidWebhookUrl => "Webhook 地址无效",
+            Self::UnsupportedWebhookEvent => "不支持的事件",
+            Self::WebhookTestDelivered => "测试事件已送达",
+            Self::WebhookDeliveryFailed => "投递失败",
+            Self::ScanStarted => "扫描已开始",
+            Self::ScanQueued => "扫描已排队",
+            Self::ForceScanStarted => "强制扫描已开始",
+            Self::ForceScanQueued => "强制扫描已排队",
+            Self::ScanCancelled => "扫描已取消",
+            Self::NoScanInProgress => "当前没有正在进行的扫描",
+            Self::ScanInProgress => "扫描正在进行中",
+            Self::NoScanReport => "还没有完成过扫描",
+            Self::UnsupportedField => "不支持的字段",
+            Self::BackfillStarted => "回填已开始",
+            Self::BackupCreated => "备份已创建",
+            Self::BackupNotFound => "备份不存在",
+            Self::InvalidBackup => "备份无效",
+            Self::RestoreStaged => "恢复已就绪，重启服务后生效",
+            Self::FailuresCleared => "已清除失败记录，文件将重新处理",
+            Self::ExportStarted => "导出已开始",
+            Self::ExportInProgress => "已有导出任务正在进行",
+            Self::ExportCancelled => "导出已取消",
+            Self::NoExportInProgress => "没有正在进行的导出",
+            Self::InvalidExportName => "导出名称无效",
+            Self::BlurDetectionDisabled => "未启用模糊检测（LATTE_BLUR_DETECTION）",
+            Self::InvalidExposureFilter => "无效的曝光筛选（underexposed、overexposed）",
+            Self::InvalidFrameTime => "帧时间必须在 0 与视频时长之间",
+            Self::FrameExtractionBusy => "视频帧请求过多，请稍后重试",
+            Self::NoteNotFound => "备注不存在",
+            Self::EmptyNote => "备注内容不能为空",
+            Self::InvalidParentNote => "上级备注不属于该文件",
+            Self::MetadataExtractionFailed => "元数据提取失败",
+            Self::LibraryReadOnly => "照片库为只读",
+            Self::InvalidTargetPath => "目标路径无效",
+            Self::TargetExists => "目标已存在",
+            Self::DirectoryNotFound => "目录不存在",
+            Self::DiskSpaceLow => "缓存所在磁盘空间不足",
+            Self::AttributeNotFound => "属性不存在",
+            Self::InvalidAttribute => "属性的键、值或来源无效",
+            Self::ConfigReloadFailed => "重新加载配置失败",
+            Self::InvalidSyncCursor => "无效的同步游标",
+            Self::TooManyIds => "id 数量过多",
+            Self::InvalidThumbnailSize => "尺寸无效",
+        }
+    }
+
+    /// Localized text followed by a detail (a file name, a field, ...)
+    pub fn text_with(&self, locale: Locale, detail: &str) -> String {
+        match locale {
+            Locale::En => format!("{}: {}", self.en(), detail),
+            Locale::ZhCn => format!("{}：{}", self.zh_cn(), detail),
+        }
+    }
+}
+
+/// Body of localized error responses
+#[derive(Debug, Serialize)]
+pub struct ApiError {
+    pub code: &'static str,
+    pub message: String,
+}
+
+/// Localized JSON error response
+pub fn error(status: StatusCode, locale: Locale, message: Message) -> Response {
+    localized(status, locale, message, message.text(locale).to_string())
+}
+
+/// Localized JSON error response with a detail appended to the message
+pub fn error_with(status: StatusCode, locale: Locale, message: Message, detail: &str) -> Response {
+    localized(status, locale, message, message.text_with(locale, detail))
+}
+
+fn localized(status: StatusCode, locale: Locale, message: Message, text: String) -> Response {
+    let mut response = (
+        status,
+        Json(ApiError {
+            code: message.code(),
+            message: text,
+        }),
+    )
+        .into_response();
+    response
+        .headers_mut()
+        .insert(header::CONTENT_LANGUAGE, HeaderValue::from_static(locale.as_str()));
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_negotiate() {
+        assert_eq!(Locale::negotiate("zh-CN,zh;q=0.9,en;q=0.8"), Locale::ZhCn);
+        assert_eq!(Locale::negotiate("en-US,en;q=0.9"), Locale::En);
+        assert_eq!(Locale::negotiate("zh-TW"), Locale::ZhCn);
+        assert_eq!(Locale::negotiate("fr-FR, zh;q=0.5"), Locale::ZhCn);
+        assert_eq!(Locale::negotiate("en;q=0.3, zh_CN;q=0.7"), Locale::ZhCn);
+        assert_eq!(Locale::negotiate("zh;q=0, en;q=0.1"), Locale::En);
+        assert_eq!(Locale::negotiate("fr, de"), Locale::En);
+        assert_eq!(Locale::negotiate("*"), Locale::En);
+        assert_eq!(Locale::negotiate(""), Locale::En);
+    }
+
+    #[test]
+    fn test_text_with_detail() {
+        assert_eq!(Message::BackupNotFound.text_with(Locale::En, "a.db"), "Backup not found: a.db");
+        assert_eq!(Message::BackupNotFound.text_with(Locale::ZhCn, "a.db"), "备份不存在：a.db");
+    }
+
+    #[test]
+    fn test_codes_are_unique() {
+        let all = [
+            Message::FileNotFound, Message::FileOutsideLibrary, Message::SubImageNotFound, Message::ThumbnailNotFound, Message::EmptyFile,
+            Message::CannotOpenFile, Message::CannotReadFile, Message::SeekFailed, Message::NotAVideo,
+            Message::NoDepthMap, Message::NoRawLinked, Message::CannotOpenRaw, Message::InvalidCrop, Message::InvalidGranularity,
+            Message::InvalidPage, Message::InvalidPageSize, Message::InvalidSortField, Message::InvalidSortOrder,
+            Message::MissingVersion, Message::MissingSearchQuery, Message::SemanticSearchDisabled,
+            Message::RemoteUnavailable, Message::WebhookNotFound, Message::InvalidWebhookUrl,
+            Message::UnsupportedWebhookEvent, Message::WebhookTestDelivered, Message::WebhookDeliveryFailed, Message::ScanStarted,
+            Message::ScanQueued, Message::ForceScanStarted, Message::ForceScanQueued, Message::ScanCancelled,
+            Message::NoScanInProgress, Message::ScanInProgress, Message::NoScanReport, Message::UnsupportedField,
+            Message::BackfillStarted, Message::BackupCreated, Message::BackupNotFound, Message::InvalidBackup, Message::RestoreStaged,
+            Message::FailuresCleared, Message::ExportStarted, Message::ExportInProgress, Message::ExportCancelled,
+            Message::NoExportInProgress, Message::InvalidExportName, Message::BlurDetectionDisabled,
+            Message::InvalidExposureFilter, Message::InvalidFrameTime, Message::FrameExtractionBusy,
+            Message::NoteNotFound,
+            Message::EmptyNote,
+            Message::InvalidParentNote,
+            Message::MetadataExtractionFailed,
+            Message::LibraryReadOnly,
+            Message::InvalidTargetPath,
+            Message::TargetExists,
+            Message::DirectoryNotFound,
+            Message::DiskSpaceLow,
+            Message::AttributeNotFound,
+            Message::InvalidAttribute,
+            Message::ConfigReloadFailed,
+            Message::InvalidSyncCursor,
+            Message::TooManyIds,
+            Message::InvalidThumbnailSize,
+        ];
+        let codes: std::collections::HashSet<&str> = all.iter().map(|m| m.code()).collect();
+        assert_eq!(codes.len(), all.len());
+    }
+}
@@ -0,0 +1,205 @@
+use crate::{
+    api::{
+        i18n::{Locale, Message},
+        AppState,
+    },
+    app::State,
+    db::{backup, DatabaseError, FailedFileRepository, MetadataField},
+    services::consistency,
+};
+use axum::{debug_handler, http::StatusCode, response::IntoResponse, Json};
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+
+/// Request body for targeted metadata backfill
+#[derive(Debug, Deserialize)]
+pub struct BackfillRequest {
+    /// Field name as exposed in the file API (e.g. "width", "exifTimestamp", "gps")
+    pub field: String,
+}
+
+/// Request body for restoring a backup
+#[derive(Debug, Deserialize)]
+pub struct RestoreRequest {
+    /// Backup file name as returned by GET /api/maintenance/backups
+    pub name: String,
+}
+
+/// Response for maintenance operations
+#[derive(Debug, Serialize)]
+pub struct MaintenanceResponse {
+    pub success: bool,
+    /// Stable message key (see api::i18n)
+    pub code: &'static str,
+    pub message: String,
+}
+
+impl MaintenanceResponse {
+    fn new(success: bool, locale: Locale, message: Message, detail: Option<&str>) -> Json<Self> {
+        Json(Self {
+            success,
+            code: message.code(),
+            message: match detail {
+                Some(detail) => message.text_with(locale, detail),
+                None => message.text(locale).to_string(),
+            },
+        })
+    }
+}
+
+/// 仅重新处理指定字段为空的文件（例如新增列后的回填），避免全量重扫。
+/// 任务在后台执行，进度通过现有的扫描进度接口 / WebSocket 推送。
+#[debug_handler]
+pub async fn backfill(
+    State(state): State<AppState>,
+    locale: Locale,
+    Json(request): Json<BackfillRequest>,
+) -> impl IntoResponse {
+    let field = match MetadataField::from_name(&request.field) {
+        Some(field) => field,
+        None => {
+            return (
+                StatusCode::BAD_REQUEST,
+                MaintenanceResponse::new(false, locale, Message::UnsupportedField, Some(&request.field)),
+            )
+                .into_response();
+        }
+    };
+
+    // 未启用模糊检测时处理器不计算清晰度，回填不会有任何结果
+    if field == MetadataField::BlurScore && !state.config.blur_detection {
+        return (
+            StatusCode::BAD_REQUEST,
+            MaintenanceResponse::new(false, locale, Message::BlurDetectionDisabled, None),
+        )
+            .into_response();
+    }
+
+    if state.scan_service.is_scanning() {
+        return (
+            StatusCode::CONFLICT,
+            MaintenanceResponse::new(false, locale, Message::ScanInProgress, None),
+        )
+            .into_response();
+    }
+
+    let scan_service = state.scan_service.clone();
+    tokio::spawn(async move {
+        scan_service.backfill(field).await;
+    });
+
+    (
+        StatusCode::ACCEPTED,
+        MaintenanceResponse::new(true, locale, Message::BackfillStarted, Some(&request.field)),
+    )
+        .into_response()
+}
+
+/// 在线备份数据库到备份目录（VACUUM INTO），并按 LATTE_BACKUP_KEEP 轮转旧备份
+#[debug_handler]
+pub async fn create_backup(State(state): State<AppState>, locale: Locale) -> impl IntoResponse {
+    let backup_dir = state.config.get_backup_dir();
+
+    match backup::create_backup(&state.db, &backup_dir, state.config.backup_keep).await {
+        Ok(path) => {
+            let name = path
+                .file_name()
+                .map(|n| n.to_string_lossy().to_string())
+                .unwrap_or_default();
+            (
+                StatusCode::CREATED,
+                MaintenanceResponse::new(true, locale, Message::BackupCreated, Some(&name)),
+            )
+                .into_response()
+        }
+        Err(e) => {
+            warn!("Failed to create database backup: {}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response()
+        }
+    }
+}
+
+/// 列出已有备份（最新在前）
+#[debug_handler]
+pub async fn list_backups(State(state): State<AppState>) -> impl IntoResponse {
+    match backup::list_backups(&state.config.get_backup_dir()) {
+        Ok(backups) => Json(backups).into_response(),
+        Err(e) => {
+            warn!("Failed to list database backups: {}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response()
+        }
+    }
+}
+
+/// 校验指定备份并暂存，重启服务后替换当前数据库。
+/// 运行中的连接池持有数据库文件，不在线替换。
+#[debug_handler]
+pub async fn restore_backup(
+    State(state): State<AppState>,
+    locale: Locale,
+    Json(request): Json<RestoreRequest>,
+) -> impl IntoResponse {
+    let Some(path) = backup::resolve_backup(&state.config.get_backup_dir(), &request.name) else {
+        return (
+            StatusCode::NOT_FOUND,
+            MaintenanceResponse::new(false, locale, Message::BackupNotFound, Some(&request.name)),
+        )
+            .into_response();
+    };
+
+    match backup::stage_restore(&path, &state.config.db_path).await {
+        Ok(_) => (
+            StatusCode::ACCEPTED,
+            MaintenanceResponse::new(true, locale, Message::RestoreStaged, Some(&request.name)),
+        )
+            .into_response(),
+        Err(DatabaseError::InvalidBackup(msg)) => (
+            StatusCode::UNPROCESSABLE_ENTITY,
+            MaintenanceResponse::new(false, locale, Message::InvalidBackup, Some(&msg)),
+        )
+            .into_response(),
+        Err(e) => {
+            warn!("Failed to stage restore from {}: {}", request.name, e);
+            (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response()
+        }
+    }
+}
+
+/// 列出处理失败的文件（缩略图解码失败等），最近的在前
+#[debug_handler]
+pub async fn list_failed_files(State(state): State<AppState>) -> impl IntoResponse {
+    match FailedFileRepository::new(&state.db).find_all().await {
+        Ok(files) => Json(files).into_response(),
+        Err(e) => {
+            warn!("Failed to list failed files: {}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response()
+        }
+    }
+}
+
+/// 清除失败记录与失败缓存，下次请求时重新生成缩略图
+#[debug_handler]
+pub async fn retry_failed_files(State(state): State<AppState>, locale: Locale) -> impl IntoResponse {
+    state.file_service.clear_failures();
+
+    match FailedFileRepository::new(&state.db).clear_all().await {
+        Ok(count) => MaintenanceResponse::new(true, locale, Message::FailuresCleared, Some(&count.to_string()))
+            .into_response(),
+        Err(e) => {
+            warn!("Failed to clear failed files: {}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response()
+        }
+    }
+}
+
+/// 按顶层目录对比数据库与磁盘上的文件数，只报告差异，不修改任何数据
+#[debug_handler]
+pub async fn consistency(State(state): State<AppState>) -> impl IntoResponse {
+    match consistency::check(&state.db, &state.config, &state.scan_service).await {
+        Ok(report) => Json(report).into_response(),
+        Err(e) => {
+            warn!("Failed to run consistency check: {}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response()
+        }
+    }
+}
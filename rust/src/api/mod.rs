@@ -1,5 +1,19 @@
+pub mod activity;
+pub mod admin;
+pub mod attributes;
 pub mod files;
+pub mod i18n;
 pub mod directories;
+pub mod dto;
+pub mod exports;
+pub mod maintenance;
+pub mod notes;
+pub mod range;
+pub mod remote;
+pub mod search;
+pub mod sync;
 pub mod system;
+pub mod thumbnails;
+pub mod webhooks;
 
 pub use crate::app::AppState;
@@ -0,0 +1,145 @@
+use crate::{
+    api::{
+        i18n::{self, Locale, Message},
+        AppState,
+    },
+    app::State,
+    db::{MediaFileRepository, MediaNote, NoteRepository},
+};
+use axum::{debug_handler, extract::Path, http::StatusCode, response::IntoResponse, Json};
+use serde::Deserialize;
+use tracing::warn;
+
+/// Request body for adding a note to a file
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateNoteRequest {
+    pub body: String,
+    /// Free-text author name (family member, device, ...)
+    pub author: Option<String>,
+    /// Note being replied to; must belong to the same file
+    pub parent_id: Option<String>,
+}
+
+/// Request body for editing a note
+#[derive(Debug, Deserialize)]
+pub struct UpdateNoteRequest {
+    pub body: String,
+}
+
+/// 列出文件的全部备注（按时间升序，回复通过 parentId 关联上级备注）
+#[debug_handler]
+pub async fn list_notes(
+    State(state): State<AppState>,
+    locale: Locale,
+    Path(id): Path<String>,
+) -> impl IntoResponse {
+    match MediaFileRepository::new(&state.db).find_by_id(&id).await {
+        Ok(Some(_)) => {}
+        Ok(None) => return i18n::error(StatusCode::NOT_FOUND, locale, Message::FileNotFound),
+        Err(e) => {
+            warn!("Failed to get file {}: {}", id, e);
+            return (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response();
+        }
+    }
+
+    match NoteRepository::new(&state.db).find_by_file(&id).await {
+        Ok(notes) => Json(notes).into_response(),
+        Err(e) => {
+            warn!("Failed to list notes of {}: {}", id, e);
+            (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response()
+        }
+    }
+}
+
+/// 添加备注；指定 parentId 时作为该备注的回复
+#[debug_handler]
+pub async fn create_note(
+    State(state): State<AppState>,
+    locale: Locale,
+    Path(id): Path<String>,
+    Json(request): Json<CreateNoteRequest>,
+) -> impl IntoResponse {
+    let body = request.body.trim();
+    if body.is_empty() {
+        return i18n::error(StatusCode::BAD_REQUEST, locale, Message::EmptyNote);
+    }
+
+    match MediaFileRepository::new(&state.db).find_by_id(&id).await {
+        Ok(Some(_)) => {}
+        Ok(None) => return i18n::error(StatusCode::NOT_FOUND, locale, Message::FileNotFound),
+        Err(e) => {
+            warn!("Failed to get file {}: {}", id, e);
+            return (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response();
+        }
+    }
+
+    let repo = NoteRepository::new(&state.db);
+    if let Some(parent_id) = &request.parent_id {
+        match repo.find_by_id(parent_id).await {
+            Ok(Some(parent)) if parent.file_id == id => {}
+            Ok(_) => return i18n::error_with(StatusCode::BAD_REQUEST, locale, Message::InvalidParentNote, parent_id),
+            Err(e) => {
+                warn!("Failed to load note {}: {}", parent_id, e);
+                return (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response();
+            }
+        }
+    }
+
+    let author = request.author.map(|a| a.trim().to_string()).filter(|a| !a.is_empty());
+    let note = MediaNote::new(id, request.parent_id, author, body.to_string());
+    if let Err(e) = repo.insert(&note).await {
+        warn!("Failed to create note on {}: {}", note.file_id, e);
+        return (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response();
+    }
+
+    // 重新读取以带上数据库生成的 created_at
+    match repo.find_by_id(&note.id).await {
+        Ok(Some(stored)) => (StatusCode::CREATED, Json(stored)).into_response(),
+        Ok(None) => (StatusCode::CREATED, Json(note)).into_response(),
+        Err(e) => {
+            warn!("Failed to read created note {}: {}", note.id, e);
+            (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response()
+        }
+    }
+}
+
+/// 修改备注内容
+#[debug_handler]
+pub async fn update_note(
+    State(state): State<AppState>,
+    locale: Locale,
+    Path(id): Path<String>,
+    Json(request): Json<UpdateNoteRequest>,
+) -> impl IntoResponse {
+    let body = request.body.trim();
+    if body.is_empty() {
+        return i18n::error(StatusCode::BAD_REQUEST, locale, Message::EmptyNote);
+    }
+
+    match NoteRepository::new(&state.db).update_body(&id, body).await {
+        Ok(Some(note)) => Json(note).into_response(),
+        Ok(None) => i18n::error(StatusCode::NOT_FOUND, locale, Message::NoteNotFound),
+        Err(e) => {
+            warn!("Failed to update note {}: {}", id, e);
+            (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response()
+        }
+    }
+}
+
+/// 删除备注及其下的全部回复
+#[debug_handler]
+pub async fn delete_note(
+    State(state): State<AppState>,
+    locale: Locale,
+    Path(id): Path<String>,
+) -> impl IntoResponse {
+    match NoteRepository::new(&state.db).delete(&id).await {
+        Ok(0) => i18n::error(StatusCode::NOT_FOUND, locale, Message::NoteNotFound),
+        Ok(_) => StatusCode::NO_CONTENT.into_response(),
+        Err(e) => {
+            warn!("Failed to delete note {}: {}", id, e);
+            (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response()
+        }
+    }
+}
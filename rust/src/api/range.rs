@@ -0,0 +1,198 @@
+//! HTTP Range 请求解析（RFC 7233 bytes 单位）
+//! 支持 "start-end"、开放区间 "start-" 和后缀区间 "-length"；
+//! 多个区间合并为一个覆盖区间返回（不生成 multipart/byteranges）。
+
+/// Inclusive byte window within a file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ByteRange {
+    pub start: u64,
+    pub end: u64,
+}
+
+impl ByteRange {
+    /// Number of bytes in the window (Content-Length of the 206 response)
+    pub fn content_length(&self) -> u64 {
+        self.end - self.start + 1
+    }
+
+    /// Content-Range header value for this window
+    pub fn content_range(&self, file_size: u64) -> String {
+        format!("bytes {}-{}/{}", self.start, self.end, file_size)
+    }
+}
+
+/// Outcome of evaluating a Range header against a file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RangeRequest {
+    /// Header missing, malformed or not in bytes: serve the whole file (200)
+    Full,
+    /// Serve this exact byte window (206)
+    Partial(ByteRange),
+    /// No requested range overlaps the file (416)
+    Unsatisfiable,
+}
+
+/// Evaluate a Range header value against a file of `file_size` bytes
+pub fn parse_range(header: &str, file_size: u64) -> RangeRequest {
+    let Some(specs) = header.trim().strip_prefix("bytes=") else {
+        return RangeRequest::Full;
+    };
+
+    let mut merged: Option<ByteRange> = None;
+    for spec in specs.split(',') {
+        let Some((first, last)) = spec.trim().split_once('-') else {
+            return RangeRequest::Full;
+        };
+        let (first, last) = (first.trim(), last.trim());
+
+        let range = if first.is_empty() {
+            // Suffix range: last N bytes
+            let Ok(suffix) = last.parse::<u64>() else {
+                return RangeRequest::Full;
+            };
+            if suffix == 0 || file_size == 0 {
+                continue;
+            }
+            ByteRange {
+                start: file_size.saturating_sub(suffix),
+                end: file_size - 1,
+            }
+        } else {
+            let Ok(start) = first.parse::<u64>() else {
+                return RangeRequest::Full;
+            };
+            let end = if last.is_empty() {
+                u64::MAX
+            } else {
+                match last.parse::<u64>() {
+                    Ok(end) if end >= start => end,
+                    // 语法错误的区间：按规范忽略整个 Range 头
+                    _ => return RangeRequest::Full,
+                }
+            };
+            if start >= file_size {
+                continue;
+            }
+            ByteRange {
+                start,
+                end: end.min(file_size - 1),
+            }
+        };
+
+        merged = Some(match merged {
+            Some(m) => ByteRange {
+                start: m.start.min(range.start),
+                end: m.end.max(range.end),
+            },
+            None => range,
+        });
+    }
+
+    match merged {
+        Some(range) => RangeRequest::Partial(range),
+        None => RangeRequest::Unsatisfiable,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn partial(start: u64, end: u64) -> RangeRequest {
+        RangeRequest::Partial(ByteRange { start, end })
+    }
+
+    #[test]
+    fn test_closed_range() {
+        assert_eq!(parse_range("bytes=0-499", 1000), partial(0, 499));
+        assert_eq!(parse_range("bytes=500-999", 1000), partial(500, 999));
+        assert_eq!(parse_range("bytes=0-0", 1000), partial(0, 0));
+    }
+
+    #[test]
+    fn test_end_clamped_to_file_size() {
+        assert_eq!(parse_range("bytes=900-5000", 1000), partial(900, 999));
+    }
+
+    #[test]
+    fn test_open_ended_range() {
+        assert_eq!(parse_range("bytes=1000-", 5000), partial(1000, 4999));
+        assert_eq!(parse_range("bytes=0-", 10), partial(0, 9));
+    }
+
+    #[test]
+    fn test_suffix_range() {
+        assert_eq!(parse_range("bytes=-500", 1000), partial(500, 999));
+        // 后缀长度超过文件大小时返回整个文件
+        assert_eq!(parse_range("bytes=-5000", 1000), partial(0, 999));
+        assert_eq!(parse_range("bytes=-0", 1000), RangeRequest::Unsatisfiable);
+    }
+
+    #[test]
+    fn test_unsatisfiable() {
+        assert_eq!(parse_range("bytes=1000-", 1000), RangeRequest::Unsatisfiable);
+        assert_eq!(parse_range("bytes=2000-3000", 1000), RangeRequest::Unsatisfiable);
+        assert_eq!(parse_range("bytes=0-10", 0), RangeRequest::Unsatisfiable);
+    }
+
+    #[test]
+    fn test_multi_range_merged() {
+        assert_eq!(parse_range("bytes=0-99, 200-299", 1000), partial(0, 299));
+        assert_eq!(parse_range("bytes=-100,0-9", 1000), partial(0, 999));
+        // 不可满足的区间被忽略
+        assert_eq!(parse_range("bytes=5000-,10-19", 1000), partial(10, 19));
+    }
+
+    #[test]
+    fn test_malformed_ignored() {
+        assert_eq!(parse_range("items=0-10", 1000), RangeRequest::Full);
+        assert_eq!(parse_range("bytes=abc", 1000), RangeRequest::Full);
+        assert_eq!(parse_range("bytes=10-5", 1000), RangeRequest::Full);
+        assert_eq!(parse_range("bytes=x-5", 1000), RangeRequest::Full);
+        assert_eq!(parse_range("bytes=-", 1000), RangeRequest::Full);
+    }
+
+    #[test]
+    fn test_content_length_and_range() {
+        let range = ByteRange { start: 100, end: 199 };
+        assert_eq!(range.content_length(), 100);
+        assert_eq!(range.content_range(1000), "bytes 100-199/1000");
+    }
+
+    /// 206 的区间必须落在文件内：start ≤ end < 文件大小
+    fn assert_within(result: RangeRequest, file_size: u64) -> Result<(), proptest::test_runner::TestCaseError> {
+        if let RangeRequest::Partial(range) = result {
+            proptest::prop_assert!(range.start <= range.end);
+            proptest::prop_assert!(range.end < file_size);
+            proptest::prop_assert!(range.content_length() <= file_size);
+        }
+        Ok(())
+    }
+
+    proptest::proptest! {
+        #[test]
+        fn prop_arbitrary_header_within_file(header in "\\PC*", file_size in 0u64..u64::MAX) {
+            assert_within(parse_range(&header, file_size), file_size)?;
+        }
+
+        #[test]
+        fn prop_range_like_header_within_file(
+            header in "bytes= ?[0-9]{0,21}-[0-9]{0,21}(, ?-?[0-9]{0,21}-?[0-9]{0,21}){0,3}",
+            file_size in 0u64..10_000_000,
+        ) {
+            assert_within(parse_range(&header, file_size), file_size)?;
+        }
+
+        #[test]
+        fn prop_closed_range_inside_file(file_size in 1u64..u64::MAX, a in proptest::num::u64::ANY, b in proptest::num::u64::ANY) {
+            let (start, end) = ((a % file_size).min(b % file_size), (a % file_size).max(b % file_size));
+            proptest::prop_assert_eq!(parse_range(&format!("bytes={}-{}", start, end), file_size), partial(start, end));
+        }
+
+        #[test]
+        fn prop_suffix_range_is_file_tail(file_size in 1u64..u64::MAX, suffix in 1u64..u64::MAX) {
+            let expected = partial(file_size.saturating_sub(suffix), file_size - 1);
+            proptest::prop_assert_eq!(parse_range(&format!("bytes=-{}", suffix), file_size), expected);
+        }
+    }
+}
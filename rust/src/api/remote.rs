@@ -0,0 +1,203 @@
+//! 远程图库合并与代理（见 services::remote_library）
+//! 列表接口按页合并：第 N 页 = 本机第 N 页 + 远程第 N 页，按排序字段重新排序，
+//! 每个文件在所有页中恰好出现一次，但单页条数最多为 2 × size。
+
+use crate::{
+    api::{
+        i18n::{self, Locale, Message},
+        AppState,
+    },
+    db::{DateInfo, SortField, SortOrder},
+    services::remote_library::{RemoteLibrary, FEDERATED_HEADER},
+};
+use axum::{
+    body::Body,
+    http::{HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+};
+use serde_json::Value;
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+use tracing::warn;
+
+/// Response headers forwarded from the remote on proxied media
+const FORWARDED_HEADERS: &[&str] = &[
+    "content-type",
+    "content-length",
+    "content-range",
+    "accept-ranges",
+    "content-disposition",
+    "cache-control",
+    "etag",
+];
+
+/// Remote library to merge for this request.
+/// Requests coming from a federated peer are never merged again (prevents loops between two instances).
+pub(crate) fn merge_target<'a>(state: &'a AppState, headers: &HeaderMap) -> Option<&'a RemoteLibrary> {
+    if headers.contains_key(FEDERATED_HEADER) {
+        return None;
+    }
+    state.remote_library.as_deref()
+}
+
+/// Remote page merged into a local one
+pub(crate) struct RemotePage {
+    pub items: Vec<Value>,
+    pub total: i64,
+    pub total_pages: i32,
+}
+
+/// Fetch the same page from the remote; failures degrade to local-only results
+pub(crate) async fn fetch_file_page(remote: &RemoteLibrary, raw_query: Option<&str>) -> Option<RemotePage> {
+    let path = match raw_query {
+        Some(q) if !q.is_empty() => format!("/api/files?{}", q),
+        _ => "/api/files".to_string(),
+    };
+
+    match remote.get_json(&path).await {
+        Ok(page) => Some(RemotePage {
+            items: page["items"]
+                .as_array()
+                .cloned()
+                .unwrap_or_default()
+                .into_iter()
+                .map(|item| remote.tag_item(item))
+                .collect(),
+            total: page["total"].as_i64().unwrap_or(0),
+            total_pages: page["totalPages"].as_i64().unwrap_or(0) as i32,
+        }),
+        Err(e) => {
+            warn!("Remote library {} unavailable: {}", remote.name(), e);
+            None
+        }
+    }
+}
+
+/// Sort merged items by the list sort key (camelCase), missing values last like the local ORDER BY
+pub(crate) fn sort_merged(items: &mut [Value], sort: SortField, order: SortOrder) {
+    let key = sort.as_str();
+    let desc = order == SortOrder::Desc;
+
+    items.sort_by(|a, b| match (non_null(a, key), non_null(b, key)) {
+        (Some(x), Some(y)) => {
+            let ord = compare_values(x, y);
+            if desc { ord.reverse() } else { ord }
+        }
+        (Some(_), None) => Ordering::Less,
+        (None, Some(_)) => Ordering::Greater,
+        (None, None) => Ordering::Equal,
+    });
+}
+
+fn non_null<'a>(item: &'a Value, key: &str) -> Option<&'a Value> {
+    item.get(key).filter(|v| !v.is_null())
+}
+
+fn compare_values(a: &Value, b: &Value) -> Ordering {
+    match (a, b) {
+        (Value::Number(x), Value::Number(y)) => x
+            .as_f64()
+            .partial_cmp(&y.as_f64())
+            .unwrap_or(Ordering::Equal),
+        _ => a.as_str().unwrap_or_default().cmp(b.as_str().unwrap_or_default()),
+    }
+}
+
+/// Merge remote timeline dates into local ones (counts summed, newest first)
+pub(crate) async fn merge_dates(remote: &RemoteLibrary, local: Vec<DateInfo>) -> Vec<DateInfo> {
+    let remote_dates: Vec<DateInfo> = match remote.get_json("/api/files/dates").await {
+        Ok(value) => serde_json::from_value(value).unwrap_or_default(),
+        Err(e) => {
+            warn!("Remote library {} unavailable: {}", remote.name(), e);
+            return local;
+        }
+    };
+
+    let mut merged: BTreeMap<String, i64> = BTreeMap::new();
+    for info in local.into_iter().chain(remote_dates) {
+        *merged.entry(info.date).or_insert(0) += info.count;
+    }
+
+    merged
+        .into_iter()
+        .rev()
+        .map(|(date, count)| DateInfo { date, count })
+        .collect()
+}
+
+/// Fetch a remote file's details, tagged for the merged view
+pub(crate) async fn get_remote_file(remote: &RemoteLibrary, remote_id: &str, locale: Locale) -> Response {
+    match remote.get_json(&format!("/api/files/{}", remote_id)).await {
+        Ok(item) => axum::Json(remote.tag_item(item)).into_response(),
+        Err(e) => {
+            warn!("Failed to get remote file {}: {}", remote_id, e);
+            i18n::error(StatusCode::NOT_FOUND, locale, Message::FileNotFound)
+        }
+    }
+}
+
+/// Stream a remote media response (thumbnail/original) back to the client
+pub(crate) async fn proxy_media(
+    remote: &RemoteLibrary,
+    method: reqwest::Method,
+    path_and_query: &str,
+    headers: &HeaderMap,
+    locale: Locale,
+) -> Response {
+    let range = headers.get("range").and_then(|v| v.to_str().ok());
+
+    let upstream = match remote.proxy(method, path_and_query, range).await {
+        Ok(response) => response,
+        Err(e) => {
+            warn!("Failed to proxy {} from remote library: {}", path_and_query, e);
+            return i18n::error(StatusCode::BAD_GATEWAY, locale, Message::RemoteUnavailable);
+        }
+    };
+
+    let status = StatusCode::from_u16(upstream.status().as_u16()).unwrap_or(StatusCode::BAD_GATEWAY);
+    let mut response_headers = HeaderMap::new();
+    for name in FORWARDED_HEADERS {
+        if let Some(value) = upstream.headers().get(*name) {
+            response_headers.insert(*name, value.clone());
+        }
+    }
+
+    // reqwest 未启用 stream feature，用 chunk() 逐块转发，避免整段读入内存
+    let stream = futures_util::stream::unfold(upstream, |mut upstream| async move {
+        match upstream.chunk().await {
+            Ok(Some(bytes)) => Some((Ok(bytes), upstream)),
+            Ok(None) => None,
+            Err(e) => Some((Err(e), upstream)),
+        }
+    });
+
+    (status, response_headers, Body::from_stream(stream)).into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_sort_merged_desc_nulls_last() {
+        let mut items = vec![
+            json!({ "id": "a", "exifTimestamp": "2021-01-01 00:00:00" }),
+            json!({ "id": "b" }),
+            json!({ "id": "c", "exifTimestamp": "2023-01-01 00:00:00" }),
+        ];
+        sort_merged(&mut items, SortField::ExifTimestamp, SortOrder::Desc);
+        let ids: Vec<&str> = items.iter().map(|i| i["id"].as_str().unwrap()).collect();
+        assert_eq!(ids, vec!["c", "a", "b"]);
+    }
+
+    #[test]
+    fn test_sort_merged_asc_by_name() {
+        let mut items = vec![
+            json!({ "id": "1", "fileName": "b.jpg" }),
+            json!({ "id": "2", "fileName": "a.jpg" }),
+        ];
+        sort_merged(&mut items, SortField::FileName, SortOrder::Asc);
+        assert_eq!(items[0]["fileName"], "a.jpg");
+    }
+}
@@ -0,0 +1,143 @@
+use crate::{
+    api::{
+        dto::FileDetail,
+        files::{self, PaginatedResponse},
+        i18n::{self, Locale, Message},
+        AppState,
+    },
+    app::State,
+    db::{MediaFileRepository, TaggingRepository},
+    services::semantic_search,
+};
+use axum::{
+    debug_handler,
+    extract::Query,
+    http::StatusCode,
+    response::IntoResponse,
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+
+/// Query parameters for full-text search
+#[derive(Debug, Deserialize)]
+pub struct TextSearchParams {
+    pub q: Option<String>,
+    pub page: Option<i32>,
+    pub size: Option<i32>,
+}
+
+/// 全文搜索：按文件名、标题与描述匹配，所有词都需命中，结果按相关度排序
+#[debug_handler]
+pub async fn text_search(
+    State(state): State<AppState>,
+    locale: Locale,
+    Query(params): Query<TextSearchParams>,
+) -> impl IntoResponse {
+    let Some(query) = params.q.as_deref().map(str::trim).filter(|q| !q.is_empty()) else {
+        return i18n::error(StatusCode::BAD_REQUEST, locale, Message::MissingSearchQuery);
+    };
+    let (page, size) = match files::validate_pagination(params.page, params.size, &state.config) {
+        Ok(pagination) => pagination,
+        Err((message, detail)) => return i18n::error_with(StatusCode::BAD_REQUEST, locale, message, &detail),
+    };
+
+    match MediaFileRepository::new(&state.db).search_text(query, page, size).await {
+        Ok((files, total)) => Json(PaginatedResponse {
+            items: files.into_iter().map(FileDetail::from).collect::<Vec<_>>(),
+            total,
+            page,
+            size,
+            total_pages: ((total as f64) / (size as f64)).ceil() as i32,
+        })
+        .into_response(),
+        Err(e) => {
+            warn!("Failed to search files: {}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response()
+        }
+    }
+}
+
+/// Query parameters for semantic search
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SemanticSearchParams {
+    pub q: Option<String>,
+    /// Maximum number of results (default: 50, max: 200)
+    pub limit: Option<usize>,
+    /// Minimum cosine similarity of returned files (default: 0)
+    pub min_score: Option<f32>,
+}
+
+/// One search result: the media file plus its similarity to the query
+#[derive(Debug, Serialize)]
+pub struct SemanticSearchHit {
+    #[serde(flatten)]
+    pub file: FileDetail,
+    pub score: f32,
+}
+
+/// 语义搜索：查询文本经外部服务转换为向量，按与图片向量的余弦相似度降序返回
+#[debug_handler]
+pub async fn semantic_search(
+    State(state): State<AppState>,
+    locale: Locale,
+    Query(params): Query<SemanticSearchParams>,
+) -> impl IntoResponse {
+    let Some(query) = params.q.as_deref().map(str::trim).filter(|q| !q.is_empty()) else {
+        return i18n::error(StatusCode::BAD_REQUEST, locale, Message::MissingSearchQuery);
+    };
+    let max_limit = state.config.api_max_page_size;
+    let limit = params.limit.unwrap_or(state.config.api_default_page_size.min(max_limit));
+    if !(1..=max_limit).contains(&limit) {
+        return i18n::error_with(StatusCode::BAD_REQUEST, locale, Message::InvalidPageSize, &format!("1-{}", max_limit));
+    }
+    let min_score = params.min_score.unwrap_or(0.0);
+
+    let Some(tagging) = state.tagging.as_ref() else {
+        return i18n::error(StatusCode::SERVICE_UNAVAILABLE, locale, Message::SemanticSearchDisabled);
+    };
+    let query_vector = match tagging.embed_text(query).await {
+        Ok(Some(vector)) => vector,
+        Ok(None) => {
+            return i18n::error(StatusCode::SERVICE_UNAVAILABLE, locale, Message::SemanticSearchDisabled);
+        }
+        Err(e) => {
+            warn!("Failed to embed search query: {}", e);
+            return (StatusCode::BAD_GATEWAY, e.to_string()).into_response();
+        }
+    };
+
+    let embeddings = match TaggingRepository::new(&state.db).find_embeddings().await {
+        Ok(embeddings) => embeddings,
+        Err(e) => {
+            warn!("Failed to load embeddings: {}", e);
+            return (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response();
+        }
+    };
+
+    // 暴力搜索是纯 CPU 计算，避免阻塞异步运行时
+    let ranked = match tokio::task::spawn_blocking(move || {
+        semantic_search::rank(&query_vector, embeddings, min_score, limit)
+    })
+    .await
+    {
+        Ok(ranked) => ranked,
+        Err(e) => return (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+    };
+
+    let repo = MediaFileRepository::new(&state.db);
+    let mut hits = Vec::with_capacity(ranked.len());
+    for (id, score) in ranked {
+        match repo.find_by_id(&id).await {
+            Ok(Some(file)) => hits.push(SemanticSearchHit { file: file.into(), score }),
+            Ok(None) => {}
+            Err(e) => {
+                warn!("Failed to load search result {}: {}", id, e);
+                return (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response();
+            }
+        }
+    }
+
+    Json(hits).into_response()
+}
@@ -0,0 +1,176 @@
+use crate::{
+    api::{
+        dto::ListItem,
+        files::thumbnail_url_version,
+        i18n::{self, Locale, Message},
+        AppState,
+    },
+    app::State,
+    db::{ChangeKind, ChangeLogRepository, MediaFileRepository},
+};
+use axum::{debug_handler, extract::Query, http::StatusCode, response::IntoResponse, Json};
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use tracing::warn;
+
+/// Changes returned per call unless `limit` is given
+const DEFAULT_LIMIT: i64 = 500;
+
+/// Largest accepted `limit`
+const MAX_LIMIT: i64 = 5000;
+
+/// Query parameters of GET /api/sync/changes
+#[derive(Debug, Deserialize)]
+pub struct SyncChangesParams {
+    /// Cursor returned by the previous call; omitted on the first call
+    pub since: Option<String>,
+    pub limit: Option<i64>,
+}
+
+/// Latest change of one media file
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncChange {
+    pub id: String,
+    /// "created", "updated" or "deleted"
+    pub change: String,
+    pub changed_at: NaiveDateTime,
+    /// Current record, absent for deletions
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub file: Option<ListItem>,
+}
+
+/// Response of GET /api/sync/changes
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncChangesResponse {
+    pub changes: Vec<SyncChange>,
+    /// Pass as `since` in the next call
+    pub cursor: String,
+    /// More changes follow; call again right away with `cursor`
+    pub has_more: bool,
+    /// `since` is older than the kept history (LATTE_SYNC_RETENTION_DAYS): download the
+    /// full list again, then continue from `cursor`
+    pub reset_required: bool,
+}
+
+/// 增量同步：返回游标之后新增、修改、删除的媒体文件（每个文件只返回最新一次变化），
+/// 供移动端或离线镜像增量更新，不必重新下载完整列表。
+/// 首次调用不带 since，只返回当前游标：先保存游标，再下载完整列表，之后从该游标开始增量同步。
+#[debug_handler]
+pub async fn list_changes(
+    State(state): State<AppState>,
+    locale: Locale,
+    Query(params): Query<SyncChangesParams>,
+) -> impl IntoResponse {
+    let since = match params.since.as_deref() {
+        None => None,
+        Some(value) => match parse_cursor(value) {
+            Some(since) => Some(since),
+            None => return i18n::error(StatusCode::BAD_REQUEST, locale, Message::InvalidSyncCursor),
+        },
+    };
+    let limit = params.limit.unwrap_or(DEFAULT_LIMIT).clamp(1, MAX_LIMIT);
+
+    match changes_since(&state, since, limit).await {
+        Ok(Some(response)) => Json(response).into_response(),
+        // 游标比最新的变更还新，不是本服务发出的
+        Ok(None) => i18n::error(StatusCode::BAD_REQUEST, locale, Message::InvalidSyncCursor),
+        Err(e) => {
+            warn!("Failed to list changes since {:?}: {}", since, e);
+            (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response()
+        }
+    }
+}
+
+/// Cursors are the decimal seq of the change log
+fn parse_cursor(value: &str) -> Option<i64> {
+    value.trim().parse::<i64>().ok().filter(|seq| *seq >= 0)
+}
+
+/// None if `since` is past the newest change
+async fn changes_since(
+    state: &AppState,
+    since: Option<i64>,
+    limit: i64,
+) -> Result<Option<SyncChangesResponse>, sqlx::Error> {
+    let changes = ChangeLogRepository::new(&state.db);
+    let latest = changes.latest_seq().await?;
+    let empty = |reset_required| SyncChangesResponse {
+        changes: Vec::new(),
+        cursor: latest.to_string(),
+        has_more: false,
+        reset_required,
+    };
+
+    let Some(since) = since else {
+        return Ok(Some(empty(false)));
+    };
+    if since > latest {
+        return Ok(None);
+    }
+    let oldest = changes.oldest_seq().await?.unwrap_or(latest + 1);
+    if since < oldest - 1 {
+        return Ok(Some(empty(true)));
+    }
+
+    let mut entries = changes.find_since(since, limit + 1).await?;
+    let has_more = entries.len() as i64 > limit;
+    entries.truncate(limit as usize);
+    let cursor = entries.last().map_or(latest, |entry| entry.seq);
+
+    let ids: Vec<String> = entries
+        .iter()
+        .filter(|entry| entry.change != ChangeKind::Deleted.as_str())
+        .map(|entry| entry.file_id.clone())
+        .collect();
+    let mut files: HashMap<String, _> = MediaFileRepository::new(&state.db)
+        .find_by_ids(&ids)
+        .await?
+        .into_iter()
+        .map(|file| (file.id.clone(), file))
+        .collect();
+
+    let changes = entries
+        .into_iter()
+        .map(|entry| {
+            let file = files.remove(&entry.file_id).map(|file| {
+                let version = file.content_hash.as_deref().map(|hash| thumbnail_url_version(state, hash));
+                ListItem::with_thumbnail_version(file, version.as_deref())
+            });
+            // 记录已不存在（例如日志写入后又被删除）时按删除返回
+            let change = match file {
+                Some(_) => entry.change,
+                None => ChangeKind::Deleted.as_str().to_string(),
+            };
+            SyncChange {
+                id: entry.file_id,
+                change,
+                changed_at: entry.changed_at,
+                file,
+            }
+        })
+        .collect();
+
+    Ok(Some(SyncChangesResponse {
+        changes,
+        cursor: cursor.to_string(),
+        has_more,
+        reset_required: false,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_cursor() {
+        assert_eq!(parse_cursor("0"), Some(0));
+        assert_eq!(parse_cursor(" 42 "), Some(42));
+        for value in ["", "-1", "abc", "1.5"] {
+            assert_eq!(parse_cursor(value), None, "{value}");
+        }
+    }
+}
@@ -1,11 +1,25 @@
-use crate::{api::AppState, app::State};
-use axum::{debug_handler, response::IntoResponse, Json};
-use serde::Serialize;
+use crate::{api::{i18n::{self, Locale, Message}, AppState}, app::State, processors::MediaType, services::{disk_usage::{disk_usage, DiskUsage}, DependencyStatus, LibraryCapabilities, ScanMode, scan_report::ScanReport}, websocket::ScanProgressMessage};
+use axum::{debug_handler, extract::Query, http::StatusCode, response::IntoResponse, Json};
+use serde::{Deserialize, Serialize};
+
+/// Query parameters for rescan trigger
+#[derive(Debug, Deserialize, Default)]
+pub struct RescanParams {
+    /// Re-extract metadata for every file, bypassing the modify_time skip list
+    #[serde(default)]
+    pub force: bool,
+    /// Only reconcile existence/modify_time and extract metadata in a follow-up scan
+    /// (ignored when force is set)
+    #[serde(default)]
+    pub quick: bool,
+}
 
 /// Response for rescan trigger
 #[derive(Debug, Serialize)]
 pub struct RescanResponse {
     pub success: bool,
+    /// Stable message key (see api::i18n)
+    pub code: &'static str,
     pub message: String,
 }
 
@@ -23,12 +37,56 @@ pub struct ScanProgressResponse {
     pub files_to_update: u64,
     pub files_to_delete: u64,
     pub start_time: Option<String>,
+    /// Scan that starts automatically after the current one ("incremental" / "force" / "quick")
+    pub queued_scan: Option<ScanMode>,
+}
+
+/// Response of GET /api/scan/status: the same state the WebSocket pushes
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScanStatusResponse {
+    #[serde(flatten)]
+    pub progress: ScanProgressMessage,
+    pub queued_scan: Option<ScanMode>,
+    /// Summary of the last finished scan, only while idle
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_scan: Option<LastScanSummary>,
+}
+
+/// Totals of a finished scan (the full report is at GET /api/scan/report)
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LastScanSummary {
+    pub mode: ScanMode,
+    /// "completed" or "cancelled"
+    pub status: String,
+    pub start_time: String,
+    pub duration_ms: u64,
+    pub total_files: u64,
+    pub processed_files: u64,
+    pub failed_files: u64,
+}
+
+impl From<ScanReport> for LastScanSummary {
+    fn from(report: ScanReport) -> Self {
+        Self {
+            mode: report.mode,
+            status: report.status,
+            start_time: report.start_time,
+            duration_ms: report.duration_ms,
+            total_files: report.total_files,
+            processed_files: report.processed_files,
+            failed_files: report.failed_files,
+        }
+    }
 }
 
 /// Response for cancel operation
 #[derive(Debug, Serialize)]
 pub struct CancelResponse {
     pub success: bool,
+    /// Stable message key (see api::i18n)
+    pub code: &'static str,
     pub message: String,
 }
 
@@ -43,19 +101,94 @@ pub struct SystemStatus {
     pub last_scan_time: Option<String>,
 }
 
+/// One registered media processor
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProcessorResponse {
+    pub name: String,
+    pub media_type: String,
+    pub extensions: Vec<String>,
+    pub priority: i32,
+    pub enabled: bool,
+}
+
+/// Optional features compiled into this build
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FeaturesResponse {
+    /// HEIC/HEIF decoding via libheif
+    pub heif: bool,
+    /// In-process video decoding via ffmpeg-next; without it videos go through the ffprobe/ffmpeg CLI
+    pub video_processing: bool,
+}
+
+/// Response for processor capability listing
+#[derive(Debug, Serialize)]
+pub struct ProcessorsResponse {
+    pub processors: Vec<ProcessorResponse>,
+    pub features: FeaturesResponse,
+}
+
+/// Library file counts
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LibraryStats {
+    pub total_files: i64,
+    pub image_count: i64,
+    pub video_count: i64,
+}
+
+/// System information response (frontend "About" page, bug reports)
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SystemInfo {
+    pub version: String,
+    pub git_hash: String,
+    pub uptime_seconds: u64,
+    pub library: LibraryStats,
+    /// Filesystem holding base_path; None when it cannot be queried
+    pub disk: Option<DiskUsage>,
+    pub cache_size_bytes: u64,
+    /// Database file plus its WAL
+    pub db_size_bytes: u64,
+    /// Native dependencies probed at startup
+    pub dependencies: Vec<DependencyStatus>,
+    /// Read-only detection for base_path; the frontend hides actions that modify originals
+    pub capabilities: LibraryCapabilities,
+}
+
 #[debug_handler]
-pub async fn trigger_rescan(State(state): State<AppState>) -> impl IntoResponse {
+pub async fn trigger_rescan(
+    State(state): State<AppState>,
+    locale: Locale,
+    Query(params): Query<RescanParams>,
+) -> impl IntoResponse {
     // Start scan in background task to avoid blocking API requests
     let scan_service = state.scan_service.clone();
+    let mode = if params.force {
+        ScanMode::Force
+    } else if params.quick {
+        ScanMode::Quick
+    } else {
+        ScanMode::Incremental
+    };
+    let queued = scan_service.is_scanning();
 
     tokio::spawn(async move {
-        tracing::info!("Triggering rescan");
-        scan_service.scan().await;
+        tracing::info!("Triggering rescan (mode: {:?})", mode);
+        scan_service.scan_with_mode(mode).await;
     });
 
+    let message = match (queued, params.force) {
+        (true, true) => Message::ForceScanQueued,
+        (true, false) => Message::ScanQueued,
+        (false, true) => Message::ForceScanStarted,
+        (false, false) => Message::ScanStarted,
+    };
     Json(RescanResponse {
         success: true,
-        message: "Scan started".to_string(),
+        code: message.code(),
+        message: message.text(locale).to_string(),
     })
 }
 
@@ -74,20 +207,45 @@ pub async fn get_scan_progress(State(state): State<AppState>) -> impl IntoRespon
         files_to_update: progress.files_to_update,
         files_to_delete: progress.files_to_delete,
         start_time: progress.start_time,
+        queued_scan: state.scan_service.queued_scan(),
+    })
+}
+
+/// Scan state for clients without a WebSocket (scripts, health checks)
+#[debug_handler]
+pub async fn get_scan_status(State(state): State<AppState>) -> impl IntoResponse {
+    let progress = state.scan_state.to_progress_message();
+    let last_scan = if progress.scanning || state.scan_service.is_scanning() {
+        None
+    } else {
+        state.scan_service.last_report().map(LastScanSummary::from)
+    };
+
+    Json(ScanStatusResponse {
+        progress,
+        queued_scan: state.scan_service.queued_scan(),
+        last_scan,
     })
 }
 
+/// Report of the last scan: slowest files, largest directories, failures by extension
 #[debug_handler]
-pub async fn cancel_scan(State(state): State<AppState>) -> impl IntoResponse {
+pub async fn get_scan_report(State(state): State<AppState>, locale: Locale) -> impl IntoResponse {
+    match state.scan_service.last_report() {
+        Some(report) => Json(report).into_response(),
+        None => i18n::error(StatusCode::NOT_FOUND, locale, Message::NoScanReport),
+    }
+}
+
+#[debug_handler]
+pub async fn cancel_scan(State(state): State<AppState>, locale: Locale) -> impl IntoResponse {
     let cancelled = state.scan_service.cancel().await;
 
+    let message = if cancelled { Message::ScanCancelled } else { Message::NoScanInProgress };
     Json(CancelResponse {
         success: cancelled,
-        message: if cancelled {
-            "Scan cancelled".to_string()
-        } else {
-            "No scan in progress".to_string()
-        },
+        code: message.code(),
+        message: message.text(locale).to_string(),
     })
 }
 
@@ -96,17 +254,17 @@ pub async fn get_status(State(state): State<AppState>) -> impl IntoResponse {
     // Get file counts
     let db = &state.db;
     let total_files = sqlx::query_scalar::<_, i64>("SELECT COUNT(*) FROM media_files")
-        .fetch_one(db.get_pool())
+        .fetch_one(db.read_pool())
         .await
         .unwrap_or(0);
 
     let image_count = sqlx::query_scalar::<_, i64>("SELECT COUNT(*) FROM media_files WHERE file_type = 'image'")
-        .fetch_one(db.get_pool())
+        .fetch_one(db.read_pool())
         .await
         .unwrap_or(0);
 
     let video_count = sqlx::query_scalar::<_, i64>("SELECT COUNT(*) FROM media_files WHERE file_type = 'video'")
-        .fetch_one(db.get_pool())
+        .fetch_one(db.read_pool())
         .await
         .unwrap_or(0);
 
@@ -121,7 +279,7 @@ pub async fn get_status(State(state): State<AppState>) -> impl IntoResponse {
     let last_scan_time = sqlx::query_scalar::<_, String>(
         "SELECT MAX(last_scanned) FROM media_files WHERE last_scanned IS NOT NULL"
     )
-    .fetch_optional(db.get_pool())
+    .fetch_optional(db.read_pool())
     .await
     .unwrap_or(None);
 
@@ -134,3 +292,78 @@ pub async fn get_status(State(state): State<AppState>) -> impl IntoResponse {
         last_scan_time,
     })
 }
+
+#[debug_handler]
+pub async fn get_processors(State(state): State<AppState>) -> impl IntoResponse {
+    let processors = state
+        .processors
+        .list()
+        .into_iter()
+        .map(|p| ProcessorResponse {
+            name: p.name.to_string(),
+            media_type: match p.media_type {
+                MediaType::Image => "image",
+                MediaType::Video => "video",
+                MediaType::Heif => "heif",
+            }
+            .to_string(),
+            extensions: p.extensions,
+            priority: p.priority,
+            enabled: p.enabled,
+        })
+        .collect();
+
+    Json(ProcessorsResponse {
+        processors,
+        features: FeaturesResponse {
+            heif: true,
+            video_processing: cfg!(feature = "video-processing"),
+        },
+    })
+}
+
+#[debug_handler]
+pub async fn get_info(State(state): State<AppState>) -> impl IntoResponse {
+    let pool = state.db.read_pool();
+    let library = sqlx::query_as::<_, (i64, i64, i64)>(
+        "SELECT COUNT(*), \
+                COALESCE(SUM(file_type = 'image'), 0), \
+                COALESCE(SUM(file_type = 'video'), 0) \
+         FROM media_files",
+    )
+    .fetch_one(pool)
+    .await
+    .map(|(total_files, image_count, video_count)| LibraryStats { total_files, image_count, video_count })
+    .unwrap_or(LibraryStats { total_files: 0, image_count: 0, video_count: 0 });
+
+    let cache_size_bytes = state
+        .cache_service
+        .get_cache_size_bytes()
+        .await
+        .unwrap_or(0);
+
+    let db_path = state.config.db_path.clone();
+    let mut wal_path = db_path.clone().into_os_string();
+    wal_path.push("-wal");
+    let mut db_size_bytes = 0;
+    for path in [db_path, wal_path.into()] {
+        db_size_bytes += tokio::fs::metadata(&path).await.map(|m| m.len()).unwrap_or(0);
+    }
+
+    let base_path = state.config.base_path.clone();
+    let disk = tokio::task::spawn_blocking(move || disk_usage(&base_path))
+        .await
+        .unwrap_or(None);
+
+    Json(SystemInfo {
+        version: env!("CARGO_PKG_VERSION").to_string(),
+        git_hash: option_env!("LATTE_GIT_HASH").unwrap_or("unknown").to_string(),
+        uptime_seconds: state.started_at.elapsed().as_secs(),
+        library,
+        disk,
+        cache_size_bytes,
+        db_size_bytes,
+        dependencies: state.dependencies.as_ref().clone(),
+        capabilities: state.capabilities.as_ref().clone(),
+    })
+}
@@ -0,0 +1,107 @@
+use crate::{
+    api::{
+        files::get_size_label,
+        i18n::{self, Locale, Message},
+        AppState,
+    },
+    app::State,
+    services::thumbnail_queue::{EnqueueResult, ThumbnailJob},
+};
+use axum::{
+    debug_handler,
+    http::{header, StatusCode},
+    response::IntoResponse,
+    Json,
+};
+use serde::{Deserialize, Serialize};
+
+/// Maximum number of file ids accepted in a single warm request
+const MAX_WARM_IDS: usize = 500;
+
+/// Request body for thumbnail pre-warming
+#[derive(Debug, Deserialize)]
+pub struct WarmThumbnailsRequest {
+    pub ids: Vec<String>,
+    /// Size labels to warm ("small", "medium", "large", "full"); defaults to ["medium"]
+    #[serde(default)]
+    pub sizes: Vec<String>,
+}
+
+/// Response for thumbnail pre-warming
+#[derive(Debug, Serialize)]
+pub struct WarmThumbnailsResponse {
+    pub queued: usize,
+    pub skipped: usize,
+    pub dropped: usize,
+}
+
+/// 将一批文件的缩略图加入后台生成队列，立即返回（不等待生成完成）。
+/// 前端在用户浏览当前页时调用，提前预热下一页的缩略图。
+#[debug_handler]
+pub async fn warm_thumbnails(
+    State(state): State<AppState>,
+    locale: Locale,
+    Json(request): Json<WarmThumbnailsRequest>,
+) -> impl IntoResponse {
+    if request.ids.len() > MAX_WARM_IDS {
+        return i18n::error_with(StatusCode::BAD_REQUEST, locale, Message::TooManyIds, &format!("max {}", MAX_WARM_IDS));
+    }
+
+    let sizes = if request.sizes.is_empty() {
+        vec!["medium".to_string()]
+    } else {
+        request.sizes
+    };
+
+    for size in &sizes {
+        if !matches!(size.as_str(), "small" | "medium" | "large" | "full") {
+            return i18n::error_with(StatusCode::BAD_REQUEST, locale, Message::InvalidThumbnailSize, size);
+        }
+    }
+
+    let mut response = WarmThumbnailsResponse {
+        queued: 0,
+        skipped: 0,
+        dropped: 0,
+    };
+
+    for id in &request.ids {
+        for size in &sizes {
+            let job = ThumbnailJob {
+                file_id: id.clone(),
+                size_label: get_size_label(size),
+                target_size: state.config.get_thumbnail_size(size),
+                fit_to_height: size == "large",
+            };
+
+            match state.thumbnail_queue.enqueue(job).await {
+                EnqueueResult::Queued => response.queued += 1,
+                EnqueueResult::Skipped => response.skipped += 1,
+                EnqueueResult::Full => response.dropped += 1,
+            }
+        }
+    }
+
+    (StatusCode::ACCEPTED, Json(response)).into_response()
+}
+
+/// 缩略图预生成进度（与 WebSocket `type: "thumbnails"` 消息相同）
+#[debug_handler]
+pub async fn get_thumbnail_progress(State(state): State<AppState>) -> impl IntoResponse {
+    Json(state.thumbnail_queue.progress().current())
+}
+
+/// 各源格式/输出格式的解码、缩放、编码耗时统计（进程启动以来），用于调整转码线程数与输出格式
+#[debug_handler]
+pub async fn get_thumbnail_stats(State(state): State<AppState>) -> impl IntoResponse {
+    Json(state.thumbnail_metrics.stats(state.config.transcoding_threads))
+}
+
+/// Prometheus 文本格式的指标
+#[debug_handler]
+pub async fn get_metrics(State(state): State<AppState>) -> impl IntoResponse {
+    (
+        [(header::CONTENT_TYPE, "text/plain; version=0.0.4; charset=utf-8")],
+        state.thumbnail_metrics.render_prometheus(),
+    )
+}
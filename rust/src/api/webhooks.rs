@@ -0,0 +1,163 @@
+use crate::{
+    api::{
+        i18n::{self, Locale, Message},
+        AppState,
+    },
+    app::State,
+    db::{Webhook, WebhookRepository},
+    services::WebhookEvent,
+};
+use axum::{debug_handler, extract::Path, http::StatusCode, response::IntoResponse, Json};
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+
+/// Request body for registering a webhook
+#[derive(Debug, Deserialize)]
+pub struct CreateWebhookRequest {
+    /// http(s) URL receiving the POST requests
+    pub url: String,
+    /// Subscribed events ("scan.completed", "scan.failed", "files.imported", "disk.low"); empty = all
+    #[serde(default)]
+    pub events: Vec<String>,
+    /// Key for the X-Latte-Signature HMAC-SHA256 header
+    pub secret: Option<String>,
+}
+
+/// Webhook as returned by the API; the secret itself is never returned
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WebhookResponse {
+    pub id: String,
+    pub url: String,
+    pub events: Vec<String>,
+    pub has_secret: bool,
+    pub enabled: bool,
+    pub created_at: Option<String>,
+}
+
+impl From<&Webhook> for WebhookResponse {
+    fn from(webhook: &Webhook) -> Self {
+        Self {
+            id: webhook.id.clone(),
+            url: webhook.url.clone(),
+            events: webhook.event_list(),
+            has_secret: webhook.secret.as_deref().is_some_and(|s| !s.is_empty()),
+            enabled: webhook.enabled,
+            created_at: webhook.created_at.map(|t| t.and_utc().to_rfc3339()),
+        }
+    }
+}
+
+/// Result of a test delivery
+#[derive(Debug, Serialize)]
+pub struct WebhookTestResponse {
+    pub success: bool,
+    /// Stable message key (see api::i18n)
+    pub code: &'static str,
+    pub message: String,
+}
+
+/// 列出已注册的 Webhook
+#[debug_handler]
+pub async fn list_webhooks(State(state): State<AppState>) -> impl IntoResponse {
+    match WebhookRepository::new(&state.db).find_all().await {
+        Ok(webhooks) => Json(webhooks.iter().map(WebhookResponse::from).collect::<Vec<_>>()).into_response(),
+        Err(e) => {
+            warn!("Failed to list webhooks: {}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response()
+        }
+    }
+}
+
+/// 注册 Webhook：校验 URL 与事件名
+#[debug_handler]
+pub async fn create_webhook(
+    State(state): State<AppState>,
+    locale: Locale,
+    Json(request): Json<CreateWebhookRequest>,
+) -> impl IntoResponse {
+    let url = request.url.trim();
+    let valid_url = reqwest::Url::parse(url)
+        .is_ok_and(|parsed| matches!(parsed.scheme(), "http" | "https") && parsed.host().is_some());
+    if !valid_url {
+        return i18n::error_with(StatusCode::BAD_REQUEST, locale, Message::InvalidWebhookUrl, &request.url);
+    }
+    if let Some(unknown) = request.events.iter().find(|e| WebhookEvent::from_name(e).is_none()) {
+        let supported: Vec<&str> = WebhookEvent::SUBSCRIBABLE.iter().map(|e| e.as_str()).collect();
+        return i18n::error_with(
+            StatusCode::BAD_REQUEST,
+            locale,
+            Message::UnsupportedWebhookEvent,
+            &format!("{} (supported: {})", unknown, supported.join(", ")),
+        );
+    }
+
+    let webhook = Webhook::new(url.to_string(), &request.events, request.secret.filter(|s| !s.is_empty()));
+    let repo = WebhookRepository::new(&state.db);
+    if let Err(e) = repo.insert(&webhook).await {
+        warn!("Failed to create webhook: {}", e);
+        return (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response();
+    }
+
+    // 重新读取以带上数据库生成的 created_at
+    match repo.find_by_id(&webhook.id).await {
+        Ok(Some(stored)) => (StatusCode::CREATED, Json(WebhookResponse::from(&stored))).into_response(),
+        Ok(None) => (StatusCode::CREATED, Json(WebhookResponse::from(&webhook))).into_response(),
+        Err(e) => {
+            warn!("Failed to read created webhook {}: {}", webhook.id, e);
+            (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response()
+        }
+    }
+}
+
+/// 删除 Webhook
+#[debug_handler]
+pub async fn delete_webhook(
+    State(state): State<AppState>,
+    locale: Locale,
+    Path(id): Path<String>,
+) -> impl IntoResponse {
+    match WebhookRepository::new(&state.db).delete(&id).await {
+        Ok(true) => StatusCode::NO_CONTENT.into_response(),
+        Ok(false) => i18n::error(StatusCode::NOT_FOUND, locale, Message::WebhookNotFound),
+        Err(e) => {
+            warn!("Failed to delete webhook {}: {}", id, e);
+            (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response()
+        }
+    }
+}
+
+/// 立即向该 Webhook 发送一条 webhook.test 事件（不重试），返回投递结果
+#[debug_handler]
+pub async fn test_webhook(
+    State(state): State<AppState>,
+    locale: Locale,
+    Path(id): Path<String>,
+) -> impl IntoResponse {
+    let webhook = match WebhookRepository::new(&state.db).find_by_id(&id).await {
+        Ok(Some(webhook)) => webhook,
+        Ok(None) => return i18n::error(StatusCode::NOT_FOUND, locale, Message::WebhookNotFound),
+        Err(e) => {
+            warn!("Failed to load webhook {}: {}", id, e);
+            return (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response();
+        }
+    };
+
+    match state.webhooks.send_test(&webhook).await {
+        Ok(()) => Json(WebhookTestResponse {
+            success: true,
+            code: Message::WebhookTestDelivered.code(),
+            message: Message::WebhookTestDelivered.text(locale).to_string(),
+        })
+        .into_response(),
+        Err(e) => (
+            StatusCode::BAD_GATEWAY,
+            Json(WebhookTestResponse {
+                success: false,
+                code: Message::WebhookDeliveryFailed.code(),
+                message: Message::WebhookDeliveryFailed.text_with(locale, &e.to_string()),
+            }),
+        )
+            .into_response(),
+    }
+}
@@ -1,14 +1,18 @@
-use crate::api::{files, directories, system};
+use crate::api::{activity, admin, attributes, exports, files, directories, maintenance, notes, search, sync, system, thumbnails, webhooks};
 use crate::config::Config;
 use crate::db::{DatabasePool, MediaFileRepository};
 use crate::processors::{ProcessorRegistry, image_processor::StandardImageProcessor, heif_processor::HeifImageProcessor, video_processor::VideoProcessor};
-use crate::services::{FileService, ScanService, CacheService, Scheduler, TranscodingPool};
-use crate::websocket::{ScanProgressBroadcaster, ScanStateManager};
+use crate::services::{FileService, ScanService, CacheService, ConfigReloader, Scheduler, QuietHours, TaggingService, ThumbnailQueue, TombstoneChecker, TranscodingPool, DependencyStatus, LibraryCapabilities, WebhookNotifier, ScanSummaryNotifier, StaticExporter, ThumbnailMetrics};
+use crate::services::remote_library::RemoteLibrary;
+use crate::services::dependency_check::check_dependencies;
+use crate::services::disk_guard::DiskSpaceGuard;
+use crate::websocket::{ScanProgressBroadcaster, ScanStateManager, ThumbnailProgress, WarningBroadcaster};
 use axum::{
     body::Body,
     extract::Path,
+    http::HeaderMap,
     response::{Html, IntoResponse, Response},
-    routing::{get, post},
+    routing::{get, patch, post},
     Router,
 };
 use std::path::PathBuf;
@@ -28,12 +32,40 @@ pub struct AppState {
     pub broadcaster: Arc<ScanProgressBroadcaster>,
     pub scan_state: Arc<ScanStateManager>,
     pub processors: Arc<ProcessorRegistry>,
+    /// Native dependency probe results from startup (libheif, FFmpeg)
+    pub dependencies: Arc<Vec<DependencyStatus>>,
+    /// Whether base_path accepts writes, probed at startup
+    pub capabilities: Arc<LibraryCapabilities>,
+    /// When the application was created, for uptime reporting
+    pub started_at: std::time::Instant,
+    pub thumbnail_queue: Arc<ThumbnailQueue>,
+    /// Warnings pushed to WebSocket clients
+    pub warnings: Arc<WarningBroadcaster>,
+    /// Background removal of entries whose files were deleted
+    pub tombstones: Arc<TombstoneChecker>,
+    /// Secondary LatteAlbum instance merged read-only into list/timeline (LATTE_REMOTE_LIBRARY_URL)
+    pub remote_library: Option<Arc<RemoteLibrary>>,
+    /// External label/embedding service worker (LATTE_TAGGING_URL)
+    pub tagging: Option<Arc<TaggingService>>,
+    /// Outbound webhooks registered through /api/webhooks
+    pub webhooks: Arc<WebhookNotifier>,
+    /// Static gallery export job (/api/exports)
+    pub exporter: Arc<StaticExporter>,
+    /// Scheduled scans (LATTE_SCAN_CRON); started by `run`
+    pub scheduler: Arc<Scheduler>,
+    /// Applies configuration changes on SIGHUP and POST /api/admin/config/reload
+    pub config_reloader: Arc<ConfigReloader>,
+    /// Thumbnail decode/resize/encode timings per source and output format
+    pub thumbnail_metrics: Arc<ThumbnailMetrics>,
     /// Canonicalized absolute path to the assets directory.
     /// Pre-computed once at startup to avoid repeated canonicalization
     /// and used for path traversal prevention.
     /// `None` when the assets directory does not exist (e.g. tests,
     /// frontend not built yet). In that case all static requests get 404.
     pub assets_base_path: Option<PathBuf>,
+    /// Canonicalized static_dir, used the same way for root-level files
+    /// served by the SPA fallback (favicon, manifest, ...).
+    pub static_base_path: Option<PathBuf>,
 }
 
 /// Main application structure
@@ -48,14 +80,35 @@ impl App {
         self.router.clone()
     }
 
+    /// Shared services, for running jobs without the HTTP server (`latte-album scan`)
+    pub fn state(&self) -> &AppState {
+        &self.state
+    }
+
     /// Create a new application instance
     pub async fn new(config: Config) -> Result<Self, Box<dyn std::error::Error>> {
+        // Apply a restore staged via /api/maintenance/restore or `latte-album restore`
+        // before the pool opens the database file
+        let restored = crate::db::backup::apply_pending_restore(&config.db_path).await?;
+
         // Initialize database
         let db = DatabasePool::new(&config.db_path).await?;
 
         // Run migrations
         let migrations_path = std::path::PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("src/db/migrations");
         db.migrate(&migrations_path).await?;
+        // VACUUM INTO 可能重排 rowid，恢复后的全文索引需要重建
+        if restored {
+            MediaFileRepository::new(&db).rebuild_search_index().await?;
+        }
+        // 浏览类查询走只读连接池，避免与扫描写入争用连接
+        let db = match &config.db_read_replica {
+            Some(replica) => {
+                tracing::info!("Serving browsing reads from {}", replica.display());
+                db.with_read_replica(replica).await?
+            }
+            None => db,
+        };
         tracing::info!(
             "Database migrations applied. GPS columns (gps_latitude, gps_longitude) available. \
              Run a full rescan to populate GPS data for existing photos."
@@ -65,7 +118,7 @@ impl App {
         tokio::fs::create_dir_all(&config.cache_dir).await?;
 
         // Create shared state
-        let mut broadcaster = Arc::new(ScanProgressBroadcaster::new());
+        let mut broadcaster = Arc::new(ScanProgressBroadcaster::with_capacity(config.ws_channel_capacity));
         let scan_state = Arc::new(ScanStateManager::new_with_interval(
             broadcaster.sender(),
             config.ws_progress_broadcast_interval,
@@ -74,38 +127,175 @@ impl App {
         // Set scan_state reference in broadcaster (break circular dependency)
         Arc::make_mut(&mut broadcaster).set_scan_state(scan_state.clone());
 
-        // Create cache service with configurable parameters
+        // Create cache service with configurable parameters; entries are keyed by content hash and output format
         let cache_service = Arc::new(CacheService::new(
             &config.cache_dir,
             config.cache_max_capacity,
             config.cache_ttl_seconds,
-        ).await?);
+        ).await?
+            .with_content_hashes(db.clone())
+            .with_thumbnail_formats(config.thumbnail_formats.clone()));
+
+        // Move thumbnails of the old flat cache layout into shard directories
+        {
+            let cache_service = cache_service.clone();
+            tokio::spawn(async move {
+                if let Err(e) = cache_service.migrate_flat_layout().await {
+                    tracing::warn!("Thumbnail cache migration failed: {}", e);
+                }
+            });
+        }
+
+        // Probe libheif/FFmpeg once so missing native dependencies are reported up front
+        // instead of surfacing as per-file thumbnail failures
+        let dependencies = {
+            let config = config.clone();
+            Arc::new(tokio::task::spawn_blocking(move || check_dependencies(&config)).await?)
+        };
+
+        // 只读挂载时关闭在原始文件旁写入的功能
+        let capabilities = {
+            let base_path = config.base_path.clone();
+            let forced = config.read_only;
+            Arc::new(tokio::task::spawn_blocking(move || LibraryCapabilities::detect(&base_path, forced)).await?)
+        };
+        if let Some(reason) = &capabilities.read_only_reason {
+            tracing::warn!("Library {:?} is read-only ({}), features writing next to originals are disabled",
+                config.base_path, reason);
+        }
 
         // Create transcoding pool for CPU-intensive image processing (MUST be created before processors)
-        let transcoding_pool = Arc::new(TranscodingPool::new(config.transcoding_threads));
+        let mut transcoding_pool = TranscodingPool::new(config.transcoding_threads);
+        // 扫描与预生成在低优先级线程上运行，前台缩略图请求不被拖慢
+        if config.background_priority.is_enabled() {
+            transcoding_pool = transcoding_pool.with_background(config.transcoding_threads, config.background_priority);
+        }
+        let transcoding_pool = Arc::new(transcoding_pool);
 
-        // Initialize processor registry with transcoding pool
-        let mut processors = ProcessorRegistry::new(Some(transcoding_pool.clone()));
+        // Per-format stage timings of thumbnail generation (/metrics, /api/stats/thumbnails)
+        let thumbnail_metrics = Arc::new(ThumbnailMetrics::new());
 
-        processors.register(Arc::new(HeifImageProcessor::new(Some(transcoding_pool.clone()))));
-        processors.register(Arc::new(StandardImageProcessor::new()));
-        processors.register(Arc::new(VideoProcessor::new(Some(config.ffmpeg_path.to_string_lossy().to_string()))));
+        // Initialize processor registry with transcoding pool
+        let mut processors = ProcessorRegistry::new(Some(transcoding_pool.clone()))
+            .with_disabled(&config.disabled_processors);
+
+        processors.register(Arc::new(
+            HeifImageProcessor::new(Some(transcoding_pool.clone()))
+                .with_max_decode_pixels(config.max_decode_pixels)
+                .with_blur_detection(config.blur_detection)
+                .with_maker_notes(config.maker_notes)
+                .with_thumbnail_metrics(thumbnail_metrics.clone()),
+        ));
+        processors.register(Arc::new(
+            StandardImageProcessor::new()
+                .with_transcoding_pool(transcoding_pool.clone())
+                .with_decode_guard(config.max_decode_pixels, Some(config.ffmpeg_path.clone()))
+                .with_extra_extensions(&config.extra_image_extensions)
+                .with_blur_detection(config.blur_detection)
+                .with_maker_notes(config.maker_notes)
+                .with_thumbnail_metrics(thumbnail_metrics.clone()),
+        ));
+        processors.register(Arc::new(
+            VideoProcessor::new(Some(config.ffmpeg_path.to_string_lossy().to_string()))
+                .with_cli_fallback(config.ffprobe_path.clone(), config.video_thumbnail_offset)
+                .with_extra_extensions(&config.extra_video_extensions)
+                .with_thumbnail_metrics(thumbnail_metrics.clone()),
+        ));
+        for processor in processors.list().iter().filter(|p| !p.enabled) {
+            info!("Processor '{}' disabled by configuration", processor.name);
+        }
         let processors = Arc::new(processors);
 
-        let scan_service = Arc::new(ScanService::new(
-            config.clone(),
+        // Shared by scans and thumbnail pregeneration so both count against the same quiet-hours limit
+        let quiet_hours = config.quiet_hours.map(|(start, end)| {
+            let concurrency = if config.quiet_hours_pause { 0 } else { config.quiet_hours_concurrency };
+            Arc::new(QuietHours::new(start, end, concurrency))
+        });
+
+        let webhooks = Arc::new(
+            WebhookNotifier::new(db.clone()).with_disk_monitor(
+                vec![config.base_path.clone(), config.cache_dir.clone()],
+                config.webhook_low_disk_mb * 1024 * 1024,
+            ),
+        );
+
+        // ntfy/email summary after scheduled scans; a misconfigured SMTP URL or address fails startup
+        let scan_summary = ScanSummaryNotifier::from_config(&config)?.map(Arc::new);
+
+        let scan_service = Arc::new(
+            ScanService::new(
+                config.clone(),
+                db.clone(),
+                processors.clone(),
+                scan_state.clone(),
+            )
+            .with_quiet_hours(quiet_hours.clone())
+            .with_webhooks(webhooks.clone())
+            .with_scan_summary(scan_summary),
+        );
+
+        // Spot-checks DB entries for deleted files between full scans
+        let tombstones = Arc::new(TombstoneChecker::new(
             db.clone(),
-            processors.clone(),
-            scan_state.clone(),
+            config.base_path.clone(),
+            std::time::Duration::from_secs(config.tombstone_check_interval_seconds),
+            config.tombstone_check_sample_size,
         ));
 
-        let file_service = Arc::new(FileService::new(
-            db.clone(),
+        // Warnings pushed over the WebSocket (low disk space)
+        let warnings = Arc::new(WarningBroadcaster::new(config.ws_channel_capacity));
+        let disk_guard = Arc::new(
+            DiskSpaceGuard::new(config.cache_dir.clone(), config.cache_min_free_mb).with_warnings(warnings.clone()),
+        );
+
+        let file_service = Arc::new(
+            FileService::new(db.clone(), cache_service.clone(), processors.clone(), tombstones.clone(), &config)
+                .with_disk_guard(disk_guard),
+        );
+
+        // Background queue for thumbnail pre-warming (POST /api/thumbnails/warm)
+        let thumbnail_queue = Arc::new(ThumbnailQueue::new(
+            file_service.clone(),
+            cache_service.clone(),
+            config.thumbnail_warm_queue_size,
+            config.thumbnail_warm_workers,
+            quiet_hours,
+            Arc::new(ThumbnailProgress::with_capacity(config.ws_progress_broadcast_interval, config.ws_channel_capacity)),
+        ));
+
+        let scheduler = Arc::new(Scheduler::new(scan_service.clone(), &config.scan_cron));
+        let config_reloader = Arc::new(ConfigReloader::new(
+            config.clone(),
+            file_service.clone(),
             cache_service.clone(),
-            processors.clone(),
-            &config,
+            scan_state.clone(),
+            thumbnail_queue.progress().clone(),
+            scheduler.clone(),
+            warnings.clone(),
         ));
 
+        let exporter = Arc::new(StaticExporter::new(config.clone(), db.clone(), file_service.clone()));
+
+        let remote_library = config.remote_library_url.as_deref().map(|url| {
+            tracing::info!("Merging remote library {:?} from {}", config.remote_library_name, url);
+            Arc::new(RemoteLibrary::new(url, &config.remote_library_name))
+        });
+
+        let tagging = config.tagging_url.as_deref().map(|url| {
+            tracing::info!("Tagging new images via {}", url);
+            let service = TaggingService::new(
+                db.clone(),
+                url,
+                config.tagging_batch_size,
+                config.tagging_max_retries,
+                std::time::Duration::from_secs(config.tagging_poll_interval_seconds),
+            );
+            Arc::new(match config.tagging_text_url.as_deref() {
+                Some(text_url) => service.with_text_url(text_url),
+                None => service,
+            })
+        });
+
         // Compute the canonicalized assets base path once at startup.
         // This serves two purposes:
         // 1. Performance: avoids repeated canonicalization on every static file request.
@@ -117,6 +307,7 @@ impl App {
         // and all static-file requests will receive 404.
         let static_assets_path = config.static_dir.join("assets");
         let assets_base_path = std::fs::canonicalize(&static_assets_path).ok();
+        let static_base_path = std::fs::canonicalize(&config.static_dir).ok();
 
         let state = AppState {
             config,
@@ -127,7 +318,21 @@ impl App {
             broadcaster,
             scan_state,
             processors,
+            dependencies,
+            capabilities,
+            started_at: std::time::Instant::now(),
+            thumbnail_queue,
+            warnings,
+            tombstones,
+            remote_library,
+            tagging,
+            webhooks,
+            exporter,
+            scheduler,
+            config_reloader,
+            thumbnail_metrics,
             assets_base_path,
+            static_base_path,
         };
 
         // Build router
@@ -140,7 +345,7 @@ impl App {
     fn build_router(state: &AppState) -> Router {
         let cors = CorsLayer::new()
             .allow_origin(Any)
-            .allow_methods([axum::http::Method::GET, axum::http::Method::POST, axum::http::Method::PUT, axum::http::Method::DELETE])
+            .allow_methods([axum::http::Method::GET, axum::http::Method::POST, axum::http::Method::PUT, axum::http::Method::PATCH, axum::http::Method::DELETE])
             .allow_headers(Any);
 
         Router::new()
@@ -148,52 +353,117 @@ impl App {
             .route("/assets/{*path}", get(Self::serve_static))
             .route("/api/files", get(files::list_files))
             .route("/api/files/dates", get(files::list_dates))
-            .route("/api/files/{id}", get(files::get_file))
+            .route("/api/files/timeline", get(files::list_timeline))
+            .route("/api/files/{id}", get(files::get_file).patch(files::update_file))
             .route("/api/files/{id}/thumbnail", get(files::get_thumbnail))
             .route("/api/files/{id}/original", get(files::get_original))
             .route("/api/files/{id}/neighbors", get(files::get_neighbors))
             .route("/api/files/{id}/gps", get(files::get_file_gps))
+            .route("/api/files/{id}/labels", get(files::get_file_labels))
+            .route("/api/files/{id}/timeline", get(files::get_file_timeline))
+            .route("/api/files/{id}/frame", get(files::get_video_frame))
+            .route("/api/files/{id}/depth", get(files::get_depth_map))
+            .route("/api/files/{id}/items", get(files::get_file_items))
+            .route("/api/files/{id}/raw", get(files::get_raw))
+            .route("/api/files/{id}/rescan", post(files::rescan_file))
+            .route("/api/files/{id}/path", patch(files::move_file))
+            .route("/api/files/{id}/notes", get(notes::list_notes).post(notes::create_note))
+            .route("/api/notes/{id}", axum::routing::patch(notes::update_note).delete(notes::delete_note))
+            .route("/api/files/{id}/attributes", get(attributes::list_attributes))
+            .route(
+                "/api/files/{id}/attributes/{key}",
+                axum::routing::put(attributes::set_attribute).delete(attributes::delete_attribute),
+            )
+            .route("/api/activity", get(activity::list_activity))
+            .route("/api/sync/changes", get(sync::list_changes))
+            .route("/api/search", get(search::text_search))
+            .route("/api/search/semantic", get(search::semantic_search))
+            .route("/api/thumbnails/progress", get(thumbnails::get_thumbnail_progress))
+            .route("/api/thumbnails/warm", post(thumbnails::warm_thumbnails))
+            .route("/api/stats/thumbnails", get(thumbnails::get_thumbnail_stats))
             .route("/api/directories", get(directories::list_directories))
+            .route("/api/directories/move", post(directories::move_directory))
+            .route("/api/scan", post(system::trigger_rescan))
+            .route("/api/scan/report", get(system::get_scan_report))
+            .route("/api/scan/status", get(system::get_scan_status))
             .route("/api/system/rescan", post(system::trigger_rescan))
             .route("/api/system/scan/progress", get(system::get_scan_progress))
             .route("/api/system/scan/cancel", post(system::cancel_scan))
             .route("/api/system/status", get(system::get_status))
+            .route("/api/system/processors", get(system::get_processors))
+            .route("/api/system/info", get(system::get_info))
+            .route("/api/maintenance/backfill", post(maintenance::backfill))
+            .route("/api/maintenance/backup", post(maintenance::create_backup))
+            .route("/api/maintenance/backups", get(maintenance::list_backups))
+            .route("/api/maintenance/restore", post(maintenance::restore_backup))
+            .route("/api/maintenance/failed-files", get(maintenance::list_failed_files))
+            .route("/api/maintenance/failed-files/retry", post(maintenance::retry_failed_files))
+            .route("/api/maintenance/consistency", get(maintenance::consistency))
+            .route("/api/admin/config/validate", get(admin::validate_config))
+            .route("/api/admin/config/reload", post(admin::reload_config))
+            .route("/api/exports", post(exports::start_export))
+            .route("/api/exports/progress", get(exports::get_export_progress))
+            .route("/api/exports/cancel", post(exports::cancel_export))
+            .route("/api/webhooks", get(webhooks::list_webhooks).post(webhooks::create_webhook))
+            .route("/api/webhooks/{id}", axum::routing::delete(webhooks::delete_webhook))
+            .route("/api/webhooks/{id}/test", post(webhooks::test_webhook))
             .route("/ws/scan", get(Self::websocket_handler))
+            .route("/metrics", get(thumbnails::get_metrics))
+            .fallback(Self::serve_spa_fallback)
             .layer(cors)
             .with_state(state.clone())
     }
 
-    /// Serve index.html
-    async fn serve_index() -> impl IntoResponse {
-        let static_dir = std::env::var("LATTE_STATIC_DIR")
-            .unwrap_or_else(|_| "./static/dist".to_string());
-
-        let index_path = std::path::PathBuf::from(&static_dir).join("index.html");
+    /// Serve index.html. Sent with `no-cache` so a new deployment is picked up immediately.
+    async fn serve_index(State(state): State<AppState>) -> Response {
+        let index_path = state.config.static_dir.join("index.html");
 
         match tokio::fs::read_to_string(&index_path).await {
-            Ok(content) => Html(content),
-            Err(_) => Html("<html><body><h1>Latte Album</h1><p>Frontend not found. Please build the frontend first.</p></body></html>".to_string()),
+            Ok(content) => (
+                [(axum::http::header::CACHE_CONTROL, "no-cache")],
+                Html(content),
+            )
+                .into_response(),
+            Err(_) => Html("<html><body><h1>Latte Album</h1><p>Frontend not found. Please build the frontend first.</p></body></html>".to_string()).into_response(),
+        }
+    }
+
+    /// Fallback for unmatched routes: serve root-level static files (favicon, manifest, ...)
+    /// and fall back to index.html for client-side routes. Unknown API paths stay 404.
+    async fn serve_spa_fallback(
+        State(state): State<AppState>,
+        method: axum::http::Method,
+        uri: axum::http::Uri,
+        headers: HeaderMap,
+    ) -> Response {
+        let path = uri.path();
+        if (method != axum::http::Method::GET && method != axum::http::Method::HEAD)
+            || path == "/api"
+            || path.starts_with("/api/")
+            || path.starts_with("/ws/")
+        {
+            return (axum::http::StatusCode::NOT_FOUND, "Not found").into_response();
+        }
+
+        if let Some(static_base) = &state.static_base_path {
+            let relative = path.trim_start_matches('/');
+            if !relative.is_empty() {
+                if let Ok(resolved) = Self::resolve_static(static_base, relative).await {
+                    return Self::send_static_file(static_base, &resolved, &headers, "no-cache").await;
+                }
+            }
         }
+
+        Self::serve_index(State(state)).await
     }
 
     /// Serve static assets with path traversal protection.
-    ///
-    /// The handler does the following for every request:
-    /// 1. Validates the user-supplied path against common bypass techniques
-    ///    (empty, null bytes).
-    /// 2. Joins the path to the pre-canonicalized assets base path.
-    /// 3. Canonicalizes the resulting path (resolves `..`, symlinks, etc.).
-    /// 4. Verifies that the canonicalized result is still within the assets
-    ///    base path.
-    /// 5. Ensures only regular files are served (not directories or symlinks
-    ///    pointing outside the tree).
-    ///
-    /// If at any step the path escapes or the file type is wrong, the request
-    /// is rejected (403 Forbidden for traversal, 404 Not Found for missing
-    /// files).
+    /// Vite emits content-hashed file names under /assets, so they are cached for
+    /// `static_assets_max_age` seconds and marked immutable.
     async fn serve_static(
         State(state): State<AppState>,
         Path(path): Path<String>,
+        headers: HeaderMap,
     ) -> impl IntoResponse {
         // If the assets directory doesn't exist (not built yet, tests, ...),
         // every static-file request is a 404.
@@ -204,69 +474,120 @@ impl App {
             }
         };
 
+        let resolved = match Self::resolve_static(assets_base, &path).await {
+            Ok(resolved) => resolved,
+            Err((status, message)) => return (status, message).into_response(),
+        };
+
+        let cache_control = format!("public, max-age={}, immutable", state.config.static_assets_max_age);
+        Self::send_static_file(assets_base, &resolved, &headers, &cache_control).await
+    }
+
+    /// Resolve a user-supplied path inside a static base directory.
+    ///
+    /// The function does the following for every request:
+    /// 1. Validates the user-supplied path against common bypass techniques
+    ///    (empty, null bytes).
+    /// 2. Joins the path to the pre-canonicalized base path.
+    /// 3. Canonicalizes the resulting path (resolves `..`, symlinks, etc.).
+    /// 4. Verifies that the canonicalized result is still within the base path.
+    /// 5. Ensures only regular files are served (not directories or symlinks
+    ///    pointing outside the tree).
+    ///
+    /// If at any step the path escapes or the file type is wrong, the request
+    /// is rejected (403 Forbidden for traversal, 404 Not Found for missing
+    /// files).
+    async fn resolve_static(
+        base: &std::path::Path,
+        path: &str,
+    ) -> Result<PathBuf, (axum::http::StatusCode, &'static str)> {
         // 1. Reject empty paths
         if path.trim().is_empty() {
-            return (axum::http::StatusCode::BAD_REQUEST, "Empty path").into_response();
+            return Err((axum::http::StatusCode::BAD_REQUEST, "Empty path"));
         }
 
         // 2. Reject paths that contain a null byte — these would be truncated by
         //    the OS (e.g. "foo\0bar" → "foo"), allowing bypass of the prefix check.
         if path.contains('\0') {
-            return (axum::http::StatusCode::BAD_REQUEST, "Invalid path").into_response();
+            return Err((axum::http::StatusCode::BAD_REQUEST, "Invalid path"));
         }
 
         // 3. Join the user path onto the trusted base and canonicalize.
         //    Path::join handles `.` and `..` components naively — canonicalize
         //    resolves these as well as symlinks into an absolute, normalized path.
-        let file_path = assets_base.join(&path);
-
-        let resolved = match std::fs::canonicalize(&file_path) {
+        let resolved = match std::fs::canonicalize(base.join(path)) {
             Ok(p) => p,
-            Err(_) => {
-                return (axum::http::StatusCode::NOT_FOUND, "Not found").into_response();
-            }
+            Err(_) => return Err((axum::http::StatusCode::NOT_FOUND, "Not found")),
         };
 
         // 4. Path traversal check: the resolved path MUST start with the
-        //    pre-canonicalized assets base path. This is the core security check.
-        if !resolved.starts_with(assets_base) {
+        //    pre-canonicalized base path. This is the core security check.
+        if !resolved.starts_with(base) {
             tracing::warn!(
                 "Path traversal attempt blocked: requested={} resolved={}",
                 path,
                 resolved.display()
             );
-            return (
-                axum::http::StatusCode::FORBIDDEN,
-                "Access denied",
-            )
-                .into_response();
+            return Err((axum::http::StatusCode::FORBIDDEN, "Access denied"));
         }
 
         // 5. Only serve regular files (not directories, not symlinks to outside).
         //    canonicalize already resolved symlinks; an explicit is_file check is
         //    defense-in-depth.
         match tokio::fs::metadata(&resolved).await {
-            Ok(meta) if meta.is_file() => {}
-            Ok(_) => {
-                return (axum::http::StatusCode::NOT_FOUND, "Not found").into_response();
+            Ok(meta) if meta.is_file() => Ok(resolved),
+            _ => Err((axum::http::StatusCode::NOT_FOUND, "Not found")),
+        }
+    }
+
+    /// Read and send a resolved static file.
+    /// If the client accepts brotli/gzip and a precompressed `<file>.br` / `<file>.gz`
+    /// sibling exists (inside `base`), that is sent instead with Content-Encoding set.
+    async fn send_static_file(
+        base: &std::path::Path,
+        resolved: &std::path::Path,
+        request_headers: &HeaderMap,
+        cache_control: &str,
+    ) -> Response {
+        let accept_encoding = request_headers
+            .get(axum::http::header::ACCEPT_ENCODING)
+            .and_then(|v| v.to_str().ok())
+            .unwrap_or("");
+
+        let mut body_path = resolved.to_path_buf();
+        let mut content_encoding = None;
+        for (encoding, extension) in [("br", "br"), ("gzip", "gz")] {
+            if !accepts_encoding(accept_encoding, encoding) {
+                continue;
             }
-            Err(_) => {
-                return (axum::http::StatusCode::NOT_FOUND, "Not found").into_response();
+            let mut candidate = resolved.as_os_str().to_owned();
+            candidate.push(".");
+            candidate.push(extension);
+            if let Ok(candidate) = std::fs::canonicalize(PathBuf::from(candidate)) {
+                if candidate.starts_with(base) && candidate.is_file() {
+                    body_path = candidate;
+                    content_encoding = Some(encoding);
+                    break;
+                }
             }
         }
 
-        // 6. Read and serve the file
-        match tokio::fs::read(&resolved).await {
+        match tokio::fs::read(&body_path).await {
             Ok(content) => {
-                let mime_type = mime_guess::from_path(&resolved)
+                // MIME type comes from the original name, not the .br/.gz sibling
+                let mime_type = mime_guess::from_path(resolved)
                     .first()
                     .map(|m| m.to_string())
                     .unwrap_or_else(|| "application/octet-stream".to_string());
 
-                Response::builder()
+                let mut builder = Response::builder()
                     .header("Content-Type", mime_type)
-                    .body(Body::from(content))
-                    .unwrap()
+                    .header("Cache-Control", cache_control)
+                    .header("Vary", "Accept-Encoding");
+                if let Some(encoding) = content_encoding {
+                    builder = builder.header("Content-Encoding", encoding);
+                }
+                builder.body(Body::from(content)).unwrap()
             }
             Err(_) => (axum::http::StatusCode::NOT_FOUND, "Not found").into_response(),
         }
@@ -278,7 +599,13 @@ impl App {
         ws: axum::extract::ws::WebSocketUpgrade,
     ) -> impl IntoResponse {
         ws.on_upgrade(move |socket| {
-            crate::websocket::handle_websocket(socket, state.broadcaster.clone())
+            crate::websocket::handle_websocket(
+                socket,
+                state.broadcaster.clone(),
+                state.thumbnail_queue.progress().clone(),
+                state.warnings.clone(),
+                state.config.ws_send_buffer,
+            )
         })
     }
 
@@ -288,23 +615,37 @@ impl App {
         let listener = TcpListener::bind(&addr).await?;
         info!("Server listening on {}", addr);
 
-        // Check if first run (database empty) and trigger initial scan
-        let repo = MediaFileRepository::new(&self.state.db);
-        if repo.is_empty().await? {
-            info!("First run detected - starting initial scan...");
-            // Spawn initial scan in background
-            let scan_service = self.state.scan_service.clone();
+        // 首次运行（数据库为空且照片目录非空）时自动扫描，进度照常通过 WebSocket 推送
+        if self.state.config.scan_on_first_run {
+            let repo = MediaFileRepository::new(&self.state.db);
+            if repo.is_empty().await? && has_entries(&self.state.config.base_path) {
+                info!("First run detected - starting initial scan...");
+                // Spawn initial scan in background
+                let scan_service = self.state.scan_service.clone();
+                tokio::spawn(async move {
+                    scan_service.scan().await;
+                });
+            }
+        }
+
+        // 补齐升级前已缓存缩略图的图片的曝光统计
+        {
+            let file_service = self.state.file_service.clone();
             tokio::spawn(async move {
-                scan_service.scan().await;
+                match file_service.backfill_exposure().await {
+                    Ok(0) => {}
+                    Ok(count) => info!("Recorded exposure statistics of {} images from cached thumbnails", count),
+                    Err(e) => tracing::warn!("Exposure backfill failed: {}", e),
+                }
             });
         }
 
         // Start scheduler
-        let scheduler = Scheduler::new(
-            self.state.scan_service.clone(),
-            &self.state.config.scan_cron,
-        );
-        scheduler.start().await;
+        self.state.scheduler.start().await;
+
+        // kill -HUP 重新加载可在运行中修改的配置
+        #[cfg(unix)]
+        tokio::spawn(self.state.config_reloader.clone().reload_on_sighup());
 
         axum::serve(listener, self.router).await?;
         Ok(())
@@ -313,3 +654,23 @@ impl App {
 
 // Re-export State extractor for use in handlers
 pub use axum::extract::State;
+
+/// Whether `dir` exists and contains at least one entry
+fn has_entries(dir: &std::path::Path) -> bool {
+    std::fs::read_dir(dir).map(|mut entries| entries.next().is_some()).unwrap_or(false)
+}
+
+/// Whether an Accept-Encoding header value allows `encoding` (entries with q=0 are refused)
+fn accepts_encoding(accept_encoding: &str, encoding: &str) -> bool {
+    accept_encoding.split(',').any(|entry| {
+        let mut parts = entry.split(';');
+        let name = parts.next().unwrap_or("").trim();
+        let refused = parts.any(|p| {
+            p.trim()
+                .strip_prefix("q=")
+                .and_then(|q| q.trim().parse::<f32>().ok())
+                .is_some_and(|q| q == 0.0)
+        });
+        name.eq_ignore_ascii_case(encoding) && !refused
+    })
+}
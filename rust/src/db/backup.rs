@@ -0,0 +1,198 @@
+//! 数据库备份与恢复
+//! 备份使用 `VACUUM INTO` 在线生成一致性快照（不阻塞读写），按数量轮转；
+//! 恢复先校验备份并暂存为 `<db_path>.restore`，在下次启动、连接池打开之前替换数据库文件。
+
+use crate::db::pool::{DatabaseError, DatabasePool};
+use chrono::{DateTime, Local, Utc};
+use serde::Serialize;
+use sqlx::sqlite::SqliteConnectOptions;
+use sqlx::{ConnectOptions, Connection};
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+
+const BACKUP_PREFIX: &str = "album-";
+const BACKUP_EXTENSION: &str = "db";
+
+/// A backup file in the backup directory
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BackupInfo {
+    pub name: String,
+    pub size: u64,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Create a consistent online backup of the database and rotate old backups.
+/// Returns the path of the new backup file.
+pub async fn create_backup(
+    db: &DatabasePool,
+    backup_dir: &Path,
+    keep: usize,
+) -> Result<PathBuf, DatabaseError> {
+    tokio::fs::create_dir_all(backup_dir).await?;
+
+    let name = format!(
+        "{}{}.{}",
+        BACKUP_PREFIX,
+        Local::now().format("%Y%m%d-%H%M%S%.3f"),
+        BACKUP_EXTENSION
+    );
+    let path = backup_dir.join(name);
+
+    sqlx::query("VACUUM INTO ?")
+        .bind(path.to_string_lossy().to_string())
+        .execute(db.get_pool())
+        .await?;
+
+    tracing::info!("Database backup created: {:?}", path);
+
+    rotate_backups(backup_dir, keep)?;
+    Ok(path)
+}
+
+/// List backups in the directory, newest first
+pub fn list_backups(backup_dir: &Path) -> Result<Vec<BackupInfo>, DatabaseError> {
+    if !backup_dir.exists() {
+        return Ok(Vec::new());
+    }
+
+    let mut backups = Vec::new();
+    for entry in std::fs::read_dir(backup_dir)? {
+        let entry = entry?;
+        let name = entry.file_name().to_string_lossy().to_string();
+        if !is_backup_name(&name) {
+            continue;
+        }
+        let metadata = entry.metadata()?;
+        if !metadata.is_file() {
+            continue;
+        }
+        backups.push(BackupInfo {
+            name,
+            size: metadata.len(),
+            created_at: metadata.modified().map(DateTime::<Utc>::from).unwrap_or_else(|_| Utc::now()),
+        });
+    }
+
+    // 文件名中包含时间戳，按名称倒序即为最新在前
+    backups.sort_by(|a, b| b.name.cmp(&a.name));
+    Ok(backups)
+}
+
+/// Delete all but the newest `keep` backups (keep = 0 disables rotation)
+fn rotate_backups(backup_dir: &Path, keep: usize) -> Result<(), DatabaseError> {
+    if keep == 0 {
+        return Ok(());
+    }
+
+    for old in list_backups(backup_dir)?.into_iter().skip(keep) {
+        let path = backup_dir.join(&old.name);
+        match std::fs::remove_file(&path) {
+            Ok(()) => tracing::debug!("Rotated out old backup {:?}", path),
+            Err(e) => tracing::warn!("Failed to remove old backup {:?}: {}", path, e),
+        }
+    }
+    Ok(())
+}
+
+/// Resolve a backup name inside the backup directory, rejecting anything that isn't a plain backup file name
+pub fn resolve_backup(backup_dir: &Path, name: &str) -> Option<PathBuf> {
+    if !is_backup_name(name) || name.contains(['/', '\\']) || name.contains("..") {
+        return None;
+    }
+    let path = backup_dir.join(name);
+    path.is_file().then_some(path)
+}
+
+fn is_backup_name(name: &str) -> bool {
+    name.starts_with(BACKUP_PREFIX) && name.ends_with(&format!(".{}", BACKUP_EXTENSION))
+}
+
+/// Check that a file is an intact Latte Album database
+pub async fn validate_backup(path: &Path) -> Result<(), DatabaseError> {
+    let invalid = |msg: String| DatabaseError::InvalidBackup(format!("{:?}: {}", path, msg));
+
+    if !path.is_file() {
+        return Err(invalid("file not found".to_string()));
+    }
+
+    let mut conn = SqliteConnectOptions::new()
+        .filename(path)
+        .read_only(true)
+        .connect()
+        .await?;
+
+    let integrity: String = sqlx::query_scalar("PRAGMA integrity_check")
+        .fetch_one(&mut conn)
+        .await?;
+    if integrity != "ok" {
+        conn.close().await.ok();
+        return Err(invalid(format!("integrity check failed: {}", integrity)));
+    }
+
+    let has_media_files: i64 = sqlx::query_scalar(
+        "SELECT COUNT(*) FROM sqlite_master WHERE type = 'table' AND name = 'media_files'",
+    )
+    .fetch_one(&mut conn)
+    .await?;
+    conn.close().await.ok();
+
+    if has_media_files == 0 {
+        return Err(invalid("not a Latte Album database".to_string()));
+    }
+
+    Ok(())
+}
+
+/// Path a validated backup is staged at until the next startup
+pub fn pending_restore_path(db_path: &Path) -> PathBuf {
+    with_suffix(db_path, ".restore")
+}
+
+/// Validate a backup and stage it to replace the database on next startup
+pub async fn stage_restore(backup: &Path, db_path: &Path) -> Result<PathBuf, DatabaseError> {
+    validate_backup(backup).await?;
+
+    let staged = pending_restore_path(db_path);
+    tokio::fs::copy(backup, &staged).await?;
+    tracing::info!("Restore from {:?} staged, will be applied on next startup", backup);
+    Ok(staged)
+}
+
+/// Apply a staged restore before the database is opened.
+/// The current database is kept as `<db_path>.pre-restore`. An invalid staged file is
+/// renamed to `<db_path>.restore.invalid` and startup continues with the current database.
+/// Returns true if a restore was applied.
+pub async fn apply_pending_restore(db_path: &Path) -> Result<bool, DatabaseError> {
+    let staged = pending_restore_path(db_path);
+    if !staged.exists() {
+        return Ok(false);
+    }
+
+    if let Err(e) = validate_backup(&staged).await {
+        tracing::error!("Staged restore is invalid, keeping current database: {}", e);
+        tokio::fs::rename(&staged, with_suffix(&staged, ".invalid")).await?;
+        return Ok(false);
+    }
+
+    if db_path.exists() {
+        tokio::fs::rename(db_path, with_suffix(db_path, ".pre-restore")).await?;
+    }
+    // WAL/SHM 属于旧数据库，必须一并移除
+    for suffix in ["-wal", "-shm"] {
+        let sidecar = with_suffix(db_path, suffix);
+        if sidecar.exists() {
+            tokio::fs::remove_file(&sidecar).await?;
+        }
+    }
+    tokio::fs::rename(&staged, db_path).await?;
+
+    tracing::info!("Database restored from staged backup");
+    Ok(true)
+}
+
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut name: OsString = path.as_os_str().to_owned();
+    name.push(suffix);
+    PathBuf::from(name)
+}
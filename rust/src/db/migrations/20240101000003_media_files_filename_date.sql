@@ -0,0 +1,5 @@
+-- Capture date inferred from the file name (fallback for files without EXIF,
+-- e.g. WhatsApp images and screenshots), and the source of the effective sort time.
+-- Existing rows keep NULL. Populated on next rescan.
+ALTER TABLE media_files ADD COLUMN filename_timestamp DATETIME;
+ALTER TABLE media_files ADD COLUMN date_source TEXT;
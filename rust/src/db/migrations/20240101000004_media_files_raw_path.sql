@@ -0,0 +1,3 @@
+-- RAW sidecar linked to a JPEG/HEIC display file (RAW+JPEG pairing).
+-- Maintained by the scanner when LATTE_RAW_JPEG_PAIRING is enabled.
+ALTER TABLE media_files ADD COLUMN raw_path TEXT;
@@ -0,0 +1,33 @@
+-- Labels and embeddings returned by the external tagging service (LATTE_TAGGING_URL).
+-- media_tagging marks files already sent to the service, so each file is tagged once.
+CREATE TABLE IF NOT EXISTS media_tagging (
+    file_id TEXT PRIMARY KEY,
+    model TEXT,
+    tagged_at DATETIME DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE TABLE IF NOT EXISTS media_labels (
+    file_id TEXT NOT NULL,
+    label TEXT NOT NULL,
+    score REAL,
+    PRIMARY KEY (file_id, label)
+);
+
+CREATE INDEX IF NOT EXISTS idx_media_labels_label ON media_labels(label);
+
+-- vector: little-endian f32 array
+CREATE TABLE IF NOT EXISTS media_embeddings (
+    file_id TEXT PRIMARY KEY,
+    model TEXT,
+    dimensions INTEGER NOT NULL,
+    vector BLOB NOT NULL
+);
+
+-- Tagging data follows the media file lifecycle (deleted with the file entry)
+CREATE TRIGGER IF NOT EXISTS trg_media_files_delete_tagging
+AFTER DELETE ON media_files
+BEGIN
+    DELETE FROM media_tagging WHERE file_id = OLD.id;
+    DELETE FROM media_labels WHERE file_id = OLD.id;
+    DELETE FROM media_embeddings WHERE file_id = OLD.id;
+END;
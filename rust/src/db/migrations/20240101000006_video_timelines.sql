@@ -0,0 +1,13 @@
+-- Chapters/keyframes of videos (JSON), extracted on first request to /api/files/{id}/timeline.
+-- modify_time records the file version the timeline was extracted from; a changed file is re-extracted.
+CREATE TABLE IF NOT EXISTS video_timelines (
+    file_id TEXT PRIMARY KEY,
+    modify_time DATETIME,
+    timeline TEXT NOT NULL
+);
+
+CREATE TRIGGER IF NOT EXISTS trg_media_files_delete_timeline
+AFTER DELETE ON media_files
+BEGIN
+    DELETE FROM video_timelines WHERE file_id = OLD.id;
+END;
@@ -0,0 +1,3 @@
+-- Per-folder overrides read from .latte.json during scan
+ALTER TABLE directories ADD COLUMN display_name TEXT;
+ALTER TABLE directories ADD COLUMN cover_path TEXT;
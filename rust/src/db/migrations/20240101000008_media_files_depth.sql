@@ -0,0 +1,3 @@
+-- HEIC auxiliary images: depth map and portrait effects matte (iPhone portrait mode)
+ALTER TABLE media_files ADD COLUMN has_depth_map BOOLEAN DEFAULT 0;
+ALTER TABLE media_files ADD COLUMN has_portrait_matte BOOLEAN DEFAULT 0;
@@ -0,0 +1,16 @@
+-- Top-level images of multi-image containers (HEIC bursts), indexed by their order in the file.
+-- Only files with more than one top-level image have rows here.
+CREATE TABLE IF NOT EXISTS media_sub_images (
+    file_id TEXT NOT NULL,
+    item_index INTEGER NOT NULL,
+    width INTEGER,
+    height INTEGER,
+    is_primary BOOLEAN NOT NULL DEFAULT 0,
+    PRIMARY KEY (file_id, item_index)
+);
+
+CREATE TRIGGER IF NOT EXISTS trg_media_files_delete_sub_images
+AFTER DELETE ON media_files
+BEGIN
+    DELETE FROM media_sub_images WHERE file_id = OLD.id;
+END;
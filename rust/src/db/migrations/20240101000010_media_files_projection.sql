@@ -0,0 +1,2 @@
+-- Panorama / 360° projection ("equirectangular", "cylindrical", "wide"); NULL for regular media
+ALTER TABLE media_files ADD COLUMN projection TEXT;
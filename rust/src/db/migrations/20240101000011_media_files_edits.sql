@@ -0,0 +1,6 @@
+-- User-edited metadata. Not written by scans, so edits survive rescans.
+-- version is incremented on every edit for optimistic concurrency (PATCH /api/files/{id}).
+-- updated_at already exists (initial media_files schema) and is set by every edit.
+ALTER TABLE media_files ADD COLUMN title TEXT;
+ALTER TABLE media_files ADD COLUMN description TEXT;
+ALTER TABLE media_files ADD COLUMN version INTEGER NOT NULL DEFAULT 1;
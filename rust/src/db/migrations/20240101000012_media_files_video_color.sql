@@ -0,0 +1,4 @@
+-- Video HDR / color space: hdr_format is "hlg", "pq" or "dolby_vision" (NULL for SDR)
+ALTER TABLE media_files ADD COLUMN hdr_format TEXT;
+ALTER TABLE media_files ADD COLUMN bit_depth INTEGER;
+ALTER TABLE media_files ADD COLUMN color_primaries TEXT;
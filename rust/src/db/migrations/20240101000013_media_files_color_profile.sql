@@ -0,0 +1,2 @@
+-- Description of the embedded ICC profile ("Display P3", "Adobe RGB (1998)"); NULL without a profile
+ALTER TABLE media_files ADD COLUMN color_profile TEXT;
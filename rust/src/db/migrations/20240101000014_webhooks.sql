@@ -0,0 +1,10 @@
+-- Outbound webhooks managed through /api/webhooks.
+-- events: comma-separated event names ("scan.completed,disk.low"); empty subscribes to every event
+CREATE TABLE IF NOT EXISTS webhooks (
+    id TEXT PRIMARY KEY,
+    url TEXT NOT NULL,
+    events TEXT NOT NULL DEFAULT '',
+    secret TEXT,
+    enabled BOOLEAN NOT NULL DEFAULT 1,
+    created_at DATETIME DEFAULT CURRENT_TIMESTAMP
+);
@@ -0,0 +1,3 @@
+-- Content fingerprint (SHA-256 of size + first/last 64 KiB), keys the thumbnail disk cache
+ALTER TABLE media_files ADD COLUMN content_hash TEXT;
+CREATE INDEX IF NOT EXISTS idx_media_files_content_hash ON media_files(content_hash);
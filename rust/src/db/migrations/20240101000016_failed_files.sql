@@ -0,0 +1,10 @@
+-- Files whose processing failed (e.g. thumbnail decode errors), kept for later retry
+-- through /api/maintenance/failed-files. Cleared once the file is processed successfully.
+CREATE TABLE IF NOT EXISTS failed_files (
+    file_id TEXT NOT NULL,
+    stage TEXT NOT NULL,
+    error TEXT NOT NULL,
+    attempts INTEGER NOT NULL DEFAULT 1,
+    last_failed_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (file_id, stage)
+);
@@ -0,0 +1,6 @@
+-- Unicode-normalized (NFC) file_path, used by scans to look up existing rows so that
+-- NFD paths reported by macOS/SMB mounts match. Existing rows start with their file_path;
+-- a row whose path is not NFC is re-keyed by the next scan's upsert.
+ALTER TABLE media_files ADD COLUMN path_key TEXT;
+UPDATE media_files SET path_key = file_path WHERE path_key IS NULL;
+CREATE INDEX IF NOT EXISTS idx_media_files_path_key ON media_files(path_key);
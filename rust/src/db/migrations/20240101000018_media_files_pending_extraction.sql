@@ -0,0 +1,4 @@
+-- Rows added by a quick scan (ScanMode::Quick) before their metadata has been extracted;
+-- the next incremental scan processes them regardless of modify_time
+ALTER TABLE media_files ADD COLUMN pending_extraction BOOLEAN NOT NULL DEFAULT 0;
+CREATE INDEX IF NOT EXISTS idx_media_files_pending_extraction ON media_files(pending_extraction) WHERE pending_extraction = 1;
@@ -0,0 +1,4 @@
+-- Sharpness (variance of the Laplacian) computed during processing when blur detection is enabled;
+-- NULL for videos and files not yet analysed
+ALTER TABLE media_files ADD COLUMN blur_score REAL;
+CREATE INDEX IF NOT EXISTS idx_media_files_blur_score ON media_files(blur_score) WHERE blur_score IS NOT NULL;
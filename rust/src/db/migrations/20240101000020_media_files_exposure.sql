@@ -0,0 +1,5 @@
+-- Exposure statistics computed from the generated thumbnail (see processors::exposure);
+-- NULL until the first thumbnail of an image has been generated
+ALTER TABLE media_files ADD COLUMN mean_luminance REAL;
+ALTER TABLE media_files ADD COLUMN clipped_highlights REAL;
+ALTER TABLE media_files ADD COLUMN clipped_shadows REAL;
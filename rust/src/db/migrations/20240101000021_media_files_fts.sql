@@ -0,0 +1,31 @@
+-- Full-text index over file names and user captions (GET /api/search).
+-- External content table keyed by media_files.rowid; the trigram tokenizer matches substrings,
+-- so Chinese captions without word separators are searchable too.
+-- VACUUM INTO may renumber rowids: restored backups rebuild the index on startup.
+CREATE VIRTUAL TABLE IF NOT EXISTS media_files_fts USING fts5(
+    file_name, title, description,
+    content = 'media_files', content_rowid = 'rowid',
+    tokenize = 'trigram'
+);
+
+CREATE TRIGGER IF NOT EXISTS media_files_fts_insert AFTER INSERT ON media_files BEGIN
+    INSERT INTO media_files_fts (rowid, file_name, title, description)
+    VALUES (new.rowid, new.file_name, new.title, new.description);
+END;
+
+CREATE TRIGGER IF NOT EXISTS media_files_fts_delete AFTER DELETE ON media_files BEGIN
+    INSERT INTO media_files_fts (media_files_fts, rowid, file_name, title, description)
+    VALUES ('delete', old.rowid, old.file_name, old.title, old.description);
+END;
+
+-- 扫描的 upsert 每次都会写 file_name，只在内容真正变化时更新索引
+CREATE TRIGGER IF NOT EXISTS media_files_fts_update AFTER UPDATE OF file_name, title, description ON media_files
+WHEN old.file_name IS NOT new.file_name OR old.title IS NOT new.title OR old.description IS NOT new.description
+BEGIN
+    INSERT INTO media_files_fts (media_files_fts, rowid, file_name, title, description)
+    VALUES ('delete', old.rowid, old.file_name, old.title, old.description);
+    INSERT INTO media_files_fts (rowid, file_name, title, description)
+    VALUES (new.rowid, new.file_name, new.title, new.description);
+END;
+
+INSERT INTO media_files_fts (media_files_fts) VALUES ('rebuild');
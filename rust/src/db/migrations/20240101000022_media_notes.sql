@@ -0,0 +1,20 @@
+-- Notes left on a media file (/api/files/{id}/notes). parent_id makes a note a reply to
+-- another note of the same file; author is free text for multi-user setups.
+CREATE TABLE IF NOT EXISTS media_notes (
+    id TEXT PRIMARY KEY,
+    file_id TEXT NOT NULL,
+    parent_id TEXT,
+    author TEXT,
+    body TEXT NOT NULL,
+    created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    updated_at DATETIME
+);
+
+CREATE INDEX IF NOT EXISTS idx_media_notes_file_id ON media_notes(file_id, created_at);
+
+-- Notes follow the media file lifecycle (deleted with the file entry)
+CREATE TRIGGER IF NOT EXISTS trg_media_files_delete_notes
+AFTER DELETE ON media_files
+BEGIN
+    DELETE FROM media_notes WHERE file_id = OLD.id;
+END;
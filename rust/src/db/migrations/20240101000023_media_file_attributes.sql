@@ -0,0 +1,14 @@
+-- Extended key/value metadata of media files (maker notes, later XMP or classifiers).
+-- New extracted fields become rows instead of new columns; a rescan replaces the rows of the file.
+CREATE TABLE IF NOT EXISTS media_file_attributes (
+    file_id TEXT NOT NULL,
+    key TEXT NOT NULL,
+    value TEXT NOT NULL,
+    PRIMARY KEY (file_id, key)
+);
+
+CREATE TRIGGER IF NOT EXISTS trg_media_files_delete_attributes
+AFTER DELETE ON media_files
+BEGIN
+    DELETE FROM media_file_attributes WHERE file_id = OLD.id;
+END;
@@ -0,0 +1,24 @@
+-- Attributes from several producers (maker notes, XMP, external classifiers, the API).
+-- source names the producer: each producer replaces only its own rows, and the same key may
+-- be stored by more than one of them. Existing rows were written by EXIF extraction.
+DROP TRIGGER IF EXISTS trg_media_files_delete_attributes;
+
+CREATE TABLE media_file_attributes_new (
+    file_id TEXT NOT NULL,
+    key TEXT NOT NULL,
+    value TEXT NOT NULL,
+    source TEXT NOT NULL,
+    PRIMARY KEY (file_id, source, key)
+);
+
+INSERT INTO media_file_attributes_new (file_id, key, value, source)
+SELECT file_id, key, value, 'exif' FROM media_file_attributes;
+
+DROP TABLE media_file_attributes;
+ALTER TABLE media_file_attributes_new RENAME TO media_file_attributes;
+
+CREATE TRIGGER IF NOT EXISTS trg_media_files_delete_attributes
+AFTER DELETE ON media_files
+BEGIN
+    DELETE FROM media_file_attributes WHERE file_id = OLD.id;
+END;
@@ -0,0 +1,12 @@
+-- Change log of media files for incremental client sync (GET /api/sync/changes).
+-- Written by the repository layer next to every create, update and delete of a media file;
+-- seq is the sync cursor. AUTOINCREMENT keeps seq increasing after old entries are pruned,
+-- so sqlite_sequence still holds the latest cursor when the log is empty.
+CREATE TABLE IF NOT EXISTS media_changes (
+    seq INTEGER PRIMARY KEY AUTOINCREMENT,
+    file_id TEXT NOT NULL,
+    change TEXT NOT NULL,             -- 'created', 'updated' or 'deleted'
+    changed_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS idx_media_changes_changed_at ON media_changes(changed_at);
@@ -0,0 +1,8 @@
+-- path_key identifies a stored file: upserts conflict on it instead of file_path, so a path
+-- reported in another Unicode form (NFD on macOS/SMB mounts) updates the existing row.
+-- Rows that already collide on the key (the same file added under both forms) keep the oldest.
+DELETE FROM media_files
+WHERE path_key IS NOT NULL
+  AND rowid NOT IN (SELECT MIN(rowid) FROM media_files WHERE path_key IS NOT NULL GROUP BY path_key);
+DROP INDEX IF EXISTS idx_media_files_path_key;
+CREATE UNIQUE INDEX IF NOT EXISTS idx_media_files_path_key ON media_files(path_key);
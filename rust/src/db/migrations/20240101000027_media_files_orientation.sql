@@ -0,0 +1,3 @@
+-- EXIF Orientation (1-8) of images; width / height stay as stored in the file, so 5-8
+-- (rotated by 90 or 270 degrees) display with the dimensions swapped. NULL when absent
+ALTER TABLE media_files ADD COLUMN orientation INTEGER;
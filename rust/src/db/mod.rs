@@ -1,7 +1,10 @@
+pub mod backup;
 pub mod models;
 pub mod pool;
+pub mod query;
 pub mod repository;
 
-pub use models::{DateInfo, Directory, MediaFile};
+pub use models::{path_key, ActivityEvent, ChangeKind, DateInfo, DateSource, Directory, DirectoryEntry, EditOutcome, ExistingFile, ExposureFilter, FailedFile, MediaAttribute, MediaChange, MediaFile, MediaFileEdit, MediaLabel, MediaNote, MediaSubImage, MetadataField, TimelineBucket, Webhook};
 pub use pool::{DatabasePool, DatabaseError};
-pub use repository::{MediaFileRepository, DirectoryRepository};
+pub use query::{FileQuery, SortField, SortOrder};
+pub use repository::{ActivityRepository, AttributeRepository, ChangeLogRepository, MediaFileRepository, MediaFileTxRepository, DirectoryRepository, FailedFileRepository, NoteRepository, RepositoryTx, TaggingRepository, WebhookRepository};
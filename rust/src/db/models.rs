@@ -4,7 +4,7 @@ use sqlx::FromRow;
 use uuid::Uuid;
 
 /// Custom serialization for NaiveDateTime to ISO string format
-mod date_serialization {
+pub(crate) mod date_serialization {
     use chrono::NaiveDateTime;
     use serde::{Deserialize, Deserializer};
 
@@ -33,7 +33,7 @@ mod date_serialization {
 /// Custom serialization for UTC NaiveDateTime to ISO string format with "Z" suffix.
 /// Used for fields stored as UTC wall clock (create_time, modify_time, last_scanned),
 /// so that clients (e.g. JavaScript `new Date()`) parse them as UTC instead of local time.
-mod utc_date_serialization {
+pub(crate) mod utc_date_serialization {
     use chrono::NaiveDateTime;
     use serde::{Deserialize, Deserializer};
 
@@ -122,6 +122,20 @@ pub struct MediaFile {
     #[serde(skip_serializing_if = "Option::is_none", rename = "exifTimezoneOffset")]
     pub exif_timezone_offset: Option<String>,
 
+    /// Capture time inferred from the file name (local wall clock, like EXIF)
+    #[serde(
+        skip_serializing_if = "Option::is_none",
+        rename = "filenameTimestamp",
+        serialize_with = "date_serialization::serialize",
+        deserialize_with = "date_serialization::deserialize",
+        default
+    )]
+    pub filename_timestamp: Option<NaiveDateTime>,
+
+    /// Which source the effective sort time comes from ("exif", "sidecar", "container", "filename", "createTime", "modifyTime")
+    #[serde(skip_serializing_if = "Option::is_none", rename = "dateSource", default)]
+    pub date_source: Option<String>,
+
     #[serde(
         skip_serializing_if = "Option::is_none",
         rename = "createTime",
@@ -173,9 +187,95 @@ pub struct MediaFile {
     #[serde(skip_serializing_if = "Option::is_none", rename = "videoCodec")]
     pub video_codec: Option<String>,
 
+    /// Video HDR format: "hlg", "pq" (HDR10/HDR10+) or "dolby_vision"; None for SDR
+    #[serde(skip_serializing_if = "Option::is_none", rename = "hdrFormat", default)]
+    pub hdr_format: Option<String>,
+
+    /// Video bit depth per color component (8, 10, 12)
+    #[serde(skip_serializing_if = "Option::is_none", rename = "bitDepth", default)]
+    pub bit_depth: Option<i32>,
+
+    /// Video color primaries as named by FFmpeg ("bt709", "bt2020", ...)
+    #[serde(skip_serializing_if = "Option::is_none", rename = "colorPrimaries", default)]
+    pub color_primaries: Option<String>,
+
+    /// RAW file paired with this display file (RAW+JPEG pairing), maintained by the scanner
+    #[serde(skip_serializing_if = "Option::is_none", rename = "rawPath", default)]
+    pub raw_path: Option<String>,
+
     #[serde(rename = "thumbnailGenerated")]
     pub thumbnail_generated: bool,
 
+    /// HEIC with an auxiliary depth map (served by GET /api/files/{id}/depth)
+    #[serde(rename = "hasDepthMap", skip_serializing_if = "std::ops::Not::not", default)]
+    pub has_depth_map: bool,
+
+    /// HEIC with a portrait effects matte (portrait mode photo)
+    #[serde(rename = "hasPortraitMatte", skip_serializing_if = "std::ops::Not::not", default)]
+    pub has_portrait_matte: bool,
+
+    /// Panorama projection: "equirectangular"/"cylindrical" from GPano XMP or spherical video
+    /// metadata, "wide" for flat panoramas detected by aspect ratio; None for regular media
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub projection: Option<String>,
+
+    /// Name of the embedded ICC profile ("Display P3", "Adobe RGB (1998)"); None without a profile
+    #[serde(skip_serializing_if = "Option::is_none", rename = "colorProfile", default)]
+    pub color_profile: Option<String>,
+
+    /// Fingerprint of the file content (see file_metadata::content_hash); keys the thumbnail
+    /// cache so that moved or re-imported files reuse their thumbnails
+    #[serde(skip_serializing_if = "Option::is_none", rename = "contentHash", default)]
+    pub content_hash: Option<String>,
+
+    /// Added by a quick scan: the row only has file system data, metadata is extracted later
+    #[serde(rename = "pendingExtraction", skip_serializing_if = "std::ops::Not::not", default)]
+    pub pending_extraction: bool,
+
+    /// Sharpness as the variance of the Laplacian of a downscaled grayscale copy; low values
+    /// are blurry. None for videos and files processed before blur detection was enabled
+    #[serde(skip_serializing_if = "Option::is_none", rename = "blurScore", default)]
+    pub blur_score: Option<f64>,
+
+    /// EXIF Orientation (1-8); width and height are stored before it is applied
+    #[serde(skip)]
+    pub orientation: Option<i32>,
+
+    /// Mean luminance in 0.0-1.0, computed when a thumbnail is generated; never written by scans
+    #[serde(skip_serializing_if = "Option::is_none", rename = "meanLuminance", default)]
+    pub mean_luminance: Option<f64>,
+
+    /// Fraction of pixels with clipped highlights (0.0-1.0)
+    #[serde(skip_serializing_if = "Option::is_none", rename = "clippedHighlights", default)]
+    pub clipped_highlights: Option<f64>,
+
+    /// Fraction of pixels with crushed shadows (0.0-1.0)
+    #[serde(skip_serializing_if = "Option::is_none", rename = "clippedShadows", default)]
+    pub clipped_shadows: Option<f64>,
+
+    /// User-edited title (PATCH /api/files/{id}); never written by scans
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub title: Option<String>,
+
+    /// User-edited description; scans only fill it while empty (Google Takeout sidecars)
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub description: Option<String>,
+
+    /// Edit version for optimistic concurrency, incremented by every edit
+    #[serde(default = "default_version")]
+    pub version: i64,
+
+    /// Time of the last user edit; rescans never change it, and files never edited keep the
+    /// time they were first indexed (column default)
+    #[serde(
+        skip_serializing_if = "Option::is_none",
+        rename = "updatedAt",
+        serialize_with = "utc_date_serialization::serialize",
+        deserialize_with = "utc_date_serialization::deserialize",
+        default
+    )]
+    pub updated_at: Option<NaiveDateTime>,
+
     // GPS 是敏感信息：默认序列化不输出，仅通过 GET /api/files/{id}/gps 端点按需返回。
     // skip 同时作用于 serialize/deserialize：前端不应回写 GPS。
     #[serde(skip)]
@@ -184,6 +284,10 @@ pub struct MediaFile {
     pub gps_longitude: Option<f64>,
 }
 
+fn default_version() -> i64 {
+    1
+}
+
 impl MediaFile {
     /// Create a new media file with basic fields
     pub fn new(file_path: String, file_name: String, file_type: String) -> Self {
@@ -198,6 +302,8 @@ impl MediaFile {
             height: None,
             exif_timestamp: None,
             exif_timezone_offset: None,
+            filename_timestamp: None,
+            date_source: None,
             create_time: None,
             modify_time: None,
             last_scanned: None,
@@ -210,40 +316,468 @@ impl MediaFile {
             focal_length: None,
             duration: None,
             video_codec: None,
+            raw_path: None,
             thumbnail_generated: false,
+            has_depth_map: false,
+            has_portrait_matte: false,
+            projection: None,
+            title: None,
+            description: None,
+            version: 1,
+            updated_at: None,
+            hdr_format: None,
+            bit_depth: None,
+            color_primaries: None,
+            color_profile: None,
+            content_hash: None,
+            pending_extraction: false,
+            blur_score: None,
+            orientation: None,
+            mean_luminance: None,
+            clipped_highlights: None,
+            clipped_shadows: None,
             gps_latitude: None,
             gps_longitude: None,
         }
     }
 
-    /// Get the effective sort time (EXIF > create > modify)
+    /// Get the effective sort time (EXIF > filename > create > modify)
     pub fn get_effective_sort_time(&self) -> Option<NaiveDateTime> {
-        // Priority: exif_timestamp > create_time > modify_time
+        self.get_effective_sort_time_with_source().map(|(time, _)| time)
+    }
+
+    /// Get the effective sort time together with the source it was taken from
+    pub fn get_effective_sort_time_with_source(&self) -> Option<(NaiveDateTime, DateSource)> {
+        // Priority: exif_timestamp > filename_timestamp > create_time > modify_time
         if let Some(ts) = self.exif_timestamp {
             if is_valid_exif_time(&ts) {
-                return Some(ts);
+                // 视频没有 EXIF，exif_timestamp 来自容器的 creation_time
+                let source = if self.file_type == "video" { DateSource::Container } else { DateSource::Exif };
+                return Some((ts, source));
+            }
+        }
+        if let Some(ft) = self.filename_timestamp {
+            if is_valid_exif_time(&ft) {
+                return Some((ft, DateSource::Filename));
             }
         }
         if let Some(ct) = self.create_time {
             if is_valid_create_time(&ct) {
-                return Some(ct);
+                return Some((ct, DateSource::CreateTime));
             }
         }
-        self.modify_time
+        self.modify_time.map(|mt| (mt, DateSource::ModifyTime))
+    }
+
+    /// Thumbnail URL of the file, versioned with `version` (see utils::thumbnail::url_version)
+    /// when given. Clients append `size` (and other thumbnail parameters) to it.
+    pub fn thumbnail_url(&self, version: Option<&str>) -> String {
+        match version {
+            Some(version) => format!("/api/files/{}/thumbnail?v={}", self.id, version),
+            None => format!("/api/files/{}/thumbnail", self.id),
+        }
+    }
+}
+
+/// User edit of a media file. For each field None leaves it unchanged,
+/// Some(None) clears it and Some(Some(v)) sets it.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MediaFileEdit {
+    pub title: Option<Option<String>>,
+    pub description: Option<Option<String>>,
+}
+
+/// Result of a versioned edit
+#[derive(Debug, Clone)]
+pub enum EditOutcome {
+    Updated(MediaFile),
+    /// The stored version differs from the expected one; holds the current state
+    Conflict(MediaFile),
+    NotFound,
+}
+
+/// Source of a file's effective sort time
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DateSource {
+    Exif,
+    /// Google Takeout sidecar; the UTC time is stored in exif_timestamp
+    Sidecar,
+    /// Video container creation time (see video_tags); stored in exif_timestamp
+    Container,
+    Filename,
+    CreateTime,
+    ModifyTime,
+}
+
+impl DateSource {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Exif => "exif",
+            Self::Sidecar => "sidecar",
+            Self::Container => "container",
+            Self::Filename => "filename",
+            Self::CreateTime => "createTime",
+            Self::ModifyTime => "modifyTime",
+        }
+    }
+}
+
+/// Exposure filter of GET /api/files (`exposure=underexposed|overexposed`).
+/// Files without exposure statistics (no thumbnail generated yet, videos) never match.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExposureFilter {
+    /// Dark overall or with large crushed shadows: night and low-light shots
+    Underexposed,
+    /// Bright overall or with large blown-out highlights
+    Overexposed,
+}
+
+impl ExposureFilter {
+    /// Mean luminance below this is underexposed
+    pub const UNDEREXPOSED_MAX_LUMINANCE: f64 = 0.25;
+    /// Mean luminance above this is overexposed
+    pub const OVEREXPOSED_MIN_LUMINANCE: f64 = 0.75;
+    /// More than this fraction of clipped pixels is badly exposed regardless of the mean
+    pub const MAX_CLIPPED_FRACTION: f64 = 0.3;
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "underexposed" | "lowLight" => Some(Self::Underexposed),
+            "overexposed" => Some(Self::Overexposed),
+            _ => None,
+        }
+    }
+
+    /// SQL condition on media_files; built from constants only, safe to interpolate
+    pub fn sql_condition(&self) -> String {
+        match self {
+            Self::Underexposed => format!(
+                "(mean_luminance < {} OR clipped_shadows > {})",
+                Self::UNDEREXPOSED_MAX_LUMINANCE, Self::MAX_CLIPPED_FRACTION
+            ),
+            Self::Overexposed => format!(
+                "(mean_luminance > {} OR clipped_highlights > {})",
+                Self::OVEREXPOSED_MIN_LUMINANCE, Self::MAX_CLIPPED_FRACTION
+            ),
+        }
+    }
+}
+
+/// Metadata fields that can be backfilled individually
+/// (POST /api/maintenance/backfill), mapped to their database column.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MetadataField {
+    Width,
+    Height,
+    ExifTimestamp,
+    ExifTimezoneOffset,
+    CameraMake,
+    CameraModel,
+    LensModel,
+    ExposureTime,
+    Aperture,
+    Iso,
+    FocalLength,
+    Duration,
+    VideoCodec,
+    Gps,
+    ContentHash,
+    BlurScore,
+}
+
+impl MetadataField {
+    /// Parse from the API field name (camelCase, same as MediaFile JSON keys)
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "width" => Some(Self::Width),
+            "height" => Some(Self::Height),
+            "exifTimestamp" => Some(Self::ExifTimestamp),
+            "exifTimezoneOffset" => Some(Self::ExifTimezoneOffset),
+            "cameraMake" => Some(Self::CameraMake),
+            "cameraModel" => Some(Self::CameraModel),
+            "lensModel" => Some(Self::LensModel),
+            "exposureTime" => Some(Self::ExposureTime),
+            "aperture" => Some(Self::Aperture),
+            "iso" => Some(Self::Iso),
+            "focalLength" => Some(Self::FocalLength),
+            "duration" => Some(Self::Duration),
+            "videoCodec" => Some(Self::VideoCodec),
+            "gps" | "latitude" | "longitude" => Some(Self::Gps),
+            "contentHash" => Some(Self::ContentHash),
+            "blurScore" => Some(Self::BlurScore),
+            _ => None,
+        }
+    }
+
+    /// Database column checked for NULL
+    pub fn column_name(&self) -> &'static str {
+        match self {
+            Self::Width => "width",
+            Self::Height => "height",
+            Self::ExifTimestamp => "exif_timestamp",
+            Self::ExifTimezoneOffset => "exif_timezone_offset",
+            Self::CameraMake => "camera_make",
+            Self::CameraModel => "camera_model",
+            Self::LensModel => "lens_model",
+            Self::ExposureTime => "exposure_time",
+            Self::Aperture => "aperture",
+            Self::Iso => "iso",
+            Self::FocalLength => "focal_length",
+            Self::Duration => "duration",
+            Self::VideoCodec => "video_codec",
+            Self::Gps => "gps_latitude",
+            Self::ContentHash => "content_hash",
+            Self::BlurScore => "blur_score",
+        }
+    }
+
+    /// Restrict the backfill to a file type when the field only applies to one
+    pub fn file_type(&self) -> Option<&'static str> {
+        match self {
+            Self::Duration | Self::VideoCodec => Some("video"),
+            Self::CameraMake
+            | Self::CameraModel
+            | Self::LensModel
+            | Self::ExposureTime
+            | Self::Aperture
+            | Self::Iso
+            | Self::FocalLength
+            | Self::BlurScore => Some("image"),
+            // 视频的拍摄时间与地点来自容器标签（见 video_tags）
+            Self::Width
+            | Self::Height
+            | Self::ExifTimestamp
+            | Self::ExifTimezoneOffset
+            | Self::Gps
+            | Self::ContentHash => None,
+        }
     }
 }
 
 /// Directory entity
 #[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct Directory {
     pub id: i64,
     pub path: String,
 
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub parent_id: Option<i64>,
+    pub parent_path: Option<String>,
+
+    pub name: String,
+
+    /// Display name from the folder's .latte.json
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub display_name: Option<String>,
+
+    /// Cover file from the folder's .latte.json
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cover_path: Option<String>,
+
+    /// Media file id of the cover, when the cover file is in the library
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cover_id: Option<String>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_scanned: Option<NaiveDateTime>,
+}
+
+/// A directory seen during scan, synced into the directories table
+#[derive(Debug, Clone, PartialEq)]
+pub struct DirectoryEntry {
+    pub path: String,
+    pub parent_path: Option<String>,
+    pub name: String,
+    pub display_name: Option<String>,
+    pub cover_path: Option<String>,
+}
+
+/// Label assigned to a file by the external tagging service
+#[derive(Debug, Clone, PartialEq, FromRow, Serialize, Deserialize)]
+pub struct MediaLabel {
+    pub label: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub score: Option<f64>,
+}
+
+/// One top-level image of a multi-image container (e.g. a HEIC burst)
+#[derive(Debug, Clone, PartialEq, FromRow, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MediaSubImage {
+    /// Position among the top-level images; used as the `?item=` thumbnail parameter
+    pub item_index: i32,
+    pub width: Option<i32>,
+    pub height: Option<i32>,
+    pub is_primary: bool,
+}
+
+/// Extended key/value metadata of a media file (media_file_attributes)
+#[derive(Debug, Clone, PartialEq, FromRow, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MediaAttribute {
+    pub key: String,
+    pub value: String,
+    /// What produced the value, e.g. "exif"; a rescan replaces only the rows of its own source
+    pub source: String,
+}
+
+/// A file whose processing failed, kept for later retry
+#[derive(Debug, Clone, FromRow, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FailedFile {
+    pub file_id: String,
+    /// Processing step that failed, e.g. "thumbnail"
+    pub stage: String,
+    pub error: String,
+    pub attempts: i64,
+    pub last_failed_at: Option<NaiveDateTime>,
+}
+
+/// Note left on a media file; `parent_id` makes it a reply to another note of the same file
+#[derive(Debug, Clone, FromRow, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MediaNote {
+    pub id: String,
+    pub file_id: String,
+    pub parent_id: Option<String>,
+    pub author: Option<String>,
+    pub body: String,
+    pub created_at: Option<NaiveDateTime>,
+    pub updated_at: Option<NaiveDateTime>,
+}
 
+impl MediaNote {
+    pub fn new(file_id: String, parent_id: Option<String>, author: Option<String>, body: String) -> Self {
+        Self {
+            id: Uuid::new_v4().to_string(),
+            file_id,
+            parent_id,
+            author,
+            body,
+            created_at: None,
+            updated_at: None,
+        }
+    }
+}
+
+/// One entry of the activity feed (GET /api/activity)
+#[derive(Debug, Clone, FromRow, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ActivityEvent {
+    /// "files_added" (files first seen by scans on one day), "note" or "edit" (title/description changed)
+    pub kind: String,
+    #[serde(serialize_with = "utc_date_serialization::serialize")]
+    pub time: Option<NaiveDateTime>,
+    /// File concerned; for "files_added" the most recently added file of the day
+    pub file_id: Option<String>,
+    /// Number of files added; 1 for the other kinds
+    pub count: i64,
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub last_modified: Option<NaiveDateTime>,
+    pub author: Option<String>,
+    /// Note body or edited title
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub text: Option<String>,
+}
+
+/// Kind of entry in the media change log
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChangeKind {
+    Created,
+    Updated,
+    Deleted,
+}
+
+impl ChangeKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Created => "created",
+            Self::Updated => "updated",
+            Self::Deleted => "deleted",
+        }
+    }
+}
+
+/// Latest change-log entry of a media file after a sync cursor
+#[derive(Debug, Clone, FromRow)]
+pub struct MediaChange {
+    /// Log position; the cursor after this change
+    pub seq: i64,
+    pub file_id: String,
+    /// "created", "updated" or "deleted" (ChangeKind)
+    pub change: String,
+    pub changed_at: NaiveDateTime,
+}
+
+/// Outbound webhook registered through /api/webhooks
+#[derive(Debug, Clone, FromRow)]
+pub struct Webhook {
+    pub id: String,
+    pub url: String,
+    /// Comma-separated event names; empty subscribes to every event
+    pub events: String,
+    /// HMAC-SHA256 key for the X-Latte-Signature header
+    pub secret: Option<String>,
+    pub enabled: bool,
+    pub created_at: Option<NaiveDateTime>,
+}
+
+impl Webhook {
+    pub fn new(url: String, events: &[String], secret: Option<String>) -> Self {
+        Self {
+            id: Uuid::new_v4().to_string(),
+            url,
+            events: events.join(","),
+            secret,
+            enabled: true,
+            created_at: None,
+        }
+    }
+
+    /// Subscribed event names (empty = all events)
+    pub fn event_list(&self) -> Vec<String> {
+        self.events
+            .split(',')
+            .map(str::trim)
+            .filter(|e| !e.is_empty())
+            .map(str::to_string)
+            .collect()
+    }
+
+    /// Whether this webhook should receive `event`
+    pub fn accepts(&self, event: &str) -> bool {
+        let events = self.event_list();
+        events.is_empty() || events.iter().any(|e| e == event)
+    }
+}
+
+/// Encode an embedding vector for storage (little-endian f32)
+pub fn encode_embedding(vector: &[f32]) -> Vec<u8> {
+    vector.iter().flat_map(|v| v.to_le_bytes()).collect()
+}
+
+/// Decode a stored embedding vector; trailing bytes of an incomplete value are ignored
+pub fn decode_embedding(bytes: &[u8]) -> Vec<f32> {
+    bytes
+        .chunks_exact(4)
+        .map(|chunk| f32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]))
+        .collect()
+}
+
+/// What a scan needs to know about a file that is already in the database
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExistingFile {
+    pub modify_time: Option<NaiveDateTime>,
+    /// Added by a quick scan; always reprocessed
+    pub pending_extraction: bool,
+    pub file_size: Option<i64>,
+    pub content_hash: Option<String>,
+}
+
+/// Lookup key of a file path (media_files.path_key): Unicode NFC, so that the decomposed
+/// names some file systems report (macOS, SMB) match the stored path
+pub fn path_key(file_path: &str) -> String {
+    use unicode_normalization::UnicodeNormalization;
+    file_path.nfc().collect()
 }
 
 /// Date info for calendar display
@@ -253,6 +787,18 @@ pub struct DateInfo {
     pub count:i64,
 }
 
+/// One bucket of the timeline (see MediaFileRepository::timeline)
+#[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
+pub struct TimelineBucket {
+    /// First day of the bucket (YYYY-MM-DD)
+    pub start: String,
+    pub count: i64,
+    /// Earliest file of the bucket by effective time
+    pub first_id: String,
+    /// Latest file of the bucket by effective time
+    pub last_id: String,
+}
+
 /// Validates EXIF timestamp (must be between 1900 and current year + 1)
 fn is_valid_exif_time(time: &NaiveDateTime) -> bool {
     let year = time.year();
@@ -271,6 +817,32 @@ mod tests {
     use super::*;
     use chrono::NaiveDate;
 
+    #[test]
+    fn test_thumbnail_url_versioned() {
+        let file = MediaFile::new("/p/a.jpg".to_string(), "a.jpg".to_string(), "image".to_string());
+        assert_eq!(file.thumbnail_url(None), format!("/api/files/{}/thumbnail", file.id));
+        assert_eq!(file.thumbnail_url(Some("0123abcd")), format!("/api/files/{}/thumbnail?v=0123abcd", file.id));
+    }
+
+    #[test]
+    fn test_path_key_nfc() {
+        // "é" 分解形式（e + U+0301）与组合形式得到同一个 key
+        assert_eq!(path_key("/photos/cafe\u{301}.jpg"), path_key("/photos/caf\u{e9}.jpg"));
+        assert_eq!(path_key("/photos/plain.jpg"), "/photos/plain.jpg");
+    }
+
+    #[test]
+    fn test_webhook_accepts() {
+        let all = Webhook::new("http://hook".to_string(), &[], None);
+        assert!(all.accepts("scan.completed"));
+        assert!(all.event_list().is_empty());
+
+        let some = Webhook::new("http://hook".to_string(), &["scan.failed".to_string(), "disk.low".to_string()], None);
+        assert_eq!(some.events, "scan.failed,disk.low");
+        assert!(some.accepts("disk.low"));
+        assert!(!some.accepts("scan.completed"));
+    }
+
     #[test]
     fn test_media_file_new() {
         let file = MediaFile::new(
@@ -357,6 +929,62 @@ mod tests {
         assert!(result.is_none());
     }
 
+    #[test]
+    fn test_media_file_get_effective_sort_time_filename_fallback() {
+        let filename_time = NaiveDate::from_ymd_opt(2019, 4, 5)
+            .unwrap()
+            .and_hms_opt(0, 0, 0)
+            .unwrap();
+        let create_time = NaiveDate::from_ymd_opt(2024, 6, 16)
+            .unwrap()
+            .and_hms_opt(0, 0, 0)
+            .unwrap();
+
+        let mut file = MediaFile::new("/IMG-20190405-WA0003.jpg".to_string(), "IMG-20190405-WA0003.jpg".to_string(), "image".to_string());
+        file.filename_timestamp = Some(filename_time);
+        file.create_time = Some(create_time);
+
+        // 文件名日期优先于文件系统时间
+        assert_eq!(file.get_effective_sort_time_with_source(), Some((filename_time, DateSource::Filename)));
+
+        // EXIF 仍然优先于文件名日期
+        let exif_time = NaiveDate::from_ymd_opt(2019, 4, 6)
+            .unwrap()
+            .and_hms_opt(8, 0, 0)
+            .unwrap();
+        file.exif_timestamp = Some(exif_time);
+        assert_eq!(file.get_effective_sort_time_with_source(), Some((exif_time, DateSource::Exif)));
+    }
+
+    #[test]
+    fn test_media_file_video_container_time() {
+        let container_time = NaiveDate::from_ymd_opt(2021, 7, 4)
+            .unwrap()
+            .and_hms_opt(19, 2, 11)
+            .unwrap();
+        let copied_at = NaiveDate::from_ymd_opt(2024, 1, 2)
+            .unwrap()
+            .and_hms_opt(0, 0, 0)
+            .unwrap();
+
+        let mut file = MediaFile::new("/VID_1.mp4".to_string(), "VID_1.mp4".to_string(), "video".to_string());
+        file.create_time = Some(copied_at);
+        file.exif_timestamp = Some(container_time);
+
+        // 容器时间优先于复制时被重置的文件系统时间
+        assert_eq!(file.get_effective_sort_time_with_source(), Some((container_time, DateSource::Container)));
+    }
+
+    #[test]
+    fn test_date_source_as_str() {
+        assert_eq!(DateSource::Exif.as_str(), "exif");
+        assert_eq!(DateSource::Sidecar.as_str(), "sidecar");
+        assert_eq!(DateSource::Container.as_str(), "container");
+        assert_eq!(DateSource::Filename.as_str(), "filename");
+        assert_eq!(DateSource::CreateTime.as_str(), "createTime");
+        assert_eq!(DateSource::ModifyTime.as_str(), "modifyTime");
+    }
+
     #[test]
     fn test_media_file_get_effective_sort_time_invalid_exif() {
         let old_exif = NaiveDate::from_ymd_opt(1800, 1, 1)
@@ -395,6 +1023,39 @@ mod tests {
         assert_eq!(FileType::from("unknown"), FileType::Image);
     }
 
+    #[test]
+    fn test_metadata_field_from_name() {
+        assert_eq!(MetadataField::from_name("width"), Some(MetadataField::Width));
+        assert_eq!(MetadataField::from_name("latitude"), Some(MetadataField::Gps));
+        assert_eq!(MetadataField::from_name("videoCodec"), Some(MetadataField::VideoCodec));
+        assert_eq!(MetadataField::from_name("file_path"), None);
+        assert_eq!(MetadataField::from_name("width; DROP TABLE media_files"), None);
+    }
+
+    #[test]
+    fn test_exposure_filter_from_name() {
+        assert_eq!(ExposureFilter::from_name("underexposed"), Some(ExposureFilter::Underexposed));
+        assert_eq!(ExposureFilter::from_name("lowLight"), Some(ExposureFilter::Underexposed));
+        assert_eq!(ExposureFilter::from_name("overexposed"), Some(ExposureFilter::Overexposed));
+        assert_eq!(ExposureFilter::from_name("dark; DROP TABLE media_files"), None);
+        assert_eq!(
+            ExposureFilter::Underexposed.sql_condition(),
+            "(mean_luminance < 0.25 OR clipped_shadows > 0.3)"
+        );
+    }
+
+    #[test]
+    fn test_metadata_field_column_and_type() {
+        assert_eq!(MetadataField::Gps.column_name(), "gps_latitude");
+        assert_eq!(MetadataField::ExifTimestamp.column_name(), "exif_timestamp");
+        assert_eq!(MetadataField::Duration.file_type(), Some("video"));
+        assert_eq!(MetadataField::CameraModel.file_type(), Some("image"));
+        assert_eq!(MetadataField::BlurScore.column_name(), "blur_score");
+        assert_eq!(MetadataField::BlurScore.file_type(), Some("image"));
+        assert_eq!(MetadataField::Width.file_type(), None);
+        assert_eq!(MetadataField::Gps.file_type(), None);
+    }
+
     #[test]
     fn test_date_info_serde() {
         let date_info = DateInfo {
@@ -412,12 +1073,18 @@ mod tests {
         let dir = Directory {
             id: 1,
             path: "/photos".to_string(),
-            parent_id: None,
-            last_modified: None,
+            parent_path: None,
+            name: "photos".to_string(),
+            display_name: Some("Photos".to_string()),
+            cover_path: None,
+            cover_id: None,
+            last_scanned: None,
         };
 
         let json = serde_json::to_string(&dir).unwrap();
         assert!(json.contains("\"path\":\"/photos\""));
+        assert!(json.contains("\"displayName\":\"Photos\""));
+        assert!(!json.contains("coverPath"));
     }
 
     #[test]
@@ -444,6 +1111,36 @@ mod tests {
         assert!(is_valid_create_time(&past_time));
     }
 
+    proptest::proptest! {
+        #[test]
+        fn prop_exif_time_valid_iff_year_in_range(year in 1i32..9999, ordinal in 1u32..=365, secs in 0u32..86_400) {
+            let time = NaiveDate::from_yo_opt(year, ordinal)
+                .unwrap()
+                .and_time(chrono::NaiveTime::from_num_seconds_from_midnight_opt(secs, 0).unwrap());
+            let current_year = Utc::now().year();
+            proptest::prop_assert_eq!(is_valid_exif_time(&time), (1900..=current_year + 1).contains(&year));
+        }
+
+        #[test]
+        fn prop_create_time_rejects_future(offset_secs in 1i64..(200 * 365 * 86_400)) {
+            let now = Utc::now().naive_utc();
+            proptest::prop_assert!(is_valid_create_time(&(now - chrono::Duration::seconds(offset_secs))));
+            proptest::prop_assert!(!is_valid_create_time(&(now + chrono::Duration::seconds(offset_secs + 60))));
+        }
+
+        /// 无效的 EXIF 时间不会被选为排序时间，回退到下一来源
+        #[test]
+        fn prop_effective_sort_time_skips_invalid_exif(year in 1i32..1900) {
+            let mut file = MediaFile::new("/p/a.jpg".to_string(), "a.jpg".to_string(), "image".to_string());
+            file.exif_timestamp = NaiveDate::from_ymd_opt(year, 1, 1).and_then(|d| d.and_hms_opt(0, 0, 0));
+            file.modify_time = NaiveDate::from_ymd_opt(2020, 1, 1).and_then(|d| d.and_hms_opt(0, 0, 0));
+            file.create_time = None;
+            let (time, source) = file.get_effective_sort_time_with_source().unwrap();
+            proptest::prop_assert_eq!(Some(time), file.modify_time);
+            proptest::prop_assert_eq!(source, DateSource::ModifyTime);
+        }
+    }
+
     #[test]
     fn test_media_file_serialization() {
         let mut file = MediaFile::new("/test.jpg".to_string(), "test.jpg".to_string(), "image".to_string());
@@ -532,4 +1229,14 @@ mod tests {
         assert_eq!(file.width, Some(1920));
         assert_eq!(file.height, Some(1080));
     }
+
+    #[test]
+    fn test_embedding_roundtrip() {
+        let vector = vec![0.5f32, -1.25, 3.0];
+        let bytes = encode_embedding(&vector);
+        assert_eq!(bytes.len(), 12);
+        assert_eq!(decode_embedding(&bytes), vector);
+        // 截断的尾部字节被忽略
+        assert_eq!(decode_embedding(&bytes[..10]), vec![0.5, -1.25]);
+    }
 }
@@ -1,4 +1,4 @@
-use sqlx::sqlite::SqlitePool;
+use sqlx::sqlite::{SqliteConnectOptions, SqlitePool};
 use sqlx::migrate::Migrator;
 use std::path::Path;
 use thiserror::Error;
@@ -13,12 +13,17 @@ pub enum DatabaseError {
 
     #[error("IO error: {0}")]
     IoError(#[from] std::io::Error),
+
+    #[error("Invalid backup: {0}")]
+    InvalidBackup(String),
 }
 
 /// Database connection pool wrapper
 #[derive(Clone, Debug)]
 pub struct DatabasePool {
     pool: SqlitePool,
+    /// Query-only pool for browsing reads (LATTE_DB_READ_REPLICA)
+    read_pool: Option<SqlitePool>,
 }
 
 impl DatabasePool {
@@ -42,7 +47,21 @@ impl DatabasePool {
         let url = format!("file:{}", absolute_path.to_string_lossy());
         let pool = SqlitePool::connect(&url).await?;
 
-        Ok(Self { pool })
+        Ok(Self { pool, read_pool: None })
+    }
+
+    /// Serve browsing reads from a second pool on `replica_path`: a replica kept current
+    /// by e.g. litestream, or the primary database file itself. Its connections are opened
+    /// with `query_only`, so a misrouted write fails instead of diverging from the primary.
+    pub async fn with_read_replica(mut self, replica_path: &Path) -> Result<Self, DatabaseError> {
+        // 副本由外部同步生成，不存在时报错而不是创建空库
+        let absolute_path = std::fs::canonicalize(replica_path)?;
+        let options = SqliteConnectOptions::new()
+            .filename(absolute_path)
+            .create_if_missing(false)
+            .pragma("query_only", "ON");
+        self.read_pool = Some(SqlitePool::connect_with(options).await?);
+        Ok(self)
     }
 
     /// Run migrations
@@ -57,5 +76,16 @@ impl DatabasePool {
         &self.pool
     }
 
+    /// Pool for reads that may lag slightly behind writes: the read replica if configured,
+    /// otherwise the primary pool. Reads that must see the caller's own writes (scans,
+    /// read-modify-write sequences) use `get_pool`.
+    pub fn read_pool(&self) -> &SqlitePool {
+        self.read_pool.as_ref().unwrap_or(&self.pool)
+    }
+
+    /// A read replica is configured
+    pub fn has_read_replica(&self) -> bool {
+        self.read_pool.is_some()
+    }
 }
 
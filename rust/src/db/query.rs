@@ -0,0 +1,261 @@
+//! 文件列表查询
+//! `FileQuery` 汇总筛选、排序与分页参数，并在一处翻译成 SQL；列表、计数、时间线与导出共用它，
+//! 新增筛选条件只需要在这里实现一次，各端点的筛选语义保持一致。
+
+use crate::db::models::ExposureFilter;
+
+/// Sort fields of file listings, named as in the API (`sortBy`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SortField {
+    #[default]
+    ExifTimestamp,
+    CreateTime,
+    ModifyTime,
+    FileName,
+    BlurScore,
+}
+
+impl SortField {
+    pub const ALL: [SortField; 5] = [
+        Self::ExifTimestamp,
+        Self::CreateTime,
+        Self::ModifyTime,
+        Self::FileName,
+        Self::BlurScore,
+    ];
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|field| field.as_str() == name)
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::ExifTimestamp => "exifTimestamp",
+            Self::CreateTime => "createTime",
+            Self::ModifyTime => "modifyTime",
+            Self::FileName => "fileName",
+            Self::BlurScore => "blurScore",
+        }
+    }
+
+    fn column(&self) -> &'static str {
+        match self {
+            Self::ExifTimestamp => "exif_timestamp",
+            Self::CreateTime => "create_time",
+            Self::ModifyTime => "modify_time",
+            Self::FileName => "file_name",
+            Self::BlurScore => "blur_score",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SortOrder {
+    Asc,
+    #[default]
+    Desc,
+}
+
+impl SortOrder {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "asc" => Some(Self::Asc),
+            "desc" => Some(Self::Desc),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Asc => "asc",
+            Self::Desc => "desc",
+        }
+    }
+}
+
+/// Filters, sort and pagination of a media file listing.
+/// Built with the chained setters; filters that are not set match every file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FileQuery {
+    /// Substring of the file path
+    pub path: Option<String>,
+    /// "image" or "video"; "all" matches both
+    pub file_type: Option<String>,
+    pub camera_model: Option<String>,
+    /// Date prefix of the capture time ("2024", "2024-05", "2024-05-01")
+    pub date: Option<String>,
+    /// Only files whose blur score is below this (unscored files are excluded)
+    pub max_blur_score: Option<f64>,
+    pub exposure: Option<ExposureFilter>,
+    pub sort: SortField,
+    pub order: SortOrder,
+    pub page: i32,
+    pub page_size: i32,
+}
+
+impl Default for FileQuery {
+    fn default() -> Self {
+        Self {
+            path: None,
+            file_type: None,
+            camera_model: None,
+            date: None,
+            max_blur_score: None,
+            exposure: None,
+            sort: SortField::default(),
+            order: SortOrder::default(),
+            page: 0,
+            page_size: 50,
+        }
+    }
+}
+
+/// WHERE conditions (each starting with " AND ") and their bind values, in order
+#[derive(Debug, Default, PartialEq)]
+pub(crate) struct FilterSql {
+    pub conditions: String,
+    pub params: Vec<String>,
+}
+
+impl FileQuery {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn path(mut self, path: Option<&str>) -> Self {
+        self.path = path.map(str::to_string);
+        self
+    }
+
+    pub fn file_type(mut self, file_type: Option<&str>) -> Self {
+        self.file_type = file_type.map(str::to_string);
+        self
+    }
+
+    pub fn camera_model(mut self, camera_model: Option<&str>) -> Self {
+        self.camera_model = camera_model.map(str::to_string);
+        self
+    }
+
+    pub fn date(mut self, date: Option<&str>) -> Self {
+        self.date = date.map(str::to_string);
+        self
+    }
+
+    pub fn max_blur_score(mut self, max_blur_score: Option<f64>) -> Self {
+        self.max_blur_score = max_blur_score;
+        self
+    }
+
+    pub fn exposure(mut self, exposure: Option<ExposureFilter>) -> Self {
+        self.exposure = exposure;
+        self
+    }
+
+    pub fn sort(mut self, sort: SortField, order: SortOrder) -> Self {
+        self.sort = sort;
+        self.order = order;
+        self
+    }
+
+    pub fn page(mut self, page: i32, page_size: i32) -> Self {
+        self.page = page;
+        self.page_size = page_size;
+        self
+    }
+
+    /// Filter conditions on media_files
+    pub(crate) fn filter_sql(&self) -> FilterSql {
+        let mut sql = FilterSql::default();
+
+        if let Some(path) = &self.path {
+            sql.conditions.push_str(" AND file_path LIKE ?");
+            sql.params.push(format!("%{}%", path));
+        }
+
+        if let Some(ft) = self.file_type.as_deref().filter(|ft| *ft != "all") {
+            sql.conditions.push_str(" AND file_type = ?");
+            sql.params.push(ft.to_string());
+        }
+
+        if let Some(camera) = &self.camera_model {
+            sql.conditions.push_str(" AND camera_model = ?");
+            sql.params.push(camera.clone());
+        }
+
+        if let Some(date) = &self.date {
+            sql.conditions.push_str(
+                " AND (exif_timestamp LIKE ? OR filename_timestamp LIKE ? OR create_time LIKE ? OR modify_time LIKE ?)",
+            );
+            let date_prefix = format!("{}%", date);
+            sql.params.extend(std::iter::repeat_n(date_prefix, 4));
+        }
+
+        if let Some(max) = self.max_blur_score {
+            // blur_score 列为 REAL 亲和性，文本参数会按数值比较
+            sql.conditions.push_str(" AND blur_score < ?");
+            sql.params.push(max.to_string());
+        }
+
+        if let Some(exposure) = self.exposure {
+            sql.conditions.push_str(&format!(" AND {}", exposure.sql_condition()));
+        }
+
+        sql
+    }
+
+    /// ORDER BY clause; files without a value for the sort field come last
+    pub(crate) fn order_sql(&self) -> String {
+        let column = self.sort.column();
+        let direction = match self.order {
+            SortOrder::Asc => "ASC",
+            SortOrder::Desc => "DESC",
+        };
+        format!(" ORDER BY CASE WHEN {column} IS NOT NULL THEN 0 ELSE 1 END, {column} {direction}")
+    }
+
+    /// LIMIT/OFFSET clause of the requested page
+    pub(crate) fn limit_sql(&self) -> String {
+        format!(" LIMIT {} OFFSET {}", self.page_size, self.page.saturating_mul(self.page_size))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sort_names_round_trip() {
+        for field in SortField::ALL {
+            assert_eq!(SortField::from_name(field.as_str()), Some(field));
+        }
+        assert_eq!(SortField::from_name("exif_timestamp"), None);
+        assert_eq!(SortOrder::from_name("asc"), Some(SortOrder::Asc));
+        assert_eq!(SortOrder::from_name("up"), None);
+    }
+
+    #[test]
+    fn test_filter_sql() {
+        assert_eq!(FileQuery::new().file_type(Some("all")).filter_sql(), FilterSql::default());
+
+        let sql = FileQuery::new()
+            .path(Some("/trip"))
+            .file_type(Some("video"))
+            .date(Some("2024-05"))
+            .max_blur_score(Some(100.0))
+            .filter_sql();
+        assert_eq!(
+            sql.conditions,
+            " AND file_path LIKE ? AND file_type = ? AND (exif_timestamp LIKE ? OR filename_timestamp LIKE ? \
+             OR create_time LIKE ? OR modify_time LIKE ?) AND blur_score < ?"
+        );
+        assert_eq!(sql.params, ["%/trip%", "video", "2024-05%", "2024-05%", "2024-05%", "2024-05%", "100"]);
+    }
+
+    #[test]
+    fn test_order_and_limit_sql() {
+        let query = FileQuery::new().sort(SortField::FileName, SortOrder::Asc).page(2, 20);
+        assert_eq!(query.order_sql(), " ORDER BY CASE WHEN file_name IS NOT NULL THEN 0 ELSE 1 END, file_name ASC");
+        assert_eq!(query.limit_sql(), " LIMIT 20 OFFSET 40");
+    }
+}
@@ -1,9 +1,17 @@
-use crate::db::models::{DateInfo, Directory, MediaFile};
+use crate::db::models::{decode_embedding, encode_embedding, path_key, ActivityEvent, ChangeKind, DateInfo, Directory, DirectoryEntry, EditOutcome, ExistingFile, FailedFile, MediaAttribute, MediaChange, MediaFile, MediaFileEdit, MediaLabel, MediaNote, MediaSubImage, MetadataField, TimelineBucket, Webhook};
+use crate::db::query::FileQuery;
 use crate::db::pool::DatabasePool;
-use chrono::{NaiveDateTime, Utc};
+use crate::utils::calendar::Granularity;
+use chrono::{NaiveDateTime, Utc, Weekday};
+use sqlx::{Sqlite, SqliteConnection, Transaction};
+use std::collections::HashMap;
 use std::path::{Path, PathBuf};
 
-/// Repository for media file database operations
+/// Row of find_existing: (path_key, modify_time, pending_extraction, file_size, content_hash)
+type ExistingFileRow = (String, Option<NaiveDateTime>, bool, Option<i64>, Option<String>);
+
+/// Repository for media file database operations. Browsing reads (listing, timeline,
+/// search, counts) use the read replica when one is configured.
 pub struct MediaFileRepository<'a> {
     db: &'a DatabasePool,
 }
@@ -14,65 +22,21 @@ impl<'a> MediaFileRepository<'a> {
     }
 
     /// Get all media files with pagination and filtering
-    pub async fn find_all(
-        &self,
-        path_filter: Option<&str>,
-        file_type: Option<&str>,
-        camera_model: Option<&str>,
-        date_filter: Option<&str>,
-        sort_by: &str,
-        order: &str,
-        page: i32,
-        page_size: i32,
-    ) -> Result<Vec<MediaFile>, sqlx::Error> {
-        let mut query = String::from("SELECT * FROM media_files WHERE 1=1");
-        let mut params: Vec<String> = Vec::new();
-
-        if let Some(path) = path_filter {
-            query.push_str(" AND file_path LIKE ?");
-            params.push(format!("%{}%", path));
-        }
-
-        if let Some(ft) = file_type {
-            if ft != "all" {
-                query.push_str(" AND file_type = ?");
-                params.push(ft.to_string());
-            }
-        }
-
-        if let Some(camera) = camera_model {
-            query.push_str(" AND camera_model = ?");
-            params.push(camera.to_string());
-        }
-
-        if let Some(date) = date_filter {
-            query.push_str(" AND (exif_timestamp LIKE ? OR create_time LIKE ? OR modify_time LIKE ?)");
-            let date_prefix = format!("{}%", date);
-            params.push(date_prefix.clone());
-            params.push(date_prefix.clone());
-            params.push(date_prefix);
-        }
-
-        // Sort by effective time (EXIF > create > modify)
-        let sort_field = match sort_by {
-            "exifTimestamp" => "exif_timestamp",
-            "createTime" => "create_time",
-            "modifyTime" => "modify_time",
-            "fileName" => "file_name",
-            _ => "exif_timestamp",
-        };
-
-        query.push_str(&format!(" ORDER BY CASE WHEN {} IS NOT NULL THEN 0 ELSE 1 END, {} {}",
-            sort_field, sort_field, if order == "asc" { "ASC" } else { "DESC" }));
-
-        query.push_str(&format!(" LIMIT {} OFFSET {}", page_size, page * page_size));
+    pub async fn find_all(&self, query: &FileQuery) -> Result<Vec<MediaFile>, sqlx::Error> {
+        let filter = query.filter_sql();
+        let sql = format!(
+            "SELECT * FROM media_files WHERE 1=1{}{}{}",
+            filter.conditions,
+            query.order_sql(),
+            query.limit_sql()
+        );
 
-        let mut sqlx_query = sqlx::query_as::<_, MediaFile>(&query);
-        for param in &params {
+        let mut sqlx_query = sqlx::query_as::<_, MediaFile>(&sql);
+        for param in &filter.params {
             sqlx_query = sqlx_query.bind(param.as_str());
         }
 
-        sqlx_query.fetch_all(self.db.get_pool()).await
+        sqlx_query.fetch_all(self.db.read_pool()).await
     }
 
     /// Get file by ID
@@ -83,6 +47,43 @@ impl<'a> MediaFileRepository<'a> {
             .await
     }
 
+    /// Files with the given ids, in no particular order; unknown ids are skipped
+    pub async fn find_by_ids(&self, ids: &[String]) -> Result<Vec<MediaFile>, sqlx::Error> {
+        use sqlx::QueryBuilder;
+
+        const MAX_IDS: usize = 32766;
+
+        let mut files = Vec::with_capacity(ids.len());
+        for chunk in ids.chunks(MAX_IDS) {
+            let mut query_builder: QueryBuilder<'_, Sqlite> = QueryBuilder::new("SELECT * FROM media_files WHERE id IN ");
+            query_builder.push_tuples(chunk.iter(), |mut b, id| {
+                b.push_bind(id.as_str());
+            });
+            files.extend(query_builder.build_query_as::<MediaFile>().fetch_all(self.db.read_pool()).await?);
+        }
+        Ok(files)
+    }
+
+    /// Content hash of a file; None if the file is unknown or not hashed yet
+    pub async fn find_content_hash(&self, id: &str) -> Result<Option<String>, sqlx::Error> {
+        let hash: Option<Option<String>> = sqlx::query_scalar("SELECT content_hash FROM media_files WHERE id = ?")
+            .bind(id)
+            .fetch_optional(self.db.get_pool())
+            .await?;
+        Ok(hash.flatten())
+    }
+
+    /// Version of a file's content for cache validators: the content hash, or size and
+    /// modification time for files not hashed yet. None if the file is unknown.
+    pub async fn find_source_version(&self, id: &str) -> Result<Option<String>, sqlx::Error> {
+        sqlx::query_scalar(
+            "SELECT COALESCE(content_hash, printf('%d-%s', file_size, modify_time)) FROM media_files WHERE id = ?",
+        )
+        .bind(id)
+        .fetch_optional(self.db.get_pool())
+        .await
+    }
+
     /// Get file by path
     pub async fn find_by_path(&self, path: &Path) -> Result<Option<MediaFile>, sqlx::Error> {
         sqlx::query_as::<_, MediaFile>("SELECT * FROM media_files WHERE file_path = ?")
@@ -103,17 +104,18 @@ impl<'a> MediaFileRepository<'a> {
 
         let query = format!(
             "SELECT * FROM media_files
-             WHERE (exif_timestamp {} ? OR (exif_timestamp IS NULL AND create_time {} ?) OR (exif_timestamp IS NULL AND create_time IS NULL AND modify_time {} ?))
-             ORDER BY CASE WHEN exif_timestamp IS NOT NULL THEN 0 ELSE 1 END, exif_timestamp {} NULLS LAST, create_time {} NULLS LAST, modify_time {} {}
+             WHERE (exif_timestamp {} ? OR (exif_timestamp IS NULL AND filename_timestamp {} ?) OR (exif_timestamp IS NULL AND filename_timestamp IS NULL AND create_time {} ?) OR (exif_timestamp IS NULL AND filename_timestamp IS NULL AND create_time IS NULL AND modify_time {} ?))
+             ORDER BY CASE WHEN exif_timestamp IS NOT NULL THEN 0 ELSE 1 END, exif_timestamp {} NULLS LAST, filename_timestamp {} NULLS LAST, create_time {} NULLS LAST, modify_time {} {}
              LIMIT 1",
-            op, op, op, order, order, order, order
+            op, op, op, op, order, order, order, order, order
         );
 
         sqlx::query_as::<_, MediaFile>(&query)
             .bind(sort_time)
             .bind(sort_time)
             .bind(sort_time)
-            .fetch_optional(self.db.get_pool())
+            .bind(sort_time)
+            .fetch_optional(self.db.read_pool())
             .await
     }
 
@@ -127,96 +129,176 @@ impl<'a> MediaFileRepository<'a> {
             "SELECT date AS date, COUNT(*) AS count FROM (
                 SELECT DISTINCT date(exif_timestamp) AS date FROM media_files WHERE exif_timestamp IS NOT NULL
                 UNION
-                SELECT DISTINCT date(create_time) AS date FROM media_files WHERE create_time IS NOT NULL AND exif_timestamp IS NULL
+                SELECT DISTINCT date(filename_timestamp) AS date FROM media_files WHERE filename_timestamp IS NOT NULL AND exif_timestamp IS NULL
                 UNION
-                SELECT DISTINCT date(modify_time) AS date FROM media_files WHERE modify_time IS NOT NULL AND exif_timestamp IS NULL AND create_time IS NULL
+                SELECT DISTINCT date(create_time) AS date FROM media_files WHERE create_time IS NOT NULL AND exif_timestamp IS NULL AND filename_timestamp IS NULL
+                UNION
+                SELECT DISTINCT date(modify_time) AS date FROM media_files WHERE modify_time IS NOT NULL AND exif_timestamp IS NULL AND filename_timestamp IS NULL AND create_time IS NULL
             ) GROUP BY date ORDER BY date DESC"
         );
 
         let sqlx_query = sqlx::query_as::<_, DateInfo>(&query);
 
-        sqlx_query.fetch_all(self.db.get_pool()).await
+        sqlx_query.fetch_all(self.db.read_pool()).await
+    }
+
+    /// Files grouped into day/week/month/year buckets by effective time
+    /// (EXIF > filename > create > modify), newest bucket first. Weeks start on `week_start`.
+    pub async fn timeline(
+        &self,
+        granularity: Granularity,
+        query: &FileQuery,
+        week_start: Weekday,
+    ) -> Result<Vec<TimelineBucket>, sqlx::Error> {
+        // 时间线不分页，只取筛选条件
+        let filter = query.filter_sql();
+        let sql = format!(
+            "SELECT bucket AS start, COUNT(*) AS count, MAX(first_id) AS first_id, MAX(last_id) AS last_id FROM (
+                SELECT bucket,
+                    FIRST_VALUE(id) OVER (PARTITION BY bucket ORDER BY ts ASC, id ASC) AS first_id,
+                    FIRST_VALUE(id) OVER (PARTITION BY bucket ORDER BY ts DESC, id DESC) AS last_id
+                FROM (
+                    SELECT id, ts, {} AS bucket FROM (
+                        SELECT id, COALESCE(exif_timestamp, filename_timestamp, create_time, modify_time) AS ts
+                        FROM media_files WHERE 1=1{}
+                    ) WHERE ts IS NOT NULL
+                )
+            ) GROUP BY bucket ORDER BY bucket DESC",
+            granularity.sql_bucket("ts"),
+            filter.conditions
+        );
+
+        let mut sqlx_query = sqlx::query_as::<_, TimelineBucket>(&sql);
+        if granularity == Granularity::Week {
+            sqlx_query = sqlx_query.bind(week_start.num_days_from_sunday() as i64);
+        }
+        for param in &filter.params {
+            sqlx_query = sqlx_query.bind(param.as_str());
+        }
+        sqlx_query.fetch_all(self.db.read_pool()).await
     }
 
     /// Insert or update a media file
-    /// Uses ON CONFLICT(file_path) to preserve stable ids across rescans
+    /// Uses UPSERT_CONFLICT (path_key) to preserve stable ids across rescans
     pub async fn upsert(&self, file: &MediaFile) -> Result<(), sqlx::Error> {
-        let now = Utc::now().naive_utc();
+        let mut conn = self.db.get_pool().acquire().await?;
+        upsert_media_file(&mut conn, file).await
+    }
 
-        sqlx::query(
-            "INSERT INTO media_files (
-                id, file_path, file_name, file_type, mime_type, file_size,
-                width, height, exif_timestamp, exif_timezone_offset,
-                create_time, modify_time, last_scanned,
-                camera_make, camera_model, lens_model,
-                exposure_time, aperture, iso, focal_length,
-                duration, video_codec, thumbnail_generated,
-                gps_latitude, gps_longitude
-            ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
-            ON CONFLICT(file_path) DO UPDATE SET
-                file_name = excluded.file_name,
-                file_type = excluded.file_type,
-                mime_type = excluded.mime_type,
-                file_size = excluded.file_size,
-                width = excluded.width,
-                height = excluded.height,
-                exif_timestamp = excluded.exif_timestamp,
-                exif_timezone_offset = excluded.exif_timezone_offset,
-                create_time = excluded.create_time,
-                modify_time = excluded.modify_time,
-                last_scanned = excluded.last_scanned,
-                camera_make = excluded.camera_make,
-                camera_model = excluded.camera_model,
-                lens_model = excluded.lens_model,
-                exposure_time = excluded.exposure_time,
-                aperture = excluded.aperture,
-                iso = excluded.iso,
-                focal_length = excluded.focal_length,
-                duration = excluded.duration,
-                video_codec = excluded.video_codec,
-                thumbnail_generated = excluded.thumbnail_generated,
-                gps_latitude = excluded.gps_latitude,
-                gps_longitude = excluded.gps_longitude"
+    /// Apply a user edit if the stored version still equals `expected_version`.
+    /// The version is incremented on success; a mismatch returns the current file.
+    pub async fn update_edits(
+        &self,
+        id: &str,
+        expected_version: i64,
+        edit: &MediaFileEdit,
+    ) -> Result<EditOutcome, sqlx::Error> {
+        let mut tx = self.db.get_pool().begin().await?;
+        let updated = sqlx::query_as::<_, MediaFile>(
+            "UPDATE media_files SET
+                title = CASE WHEN ? THEN ? ELSE title END,
+                description = CASE WHEN ? THEN ? ELSE description END,
+                version = version + 1,
+                updated_at = ?
+            WHERE id = ? AND version = ?
+            RETURNING *",
         )
-        .bind(&file.id)
-        .bind(&file.file_path)
-        .bind(&file.file_name)
-        .bind(&file.file_type)
-        .bind(&file.mime_type)
-        .bind(file.file_size)
-        .bind(file.width)
-        .bind(file.height)
-        .bind(file.exif_timestamp)
-        .bind(&file.exif_timezone_offset)
-        .bind(file.create_time)
-        .bind(file.modify_time)
-        .bind(now)
-        .bind(&file.camera_make)
-        .bind(&file.camera_model)
-        .bind(&file.lens_model)
-        .bind(&file.exposure_time)
-        .bind(&file.aperture)
-        .bind(file.iso)
-        .bind(&file.focal_length)
-        .bind(file.duration)
-        .bind(&file.video_codec)
-        .bind(if file.thumbnail_generated { 1 } else { 0 })
-        .bind(file.gps_latitude)
-        .bind(file.gps_longitude)
-        .execute(self.db.get_pool())
+        .bind(edit.title.is_some())
+        .bind(edit.title.clone().flatten())
+        .bind(edit.description.is_some())
+        .bind(edit.description.clone().flatten())
+        .bind(Utc::now().naive_utc())
+        .bind(id)
+        .bind(expected_version)
+        .fetch_optional(&mut *tx)
         .await?;
+        if let Some(file) = updated {
+            log_media_changes(&mut tx, &[(file.id.clone(), ChangeKind::Updated)]).await?;
+            tx.commit().await?;
+            return Ok(EditOutcome::Updated(file));
+        }
+        drop(tx);
 
-        Ok(())
+        Ok(match self.find_by_id(id).await? {
+            Some(current) => EditOutcome::Conflict(current),
+            None => EditOutcome::NotFound,
+        })
     }
 
     /// Delete a media file by ID
     pub async fn delete_by_id(&self, id: &str) -> Result<bool, sqlx::Error> {
-        let result = sqlx::query("DELETE FROM media_files WHERE id = ?")
-            .bind(id)
-            .execute(self.db.get_pool())
-            .await?;
+        let mut conn = self.db.get_pool().acquire().await?;
+        delete_media_file(&mut conn, id).await
+    }
 
-        Ok(result.rows_affected() > 0)
+    /// Stored timeline JSON of a video, only if extracted from the given file version
+    pub async fn find_timeline(
+        &self,
+        id: &str,
+        modify_time: Option<NaiveDateTime>,
+    ) -> Result<Option<String>, sqlx::Error> {
+        let row: Option<(String,)> = sqlx::query_as(
+            "SELECT timeline FROM video_timelines WHERE file_id = ? AND modify_time IS ?",
+        )
+        .bind(id)
+        .bind(modify_time)
+        .fetch_optional(self.db.get_pool())
+        .await?;
+        Ok(row.map(|(timeline,)| timeline))
+    }
+
+    /// Store the timeline JSON of a video extracted from the given file version
+    pub async fn save_timeline(
+        &self,
+        id: &str,
+        modify_time: Option<NaiveDateTime>,
+        timeline: &str,
+    ) -> Result<(), sqlx::Error> {
+        let mut conn = self.db.get_pool().acquire().await?;
+        save_video_timeline(&mut conn, id, modify_time, timeline).await
+    }
+
+    /// Top-level images of a multi-image file, ordered by index; empty for single-image files
+    pub async fn find_sub_images(&self, id: &str) -> Result<Vec<MediaSubImage>, sqlx::Error> {
+        sqlx::query_as(
+            "SELECT item_index, width, height, is_primary FROM media_sub_images WHERE file_id = ? ORDER BY item_index",
+        )
+        .bind(id)
+        .fetch_all(self.db.read_pool())
+        .await
+    }
+
+    /// Replace the sub-images of the file at `file_path`.
+    /// Keyed by path because batch_upsert keeps the existing id of a rescanned file.
+    pub async fn replace_sub_images(
+        &self,
+        file_path: &str,
+        sub_images: &[MediaSubImage],
+    ) -> Result<(), sqlx::Error> {
+        let mut tx = self.db.get_pool().begin().await?;
+        replace_media_sub_images(&mut tx, file_path, sub_images).await?;
+        tx.commit().await
+    }
+
+    /// Replace the attributes of `source` for the file at `file_path`; other sources are kept.
+    /// Keyed by path for the same reason as replace_sub_images.
+    pub async fn replace_attributes(
+        &self,
+        file_path: &str,
+        source: &str,
+        attributes: &[MediaAttribute],
+    ) -> Result<(), sqlx::Error> {
+        let mut tx = self.db.get_pool().begin().await?;
+        replace_media_attributes(&mut tx, file_path, source, attributes).await?;
+        tx.commit().await
+    }
+
+    /// Random sample of (id, file_path) pairs, used to spot-check for deleted files
+    pub async fn sample_paths(&self, limit: usize) -> Result<Vec<(String, String)>, sqlx::Error> {
+        sqlx::query_as("SELECT id, file_path FROM media_files ORDER BY RANDOM() LIMIT ?")
+            .bind(limit as i64)
+            .fetch_all(self.db.get_pool())
+            .await
     }
 
     /// Delete files not in the given path list using batch DELETE
@@ -225,13 +307,17 @@ impl<'a> MediaFileRepository<'a> {
         use sqlx::QueryBuilder;
         use sqlx::Sqlite;
 
+        let mut tx = self.db.get_pool().begin().await?;
+
         // 如果没有现有文件，删除所有记录
         if existing_paths.is_empty() {
-            let result = sqlx::query("DELETE FROM media_files WHERE last_scanned IS NOT NULL")
-                .execute(self.db.get_pool())
+            let deleted: Vec<String> = sqlx::query_scalar("DELETE FROM media_files WHERE last_scanned IS NOT NULL RETURNING id")
+                .fetch_all(&mut *tx)
                 .await?;
-            tracing::debug!("delete_missing: deleted {} files (all)", result.rows_affected());
-            return Ok(result.rows_affected());
+            log_deleted(&mut tx, deleted.iter()).await?;
+            tx.commit().await?;
+            tracing::debug!("delete_missing: deleted {} files (all)", deleted.len());
+            return Ok(deleted.len() as u64);
         }
 
         // SQLite parameter limit: 32766
@@ -242,64 +328,333 @@ impl<'a> MediaFileRepository<'a> {
         let mut total_deleted = 0u64;
 
         // Process in batches to stay within SQLite parameter limits
-        for chunk in existing_paths.chunks(MAX_PATHS) {
+        let keys: Vec<String> = existing_paths.iter().map(|p| path_key(p)).collect();
+        for chunk in keys.chunks(MAX_PATHS) {
             let mut query_builder: QueryBuilder<'_, Sqlite> = QueryBuilder::new(
-                "DELETE FROM media_files WHERE last_scanned IS NOT NULL AND file_path NOT IN "
+                "DELETE FROM media_files WHERE last_scanned IS NOT NULL AND path_key NOT IN "
             );
 
-            query_builder.push_tuples(chunk.iter(), |mut b, path| {
-                b.push_bind(path.as_str());
+            query_builder.push_tuples(chunk.iter(), |mut b, key| {
+                b.push_bind(key.as_str());
             });
+            query_builder.push(" RETURNING id");
 
-            let query = query_builder.build();
-            let result = query.execute(self.db.get_pool()).await?;
-            total_deleted += result.rows_affected();
+            let deleted: Vec<String> = query_builder.build_query_scalar().fetch_all(&mut *tx).await?;
+            log_deleted(&mut tx, deleted.iter()).await?;
+            total_deleted += deleted.len() as u64;
         }
+        tx.commit().await?;
 
         tracing::debug!("delete_missing: {} files deleted", total_deleted);
         Ok(total_deleted)
     }
 
-    /// Count files with filters
-    pub async fn count(
-        &self,
-        path_filter: Option<&str>,
-        file_type: Option<&str>,
-    ) -> Result<i64, sqlx::Error> {
-        let mut query = String::from("SELECT COUNT(*) FROM media_files WHERE 1=1");
-        let mut params: Vec<String> = Vec::new();
-
-        if let Some(path) = path_filter {
-            query.push_str(" AND file_path LIKE ?");
-            params.push(format!("%{}%", path));
+    /// Replace the `from` path prefix with `to` in every stored path (media files, RAW links,
+    /// directories) after the library mount point moved. Whole components only; rows already
+    /// re-added under the new path by a scan are dropped in favour of the old rows, which keep
+    /// their ids, titles and thumbnails. Returns the number of media files rewritten.
+    pub async fn rewrite_path_prefix(&self, from: &str, to: &str) -> Result<u64, sqlx::Error> {
+        self.move_directory(from, to, &[]).await
+    }
+
+    /// `rewrite_path_prefix` for a directory moved within the library. In the same transaction the
+    /// rows of `directories` (the moved directory under its new parent and name, and the parents
+    /// created for it) are added, or get their parent and name updated when they exist.
+    pub async fn move_directory(&self, from: &str, to: &str, directories: &[DirectoryEntry]) -> Result<u64, sqlx::Error> {
+        let from = from.trim_end_matches('/');
+        let to = to.trim_end_matches('/');
+        if from.is_empty() || from == to {
+            return Ok(0);
         }
+        let mut tx = self.db.get_pool().begin().await?;
 
-        if let Some(ft) = file_type {
-            if ft != "all" {
-                query.push_str(" AND file_type = ?");
-                params.push(ft.to_string());
+        // (表, 列, 是否唯一)；path_key 按 NFC 形式替换
+        let columns = [
+            ("media_files", "file_path", true),
+            ("media_files", "raw_path", false),
+            ("directories", "path", true),
+            ("directories", "parent_path", false),
+            ("directories", "cover_path", false),
+        ];
+        let mut rewritten = 0;
+        for (table, column, unique) in columns {
+            let matches = format!("({column} = ?1 OR substr({column}, 1, length(?1) + 1) = ?1 || '/')");
+            let replaced = format!("?2 || substr({column}, length(?1) + 1)");
+            if (table, column) == ("media_files", "file_path") {
+                // 文件路径变化需要记入变更日志，其他列只是附属信息
+                let dropped: Vec<String> = sqlx::query_scalar(&format!(
+                    "DELETE FROM {table} WHERE {column} IN (SELECT {replaced} FROM {table} WHERE {matches}) RETURNING id"
+                ))
+                .bind(from)
+                .bind(to)
+                .fetch_all(&mut *tx)
+                .await?;
+                log_deleted(&mut tx, dropped.iter()).await?;
+
+                let moved: Vec<String> =
+                    sqlx::query_scalar(&format!("UPDATE {table} SET {column} = {replaced} WHERE {matches} RETURNING id"))
+                        .bind(from)
+                        .bind(to)
+                        .fetch_all(&mut *tx)
+                        .await?;
+                let changes: Vec<(String, ChangeKind)> = moved.into_iter().map(|id| (id, ChangeKind::Updated)).collect();
+                log_media_changes(&mut tx, &changes).await?;
+                rewritten = changes.len() as u64;
+                continue;
+            }
+            if unique {
+                sqlx::query(&format!(
+                    "DELETE FROM {table} WHERE {column} IN (SELECT {replaced} FROM {table} WHERE {matches})"
+                ))
+                .bind(from)
+                .bind(to)
+                .execute(&mut *tx)
+                .await?;
             }
+            sqlx::query(&format!("UPDATE {table} SET {column} = {replaced} WHERE {matches}"))
+                .bind(from)
+                .bind(to)
+                .execute(&mut *tx)
+                .await?;
         }
 
-        let mut sqlx_query = sqlx::query_scalar::<_, i64>(&query);
-        for param in &params {
+        sqlx::query(
+            "UPDATE media_files SET path_key = ?2 || substr(path_key, length(?1) + 1)
+             WHERE path_key = ?1 OR substr(path_key, 1, length(?1) + 1) = ?1 || '/'",
+        )
+        .bind(path_key(from))
+        .bind(path_key(to))
+        .execute(&mut *tx)
+        .await?;
+
+        for entry in directories {
+            sqlx::query(
+                "INSERT INTO directories (path, parent_path, name, display_name, cover_path, is_valid)
+                 VALUES (?, ?, ?, ?, ?, 1)
+                 ON CONFLICT(path) DO UPDATE SET parent_path = excluded.parent_path, name = excluded.name",
+            )
+            .bind(&entry.path)
+            .bind(&entry.parent_path)
+            .bind(&entry.name)
+            .bind(&entry.display_name)
+            .bind(&entry.cover_path)
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        tx.commit().await?;
+        tracing::info!("Rewrote path prefix {} -> {} for {} files", from, to, rewritten);
+        Ok(rewritten)
+    }
+
+    /// Store the location of a file moved on disk: path, name, the name-derived date and the
+    /// paired RAW file. Everything else (id, metadata, edits) is kept. Returns false if the id is unknown.
+    pub async fn update_location(&self, file: &MediaFile) -> Result<bool, sqlx::Error> {
+        let mut tx = self.db.get_pool().begin().await?;
+        let result = sqlx::query(
+            "UPDATE media_files SET file_path = ?, path_key = ?, file_name = ?, filename_timestamp = ?, date_source = ?,
+             raw_path = ? WHERE id = ?",
+        )
+        .bind(&file.file_path)
+        .bind(path_key(&file.file_path))
+        .bind(&file.file_name)
+        .bind(file.filename_timestamp)
+        .bind(&file.date_source)
+        .bind(&file.raw_path)
+        .bind(&file.id)
+        .execute(&mut *tx)
+        .await?;
+        if result.rows_affected() == 0 {
+            return Ok(false);
+        }
+        log_media_changes(&mut tx, &[(file.id.clone(), ChangeKind::Updated)]).await?;
+        tx.commit().await?;
+        Ok(true)
+    }
+
+    /// Count files matching the filters of `query` (sort and pagination are ignored)
+    pub async fn count(&self, query: &FileQuery) -> Result<i64, sqlx::Error> {
+        let filter = query.filter_sql();
+        let sql = format!("SELECT COUNT(*) FROM media_files WHERE 1=1{}", filter.conditions);
+
+        let mut sqlx_query = sqlx::query_scalar::<_, i64>(&sql);
+        for param in &filter.params {
             sqlx_query = sqlx_query.bind(param.as_str());
         }
 
-        sqlx_query.fetch_one(self.db.get_pool()).await
+        sqlx_query.fetch_one(self.db.read_pool()).await
     }
 
     /// Update thumbnail generated status
     pub async fn update_thumbnail_status(&self, id: &str, generated: bool) -> Result<(), sqlx::Error> {
-        sqlx::query("UPDATE media_files SET thumbnail_generated = ? WHERE id = ?")
-            .bind(if generated { 1 } else { 0 })
-            .bind(id)
-            .execute(self.db.get_pool())
+        let mut tx = self.db.get_pool().begin().await?;
+        update_media_thumbnail_status(&mut tx, id, generated).await?;
+        tx.commit().await
+    }
+
+    /// Store the exposure statistics computed from a generated thumbnail
+    pub async fn update_exposure(
+        &self,
+        id: &str,
+        mean_luminance: f64,
+        clipped_highlights: f64,
+        clipped_shadows: f64,
+    ) -> Result<(), sqlx::Error> {
+        let mut tx = self.db.get_pool().begin().await?;
+        // 同样的统计重新写入时不记变更
+        let result = sqlx::query(
+            "UPDATE media_files SET mean_luminance = ?1, clipped_highlights = ?2, clipped_shadows = ?3
+             WHERE id = ?4 AND (mean_luminance IS NOT ?1 OR clipped_highlights IS NOT ?2 OR clipped_shadows IS NOT ?3)",
+        )
+        .bind(mean_luminance)
+        .bind(clipped_highlights)
+        .bind(clipped_shadows)
+        .bind(id)
+        .execute(&mut *tx)
+        .await?;
+        if result.rows_affected() > 0 {
+            log_media_changes(&mut tx, &[(id.to_string(), ChangeKind::Updated)]).await?;
+        }
+        tx.commit().await
+    }
+
+    /// Ids of images without exposure statistics (thumbnails cached before they were recorded)
+    pub async fn find_image_ids_missing_exposure(&self) -> Result<Vec<String>, sqlx::Error> {
+        sqlx::query_scalar("SELECT id FROM media_files WHERE file_type = 'image' AND mean_luminance IS NULL")
+            .fetch_all(self.db.get_pool())
+            .await
+    }
+
+    /// Full-text search over file names, titles and descriptions, best matches first.
+    /// Returns one page of files and the total number of matches.
+    pub async fn search_text(
+        &self,
+        query: &str,
+        page: i32,
+        page_size: i32,
+    ) -> Result<(Vec<MediaFile>, i64), sqlx::Error> {
+        let terms: Vec<&str> = query.split_whitespace().collect();
+        let offset = page * page_size;
+
+        // trigram 分词器至少需要 3 个字符；含更短词（如两个汉字）时退回 LIKE 子串匹配
+        if terms.iter().all(|term| term.chars().count() >= 3) {
+            let expression = terms
+                .iter()
+                .map(|term| format!("\"{}\"", term.replace('"', "\"\"")))
+                .collect::<Vec<_>>()
+                .join(" ");
+            let files = sqlx::query_as::<_, MediaFile>(
+                "SELECT m.* FROM media_files_fts f JOIN media_files m ON m.rowid = f.rowid
+                 WHERE media_files_fts MATCH ? ORDER BY f.rank LIMIT ? OFFSET ?",
+            )
+            .bind(&expression)
+            .bind(page_size)
+            .bind(offset)
+            .fetch_all(self.db.read_pool())
             .await?;
+            let total = sqlx::query_scalar("SELECT COUNT(*) FROM media_files_fts WHERE media_files_fts MATCH ?")
+                .bind(&expression)
+                .fetch_one(self.db.read_pool())
+                .await?;
+            return Ok((files, total));
+        }
+
+        let mut filter = String::from(" WHERE 1=1");
+        let mut params = Vec::with_capacity(terms.len() * 3);
+        for term in &terms {
+            filter.push_str(
+                " AND (file_name LIKE ? ESCAPE '\\' OR title LIKE ? ESCAPE '\\' OR description LIKE ? ESCAPE '\\')",
+            );
+            let escaped = term.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_");
+            let pattern = format!("%{}%", escaped);
+            params.extend([pattern.clone(), pattern.clone(), pattern]);
+        }
+
+        let files_sql = format!(
+            "SELECT * FROM media_files{} ORDER BY exif_timestamp DESC LIMIT {} OFFSET {}",
+            filter, page_size, offset
+        );
+        let mut files_query = sqlx::query_as::<_, MediaFile>(&files_sql);
+        for param in &params {
+            files_query = files_query.bind(param.as_str());
+        }
+        let files = files_query.fetch_all(self.db.read_pool()).await?;
+
+        let count_sql = format!("SELECT COUNT(*) FROM media_files{}", filter);
+        let mut count_query = sqlx::query_scalar::<_, i64>(&count_sql);
+        for param in &params {
+            count_query = count_query.bind(param.as_str());
+        }
+        let total = count_query.fetch_one(self.db.read_pool()).await?;
+        Ok((files, total))
+    }
 
+    /// Rebuild the full-text index from media_files (after a restore, whose rowids may differ)
+    pub async fn rebuild_search_index(&self) -> Result<(), sqlx::Error> {
+        sqlx::query("INSERT INTO media_files_fts (media_files_fts) VALUES ('rebuild')")
+            .execute(self.db.get_pool())
+            .await?;
         Ok(())
     }
 
+    /// Paths of all indexed files (consistency check)
+    pub async fn find_all_paths(&self) -> Result<Vec<String>, sqlx::Error> {
+        sqlx::query_scalar("SELECT file_path FROM media_files")
+            .fetch_all(self.db.get_pool())
+            .await
+    }
+
+    /// Get paths of files whose metadata field is NULL (used by targeted backfill)
+    pub async fn find_paths_missing_field(&self, field: MetadataField) -> Result<Vec<String>, sqlx::Error> {
+        // column_name() comes from a fixed whitelist, safe to interpolate
+        let mut query = format!("SELECT file_path FROM media_files WHERE {} IS NULL", field.column_name());
+        if field.file_type().is_some() {
+            query.push_str(" AND file_type = ?");
+        }
+
+        let mut sqlx_query = sqlx::query_scalar::<_, String>(&query);
+        if let Some(ft) = field.file_type() {
+            sqlx_query = sqlx_query.bind(ft);
+        }
+
+        sqlx_query.fetch_all(self.db.get_pool()).await
+    }
+
+    /// Replace all RAW+JPEG links with the given (display_path, raw_path) pairs.
+    /// Links not present in `pairs` are cleared; returns the number of files linked.
+    /// Files whose link changed are recorded in the change log.
+    pub async fn sync_raw_pairs(&self, pairs: &[(String, String)]) -> Result<u64, sqlx::Error> {
+        let mut tx = self.db.get_pool().begin().await?;
+
+        let before: HashMap<String, String> = sqlx::query_as("UPDATE media_files SET raw_path = NULL WHERE raw_path IS NOT NULL RETURNING id, raw_path")
+            .fetch_all(&mut *tx)
+            .await?
+            .into_iter()
+            .collect();
+
+        let mut after: HashMap<String, String> = HashMap::with_capacity(pairs.len());
+        for (display_path, raw_path) in pairs {
+            let ids: Vec<String> = sqlx::query_scalar("UPDATE media_files SET raw_path = ? WHERE file_path = ? RETURNING id")
+                .bind(raw_path)
+                .bind(display_path)
+                .fetch_all(&mut *tx)
+                .await?;
+            after.extend(ids.into_iter().map(|id| (id, raw_path.clone())));
+        }
+
+        let mut changes: Vec<(String, ChangeKind)> = after
+            .iter()
+            .filter(|(id, raw_path)| before.get(*id) != Some(*raw_path))
+            .chain(before.iter().filter(|(id, _)| !after.contains_key(*id)))
+            .map(|(id, _)| (id.clone(), ChangeKind::Updated))
+            .collect();
+        changes.sort_by(|a, b| a.0.cmp(&b.0));
+        log_media_changes(&mut tx, &changes).await?;
+
+        tx.commit().await?;
+        Ok(after.len() as u64)
+    }
+
     /// Check if database is empty (no files scanned yet)
     pub async fn is_empty(&self) -> Result<bool, sqlx::Error> {
         let count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM media_files")
@@ -308,166 +663,104 @@ impl<'a> MediaFileRepository<'a> {
         Ok(count == 0)
     }
 
-    /// Batch check file existence using single SQL query with IN clause
-    /// Uses QueryBuilder for efficient bulk SELECT
-    pub async fn batch_find_by_paths_batch(&self, paths: &[PathBuf]) -> Result<Vec<MediaFile>, sqlx::Error> {
+    /// Existence check for scans: returns path key (see `path_key`) -> stored state for
+    /// every given path already in the database. One IN query per 32766 paths on the indexed
+    /// path_key column, reading only the columns the scan compares.
+    pub async fn find_existing(&self, paths: &[PathBuf]) -> Result<HashMap<String, ExistingFile>, sqlx::Error> {
         use sqlx::QueryBuilder;
-        use sqlx::Sqlite;
-
-        if paths.is_empty() {
-            return Ok(vec![]);
-        }
 
-        // For very large batches, we need to chunk to avoid SQLite parameter limits
         // SQLite: 32766 parameters max, each path uses 1 parameter
-        const MAX_PARAMS: usize = 32766;
-        const MAX_PATHS: usize = MAX_PARAMS;
+        const MAX_PATHS: usize = 32766;
 
-        // Collect owned strings first to avoid lifetime issues with chunks
-        let path_strings: Vec<String> = paths.iter()
-            .map(|p| p.to_string_lossy().to_string())
+        let keys: Vec<String> = paths.iter()
+            .map(|p| path_key(&p.to_string_lossy()))
             .collect();
 
-        let mut all_files: Vec<MediaFile> = Vec::new();
-
-        // Use standard slice chunks
-        for chunk in path_strings.chunks(MAX_PATHS) {
+        let mut existing = HashMap::with_capacity(keys.len());
+        for chunk in keys.chunks(MAX_PATHS) {
             let mut query_builder: QueryBuilder<'_, Sqlite> = QueryBuilder::new(
-                "SELECT * FROM media_files WHERE file_path IN "
+                "SELECT path_key, modify_time, pending_extraction, file_size, content_hash FROM media_files WHERE path_key IN "
             );
-
-            query_builder.push_tuples(chunk.iter(), |mut b, path| {
-                b.push_bind(path.as_str());
+            query_builder.push_tuples(chunk.iter(), |mut b, key| {
+                b.push_bind(key.as_str());
             });
 
-            let query = query_builder.build_query_as::<MediaFile>();
-            let files = query.fetch_all(self.db.get_pool()).await?;
-            all_files.extend(files);
+            let rows: Vec<ExistingFileRow> = query_builder
+                .build_query_as()
+                .fetch_all(self.db.get_pool())
+                .await?;
+            existing.extend(rows.into_iter().map(|(key, modify_time, pending_extraction, file_size, content_hash)| {
+                (key, ExistingFile { modify_time, pending_extraction, file_size, content_hash })
+            }));
         }
 
-        tracing::debug!("batch_find_by_paths_batch: {} paths, {} files found",
-            paths.len(), all_files.len());
-
-        Ok(all_files)
+        tracing::debug!("find_existing: {} paths, {} files found", paths.len(), existing.len());
+        Ok(existing)
     }
 
-    /// Batch upsert files using QueryBuilder for efficient bulk INSERT
-    /// Uses ON CONFLICT(file_path) DO UPDATE to preserve stable ids across rescans
-    pub async fn batch_upsert(&self, files: &[MediaFile]) -> Result<(), sqlx::Error> {
+    /// Flag files for metadata extraction without touching their metadata (quick scans of
+    /// modified files); returns the number of rows flagged
+    pub async fn mark_pending_extraction(&self, paths: &[PathBuf]) -> Result<u64, sqlx::Error> {
         use sqlx::QueryBuilder;
-        use sqlx::Sqlite;
 
-        if files.is_empty() {
-            return Ok(());
-        }
-
-        // SQLite parameter limit: 32766
-        // Each file uses 25 parameters, so max ~1310 files per batch
-        const MAX_PARAMS: usize = 32766;
-        const FIELDS_PER_FILE: usize = 25;
-        const MAX_FILES_PER_BATCH: usize = MAX_PARAMS / FIELDS_PER_FILE;
+        const MAX_PATHS: usize = 32766 - 1;
 
-        let mut tx = self.db.get_pool().begin().await?;
+        let keys: Vec<String> = paths.iter()
+            .map(|p| path_key(&p.to_string_lossy()))
+            .collect();
         let now = Utc::now().naive_utc();
 
-        // Process in batches to stay within SQLite parameter limits
-        for chunk in files.chunks(MAX_FILES_PER_BATCH) {
+        let mut flagged = 0u64;
+        for chunk in keys.chunks(MAX_PATHS) {
             let mut query_builder: QueryBuilder<'_, Sqlite> = QueryBuilder::new(
-                "INSERT INTO media_files (
-                    id, file_path, file_name, file_type, mime_type, file_size,
-                    width, height, exif_timestamp, exif_timezone_offset,
-                    create_time, modify_time, last_scanned,
-                    camera_make, camera_model, lens_model,
-                    exposure_time, aperture, iso, focal_length,
-                    duration, video_codec, thumbnail_generated,
-                    gps_latitude, gps_longitude
-                ) "
+                "UPDATE media_files SET pending_extraction = 1, last_scanned = "
             );
-
-            query_builder.push_values(chunk.iter(), |mut b, file| {
-                b.push_bind(&file.id)
-                    .push_bind(&file.file_path)
-                    .push_bind(&file.file_name)
-                    .push_bind(&file.file_type)
-                    .push_bind(&file.mime_type)
-                    .push_bind(file.file_size)
-                    .push_bind(file.width)
-                    .push_bind(file.height)
-                    .push_bind(file.exif_timestamp)
-                    .push_bind(file.exif_timezone_offset.clone())
-                    .push_bind(file.create_time)
-                    .push_bind(file.modify_time)
-                    .push_bind(now)
-                    .push_bind(file.camera_make.clone())
-                    .push_bind(file.camera_model.clone())
-                    .push_bind(file.lens_model.clone())
-                    .push_bind(file.exposure_time.clone())
-                    .push_bind(file.aperture.clone())
-                    .push_bind(file.iso)
-                    .push_bind(file.focal_length.clone())
-                    .push_bind(file.duration)
-                    .push_bind(file.video_codec.clone())
-                    .push_bind(if file.thumbnail_generated { 1 } else { 0 })
-                    .push_bind(file.gps_latitude)
-                    .push_bind(file.gps_longitude);
+            query_builder.push_bind(now);
+            query_builder.push(" WHERE path_key IN ");
+            query_builder.push_tuples(chunk.iter(), |mut b, key| {
+                b.push_bind(key.as_str());
             });
+            flagged += query_builder.build().execute(self.db.get_pool()).await?.rows_affected();
+        }
+        Ok(flagged)
+    }
 
-            // Append ON CONFLICT clause to preserve existing id on file_path conflict
-            query_builder.push(
-                " ON CONFLICT(file_path) DO UPDATE SET \
-                    file_name = excluded.file_name, \
-                    file_type = excluded.file_type, \
-                    mime_type = excluded.mime_type, \
-                    file_size = excluded.file_size, \
-                    width = excluded.width, \
-                    height = excluded.height, \
-                    exif_timestamp = excluded.exif_timestamp, \
-                    exif_timezone_offset = excluded.exif_timezone_offset, \
-                    create_time = excluded.create_time, \
-                    modify_time = excluded.modify_time, \
-                    last_scanned = excluded.last_scanned, \
-                    camera_make = excluded.camera_make, \
-                    camera_model = excluded.camera_model, \
-                    lens_model = excluded.lens_model, \
-                    exposure_time = excluded.exposure_time, \
-                    aperture = excluded.aperture, \
-                    iso = excluded.iso, \
-                    focal_length = excluded.focal_length, \
-                    duration = excluded.duration, \
-                    video_codec = excluded.video_codec, \
-                    thumbnail_generated = excluded.thumbnail_generated, \
-                    gps_latitude = excluded.gps_latitude, \
-                    gps_longitude = excluded.gps_longitude"
-            );
+    /// Number of files still waiting for metadata extraction after a quick scan
+    pub async fn count_pending_extraction(&self) -> Result<i64, sqlx::Error> {
+        sqlx::query_scalar("SELECT COUNT(*) FROM media_files WHERE pending_extraction = 1")
+            .fetch_one(self.db.get_pool())
+            .await
+    }
 
-            let query = query_builder.build();
-            query.execute(tx.as_mut()).await?;
+    /// Batch upsert files using QueryBuilder for efficient bulk INSERT
+    /// Uses UPSERT_CONFLICT (path_key) to preserve stable ids across rescans
+    pub async fn batch_upsert(&self, files: &[MediaFile]) -> Result<(), sqlx::Error> {
+        if files.is_empty() {
+            return Ok(());
         }
 
-        tx.commit().await?;
-
-        tracing::debug!("batch_upsert: {} files inserted/updated", files.len());
-        Ok(())
+        let mut tx = self.db.get_pool().begin().await?;
+        batch_upsert_media_files(&mut tx, files).await?;
+        tx.commit().await
     }
 
     /// Batch update last_scanned for files using QueryBuilder for efficient bulk UPDATE
     /// Uses UPDATE ... WHERE IN (...) for batch operation
-    pub async fn batch_touch(&self, paths: &[PathBuf]) -> Result<u64, sqlx::Error> {
+    pub async fn batch_touch<'p>(&self, paths: impl IntoIterator<Item = &'p PathBuf>) -> Result<u64, sqlx::Error> {
         use sqlx::QueryBuilder;
         use sqlx::Sqlite;
 
-        if paths.is_empty() {
-            return Ok(0);
-        }
-
         // SQLite parameter limit: 32766
         // Each path uses 1 parameter for IN clause, plus 1 for last_scanned
         const MAX_PARAMS: usize = 32766;
         const MAX_PATHS: usize = MAX_PARAMS - 1;  // Reserve one for last_scanned
 
-        let path_strings: Vec<String> = paths.iter()
-            .map(|p| p.to_string_lossy().to_string())
+        let path_strings: Vec<String> = paths.into_iter()
+            .map(|p| path_key(&p.to_string_lossy()))
             .collect();
+        if path_strings.is_empty() {
+            return Ok(0);
+        }
 
         let mut total_updated = 0u64;
         let now = Utc::now().naive_utc();
@@ -478,7 +771,7 @@ impl<'a> MediaFileRepository<'a> {
                 "UPDATE media_files SET last_scanned = "
             );
             query_builder.push_bind(now);
-            query_builder.push(" WHERE file_path IN ");
+            query_builder.push(" WHERE path_key IN ");
 
             query_builder.push_tuples(chunk.iter(), |mut b, path| {
                 b.push_bind(path.as_str());
@@ -506,16 +799,16 @@ impl<'a> MediaFileRepository<'a> {
             return Ok(count as u64);
         }
 
-        // Get all file paths from database that have been scanned
+        // Get the path keys of all files in the database that have been scanned
         let all_db_files: Vec<String> = sqlx::query_scalar(
-            "SELECT file_path FROM media_files WHERE last_scanned IS NOT NULL"
+            "SELECT path_key FROM media_files WHERE last_scanned IS NOT NULL AND path_key IS NOT NULL"
         )
             .fetch_all(self.db.get_pool())
             .await?;
 
         // Convert existing_paths to owned Strings for HashSet
         let existing_set: HashSet<String> = existing_paths.iter()
-            .map(|p| p.to_string_lossy().to_string())
+            .map(|p| path_key(&p.to_string_lossy()))
             .collect();
 
         // Count files in DB but not in filesystem
@@ -527,6 +820,460 @@ impl<'a> MediaFileRepository<'a> {
     }
 }
 
+/// Assignments of the media file upserts when the file is already stored.
+/// 曝光统计来自缩略图而不是扫描：内容未变时保留，内容变化后清空，等新缩略图生成时重新计算。
+/// 描述以用户编辑为准，扫描（Takeout 旁车文件）只补全空描述
+macro_rules! upsert_assignments {
+    () => {
+        "file_name = excluded.file_name,
+            file_type = excluded.file_type,
+            mime_type = excluded.mime_type,
+            file_size = excluded.file_size,
+            width = excluded.width,
+            height = excluded.height,
+            exif_timestamp = excluded.exif_timestamp,
+            exif_timezone_offset = excluded.exif_timezone_offset,
+            create_time = excluded.create_time,
+            modify_time = excluded.modify_time,
+            last_scanned = excluded.last_scanned,
+            camera_make = excluded.camera_make,
+            camera_model = excluded.camera_model,
+            lens_model = excluded.lens_model,
+            exposure_time = excluded.exposure_time,
+            aperture = excluded.aperture,
+            iso = excluded.iso,
+            focal_length = excluded.focal_length,
+            duration = excluded.duration,
+            video_codec = excluded.video_codec,
+            thumbnail_generated = excluded.thumbnail_generated,
+            gps_latitude = excluded.gps_latitude,
+            gps_longitude = excluded.gps_longitude,
+            filename_timestamp = excluded.filename_timestamp,
+            date_source = excluded.date_source,
+            has_depth_map = excluded.has_depth_map,
+            has_portrait_matte = excluded.has_portrait_matte,
+            projection = excluded.projection,
+            hdr_format = excluded.hdr_format,
+            bit_depth = excluded.bit_depth,
+            color_primaries = excluded.color_primaries,
+            color_profile = excluded.color_profile,
+            content_hash = excluded.content_hash,
+            path_key = excluded.path_key,
+            pending_extraction = excluded.pending_extraction,
+            blur_score = excluded.blur_score,
+            orientation = excluded.orientation,
+            description = COALESCE(media_files.description, excluded.description),
+            mean_luminance = CASE WHEN media_files.content_hash IS excluded.content_hash THEN media_files.mean_luminance END,
+            clipped_highlights = CASE WHEN media_files.content_hash IS excluded.content_hash THEN media_files.clipped_highlights END,
+            clipped_shadows = CASE WHEN media_files.content_hash IS excluded.content_hash THEN media_files.clipped_shadows END"
+    };
+}
+
+/// Conflict clause of the media file upserts, keeping the stored id. Rows are matched by
+/// path_key, so a path reported in another Unicode form updates the stored row; a row keyed
+/// before path keys were normalized (non-NFC file_path) still matches by file_path and is re-keyed.
+const UPSERT_CONFLICT: &str = concat!(
+    " ON CONFLICT(path_key) DO UPDATE SET ",
+    upsert_assignments!(),
+    " ON CONFLICT(file_path) DO UPDATE SET ",
+    upsert_assignments!()
+);
+
+/// Shared by MediaFileRepository::upsert and MediaFileTxRepository::upsert
+async fn upsert_media_file(conn: &mut SqliteConnection, file: &MediaFile) -> Result<(), sqlx::Error> {
+    let now = Utc::now().naive_utc();
+    // 与 UPSERT_CONFLICT 相同的匹配方式
+    let existing_id: Option<String> = sqlx::query_scalar("SELECT id FROM media_files WHERE path_key = ? OR file_path = ?")
+        .bind(path_key(&file.file_path))
+        .bind(&file.file_path)
+        .fetch_optional(&mut *conn)
+        .await?;
+
+    sqlx::query(&format!(
+        "INSERT INTO media_files (
+            id, file_path, file_name, file_type, mime_type, file_size,
+            width, height, exif_timestamp, exif_timezone_offset,
+            create_time, modify_time, last_scanned,
+            camera_make, camera_model, lens_model,
+            exposure_time, aperture, iso, focal_length,
+            duration, video_codec, thumbnail_generated,
+            gps_latitude, gps_longitude,
+            filename_timestamp, date_source,
+            has_depth_map, has_portrait_matte, projection,
+            hdr_format, bit_depth, color_primaries, color_profile, content_hash, path_key,
+            pending_extraction, blur_score, orientation, description
+        ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?){}",
+        UPSERT_CONFLICT
+    ))
+    .bind(&file.id)
+    .bind(&file.file_path)
+    .bind(&file.file_name)
+    .bind(&file.file_type)
+    .bind(&file.mime_type)
+    .bind(file.file_size)
+    .bind(file.width)
+    .bind(file.height)
+    .bind(file.exif_timestamp)
+    .bind(&file.exif_timezone_offset)
+    .bind(file.create_time)
+    .bind(file.modify_time)
+    .bind(now)
+    .bind(&file.camera_make)
+    .bind(&file.camera_model)
+    .bind(&file.lens_model)
+    .bind(&file.exposure_time)
+    .bind(&file.aperture)
+    .bind(file.iso)
+    .bind(&file.focal_length)
+    .bind(file.duration)
+    .bind(&file.video_codec)
+    .bind(if file.thumbnail_generated { 1 } else { 0 })
+    .bind(file.gps_latitude)
+    .bind(file.gps_longitude)
+    .bind(file.filename_timestamp)
+    .bind(&file.date_source)
+    .bind(file.has_depth_map)
+    .bind(file.has_portrait_matte)
+    .bind(&file.projection)
+    .bind(&file.hdr_format)
+    .bind(file.bit_depth)
+    .bind(&file.color_primaries)
+    .bind(&file.color_profile)
+    .bind(&file.content_hash)
+    .bind(path_key(&file.file_path))
+    .bind(file.pending_extraction)
+    .bind(file.blur_score)
+    .bind(file.orientation)
+    .bind(&file.description)
+    .execute(&mut *conn)
+    .await?;
+
+    let change = match existing_id {
+        Some(id) => (id, ChangeKind::Updated),
+        None => (file.id.clone(), ChangeKind::Created),
+    };
+    log_media_changes(conn, &[change]).await
+}
+
+/// Shared by MediaFileRepository::batch_upsert and MediaFileTxRepository::batch_upsert
+async fn batch_upsert_media_files(conn: &mut SqliteConnection, files: &[MediaFile]) -> Result<(), sqlx::Error> {
+    use sqlx::QueryBuilder;
+
+    // SQLite parameter limit: 32766
+    // Each file uses 40 parameters, so max ~819 files per batch
+    const MAX_PARAMS: usize = 32766;
+    const FIELDS_PER_FILE: usize = 40;
+    const MAX_FILES_PER_BATCH: usize = MAX_PARAMS / FIELDS_PER_FILE;
+
+    let now = Utc::now().naive_utc();
+
+    // Process in batches to stay within SQLite parameter limits
+    for chunk in files.chunks(MAX_FILES_PER_BATCH) {
+        // 已入库的文件保留原 id，变更日志记为 updated
+        let paths: Vec<&str> = chunk.iter().map(|file| file.file_path.as_str()).collect();
+        let existing_ids = find_ids_by_path(&mut *conn, &paths).await?;
+
+        let mut query_builder: QueryBuilder<'_, Sqlite> = QueryBuilder::new(
+            "INSERT INTO media_files (
+                id, file_path, file_name, file_type, mime_type, file_size,
+                width, height, exif_timestamp, exif_timezone_offset,
+                create_time, modify_time, last_scanned,
+                camera_make, camera_model, lens_model,
+                exposure_time, aperture, iso, focal_length,
+                duration, video_codec, thumbnail_generated,
+                gps_latitude, gps_longitude,
+                filename_timestamp, date_source,
+                has_depth_map, has_portrait_matte, projection,
+                hdr_format, bit_depth, color_primaries, color_profile, content_hash, path_key,
+                pending_extraction, blur_score, orientation, description
+            ) "
+        );
+
+        query_builder.push_values(chunk.iter(), |mut b, file| {
+            b.push_bind(&file.id)
+                .push_bind(&file.file_path)
+                .push_bind(&file.file_name)
+                .push_bind(&file.file_type)
+                .push_bind(&file.mime_type)
+                .push_bind(file.file_size)
+                .push_bind(file.width)
+                .push_bind(file.height)
+                .push_bind(file.exif_timestamp)
+                .push_bind(file.exif_timezone_offset.clone())
+                .push_bind(file.create_time)
+                .push_bind(file.modify_time)
+                .push_bind(now)
+                .push_bind(file.camera_make.clone())
+                .push_bind(file.camera_model.clone())
+                .push_bind(file.lens_model.clone())
+                .push_bind(file.exposure_time.clone())
+                .push_bind(file.aperture.clone())
+                .push_bind(file.iso)
+                .push_bind(file.focal_length.clone())
+                .push_bind(file.duration)
+                .push_bind(file.video_codec.clone())
+                .push_bind(if file.thumbnail_generated { 1 } else { 0 })
+                .push_bind(file.gps_latitude)
+                .push_bind(file.gps_longitude)
+                .push_bind(file.filename_timestamp)
+                .push_bind(file.date_source.clone())
+                .push_bind(file.has_depth_map)
+                .push_bind(file.has_portrait_matte)
+                .push_bind(file.projection.clone())
+                .push_bind(file.hdr_format.clone())
+                .push_bind(file.bit_depth)
+                .push_bind(file.color_primaries.clone())
+                .push_bind(file.color_profile.clone())
+                .push_bind(file.content_hash.clone())
+                .push_bind(path_key(&file.file_path))
+                .push_bind(file.pending_extraction)
+                .push_bind(file.blur_score)
+                .push_bind(file.orientation)
+                .push_bind(&file.description);
+        });
+
+        query_builder.push(UPSERT_CONFLICT);
+
+        let query = query_builder.build();
+        query.execute(&mut *conn).await?;
+
+        let changes: Vec<(String, ChangeKind)> = chunk
+            .iter()
+            .map(|file| match existing_ids.get(&path_key(&file.file_path)) {
+                Some(id) => (id.clone(), ChangeKind::Updated),
+                None => (file.id.clone(), ChangeKind::Created),
+            })
+            .collect();
+        log_media_changes(&mut *conn, &changes).await?;
+    }
+
+    tracing::debug!("batch_upsert: {} files inserted/updated", files.len());
+    Ok(())
+}
+
+async fn delete_media_file(conn: &mut SqliteConnection, id: &str) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query("DELETE FROM media_files WHERE id = ?")
+        .bind(id)
+        .execute(&mut *conn)
+        .await?;
+
+    if result.rows_affected() == 0 {
+        return Ok(false);
+    }
+    log_media_changes(conn, &[(id.to_string(), ChangeKind::Deleted)]).await?;
+    Ok(true)
+}
+
+/// path key -> id of the stored files among `paths`, matched as UPSERT_CONFLICT does
+/// (by path_key, or by file_path for rows keyed before path keys were normalized)
+async fn find_ids_by_path(conn: &mut SqliteConnection, paths: &[&str]) -> Result<HashMap<String, String>, sqlx::Error> {
+    use sqlx::QueryBuilder;
+
+    // 每个路径 2 个参数
+    const MAX_PATHS: usize = 32766 / 2;
+
+    let mut ids = HashMap::with_capacity(paths.len());
+    for chunk in paths.chunks(MAX_PATHS) {
+        let mut query_builder: QueryBuilder<'_, Sqlite> =
+            QueryBuilder::new("SELECT file_path, id FROM media_files WHERE path_key IN ");
+        query_builder.push_tuples(chunk.iter(), |mut b, path| {
+            b.push_bind(path_key(path));
+        });
+        query_builder.push(" OR file_path IN ");
+        query_builder.push_tuples(chunk.iter(), |mut b, path| {
+            b.push_bind(*path);
+        });
+        let rows: Vec<(String, String)> = query_builder.build_query_as().fetch_all(&mut *conn).await?;
+        ids.extend(rows.into_iter().map(|(file_path, id)| (path_key(&file_path), id)));
+    }
+    Ok(ids)
+}
+
+/// Append entries to the media change log (see ChangeLogRepository), in the transaction of
+/// the write. Called by every repository method that creates or deletes media files or changes
+/// fields clients see; scan bookkeeping (last_scanned, pending_extraction) is not logged.
+async fn log_media_changes(conn: &mut SqliteConnection, changes: &[(String, ChangeKind)]) -> Result<(), sqlx::Error> {
+    use sqlx::QueryBuilder;
+
+    // 每条 3 个参数
+    const MAX_CHANGES: usize = 32766 / 3;
+
+    let now = Utc::now().naive_utc();
+    for chunk in changes.chunks(MAX_CHANGES) {
+        let mut query_builder: QueryBuilder<'_, Sqlite> =
+            QueryBuilder::new("INSERT INTO media_changes (file_id, change, changed_at) ");
+        query_builder.push_values(chunk.iter(), |mut b, (id, kind)| {
+            b.push_bind(id.as_str()).push_bind(kind.as_str()).push_bind(now);
+        });
+        query_builder.build().execute(&mut *conn).await?;
+    }
+    Ok(())
+}
+
+async fn log_deleted<'i>(conn: &mut SqliteConnection, ids: impl Iterator<Item = &'i String>) -> Result<(), sqlx::Error> {
+    let changes: Vec<(String, ChangeKind)> = ids.map(|id| (id.clone(), ChangeKind::Deleted)).collect();
+    log_media_changes(conn, &changes).await
+}
+
+async fn update_media_thumbnail_status(conn: &mut SqliteConnection, id: &str, generated: bool) -> Result<(), sqlx::Error> {
+    // 状态不变（重新生成缩略图）时不记变更
+    let result = sqlx::query("UPDATE media_files SET thumbnail_generated = ?1 WHERE id = ?2 AND thumbnail_generated IS NOT ?1")
+        .bind(if generated { 1 } else { 0 })
+        .bind(id)
+        .execute(&mut *conn)
+        .await?;
+    if result.rows_affected() > 0 {
+        log_media_changes(conn, &[(id.to_string(), ChangeKind::Updated)]).await?;
+    }
+    Ok(())
+}
+
+async fn save_video_timeline(
+    conn: &mut SqliteConnection,
+    id: &str,
+    modify_time: Option<NaiveDateTime>,
+    timeline: &str,
+) -> Result<(), sqlx::Error> {
+    sqlx::query("INSERT OR REPLACE INTO video_timelines (file_id, modify_time, timeline) VALUES (?, ?, ?)")
+        .bind(id)
+        .bind(modify_time)
+        .bind(timeline)
+        .execute(conn)
+        .await?;
+    Ok(())
+}
+
+async fn replace_media_sub_images(
+    conn: &mut SqliteConnection,
+    file_path: &str,
+    sub_images: &[MediaSubImage],
+) -> Result<(), sqlx::Error> {
+    sqlx::query("DELETE FROM media_sub_images WHERE file_id = (SELECT id FROM media_files WHERE file_path = ?)")
+        .bind(file_path)
+        .execute(&mut *conn)
+        .await?;
+    for sub_image in sub_images {
+        sqlx::query(
+            "INSERT INTO media_sub_images (file_id, item_index, width, height, is_primary) \
+             SELECT id, ?, ?, ?, ? FROM media_files WHERE file_path = ?",
+        )
+        .bind(sub_image.item_index)
+        .bind(sub_image.width)
+        .bind(sub_image.height)
+        .bind(sub_image.is_primary)
+        .bind(file_path)
+        .execute(&mut *conn)
+        .await?;
+    }
+    Ok(())
+}
+
+async fn replace_media_attributes(
+    conn: &mut SqliteConnection,
+    file_path: &str,
+    source: &str,
+    attributes: &[MediaAttribute],
+) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        "DELETE FROM media_file_attributes \
+         WHERE file_id = (SELECT id FROM media_files WHERE file_path = ?) AND source = ?",
+    )
+    .bind(file_path)
+    .bind(source)
+    .execute(&mut *conn)
+    .await?;
+    for attribute in attributes {
+        sqlx::query(
+            "INSERT OR REPLACE INTO media_file_attributes (file_id, key, value, source) \
+             SELECT id, ?, ?, ? FROM media_files WHERE file_path = ?",
+        )
+        .bind(&attribute.key)
+        .bind(&attribute.value)
+        .bind(source)
+        .bind(file_path)
+        .execute(&mut *conn)
+        .await?;
+    }
+    Ok(())
+}
+
+/// Transaction-scoped unit of work. Repository calls made through it are committed
+/// together by `commit`; dropping it without committing rolls everything back.
+///
+/// ```ignore
+/// let mut tx = RepositoryTx::begin(&db).await?;
+/// tx.media_files().upsert(&file).await?;
+/// tx.media_files().replace_sub_images(&file.file_path, &sub_images).await?;
+/// tx.commit().await?;
+/// ```
+pub struct RepositoryTx {
+    tx: Transaction<'static, Sqlite>,
+}
+
+impl RepositoryTx {
+    pub async fn begin(db: &DatabasePool) -> Result<Self, sqlx::Error> {
+        Ok(Self { tx: db.get_pool().begin().await? })
+    }
+
+    /// Media file writes within this transaction
+    pub fn media_files(&mut self) -> MediaFileTxRepository<'_> {
+        MediaFileTxRepository { conn: &mut self.tx }
+    }
+
+    pub async fn commit(self) -> Result<(), sqlx::Error> {
+        self.tx.commit().await
+    }
+
+    pub async fn rollback(self) -> Result<(), sqlx::Error> {
+        self.tx.rollback().await
+    }
+}
+
+/// Media file writes bound to a RepositoryTx (same semantics as MediaFileRepository)
+pub struct MediaFileTxRepository<'t> {
+    conn: &'t mut SqliteConnection,
+}
+
+impl MediaFileTxRepository<'_> {
+    pub async fn upsert(&mut self, file: &MediaFile) -> Result<(), sqlx::Error> {
+        upsert_media_file(self.conn, file).await
+    }
+
+    pub async fn batch_upsert(&mut self, files: &[MediaFile]) -> Result<(), sqlx::Error> {
+        batch_upsert_media_files(self.conn, files).await
+    }
+
+    pub async fn delete_by_id(&mut self, id: &str) -> Result<bool, sqlx::Error> {
+        delete_media_file(self.conn, id).await
+    }
+
+    pub async fn update_thumbnail_status(&mut self, id: &str, generated: bool) -> Result<(), sqlx::Error> {
+        update_media_thumbnail_status(self.conn, id, generated).await
+    }
+
+    pub async fn save_timeline(
+        &mut self,
+        id: &str,
+        modify_time: Option<NaiveDateTime>,
+        timeline: &str,
+    ) -> Result<(), sqlx::Error> {
+        save_video_timeline(self.conn, id, modify_time, timeline).await
+    }
+
+    pub async fn replace_sub_images(&mut self, file_path: &str, sub_images: &[MediaSubImage]) -> Result<(), sqlx::Error> {
+        replace_media_sub_images(self.conn, file_path, sub_images).await
+    }
+
+    pub async fn replace_attributes(
+        &mut self,
+        file_path: &str,
+        source: &str,
+        attributes: &[MediaAttribute],
+    ) -> Result<(), sqlx::Error> {
+        replace_media_attributes(self.conn, file_path, source, attributes).await
+    }
+}
+
 /// Repository for directory operations
 pub struct DirectoryRepository<'a> {
     db: &'a DatabasePool,
@@ -537,10 +1284,489 @@ impl<'a> DirectoryRepository<'a> {
         Self { db }
     }
 
-    /// Get all directories
+    /// Get all directories, with the media file id of each configured cover
     pub async fn find_all(&self) -> Result<Vec<Directory>, sqlx::Error> {
-        sqlx::query_as::<_, Directory>("SELECT * FROM directories ORDER BY path")
+        sqlx::query_as::<_, Directory>(
+            "SELECT d.id, d.path, d.parent_path, d.name, d.display_name, d.cover_path, d.last_scanned,
+                    m.id AS cover_id
+             FROM directories d
+             LEFT JOIN media_files m ON m.file_path = d.cover_path
+             ORDER BY d.path",
+        )
+        .fetch_all(self.db.read_pool())
+        .await
+    }
+
+    /// Replace the directory list with the directories seen by a completed scan
+    pub async fn sync(&self, entries: &[DirectoryEntry]) -> Result<(), sqlx::Error> {
+        let mut tx = self.db.get_pool().begin().await?;
+
+        // 先标记全部无效，扫描到的目录重新置为有效，最后删除仍无效的目录
+        sqlx::query("UPDATE directories SET is_valid = 0")
+            .execute(&mut *tx)
+            .await?;
+
+        for entry in entries {
+            sqlx::query(
+                "INSERT INTO directories (path, parent_path, name, display_name, cover_path, is_valid, last_scanned)
+                 VALUES (?, ?, ?, ?, ?, 1, CURRENT_TIMESTAMP)
+                 ON CONFLICT(path) DO UPDATE SET
+                    parent_path = excluded.parent_path,
+                    name = excluded.name,
+                    display_name = excluded.display_name,
+                    cover_path = excluded.cover_path,
+                    is_valid = 1,
+                    last_scanned = excluded.last_scanned",
+            )
+            .bind(&entry.path)
+            .bind(&entry.parent_path)
+            .bind(&entry.name)
+            .bind(&entry.display_name)
+            .bind(&entry.cover_path)
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        // media_files.directory_id 引用目录，删除前先解除引用
+        sqlx::query(
+            "UPDATE media_files SET directory_id = NULL
+             WHERE directory_id IN (SELECT id FROM directories WHERE is_valid = 0)",
+        )
+        .execute(&mut *tx)
+        .await?;
+        sqlx::query("DELETE FROM directories WHERE is_valid = 0")
+            .execute(&mut *tx)
+            .await?;
+
+        tx.commit().await
+    }
+}
+
+/// Repository for labels/embeddings from the external tagging service
+pub struct TaggingRepository<'a> {
+    db: &'a DatabasePool,
+}
+
+impl<'a> TaggingRepository<'a> {
+    pub fn new(db: &'a DatabasePool) -> Self {
+        Self { db }
+    }
+
+    /// Images not yet sent to the tagging service, as (id, file_path)
+    pub async fn find_untagged(&self, limit: usize) -> Result<Vec<(String, String)>, sqlx::Error> {
+        sqlx::query_as(
+            "SELECT m.id, m.file_path FROM media_files m
+             LEFT JOIN media_tagging t ON t.file_id = m.id
+             WHERE t.file_id IS NULL AND m.file_type = 'image'
+             ORDER BY m.create_time DESC
+             LIMIT ?",
+        )
+        .bind(limit as i64)
+        .fetch_all(self.db.get_pool())
+        .await
+    }
+
+    /// Store the tagging result of one file, replacing any previous labels/embedding
+    pub async fn save_result(
+        &self,
+        file_id: &str,
+        model: Option<&str>,
+        labels: &[MediaLabel],
+        embedding: Option<&[f32]>,
+    ) -> Result<(), sqlx::Error> {
+        let mut tx = self.db.get_pool().begin().await?;
+
+        sqlx::query("DELETE FROM media_labels WHERE file_id = ?")
+            .bind(file_id)
+            .execute(&mut *tx)
+            .await?;
+        for label in labels {
+            sqlx::query("INSERT OR REPLACE INTO media_labels (file_id, label, score) VALUES (?, ?, ?)")
+                .bind(file_id)
+                .bind(&label.label)
+                .bind(label.score)
+                .execute(&mut *tx)
+                .await?;
+        }
+
+        if let Some(vector) = embedding.filter(|v| !v.is_empty()) {
+            sqlx::query(
+                "INSERT OR REPLACE INTO media_embeddings (file_id, model, dimensions, vector) VALUES (?, ?, ?, ?)",
+            )
+            .bind(file_id)
+            .bind(model)
+            .bind(vector.len() as i64)
+            .bind(encode_embedding(vector))
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        sqlx::query("INSERT OR REPLACE INTO media_tagging (file_id, model, tagged_at) VALUES (?, ?, CURRENT_TIMESTAMP)")
+            .bind(file_id)
+            .bind(model)
+            .execute(&mut *tx)
+            .await?;
+
+        tx.commit().await
+    }
+
+    /// Labels of a file, highest score first
+    pub async fn find_labels(&self, file_id: &str) -> Result<Vec<MediaLabel>, sqlx::Error> {
+        sqlx::query_as::<_, MediaLabel>(
+            "SELECT label, score FROM media_labels WHERE file_id = ? ORDER BY score DESC, label",
+        )
+        .bind(file_id)
+        .fetch_all(self.db.get_pool())
+        .await
+    }
+
+    /// All stored embeddings as (file_id, vector)
+    pub async fn find_embeddings(&self) -> Result<Vec<(String, Vec<f32>)>, sqlx::Error> {
+        let rows: Vec<(String, Vec<u8>)> = sqlx::query_as("SELECT file_id, vector FROM media_embeddings")
             .fetch_all(self.db.get_pool())
+            .await?;
+        Ok(rows
+            .into_iter()
+            .map(|(file_id, vector)| (file_id, decode_embedding(&vector)))
+            .collect())
+    }
+}
+
+/// Repository for outbound webhooks
+pub struct WebhookRepository<'a> {
+    db: &'a DatabasePool,
+}
+
+impl<'a> WebhookRepository<'a> {
+    pub fn new(db: &'a DatabasePool) -> Self {
+        Self { db }
+    }
+
+    /// All webhooks, oldest first
+    pub async fn find_all(&self) -> Result<Vec<Webhook>, sqlx::Error> {
+        sqlx::query_as::<_, Webhook>(
+            "SELECT id, url, events, secret, enabled, created_at FROM webhooks ORDER BY created_at, id",
+        )
+        .fetch_all(self.db.get_pool())
+        .await
+    }
+
+    pub async fn find_by_id(&self, id: &str) -> Result<Option<Webhook>, sqlx::Error> {
+        sqlx::query_as::<_, Webhook>(
+            "SELECT id, url, events, secret, enabled, created_at FROM webhooks WHERE id = ?",
+        )
+        .bind(id)
+        .fetch_optional(self.db.get_pool())
+        .await
+    }
+
+    pub async fn insert(&self, webhook: &Webhook) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            "INSERT INTO webhooks (id, url, events, secret, enabled, created_at)
+             VALUES (?, ?, ?, ?, ?, CURRENT_TIMESTAMP)",
+        )
+        .bind(&webhook.id)
+        .bind(&webhook.url)
+        .bind(&webhook.events)
+        .bind(&webhook.secret)
+        .bind(webhook.enabled)
+        .execute(self.db.get_pool())
+        .await?;
+        Ok(())
+    }
+
+    pub async fn delete(&self, id: &str) -> Result<bool, sqlx::Error> {
+        let result = sqlx::query("DELETE FROM webhooks WHERE id = ?")
+            .bind(id)
+            .execute(self.db.get_pool())
+            .await?;
+        Ok(result.rows_affected() > 0)
+    }
+}
+
+/// Repository for the extended key/value metadata of media files (media_file_attributes).
+/// Extraction writes through MediaFileRepository::replace_attributes during scans.
+pub struct AttributeRepository<'a> {
+    db: &'a DatabasePool,
+}
+
+impl<'a> AttributeRepository<'a> {
+    pub fn new(db: &'a DatabasePool) -> Self {
+        Self { db }
+    }
+
+    /// All attributes of a file, ordered by key and source
+    pub async fn find_by_file(&self, file_id: &str) -> Result<Vec<MediaAttribute>, sqlx::Error> {
+        sqlx::query_as::<_, MediaAttribute>(
+            "SELECT key, value, source FROM media_file_attributes WHERE file_id = ? ORDER BY key, source",
+        )
+        .bind(file_id)
+        .fetch_all(self.db.get_pool())
+        .await
+    }
+
+    pub async fn find(&self, file_id: &str, source: &str, key: &str) -> Result<Option<MediaAttribute>, sqlx::Error> {
+        sqlx::query_as::<_, MediaAttribute>(
+            "SELECT key, value, source FROM media_file_attributes WHERE file_id = ? AND source = ? AND key = ?",
+        )
+        .bind(file_id)
+        .bind(source)
+        .bind(key)
+        .fetch_optional(self.db.get_pool())
+        .await
+    }
+
+    /// Insert or overwrite the value of `attribute.key` from `attribute.source`;
+    /// values of the same key from other sources are kept
+    pub async fn set(&self, file_id: &str, attribute: &MediaAttribute) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            "INSERT INTO media_file_attributes (file_id, key, value, source) VALUES (?, ?, ?, ?)
+             ON CONFLICT(file_id, source, key) DO UPDATE SET value = excluded.value",
+        )
+        .bind(file_id)
+        .bind(&attribute.key)
+        .bind(&attribute.value)
+        .bind(&attribute.source)
+        .execute(self.db.get_pool())
+        .await?;
+        Ok(())
+    }
+
+    pub async fn delete(&self, file_id: &str, source: &str, key: &str) -> Result<bool, sqlx::Error> {
+        let result = sqlx::query("DELETE FROM media_file_attributes WHERE file_id = ? AND source = ? AND key = ?")
+            .bind(file_id)
+            .bind(source)
+            .bind(key)
+            .execute(self.db.get_pool())
+            .await?;
+        Ok(result.rows_affected() > 0)
+    }
+}
+
+/// Repository for notes on media files
+pub struct NoteRepository<'a> {
+    db: &'a DatabasePool,
+}
+
+impl<'a> NoteRepository<'a> {
+    pub fn new(db: &'a DatabasePool) -> Self {
+        Self { db }
+    }
+
+    /// All notes of a file, oldest first; replies reference their parent via parent_id
+    pub async fn find_by_file(&self, file_id: &str) -> Result<Vec<MediaNote>, sqlx::Error> {
+        sqlx::query_as::<_, MediaNote>(
+            "SELECT id, file_id, parent_id, author, body, created_at, updated_at
+             FROM media_notes WHERE file_id = ? ORDER BY created_at, rowid",
+        )
+        .bind(file_id)
+        .fetch_all(self.db.get_pool())
+        .await
+    }
+
+    pub async fn find_by_id(&self, id: &str) -> Result<Option<MediaNote>, sqlx::Error> {
+        sqlx::query_as::<_, MediaNote>(
+            "SELECT id, file_id, parent_id, author, body, created_at, updated_at FROM media_notes WHERE id = ?",
+        )
+        .bind(id)
+        .fetch_optional(self.db.get_pool())
+        .await
+    }
+
+    pub async fn count_by_file(&self, file_id: &str) -> Result<i64, sqlx::Error> {
+        sqlx::query_scalar("SELECT COUNT(*) FROM media_notes WHERE file_id = ?")
+            .bind(file_id)
+            .fetch_one(self.db.get_pool())
             .await
     }
+
+    pub async fn insert(&self, note: &MediaNote) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            "INSERT INTO media_notes (id, file_id, parent_id, author, body, created_at)
+             VALUES (?, ?, ?, ?, ?, CURRENT_TIMESTAMP)",
+        )
+        .bind(&note.id)
+        .bind(&note.file_id)
+        .bind(&note.parent_id)
+        .bind(&note.author)
+        .bind(&note.body)
+        .execute(self.db.get_pool())
+        .await?;
+        Ok(())
+    }
+
+    /// Replace the body of a note; returns the updated note, None if it does not exist
+    pub async fn update_body(&self, id: &str, body: &str) -> Result<Option<MediaNote>, sqlx::Error> {
+        sqlx::query_as::<_, MediaNote>(
+            "UPDATE media_notes SET body = ?, updated_at = CURRENT_TIMESTAMP WHERE id = ?
+             RETURNING id, file_id, parent_id, author, body, created_at, updated_at",
+        )
+        .bind(body)
+        .bind(id)
+        .fetch_optional(self.db.get_pool())
+        .await
+    }
+
+    /// Delete a note together with all replies below it; returns the number of notes removed
+    pub async fn delete(&self, id: &str) -> Result<u64, sqlx::Error> {
+        let result = sqlx::query(
+            "WITH RECURSIVE thread(id) AS (
+                 SELECT id FROM media_notes WHERE id = ?
+                 UNION SELECT n.id FROM media_notes n JOIN thread t ON n.parent_id = t.id
+             )
+             DELETE FROM media_notes WHERE id IN (SELECT id FROM thread)",
+        )
+        .bind(id)
+        .execute(self.db.get_pool())
+        .await?;
+        Ok(result.rows_affected())
+    }
+}
+
+/// 动态流的各类事件（UNION ALL 的各分支列数与列序一致）
+const ACTIVITY_EVENTS_SQL: &str = "
+    SELECT 'files_added' AS kind, MAX(created_at) AS time, id AS file_id, COUNT(*) AS count,
+           NULL AS author, NULL AS text
+    FROM media_files WHERE created_at IS NOT NULL GROUP BY date(created_at)
+    UNION ALL
+    SELECT 'note', created_at, file_id, 1, author, body FROM media_notes
+    UNION ALL
+    SELECT 'edit', updated_at, id, 1, NULL, title FROM media_files WHERE version > 1";
+
+/// Read-only aggregation of recent events over media files and notes
+pub struct ActivityRepository<'a> {
+    db: &'a DatabasePool,
+}
+
+impl<'a> ActivityRepository<'a> {
+    pub fn new(db: &'a DatabasePool) -> Self {
+        Self { db }
+    }
+
+    /// One page of the feed, newest first. Files added are grouped per day; SQLite returns
+    /// the id of the row holding MAX(created_at), i.e. the latest file added that day.
+    pub async fn find_page(&self, page: i32, page_size: i32) -> Result<Vec<ActivityEvent>, sqlx::Error> {
+        sqlx::query_as::<_, ActivityEvent>(&format!(
+            "SELECT * FROM ({ACTIVITY_EVENTS_SQL}) ORDER BY time DESC, kind LIMIT ? OFFSET ?"
+        ))
+        .bind(page_size)
+        .bind(page * page_size)
+        .fetch_all(self.db.read_pool())
+        .await
+    }
+
+    pub async fn count(&self) -> Result<i64, sqlx::Error> {
+        sqlx::query_scalar(&format!("SELECT COUNT(*) FROM ({ACTIVITY_EVENTS_SQL})"))
+            .fetch_one(self.db.read_pool())
+            .await
+    }
+}
+
+/// Media change log for incremental sync (GET /api/sync/changes). Entries are appended by
+/// MediaFileRepository; `seq` is the cursor handed to clients.
+pub struct ChangeLogRepository<'a> {
+    db: &'a DatabasePool,
+}
+
+impl<'a> ChangeLogRepository<'a> {
+    pub fn new(db: &'a DatabasePool) -> Self {
+        Self { db }
+    }
+
+    /// Cursor of the newest entry ever written (0 before the first change), kept by
+    /// AUTOINCREMENT even after the entry was pruned
+    pub async fn latest_seq(&self) -> Result<i64, sqlx::Error> {
+        let seq: Option<i64> = sqlx::query_scalar("SELECT seq FROM sqlite_sequence WHERE name = 'media_changes'")
+            .fetch_optional(self.db.read_pool())
+            .await?;
+        Ok(seq.unwrap_or(0))
+    }
+
+    /// Cursor of the oldest entry still in the log
+    pub async fn oldest_seq(&self) -> Result<Option<i64>, sqlx::Error> {
+        sqlx::query_scalar("SELECT MIN(seq) FROM media_changes")
+            .fetch_one(self.db.read_pool())
+            .await
+    }
+
+    /// The latest change of every file changed after `since`, oldest first, at most `limit`.
+    /// A page ends at the seq of its last entry; files changed again later appear in a
+    /// following page with their newer change.
+    pub async fn find_since(&self, since: i64, limit: i64) -> Result<Vec<MediaChange>, sqlx::Error> {
+        // SQLite 取 MAX(seq) 所在行的其他列
+        sqlx::query_as::<_, MediaChange>(
+            "SELECT MAX(seq) AS seq, file_id, change, changed_at FROM media_changes
+             WHERE seq > ? GROUP BY file_id ORDER BY seq LIMIT ?",
+        )
+        .bind(since)
+        .bind(limit)
+        .fetch_all(self.db.read_pool())
+        .await
+    }
+
+    /// Drop entries written before `before`; returns how many were removed
+    pub async fn prune(&self, before: NaiveDateTime) -> Result<u64, sqlx::Error> {
+        let result = sqlx::query("DELETE FROM media_changes WHERE changed_at < ?")
+            .bind(before)
+            .execute(self.db.get_pool())
+            .await?;
+        Ok(result.rows_affected())
+    }
+}
+
+/// Repository for the failed-file registry
+pub struct FailedFileRepository<'a> {
+    db: &'a DatabasePool,
+}
+
+impl<'a> FailedFileRepository<'a> {
+    pub fn new(db: &'a DatabasePool) -> Self {
+        Self { db }
+    }
+
+    /// Record a failure; repeated failures of the same stage bump `attempts`
+    pub async fn record(&self, file_id: &str, stage: &str, error: &str) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            "INSERT INTO failed_files (file_id, stage, error, attempts, last_failed_at)
+             VALUES (?, ?, ?, 1, CURRENT_TIMESTAMP)
+             ON CONFLICT(file_id, stage) DO UPDATE SET
+                error = excluded.error,
+                attempts = failed_files.attempts + 1,
+                last_failed_at = excluded.last_failed_at",
+        )
+        .bind(file_id)
+        .bind(stage)
+        .bind(error)
+        .execute(self.db.get_pool())
+        .await?;
+        Ok(())
+    }
+
+    /// All failures, most recent first
+    pub async fn find_all(&self) -> Result<Vec<FailedFile>, sqlx::Error> {
+        sqlx::query_as::<_, FailedFile>(
+            "SELECT file_id, stage, error, attempts, last_failed_at FROM failed_files
+             ORDER BY last_failed_at DESC, file_id",
+        )
+        .fetch_all(self.db.get_pool())
+        .await
+    }
+
+    /// Forget a failure after the stage succeeded
+    pub async fn clear(&self, file_id: &str, stage: &str) -> Result<bool, sqlx::Error> {
+        let result = sqlx::query("DELETE FROM failed_files WHERE file_id = ? AND stage = ?")
+            .bind(file_id)
+            .bind(stage)
+            .execute(self.db.get_pool())
+            .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Forget every failure (before a retry); returns how many were removed
+    pub async fn clear_all(&self) -> Result<u64, sqlx::Error> {
+        let result = sqlx::query("DELETE FROM failed_files")
+            .execute(self.db.get_pool())
+            .await?;
+        Ok(result.rows_affected())
+    }
 }
@@ -0,0 +1,155 @@
+//! Seeded synthetic library for regression tests and benchmarks
+//!
+//! Generates N media rows (and optionally tiny valid JPEG files) whose values only depend
+//! on the seed, so a benchmark run is comparable with the previous one.
+
+use std::path::{Path, PathBuf};
+use chrono::{Duration, NaiveDateTime, TimeZone, Utc};
+use crate::db::{DatabasePool, MediaFile, MediaFileRepository};
+use super::create_test_media_file;
+
+/// Camera models spread over the synthetic rows, for filter queries
+const CAMERA_MODELS: [&str; 4] = ["X100V", "ILCE-7M4", "iPhone 15 Pro", "Xiaomi 14"];
+
+/// Rows per batch_upsert call when seeding
+const SEED_BATCH_SIZE: usize = 500;
+
+/// Deterministic generator of a synthetic library
+pub struct SyntheticLibrary {
+    state: u64,
+    /// Root of the generated file paths
+    root: PathBuf,
+    /// Index of the next generated file; consecutive calls continue the numbering
+    next_index: usize,
+}
+
+impl SyntheticLibrary {
+    pub fn new(seed: u64) -> Self {
+        Self::with_root(seed, "/synthetic")
+    }
+
+    /// Generate paths under `root`, e.g. a temp dir the JPEGs are written to
+    pub fn with_root(seed: u64, root: impl Into<PathBuf>) -> Self {
+        Self { state: seed, root: root.into(), next_index: 0 }
+    }
+
+    /// splitmix64: 不需要引入 rand，且同一种子在所有平台上结果相同
+    fn next(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    fn below(&mut self, n: u64) -> u64 {
+        self.next() % n
+    }
+
+    /// Path of the i-th file: 100 files per directory, two directory levels
+    pub fn file_path(&self, index: usize) -> PathBuf {
+        self.root
+            .join(format!("{:03}", index / 10_000))
+            .join(format!("{:02}", index / 100 % 100))
+            .join(format!("IMG_{:06}.jpg", index))
+    }
+
+    /// `count` media rows: ~10% videos, capture times over 2015–2024, varied cameras,
+    /// dimensions and blur/exposure statistics
+    pub fn media_files(&mut self, count: usize) -> Vec<MediaFile> {
+        let start = Utc.with_ymd_and_hms(2015, 1, 1, 0, 0, 0).unwrap().naive_utc();
+        let span_secs = 10 * 365 * 24 * 3600;
+
+        let first = self.next_index;
+        self.next_index += count;
+        (first..first + count)
+            .map(|index| {
+                let path = self.file_path(index);
+                let file_name = path.file_name().unwrap().to_string_lossy().to_string();
+                let mut file = create_test_media_file(&file_name);
+                file.file_path = path.to_string_lossy().to_string();
+
+                let taken: NaiveDateTime = start + Duration::seconds(self.below(span_secs) as i64);
+                file.exif_timestamp = Some(taken);
+                file.create_time = Some(taken);
+                file.modify_time = Some(taken);
+                file.camera_model = Some(CAMERA_MODELS[self.below(CAMERA_MODELS.len() as u64) as usize].to_string());
+                file.file_size = Some(500_000 + self.below(8_000_000) as i64);
+
+                let landscape = self.below(3) > 0;
+                let (w, h) = if landscape { (4000, 3000) } else { (3000, 4000) };
+                file.width = Some(w);
+                file.height = Some(h);
+
+                if self.below(10) == 0 {
+                    file.file_type = "video".to_string();
+                    file.mime_type = Some("video/mp4".to_string());
+                    file.duration = Some(1.0 + self.below(300) as f64);
+                    file.video_codec = Some("H264".to_string());
+                } else {
+                    file.blur_score = Some(self.below(2_000) as f64);
+                    file.mean_luminance = Some(self.below(1_000) as f64 / 1_000.0);
+                }
+                file
+            })
+            .collect()
+    }
+
+    /// Insert `count` synthetic rows into `db` in batches; returns the rows
+    pub async fn seed_database(&mut self, db: &DatabasePool, count: usize) -> Result<Vec<MediaFile>, sqlx::Error> {
+        let files = self.media_files(count);
+        let repo = MediaFileRepository::new(db);
+        for chunk in files.chunks(SEED_BATCH_SIZE) {
+            repo.batch_upsert(chunk).await?;
+        }
+        Ok(files)
+    }
+
+    /// Write a tiny valid JPEG for each of the next `count` paths under the root and
+    /// return the paths. Colors vary with the seed so files differ in content.
+    pub fn write_jpegs(&mut self, count: usize) -> std::io::Result<Vec<PathBuf>> {
+        let first = self.next_index;
+        self.next_index += count;
+        let mut paths = Vec::with_capacity(count);
+        for index in first..first + count {
+            let path = self.file_path(index);
+            let color = image::Rgb([self.below(256) as u8, self.below(256) as u8, self.below(256) as u8]);
+            write_jpeg(&path, color)?;
+            paths.push(path);
+        }
+        Ok(paths)
+    }
+}
+
+/// 16×12 solid-color JPEG, enough for the image processors to decode
+fn write_jpeg(path: &Path, color: image::Rgb<u8>) -> std::io::Result<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    image::RgbImage::from_pixel(16, 12, color)
+        .save_with_format(path, image::ImageFormat::Jpeg)
+        .map_err(std::io::Error::other)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_same_seed_same_library() {
+        let a = SyntheticLibrary::new(7).media_files(50);
+        let b = SyntheticLibrary::new(7).media_files(50);
+        let key = |f: &MediaFile| (f.file_path.clone(), f.exif_timestamp, f.camera_model.clone(), f.file_type.clone());
+        assert_eq!(a.iter().map(key).collect::<Vec<_>>(), b.iter().map(key).collect::<Vec<_>>());
+        assert!(a.iter().any(|f| f.file_type == "video"));
+    }
+
+    #[test]
+    fn test_write_jpegs_decodable() {
+        let dir = tempfile::tempdir().unwrap();
+        let paths = SyntheticLibrary::with_root(1, dir.path()).write_jpegs(3).unwrap();
+        assert_eq!(paths.len(), 3);
+        let img = image::open(&paths[2]).unwrap();
+        assert_eq!((img.width(), img.height()), (16, 12));
+    }
+}
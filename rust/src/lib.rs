@@ -4,6 +4,7 @@ pub mod api;
 pub mod db;
 pub mod services;
 pub mod processors;
+pub mod utils;
 pub mod websocket;
 
 // Test fixtures and helpers (available for integration tests)
@@ -1,24 +1,222 @@
+use clap::{Parser, Subcommand};
+use indicatif::{ProgressBar, ProgressStyle};
 use latte_album::app::App;
 use latte_album::config::Config;
+use latte_album::db::{backup, DatabasePool, MediaFileRepository};
+use latte_album::services::{config_check, ScanMode};
+use latte_album::websocket::broadcast::ScanProgressMessage;
+use std::path::PathBuf;
+use std::process::ExitCode;
+use std::time::Duration;
+use tokio::sync::broadcast::error::RecvError;
 use tracing::info;
 
+/// Latte Album: a personal photo album for NAS deployment.
+/// Without a subcommand the HTTP server is started.
+#[derive(Debug, Parser)]
+#[command(name = "latte-album", version)]
+struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Run one scan of LATTE_BASE_PATH without the HTTP server, then exit.
+    /// Exit code 1 if the scan failed or was cancelled, 2 if some files failed to process.
+    Scan {
+        /// Re-extract metadata for every file instead of skipping unchanged ones
+        #[arg(long)]
+        force: bool,
+    },
+    /// Create a database backup and exit
+    Backup,
+    /// Stage a backup; it replaces the database on the next server start
+    Restore {
+        /// Backup file, or a file name inside the backup directory
+        file: PathBuf,
+    },
+    /// Rewrite the path prefix of stored files after the library mount point moved, then exit.
+    /// Without --from/--to the LATTE_PATH_PREFIX_MAP rules are applied.
+    RemapPaths {
+        /// Old prefix, e.g. /photos
+        #[arg(long, requires = "to")]
+        from: Option<String>,
+        /// New prefix, e.g. /mnt/photos
+        #[arg(long, requires = "from")]
+        to: Option<String>,
+    },
+}
+
 #[tokio::main]
-async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    // 初始化日志
+async fn main() -> Result<ExitCode, Box<dyn std::error::Error>> {
+    let cli = Cli::parse();
+
+    // 初始化日志（scan 模式下只输出警告，避免打乱进度条）
+    let level = match cli.command {
+        Some(Command::Scan { .. }) => tracing::Level::WARN,
+        _ => tracing::Level::INFO,
+    };
     tracing_subscriber::fmt::fmt()
-        .with_max_level(tracing::Level::INFO)
+        .with_max_level(level)
         .init();
 
     // 加载配置
     let config = Config::from_env()?;
 
+    match cli.command {
+        Some(Command::Scan { force }) => return run_scan(config, force).await,
+        Some(Command::Backup) => {
+            run_backup(&config).await?;
+            return Ok(ExitCode::SUCCESS);
+        }
+        Some(Command::Restore { file }) => {
+            run_restore(&config, file).await?;
+            return Ok(ExitCode::SUCCESS);
+        }
+        Some(Command::RemapPaths { from, to }) => {
+            run_remap_paths(&config, from.zip(to)).await?;
+            return Ok(ExitCode::SUCCESS);
+        }
+        None => {}
+    }
+
     info!("Starting Latte Album server...");
-    info!("Server address: {}:{}", config.host, config.port);
-    info!("Photo base path: {:?}", config.base_path);
+
+    // 校验配置并输出生效配置摘要；问题只记录日志，不阻止启动
+    let report = {
+        let config = config.clone();
+        tokio::task::spawn_blocking(move || config_check::validate(&config, true)).await?
+    };
+    config_check::log_report(&report);
 
     // 创建并运行应用
     let app = App::new(config).await?;
     app.run().await?;
 
+    Ok(ExitCode::SUCCESS)
+}
+
+/// 不启动 HTTP 服务，执行一次扫描并在控制台显示进度，供 cron / systemd timer 调用
+async fn run_scan(config: Config, force: bool) -> Result<ExitCode, Box<dyn std::error::Error>> {
+    let app = App::new(config).await?;
+    let state = app.state();
+
+    let bar = ProgressBar::new_spinner();
+    bar.set_style(ProgressStyle::with_template("{spinner} {msg}")?);
+    bar.set_message("collecting files");
+    bar.enable_steady_tick(Duration::from_millis(120));
+
+    // 与 WebSocket 推送的是同一份进度消息
+    let mut progress = state.broadcaster.subscribe();
+    let render = {
+        let bar = bar.clone();
+        let processing_style = ProgressStyle::with_template(
+            "{spinner} [{elapsed_precise}] {wide_bar} {pos}/{len} ({eta}) {msg}",
+        )?;
+        tokio::spawn(async move {
+            loop {
+                match progress.recv().await {
+                    Ok(message) => render_progress(&bar, &processing_style, &message),
+                    Err(RecvError::Lagged(_)) => continue,
+                    Err(RecvError::Closed) => break,
+                }
+            }
+        })
+    };
+
+    let mode = if force { ScanMode::Force } else { ScanMode::Incremental };
+    state.scan_service.scan_with_mode(mode).await;
+    render.abort();
+    bar.finish_and_clear();
+
+    // 收集文件失败时不会生成报告
+    let Some(report) = state.scan_service.last_report() else {
+        eprintln!("Scan failed, see the log above");
+        return Ok(ExitCode::FAILURE);
+    };
+    if report.status != "completed" {
+        eprintln!("Scan {}", report.status);
+        return Ok(ExitCode::FAILURE);
+    }
+
+    println!(
+        "Scan completed: {} files, {} processed, {} failed in {:.1}s",
+        report.total_files,
+        report.processed_files,
+        report.failed_files,
+        report.duration_ms as f64 / 1000.0
+    );
+    if report.failed_files > 0 {
+        for failures in &report.failures_by_extension {
+            eprintln!("  .{}: {} failed, e.g. {}", failures.extension, failures.count, failures.sample_error);
+        }
+        return Ok(ExitCode::from(2));
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+/// 处理阶段显示进度条，其余阶段显示阶段名
+fn render_progress(bar: &ProgressBar, processing_style: &ProgressStyle, message: &ScanProgressMessage) {
+    let phase = message.phase.as_deref().unwrap_or("idle");
+    if phase == "processing" && message.total_files > 0 {
+        if bar.length().is_none() {
+            bar.set_style(processing_style.clone());
+        }
+        bar.set_length(message.total_files);
+        bar.set_position(message.success_count + message.failure_count);
+        if message.failure_count > 0 {
+            bar.set_message(format!("{} failed", message.failure_count));
+        }
+    } else if bar.length().is_none() {
+        bar.set_message(phase.to_string());
+    } else {
+        bar.set_message(format!("{} ({} failed)", phase, message.failure_count));
+    }
+}
+
+/// 创建一次数据库备份后退出
+async fn run_backup(config: &Config) -> Result<(), Box<dyn std::error::Error>> {
+    let db = DatabasePool::new(&config.db_path).await?;
+    let path = backup::create_backup(&db, &config.get_backup_dir(), config.backup_keep).await?;
+    info!("Backup written to {:?}", path);
+    Ok(())
+}
+
+/// 校验备份并暂存，下次启动服务时替换数据库
+async fn run_restore(config: &Config, file: PathBuf) -> Result<(), Box<dyn std::error::Error>> {
+    // 允许直接传备份目录中的文件名
+    let file = if file.exists() {
+        file
+    } else {
+        config.get_backup_dir().join(file)
+    };
+
+    backup::stage_restore(&file, &config.db_path).await?;
+    info!("Restore staged from {:?}, it will be applied on next server start", file);
+    Ok(())
+}
+
+/// 挂载点变化后改写数据库中的路径前缀（--from/--to，或 LATTE_PATH_PREFIX_MAP 的全部规则）
+async fn run_remap_paths(config: &Config, rule: Option<(String, String)>) -> Result<(), Box<dyn std::error::Error>> {
+    let rules: Vec<(String, String)> = match rule {
+        Some(rule) => vec![rule],
+        None => config
+            .path_prefix_map
+            .rules()
+            .iter()
+            .map(|(from, to)| (from.to_string_lossy().to_string(), to.to_string_lossy().to_string()))
+            .collect(),
+    };
+    if rules.is_empty() {
+        return Err("No prefix to rewrite: pass --from/--to or set LATTE_PATH_PREFIX_MAP".into());
+    }
+
+    let db = DatabasePool::new(&config.db_path).await?;
+    let repo = MediaFileRepository::new(&db);
+    for (from, to) in rules {
+        let count = repo.rewrite_path_prefix(&from, &to).await?;
+        info!("Rewrote {} -> {} for {} files", from, to, count);
+    }
     Ok(())
 }
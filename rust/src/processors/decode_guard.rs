@@ -0,0 +1,84 @@
+//! 超大图片解码保护
+//! 生成缩略图前只读取文件头获取尺寸，像素数超过上限（LATTE_MAX_DECODE_PIXELS）时
+//! 改走缩小解码路径（JPEG DCT 缩放 / HEIC 内嵌缩略图），避免 300MP 全景图整张解码占满内存。
+
+use crate::processors::processor_trait::ProcessingError;
+use crate::services::background_priority;
+use std::path::Path;
+use std::process::Command;
+
+/// Default maximum pixel count decoded at full resolution (100MP)
+pub const DEFAULT_MAX_DECODE_PIXELS: u64 = 100_000_000;
+
+/// Largest JPEG DCT scaling exponent: the decoder can reduce each side by up to 1/8
+const MAX_JPEG_SCALE_SHIFT: u8 = 3;
+
+/// Total pixel count, computed in u64 so huge panoramas don't overflow
+pub fn pixel_count(width: u32, height: u32) -> u64 {
+    width as u64 * height as u64
+}
+
+/// Whether an image is too large to decode at full resolution
+pub fn exceeds_limit(width: u32, height: u32, max_pixels: u64) -> bool {
+    pixel_count(width, height) > max_pixels
+}
+
+/// Smallest DCT scaling exponent n (decode at 1/2^n per side) that brings the image
+/// within the limit. None if even 1/8 scaling is not enough.
+pub fn jpeg_scale_shift(width: u32, height: u32, max_pixels: u64) -> Option<u8> {
+    (0..=MAX_JPEG_SCALE_SHIFT).find(|shift| pixel_count(width, height) >> (2 * shift) <= max_pixels)
+}
+
+/// Decode a JPEG at 1/2^shift size per side using ffmpeg's `-lowres` (DCT domain scaling),
+/// so the full-resolution bitmap is never allocated.
+pub fn decode_jpeg_scaled(
+    ffmpeg_path: &Path,
+    path: &Path,
+    shift: u8,
+) -> Result<image::DynamicImage, ProcessingError> {
+    let mut command = Command::new(ffmpeg_path);
+    background_priority::lower_child(&mut command);
+    let output = command
+        .args(["-v", "error", "-lowres", &shift.to_string(), "-i"])
+        .arg(path)
+        .args(["-frames:v", "1", "-f", "image2pipe", "-vcodec", "png", "-compression_level", "1", "pipe:1"])
+        .output()
+        .map_err(|e| ProcessingError::ExternalTool(format!("Failed to run ffmpeg: {}", e)))?;
+
+    if !output.status.success() {
+        return Err(ProcessingError::ExternalTool(format!(
+            "ffmpeg scaled decode failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+
+    Ok(image::load_from_memory_with_format(&output.stdout, image::ImageFormat::Png)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pixel_count_no_overflow() {
+        assert_eq!(pixel_count(100_000, 100_000), 10_000_000_000);
+    }
+
+    #[test]
+    fn test_exceeds_limit() {
+        assert!(!exceeds_limit(10_000, 10_000, 100_000_000));
+        assert!(exceeds_limit(10_001, 10_000, 100_000_000));
+    }
+
+    #[test]
+    fn test_jpeg_scale_shift() {
+        // 已在上限内：不缩放
+        assert_eq!(jpeg_scale_shift(4000, 3000, 100_000_000), Some(0));
+        // 300MP 全景：1/2 缩放后 75MP
+        assert_eq!(jpeg_scale_shift(30_000, 10_000, 100_000_000), Some(1));
+        // 2GP：需要 1/8 缩放（约 31MP）
+        assert_eq!(jpeg_scale_shift(100_000, 20_000, 100_000_000), Some(3));
+        // 1/8 仍超限
+        assert_eq!(jpeg_scale_shift(200_000, 100_000, 100_000_000), None);
+    }
+}
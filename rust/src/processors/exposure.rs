@@ -0,0 +1,87 @@
+//! 曝光统计
+//! 在生成缩略图时顺带计算：平均亮度、高光溢出与暗部死黑像素的比例。
+//! 缩略图已经缩小，统计几乎没有额外开销；结果用于筛选欠曝/过曝（夜景、逆光）照片。
+
+use crate::processors::processor_trait::ProcessingError;
+use image::DynamicImage;
+
+/// Luma at or above this counts as a clipped highlight
+const HIGHLIGHT_LEVEL: u8 = 250;
+/// Luma at or below this counts as a crushed shadow
+const SHADOW_LEVEL: u8 = 5;
+
+/// Exposure statistics of one image
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ExposureStats {
+    /// Mean luma in 0.0-1.0
+    pub mean_luminance: f64,
+    /// Fraction of pixels at or above HIGHLIGHT_LEVEL
+    pub clipped_highlights: f64,
+    /// Fraction of pixels at or below SHADOW_LEVEL
+    pub clipped_shadows: f64,
+}
+
+/// Statistics over the luma of every pixel; None for an empty image
+pub fn exposure_stats(img: &DynamicImage) -> Option<ExposureStats> {
+    let gray = img.to_luma8();
+    let count = gray.pixels().len();
+    if count == 0 {
+        return None;
+    }
+
+    let mut sum = 0u64;
+    let mut highlights = 0usize;
+    let mut shadows = 0usize;
+    for pixel in gray.pixels() {
+        let luma = pixel[0];
+        sum += luma as u64;
+        if luma >= HIGHLIGHT_LEVEL {
+            highlights += 1;
+        } else if luma <= SHADOW_LEVEL {
+            shadows += 1;
+        }
+    }
+
+    let count = count as f64;
+    Some(ExposureStats {
+        mean_luminance: sum as f64 / count / 255.0,
+        clipped_highlights: highlights as f64 / count,
+        clipped_shadows: shadows as f64 / count,
+    })
+}
+
+/// Statistics of an encoded thumbnail
+pub fn thumbnail_exposure_stats(data: &[u8]) -> Result<Option<ExposureStats>, ProcessingError> {
+    Ok(exposure_stats(&image::load_from_memory(data)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{GrayImage, Luma};
+
+    #[test]
+    fn test_exposure_stats() {
+        // 左半黑、右半白
+        let img = DynamicImage::ImageLuma8(GrayImage::from_fn(10, 10, |x, _| {
+            if x < 5 { Luma([0]) } else { Luma([255]) }
+        }));
+        let stats = exposure_stats(&img).unwrap();
+        assert!((stats.mean_luminance - 0.5).abs() < 1e-9);
+        assert_eq!(stats.clipped_highlights, 0.5);
+        assert_eq!(stats.clipped_shadows, 0.5);
+    }
+
+    #[test]
+    fn test_mid_gray_has_no_clipping() {
+        let img = DynamicImage::ImageLuma8(GrayImage::from_pixel(4, 4, Luma([128])));
+        let stats = exposure_stats(&img).unwrap();
+        assert_eq!(stats.clipped_highlights, 0.0);
+        assert_eq!(stats.clipped_shadows, 0.0);
+    }
+
+    #[test]
+    fn test_empty_image() {
+        assert_eq!(exposure_stats(&DynamicImage::new_luma8(0, 0)), None);
+    }
+}
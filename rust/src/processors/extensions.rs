@@ -0,0 +1,78 @@
+//! 支持的扩展名集中定义：处理器与扫描共用同一份列表，
+//! LATTE_EXTRA_IMAGE_EXTS / LATTE_EXTRA_VIDEO_EXTS 可在不重新编译的情况下追加
+
+use crate::config::Config;
+use std::path::Path;
+
+/// Formats decoded by the image crate (StandardImageProcessor)
+pub const IMAGE_EXTENSIONS: &[&str] = &["jpg", "jpeg", "png", "gif", "bmp", "webp", "tiff"];
+
+/// HEIC/HEIF containers (HeifImageProcessor)
+pub const HEIF_EXTENSIONS: &[&str] = &["heic", "heif"];
+
+/// Containers handled by ffmpeg (VideoProcessor)
+pub const VIDEO_EXTENSIONS: &[&str] = &["mp4", "avi", "mov", "mkv", "wmv", "flv", "webm"];
+
+/// Normalize a configured extension: trimmed, lower-case, without the leading dot.
+/// None for empty entries.
+pub fn normalize(ext: &str) -> Option<String> {
+    let ext = ext.trim().trim_start_matches('.').to_lowercase();
+    (!ext.is_empty()).then_some(ext)
+}
+
+/// Built-in extensions followed by the configured extra ones, without duplicates
+pub fn with_extra(builtin: &[&str], extra: &[String]) -> Vec<String> {
+    let mut extensions: Vec<String> = builtin.iter().map(|e| e.to_string()).collect();
+    for ext in extra.iter().filter_map(|e| normalize(e)) {
+        if !extensions.contains(&ext) {
+            extensions.push(ext);
+        }
+    }
+    extensions
+}
+
+/// Whether the file extension is in the list (case-insensitive)
+pub fn matches(path: &Path, extensions: &[String]) -> bool {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|ext| extensions.iter().any(|e| e.eq_ignore_ascii_case(ext)))
+}
+
+/// Every extension collected by the scanner: images, HEIF and videos including configured extras
+pub fn supported_extensions(config: &Config) -> Vec<String> {
+    let mut extensions = with_extra(IMAGE_EXTENSIONS, &config.extra_image_extensions);
+    extensions.extend(with_extra(HEIF_EXTENSIONS, &[]));
+    for ext in with_extra(VIDEO_EXTENSIONS, &config.extra_video_extensions) {
+        if !extensions.contains(&ext) {
+            extensions.push(ext);
+        }
+    }
+    extensions
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_extra_extensions_are_normalized() {
+        let extensions = with_extra(VIDEO_EXTENSIONS, &[".INSV".to_string(), " mp4 ".to_string(), "".to_string()]);
+        assert_eq!(extensions.len(), VIDEO_EXTENSIONS.len() + 1);
+        assert_eq!(extensions.last().map(String::as_str), Some("insv"));
+        assert!(matches(Path::new("/p/VID_0001.InSv"), &extensions));
+        assert!(!matches(Path::new("/p/VID_0001"), &extensions));
+    }
+
+    #[test]
+    fn test_supported_extensions_include_config_extras() {
+        let config = Config {
+            extra_image_extensions: vec!["dng".to_string()],
+            extra_video_extensions: vec!["insv".to_string()],
+            ..Config::default()
+        };
+        let extensions = supported_extensions(&config);
+        for ext in ["jpg", "heic", "mp4", "dng", "insv"] {
+            assert!(extensions.iter().any(|e| e == ext), "{} missing", ext);
+        }
+    }
+}
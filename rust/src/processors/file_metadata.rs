@@ -1,12 +1,26 @@
 //! Unified file metadata extraction for all media types.
-//! Handles file_size, create_time, and modify_time which are format-independent.
+//! Handles file_size, create_time, modify_time and the content hash, which are format-independent.
 
 use crate::processors::processor_trait::MediaMetadata;
+use sha2::{Digest, Sha256};
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
 use std::path::Path;
 
+/// Bytes hashed from the start and from the end of a file
+const HASH_SAMPLE_BYTES: u64 = 64 * 1024;
+
 /// Extract file metadata that is common to all file types.
-/// This includes file size, creation time, and modification time.
+/// This includes file size, creation time, modification time and the content hash.
 pub fn extract_file_metadata(path: &Path) -> MediaMetadata {
+    let mut metadata = extract_fs_metadata(path);
+    metadata.content_hash = content_hash(path).ok();
+    metadata
+}
+
+/// File size, creation and modification time from a single stat, without reading the file
+/// (quick scans)
+pub fn extract_fs_metadata(path: &Path) -> MediaMetadata {
     let mut metadata = MediaMetadata::default();
 
     if let Ok(file_meta) = path.metadata() {
@@ -26,9 +40,65 @@ pub fn extract_file_metadata(path: &Path) -> MediaMetadata {
     metadata
 }
 
+/// Content fingerprint: hex SHA-256 of the file length plus its first and last 64 KiB
+/// (the whole file when smaller). Reading only the ends keeps scans of large videos cheap;
+/// the length and both ends together identify a photo or video in practice.
+pub fn content_hash(path: &Path) -> std::io::Result<String> {
+    let mut file = File::open(path)?;
+    let len = file.metadata()?.len();
+
+    let mut hasher = Sha256::new();
+    hasher.update(len.to_le_bytes());
+
+    let mut buffer = Vec::with_capacity(HASH_SAMPLE_BYTES as usize);
+    if len <= HASH_SAMPLE_BYTES * 2 {
+        file.read_to_end(&mut buffer)?;
+        hasher.update(&buffer);
+    } else {
+        (&mut file).take(HASH_SAMPLE_BYTES).read_to_end(&mut buffer)?;
+        hasher.update(&buffer);
+        buffer.clear();
+        file.seek(SeekFrom::End(-(HASH_SAMPLE_BYTES as i64)))?;
+        file.read_to_end(&mut buffer)?;
+        hasher.update(&buffer);
+    }
+
+    Ok(hasher.finalize().iter().map(|b| format!("{:02x}", b)).collect())
+}
+
 /// Convert std::time::SystemTime to chrono::NaiveDateTime
 fn system_time_to_naive_datetime(time: std::time::SystemTime) -> Option<chrono::NaiveDateTime> {
     let duration = time.duration_since(std::time::UNIX_EPOCH).ok()?;
     chrono::DateTime::from_timestamp(duration.as_secs() as i64, 0)
         .map(|dt| dt.naive_utc())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_content_hash() {
+        let dir = tempfile::tempdir().unwrap();
+        let small = dir.path().join("a.jpg");
+        let copy = dir.path().join("moved.jpg");
+        std::fs::write(&small, b"same bytes").unwrap();
+        std::fs::write(&copy, b"same bytes").unwrap();
+        let hash = content_hash(&small).unwrap();
+        assert_eq!(hash.len(), 64);
+        // 与文件名/路径无关
+        assert_eq!(content_hash(&copy).unwrap(), hash);
+
+        // 大文件只读首尾，中间的改动不影响指纹，末尾的改动会改变指纹
+        let large = dir.path().join("large.mp4");
+        let mut data = vec![0u8; (HASH_SAMPLE_BYTES * 3) as usize];
+        std::fs::write(&large, &data).unwrap();
+        let before = content_hash(&large).unwrap();
+        data[(HASH_SAMPLE_BYTES * 3 / 2) as usize] = 1;
+        std::fs::write(&large, &data).unwrap();
+        assert_eq!(content_hash(&large).unwrap(), before);
+        *data.last_mut().unwrap() = 1;
+        std::fs::write(&large, &data).unwrap();
+        assert_ne!(content_hash(&large).unwrap(), before);
+    }
+}
@@ -0,0 +1,136 @@
+//! Filename-based date inference.
+//! Many files without EXIF (WhatsApp images, screenshots, Android camera exports)
+//! still encode the capture date in their file name, e.g.:
+//! - `IMG-20190405-WA0003.jpg`
+//! - `Screenshot_20210203-101010.png`
+//! - `PXL_20210203_101010123.jpg`
+//! - `2021-02-03 10.10.10.jpg`
+
+use chrono::{Datelike, NaiveDate, NaiveDateTime, NaiveTime, Utc};
+
+/// Parse a capture date from a file name.
+/// Returns None if no plausible date is found. Dates without a time part
+/// are returned at 00:00:00.
+pub fn parse_filename_date(file_name: &str) -> Option<NaiveDateTime> {
+    let stem = file_name
+        .rsplit_once('.')
+        .map(|(stem, _)| stem)
+        .unwrap_or(file_name);
+
+    // Split into runs of consecutive ASCII digits
+    let runs: Vec<&str> = stem
+        .split(|c: char| !c.is_ascii_digit())
+        .filter(|s| !s.is_empty())
+        .collect();
+
+    for (i, run) in runs.iter().enumerate() {
+        // Compact form: YYYYMMDD, optionally followed by HHMMSS in the same or next run
+        if run.len() >= 8 {
+            if let Some(date) = parse_ymd(&run[0..4], &run[4..6], &run[6..8]) {
+                let time = if run.len() >= 14 {
+                    parse_hms(&run[8..10], &run[10..12], &run[12..14])
+                } else if run.len() == 8 {
+                    runs.get(i + 1)
+                        .filter(|next| next.len() >= 6)
+                        .and_then(|next| parse_hms(&next[0..2], &next[2..4], &next[4..6]))
+                } else {
+                    None
+                };
+                return Some(date.and_time(time.unwrap_or(NaiveTime::MIN)));
+            }
+        }
+
+        // Separated form: YYYY-MM-DD, optionally followed by HH.MM.SS
+        if run.len() == 4 && i + 2 < runs.len() && runs[i + 1].len() == 2 && runs[i + 2].len() == 2 {
+            if let Some(date) = parse_ymd(run, runs[i + 1], runs[i + 2]) {
+                let time = match runs.get(i + 3..i + 6) {
+                    Some([h, m, s]) if h.len() == 2 && m.len() == 2 && s.len() == 2 => parse_hms(h, m, s),
+                    _ => None,
+                };
+                return Some(date.and_time(time.unwrap_or(NaiveTime::MIN)));
+            }
+        }
+    }
+
+    None
+}
+
+/// Parse year/month/day digits, rejecting implausible years (before 1990 or in the future)
+fn parse_ymd(year: &str, month: &str, day: &str) -> Option<NaiveDate> {
+    let year: i32 = year.parse().ok()?;
+    let month: u32 = month.parse().ok()?;
+    let day: u32 = day.parse().ok()?;
+
+    if year < 1990 || year > Utc::now().year() + 1 {
+        return None;
+    }
+
+    NaiveDate::from_ymd_opt(year, month, day)
+}
+
+/// Parse hour/minute/second digits
+fn parse_hms(hour: &str, minute: &str, second: &str) -> Option<NaiveTime> {
+    NaiveTime::from_hms_opt(hour.parse().ok()?, minute.parse().ok()?, second.parse().ok()?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dt(y: i32, mo: u32, d: u32, h: u32, mi: u32, s: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(y, mo, d)
+            .unwrap()
+            .and_hms_opt(h, mi, s)
+            .unwrap()
+    }
+
+    #[test]
+    fn test_whatsapp_date_only() {
+        assert_eq!(parse_filename_date("IMG-20190405-WA0003.jpg"), Some(dt(2019, 4, 5, 0, 0, 0)));
+        assert_eq!(parse_filename_date("VID-20190405-WA0001.mp4"), Some(dt(2019, 4, 5, 0, 0, 0)));
+    }
+
+    #[test]
+    fn test_screenshot_with_time() {
+        assert_eq!(parse_filename_date("Screenshot_20210203-101010.png"), Some(dt(2021, 2, 3, 10, 10, 10)));
+        assert_eq!(parse_filename_date("IMG_20210203_101010.jpg"), Some(dt(2021, 2, 3, 10, 10, 10)));
+    }
+
+    #[test]
+    fn test_pixel_milliseconds() {
+        assert_eq!(parse_filename_date("PXL_20210203_101010123.jpg"), Some(dt(2021, 2, 3, 10, 10, 10)));
+    }
+
+    #[test]
+    fn test_compact_datetime() {
+        assert_eq!(parse_filename_date("20210203101010.jpg"), Some(dt(2021, 2, 3, 10, 10, 10)));
+    }
+
+    #[test]
+    fn test_separated_date() {
+        assert_eq!(parse_filename_date("2021-02-03 10.10.10.jpg"), Some(dt(2021, 2, 3, 10, 10, 10)));
+        assert_eq!(parse_filename_date("Screenshot 2021-02-03 at 10.10.10.png"), Some(dt(2021, 2, 3, 10, 10, 10)));
+        assert_eq!(parse_filename_date("trip_2021_02_03.jpg"), Some(dt(2021, 2, 3, 0, 0, 0)));
+    }
+
+    #[test]
+    fn test_no_date() {
+        assert_eq!(parse_filename_date("DSC_0001.jpg"), None);
+        assert_eq!(parse_filename_date("vacation.jpg"), None);
+        assert_eq!(parse_filename_date(""), None);
+    }
+
+    #[test]
+    fn test_invalid_dates_rejected() {
+        // 月份/日期非法
+        assert_eq!(parse_filename_date("IMG_20211332_000000.jpg"), None);
+        // 年份不合理（Unix 时间戳等）
+        assert_eq!(parse_filename_date("1617181920.jpg"), None);
+        assert_eq!(parse_filename_date("IMG_19800101.jpg"), None);
+    }
+
+    #[test]
+    fn test_invalid_time_falls_back_to_midnight() {
+        assert_eq!(parse_filename_date("IMG_20210203_256161.jpg"), Some(dt(2021, 2, 3, 0, 0, 0)));
+    }
+}
@@ -1,10 +1,19 @@
+use crate::db::MediaSubImage;
+use crate::processors::decode_guard::{self, DEFAULT_MAX_DECODE_PIXELS};
+use crate::processors::extensions;
 use crate::processors::image_processor::extract_exif;
+use crate::processors::panorama;
+use crate::processors::sharpness;
 use crate::processors::processor_trait::{
-    MediaMetadata, MediaProcessor, MediaType, ProcessingError,
+    run_cpu_bound, MediaMetadata, MediaProcessor, MediaType, ProcessingError,
 };
+use crate::services::background_priority;
+use crate::services::thumbnail_metrics::{self, Stage, StageTimer, ThumbnailMetrics};
 use crate::services::TranscodingPool;
+use crate::utils::color_profile;
+use crate::utils::{ThumbnailFormat, ThumbnailOptions, ThumbnailPipeline};
 use async_trait::async_trait;
-use libheif_rs::{ColorSpace, HeifContext, LibHeif, RgbChroma};
+use libheif_rs::{AuxiliaryImagesFilter, ColorSpace, HeifContext, ImageHandle, LibHeif, RgbChroma};
 use std::path::Path;
 use std::sync::Arc;
 
@@ -12,24 +21,104 @@ use std::sync::Arc;
 /// Uses libheif-rs for HEIC decoding
 pub struct HeifImageProcessor {
     transcoding_pool: Option<Arc<TranscodingPool>>,
+    /// Primary images above this pixel count are not decoded; the embedded thumbnail is used instead
+    max_decode_pixels: u64,
+    /// Compute sharpness::blur_score during processing (decodes the primary image)
+    blur_detection: bool,
+    /// Extract maker-note attributes (see maker_notes)
+    maker_notes: bool,
+    /// Stage timings of generated thumbnails
+    metrics: Option<Arc<ThumbnailMetrics>>,
 }
 
 impl HeifImageProcessor {
     pub fn new(transcoding_pool: Option<Arc<TranscodingPool>>) -> Self {
-        Self { transcoding_pool }
+        Self {
+            transcoding_pool,
+            max_decode_pixels: DEFAULT_MAX_DECODE_PIXELS,
+            blur_detection: false,
+            maker_notes: false,
+            metrics: None,
+        }
+    }
+
+    /// Set the pixel limit for full-resolution decoding
+    pub fn with_max_decode_pixels(mut self, max_decode_pixels: u64) -> Self {
+        self.max_decode_pixels = max_decode_pixels;
+        self
+    }
+
+    /// Compute a blur score for every processed HEIC (LATTE_BLUR_DETECTION)
+    pub fn with_blur_detection(mut self, enabled: bool) -> Self {
+        self.blur_detection = enabled;
+        self
+    }
+
+    /// Store maker-note values as file attributes (LATTE_MAKER_NOTES)
+    pub fn with_maker_notes(mut self, enabled: bool) -> Self {
+        self.maker_notes = enabled;
+        self
+    }
+
+    /// Record decode/resize/encode timings of thumbnails
+    pub fn with_thumbnail_metrics(mut self, metrics: Arc<ThumbnailMetrics>) -> Self {
+        self.metrics = Some(metrics);
+        self
+    }
+
+    /// Score the primary image's sharpness; failures only cost the score
+    async fn compute_blur_score(&self, path: &Path) -> Option<f64> {
+        let owned_path = path.to_path_buf();
+        let max_decode_pixels = self.max_decode_pixels;
+        let result = run_cpu_bound(self.transcoding_pool.as_ref(), move || {
+            // libheif 已按分析尺寸缩放，高质量编码后再解码，压缩损失对方差的影响可以忽略
+            let options = ThumbnailOptions::new(sharpness::ANALYSIS_SIZE, BLUR_ANALYSIS_QUALITY, false);
+            let bytes = transcoding_generate_heic_thumbnail(&owned_path, None, options, max_decode_pixels, &mut StageTimer::start())?
+            .ok_or_else(|| ProcessingError::Processing("No primary image".to_string()))?;
+            Ok::<f64, ProcessingError>(sharpness::blur_score(&image::load_from_memory(&bytes)?))
+        })
+        .await;
+
+        match result {
+            Ok(Ok(score)) => Some(score),
+            Ok(Err(e)) | Err(e) => {
+                tracing::warn!("Blur detection failed for {:?}: {}", path, e);
+                None
+            }
+        }
     }
+}
+
+/// JPEG quality of the intermediate analysis image used for blur detection
+const BLUR_ANALYSIS_QUALITY: f32 = 0.95;
+
+/// Auxiliary image type of Apple's portrait effects matte
+const PORTRAIT_MATTE_AUX_TYPE: &str = "portraiteffectsmatte";
 
-    const SUPPORTED_EXTENSIONS: &[&str] = &["heic", "heif"];
+/// Dimensions and auxiliary image flags read from a HEIC header
+struct HeifInfo {
+    width: u32,
+    height: u32,
+    has_depth_map: bool,
+    has_portrait_matte: bool,
+    color_profile: Option<String>,
+    sub_images: Vec<MediaSubImage>,
 }
 
 #[async_trait]
 impl MediaProcessor for HeifImageProcessor {
+    fn name(&self) -> &'static str {
+        "heif"
+    }
+
+    fn extensions(&self) -> Vec<String> {
+        extensions::with_extra(extensions::HEIF_EXTENSIONS, &[])
+    }
+
     fn supports(&self, path: &Path) -> bool {
-        if let Some(ext) = path.extension().and_then(|e| e.to_str()) {
-            Self::SUPPORTED_EXTENSIONS.contains(&ext.to_lowercase().as_str())
-        } else {
-            false
-        }
+        path.extension()
+            .and_then(|e| e.to_str())
+            .is_some_and(|ext| extensions::HEIF_EXTENSIONS.contains(&ext.to_lowercase().as_str()))
     }
 
     fn priority(&self) -> i32 {
@@ -45,23 +134,43 @@ impl MediaProcessor for HeifImageProcessor {
 
         // Use libheif-rs to read HEIC dimensions (format-specific)
         let path_buf = path.to_path_buf();
-        let dimensions = tokio::task::spawn_blocking(move || {
+        let info = background_priority::spawn_blocking(move || {
             let path_str = path_buf.to_string_lossy();
             let ctx = HeifContext::read_from_file(&path_str)
                 .map_err(|e| ProcessingError::Processing(e.to_string()))?;
             let handle = ctx.primary_image_handle()
                 .map_err(|e| ProcessingError::Processing(e.to_string()))?;
-            Ok::<(u32, u32), ProcessingError>((handle.width(), handle.height()))
+            Ok::<HeifInfo, ProcessingError>(HeifInfo {
+                width: handle.width(),
+                height: handle.height(),
+                has_depth_map: handle.has_depth_image(),
+                has_portrait_matte: has_portrait_matte(&handle),
+                color_profile: handle
+                    .color_profile_raw()
+                    .and_then(|profile| color_profile::profile_description(&profile.data)),
+                sub_images: top_level_images(&ctx),
+            })
         })
         .await
         .map_err(|e| ProcessingError::Processing(e.to_string()))??;
 
-        metadata.width = Some(dimensions.0 as i32);
-        metadata.height = Some(dimensions.1 as i32);
+        metadata.width = Some(info.width as i32);
+        metadata.height = Some(info.height as i32);
+        metadata.has_depth_map = info.has_depth_map;
+        metadata.has_portrait_matte = info.has_portrait_matte;
+        metadata.color_profile = info.color_profile;
+        // 只有多图容器（连拍等）才记录子图，单图 HEIC 记为空以清除旧记录
+        metadata.sub_images = Some(if info.sub_images.len() > 1 { info.sub_images } else { Vec::new() });
         metadata.mime_type = Some("image/heic".to_string());
 
         // Extract EXIF metadata (supports HEIC via kamadak-exif)
-        extract_exif(path, &mut metadata);
+        extract_exif(path, &mut metadata, self.maker_notes);
+
+        let xmp = panorama::read_xmp(path);
+        metadata.projection = panorama::detect_image_projection(xmp.as_deref(), metadata.width, metadata.height);
+        if self.blur_detection {
+            metadata.blur_score = self.compute_blur_score(path).await;
+        }
 
         Ok(metadata)
     }
@@ -71,39 +180,169 @@ impl MediaProcessor for HeifImageProcessor {
         path: &Path,
         target_size: u32,
         quality: f32,
+        format: ThumbnailFormat,
         fit_to_height: bool,
     ) -> Result<Option<Vec<u8>>, ProcessingError> {
         let path = path.to_path_buf();
-        let pool = self.transcoding_pool.clone();
-
-        // Use transcoding pool if available, otherwise fallback to spawn_blocking
-        if let Some(ref pool) = pool {
-            // Run in transcoding pool (rayon thread)
-            pool.scope(|_| {
-                // Synchronous HEIC transcoding logic
-                transcoding_generate_heic_thumbnail(&path, target_size, quality, fit_to_height)
-            })
-        } else {
-            // Fallback to spawn_blocking
-            tokio::task::spawn_blocking(move || {
-                transcoding_generate_heic_thumbnail(&path, target_size, quality, fit_to_height)
+        let max_decode_pixels = self.max_decode_pixels;
+        let options = ThumbnailOptions::new(target_size, quality, fit_to_height).with_format(format);
+        let metrics = self.metrics.clone();
+
+        run_cpu_bound(self.transcoding_pool.as_ref(), move || {
+            timed_heic_thumbnail(&path, None, options, max_decode_pixels, metrics.as_deref())
+        })
+        .await?
+    }
+
+    async fn generate_item_thumbnail(
+        &self,
+        path: &Path,
+        item: u32,
+        target_size: u32,
+        quality: f32,
+        format: ThumbnailFormat,
+        fit_to_height: bool,
+    ) -> Result<Option<Vec<u8>>, ProcessingError> {
+        let path = path.to_path_buf();
+        let max_decode_pixels = self.max_decode_pixels;
+        let options = ThumbnailOptions::new(target_size, quality, fit_to_height).with_format(format);
+        let metrics = self.metrics.clone();
+
+        run_cpu_bound(self.transcoding_pool.as_ref(), move || {
+            timed_heic_thumbnail(&path, Some(item), options, max_decode_pixels, metrics.as_deref())
+        })
+        .await?
+    }
+}
+
+/// Handles of all top-level images, in file order
+fn top_level_handles(ctx: &HeifContext) -> Vec<ImageHandle> {
+    ctx.image_ids()
+        .into_iter()
+        .filter_map(|id| ctx.image_handle(id).ok())
+        .collect()
+}
+
+/// Enumerate the top-level images of a HEIC (more than one for bursts and image collections)
+fn top_level_images(ctx: &HeifContext) -> Vec<MediaSubImage> {
+    top_level_handles(ctx)
+        .iter()
+        .enumerate()
+        .map(|(index, handle)| MediaSubImage {
+            item_index: index as i32,
+            width: Some(handle.width() as i32),
+            height: Some(handle.height() as i32),
+            is_primary: handle.is_primary(),
+        })
+        .collect()
+}
+
+/// Whether the image has a portrait effects matte among its auxiliary images
+fn has_portrait_matte(handle: &ImageHandle) -> bool {
+    handle
+        .auxiliary_images(AuxiliaryImagesFilter::OMIT_ALPHA)
+        .iter()
+        .filter_map(|aux| aux.auxiliary_type().ok())
+        .any(|aux_type| aux_type.to_lowercase().contains(PORTRAIT_MATTE_AUX_TYPE))
+}
+
+/// Decode the first depth map of a HEIC as a grayscale PNG (16-bit for high bit-depth maps).
+/// Returns None when the file has no depth image.
+pub fn extract_depth_map_png(path: &Path) -> Result<Option<Vec<u8>>, ProcessingError> {
+    let path_str = path.to_string_lossy();
+    let ctx = HeifContext::read_from_file(&path_str)
+        .map_err(|e| ProcessingError::Processing(e.to_string()))?;
+    let handle = ctx.primary_image_handle()
+        .map_err(|e| ProcessingError::Processing(e.to_string()))?;
+
+    let mut ids = vec![0; handle.number_of_depth_images() as usize];
+    let count = handle.depth_image_ids(&mut ids);
+    let Some(&depth_id) = ids[..count].first() else {
+        return Ok(None);
+    };
+    let depth_handle = handle.depth_image_handle(depth_id)
+        .map_err(|e| ProcessingError::Processing(e.to_string()))?;
+
+    let image = LibHeif::new()
+        .decode(&depth_handle, ColorSpace::Monochrome, None)
+        .map_err(|e| ProcessingError::Processing(e.to_string()))?;
+    let planes = image.planes();
+    let plane = planes.y
+        .as_ref()
+        .ok_or_else(|| ProcessingError::Processing("No luma plane in depth image".to_string()))?;
+
+    let (width, height, stride) = (plane.width, plane.height, plane.stride);
+    let mut png = std::io::Cursor::new(Vec::new());
+    if plane.storage_bits_per_pixel > 8 {
+        // 高位深深度图：每像素两个字节（本机字节序），保留精度输出 16 位 PNG
+        let shift = 16 - plane.bits_per_pixel.min(16);
+        let data: Vec<u16> = (0..height as usize)
+            .flat_map(|row| {
+                plane.data[row * stride..row * stride + width as usize * 2]
+                    .chunks_exact(2)
+                    .map(|px| u16::from_ne_bytes([px[0], px[1]]) << shift)
             })
-            .await
-            .map_err(|e| ProcessingError::Processing(e.to_string()))?
-        }
+            .collect();
+        let depth = image::ImageBuffer::<image::Luma<u16>, _>::from_raw(width, height, data)
+            .ok_or_else(|| ProcessingError::Processing("Invalid depth image size".to_string()))?;
+        depth.write_to(&mut png, image::ImageFormat::Png)?;
+    } else {
+        let data: Vec<u8> = (0..height as usize)
+            .flat_map(|row| plane.data[row * stride..row * stride + width as usize].to_owned())
+            .collect();
+        let depth = image::GrayImage::from_raw(width, height, data)
+            .ok_or_else(|| ProcessingError::Processing("Invalid depth image size".to_string()))?;
+        depth.write_to(&mut png, image::ImageFormat::Png)?;
+    }
+
+    Ok(Some(png.into_inner()))
+}
+
+/// Largest embedded thumbnail that fits within the pixel limit
+fn largest_embedded_thumbnail(handle: &ImageHandle, max_pixels: u64) -> Option<ImageHandle> {
+    let mut ids = vec![0; handle.number_of_thumbnails()];
+    let count = handle.thumbnail_ids(&mut ids);
+    ids.truncate(count);
+
+    ids.into_iter()
+        .filter_map(|id| handle.thumbnail(id).ok())
+        .filter(|thumb| !decode_guard::exceeds_limit(thumb.width(), thumb.height(), max_pixels))
+        .max_by_key(|thumb| decode_guard::pixel_count(thumb.width(), thumb.height()))
+}
+
+/// Generate a thumbnail and record its stage timings in `metrics`
+fn timed_heic_thumbnail(
+    path: &Path,
+    item: Option<u32>,
+    options: ThumbnailOptions,
+    max_decode_pixels: u64,
+    metrics: Option<&ThumbnailMetrics>,
+) -> Result<Option<Vec<u8>>, ProcessingError> {
+    let mut timer = StageTimer::start();
+    let bytes = transcoding_generate_heic_thumbnail(path, item, options, max_decode_pixels, &mut timer)?;
+    if let (Some(metrics), Some(_)) = (metrics, &bytes) {
+        metrics.record(&thumbnail_metrics::source_format(path), options.format, &timer);
     }
+    Ok(bytes)
 }
 
-/// Synchronous HEIC thumbnail generation for transcoding pool
+/// Synchronous HEIC thumbnail generation for transcoding pool.
+/// `item` selects a top-level image by index instead of the primary image; None if out of range.
+/// Stage durations are added to `timer`.
 fn transcoding_generate_heic_thumbnail(
     path: &Path,
-    target_size: u32,
-    quality: f32,
-    fit_to_height: bool,
+    item: Option<u32>,
+    options: ThumbnailOptions,
+    max_decode_pixels: u64,
+    timer: &mut StageTimer,
 ) -> Result<Option<Vec<u8>>, ProcessingError> {
     // 读取 EXIF Orientation，用于处理竖拍等方向变换
     // 需要在缩放前检查方向，因为 90/270 度旋转会交换宽高
-    let orientation = crate::processors::image_processor::read_exif_orientation(path);
+    // EXIF 只描述主图，连拍中的其他子图不使用
+    let orientation = match item {
+        None => crate::processors::image_processor::read_exif_orientation(path),
+        Some(_) => None,
+    };
     let swaps_dimensions = orientation.as_ref().map_or(false, |o| {
         use image::metadata::Orientation;
         matches!(
@@ -119,8 +358,35 @@ fn transcoding_generate_heic_thumbnail(
     let path_str = path.to_string_lossy();
     let ctx = HeifContext::read_from_file(&path_str)
         .map_err(|e| ProcessingError::Processing(e.to_string()))?;
-    let handle = ctx.primary_image_handle()
-        .map_err(|e| ProcessingError::Processing(e.to_string()))?;
+    let handle = match item {
+        None => ctx.primary_image_handle()
+            .map_err(|e| ProcessingError::Processing(e.to_string()))?,
+        Some(index) => match top_level_handles(&ctx).into_iter().nth(index as usize) {
+            Some(handle) => handle,
+            None => return Ok(None),
+        },
+    };
+
+    // 内嵌缩略图通常不带配置文件，按原图的配置文件转换
+    let icc = handle.color_profile_raw().map(|profile| profile.data);
+
+    // 尺寸来自文件头；超大主图不解码，改用内嵌缩略图（libheif 没有按目标尺寸解码的选项）
+    let (width, height) = (handle.width(), handle.height());
+    let handle = if decode_guard::exceeds_limit(width, height, max_decode_pixels) {
+        let thumbnail = largest_embedded_thumbnail(&handle, max_decode_pixels).ok_or_else(|| {
+            ProcessingError::Processing(format!(
+                "HEIC too large to decode: {}x{} exceeds {} pixels and has no usable embedded thumbnail",
+                width, height, max_decode_pixels
+            ))
+        })?;
+        tracing::info!(
+            "Using {}x{} embedded thumbnail for {:?} ({}x{})",
+            thumbnail.width(), thumbnail.height(), path, width, height
+        );
+        thumbnail
+    } else {
+        handle
+    };
 
     // Decode to RGBA
     // HEIC 文件使用 YCbCr 颜色空间，libheif 解码时使用 Rgba 会自动转换
@@ -130,31 +396,22 @@ fn transcoding_generate_heic_thumbnail(
         ColorSpace::Rgb(RgbChroma::Rgba),
         None,
     ).map_err(|e| ProcessingError::Processing(e.to_string()))?;
+    timer.lap(Stage::Decode);
 
-    // If target_size is 0, use full size (no resize)
-    let scaled = if target_size == 0 {
+    // 用 libheif 直接缩放到目标尺寸（按方向校正后的宽高计算，再换回存储方向）
+    let pipeline = ThumbnailPipeline::new(options);
+    let (ew, eh) = if swaps_dimensions {
+        (image.height(), image.width())
+    } else {
+        (image.width(), image.height())
+    };
+    let (target_w, target_h) = pipeline.target_dimensions(ew, eh);
+    let scaled = if (target_w, target_h) == (ew, eh) {
         image
     } else {
-        // 使用方向校正后的有效宽高计算缩放尺寸
-        let (ew, eh) = if swaps_dimensions {
-            (image.height(), image.width())
-        } else {
-            (image.width(), image.height())
-        };
-        let (target_w, target_h) = if fit_to_height {
-            // fit_to_height=true: 按固定高度缩放
-            let ratio = ew as f64 / eh as f64;
-            ((target_size as f64 * ratio) as u32, target_size)
-        } else {
-            // fit_to_height=false: 按固定宽度缩放
-            (target_size, (target_size as f64 * (eh as f64 / ew as f64)) as u32)
-        };
-        if ew > target_w || eh > target_h {
-            image.scale(target_w, target_h, None)
-                .map_err(|e| ProcessingError::Processing(e.to_string()))?
-        } else {
-            image
-        }
+        let (scale_w, scale_h) = if swaps_dimensions { (target_h, target_w) } else { (target_w, target_h) };
+        image.scale(scale_w, scale_h, None)
+            .map_err(|e| ProcessingError::Processing(e.to_string()))?
     };
 
     // Get interleaved RGBA data
@@ -187,22 +444,14 @@ fn transcoding_generate_heic_thumbnail(
             .ok_or_else(|| ProcessingError::Processing("Failed to create image from HEIC data".to_string()))?
     };
 
-    // RGBA to RGB conversion (discard alpha channel)
-    // JPEG encoder requires 3-channel RGB data
     let mut dyn_image = image::DynamicImage::ImageRgba8(rgba_image);
     if let Some(orientation) = orientation {
         dyn_image.apply_orientation(orientation);
     }
-    let rgb_image = dyn_image.to_rgb8();
-
-    // Encode as JPEG
-    let mut jpeg_bytes = Vec::new();
-    let mut encoder = image::codecs::jpeg::JpegEncoder::new_with_quality(
-        &mut jpeg_bytes,
-        (quality * 100.0) as u8,
-    );
-    encoder.encode_image(&rgb_image)
-        .map_err(|e| ProcessingError::Processing(e.to_string()))?;
+    let dyn_image = color_profile::convert_to_srgb(dyn_image, icc.as_deref());
+    timer.lap(Stage::Resize);
 
-    Ok(Some(jpeg_bytes))
+    let bytes = pipeline.finish(dyn_image)?;
+    timer.lap(Stage::Encode);
+    Ok(Some(bytes))
 }
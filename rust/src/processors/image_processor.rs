@@ -1,7 +1,16 @@
-use crate::processors::processor_trait::{MediaMetadata, MediaProcessor, MediaType, ProcessingError};
+use crate::processors::decode_guard::{self, DEFAULT_MAX_DECODE_PIXELS};
+use crate::processors::processor_trait::{run_cpu_bound, MediaMetadata, MediaProcessor, MediaType, ProcessingError};
+use crate::processors::extensions;
+use crate::processors::panorama;
+use crate::processors::sharpness;
+use crate::services::thumbnail_metrics::{self, Stage, StageTimer, ThumbnailMetrics};
+use crate::services::TranscodingPool;
+use crate::utils::color_profile;
+use crate::utils::{ThumbnailFormat, ThumbnailOptions, ThumbnailPipeline};
 use async_trait::async_trait;
 use chrono::NaiveDateTime;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 
 /// EXIF Tag 枚举 - 基于实际日志分析
 /// 用于文档化和扩展EXIF字段提取
@@ -46,7 +55,7 @@ pub enum ExifTag {
 
     // === 厂商特定 (低优先级) ===
     Software,           // 305 - 软件版本
-    SerialNumber,       // 37520 - 相机序列号
+    SerialNumber,       // 42033 - 相机序列号（BodySerialNumber）
 }
 
 impl ExifTag {
@@ -92,7 +101,7 @@ impl ExifTag {
 
             // 厂商特定
             ("Tiff", 305) => Some(Self::Software),
-            ("Exif", 37520) => Some(Self::SerialNumber),
+            ("Exif", 42033) => Some(Self::SerialNumber),
 
             _ => None,
         }
@@ -134,7 +143,22 @@ impl ExifTag {
 }
 
 /// Standard image processor for JPEG, PNG, GIF, WebP, TIFF, BMP
-pub struct StandardImageProcessor;
+pub struct StandardImageProcessor {
+    /// Images above this pixel count are not decoded at full resolution
+    max_decode_pixels: u64,
+    /// ffmpeg binary used for DCT-scaled decoding of huge JPEGs
+    ffmpeg_path: Option<PathBuf>,
+    /// Dedicated pool for decode/resize/encode; spawn_blocking when None
+    transcoding_pool: Option<Arc<TranscodingPool>>,
+    /// Built-in extensions plus LATTE_EXTRA_IMAGE_EXTS
+    extensions: Vec<String>,
+    /// Compute sharpness::blur_score during processing (decodes the full image)
+    blur_detection: bool,
+    /// Extract maker-note attributes (see maker_notes)
+    maker_notes: bool,
+    /// Stage timings of generated thumbnails
+    metrics: Option<Arc<ThumbnailMetrics>>,
+}
 
 impl Default for StandardImageProcessor {
     fn default() -> Self {
@@ -144,20 +168,87 @@ impl Default for StandardImageProcessor {
 
 impl StandardImageProcessor {
     pub fn new() -> Self {
-        Self
+        Self {
+            max_decode_pixels: DEFAULT_MAX_DECODE_PIXELS,
+            ffmpeg_path: None,
+            transcoding_pool: None,
+            extensions: extensions::with_extra(extensions::IMAGE_EXTENSIONS, &[]),
+            blur_detection: false,
+            maker_notes: false,
+            metrics: None,
+        }
+    }
+
+    /// Also handle these extensions (LATTE_EXTRA_IMAGE_EXTS), decoded like the built-in formats
+    pub fn with_extra_extensions(mut self, extra: &[String]) -> Self {
+        self.extensions = extensions::with_extra(extensions::IMAGE_EXTENSIONS, extra);
+        self
+    }
+
+    /// Run thumbnail decode/resize/encode on the transcoding pool instead of tokio's blocking pool
+    pub fn with_transcoding_pool(mut self, transcoding_pool: Arc<TranscodingPool>) -> Self {
+        self.transcoding_pool = Some(transcoding_pool);
+        self
+    }
+
+    /// Set the decode memory guard: pixel limit and the ffmpeg used for scaled JPEG decoding
+    pub fn with_decode_guard(mut self, max_decode_pixels: u64, ffmpeg_path: Option<PathBuf>) -> Self {
+        self.max_decode_pixels = max_decode_pixels;
+        self.ffmpeg_path = ffmpeg_path;
+        self
+    }
+
+    /// Compute a blur score for every processed image (LATTE_BLUR_DETECTION)
+    pub fn with_blur_detection(mut self, enabled: bool) -> Self {
+        self.blur_detection = enabled;
+        self
     }
 
-    const SUPPORTED_EXTENSIONS: &[&str] = &["jpg", "jpeg", "png", "gif", "bmp", "webp", "tiff"];
+    /// Store maker-note values as file attributes (LATTE_MAKER_NOTES)
+    pub fn with_maker_notes(mut self, enabled: bool) -> Self {
+        self.maker_notes = enabled;
+        self
+    }
+
+    /// Record decode/resize/encode timings of thumbnails
+    pub fn with_thumbnail_metrics(mut self, metrics: Arc<ThumbnailMetrics>) -> Self {
+        self.metrics = Some(metrics);
+        self
+    }
+
+    /// Decode the image (honouring the decode guard) and score its sharpness.
+    /// Failures only cost the score, never the rest of the metadata.
+    async fn compute_blur_score(&self, path: &Path) -> Option<f64> {
+        let owned_path = path.to_path_buf();
+        let max_decode_pixels = self.max_decode_pixels;
+        let ffmpeg_path = self.ffmpeg_path.clone();
+        let result = run_cpu_bound(self.transcoding_pool.as_ref(), move || {
+            decode_guarded(&owned_path, max_decode_pixels, ffmpeg_path.as_deref()).map(|img| sharpness::blur_score(&img))
+        })
+        .await;
+
+        match result {
+            Ok(Ok(score)) => Some(score),
+            Ok(Err(e)) | Err(e) => {
+                tracing::warn!("Blur detection failed for {:?}: {}", path, e);
+                None
+            }
+        }
+    }
 }
 
 #[async_trait]
 impl MediaProcessor for StandardImageProcessor {
+    fn name(&self) -> &'static str {
+        "image"
+    }
+
+    fn extensions(&self) -> Vec<String> {
+        self.extensions.clone()
+    }
+
     fn supports(&self, path: &Path) -> bool {
-        if let Some(ext) = path.extension().and_then(|e| e.to_str()) {
-            Self::SUPPORTED_EXTENSIONS.contains(&ext.to_lowercase().as_str())
-        } else {
-            false
-        }
+        extensions::matches(path, &self.extensions)
     }
 
     fn priority(&self) -> i32 {
@@ -177,7 +268,14 @@ impl MediaProcessor for StandardImageProcessor {
         metadata.height = Some(height as i32);
 
         // Extract EXIF metadata for all supported image formats
-        extract_exif(path, &mut metadata);
+        extract_exif(path, &mut metadata, self.maker_notes);
+
+        let xmp = panorama::read_xmp(path);
+        metadata.projection = panorama::detect_image_projection(xmp.as_deref(), metadata.width, metadata.height);
+        metadata.color_profile = read_icc_profile(path).and_then(|icc| color_profile::profile_description(&icc));
+        if self.blur_detection {
+            metadata.blur_score = self.compute_blur_score(path).await;
+        }
 
         // Set MIME type
         if let Some(ext) = path.extension().and_then(|e| e.to_str()) {
@@ -200,66 +298,95 @@ impl MediaProcessor for StandardImageProcessor {
         path: &Path,
         target_size: u32,
         quality: f32,
+        format: ThumbnailFormat,
         fit_to_height: bool,
     ) -> Result<Option<Vec<u8>>, ProcessingError> {
         let path = path.to_path_buf();
         let orientation = read_exif_orientation(&path);
-        tokio::task::spawn_blocking(move || {
-            use image::{DynamicImage, ImageReader};
-
-            let mut img = ImageReader::open(path)?.decode()?;
+        let max_decode_pixels = self.max_decode_pixels;
+        let ffmpeg_path = self.ffmpeg_path.clone();
+        let metrics = self.metrics.clone();
+        run_cpu_bound(self.transcoding_pool.as_ref(), move || {
+            let mut timer = StageTimer::start();
+            let mut img = decode_guarded(&path, max_decode_pixels, ffmpeg_path.as_deref())?;
 
             if let Some(orientation) = orientation {
                 img.apply_orientation(orientation);
             }
+            timer.lap(Stage::Decode);
 
-            // If target_size is 0, return full-size transcoded image (no resize)
-            let result_img = if target_size == 0 {
-                // 先转为 RGBA8 保留 alpha，再用 ImageRgba8 包装后 to_rgb8()
-                // 这样会对透明/半透明区域进行白色背景合成，避免颜色错误
-                DynamicImage::ImageRgba8(img.to_rgba8()).to_rgb8()
-            } else {
-                // thumbnail(w, h) - 缩放到不超过 w×h 范围，保持宽高比
-                let thumb = if fit_to_height {
-                    // fit_to_height=true: 按固定高度缩放
-                    // 目标高度 = target_size，需要计算对应的宽度
-                    let ratio = img.width() as f64 / img.height() as f64;
-                    let target_width = (target_size as f64 * ratio) as u32;
-                    img.thumbnail(target_width, target_size)
-                } else {
-                    // fit_to_height=false: 按固定宽度缩放
-                    // 目标宽度 = target_size，高度按比例计算
-                    img.thumbnail(target_size, u32::MAX)
-                };
-                let thumb = thumb.to_rgba8();
-                // 转为 RGBA8 保留 alpha，再用 ImageRgba8 包装后 to_rgb8() 进行白色背景合成
-                DynamicImage::ImageRgba8(thumb).to_rgb8()
-            };
-
-            let mut bytes = Vec::new();
-            let mut encoder = image::codecs::jpeg::JpegEncoder::new_with_quality(
-                &mut bytes,
-                (quality * 100.0) as u8,
+            // 先缩放再做色彩转换，只需转换缩略图尺寸的像素
+            let pipeline = ThumbnailPipeline::new(
+                ThumbnailOptions::new(target_size, quality, fit_to_height).with_format(format),
             );
-            encoder.encode_image(&result_img)?;
+            let img = color_profile::convert_to_srgb(pipeline.resize(img), read_icc_profile(&path).as_deref());
+            timer.lap(Stage::Resize);
+            let bytes = pipeline.finish(img)?;
+            timer.lap(Stage::Encode);
 
+            if let Some(metrics) = metrics {
+                metrics.record(&thumbnail_metrics::source_format(&path), format, &timer);
+            }
             Ok(Some(bytes))
         })
-        .await
-        .map_err(|e| ProcessingError::Processing(e.to_string()))?
+        .await?
     }
 }
 
+/// Embedded ICC profile, read from the header without decoding pixel data
+/// (also covers the scaled ffmpeg decode, which loses the profile)
+fn read_icc_profile(path: &Path) -> Option<Vec<u8>> {
+    use image::{ImageDecoder, ImageReader};
+
+    let mut decoder = ImageReader::open(path).ok()?.into_decoder().ok()?;
+    decoder.icc_profile().ok().flatten()
+}
+
+/// Read dimensions from the image header only, without decoding pixel data
 fn get_image_dimensions(path: &Path) -> Result<(u32, u32), ProcessingError> {
-    use image::{ImageReader, GenericImageView};
+    use image::ImageReader;
 
-    let img = ImageReader::open(path)?.decode()?;
-    Ok(img.dimensions())
+    Ok(ImageReader::open(path)?.into_dimensions()?)
+}
+
+/// Decode an image for thumbnailing, refusing to allocate a full-resolution bitmap above the pixel limit.
+/// Oversized JPEGs are decoded at 1/2, 1/4 or 1/8 scale when ffmpeg is available; other formats fail.
+fn decode_guarded(
+    path: &Path,
+    max_decode_pixels: u64,
+    ffmpeg_path: Option<&Path>,
+) -> Result<image::DynamicImage, ProcessingError> {
+    use image::{ImageFormat, ImageReader};
+
+    let (width, height) = get_image_dimensions(path)?;
+    if !decode_guard::exceeds_limit(width, height, max_decode_pixels) {
+        return Ok(ImageReader::open(path)?.decode()?);
+    }
+
+    let too_large = || {
+        ProcessingError::Processing(format!(
+            "Image too large to decode: {}x{} exceeds {} pixels",
+            width, height, max_decode_pixels
+        ))
+    };
+
+    let is_jpeg = matches!(ImageFormat::from_path(path), Ok(ImageFormat::Jpeg));
+    match (is_jpeg, ffmpeg_path) {
+        (true, Some(ffmpeg)) => {
+            let shift = decode_guard::jpeg_scale_shift(width, height, max_decode_pixels).ok_or_else(too_large)?;
+            tracing::info!(
+                "Decoding {:?} ({}x{}) at 1/{} scale to stay within the pixel limit",
+                path, width, height, 1 << shift
+            );
+            decode_guard::decode_jpeg_scaled(ffmpeg, path, shift)
+        }
+        _ => Err(too_large()),
+    }
 }
 
 /// Extract EXIF metadata from image files (JPEG, HEIC, etc.)
 /// Uses kamadak-exif which supports multiple formats
-pub(crate) fn extract_exif(path: &Path, metadata: &mut MediaMetadata) {
+pub(crate) fn extract_exif(path: &Path, metadata: &mut MediaMetadata, maker_notes: bool) {
     use exif::Reader;
 
     let file_name = path.file_name().and_then(|n| n.to_str()).unwrap_or("unknown");
@@ -283,6 +410,17 @@ pub(crate) fn extract_exif(path: &Path, metadata: &mut MediaMetadata) {
         }
     };
 
+    if maker_notes {
+        metadata.attributes = Some(crate::processors::maker_notes::extract(&exif));
+    }
+
+    // 只取主图的方向：缩略图 IFD 也可能带 Orientation
+    metadata.orientation = exif
+        .get_field(exif::Tag::Orientation, exif::In::PRIMARY)
+        .and_then(|field| field.value.get_uint(0))
+        .filter(|value| (1..=8).contains(value))
+        .map(|value| value as i32);
+
     // GPS DMS 原始值暂存：Lat/Lon 与各自的 Ref 是独立 tag，出现顺序不可预测
     let mut lat_rational: Option<Vec<exif::Rational>> = None;
     let mut lon_rational: Option<Vec<exif::Rational>> = None;
@@ -296,7 +434,7 @@ pub(crate) fn extract_exif(path: &Path, metadata: &mut MediaMetadata) {
         match tag {
             // --- Time & Timestamp ---
             exif::Tag::DateTimeOriginal | exif::Tag::DateTimeDigitized => {
-                if let Ok(ts) = NaiveDateTime::parse_from_str(&value_str, "%Y-%m-%d %H:%M:%S") {
+                if let Some(ts) = parse_exif_timestamp(&value_str) {
                     if metadata.exif_timestamp.is_none() || tag == exif::Tag::DateTimeOriginal {
                         metadata.exif_timestamp = Some(ts);
                     }
@@ -304,16 +442,15 @@ pub(crate) fn extract_exif(path: &Path, metadata: &mut MediaMetadata) {
             }
             exif::Tag::OffsetTimeOriginal => {
                 // Timezone offset from DateTimeOriginal (e.g., "+08:00")
-                if !value_str.is_empty() {
-                    metadata.exif_timezone_offset = Some(value_str);
+                if let Some(offset) = parse_timezone_offset(&value_str) {
+                    metadata.exif_timezone_offset = Some(offset);
                 }
             }
-            exif::Tag::OffsetTime => {
+            exif::Tag::OffsetTime
                 // Fallback: timezone offset from DateTime
-                if metadata.exif_timezone_offset.is_none() && !value_str.is_empty() {
-                    metadata.exif_timezone_offset = Some(value_str);
+                if metadata.exif_timezone_offset.is_none() => {
+                    metadata.exif_timezone_offset = parse_timezone_offset(&value_str);
                 }
-            }
 
             // --- Camera Info ---
             exif::Tag::Make => {
@@ -487,6 +624,31 @@ pub(crate) fn clean_exif_string(s: &str) -> String {
     s.to_string()
 }
 
+/// Parse an EXIF date/time, as displayed by the exif crate ("2024-05-01 08:30:00")
+/// or in the raw tag format ("2024:05:01 08:30:00")
+pub(crate) fn parse_exif_timestamp(s: &str) -> Option<NaiveDateTime> {
+    NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M:%S")
+        .or_else(|_| NaiveDateTime::parse_from_str(s, "%Y:%m:%d %H:%M:%S"))
+        .ok()
+}
+
+/// Validate an EXIF OffsetTime value ("+08:00", "-05:30").
+/// 相机写入的空白占位（"   :  "）等无效值返回 None，不入库
+pub(crate) fn parse_timezone_offset(s: &str) -> Option<String> {
+    let s = s.trim();
+    let bytes = s.as_bytes();
+    if bytes.len() != 6 || !matches!(bytes[0], b'+' | b'-') || bytes[3] != b':' {
+        return None;
+    }
+    let hours: u32 = s.get(1..3)?.parse().ok()?;
+    let minutes: u32 = s.get(4..6)?.parse().ok()?;
+    let digits = [bytes[1], bytes[2], bytes[4], bytes[5]];
+    if !digits.iter().all(u8::is_ascii_digit) || hours > 14 || minutes >= 60 {
+        return None;
+    }
+    Some(s.to_string())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -571,10 +733,22 @@ mod tests {
 
     #[test]
     fn test_standard_image_processor_default() {
-        let processor = StandardImageProcessor;
+        let processor = StandardImageProcessor::default();
         assert!(processor.supports(Path::new("test.jpg")));
     }
 
+    #[test]
+    fn test_decode_guarded_rejects_oversized_image() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("pano.png");
+        image::RgbImage::new(40, 10).save(&path).unwrap();
+
+        assert_eq!(get_image_dimensions(&path).unwrap(), (40, 10));
+        assert!(decode_guarded(&path, 400, None).is_ok());
+        // PNG 没有缩小解码路径，超限直接拒绝
+        assert!(decode_guarded(&path, 399, None).is_err());
+    }
+
     #[test]
     fn test_clean_exif_string() {
         assert_eq!(clean_exif_string("\"value\""), "value");
@@ -606,7 +780,9 @@ mod tests {
     #[test]
     fn test_exif_tag_vendor_specific() {
         assert_eq!(ExifTag::from_raw("Tiff", 305), Some(ExifTag::Software));
-        assert_eq!(ExifTag::from_raw("Exif", 37520), Some(ExifTag::SerialNumber));
+        assert_eq!(ExifTag::from_raw("Exif", 42033), Some(ExifTag::SerialNumber));
+        // 37520 是 SubSecTime，不是序列号
+        assert_eq!(ExifTag::from_raw("Exif", 37520), None);
     }
 
     #[test]
@@ -710,4 +886,65 @@ mod tests {
         assert_eq!(round6(0.0), 0.0);
         assert_eq!(round6(-33.86500000000001), -33.865);
     }
+
+    #[test]
+    fn test_parse_exif_timestamp_formats() {
+        let expected = chrono::NaiveDate::from_ymd_opt(2024, 5, 1).and_then(|d| d.and_hms_opt(8, 30, 0));
+        assert_eq!(parse_exif_timestamp("2024-05-01 08:30:00"), expected);
+        assert_eq!(parse_exif_timestamp("2024:05:01 08:30:00"), expected);
+        assert_eq!(parse_exif_timestamp("0000:00:00 00:00:00"), None);
+        assert_eq!(parse_exif_timestamp("    :  :     :  :  "), None);
+    }
+
+    #[test]
+    fn test_parse_timezone_offset() {
+        assert_eq!(parse_timezone_offset("+08:00"), Some("+08:00".to_string()));
+        assert_eq!(parse_timezone_offset(" -05:30 "), Some("-05:30".to_string()));
+        assert_eq!(parse_timezone_offset("   :  "), None);
+        assert_eq!(parse_timezone_offset("++8:00"), None);
+        assert_eq!(parse_timezone_offset("+15:00"), None);
+        assert_eq!(parse_timezone_offset("+08:60"), None);
+    }
+
+    // 真实照片里的 EXIF 字符串千奇百怪：解析函数对任意输入都不能 panic
+    proptest::proptest! {
+        #[test]
+        fn prop_clean_exif_string_strips_one_quote_pair(inner in ".*") {
+            proptest::prop_assert_eq!(clean_exif_string(&format!("\"{}\"", inner)), inner.clone());
+            proptest::prop_assert_eq!(clean_exif_string(&format!("  '{}' ", inner)), inner);
+        }
+
+        #[test]
+        fn prop_clean_exif_string_unquoted_is_trimmed(s in "[^\"']*") {
+            proptest::prop_assert_eq!(clean_exif_string(&s), s.trim());
+        }
+
+        #[test]
+        fn prop_exif_timestamp_round_trip(secs in -2_208_988_800i64..4_102_444_800i64) {
+            let ts = chrono::DateTime::from_timestamp(secs, 0).unwrap().naive_utc();
+            let raw = ts.format("%Y:%m:%d %H:%M:%S").to_string();
+            let displayed = ts.format("%Y-%m-%d %H:%M:%S").to_string();
+            proptest::prop_assert_eq!(parse_exif_timestamp(&raw), Some(ts));
+            proptest::prop_assert_eq!(parse_exif_timestamp(&displayed), Some(ts));
+        }
+
+        #[test]
+        fn prop_exif_timestamp_arbitrary_input(s in "\\PC*") {
+            let _ = parse_exif_timestamp(&s);
+        }
+
+        #[test]
+        fn prop_timezone_offset_valid_round_trip(sign in "[+-]", hours in 0u32..=14, minutes in 0u32..60) {
+            let offset = format!("{}{:02}:{:02}", sign, hours, minutes);
+            proptest::prop_assert_eq!(parse_timezone_offset(&offset), Some(offset.clone()));
+        }
+
+        #[test]
+        fn prop_timezone_offset_arbitrary_input(s in "\\PC*") {
+            if let Some(offset) = parse_timezone_offset(&s) {
+                proptest::prop_assert_eq!(offset.len(), 6);
+                proptest::prop_assert!(offset.starts_with('+') || offset.starts_with('-'));
+            }
+        }
+    }
 }
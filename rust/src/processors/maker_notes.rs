@@ -0,0 +1,366 @@
+//! 厂商 MakerNote 解析（LATTE_MAKER_NOTES，默认关闭）
+//! MakerNote 是 EXIF 中厂商私有的 IFD，标准字段里没有的拍摄信息（场景模式、机身序列号等）只记录在这里。
+//! 目前解析 Apple、Sony、Canon 三种格式中的少量字段，结果与标准 EXIF 中的对应字段合并后
+//! 写入 media_file_attributes 键值表，新增字段不需要改表结构。
+//! 各厂商格式没有公开规范，字段位置参考 ExifTool 的标签表；无法识别的内容直接忽略。
+
+use crate::db::MediaAttribute;
+
+/// Source recorded for attributes extracted from EXIF and maker notes
+pub const SOURCE: &str = "exif";
+
+/// Subject distance in metres
+pub const SUBJECT_DISTANCE: &str = "subject_distance";
+/// Scene / capture mode chosen on the camera (e.g. "Portrait", "Night Scene")
+pub const SHOT_MODE: &str = "shot_mode";
+/// Camera body serial number
+pub const SERIAL_NUMBER: &str = "serial_number";
+
+/// Values read from a maker note
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct MakerNote {
+    pub subject_distance: Option<f64>,
+    pub shot_mode: Option<String>,
+    pub serial_number: Option<String>,
+}
+
+/// Attributes of one file: standard EXIF values first, maker-note values fill the gaps.
+/// Empty when nothing was found, so a rescan clears stale values.
+pub fn extract(exif: &exif::Exif) -> Vec<MediaAttribute> {
+    let field = |tag| exif.get_field(tag, exif::In::PRIMARY);
+    let ascii = |tag| {
+        field(tag).and_then(|f| match &f.value {
+            exif::Value::Ascii(values) => values.first().and_then(|v| clean_ascii(v)),
+            _ => None,
+        })
+    };
+
+    let make = ascii(exif::Tag::Make).unwrap_or_default();
+    let note = match field(exif::Tag::MakerNote).map(|f| &f.value) {
+        Some(exif::Value::Undefined(_, offset)) => {
+            parse(&make, exif.buf(), *offset as usize, exif.little_endian()).unwrap_or_default()
+        }
+        _ => MakerNote::default(),
+    };
+
+    // SubjectDistance 为 0 表示未知，0xFFFFFFFF 表示无穷远
+    let subject_distance = field(exif::Tag::SubjectDistance)
+        .and_then(|f| match &f.value {
+            exif::Value::Rational(values) => values.first().map(|r| r.to_f64()),
+            _ => None,
+        })
+        .filter(|d| d.is_finite() && *d > 0.0 && *d < u32::MAX as f64)
+        .or(note.subject_distance);
+    let serial_number = ascii(exif::Tag::BodySerialNumber).or(note.serial_number);
+
+    let mut attributes = Vec::new();
+    let mut push = |key: &str, value: Option<String>| {
+        if let Some(value) = value {
+            attributes.push(MediaAttribute {
+                key: key.to_string(),
+                value,
+                source: SOURCE.to_string(),
+            });
+        }
+    };
+    push(SUBJECT_DISTANCE, subject_distance.map(|d| format!("{}", (d * 100.0).round() / 100.0)));
+    push(SHOT_MODE, note.shot_mode);
+    push(SERIAL_NUMBER, serial_number);
+    attributes
+}
+
+/// Parse the maker note starting at `offset` of the TIFF data `tiff`.
+/// None for makers without a parser or when the note is malformed.
+pub fn parse(make: &str, tiff: &[u8], offset: usize, little_endian: bool) -> Option<MakerNote> {
+    let note = tiff.get(offset..)?;
+    let make = make.trim().to_ascii_lowercase();
+    if note.starts_with(b"Apple iOS\0") {
+        parse_apple(tiff, offset)
+    } else if make.starts_with("sony") {
+        parse_sony(tiff, offset, little_endian)
+    } else if make.starts_with("canon") {
+        parse_canon(tiff, offset, little_endian)
+    } else {
+        None
+    }
+}
+
+/// Apple: "Apple iOS\0" + version + "MM" header, big-endian IFD at +14 with offsets
+/// relative to the start of the note
+fn parse_apple(tiff: &[u8], offset: usize) -> Option<MakerNote> {
+    let little_endian = tiff.get(offset + 12..offset + 14)? == b"II";
+    let ifd = Ifd::read(tiff, offset + 14, offset, little_endian)?;
+    Some(MakerNote {
+        // ImageCaptureType
+        shot_mode: ifd.uint(0x0014, 0).and_then(|v| {
+            Some(match v {
+                1 => "ProRAW",
+                2 => "Portrait",
+                10 => "Photo",
+                11 => "Manual Focus",
+                12 => "Scene",
+                _ => return None,
+            })
+        }).map(str::to_string),
+        ..MakerNote::default()
+    })
+}
+
+/// Sony: optional 12-byte "SONY DSC " / "SONY CAM " / "SONY MOBILE" header, IFD offsets
+/// relative to the TIFF header. The serial number is stored encrypted and not read.
+fn parse_sony(tiff: &[u8], offset: usize, little_endian: bool) -> Option<MakerNote> {
+    let note = tiff.get(offset..)?;
+    let start = if note.starts_with(b"SONY") { offset + 12 } else { offset };
+    let ifd = Ifd::read(tiff, start, 0, little_endian)?;
+    Some(MakerNote {
+        // ExposureMode (0xb041)
+        shot_mode: ifd.uint(0xb041, 0).and_then(|v| {
+            Some(match v {
+                0 => "Program AE",
+                1 => "Portrait",
+                2 => "Beach",
+                3 => "Sports",
+                4 => "Snow",
+                5 => "Landscape",
+                6 => "Auto",
+                7 => "Aperture-priority AE",
+                8 => "Shutter speed priority AE",
+                9 => "Night Scene",
+                10 => "Hi-Speed Shutter",
+                11 => "Twilight Portrait",
+                12 => "Soft Snap",
+                13 => "Fireworks",
+                14 => "Smile Shutter",
+                15 => "Manual",
+                18 => "High Sensitivity",
+                19 => "Macro",
+                20 => "Advanced Sports Shooting",
+                29 => "Underwater",
+                33 => "Food",
+                34 => "Sweep Panorama",
+                35 => "Handheld Night Shot",
+                36 => "Anti Motion Blur",
+                37 => "Pet",
+                38 => "Backlight Correction HDR",
+                40 => "Background Defocus",
+                _ => return None,
+            })
+        }).map(str::to_string),
+        ..MakerNote::default()
+    })
+}
+
+/// Canon: headerless IFD, offsets relative to the TIFF header
+fn parse_canon(tiff: &[u8], offset: usize, little_endian: bool) -> Option<MakerNote> {
+    let ifd = Ifd::read(tiff, offset, 0, little_endian)?;
+
+    // ShotInfo[19]：单位 0.01 m，0 为未知，65535 为无穷远
+    let subject_distance = ifd
+        .uint(0x0004, 19)
+        .filter(|v| *v > 0 && *v < 0xffff)
+        .map(|v| v as f64 / 100.0);
+    // CameraSettings[11]：EasyMode
+    let shot_mode = ifd.uint(0x0001, 11).and_then(|v| {
+        Some(match v {
+            0 => "Full auto",
+            1 => "Manual",
+            2 => "Landscape",
+            3 => "Fast shutter",
+            4 => "Slow shutter",
+            5 => "Night",
+            6 => "Gray Scale",
+            7 => "Sepia",
+            8 => "Portrait",
+            9 => "Sports",
+            10 => "Macro",
+            11 => "Black & White",
+            12 => "Pan focus",
+            13 => "Vivid",
+            14 => "Neutral",
+            15 => "Flash Off",
+            16 => "Long Shutter",
+            17 => "Super Macro",
+            18 => "Foliage",
+            19 => "Indoor",
+            20 => "Fireworks",
+            21 => "Beach",
+            22 => "Underwater",
+            23 => "Snow",
+            24 => "Kids & Pets",
+            25 => "Night Snapshot",
+            _ => return None,
+        })
+    });
+    // SerialNumber (0x000c)，EOS 机型按 10 位补零显示
+    let serial_number = ifd.uint(0x000c, 0).filter(|v| *v > 0).map(|v| format!("{:010}", v));
+
+    Some(MakerNote {
+        subject_distance,
+        shot_mode: shot_mode.map(str::to_string),
+        serial_number,
+    })
+}
+
+/// Directory entries of one maker-note IFD
+struct Ifd<'a> {
+    entries: Vec<(u16, u16, &'a [u8])>,
+    little_endian: bool,
+}
+
+impl<'a> Ifd<'a> {
+    /// Read the IFD at `start`; values that do not fit in an entry are stored at `base` + offset
+    fn read(data: &'a [u8], start: usize, base: usize, little_endian: bool) -> Option<Self> {
+        let count = read_u16(data, start, little_endian)? as usize;
+        // 条目数不合理时多半不是 IFD（未知格式或数据损坏）
+        if count == 0 || count > 512 {
+            return None;
+        }
+        let mut entries = Vec::with_capacity(count);
+        for i in 0..count {
+            let entry = start + 2 + i * 12;
+            let tag = read_u16(data, entry, little_endian)?;
+            let value_type = read_u16(data, entry + 2, little_endian)?;
+            let components = read_u32(data, entry + 4, little_endian)? as usize;
+            let Some(size) = type_size(value_type).and_then(|s| s.checked_mul(components)) else {
+                continue;
+            };
+            let value = if size <= 4 {
+                data.get(entry + 8..entry + 8 + size)
+            } else {
+                let at = base.checked_add(read_u32(data, entry + 8, little_endian)? as usize)?;
+                data.get(at..at.checked_add(size)?)
+            };
+            if let Some(value) = value {
+                entries.push((tag, value_type, value));
+            }
+        }
+        Some(Self { entries, little_endian })
+    }
+
+    /// Element `index` of an integer entry
+    fn uint(&self, tag: u16, index: usize) -> Option<u32> {
+        let (_, value_type, value) = self.entries.iter().find(|(t, _, _)| *t == tag)?;
+        match value_type {
+            1 | 7 => value.get(index).map(|v| *v as u32),
+            3 | 8 => read_u16(value, index * 2, self.little_endian).map(u32::from),
+            4 | 9 => read_u32(value, index * 4, self.little_endian),
+            _ => None,
+        }
+    }
+}
+
+/// Size in bytes of one component of a TIFF value type
+fn type_size(value_type: u16) -> Option<usize> {
+    match value_type {
+        1 | 2 | 6 | 7 => Some(1),
+        3 | 8 => Some(2),
+        4 | 9 | 11 => Some(4),
+        5 | 10 | 12 => Some(8),
+        _ => None,
+    }
+}
+
+fn read_u16(data: &[u8], at: usize, little_endian: bool) -> Option<u16> {
+    let bytes: [u8; 2] = data.get(at..at.checked_add(2)?)?.try_into().ok()?;
+    Some(if little_endian { u16::from_le_bytes(bytes) } else { u16::from_be_bytes(bytes) })
+}
+
+fn read_u32(data: &[u8], at: usize, little_endian: bool) -> Option<u32> {
+    let bytes: [u8; 4] = data.get(at..at.checked_add(4)?)?.try_into().ok()?;
+    Some(if little_endian { u32::from_le_bytes(bytes) } else { u32::from_be_bytes(bytes) })
+}
+
+/// ASCII value without NUL padding and surrounding blanks; None when empty
+fn clean_ascii(value: &[u8]) -> Option<String> {
+    let text = String::from_utf8_lossy(value);
+    let text = text.trim_matches(|c: char| c == '\0' || c.is_whitespace());
+    (!text.is_empty()).then(|| text.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// IFD bytes with (tag, type, count, inline value or offset) entries
+    fn ifd(entries: &[(u16, u16, u32, [u8; 4])], little_endian: bool) -> Vec<u8> {
+        let u16_bytes = |v: u16| if little_endian { v.to_le_bytes() } else { v.to_be_bytes() };
+        let u32_bytes = |v: u32| if little_endian { v.to_le_bytes() } else { v.to_be_bytes() };
+        let mut data = u16_bytes(entries.len() as u16).to_vec();
+        for (tag, value_type, count, value) in entries {
+            data.extend_from_slice(&u16_bytes(*tag));
+            data.extend_from_slice(&u16_bytes(*value_type));
+            data.extend_from_slice(&u32_bytes(*count));
+            data.extend_from_slice(value);
+        }
+        data.extend_from_slice(&[0; 4]);
+        data
+    }
+
+    #[test]
+    fn test_parse_apple() {
+        let mut note = b"Apple iOS\0\0\x01MM".to_vec();
+        note.extend(ifd(&[(0x0014, 9, 1, [0, 0, 0, 2])], false));
+        // maker note 位于 TIFF 数据中间，偏移量相对于 note 起点
+        let mut tiff = vec![0u8; 100];
+        tiff.extend(&note);
+
+        let parsed = parse("Apple", &tiff, 100, true).unwrap();
+        assert_eq!(parsed.shot_mode.as_deref(), Some("Portrait"));
+        assert_eq!(parsed.serial_number, None);
+    }
+
+    #[test]
+    fn test_parse_sony() {
+        let mut tiff = vec![0u8; 8];
+        tiff.extend_from_slice(b"SONY DSC \0\0\0");
+        tiff.extend(ifd(&[(0xb041, 3, 1, [9, 0, 0, 0])], true));
+
+        let parsed = parse("SONY", &tiff, 8, true).unwrap();
+        assert_eq!(parsed.shot_mode.as_deref(), Some("Night Scene"));
+    }
+
+    #[test]
+    fn test_parse_canon_arrays_stored_outside_entries() {
+        let le = true;
+        let shorts = |values: &[u16]| values.iter().flat_map(|v| v.to_le_bytes()).collect::<Vec<u8>>();
+        let mut camera_settings = vec![0u16; 12];
+        camera_settings[11] = 8;
+        let mut shot_info = vec![0u16; 20];
+        shot_info[19] = 250;
+
+        // 布局：[IFD @0][CameraSettings][ShotInfo]
+        let ifd_len = 2 + 3 * 12 + 4;
+        let settings_at = ifd_len as u32;
+        let shot_info_at = settings_at + 24;
+        let mut tiff = ifd(
+            &[
+                (0x0001, 3, 12, settings_at.to_le_bytes()),
+                (0x0004, 3, 20, shot_info_at.to_le_bytes()),
+                (0x000c, 4, 1, 123456u32.to_le_bytes()),
+            ],
+            le,
+        );
+        tiff.extend(shorts(&camera_settings));
+        tiff.extend(shorts(&shot_info));
+
+        let parsed = parse("Canon", &tiff, 0, le).unwrap();
+        assert_eq!(parsed.shot_mode.as_deref(), Some("Portrait"));
+        assert_eq!(parsed.subject_distance, Some(2.5));
+        assert_eq!(parsed.serial_number.as_deref(), Some("0000123456"));
+    }
+
+    #[test]
+    fn test_parse_rejects_unknown_and_malformed() {
+        assert_eq!(parse("NIKON CORPORATION", &[0; 64], 0, true), None);
+        // 数据截断：条目越界
+        assert_eq!(parse("Canon", &[5, 0, 1, 0], 0, true), None);
+        assert_eq!(parse("Canon", &[0xff, 0xff], 0, true), None);
+        assert_eq!(parse("Canon", &[], 10, true), None);
+    }
+
+    #[test]
+    fn test_clean_ascii() {
+        assert_eq!(clean_ascii(b" 012345\0\0"), Some("012345".to_string()));
+        assert_eq!(clean_ascii(b"\0\0"), None);
+    }
+}
@@ -2,6 +2,19 @@ pub mod processor_trait;
 pub mod image_processor;
 pub mod heif_processor; // Enabled: uses image crate's built-in HEIF support
 pub mod video_processor;
+pub mod video_cli; // ffprobe/ffmpeg CLI fallback when built without the video-processing feature
+pub mod video_color; // HDR format / bit depth / color primaries of video streams
+pub mod video_tags; // Capture location from container tags (QuickTime ISO6709 / ©xyz)
+pub mod video_timeline; // Chapter markers and keyframe timestamps read with ffprobe
+pub mod extensions; // Supported extension lists shared by processors and the scanner, plus configured extras
 pub mod file_metadata; // Unified file metadata extraction (file_size, create_time, modify_time)
+pub mod filename_date; // Capture date inferred from file names (fallback when EXIF is missing)
+pub mod takeout; // Google Takeout JSON sidecars: capture time, GPS and description
+pub mod decode_guard; // Header-only size checks and reduced decoding for huge images
+pub mod panorama; // Panorama / 360° detection and grid thumbnail crops
+pub mod thumbnail_crop; // Square and entropy-based (smart) thumbnail crops
+pub mod sharpness; // Laplacian-variance blur score for culling blurry photos
+pub mod exposure; // Mean luminance and clipped highlight/shadow fractions of thumbnails
+pub mod maker_notes; // Apple / Sony / Canon maker-note fields stored as file attributes
 
-pub use processor_trait::{MediaProcessor, MediaMetadata, MediaType, ProcessingError, ProcessorRegistry};
+pub use processor_trait::{MediaProcessor, MediaMetadata, MediaType, ProcessingError, ProcessorInfo, ProcessorRegistry};
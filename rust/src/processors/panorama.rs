@@ -0,0 +1,157 @@
+//! 全景与 360° 照片/视频识别
+//! 照片优先读取 XMP 中的 GPano:ProjectionType（Google Photo Sphere 规范），
+//! 没有投影元数据时按极端宽高比判定为普通宽幅全景；视频读取 Spherical Mapping 元数据。
+//! 网格中的全景缩略图居中裁剪为固定宽高比，避免过于扁长。
+
+use crate::processors::processor_trait::ProcessingError;
+use crate::utils::{ThumbnailFormat, ThumbnailOptions, ThumbnailPipeline};
+use std::io::Read;
+use std::path::Path;
+
+/// 360° spherical projection
+pub const PROJECTION_EQUIRECTANGULAR: &str = "equirectangular";
+/// Flat panorama detected only by its aspect ratio
+pub const PROJECTION_WIDE: &str = "wide";
+
+/// Images at least this much wider than tall are treated as flat panoramas
+const MIN_WIDE_ASPECT_RATIO: f64 = 2.5;
+
+/// Aspect ratio of panorama thumbnails in the grid
+pub const GRID_ASPECT_RATIO: f64 = 2.0;
+
+/// XMP packets are stored near the start of JPEG/HEIC files; only this much is searched
+const XMP_SEARCH_BYTES: u64 = 512 * 1024;
+
+/// Read the XMP packet from the head of an image file
+pub fn read_xmp(path: &Path) -> Option<String> {
+    let mut head = Vec::new();
+    std::fs::File::open(path)
+        .ok()?
+        .take(XMP_SEARCH_BYTES)
+        .read_to_end(&mut head)
+        .ok()?;
+    extract_xmp(&head)
+}
+
+/// Find the `<x:xmpmeta>` packet in raw file bytes
+fn extract_xmp(data: &[u8]) -> Option<String> {
+    let start = find(data, b"<x:xmpmeta")?;
+    let end = start + find(&data[start..], b"</x:xmpmeta>")? + b"</x:xmpmeta>".len();
+    Some(String::from_utf8_lossy(&data[start..end]).into_owned())
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack.windows(needle.len()).position(|w| w == needle)
+}
+
+/// GPano:ProjectionType from an XMP packet, as attribute or element
+pub fn parse_gpano_projection(xmp: &str) -> Option<String> {
+    const TAG: &str = "GPano:ProjectionType";
+
+    let value = if let Some(pos) = xmp.find(&format!("{}=", TAG)) {
+        // 属性形式：GPano:ProjectionType="equirectangular"
+        let rest = &xmp[pos + TAG.len() + 1..];
+        let quote = rest.chars().next().filter(|c| *c == '"' || *c == '\'')?;
+        let rest = &rest[1..];
+        &rest[..rest.find(quote)?]
+    } else {
+        // 元素形式：<GPano:ProjectionType>equirectangular</GPano:ProjectionType>
+        let open = format!("<{}>", TAG);
+        let rest = &xmp[xmp.find(&open)? + open.len()..];
+        &rest[..rest.find('<')?]
+    };
+
+    let value = value.trim().to_lowercase();
+    (!value.is_empty()).then_some(value)
+}
+
+/// Projection of an image: XMP metadata wins, otherwise an extreme aspect ratio means a flat panorama
+pub fn detect_image_projection(xmp: Option<&str>, width: Option<i32>, height: Option<i32>) -> Option<String> {
+    if let Some(projection) = xmp.and_then(parse_gpano_projection) {
+        return Some(projection);
+    }
+    match (width, height) {
+        (Some(w), Some(h)) if w > 0 && h > 0 => {
+            let ratio = w.max(h) as f64 / w.min(h) as f64;
+            (ratio >= MIN_WIDE_ASPECT_RATIO).then(|| PROJECTION_WIDE.to_string())
+        }
+        _ => None,
+    }
+}
+
+/// Normalize the projection name of a spherical video ("equirectangular", "cubemap", ...)
+pub fn normalize_video_projection(projection: &str) -> Option<String> {
+    let projection = projection.trim().to_lowercase();
+    match projection.as_str() {
+        "" | "flat" | "rectangular" => None,
+        _ => Some(projection.replace(' ', "_")),
+    }
+}
+
+/// Center-crop a JPEG thumbnail to the grid aspect ratio and scale it to `target_width`, encoded as `format`
+pub fn crop_grid_thumbnail(
+    jpeg: &[u8],
+    target_width: u32,
+    quality: f32,
+    format: ThumbnailFormat,
+) -> Result<Vec<u8>, ProcessingError> {
+    let image = image::load_from_memory_with_format(jpeg, image::ImageFormat::Jpeg)?;
+    let (width, height) = (image.width(), image.height());
+
+    let crop_width = ((height as f64 * GRID_ASPECT_RATIO).round() as u32).min(width);
+    let cropped = image.crop_imm((width - crop_width) / 2, 0, crop_width, height);
+    ThumbnailPipeline::new(ThumbnailOptions::new(target_width, quality, false).with_format(format)).run(cropped)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_gpano_projection() {
+        let attribute = r#"<x:xmpmeta><rdf:Description GPano:ProjectionType="equirectangular" GPano:UsePanoramaViewer="True"/></x:xmpmeta>"#;
+        assert_eq!(parse_gpano_projection(attribute).as_deref(), Some("equirectangular"));
+
+        let element = "<x:xmpmeta><GPano:ProjectionType> Cylindrical </GPano:ProjectionType></x:xmpmeta>";
+        assert_eq!(parse_gpano_projection(element).as_deref(), Some("cylindrical"));
+
+        assert_eq!(parse_gpano_projection("<x:xmpmeta></x:xmpmeta>"), None);
+    }
+
+    #[test]
+    fn test_extract_xmp() {
+        let data = b"\xff\xd8\xff\xe1junk<x:xmpmeta a=\"1\">body</x:xmpmeta>trailing";
+        assert_eq!(extract_xmp(data).as_deref(), Some("<x:xmpmeta a=\"1\">body</x:xmpmeta>"));
+        assert_eq!(extract_xmp(b"no xmp here"), None);
+    }
+
+    #[test]
+    fn test_detect_image_projection() {
+        let xmp = r#"<x:xmpmeta GPano:ProjectionType="equirectangular"></x:xmpmeta>"#;
+        assert_eq!(detect_image_projection(Some(xmp), Some(8000), Some(4000)).as_deref(), Some("equirectangular"));
+        assert_eq!(detect_image_projection(None, Some(12000), Some(3000)).as_deref(), Some(PROJECTION_WIDE));
+        assert_eq!(detect_image_projection(None, Some(1000), Some(4000)).as_deref(), Some(PROJECTION_WIDE));
+        assert_eq!(detect_image_projection(None, Some(4032), Some(3024)), None);
+        assert_eq!(detect_image_projection(None, None, None), None);
+    }
+
+    #[test]
+    fn test_normalize_video_projection() {
+        assert_eq!(normalize_video_projection("equirectangular").as_deref(), Some("equirectangular"));
+        assert_eq!(normalize_video_projection("Tiled Equirectangular").as_deref(), Some("tiled_equirectangular"));
+        assert_eq!(normalize_video_projection("flat"), None);
+    }
+
+    #[test]
+    fn test_crop_grid_thumbnail() {
+        let image = image::RgbImage::from_pixel(600, 100, image::Rgb([10, 20, 30]));
+        let mut jpeg = Vec::new();
+        image::DynamicImage::ImageRgb8(image)
+            .write_to(&mut std::io::Cursor::new(&mut jpeg), image::ImageFormat::Jpeg)
+            .unwrap();
+
+        let cropped = crop_grid_thumbnail(&jpeg, 150, 0.85, ThumbnailFormat::WebP).unwrap();
+        let cropped = image::load_from_memory_with_format(&cropped, image::ImageFormat::WebP).unwrap();
+        assert_eq!((cropped.width(), cropped.height()), (150, 75));
+    }
+}
@@ -4,7 +4,9 @@ use std::path::Path;
 use std::sync::Arc;
 use thiserror::Error;
 
+use crate::db::{MediaAttribute, MediaSubImage};
 use crate::services::TranscodingPool;
+use crate::utils::ThumbnailFormat;
 
 /// Media type enumeration
 #[derive(Debug, Clone, PartialEq)]
@@ -34,8 +36,31 @@ pub struct MediaMetadata {
     pub focal_length: Option<String>,
     pub duration: Option<f64>,
     pub video_codec: Option<String>,
+    /// Video HDR format (see video_color)
+    pub hdr_format: Option<String>,
+    pub bit_depth: Option<i32>,
+    pub color_primaries: Option<String>,
     pub gps_latitude: Option<f64>,
     pub gps_longitude: Option<f64>,
+    /// HEIC contains an auxiliary depth image
+    pub has_depth_map: bool,
+    /// HEIC contains a portrait effects matte
+    pub has_portrait_matte: bool,
+    /// Panorama projection (see MediaFile::projection)
+    pub projection: Option<String>,
+    /// Description of the embedded ICC profile (see utils::color_profile)
+    pub color_profile: Option<String>,
+    /// Content fingerprint (see file_metadata::content_hash)
+    pub content_hash: Option<String>,
+    /// Laplacian-variance sharpness (see sharpness::blur_score); None unless blur detection is enabled
+    pub blur_score: Option<f64>,
+    /// EXIF Orientation of the primary image (1-8)
+    pub orientation: Option<i32>,
+    /// Top-level images of a multi-image container (empty unless there is more than one);
+    /// None for formats that cannot hold multiple images
+    pub sub_images: Option<Vec<MediaSubImage>>,
+    /// Extended key/value metadata (see maker_notes); None unless maker-note extraction is enabled
+    pub attributes: Option<Vec<MediaAttribute>>,
 }
 
 /// Processing error
@@ -63,6 +88,12 @@ impl From<image::ImageError> for ProcessingError {
 /// Trait for media processors
 #[async_trait]
 pub trait MediaProcessor: Send + Sync {
+    /// Short processor name, used by `disabled_processors` and the capability listing
+    fn name(&self) -> &'static str;
+
+    /// Lower-case file extensions this processor handles
+    fn extensions(&self) -> Vec<String>;
+
     /// Check if this processor supports the given file
     fn supports(&self, path: &Path) -> bool;
 
@@ -75,15 +106,62 @@ pub trait MediaProcessor: Send + Sync {
     /// Process the file and extract metadata
     async fn process(&self, path: &Path) -> Result<MediaMetadata, ProcessingError>;
 
-    /// Generate a thumbnail for the file
+    /// Generate a thumbnail for the file, encoded as `format`
     /// fit_to_height: true = 按固定高度缩放（保持宽高比），false = 按固定宽度缩放
     async fn generate_thumbnail(
         &self,
         path: &Path,
         target_size: u32,
         quality: f32,
+        format: ThumbnailFormat,
         fit_to_height: bool,
     ) -> Result<Option<Vec<u8>>, ProcessingError>;
+
+    /// Generate a thumbnail for one top-level image of a multi-image container (e.g. a HEIC burst).
+    /// Returns None when the format has no sub-images or `item` is out of range.
+    async fn generate_item_thumbnail(
+        &self,
+        _path: &Path,
+        _item: u32,
+        _target_size: u32,
+        _quality: f32,
+        _format: ThumbnailFormat,
+        _fit_to_height: bool,
+    ) -> Result<Option<Vec<u8>>, ProcessingError> {
+        Ok(None)
+    }
+}
+
+/// Run CPU-bound work (decode/resize/encode) on the transcoding pool so it doesn't
+/// compete with file I/O on tokio's blocking pool. Without a pool (e.g. in tests)
+/// falls back to spawn_blocking.
+pub(crate) async fn run_cpu_bound<F, R>(
+    pool: Option<&Arc<TranscodingPool>>,
+    f: F,
+) -> Result<R, ProcessingError>
+where
+    F: FnOnce() -> R + Send + 'static,
+    R: Send + 'static,
+{
+    match pool {
+        Some(pool) => pool
+            .run(f)
+            .await
+            .ok_or_else(|| ProcessingError::Processing("Transcoding task panicked".to_string())),
+        None => tokio::task::spawn_blocking(f)
+            .await
+            .map_err(|e| ProcessingError::Processing(e.to_string())),
+    }
+}
+
+/// Registered processor as reported by `ProcessorRegistry::list`
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProcessorInfo {
+    pub name: &'static str,
+    pub media_type: MediaType,
+    pub extensions: Vec<String>,
+    pub priority: i32,
+    pub enabled: bool,
 }
 
 /// Registry for managing media processors
@@ -91,6 +169,8 @@ pub trait MediaProcessor: Send + Sync {
 pub struct ProcessorRegistry {
     processors: Vec<Arc<dyn MediaProcessor>>,
     transcoding_pool: Option<Arc<TranscodingPool>>,
+    /// Names of processors that stay registered (and listed) but never handle files
+    disabled: Vec<String>,
 }
 
 impl ProcessorRegistry {
@@ -99,9 +179,20 @@ impl ProcessorRegistry {
         Self {
             processors: Vec::new(),
             transcoding_pool,
+            disabled: Vec::new(),
         }
     }
 
+    /// Disable processors by name (case-insensitive)
+    pub fn with_disabled(mut self, names: &[String]) -> Self {
+        self.disabled = names.iter().map(|n| n.to_lowercase()).collect();
+        self
+    }
+
+    fn is_enabled(&self, processor: &dyn MediaProcessor) -> bool {
+        !self.disabled.iter().any(|n| n == processor.name())
+    }
+
     /// Register a processor
     pub fn register(&mut self, processor: Arc<dyn MediaProcessor>) {
         self.processors.push(processor);
@@ -113,10 +204,32 @@ impl ProcessorRegistry {
     pub fn find_processor(&self, path: &Path) -> Option<Arc<dyn MediaProcessor>> {
         self.processors
             .iter()
-            .find(|p| p.supports(path))
+            .find(|p| self.is_enabled(p.as_ref()) && p.supports(path))
             .cloned()
     }
 
+    /// Whether an enabled processor handles files with this extension (case-insensitive)
+    pub fn handles_extension(&self, extension: &str) -> bool {
+        self.processors
+            .iter()
+            .filter(|p| self.is_enabled(p.as_ref()))
+            .any(|p| p.extensions().iter().any(|e| e.eq_ignore_ascii_case(extension)))
+    }
+
+    /// All registered processors in priority order, including disabled ones
+    pub fn list(&self) -> Vec<ProcessorInfo> {
+        self.processors
+            .iter()
+            .map(|p| ProcessorInfo {
+                name: p.name(),
+                media_type: p.media_type(),
+                extensions: p.extensions(),
+                priority: p.priority(),
+                enabled: self.is_enabled(p.as_ref()),
+            })
+            .collect()
+    }
+
     /// Get transcoding pool reference
     pub fn transcoding_pool(&self) -> Option<&Arc<TranscodingPool>> {
         self.transcoding_pool.as_ref()
@@ -227,6 +340,26 @@ mod tests {
         assert!(result.is_some());
     }
 
+    #[test]
+    fn test_processor_registry_disabled() {
+        let mut registry = ProcessorRegistry::new(None).with_disabled(&["Image".to_string()]);
+        registry.register(Arc::new(crate::processors::image_processor::StandardImageProcessor::new()));
+
+        // 停用的处理器仍然列出，但不再处理文件
+        assert!(registry.find_processor(Path::new("test.jpg")).is_none());
+        let list = registry.list();
+        assert_eq!(list.len(), 1);
+        assert_eq!(list[0].name, "image");
+        assert!(list[0].extensions.contains(&"jpg".to_string()));
+        assert!(!list[0].enabled);
+        assert!(!registry.handles_extension("jpg"));
+
+        let mut registry = ProcessorRegistry::new(None);
+        registry.register(Arc::new(crate::processors::image_processor::StandardImageProcessor::new()));
+        assert!(registry.handles_extension("JPG"));
+        assert!(!registry.handles_extension("mp4"));
+    }
+
     #[test]
     fn test_processing_error_from_io() {
         use std::io;
@@ -0,0 +1,70 @@
+//! 清晰度（模糊）检测
+//! 把图片缩小到固定尺寸并转为灰度，计算 4 邻域拉普拉斯算子响应的方差：
+//! 清晰的照片边缘多、方差大，失焦或抖动的照片方差小。
+//! 先缩小再计算，使得分与原图分辨率基本无关，也能控制计算量。
+
+use image::DynamicImage;
+
+/// Longest edge of the grayscale copy the score is computed on
+pub const ANALYSIS_SIZE: u32 = 512;
+
+/// Variance of the Laplacian of a downscaled grayscale copy; higher is sharper.
+/// Images smaller than 3x3 have no interior pixels and score 0.
+pub fn blur_score(img: &DynamicImage) -> f64 {
+    let img = if img.width() > ANALYSIS_SIZE || img.height() > ANALYSIS_SIZE {
+        img.thumbnail(ANALYSIS_SIZE, ANALYSIS_SIZE)
+    } else {
+        img.clone()
+    };
+    let gray = img.to_luma8();
+    let (width, height) = gray.dimensions();
+    if width < 3 || height < 3 {
+        return 0.0;
+    }
+
+    let px = |x: u32, y: u32| gray.get_pixel(x, y)[0] as f64;
+    let mut sum = 0.0;
+    let mut sum_sq = 0.0;
+    for y in 1..height - 1 {
+        for x in 1..width - 1 {
+            let laplacian = px(x - 1, y) + px(x + 1, y) + px(x, y - 1) + px(x, y + 1) - 4.0 * px(x, y);
+            sum += laplacian;
+            sum_sq += laplacian * laplacian;
+        }
+    }
+
+    let count = ((width - 2) * (height - 2)) as f64;
+    let mean = sum / count;
+    (sum_sq / count - mean * mean).max(0.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{GrayImage, Luma};
+
+    fn checkerboard(size: u32, cell: u32) -> DynamicImage {
+        DynamicImage::ImageLuma8(GrayImage::from_fn(size, size, |x, y| {
+            if (x / cell + y / cell).is_multiple_of(2) { Luma([0]) } else { Luma([255]) }
+        }))
+    }
+
+    #[test]
+    fn test_flat_image_scores_zero() {
+        let flat = DynamicImage::ImageLuma8(GrayImage::from_pixel(64, 64, Luma([128])));
+        assert_eq!(blur_score(&flat), 0.0);
+    }
+
+    #[test]
+    fn test_sharp_image_scores_higher_than_blurred() {
+        let sharp = checkerboard(128, 8);
+        let blurred = sharp.blur(3.0);
+        assert!(blur_score(&sharp) > blur_score(&blurred) * 4.0);
+    }
+
+    #[test]
+    fn test_tiny_image_scores_zero() {
+        let tiny = DynamicImage::ImageLuma8(GrayImage::from_pixel(2, 2, Luma([255])));
+        assert_eq!(blur_score(&tiny), 0.0);
+    }
+}
@@ -0,0 +1,184 @@
+//! Google Takeout JSON 旁车文件
+//! Google Photos 导出时会把拍摄时间、位置和描述写进与照片同名的 JSON 文件，
+//! 而照片本身的 EXIF 常被剥离。扫描时读取这些文件，补全缺失的时间、GPS 和描述。
+//! 旁车文件名的几种形式：
+//! - `IMG_1234.jpg.json`
+//! - `IMG_1234.jpg.supplemental-metadata.json`（较新的导出）
+//! - `IMG_1234.json`
+//! - 编辑版 `IMG_1234-edited.jpg` 使用原图的 `IMG_1234.jpg.json`
+//! - 重名文件 `IMG_1234(1).jpg` 对应 `IMG_1234.jpg(1).json`
+
+use chrono::{DateTime, NaiveDateTime};
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+
+/// Suffix Google Photos appends to edited copies
+const EDITED_SUFFIX: &str = "-edited";
+
+/// Metadata read from a Takeout sidecar
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TakeoutMetadata {
+    /// Capture time in UTC
+    pub taken_time: Option<NaiveDateTime>,
+    pub latitude: Option<f64>,
+    pub longitude: Option<f64>,
+    pub description: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TakeoutJson {
+    photo_taken_time: Option<TakeoutTime>,
+    geo_data: Option<TakeoutGeo>,
+    geo_data_exif: Option<TakeoutGeo>,
+    description: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct TakeoutTime {
+    /// Seconds since the epoch, as a string
+    timestamp: String,
+}
+
+#[derive(Deserialize)]
+struct TakeoutGeo {
+    latitude: f64,
+    longitude: f64,
+}
+
+impl TakeoutGeo {
+    /// Takeout writes 0.0/0.0 when the location is unknown
+    fn known(&self) -> Option<(f64, f64)> {
+        (self.latitude != 0.0 || self.longitude != 0.0).then_some((self.latitude, self.longitude))
+    }
+}
+
+/// Sidecar file names that may belong to `file_name`, most specific first
+fn candidate_names(file_name: &str) -> Vec<String> {
+    let (stem, ext) = match file_name.rsplit_once('.') {
+        Some((stem, ext)) => (stem, Some(ext)),
+        None => (file_name, None),
+    };
+    let mut names = vec![
+        format!("{}.json", file_name),
+        format!("{}.supplemental-metadata.json", file_name),
+        format!("{}.json", stem),
+    ];
+
+    if let Some(ext) = ext {
+        if let Some(original) = stem.strip_suffix(EDITED_SUFFIX) {
+            names.push(format!("{}.{}.json", original, ext));
+            names.push(format!("{}.{}.supplemental-metadata.json", original, ext));
+        }
+        // IMG_1234(1).jpg -> IMG_1234.jpg(1).json
+        if let Some((base, counter)) = stem.strip_suffix(')').and_then(|s| s.rsplit_once('(')) {
+            if !counter.is_empty() && counter.chars().all(|c| c.is_ascii_digit()) {
+                names.push(format!("{}.{}({}).json", base, ext, counter));
+            }
+        }
+    }
+    names
+}
+
+/// Find the Takeout sidecar of a media file
+pub fn find_sidecar(path: &Path) -> Option<PathBuf> {
+    let file_name = path.file_name()?.to_str()?;
+    let dir = path.parent()?;
+    candidate_names(file_name)
+        .into_iter()
+        .map(|name| dir.join(name))
+        .find(|candidate| candidate.is_file())
+}
+
+/// Parse a Takeout sidecar; None if it is not Takeout JSON
+pub fn parse_sidecar(json: &[u8]) -> Option<TakeoutMetadata> {
+    let parsed: TakeoutJson = serde_json::from_slice(json).ok()?;
+
+    let taken_time = parsed
+        .photo_taken_time
+        .and_then(|time| time.timestamp.trim().parse::<i64>().ok())
+        .and_then(|seconds| DateTime::from_timestamp(seconds, 0))
+        .map(|time| time.naive_utc());
+    // geoData 是用户在 Google Photos 中修正后的位置，优先于原始 EXIF 位置
+    let location = parsed
+        .geo_data
+        .as_ref()
+        .and_then(TakeoutGeo::known)
+        .or_else(|| parsed.geo_data_exif.as_ref().and_then(TakeoutGeo::known));
+    let description = parsed
+        .description
+        .map(|d| d.trim().to_string())
+        .filter(|d| !d.is_empty());
+
+    Some(TakeoutMetadata {
+        taken_time,
+        latitude: location.map(|(lat, _)| lat),
+        longitude: location.map(|(_, lon)| lon),
+        description,
+    })
+}
+
+/// Read the Takeout sidecar of a media file, if there is one
+pub fn read_sidecar(path: &Path) -> Option<TakeoutMetadata> {
+    let sidecar = find_sidecar(path)?;
+    let json = std::fs::read(&sidecar).ok()?;
+    let metadata = parse_sidecar(&json);
+    if metadata.is_none() {
+        tracing::debug!("Ignoring non-Takeout JSON next to {:?}: {:?}", path, sidecar);
+    }
+    metadata
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    #[test]
+    fn test_parse_sidecar() {
+        let json = br#"{
+            "title": "IMG_1234.jpg",
+            "description": "  Beach day ",
+            "photoTakenTime": { "timestamp": "1500000000", "formatted": "Jul 14, 2017, 2:40:00 AM UTC" },
+            "geoData": { "latitude": 0.0, "longitude": 0.0, "altitude": 0.0 },
+            "geoDataExif": { "latitude": 48.8584, "longitude": 2.2945, "altitude": 35.0 }
+        }"#;
+        let metadata = parse_sidecar(json).unwrap();
+        assert_eq!(
+            metadata.taken_time,
+            NaiveDate::from_ymd_opt(2017, 7, 14).unwrap().and_hms_opt(2, 40, 0)
+        );
+        assert_eq!(metadata.latitude, Some(48.8584));
+        assert_eq!(metadata.longitude, Some(2.2945));
+        assert_eq!(metadata.description.as_deref(), Some("Beach day"));
+    }
+
+    #[test]
+    fn test_parse_sidecar_without_location() {
+        let json = br#"{ "description": "", "geoData": { "latitude": 0.0, "longitude": 0.0 } }"#;
+        assert_eq!(parse_sidecar(json).unwrap(), TakeoutMetadata::default());
+        assert!(parse_sidecar(b"[1, 2, 3]").is_none());
+    }
+
+    #[test]
+    fn test_candidate_names() {
+        assert_eq!(
+            candidate_names("IMG_1234.jpg")[..3],
+            ["IMG_1234.jpg.json", "IMG_1234.jpg.supplemental-metadata.json", "IMG_1234.json"]
+        );
+        assert!(candidate_names("IMG_1234-edited.jpg").contains(&"IMG_1234.jpg.json".to_string()));
+        assert!(candidate_names("IMG_1234(1).jpg").contains(&"IMG_1234.jpg(1).json".to_string()));
+    }
+
+    #[test]
+    fn test_find_sidecar() {
+        let dir = tempfile::tempdir().unwrap();
+        let photo = dir.path().join("IMG_1234(1).jpg");
+        std::fs::write(&photo, b"").unwrap();
+        assert_eq!(find_sidecar(&photo), None);
+
+        let sidecar = dir.path().join("IMG_1234.jpg(1).json");
+        std::fs::write(&sidecar, b"{}").unwrap();
+        assert_eq!(find_sidecar(&photo), Some(sidecar));
+    }
+}
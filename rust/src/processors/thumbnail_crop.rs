@@ -0,0 +1,160 @@
+//! 方形缩略图裁剪
+//! 网格布局常用方形缩略图：`square` 居中裁剪，`smart` 在长边方向上选取信息熵最高
+//! （细节最丰富）的窗口，尽量保留主体而不是天空、墙面等大块平坦区域。
+
+use crate::processors::processor_trait::ProcessingError;
+use crate::utils::{ThumbnailFormat, ThumbnailOptions, ThumbnailPipeline};
+use image::{DynamicImage, GrayImage};
+
+/// Number of window positions compared along the long side by smart cropping
+const SMART_CROP_CANDIDATES: u32 = 9;
+
+/// Thumbnail crop mode (`?crop=` parameter)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CropMode {
+    /// Center square crop
+    Square,
+    /// Square crop at the most detailed (highest entropy) position
+    Smart,
+}
+
+impl CropMode {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "square" => Some(Self::Square),
+            "smart" => Some(Self::Smart),
+            _ => None,
+        }
+    }
+
+    /// Suffix of the cache key, keeping cropped thumbnails apart from the uncropped ones
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Square => "square",
+            Self::Smart => "smart",
+        }
+    }
+}
+
+/// Crop a JPEG thumbnail to a square and scale it to `target_size` (0 = keep the short side),
+/// encoded as `format`
+pub fn crop_square_thumbnail(
+    jpeg: &[u8],
+    target_size: u32,
+    mode: CropMode,
+    quality: f32,
+    format: ThumbnailFormat,
+) -> Result<Vec<u8>, ProcessingError> {
+    let image = image::load_from_memory_with_format(jpeg, image::ImageFormat::Jpeg)?;
+    let cropped = crop_square(&image, mode);
+    ThumbnailPipeline::new(ThumbnailOptions::new(target_size, quality, false).with_format(format)).run(cropped)
+}
+
+/// Square crop of the short side at the position chosen by `mode`
+fn crop_square(image: &DynamicImage, mode: CropMode) -> DynamicImage {
+    let (width, height) = (image.width(), image.height());
+    let side = width.min(height);
+    let horizontal = width > height;
+    let span = width.max(height) - side;
+
+    let offset = match mode {
+        CropMode::Square => span / 2,
+        CropMode::Smart => smart_offset(&image.to_luma8(), side, horizontal),
+    };
+    if horizontal {
+        image.crop_imm(offset, 0, side, side)
+    } else {
+        image.crop_imm(0, offset, side, side)
+    }
+}
+
+/// Offset along the long side of the highest-entropy square window; ties prefer the center
+fn smart_offset(gray: &GrayImage, side: u32, horizontal: bool) -> u32 {
+    let long = if horizontal { gray.width() } else { gray.height() };
+    let span = long - side;
+    if span == 0 {
+        return 0;
+    }
+
+    let steps = SMART_CROP_CANDIDATES.min(span + 1);
+    let center = span / 2;
+    let mut candidates: Vec<u32> = (0..steps).map(|i| span * i / (steps - 1)).collect();
+    candidates.push(center);
+    candidates.sort_by_key(|offset| offset.abs_diff(center));
+
+    let mut best = (center, f64::MIN);
+    for offset in candidates {
+        let (x, y) = if horizontal { (offset, 0) } else { (0, offset) };
+        let score = window_entropy(gray, x, y, side);
+        if score > best.1 {
+            best = (offset, score);
+        }
+    }
+    best.0
+}
+
+/// Shannon entropy of the luma histogram of a square window
+fn window_entropy(gray: &GrayImage, x: u32, y: u32, side: u32) -> f64 {
+    let mut histogram = [0u64; 256];
+    for row in y..y + side {
+        for col in x..x + side {
+            histogram[gray.get_pixel(col, row).0[0] as usize] += 1;
+        }
+    }
+
+    let total = (side as f64) * (side as f64);
+    histogram
+        .iter()
+        .filter(|&&count| count > 0)
+        .map(|&count| {
+            let p = count as f64 / total;
+            -p * p.log2()
+        })
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_crop_mode() {
+        assert_eq!(CropMode::parse("square"), Some(CropMode::Square));
+        assert_eq!(CropMode::parse("smart"), Some(CropMode::Smart));
+        assert_eq!(CropMode::parse("cover"), None);
+    }
+
+    #[test]
+    fn test_square_crop_is_centered() {
+        let mut image = image::RgbImage::from_pixel(300, 100, image::Rgb([0, 0, 0]));
+        image.put_pixel(150, 50, image::Rgb([255, 255, 255]));
+        let cropped = crop_square(&DynamicImage::ImageRgb8(image), CropMode::Square);
+        assert_eq!((cropped.width(), cropped.height()), (100, 100));
+        assert_eq!(cropped.to_rgb8().get_pixel(50, 50).0, [255, 255, 255]);
+    }
+
+    #[test]
+    fn test_smart_crop_prefers_detail() {
+        // 左侧平坦，右侧为噪声纹理：smart 裁剪应落在右端
+        let image = image::GrayImage::from_fn(400, 100, |x, y| {
+            if x < 300 { image::Luma([128]) } else { image::Luma([((x * 31 + y * 17) % 256) as u8]) }
+        });
+        assert_eq!(smart_offset(&image, 100, true), 300);
+        // 完全平坦时回到居中
+        let flat = image::GrayImage::from_pixel(100, 400, image::Luma([0]));
+        assert_eq!(smart_offset(&flat, 100, false), 150);
+    }
+
+    #[test]
+    fn test_crop_square_thumbnail_scales() {
+        let image = image::RgbImage::from_pixel(200, 400, image::Rgb([10, 20, 30]));
+        let mut jpeg = Vec::new();
+        DynamicImage::ImageRgb8(image)
+            .write_to(&mut std::io::Cursor::new(&mut jpeg), image::ImageFormat::Jpeg)
+            .unwrap();
+
+        let thumbnail = crop_square_thumbnail(&jpeg, 50, CropMode::Smart, 0.85, ThumbnailFormat::Jpeg).unwrap();
+        let thumbnail = image::load_from_memory(&thumbnail).unwrap();
+        assert_eq!((thumbnail.width(), thumbnail.height()), (50, 50));
+    }
+}
@@ -0,0 +1,284 @@
+//! 调用 ffprobe / ffmpeg 可执行文件处理视频
+//! 未启用 video-processing feature（未链接 FFmpeg 库）时的回退实现：
+//! 用 ffprobe 读取时长/尺寸/编码/色彩信息，用 ffmpeg 截取封面帧（PNG，再交给缩略图流水线编码）。
+
+use crate::processors::panorama::normalize_video_projection;
+use crate::processors::processor_trait::ProcessingError;
+use crate::processors::video_color::VideoColorInfo;
+use crate::processors::video_tags::{VideoTags, CREATION_TIME_TAGS, LOCATION_TAGS};
+use crate::services::background_priority;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::path::Path;
+use std::process::Command;
+
+/// Video metadata read by ffprobe
+#[derive(Debug, Default, PartialEq)]
+pub struct ProbedVideo {
+    pub width: Option<i32>,
+    pub height: Option<i32>,
+    pub duration: Option<f64>,
+    pub codec: Option<String>,
+    /// Spherical projection of 360° videos (see panorama::normalize_video_projection)
+    pub projection: Option<String>,
+    /// HDR format, bit depth and color primaries
+    pub color: VideoColorInfo,
+    /// Container tags (capture location and time)
+    pub tags: VideoTags,
+}
+
+#[derive(Deserialize)]
+struct ProbeOutput {
+    #[serde(default)]
+    streams: Vec<ProbeStream>,
+    format: Option<ProbeFormat>,
+}
+
+#[derive(Deserialize)]
+struct ProbeStream {
+    width: Option<i32>,
+    height: Option<i32>,
+    codec_name: Option<String>,
+    // ffprobe 的时长是字符串（例如 "12.345000"）
+    duration: Option<String>,
+    color_transfer: Option<String>,
+    color_primaries: Option<String>,
+    pix_fmt: Option<String>,
+    // 同样是字符串（例如 "10"）
+    bits_per_raw_sample: Option<String>,
+    #[serde(default)]
+    side_data_list: Vec<ProbeSideData>,
+}
+
+#[derive(Deserialize)]
+struct ProbeSideData {
+    side_data_type: Option<String>,
+    projection: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct ProbeFormat {
+    duration: Option<String>,
+    #[serde(default)]
+    tags: HashMap<String, String>,
+}
+
+/// Run ffprobe on the first video stream
+pub fn probe(ffprobe_path: &Path, path: &Path) -> Result<ProbedVideo, ProcessingError> {
+    let entries = format!(
+        "stream=width,height,codec_name,duration,color_transfer,color_primaries,pix_fmt,bits_per_raw_sample\
+         :stream_side_data=side_data_type,projection:format=duration:format_tags={}",
+        [LOCATION_TAGS.as_slice(), CREATION_TIME_TAGS.as_slice()].concat().join(",")
+    );
+    let mut command = Command::new(ffprobe_path);
+    background_priority::lower_child(&mut command);
+    let output = command
+        .args(["-v", "error", "-select_streams", "v:0", "-show_entries", &entries, "-of", "json"])
+        .arg(path)
+        .output()
+        .map_err(|e| ProcessingError::ExternalTool(format!("Failed to run ffprobe: {}", e)))?;
+
+    if !output.status.success() {
+        return Err(ProcessingError::ExternalTool(format!(
+            "ffprobe failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+
+    parse_probe_output(&output.stdout)
+}
+
+/// Parse `ffprobe -of json` output; the stream duration falls back to the container duration
+pub fn parse_probe_output(json: &[u8]) -> Result<ProbedVideo, ProcessingError> {
+    let probe: ProbeOutput = serde_json::from_slice(json)
+        .map_err(|e| ProcessingError::ExternalTool(format!("Invalid ffprobe output: {}", e)))?;
+
+    let parse_duration = |d: Option<String>| d.and_then(|d| d.parse::<f64>().ok()).filter(|d| *d > 0.0);
+    let (format_duration, format_tags) = match probe.format {
+        Some(format) => (parse_duration(format.duration), format.tags),
+        None => (None, HashMap::new()),
+    };
+    let tags = VideoTags::from_lookup(|key| format_tags.get(key).map(String::as_str));
+
+    Ok(match probe.streams.into_iter().next() {
+        Some(stream) => ProbedVideo {
+            width: stream.width,
+            height: stream.height,
+            duration: parse_duration(stream.duration).or(format_duration),
+            codec: stream.codec_name,
+            color: VideoColorInfo::from_stream(
+                stream.color_transfer.as_deref(),
+                stream.color_primaries.as_deref(),
+                stream.pix_fmt.as_deref(),
+                stream.bits_per_raw_sample.and_then(|b| b.parse().ok()),
+                stream
+                    .side_data_list
+                    .iter()
+                    .any(|side_data| side_data.side_data_type.as_deref() == Some("DOVI configuration record")),
+            ),
+            projection: stream
+                .side_data_list
+                .into_iter()
+                .filter(|side_data| side_data.side_data_type.as_deref() == Some("Spherical Mapping"))
+                .find_map(|side_data| side_data.projection.as_deref().and_then(normalize_video_projection)),
+            tags,
+        },
+        None => ProbedVideo {
+            duration: format_duration,
+            tags,
+            ..Default::default()
+        },
+    })
+}
+
+/// Extract a PNG poster frame at `offset_seconds`, scaled to `target_width` (0 = original size).
+/// The ffmpeg CLI applies the display rotation itself. Videos shorter than the offset
+/// fall back to the first frame.
+pub fn extract_poster_frame(
+    ffmpeg_path: &Path,
+    path: &Path,
+    offset_seconds: f64,
+    target_width: u32,
+) -> Result<Vec<u8>, ProcessingError> {
+    let frame = run_ffmpeg_frame(ffmpeg_path, path, offset_seconds, target_width)?;
+    if !frame.is_empty() || offset_seconds <= 0.0 {
+        return non_empty(frame);
+    }
+    non_empty(run_ffmpeg_frame(ffmpeg_path, path, 0.0, target_width)?)
+}
+
+/// Extract a PNG frame at exactly `seconds` (no fallback), scaled to `target_width`.
+/// Fails when the timestamp is past the end of the video.
+pub fn extract_frame(
+    ffmpeg_path: &Path,
+    path: &Path,
+    seconds: f64,
+    target_width: u32,
+) -> Result<Vec<u8>, ProcessingError> {
+    non_empty(run_ffmpeg_frame(ffmpeg_path, path, seconds, target_width)?)
+}
+
+fn run_ffmpeg_frame(
+    ffmpeg_path: &Path,
+    path: &Path,
+    offset_seconds: f64,
+    target_width: u32,
+) -> Result<Vec<u8>, ProcessingError> {
+    let mut command = Command::new(ffmpeg_path);
+    background_priority::lower_child(&mut command);
+    command
+        .args(["-v", "error", "-ss", &offset_seconds.to_string(), "-i"])
+        .arg(path)
+        .args(["-frames:v", "1"]);
+    if target_width > 0 {
+        // -2：按比例计算高度并保持偶数
+        command.args(["-vf", &format!("scale={}:-2", target_width)]);
+    }
+    let output = command
+        // PNG 无损输出，由缩略图流水线统一缩放和编码
+        .args(["-f", "image2pipe", "-vcodec", "png", "pipe:1"])
+        .output()
+        .map_err(|e| ProcessingError::ExternalTool(format!("Failed to run ffmpeg: {}", e)))?;
+
+    if !output.status.success() {
+        return Err(ProcessingError::ExternalTool(format!(
+            "ffmpeg frame extraction failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    Ok(output.stdout)
+}
+
+fn non_empty(frame: Vec<u8>) -> Result<Vec<u8>, ProcessingError> {
+    if frame.is_empty() {
+        Err(ProcessingError::Processing("Failed to decode video frame".to_string()))
+    } else {
+        Ok(frame)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_probe_output() {
+        let json = br#"{
+            "programs": [],
+            "streams": [{ "codec_name": "h264", "width": 1920, "height": 1080, "duration": "12.500000" }],
+            "format": { "duration": "12.533000" }
+        }"#;
+        let probed = parse_probe_output(json).unwrap();
+        assert_eq!(
+            probed,
+            ProbedVideo {
+                width: Some(1920),
+                height: Some(1080),
+                duration: Some(12.5),
+                codec: Some("h264".to_string()),
+                projection: None,
+                color: VideoColorInfo::default(),
+                tags: VideoTags::default(),
+            }
+        );
+    }
+
+    #[test]
+    fn test_parse_probe_output_location() {
+        let json = br#"{ "streams": [{ "codec_name": "hevc", "width": 1920, "height": 1080 }], "format": {
+            "duration": "4.0", "tags": { "com.apple.quicktime.location.ISO6709": "+37.3349-122.0090+015.000/" } } }"#;
+        let probed = parse_probe_output(json).unwrap();
+        assert_eq!(probed.tags.location, Some((37.3349, -122.009)));
+    }
+
+    #[test]
+    fn test_parse_probe_output_creation_time() {
+        let json = br#"{ "streams": [{ "codec_name": "h264", "width": 1280, "height": 720 }], "format": {
+            "duration": "4.0", "tags": { "creation_time": "2021-07-04T19:02:11.000000Z" } } }"#;
+        let (time, offset) = parse_probe_output(json).unwrap().tags.creation_time.unwrap();
+        assert_eq!(time.to_string(), "2021-07-04 19:02:11");
+        assert_eq!(offset, "+00:00");
+    }
+
+    #[test]
+    fn test_parse_probe_output_spherical() {
+        let json = br#"{ "streams": [{ "codec_name": "hevc", "width": 5760, "height": 2880, "side_data_list": [
+            { "side_data_type": "Display Matrix", "rotation": 0 },
+            { "side_data_type": "Spherical Mapping", "projection": "equirectangular", "yaw": 0 }
+        ] }] }"#;
+        let probed = parse_probe_output(json).unwrap();
+        assert_eq!(probed.projection.as_deref(), Some("equirectangular"));
+    }
+
+    #[test]
+    fn test_parse_probe_output_hdr() {
+        let json = br#"{ "streams": [{ "codec_name": "hevc", "width": 3840, "height": 2160, "pix_fmt": "yuv420p10le",
+            "color_transfer": "smpte2084", "color_primaries": "bt2020", "bits_per_raw_sample": "10", "side_data_list": [
+            { "side_data_type": "DOVI configuration record", "dv_profile": 8 }
+        ] }] }"#;
+        let probed = parse_probe_output(json).unwrap();
+        assert_eq!(probed.color.hdr_format.as_deref(), Some(crate::processors::video_color::HDR_DOLBY_VISION));
+        assert_eq!(probed.color.bit_depth, Some(10));
+        assert_eq!(probed.color.color_primaries.as_deref(), Some("bt2020"));
+    }
+
+    #[test]
+    fn test_parse_probe_output_container_duration() {
+        // MKV/WebM 的视频流通常没有时长，回退到容器时长
+        let json = br#"{ "streams": [{ "codec_name": "vp9", "width": 640, "height": 360 }], "format": { "duration": "3.0" } }"#;
+        let probed = parse_probe_output(json).unwrap();
+        assert_eq!(probed.duration, Some(3.0));
+        assert_eq!(probed.codec.as_deref(), Some("vp9"));
+    }
+
+    #[test]
+    fn test_parse_probe_output_no_video_stream() {
+        let probed = parse_probe_output(br#"{ "streams": [], "format": {} }"#).unwrap();
+        assert_eq!(probed, ProbedVideo::default());
+    }
+
+    #[test]
+    fn test_parse_probe_output_invalid() {
+        assert!(parse_probe_output(b"not json").is_err());
+    }
+}
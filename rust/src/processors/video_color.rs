@@ -0,0 +1,134 @@
+//! 视频 HDR / 色彩空间识别
+//! 根据视频流的传输特性（color_transfer）、Dolby Vision 配置、像素格式判断 HDR 格式和位深。
+//! FFmpeg 库与 ffprobe 回退共用这里的判断逻辑，名称统一使用 FFmpeg 的命名（smpte2084、bt2020 ...）。
+//! 结果只用于展示：前端据此提示 HDR 视频在 SDR 屏幕上可能发灰；缩略图和预览不做色调映射。
+
+/// Hybrid Log-Gamma (broadcast HDR, iPhone/Android HDR video)
+pub const HDR_HLG: &str = "hlg";
+/// SMPTE ST 2084 perceptual quantizer (HDR10 / HDR10+)
+pub const HDR_PQ: &str = "pq";
+pub const HDR_DOLBY_VISION: &str = "dolby_vision";
+
+/// HDR and color space information of a video stream
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct VideoColorInfo {
+    pub hdr_format: Option<String>,
+    pub bit_depth: Option<i32>,
+    pub color_primaries: Option<String>,
+}
+
+impl VideoColorInfo {
+    /// Build from the FFmpeg names of the stream properties
+    pub fn from_stream(
+        color_transfer: Option<&str>,
+        color_primaries: Option<&str>,
+        pix_fmt: Option<&str>,
+        bits_per_raw_sample: Option<i32>,
+        has_dolby_vision: bool,
+    ) -> Self {
+        Self {
+            hdr_format: classify_hdr(color_transfer, has_dolby_vision).map(str::to_string),
+            bit_depth: bits_per_raw_sample
+                .filter(|bits| *bits > 0)
+                .or_else(|| pix_fmt.and_then(bit_depth_from_pix_fmt)),
+            // "unknown"/"reserved" 等同于未标记
+            color_primaries: color_primaries
+                .filter(|p| !p.is_empty() && *p != "unknown" && *p != "reserved")
+                .map(str::to_string),
+        }
+    }
+}
+
+/// HDR format from the transfer characteristic; Dolby Vision wins because its base layer
+/// usually also signals PQ or HLG
+pub fn classify_hdr(color_transfer: Option<&str>, has_dolby_vision: bool) -> Option<&'static str> {
+    if has_dolby_vision {
+        return Some(HDR_DOLBY_VISION);
+    }
+    match color_transfer? {
+        "smpte2084" => Some(HDR_PQ),
+        "arib-std-b67" => Some(HDR_HLG),
+        _ => None,
+    }
+}
+
+/// Bit depth from an FFmpeg pixel format name ("yuv420p" -> 8, "yuv420p10le" -> 10, "p010le" -> 10)
+pub fn bit_depth_from_pix_fmt(pix_fmt: &str) -> Option<i32> {
+    let name = pix_fmt
+        .strip_suffix("le")
+        .or_else(|| pix_fmt.strip_suffix("be"))
+        .unwrap_or(pix_fmt);
+
+    // 半平面 P010/P016 等格式：p + 三位位深
+    if let Some(depth) = name.strip_prefix('p').filter(|d| d.len() == 3) {
+        return depth.parse::<i32>().ok().filter(|d| *d > 8);
+    }
+    if ["nv12", "nv16", "nv21", "nv24"].contains(&name) {
+        return Some(8);
+    }
+
+    // 平面 YUV / GBR / 灰度：位深跟在末尾的 "p" 之后，没有数字即 8 位
+    let planar = ["yuv", "yuvj", "yuva", "gbrp", "gbrap", "gray"]
+        .iter()
+        .any(|prefix| name.starts_with(prefix));
+    if !planar {
+        return None;
+    }
+    let digits = name.len() - name.trim_end_matches(|c: char| c.is_ascii_digit()).len();
+    let (head, depth) = name.split_at(name.len() - digits);
+    if depth.is_empty() {
+        return Some(8);
+    }
+    // "yuv420p10" 的位深前缀是 "p"；"gray10" 直接跟数字
+    if head.ends_with('p') || head == "gray" {
+        depth.parse().ok()
+    } else {
+        Some(8)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_classify_hdr() {
+        assert_eq!(classify_hdr(Some("smpte2084"), false), Some(HDR_PQ));
+        assert_eq!(classify_hdr(Some("arib-std-b67"), false), Some(HDR_HLG));
+        assert_eq!(classify_hdr(Some("arib-std-b67"), true), Some(HDR_DOLBY_VISION));
+        assert_eq!(classify_hdr(Some("bt709"), false), None);
+        assert_eq!(classify_hdr(None, false), None);
+    }
+
+    #[test]
+    fn test_bit_depth_from_pix_fmt() {
+        assert_eq!(bit_depth_from_pix_fmt("yuv420p"), Some(8));
+        assert_eq!(bit_depth_from_pix_fmt("yuvj420p"), Some(8));
+        assert_eq!(bit_depth_from_pix_fmt("yuv420p10le"), Some(10));
+        assert_eq!(bit_depth_from_pix_fmt("yuv422p12be"), Some(12));
+        assert_eq!(bit_depth_from_pix_fmt("p010le"), Some(10));
+        assert_eq!(bit_depth_from_pix_fmt("nv12"), Some(8));
+        assert_eq!(bit_depth_from_pix_fmt("gray10le"), Some(10));
+        assert_eq!(bit_depth_from_pix_fmt("gbrp"), Some(8));
+        assert_eq!(bit_depth_from_pix_fmt("rgb24"), None);
+    }
+
+    #[test]
+    fn test_from_stream() {
+        let info = VideoColorInfo::from_stream(Some("arib-std-b67"), Some("bt2020"), Some("yuv420p10le"), None, false);
+        assert_eq!(
+            info,
+            VideoColorInfo {
+                hdr_format: Some(HDR_HLG.to_string()),
+                bit_depth: Some(10),
+                color_primaries: Some("bt2020".to_string()),
+            }
+        );
+
+        // bits_per_raw_sample 优先于像素格式
+        let info = VideoColorInfo::from_stream(None, Some("unknown"), Some("yuv420p"), Some(10), false);
+        assert_eq!(info.bit_depth, Some(10));
+        assert_eq!(info.color_primaries, None);
+        assert_eq!(info.hdr_format, None);
+    }
+}
@@ -1,8 +1,15 @@
 use crate::processors::processor_trait::{
     MediaMetadata, MediaProcessor, MediaType, ProcessingError,
 };
+use crate::processors::extensions;
+use crate::processors::video_color::VideoColorInfo;
+use crate::processors::video_tags::VideoTags;
+use crate::services::background_priority;
+use crate::services::thumbnail_metrics::{Stage, StageTimer, ThumbnailMetrics};
+use crate::utils::{ThumbnailFormat, ThumbnailOptions, ThumbnailPipeline};
 use async_trait::async_trait;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 
 #[cfg(feature = "video-processing")]
 use ffmpeg_next::codec::packet::side_data::Type as PacketSideDataType;
@@ -50,29 +57,92 @@ fn get_rotation_angle(stream: &ffmpeg_next::Stream) -> Option<i32> {
     None
 }
 
+/// Get the projection of a 360° video from the stream's Spherical Mapping side data
+#[cfg(feature = "video-processing")]
+fn get_spherical_projection(stream: &ffmpeg_next::Stream) -> Option<String> {
+    let side_data = stream.side_data().find(|side_data| side_data.kind() == PacketSideDataType::Spherical)?;
+    // AVSphericalMapping 的第一个字段是 AVSphericalProjection 枚举
+    let data = side_data.data();
+    let projection = u32::from_ne_bytes(data.get(..4)?.try_into().ok()?);
+    let name = match projection {
+        0 => "equirectangular",
+        1 => "cubemap",
+        2 => "tiled equirectangular",
+        _ => return None,
+    };
+    crate::processors::panorama::normalize_video_projection(name)
+}
+
+/// Dolby Vision streams carry a DOVI configuration record in their side data
+#[cfg(feature = "video-processing")]
+fn has_dolby_vision_config(stream: &ffmpeg_next::Stream) -> bool {
+    stream.side_data().any(|side_data| side_data.kind() == PacketSideDataType::DOVIConf)
+}
+
+/// Source label of video thumbnails in ThumbnailMetrics, whatever the container
+const VIDEO_SOURCE: &str = "video";
+
 /// Video processor for MP4, AVI, MOV, MKV, etc.
-/// Uses ffmpeg-next for video processing when available
+/// Uses ffmpeg-next for video processing when available,
+/// otherwise shells out to the ffprobe/ffmpeg binaries (see video_cli)
 pub struct VideoProcessor {
     #[allow(dead_code)]
     ffmpeg_path: Option<String>,
+    /// ffprobe binary for the CLI fallback
+    #[cfg_attr(feature = "video-processing", allow(dead_code))]
+    ffprobe_path: Option<PathBuf>,
+    /// Poster frame offset in seconds for the CLI fallback
+    #[cfg_attr(feature = "video-processing", allow(dead_code))]
+    thumbnail_offset: f64,
+    /// Built-in extensions plus LATTE_EXTRA_VIDEO_EXTS
+    extensions: Vec<String>,
+    /// Stage timings of generated thumbnails
+    metrics: Option<Arc<ThumbnailMetrics>>,
 }
 
 impl VideoProcessor {
     pub fn new(ffmpeg_path: Option<String>) -> Self {
-        Self { ffmpeg_path }
+        Self {
+            ffmpeg_path,
+            ffprobe_path: None,
+            thumbnail_offset: 1.0,
+            extensions: extensions::with_extra(extensions::VIDEO_EXTENSIONS, &[]),
+            metrics: None,
+        }
     }
 
-    const SUPPORTED_EXTENSIONS: &[&str] = &["mp4", "avi", "mov", "mkv", "wmv", "flv", "webm"];
+    /// Configure the ffprobe/ffmpeg CLI fallback used when built without the video-processing feature
+    pub fn with_cli_fallback(mut self, ffprobe_path: PathBuf, thumbnail_offset: f64) -> Self {
+        self.ffprobe_path = Some(ffprobe_path);
+        self.thumbnail_offset = thumbnail_offset;
+        self
+    }
+
+    /// Also handle these extensions (LATTE_EXTRA_VIDEO_EXTS), e.g. .insv from 360° cameras
+    pub fn with_extra_extensions(mut self, extra: &[String]) -> Self {
+        self.extensions = extensions::with_extra(extensions::VIDEO_EXTENSIONS, extra);
+        self
+    }
+
+    /// Record decode/resize/encode timings of thumbnails, all under the "video" source
+    pub fn with_thumbnail_metrics(mut self, metrics: Arc<ThumbnailMetrics>) -> Self {
+        self.metrics = Some(metrics);
+        self
+    }
 }
 
 #[async_trait]
 impl MediaProcessor for VideoProcessor {
+    fn name(&self) -> &'static str {
+        "video"
+    }
+
+    fn extensions(&self) -> Vec<String> {
+        self.extensions.clone()
+    }
+
     fn supports(&self, path: &Path) -> bool {
-        if let Some(ext) = path.extension().and_then(|e| e.to_str()) {
-            Self::SUPPORTED_EXTENSIONS.contains(&ext.to_lowercase().as_str())
-        } else {
-            false
-        }
+        extensions::matches(path, &self.extensions)
     }
 
     fn priority(&self) -> i32 {
@@ -90,11 +160,18 @@ impl MediaProcessor for VideoProcessor {
         {
             // Try to extract video metadata using FFmpeg (format-specific)
             match extract_video_metadata(path) {
-                Ok((width, height, duration, codec)) => {
+                Ok((width, height, duration, codec, projection, color, tags)) => {
                     metadata.width = width;
                     metadata.height = height;
                     metadata.duration = duration;
                     metadata.video_codec = codec;
+                    metadata.projection = projection;
+                    metadata.hdr_format = color.hdr_format;
+                    metadata.bit_depth = color.bit_depth;
+                    metadata.color_primaries = color.color_primaries;
+                    metadata.gps_latitude = tags.location.map(|(lat, _)| lat);
+                    metadata.gps_longitude = tags.location.map(|(_, lon)| lon);
+                    (metadata.exif_timestamp, metadata.exif_timezone_offset) = tags.creation_time.unzip();
                 }
                 Err(e) => {
                     tracing::warn!("Failed to extract video metadata: {}", e);
@@ -104,7 +181,39 @@ impl MediaProcessor for VideoProcessor {
 
         #[cfg(not(feature = "video-processing"))]
         {
-            tracing::warn!("Video processing not enabled - skipping metadata extraction for {}", path.display());
+            match &self.ffprobe_path {
+                Some(ffprobe_path) => {
+                    let ffprobe_path = ffprobe_path.clone();
+                    let path_buf = path.to_path_buf();
+                    let probed = background_priority::spawn_blocking(move || {
+                        crate::processors::video_cli::probe(&ffprobe_path, &path_buf)
+                    })
+                    .await
+                    .map_err(|e| ProcessingError::Processing(e.to_string()))?;
+
+                    match probed {
+                        Ok(probed) => {
+                            metadata.width = probed.width;
+                            metadata.height = probed.height;
+                            metadata.duration = probed.duration;
+                            metadata.video_codec = probed.codec;
+                            metadata.projection = probed.projection;
+                            metadata.hdr_format = probed.color.hdr_format;
+                            metadata.bit_depth = probed.color.bit_depth;
+                            metadata.color_primaries = probed.color.color_primaries;
+                            metadata.gps_latitude = probed.tags.location.map(|(lat, _)| lat);
+                            metadata.gps_longitude = probed.tags.location.map(|(_, lon)| lon);
+                            (metadata.exif_timestamp, metadata.exif_timezone_offset) = probed.tags.creation_time.unzip();
+                        }
+                        Err(e) => {
+                            tracing::warn!("Failed to extract video metadata: {}", e);
+                        }
+                    }
+                }
+                None => {
+                    tracing::warn!("Video processing not enabled - skipping metadata extraction for {}", path.display());
+                }
+            }
         }
 
         // Set MIME type
@@ -127,17 +236,29 @@ impl MediaProcessor for VideoProcessor {
     async fn generate_thumbnail(
         &self,
         path: &Path,
-        _target_size: u32,
-        _quality: f32,
-        _fit_to_height: bool,
+        target_size: u32,
+        quality: f32,
+        format: ThumbnailFormat,
+        fit_to_height: bool,
     ) -> Result<Option<Vec<u8>>, ProcessingError> {
+        let options = ThumbnailOptions::new(target_size, quality, fit_to_height).with_format(format);
+        let metrics = self.metrics.clone();
+        let record = move |timer: &StageTimer| {
+            if let Some(metrics) = &metrics {
+                metrics.record(VIDEO_SOURCE, format, timer);
+            }
+        };
+
         #[cfg(feature = "video-processing")]
         {
             let path = path.to_path_buf();
             let ffmpeg_path = self.ffmpeg_path.clone();
 
-            let result = tokio::task::spawn_blocking(move || {
-                generate_video_thumbnail(&path, _target_size, ffmpeg_path.as_deref())
+            let result = background_priority::spawn_blocking(move || {
+                let mut timer = StageTimer::start();
+                let bytes = generate_video_thumbnail(&path, options, ffmpeg_path.as_deref(), &mut timer)?;
+                record(&timer);
+                Ok::<_, ProcessingError>(bytes)
             })
             .await
             .map_err(|e| ProcessingError::Processing(e.to_string()))?;
@@ -147,14 +268,36 @@ impl MediaProcessor for VideoProcessor {
 
         #[cfg(not(feature = "video-processing"))]
         {
-            tracing::warn!("Video processing not enabled - cannot generate thumbnail for {}", path.display());
-            return Ok(None);
+            let Some(ffmpeg_path) = self.ffmpeg_path.clone() else {
+                tracing::warn!("Video processing not enabled - cannot generate thumbnail for {}", path.display());
+                return Ok(None);
+            };
+            let path = path.to_path_buf();
+            let offset = self.thumbnail_offset;
+
+            let result = background_priority::spawn_blocking(move || {
+                let mut timer = StageTimer::start();
+                let frame = crate::processors::video_cli::extract_poster_frame(Path::new(&ffmpeg_path), &path, offset, target_size)?;
+                let frame = image::load_from_memory_with_format(&frame, image::ImageFormat::Png)?;
+                timer.lap(Stage::Decode);
+                let pipeline = ThumbnailPipeline::new(options);
+                let frame = pipeline.resize(frame);
+                timer.lap(Stage::Resize);
+                let bytes = pipeline.finish(frame)?;
+                timer.lap(Stage::Encode);
+                record(&timer);
+                Ok::<_, ProcessingError>(bytes)
+            })
+            .await
+            .map_err(|e| ProcessingError::Processing(e.to_string()))?;
+
+            return result.map(Some);
         }
     }
 }
 
-/// 从视频文件提取的元数据：(宽, 高, 时长秒, 编码器名称)
-type VideoMetadata = (Option<i32>, Option<i32>, Option<f64>, Option<String>);
+/// 从视频文件提取的元数据：(宽, 高, 时长秒, 编码器名称, 360° 投影, HDR/色彩信息, 容器标签)
+type VideoMetadata = (Option<i32>, Option<i32>, Option<f64>, Option<String>, Option<String>, VideoColorInfo, VideoTags);
 
 #[cfg(feature = "video-processing")]
 fn extract_video_metadata(path: &Path) -> Result<VideoMetadata, ProcessingError> {
@@ -168,6 +311,8 @@ fn extract_video_metadata(path: &Path) -> Result<VideoMetadata, ProcessingError>
     let mut height = None;
     let mut duration = None;
     let mut codec = None;
+    let mut projection = None;
+    let mut color = VideoColorInfo::default();
 
     // Get stream information
     for stream in input.streams() {
@@ -181,8 +326,16 @@ fn extract_video_metadata(path: &Path) -> Result<VideoMetadata, ProcessingError>
                     // Get codec name
                     let codec_id = decoder.id();
                     codec = Some(codec_id.name().to_string());
+                    color = VideoColorInfo::from_stream(
+                        decoder.color_transfer_characteristic().name(),
+                        decoder.color_primaries().name(),
+                        decoder.format().descriptor().map(|d| d.name()),
+                        None,
+                        has_dolby_vision_config(&stream),
+                    );
                 }
             }
+            projection = projection.or_else(|| get_spherical_projection(&stream));
 
             // Get duration from stream (returns i64 directly in new API)
             let dur = stream.duration();
@@ -201,14 +354,18 @@ fn extract_video_metadata(path: &Path) -> Result<VideoMetadata, ProcessingError>
         }
     }
 
-    Ok((width, height, duration, codec))
+    let metadata = input.metadata();
+    let tags = VideoTags::from_lookup(|key| metadata.get(key));
+
+    Ok((width, height, duration, codec, projection, color, tags))
 }
 
 #[cfg(feature = "video-processing")]
 fn generate_video_thumbnail(
     path: &Path,
-    target_width: u32,
+    options: ThumbnailOptions,
     _ffmpeg_path: Option<&str>,
+    timer: &mut StageTimer,
 ) -> Result<Vec<u8>, ProcessingError> {
     use ffmpeg_next::format::input;
     use ffmpeg_next::media::Type;
@@ -268,17 +425,15 @@ fn generate_video_thumbnail(
     // 90, -90, 270, -270 degree rotations swap width and height visually
     let needs_swap = matches!(rotation, Some(r) if r == 90 || r == -90 || r == 270 || r == -270);
 
-    // Use original decoder dimensions for scaler
+    // Use original decoder dimensions for scaler; the target size is computed on the
+    // displayed (rotated) dimensions and mapped back to the stored orientation
+    let pipeline = ThumbnailPipeline::new(options);
     let (scaler_width, scaler_height) = (decoder.width(), decoder.height());
     let (target_width, target_height) = if needs_swap {
-        // For 90/-90 rotation, the visual aspect ratio is swapped
-        let aspect_ratio = scaler_width as f64 / scaler_height as f64;
-        let target_h = (target_width as f64 / aspect_ratio) as u32;
-        (target_width, target_h)
+        let (w, h) = pipeline.target_dimensions(scaler_height, scaler_width);
+        (h, w)
     } else {
-        let aspect_ratio = scaler_height as f64 / scaler_width as f64;
-        let target_h = (target_width as f64 * aspect_ratio) as u32;
-        (target_width, target_h)
+        pipeline.target_dimensions(scaler_width, scaler_height)
     };
 
     // Seek to target time (default 1.0 second)
@@ -344,6 +499,8 @@ fn generate_video_thumbnail(
         tracing::warn!("Failed to decode any frame from video");
         return Err(ProcessingError::Processing("Failed to decode video frame".to_string()));
     }
+    // FFmpeg 的缩放在解码循环中完成，计入解码阶段
+    timer.lap(Stage::Decode);
 
     // Get RGB data and handle stride padding
     let width = rgb_frame.width();
@@ -398,15 +555,9 @@ fn generate_video_thumbnail(
         }
     };
 
-    // Encode as JPEG with 80% quality
-    let mut jpeg_bytes = Vec::new();
-    {
-        let mut encoder = image::codecs::jpeg::JpegEncoder::new_with_quality(&mut jpeg_bytes, 80);
-        if let Err(e) = encoder.encode_image(&final_image) {
-            tracing::warn!("Failed to encode JPEG: {}", e);
-            return Err(ProcessingError::Processing(e.to_string()));
-        }
-    }
+    timer.lap(Stage::Resize);
 
-    Ok(jpeg_bytes)
+    let bytes = pipeline.finish(image::DynamicImage::ImageRgb8(final_image))?;
+    timer.lap(Stage::Encode);
+    Ok(bytes)
 }
@@ -0,0 +1,222 @@
+//! 视频容器元数据标签
+//! 手机拍摄的视频把拍摄地点写在容器级标签中：iPhone 使用 QuickTime 的
+//! com.apple.quicktime.location.ISO6709，Android 和较早的相机使用 ©xyz（FFmpeg 中为 "location"）。
+//! 值为 ISO 6709 字符串（例如 "+37.3349-122.0090+015.000/"），换算为与照片相同的十进制经纬度，
+//! 视频因此也能出现在地图和地点筛选中。
+//! 拍摄时间同样取自容器：文件系统的创建时间在复制后会被重置，容器中的 creation_time 不会。
+//! iPhone 的 com.apple.quicktime.creationdate 带本地时区，优先于只有 UTC 时间的 creation_time；
+//! 只有 creation_time 时按服务器时区换算为本地时间，与没有时区信息的照片一致。
+//! FFmpeg 库与 ffprobe 回退共用这里的解析逻辑。
+
+use chrono::{DateTime, Datelike, FixedOffset, Local, NaiveDateTime};
+
+/// Tags holding the capture location, most specific first. FFmpeg appends the language
+/// of ©xyz ("location-eng") when it is set.
+pub const LOCATION_TAGS: [&str; 3] = ["com.apple.quicktime.location.ISO6709", "location", "location-eng"];
+
+/// QuickTime capture time with the local offset of the device
+pub const QUICKTIME_CREATION_DATE_TAG: &str = "com.apple.quicktime.creationdate";
+
+/// Container capture time in UTC
+pub const UTC_CREATION_TIME_TAG: &str = "creation_time";
+
+/// Tags holding the capture time, most specific first
+pub const CREATION_TIME_TAGS: [&str; 2] = [QUICKTIME_CREATION_DATE_TAG, UTC_CREATION_TIME_TAG];
+
+/// QuickTime stores 0 seconds since 1904 when the time is unset; ffmpeg may show it as 1904 or 1970
+const MIN_CREATION_YEAR: i32 = 1971;
+
+/// Values read from the container tags of a video
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct VideoTags {
+    /// (latitude, longitude) in decimal degrees
+    pub location: Option<(f64, f64)>,
+    /// Capture time as local wall-clock time plus its UTC offset ("+08:00"),
+    /// the same representation as exif_timestamp / exif_timezone_offset of photos
+    pub creation_time: Option<(NaiveDateTime, String)>,
+}
+
+impl VideoTags {
+    /// Build from a tag lookup (FFmpeg format metadata or ffprobe `format.tags`)
+    pub fn from_lookup<'a>(lookup: impl Fn(&str) -> Option<&'a str>) -> Self {
+        Self {
+            location: LOCATION_TAGS.iter().find_map(|tag| lookup(tag).and_then(parse_iso6709)),
+            creation_time: lookup(QUICKTIME_CREATION_DATE_TAG)
+                .and_then(parse_creation_time)
+                .or_else(|| lookup(UTC_CREATION_TIME_TAG).and_then(parse_utc_creation_time)),
+        }
+    }
+}
+
+/// Local time and offset of an ISO 8601 container timestamp: "2024-05-01T08:30:00.000000Z"
+/// (creation_time, UTC) or "2024-05-01T16:30:00+0800" (QuickTime creationdate).
+/// Unset QuickTime times (1904 / 1970) are rejected.
+pub fn parse_creation_time(value: &str) -> Option<(NaiveDateTime, String)> {
+    let time = parse_timestamp(value)?;
+    Some((time.naive_local(), time.offset().to_string()))
+}
+
+/// Like [`parse_creation_time`], but converted to the server's local time zone. Used for
+/// creation_time, which only records the UTC instant, so that videos line up with photos
+/// taken at the same moment.
+pub fn parse_utc_creation_time(value: &str) -> Option<(NaiveDateTime, String)> {
+    let time = parse_timestamp(value)?.with_timezone(&Local);
+    Some((time.naive_local(), time.offset().to_string()))
+}
+
+fn parse_timestamp(value: &str) -> Option<DateTime<FixedOffset>> {
+    let value = value.trim();
+    let time = DateTime::parse_from_rfc3339(value)
+        .or_else(|_| DateTime::parse_from_str(value, "%Y-%m-%dT%H:%M:%S%.f%z"))
+        .ok()?;
+    (time.year() >= MIN_CREATION_YEAR).then_some(time)
+}
+
+/// Latitude and longitude of an ISO 6709 point ("±DD.DDDD±DDD.DDDD[±AAA.AAA][CRS]/").
+/// Degrees may also be written as ±DDMM.MM / ±DDMMSS.SS. Returns None for malformed or
+/// out-of-range values and for (0, 0), which devices write when no fix was available.
+pub fn parse_iso6709(value: &str) -> Option<(f64, f64)> {
+    let value = value.trim();
+    let (latitude, rest) = split_signed(value)?;
+    let (longitude, _) = split_signed(rest)?;
+
+    let latitude = angle(latitude, 2)?;
+    let longitude = angle(longitude, 3)?;
+    if latitude.abs() > 90.0 || longitude.abs() > 180.0 || (latitude == 0.0 && longitude == 0.0) {
+        return None;
+    }
+    Some((round6(latitude), round6(longitude)))
+}
+
+/// Split off the leading signed component ("+37.33", rest)
+fn split_signed(value: &str) -> Option<(&str, &str)> {
+    if !value.starts_with(['+', '-']) {
+        return None;
+    }
+    let end = value[1..]
+        .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+        .map_or(value.len(), |i| i + 1);
+    (end > 1).then(|| value.split_at(end))
+}
+
+/// Decimal degrees of a signed component; `degree_digits` is 2 for latitude, 3 for longitude
+fn angle(component: &str, degree_digits: usize) -> Option<f64> {
+    let (sign, digits) = component.split_at(1);
+    let integer_len = digits.find('.').unwrap_or(digits.len());
+    let number: f64 = digits.parse().ok()?;
+
+    let degrees = if integer_len == degree_digits + 2 {
+        // ±DDMM.MM
+        let degrees = (number / 100.0).trunc();
+        let minutes = number - degrees * 100.0;
+        if minutes >= 60.0 {
+            return None;
+        }
+        degrees + minutes / 60.0
+    } else if integer_len == degree_digits + 4 {
+        // ±DDMMSS.SS
+        let degrees = (number / 10_000.0).trunc();
+        let minutes = ((number - degrees * 10_000.0) / 100.0).trunc();
+        let seconds = number - degrees * 10_000.0 - minutes * 100.0;
+        if minutes >= 60.0 || seconds >= 60.0 {
+            return None;
+        }
+        degrees + minutes / 60.0 + seconds / 3600.0
+    } else {
+        number
+    };
+    Some(if sign == "-" { -degrees } else { degrees })
+}
+
+/// Round to 6 decimal places, like the GPS values of photos
+fn round6(v: f64) -> f64 {
+    (v * 1_000_000.0).round() / 1_000_000.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_decimal_degrees() {
+        assert_eq!(parse_iso6709("+37.3349-122.0090+015.000/"), Some((37.3349, -122.009)));
+        assert_eq!(parse_iso6709("-33.8568+151.2153/"), Some((-33.8568, 151.2153)));
+        assert_eq!(parse_iso6709("+35.6581+139.7414+040.285CRSWGS_84/"), Some((35.6581, 139.7414)));
+        assert_eq!(parse_iso6709("+48.8584+002.2945"), Some((48.8584, 2.2945)));
+    }
+
+    #[test]
+    fn test_parse_minutes_and_seconds() {
+        // 40°26.5'N 079°58.9'W
+        let (lat, lon) = parse_iso6709("+4026.50-07958.90/").unwrap();
+        assert!((lat - 40.441667).abs() < 1e-6);
+        assert!((lon + 79.981667).abs() < 1e-6);
+        // 40°26'46"N 079°58'56"W
+        let (lat, lon) = parse_iso6709("+402646-0795856/").unwrap();
+        assert!((lat - 40.446111).abs() < 1e-6);
+        assert!((lon + 79.982222).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_parse_rejects_invalid() {
+        for value in ["", "37.33-122.00/", "+91.0000+000.0000/", "+10.0+190.0/", "+00.0000+000.0000/", "+4070.00+00100.00/", "+37.33/"] {
+            assert_eq!(parse_iso6709(value), None, "{value}");
+        }
+    }
+
+    fn local(value: &str) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(value, "%Y-%m-%d %H:%M:%S").unwrap()
+    }
+
+    #[test]
+    fn test_parse_creation_time() {
+        assert_eq!(
+            parse_creation_time("2024-05-01T08:30:00.000000Z"),
+            Some((local("2024-05-01 08:30:00"), "+00:00".to_string()))
+        );
+        assert_eq!(
+            parse_creation_time("2024-05-01T16:30:00+0800"),
+            Some((local("2024-05-01 16:30:00"), "+08:00".to_string()))
+        );
+        assert_eq!(
+            parse_creation_time("2023-12-24T18:05:09-05:00"),
+            Some((local("2023-12-24 18:05:09"), "-05:00".to_string()))
+        );
+        for value in ["", "yesterday", "1904-01-01T00:00:00.000000Z", "1970-01-01T00:00:00Z", "2024-05-01 08:30:00"] {
+            assert_eq!(parse_creation_time(value), None, "{value}");
+        }
+    }
+
+    #[test]
+    fn test_from_lookup_prefers_quicktime_key() {
+        let tags = [("location", "+10.0000+020.0000/"), ("com.apple.quicktime.location.ISO6709", "+37.3349-122.0090/")];
+        let lookup = |key: &str| tags.iter().find(|(k, _)| *k == key).map(|(_, v)| *v);
+        assert_eq!(VideoTags::from_lookup(lookup).location, Some((37.3349, -122.009)));
+
+        let android = |key: &str| (key == "location-eng").then_some("+48.8584+002.2945/");
+        assert_eq!(VideoTags::from_lookup(android).location, Some((48.8584, 2.2945)));
+        assert_eq!(VideoTags::from_lookup(|_| None), VideoTags::default());
+
+        // creationdate 带本地时区，优先于 UTC 的 creation_time
+        let iphone = [
+            ("creation_time", "2024-05-01T08:30:00.000000Z"),
+            ("com.apple.quicktime.creationdate", "2024-05-01T16:30:00+0800"),
+        ];
+        let lookup = |key: &str| iphone.iter().find(|(k, _)| *k == key).map(|(_, v)| *v);
+        assert_eq!(
+            VideoTags::from_lookup(lookup).creation_time,
+            Some((local("2024-05-01 16:30:00"), "+08:00".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_utc_creation_time_uses_local_zone() {
+        let utc = DateTime::parse_from_rfc3339("2024-05-01T08:30:00Z").unwrap().with_timezone(&Local);
+        let expected = Some((utc.naive_local(), utc.offset().to_string()));
+        assert_eq!(parse_utc_creation_time("2024-05-01T08:30:00.000000Z"), expected);
+
+        let android = |key: &str| (key == "creation_time").then_some("2024-05-01T08:30:00.000000Z");
+        assert_eq!(VideoTags::from_lookup(android).creation_time, expected);
+        assert_eq!(parse_utc_creation_time("1970-01-01T00:00:00Z"), None);
+    }
+}
@@ -0,0 +1,174 @@
+//! 视频章节与关键帧时间线
+//! 用 ffprobe 读取章节标记和关键帧时间戳，供前端进度条预览（在章节边界显示缩略图）。
+//! 只读取数据包标志位，不解码画面，长视频也能较快完成。
+
+use crate::processors::processor_trait::ProcessingError;
+use crate::services::background_priority;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::process::Command;
+
+/// Chapter marker embedded in the container (MP4/MKV chapters)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VideoChapter {
+    pub start: f64,
+    pub end: f64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+}
+
+/// Chapters and keyframe timestamps (seconds) of a video
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VideoTimeline {
+    pub chapters: Vec<VideoChapter>,
+    pub keyframes: Vec<f64>,
+}
+
+#[derive(Deserialize)]
+struct ChaptersOutput {
+    #[serde(default)]
+    chapters: Vec<ProbeChapter>,
+}
+
+#[derive(Deserialize)]
+struct ProbeChapter {
+    start_time: Option<String>,
+    end_time: Option<String>,
+    #[serde(default)]
+    tags: std::collections::HashMap<String, String>,
+}
+
+/// Read chapters and keyframes; keyframes are thinned to at most `max_keyframes`
+pub fn extract_timeline(
+    ffprobe_path: &Path,
+    path: &Path,
+    max_keyframes: usize,
+) -> Result<VideoTimeline, ProcessingError> {
+    let chapters = run_ffprobe(
+        ffprobe_path,
+        path,
+        &["-v", "error", "-show_chapters", "-of", "json"],
+    )?;
+    let packets = run_ffprobe(
+        ffprobe_path,
+        path,
+        &[
+            "-v", "error",
+            "-select_streams", "v:0",
+            "-show_entries", "packet=pts_time,flags",
+            "-of", "csv=p=0",
+        ],
+    )?;
+
+    Ok(VideoTimeline {
+        chapters: parse_chapters(&chapters)?,
+        keyframes: thin_keyframes(parse_keyframes(&String::from_utf8_lossy(&packets)), max_keyframes),
+    })
+}
+
+fn run_ffprobe(ffprobe_path: &Path, path: &Path, args: &[&str]) -> Result<Vec<u8>, ProcessingError> {
+    let mut command = Command::new(ffprobe_path);
+    background_priority::lower_child(&mut command);
+    let output = command
+        .args(args)
+        .arg(path)
+        .output()
+        .map_err(|e| ProcessingError::ExternalTool(format!("Failed to run ffprobe: {}", e)))?;
+
+    if !output.status.success() {
+        return Err(ProcessingError::ExternalTool(format!(
+            "ffprobe failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    Ok(output.stdout)
+}
+
+/// Parse `ffprobe -show_chapters -of json` output
+pub fn parse_chapters(json: &[u8]) -> Result<Vec<VideoChapter>, ProcessingError> {
+    let output: ChaptersOutput = serde_json::from_slice(json)
+        .map_err(|e| ProcessingError::ExternalTool(format!("Invalid ffprobe output: {}", e)))?;
+
+    Ok(output
+        .chapters
+        .into_iter()
+        .filter_map(|chapter| {
+            let start = chapter.start_time?.parse::<f64>().ok()?;
+            let end = chapter.end_time?.parse::<f64>().ok()?;
+            let title = chapter.tags.get("title").filter(|t| !t.is_empty()).cloned();
+            Some(VideoChapter { start, end, title })
+        })
+        .collect())
+}
+
+/// Parse `pts_time,flags` CSV lines, keeping packets flagged as keyframes ('K'), sorted
+pub fn parse_keyframes(csv: &str) -> Vec<f64> {
+    let mut keyframes: Vec<f64> = csv
+        .lines()
+        .filter_map(|line| {
+            let (pts_time, flags) = line.trim().split_once(',')?;
+            if !flags.contains('K') {
+                return None;
+            }
+            pts_time.parse::<f64>().ok().filter(|t| *t >= 0.0)
+        })
+        .collect();
+    keyframes.sort_by(f64::total_cmp);
+    keyframes.dedup();
+    keyframes
+}
+
+/// Keep at most `max` keyframes, evenly spread over the list (first and last kept)
+pub fn thin_keyframes(keyframes: Vec<f64>, max: usize) -> Vec<f64> {
+    if max == 0 || keyframes.len() <= max {
+        return keyframes;
+    }
+    if max == 1 {
+        return keyframes.into_iter().take(1).collect();
+    }
+    let step = (keyframes.len() - 1) as f64 / (max - 1) as f64;
+    (0..max)
+        .map(|i| keyframes[(i as f64 * step).round() as usize])
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_chapters() {
+        let json = br#"{
+            "chapters": [
+                { "id": 0, "start_time": "0.000000", "end_time": "60.500000", "tags": { "title": "Intro" } },
+                { "id": 1, "start_time": "60.500000", "end_time": "120.000000" }
+            ]
+        }"#;
+        let chapters = parse_chapters(json).unwrap();
+        assert_eq!(
+            chapters,
+            vec![
+                VideoChapter { start: 0.0, end: 60.5, title: Some("Intro".to_string()) },
+                VideoChapter { start: 60.5, end: 120.0, title: None },
+            ]
+        );
+        assert!(parse_chapters(br#"{ "chapters": [] }"#).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_parse_keyframes() {
+        let csv = "0.000000,K__\n0.033367,___\n2.002000,K_\nN/A,K__\n1.001000,K__\n";
+        assert_eq!(parse_keyframes(csv), vec![0.0, 1.001, 2.002]);
+    }
+
+    #[test]
+    fn test_thin_keyframes() {
+        let keyframes: Vec<f64> = (0..10).map(f64::from).collect();
+        assert_eq!(thin_keyframes(keyframes.clone(), 20), keyframes);
+        assert_eq!(thin_keyframes(keyframes.clone(), 4), vec![0.0, 3.0, 6.0, 9.0]);
+        assert_eq!(thin_keyframes(keyframes.clone(), 1), vec![0.0]);
+        assert_eq!(thin_keyframes(keyframes.clone(), 0), keyframes);
+    }
+}
@@ -0,0 +1,220 @@
+//! 后台任务的 CPU / I/O 优先级
+//! 扫描和缩略图预生成在大图库上会长时间占满 CPU 与磁盘，浏览时的缩略图请求随之变慢。
+//! Linux 上后台任务的解码、缩放等工作改在单独的转码线程池中执行，其线程启动时调低 nice 值
+//! 并设置 I/O 调度类（ioprio_set，相当于 ionice），前台请求仍使用普通优先级的线程池。
+//! 任务是否属于后台由 `background` 设置的 task-local 标记决定，处理器代码无需区分。
+//! 后台任务中的阻塞读取（哈希、元数据）经 `spawn_blocking` 在降低优先级的线程中执行，
+//! 这些线程启动的 ffmpeg / ffprobe 子进程经 `lower_child` 同样降低优先级。
+//! 其他平台上不创建低优先级线程池，后台任务与前台共用线程池。
+
+use std::cell::Cell;
+use std::future::Future;
+use std::process::Command;
+use std::sync::OnceLock;
+
+tokio::task_local! {
+    static BACKGROUND: ();
+}
+
+/// Priority of background work, set when the low-priority pool is created
+static CONFIGURED: OnceLock<BackgroundPriority> = OnceLock::new();
+
+thread_local! {
+    /// Priority the current thread was lowered to, if it runs background work
+    static THREAD_PRIORITY: Cell<Option<BackgroundPriority>> = const { Cell::new(None) };
+}
+
+/// Run `future` as background work: CPU-bound steps it hands to the transcoding pool run on
+/// the low-priority threads. Tasks it spawns are not marked and must be wrapped themselves.
+pub async fn background<F: Future>(future: F) -> F::Output {
+    BACKGROUND.scope((), future).await
+}
+
+/// Whether the current task runs inside `background`
+pub fn is_background() -> bool {
+    BACKGROUND.try_with(|_| ()).is_ok()
+}
+
+/// Record the priority of background work for `spawn_blocking`; the first call wins
+pub fn configure(priority: BackgroundPriority) {
+    if priority.is_enabled() {
+        let _ = CONFIGURED.set(priority);
+    }
+}
+
+/// Run blocking work (file reads, hashing, metadata extraction) off the async runtime.
+/// Inside `background` it runs on a new thread lowered to the configured priority: a thread
+/// of tokio's blocking pool could not be raised again afterwards without privileges.
+pub async fn spawn_blocking<F, R>(f: F) -> std::io::Result<R>
+where
+    F: FnOnce() -> R + Send + 'static,
+    R: Send + 'static,
+{
+    let Some(priority) = CONFIGURED.get().copied().filter(|_| is_background()) else {
+        return tokio::task::spawn_blocking(f).await.map_err(std::io::Error::other);
+    };
+
+    let (tx, rx) = tokio::sync::oneshot::channel();
+    std::thread::Builder::new()
+        .name("latte-background-io".to_string())
+        .spawn(move || {
+            if let Err(e) = priority.apply_to_current_thread() {
+                tracing::warn!("Failed to lower background thread priority: {}", e);
+            }
+            let _ = tx.send(f());
+        })?;
+    rx.await.map_err(|_| std::io::Error::other("Background task panicked"))
+}
+
+/// Give a child process started from a background thread the same priority. Linux children
+/// inherit the nice value and I/O priority of the spawning thread only, so this is set in the
+/// child explicitly; foreground threads start children unchanged.
+pub fn lower_child(command: &mut Command) {
+    #[cfg(target_os = "linux")]
+    if let Some(priority) = THREAD_PRIORITY.with(Cell::get) {
+        use std::os::unix::process::CommandExt;
+        // SAFETY: the closure only makes the async-signal-safe setpriority / ioprio_set
+        // system calls; failures are ignored so the child still starts
+        unsafe {
+            command.pre_exec(move || {
+                let _ = priority.apply_to_current_thread();
+                Ok(())
+            });
+        }
+    }
+    #[cfg(not(target_os = "linux"))]
+    let _ = command;
+}
+
+/// I/O scheduling class of background threads (LATTE_BACKGROUND_IO_CLASS)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IoPriorityClass {
+    /// Lowest level of the default class: still progresses on a busy disk
+    BestEffort,
+    /// Only gets disk time when no other process uses the disk
+    Idle,
+    /// Leave the I/O priority unchanged
+    Unchanged,
+}
+
+impl IoPriorityClass {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "best-effort" | "besteffort" => Some(Self::BestEffort),
+            "idle" => Some(Self::Idle),
+            "none" | "" => Some(Self::Unchanged),
+            _ => None,
+        }
+    }
+}
+
+/// Priority applied to the threads of the background transcoding pool
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BackgroundPriority {
+    /// Nice value 0-19; 0 leaves the CPU priority unchanged
+    pub nice: u32,
+    pub io_class: IoPriorityClass,
+}
+
+impl BackgroundPriority {
+    /// Whether a separate low-priority pool is worth creating on this platform
+    pub fn is_enabled(&self) -> bool {
+        cfg!(target_os = "linux") && (self.nice > 0 || self.io_class != IoPriorityClass::Unchanged)
+    }
+
+    /// Lower the priority of the calling thread. Linux applies nice values and I/O priorities
+    /// per thread, so the rest of the process keeps its priority. A thread that already runs
+    /// at a higher nice value keeps it.
+    #[cfg(target_os = "linux")]
+    pub fn apply_to_current_thread(&self) -> std::io::Result<()> {
+        // ioprio_set(2)：IOPRIO_WHO_PROCESS + 0 表示调用线程
+        const IOPRIO_WHO_PROCESS: libc::c_int = 1;
+        const IOPRIO_CLASS_SHIFT: libc::c_int = 13;
+        const IOPRIO_CLASS_BE: libc::c_int = 2;
+        const IOPRIO_CLASS_IDLE: libc::c_int = 3;
+        const BE_LOWEST_LEVEL: libc::c_int = 7;
+
+        THREAD_PRIORITY.with(|priority| priority.set(Some(*self)));
+
+        let nice = self.nice.min(19) as libc::c_int;
+        let tid = unsafe { libc::gettid() } as libc::id_t;
+        // 只降低不提高：提高优先级需要 CAP_SYS_NICE
+        if nice > 0 && unsafe { libc::getpriority(libc::PRIO_PROCESS, tid) } < nice
+            && unsafe { libc::setpriority(libc::PRIO_PROCESS, tid, nice) } != 0
+        {
+            return Err(std::io::Error::last_os_error());
+        }
+
+        let ioprio = match self.io_class {
+            IoPriorityClass::BestEffort => (IOPRIO_CLASS_BE << IOPRIO_CLASS_SHIFT) | BE_LOWEST_LEVEL,
+            IoPriorityClass::Idle => IOPRIO_CLASS_IDLE << IOPRIO_CLASS_SHIFT,
+            IoPriorityClass::Unchanged => return Ok(()),
+        };
+        if unsafe { libc::syscall(libc::SYS_ioprio_set, IOPRIO_WHO_PROCESS, 0, ioprio) } != 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+        Ok(())
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    pub fn apply_to_current_thread(&self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_io_class() {
+        assert_eq!(IoPriorityClass::parse("idle"), Some(IoPriorityClass::Idle));
+        assert_eq!(IoPriorityClass::parse(" Best-Effort "), Some(IoPriorityClass::BestEffort));
+        assert_eq!(IoPriorityClass::parse("none"), Some(IoPriorityClass::Unchanged));
+        assert_eq!(IoPriorityClass::parse("realtime"), None);
+    }
+
+    #[tokio::test]
+    async fn test_background_marker_is_task_local() {
+        assert!(!is_background());
+        assert!(background(async { is_background() }).await);
+        assert!(!is_background());
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_apply_lowers_current_thread_only() {
+        let priority = BackgroundPriority { nice: 19, io_class: IoPriorityClass::BestEffort };
+        let before = unsafe { libc::getpriority(libc::PRIO_PROCESS, 0) };
+        let lowered = std::thread::spawn(move || {
+            priority.apply_to_current_thread().unwrap();
+            unsafe { libc::getpriority(libc::PRIO_PROCESS, libc::gettid() as libc::id_t) }
+        })
+        .join()
+        .unwrap();
+        // nice 值是绝对值，不是在原值上增加
+        assert_eq!(lowered, 19);
+        assert_eq!(unsafe { libc::getpriority(libc::PRIO_PROCESS, 0) }, before);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_lower_child_only_from_background_threads() {
+        fn child_nice() -> String {
+            let mut command = Command::new("nice");
+            lower_child(&mut command);
+            String::from_utf8(command.output().unwrap().stdout).unwrap().trim().to_string()
+        }
+
+        let before = child_nice();
+        let priority = BackgroundPriority { nice: 19, io_class: IoPriorityClass::Unchanged };
+        let lowered = std::thread::spawn(move || {
+            priority.apply_to_current_thread().unwrap();
+            child_nice()
+        })
+        .join()
+        .unwrap();
+        assert_eq!(lowered, "19");
+        assert_eq!(child_nice(), before);
+    }
+}
@@ -1,15 +1,47 @@
+//! 缩略图缓存
+//! 磁盘缓存按文件 id 前缀分两级子目录存放（`ab/cd/<id>_<size>`），避免单目录数十万文件拖慢 ext4。
+//! 写入先落到 `tmp/` 再 rename 到目标位置，读取方不会看到写了一半的文件。
+//! 旧版平铺布局（`<cache_dir>/<id>_<size>`）由 `migrate_flat_layout` 在后台迁移，迁移完成前读取会回退到旧路径。
+//! 配置了数据库时条目按内容哈希寻址（`<hash>_<size>_<format>`），移动、重新导入的文件以及重建数据库后都能复用已有缩略图；
+//! 尚未计算哈希的文件仍按 id 存放，计算出哈希后首次命中时改名为内容寻址的文件名。
+//! 输出格式按尺寸配置（LATTE_THUMBNAIL_FORMATS），格式是键的一部分，切换格式后不会命中旧格式的条目。
+
+use crate::db::{DatabasePool, MediaFileRepository};
+use crate::utils::{ThumbnailFormat, ThumbnailFormats};
 use bytes::Bytes;
 use moka::future::Cache;
-use std::path::PathBuf;
-use std::sync::Arc;
+use sha2::{Digest, Sha256};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, RwLock};
 use tokio::fs;
 
+/// Staging directory for atomic writes, emptied on startup
+const TMP_DIR: &str = "tmp";
+
+/// How long a file id -> content hash lookup is reused (a file rewritten in place gets a new hash)
+const CONTENT_HASH_TTL_SECONDS: u64 = 600;
+
+/// Where a cache entry lives: the id used for sharding and the file name
+struct EntryKey {
+    shard_id: String,
+    name: String,
+}
+
 /// Three-level cache service for thumbnails
 pub struct CacheService {
     // L1: Memory cache - using Bytes for efficient cloning
-    memory_cache: Arc<Cache<String, Bytes>>,
+    memory_cache: RwLock<Arc<Cache<String, Bytes>>>,
     // L2: Disk cache directory
     disk_cache_dir: PathBuf,
+    /// Flat-layout files may still exist in the cache root (until migrate_flat_layout finishes)
+    legacy_layout: AtomicBool,
+    /// Resolves content hashes; without it entries are keyed by file id
+    db: Option<DatabasePool>,
+    /// file id -> content hash
+    content_hashes: Cache<String, String>,
+    /// Output format per size; part of every key
+    formats: ThumbnailFormats,
 }
 
 impl CacheService {
@@ -22,34 +54,149 @@ impl CacheService {
         // Ensure cache directory exists
         fs::create_dir_all(cache_dir).await?;
 
-        let memory_cache = Arc::new(Cache::builder()
-            .max_capacity(max_capacity as u64)
-            .time_to_live(std::time::Duration::from_secs(ttl_seconds))
-            .build());
+        // 上次异常退出遗留的临时文件
+        let tmp_dir = cache_dir.join(TMP_DIR);
+        if fs::try_exists(&tmp_dir).await? {
+            fs::remove_dir_all(&tmp_dir).await?;
+        }
+        fs::create_dir_all(&tmp_dir).await?;
 
         Ok(Self {
-            memory_cache,
+            memory_cache: RwLock::new(memory_cache(max_capacity, ttl_seconds)),
             disk_cache_dir: cache_dir.clone(),
+            legacy_layout: AtomicBool::new(has_flat_entries(cache_dir).await?),
+            db: None,
+            content_hashes: Cache::builder()
+                .max_capacity(max_capacity as u64)
+                .time_to_live(std::time::Duration::from_secs(CONTENT_HASH_TTL_SECONDS))
+                .build(),
+            formats: ThumbnailFormats::default(),
         })
     }
 
+    /// L1 cache; replaced as a whole when its limits change
+    fn memory(&self) -> Arc<Cache<String, Bytes>> {
+        self.memory_cache.read().unwrap().clone()
+    }
+
+    /// Apply new memory cache limits (configuration reload). Moka caches cannot be resized,
+    /// so the memory cache starts empty; entries are reloaded from the disk cache on demand.
+    pub fn set_memory_limits(&self, max_capacity: usize, ttl_seconds: u64) {
+        *self.memory_cache.write().unwrap() = memory_cache(max_capacity, ttl_seconds);
+    }
+
+    /// Key entries by the content hash of the file (see file_metadata::content_hash)
+    pub fn with_content_hashes(mut self, db: DatabasePool) -> Self {
+        self.db = Some(db);
+        self
+    }
+
+    /// Key entries by the configured output format of their size (LATTE_THUMBNAIL_FORMATS)
+    pub fn with_thumbnail_formats(mut self, formats: ThumbnailFormats) -> Self {
+        self.formats = formats;
+        self
+    }
+
+    /// Content hash of a file, None if there is no database or the file is not hashed yet
+    async fn content_hash(&self, file_id: &str) -> Option<String> {
+        let db = self.db.as_ref()?;
+        if let Some(hash) = self.content_hashes.get(file_id).await {
+            return Some(hash);
+        }
+        // 未计算哈希的文件不缓存查询结果，扫描补上哈希后立即生效
+        let hash = MediaFileRepository::new(db).find_content_hash(file_id).await.ok()??;
+        self.content_hashes.insert(file_id.to_string(), hash.clone()).await;
+        Some(hash)
+    }
+
+    /// Whether entries of the file are addressed by `content_hash`, its current hash in the database.
+    /// A lookup from before the file was rewritten is dropped and read again.
+    pub async fn is_keyed_by(&self, file_id: &str, content_hash: &str) -> bool {
+        match self.content_hash(file_id).await {
+            Some(hash) if hash == content_hash => true,
+            Some(_) => {
+                self.content_hashes.invalidate(file_id).await;
+                self.content_hash(file_id).await.as_deref() == Some(content_hash)
+            }
+            None => false,
+        }
+    }
+
+    /// Content-addressed key when the file is hashed, id-based key otherwise.
+    /// "full" entries of browser-native files keep the source bytes; the content hash already
+    /// determines their format, so the configured one only tells them apart from transcoded output.
+    async fn entry_key(&self, file_id: &str, size: &str) -> EntryKey {
+        let format = self.formats.for_size(size);
+        match self.content_hash(file_id).await {
+            Some(hash) => EntryKey {
+                name: format!("{}_{}_{}", hash, size, format.name()),
+                shard_id: hash,
+            },
+            None => id_key(file_id, size, format),
+        }
+    }
+
+    /// Sharded disk path of a cache entry: `<cache_dir>/ab/cd/<name>`, sharded by `shard_id`
+    fn disk_path(&self, shard_id: &str, name: &str) -> PathBuf {
+        let (first, second) = shard_of(shard_id);
+        self.disk_cache_dir.join(first).join(second).join(name)
+    }
+
+    /// Existing disk file of a cache entry. Falls back to the id-based name (moved to the
+    /// content-addressed name on a hit) and, before migration, to the flat layout.
+    async fn find_on_disk(&self, file_id: &str, size: &str, key: &EntryKey) -> Option<PathBuf> {
+        let path = self.disk_path(&key.shard_id, &key.name);
+        if path.exists() {
+            return Some(path);
+        }
+
+        let by_id = id_key(file_id, size, self.formats.for_size(size));
+        if by_id.name != key.name {
+            let old = self.disk_path(&by_id.shard_id, &by_id.name);
+            if old.exists() {
+                return Some(self.promote(old, path).await);
+            }
+        }
+
+        if self.legacy_layout.load(Ordering::Relaxed) {
+            let legacy = self.disk_cache_dir.join(&by_id.name);
+            if legacy.exists() {
+                return Some(legacy);
+            }
+        }
+        None
+    }
+
+    /// Rename an id-keyed entry to its content-addressed path; returns where the entry is now
+    async fn promote(&self, old: PathBuf, new: PathBuf) -> PathBuf {
+        if let Some(parent) = new.parent() {
+            if fs::create_dir_all(parent).await.is_err() {
+                return old;
+            }
+        }
+        match fs::rename(&old, &new).await {
+            Ok(()) => new,
+            Err(_) => old,
+        }
+    }
+
     /// Get thumbnail from cache
     /// Returns Bytes for efficient cloning in downstream operations
     pub async fn get_thumbnail(&self, file_id: &str, size: &str) -> Option<Bytes> {
-        let cache_key = format!("{}_{}", file_id, size);
+        let key = self.entry_key(file_id, size).await;
 
         // 1. Check memory cache - Bytes supports cheap cloning
-        if let Some(data) = self.memory_cache.get(&cache_key).await {
+        if let Some(data) = self.memory().get(&key.name).await {
             return Some(data);
         }
 
         // 2. Check disk cache
-        let disk_path = self.disk_cache_dir.join(&cache_key);
+        let disk_path = self.find_on_disk(file_id, size, &key).await?;
         if let Ok(data) = fs::read(&disk_path).await {
             // Convert to Bytes - cheap clone for memory cache insertion
             let bytes = Bytes::from(data);
             // Clone for memory cache (Bytes clone is O(1))
-            self.memory_cache.insert(cache_key.clone(), bytes.clone()).await;
+            self.memory().insert(key.name, bytes.clone()).await;
             return Some(bytes);
         }
 
@@ -58,43 +205,298 @@ impl CacheService {
 
     /// Get thumbnail disk cache path (for streaming)
     /// Returns None if not in disk cache
-    pub fn get_thumbnail_disk_path(&self, file_id: &str, size: &str) -> Option<PathBuf> {
-        let cache_key = format!("{}_{}", file_id, size);
-        let disk_path = self.disk_cache_dir.join(&cache_key);
-        if disk_path.exists() {
-            Some(disk_path)
-        } else {
-            None
+    pub async fn get_thumbnail_disk_path(&self, file_id: &str, size: &str) -> Option<PathBuf> {
+        let key = self.entry_key(file_id, size).await;
+        self.find_on_disk(file_id, size, &key).await
+    }
+
+    /// Which of `sizes` are already cached for a file (memory or disk), in the given order
+    pub async fn cached_sizes(&self, file_id: &str, sizes: &[&'static str]) -> Vec<&'static str> {
+        let mut cached = Vec::with_capacity(sizes.len());
+        for &size in sizes {
+            let key = self.entry_key(file_id, size).await;
+            if self.memory().contains_key(&key.name) || self.find_on_disk(file_id, size, &key).await.is_some() {
+                cached.push(size);
+            }
         }
+        cached
+    }
+
+    /// Path of a disk cache file relative to the cache directory, '/'-separated
+    /// (for X-Accel-Redirect, where nginx maps an internal location onto the cache directory)
+    pub fn relative_url_path(&self, path: &Path) -> Option<String> {
+        let relative = path.strip_prefix(&self.disk_cache_dir).ok()?;
+        let parts: Vec<String> = relative
+            .components()
+            .map(|c| c.as_os_str().to_string_lossy().into_owned())
+            .collect();
+        Some(parts.join("/"))
     }
 
     /// Alternative put method that accepts Bytes directly
     /// Avoids reallocation if caller already has Bytes
     pub async fn put_thumbnail_bytes(&self, file_id: &str, size: &str, data: Bytes) -> std::io::Result<()> {
-        let cache_key = format!("{}_{}", file_id, size);
+        let key = self.entry_key(file_id, size).await;
 
         // Store in memory cache (Bytes is efficient)
-        self.memory_cache.insert(cache_key.clone(), data.clone()).await;
+        self.memory().insert(key.name.clone(), data.clone()).await;
 
-        // Store in disk cache
-        let disk_path = self.disk_cache_dir.join(&cache_key);
-        fs::write(&disk_path, &data).await?;
+        // Store in disk cache: write to tmp/, then rename into place
+        let disk_path = self.disk_path(&key.shard_id, &key.name);
+        if let Some(parent) = disk_path.parent() {
+            fs::create_dir_all(parent).await?;
+        }
+        let tmp_path = self
+            .disk_cache_dir
+            .join(TMP_DIR)
+            .join(format!("{}.{}", key.name, uuid::Uuid::new_v4()));
+        fs::write(&tmp_path, &data).await?;
+        if let Err(e) = fs::rename(&tmp_path, &disk_path).await {
+            let _ = fs::remove_file(&tmp_path).await;
+            return Err(e);
+        }
 
         Ok(())
     }
 
+    /// Drop every cached size of a file from memory and disk, e.g. after its content changed.
+    /// Entries are found by name prefix ("<id>_" and "<content hash>_"), so resized and "full"
+    /// entries go too; identical files sharing the content hash regenerate on their next request.
+    /// Returns the number of disk files removed.
+    pub async fn invalidate(&self, file_id: &str) -> std::io::Result<u64> {
+        let mut entries = vec![(file_id.to_string(), format!("{}_", file_id))];
+        if let Some(hash) = self.content_hash(file_id).await {
+            entries.push((hash.clone(), format!("{}_", hash)));
+        }
+        // 重新扫描后哈希可能已变化，下次请求重新查询
+        self.content_hashes.invalidate(file_id).await;
+
+        let memory = self.memory();
+        let stale: Vec<Arc<String>> = memory
+            .iter()
+            .map(|(key, _)| key)
+            .filter(|key| entries.iter().any(|(_, prefix)| key.starts_with(prefix.as_str())))
+            .collect();
+        for key in stale {
+            memory.invalidate(key.as_str()).await;
+        }
+
+        let mut removed = 0u64;
+        let mut dirs: Vec<(PathBuf, &str)> = entries
+            .iter()
+            .filter_map(|(shard_id, prefix)| {
+                let dir = self.disk_path(shard_id, prefix).parent()?.to_path_buf();
+                Some((dir, prefix.as_str()))
+            })
+            .collect();
+        if self.legacy_layout.load(Ordering::Relaxed) {
+            dirs.push((self.disk_cache_dir.clone(), entries[0].1.as_str()));
+        }
+        for (dir, prefix) in dirs {
+            let mut read_dir = match fs::read_dir(&dir).await {
+                Ok(read_dir) => read_dir,
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => continue,
+                Err(e) => return Err(e),
+            };
+            while let Some(entry) = read_dir.next_entry().await? {
+                if entry.file_name().to_string_lossy().starts_with(prefix) && entry.file_type().await?.is_file() {
+                    fs::remove_file(entry.path()).await?;
+                    removed += 1;
+                }
+            }
+        }
+        Ok(removed)
+    }
+
+    /// Move entries of the old flat layout into their shard directories.
+    /// Runs in the background at startup; returns the number of files moved.
+    pub async fn migrate_flat_layout(&self) -> std::io::Result<u64> {
+        if !self.legacy_layout.load(Ordering::Relaxed) {
+            return Ok(0);
+        }
+
+        let mut moved = 0u64;
+        let mut entries = fs::read_dir(&self.disk_cache_dir).await?;
+        while let Some(entry) = entries.next_entry().await? {
+            if !entry.file_type().await?.is_file() {
+                continue;
+            }
+            let name = entry.file_name();
+            let Some(cache_key) = name.to_str() else {
+                continue;
+            };
+            let Some(file_id) = legacy_file_id(cache_key) else {
+                continue;
+            };
+
+            let target = self.disk_path(file_id, cache_key);
+            if let Some(parent) = target.parent() {
+                fs::create_dir_all(parent).await?;
+            }
+            // 目标已存在说明迁移期间已重新生成，旧文件直接丢弃
+            if fs::try_exists(&target).await? {
+                fs::remove_file(entry.path()).await?;
+            } else {
+                fs::rename(entry.path(), &target).await?;
+                moved += 1;
+            }
+        }
+
+        self.legacy_layout.store(false, Ordering::Relaxed);
+        if moved > 0 {
+            tracing::info!("Migrated {} thumbnail cache files to the sharded layout", moved);
+        }
+        Ok(moved)
+    }
+
     /// Get cache size in MB
     pub async fn get_cache_size_mb(&self) -> std::io::Result<f64> {
+        Ok(self.get_cache_size_bytes().await? as f64 / (1024.0 * 1024.0))
+    }
+
+    /// Total size of the disk cache in bytes
+    /// (shard directories plus not yet migrated flat files; backups and other subdirectories excluded)
+    pub async fn get_cache_size_bytes(&self) -> std::io::Result<u64> {
         let mut total_size = 0u64;
 
-        let mut entries = tokio::fs::read_dir(&self.disk_cache_dir).await?;
-        while let Some(entry) = entries.next_entry().await? {
-            if entry.file_type().await?.is_file() {
-                total_size += entry.metadata().await?.len();
+        let mut pending = vec![(self.disk_cache_dir.clone(), 0usize)];
+        while let Some((dir, depth)) = pending.pop() {
+            let mut entries = fs::read_dir(&dir).await?;
+            while let Some(entry) = entries.next_entry().await? {
+                let file_type = entry.file_type().await?;
+                if file_type.is_file() {
+                    total_size += entry.metadata().await?.len();
+                } else if file_type.is_dir() && depth < 2 && is_shard_name(&entry.file_name().to_string_lossy()) {
+                    pending.push((entry.path(), depth + 1));
+                }
             }
         }
 
-        Ok(total_size as f64 / (1024.0 * 1024.0))
+        Ok(total_size)
+    }
+
+}
+
+fn memory_cache(max_capacity: usize, ttl_seconds: u64) -> Arc<Cache<String, Bytes>> {
+    Arc::new(
+        Cache::builder()
+            .max_capacity(max_capacity as u64)
+            .time_to_live(std::time::Duration::from_secs(ttl_seconds))
+            .build(),
+    )
+}
+
+/// Entry key of a file that has no content hash. JPEG entries keep the name from before
+/// formats were configurable (`<id>_<size>`), other formats get a suffix.
+fn id_key(file_id: &str, size: &str, format: ThumbnailFormat) -> EntryKey {
+    let name = match format {
+        ThumbnailFormat::Jpeg => format!("{}_{}", file_id, size),
+        _ => format!("{}_{}_{}", file_id, size, format.name()),
+    };
+    EntryKey {
+        shard_id: file_id.to_string(),
+        name,
+    }
+}
+
+/// Two-level shard directories of a file id or content hash: its first four characters for UUIDs and hashes,
+/// a hash prefix for ids that are too short or not path-safe
+fn shard_of(file_id: &str) -> (String, String) {
+    let prefix: String = file_id.chars().take(4).collect();
+    let prefix = if prefix.len() == 4 && prefix.chars().all(|c| c.is_ascii_alphanumeric()) {
+        prefix.to_ascii_lowercase()
+    } else {
+        let digest = Sha256::digest(file_id.as_bytes());
+        format!("{:02x}{:02x}", digest[0], digest[1])
+    };
+    (prefix[..2].to_string(), prefix[2..].to_string())
+}
+
+fn is_shard_name(name: &str) -> bool {
+    name.len() == 2 && name.chars().all(|c| c.is_ascii_alphanumeric())
+}
+
+/// File id of a flat-layout cache file name ("<id>_<size>"); ids never contain '_'
+fn legacy_file_id(name: &str) -> Option<&str> {
+    let (file_id, size) = name.split_once('_')?;
+    (!file_id.is_empty() && !size.is_empty() && !name.starts_with('.')).then_some(file_id)
+}
+
+/// Whether the cache root still holds flat-layout thumbnail files
+async fn has_flat_entries(cache_dir: &Path) -> std::io::Result<bool> {
+    let mut entries = fs::read_dir(cache_dir).await?;
+    while let Some(entry) = entries.next_entry().await? {
+        if entry.file_type().await?.is_file() && entry.file_name().to_str().and_then(legacy_file_id).is_some() {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_shard_of() {
+        assert_eq!(
+            shard_of("3F2504E0-4F89-11D3-9A0C-0305E82C3301"),
+            ("3f".to_string(), "25".to_string())
+        );
+        // 过短或含路径字符的 id 改用哈希前缀
+        let (first, second) = shard_of("a/");
+        assert!(is_shard_name(&first) && is_shard_name(&second));
+        assert_eq!(shard_of("a/"), (first, second));
+    }
+
+    #[test]
+    fn test_legacy_file_id() {
+        assert_eq!(legacy_file_id("3f2504e0-4f89_small"), Some("3f2504e0-4f89"));
+        assert_eq!(legacy_file_id("abc_medium_square"), Some("abc"));
+        assert_eq!(legacy_file_id("album.db"), None);
+        assert_eq!(legacy_file_id("_small"), None);
+    }
+
+    #[tokio::test]
+    async fn test_invalidate_removes_all_sizes_of_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = CacheService::new(&dir.path().to_path_buf(), 100, 60).await.unwrap();
+        for size in ["small", "medium", "1080w"] {
+            cache.put_thumbnail_bytes("abcd1234", size, Bytes::from_static(b"old")).await.unwrap();
+        }
+        cache.put_thumbnail_bytes("efgh5678", "small", Bytes::from_static(b"other")).await.unwrap();
+
+        assert_eq!(cache.invalidate("abcd1234").await.unwrap(), 3);
+        assert!(cache.get_thumbnail("abcd1234", "small").await.is_none());
+        assert!(cache.get_thumbnail("abcd1234", "1080w").await.is_none());
+        assert!(cache.get_thumbnail("efgh5678", "small").await.is_some());
     }
 
+    #[tokio::test]
+    async fn test_format_is_part_of_key() {
+        let dir = tempfile::tempdir().unwrap();
+        let jpeg = CacheService::new(&dir.path().to_path_buf(), 100, 60).await.unwrap();
+        jpeg.put_thumbnail_bytes("abcd1234", "small", Bytes::from_static(b"jpeg")).await.unwrap();
+
+        let formats = ThumbnailFormats::parse(&["small=webp".to_string()]).unwrap();
+        let webp = CacheService::new(&dir.path().to_path_buf(), 100, 60).await.unwrap().with_thumbnail_formats(formats);
+        assert!(webp.get_thumbnail("abcd1234", "small").await.is_none());
+        webp.put_thumbnail_bytes("abcd1234", "small", Bytes::from_static(b"webp")).await.unwrap();
+
+        let path = webp.get_thumbnail_disk_path("abcd1234", "small").await.unwrap();
+        assert!(path.ends_with("ab/cd/abcd1234_small_webp"));
+        assert_eq!(jpeg.get_thumbnail("abcd1234", "small").await.unwrap(), Bytes::from_static(b"jpeg"));
+    }
+
+    #[tokio::test]
+    async fn test_memory_limits_change_keeps_disk_entries() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = CacheService::new(&dir.path().to_path_buf(), 100, 60).await.unwrap();
+        cache.put_thumbnail_bytes("abcd1234", "small", Bytes::from_static(b"thumb")).await.unwrap();
+
+        cache.set_memory_limits(10, 5);
+        assert!(cache.memory().get("abcd1234_small").await.is_none());
+        assert_eq!(cache.get_thumbnail("abcd1234", "small").await.unwrap(), Bytes::from_static(b"thumb"));
+        assert!(cache.memory().get("abcd1234_small").await.is_some());
+    }
 }
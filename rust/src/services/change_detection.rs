@@ -0,0 +1,104 @@
+//! 扫描时判断已入库文件是否变化
+//! 默认只比较修改时间（秒）。部分同步工具（rsync -t、某些网盘客户端）在内容变化后仍保留原修改时间，
+//! 这类修改会被跳过。LATTE_SCAN_CHANGE_DETECTION 可额外比较文件大小（只需 stat，几乎无开销），
+//! 或再比较内容指纹（file_metadata::content_hash，读取文件首尾各 64 KiB），在准确性和扫描速度之间取舍。
+//! 每一级都包含前一级的检查；修改时间不同的文件直接视为已变化，不再读取内容。
+
+use std::fs::Metadata;
+
+/// How a scan decides that a known file changed (LATTE_SCAN_CHANGE_DETECTION)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ChangeDetection {
+    /// Compare modify times only
+    ModifyTime,
+    /// Also compare file sizes
+    Size,
+    /// Also compare content hashes of the first and last bytes
+    Hash,
+}
+
+impl ChangeDetection {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "mtime" | "" => Some(Self::ModifyTime),
+            "size" => Some(Self::Size),
+            "hash" => Some(Self::Hash),
+            _ => None,
+        }
+    }
+}
+
+/// Outcome of the stat-based checks for a known file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Verdict {
+    Unchanged,
+    Changed,
+    /// Modify time and size match; the content hash must be compared
+    CompareHash,
+}
+
+/// Compare a file on disk with its database row using the checks that need no file read.
+/// `stored_size` is None for rows written before sizes were recorded; such rows are
+/// only compared by modify time.
+pub fn compare_stat(
+    mode: ChangeDetection,
+    metadata: &Metadata,
+    stored_modify_secs: u64,
+    stored_size: Option<i64>,
+) -> Verdict {
+    let modify_secs = match metadata.modified() {
+        Ok(modified) => modified
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs(),
+        Err(_) => return Verdict::Changed,
+    };
+    if modify_secs != stored_modify_secs {
+        return Verdict::Changed;
+    }
+    if mode >= ChangeDetection::Size && stored_size.is_some_and(|size| size != metadata.len() as i64) {
+        return Verdict::Changed;
+    }
+    if mode == ChangeDetection::Hash {
+        return Verdict::CompareHash;
+    }
+    Verdict::Unchanged
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(ChangeDetection::parse("mtime"), Some(ChangeDetection::ModifyTime));
+        assert_eq!(ChangeDetection::parse(" Size "), Some(ChangeDetection::Size));
+        assert_eq!(ChangeDetection::parse("hash"), Some(ChangeDetection::Hash));
+        assert_eq!(ChangeDetection::parse("sha256"), None);
+    }
+
+    #[test]
+    fn test_compare_stat() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("a.jpg");
+        std::fs::File::create(&path).unwrap().write_all(b"0123456789").unwrap();
+        let metadata = path.metadata().unwrap();
+        let secs = metadata
+            .modified()
+            .unwrap()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+
+        use ChangeDetection::*;
+        assert_eq!(compare_stat(ModifyTime, &metadata, secs + 1, Some(10)), Verdict::Changed);
+        // 修改时间相同但大小不同：只有 size 及以上级别能发现
+        assert_eq!(compare_stat(ModifyTime, &metadata, secs, Some(20)), Verdict::Unchanged);
+        assert_eq!(compare_stat(Size, &metadata, secs, Some(20)), Verdict::Changed);
+        assert_eq!(compare_stat(Hash, &metadata, secs, Some(20)), Verdict::Changed);
+        assert_eq!(compare_stat(Size, &metadata, secs, Some(10)), Verdict::Unchanged);
+        assert_eq!(compare_stat(Size, &metadata, secs, None), Verdict::Unchanged);
+        assert_eq!(compare_stat(Hash, &metadata, secs, Some(10)), Verdict::CompareHash);
+    }
+}
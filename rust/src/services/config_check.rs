@@ -0,0 +1,258 @@
+//! 配置校验与启动诊断
+//! 各配置项在解析时只检查格式，路径不存在、端口被占用、cron 表达式写错等问题要到运行时才暴露，
+//! 且往往只是一条容易被忽略的日志（例如定时扫描被静默禁用）。
+//! 启动时统一校验一次并输出生效配置摘要与警告；设置页通过 GET /api/admin/config/validate 获取同一份报告。
+
+use crate::config::Config;
+use crate::services::library_access::probe_writable;
+use crate::services::scheduler::parse_cron;
+use serde::Serialize;
+use std::path::Path;
+use tracing::{error, info, warn};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    /// The setting cannot work as configured
+    Error,
+    /// Works, but probably not as intended
+    Warning,
+}
+
+/// One problem found in the configuration
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConfigIssue {
+    /// Environment variable of the setting
+    pub key: String,
+    pub severity: Severity,
+    pub message: String,
+}
+
+/// Effective value of a setting, for the startup summary
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConfigEntry {
+    pub key: String,
+    pub value: String,
+}
+
+/// Result of validating the whole configuration
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConfigReport {
+    /// No issue of severity Error
+    pub valid: bool,
+    pub summary: Vec<ConfigEntry>,
+    pub issues: Vec<ConfigIssue>,
+}
+
+#[derive(Default)]
+struct Issues(Vec<ConfigIssue>);
+
+impl Issues {
+    fn error(&mut self, key: &str, message: String) {
+        self.0.push(ConfigIssue { key: key.to_string(), severity: Severity::Error, message });
+    }
+
+    fn warning(&mut self, key: &str, message: String) {
+        self.0.push(ConfigIssue { key: key.to_string(), severity: Severity::Warning, message });
+    }
+}
+
+/// Validate `config`. Touches the file system (and binds the port when `check_port` is set),
+/// so call it off the async runtime. The running server skips the port check: it holds the port.
+pub fn validate(config: &Config, check_port: bool) -> ConfigReport {
+    let mut issues = Issues::default();
+
+    match std::fs::metadata(&config.base_path) {
+        Ok(metadata) if metadata.is_dir() => {}
+        Ok(_) => issues.error("LATTE_BASE_PATH", format!("{} is not a directory", config.base_path.display())),
+        Err(e) => issues.error("LATTE_BASE_PATH", format!("{} is not accessible: {}", config.base_path.display(), e)),
+    }
+    for root in &config.extra_library_roots {
+        if !root.is_dir() {
+            issues.warning("LATTE_EXTRA_LIBRARY_ROOTS", format!("{} is not a directory", root.display()));
+        }
+    }
+
+    if let Some(parent) = config.db_path.parent() {
+        check_creatable(&mut issues, "LATTE_DB_PATH", parent);
+    }
+    if let Some(replica) = &config.db_read_replica {
+        if !replica.is_file() {
+            issues.error("LATTE_DB_READ_REPLICA", format!("{} is not a file", replica.display()));
+        }
+    }
+    check_creatable(&mut issues, "LATTE_CACHE_DIR", &config.cache_dir);
+    if let Some(dir) = &config.backup_dir {
+        check_creatable(&mut issues, "LATTE_BACKUP_DIR", dir);
+    }
+    if let Some(dir) = &config.export_dir {
+        check_creatable(&mut issues, "LATTE_EXPORT_DIR", dir);
+    }
+    if !config.static_dir.is_dir() {
+        issues.warning(
+            "LATTE_STATIC_DIR",
+            format!("{} does not exist; the web frontend is not served", config.static_dir.display()),
+        );
+    }
+
+    if check_port {
+        if let Err(e) = std::net::TcpListener::bind((config.host.as_str(), config.port)) {
+            issues.error("LATTE_PORT", format!("Cannot bind {}:{}: {}", config.host, config.port, e));
+        }
+    }
+
+    if !(config.thumbnail_quality > 0.0 && config.thumbnail_quality <= 1.0) {
+        issues.error("LATTE_THUMBNAIL_QUALITY", format!("{} is outside (0, 1]", config.thumbnail_quality));
+    }
+    if config.thumbnail_small > config.thumbnail_medium || config.thumbnail_medium > config.thumbnail_large {
+        issues.warning(
+            "LATTE_THUMBNAIL_MEDIUM",
+            format!(
+                "Thumbnail sizes are not ascending (small {}, medium {}, large {})",
+                config.thumbnail_small, config.thumbnail_medium, config.thumbnail_large
+            ),
+        );
+    }
+
+    if let Err(e) = parse_cron(&config.scan_cron) {
+        issues.error("LATTE_SCAN_CRON", format!("Invalid cron expression {:?}: {}; scheduled scans are disabled", config.scan_cron, e));
+    }
+
+    let issues = issues.0;
+    ConfigReport {
+        valid: !issues.iter().any(|issue| issue.severity == Severity::Error),
+        summary: summary(config),
+        issues,
+    }
+}
+
+/// A directory that exists, or whose nearest existing ancestor is a writable directory
+fn check_creatable(issues: &mut Issues, key: &str, dir: &Path) {
+    let Some(existing) = dir.ancestors().find(|p| p.exists()) else {
+        issues.error(key, format!("{} cannot be created", dir.display()));
+        return;
+    };
+    match std::fs::metadata(existing) {
+        Ok(metadata) if !metadata.is_dir() => {
+            issues.error(key, format!("{} is not a directory", existing.display()));
+        }
+        // 只读位反映不了属主、ACL 和只读挂载，实际创建一个探测文件
+        Ok(_) => {
+            if let Err(e) = probe_writable(existing) {
+                issues.error(key, format!("{} is not writable: {}", existing.display(), e));
+            }
+        }
+        Err(e) => issues.error(key, format!("{} is not accessible: {}", existing.display(), e)),
+    }
+}
+
+/// Settings worth seeing at a glance; credentials are left out
+fn summary(config: &Config) -> Vec<ConfigEntry> {
+    let entry = |key: &str, value: String| ConfigEntry { key: key.to_string(), value };
+    let path = |p: &Path| p.display().to_string();
+    vec![
+        entry("LATTE_HOST", config.host.clone()),
+        entry("LATTE_PORT", config.port.to_string()),
+        entry("LATTE_BASE_PATH", path(&config.base_path)),
+        entry("LATTE_DB_PATH", path(&config.db_path)),
+        entry(
+            "LATTE_DB_READ_REPLICA",
+            config.db_read_replica.as_deref().map_or("none".to_string(), path),
+        ),
+        entry("LATTE_CACHE_DIR", path(&config.cache_dir)),
+        entry("LATTE_STATIC_DIR", path(&config.static_dir)),
+        entry(
+            "LATTE_THUMBNAIL_SMALL/MEDIUM/LARGE",
+            format!("{}/{}/{}", config.thumbnail_small, config.thumbnail_medium, config.thumbnail_large),
+        ),
+        entry("LATTE_THUMBNAIL_QUALITY", config.thumbnail_quality.to_string()),
+        entry("LATTE_SCAN_CRON", config.scan_cron.clone()),
+        entry(
+            "LATTE_SCAN_WORKER_COUNT",
+            config.scan_worker_count.map_or("auto".to_string(), |n| n.to_string()),
+        ),
+        entry("LATTE_TRANSCODING_THREADS", config.transcoding_threads.to_string()),
+        entry("LATTE_CACHE_MAX_CAPACITY", config.cache_max_capacity.to_string()),
+        entry("LATTE_READ_ONLY", config.read_only.to_string()),
+    ]
+}
+
+/// Log the summary and every issue
+pub fn log_report(report: &ConfigReport) {
+    for entry in &report.summary {
+        info!("Config {} = {}", entry.key, entry.value);
+    }
+    for issue in &report.issues {
+        match issue.severity {
+            Severity::Error => error!("Config {}: {}", issue.key, issue.message),
+            Severity::Warning => warn!("Config {}: {}", issue.key, issue.message),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config_in(dir: &Path) -> Config {
+        Config {
+            base_path: dir.to_path_buf(),
+            db_path: dir.join("data/album.db"),
+            cache_dir: dir.join("cache"),
+            static_dir: dir.to_path_buf(),
+            ..Config::default()
+        }
+    }
+
+    #[test]
+    fn test_valid_config() {
+        let dir = tempfile::tempdir().unwrap();
+        let report = validate(&config_in(dir.path()), false);
+        assert!(report.valid, "{:?}", report.issues);
+        assert!(report.issues.is_empty());
+        assert!(report.summary.iter().any(|e| e.key == "LATTE_SCAN_CRON"));
+    }
+
+    #[test]
+    fn test_reports_each_problem() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("file");
+        std::fs::write(&file, b"").unwrap();
+
+        let config = Config {
+            base_path: dir.path().join("missing"),
+            cache_dir: file.join("cache"),
+            scan_cron: "every night".to_string(),
+            thumbnail_small: 1000,
+            db_read_replica: Some(dir.path().join("replica.db")),
+            ..config_in(dir.path())
+        };
+        let report = validate(&config, false);
+        assert!(!report.valid);
+
+        let severity = |key: &str| report.issues.iter().find(|i| i.key == key).map(|i| i.severity);
+        assert_eq!(severity("LATTE_BASE_PATH"), Some(Severity::Error));
+        assert_eq!(severity("LATTE_CACHE_DIR"), Some(Severity::Error));
+        assert_eq!(severity("LATTE_SCAN_CRON"), Some(Severity::Error));
+        assert_eq!(severity("LATTE_THUMBNAIL_MEDIUM"), Some(Severity::Warning));
+        assert_eq!(severity("LATTE_DB_READ_REPLICA"), Some(Severity::Error));
+        assert_eq!(severity("LATTE_DB_PATH"), None);
+    }
+
+    #[test]
+    fn test_port_in_use() {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        let config = Config {
+            host: "127.0.0.1".to_string(),
+            port: listener.local_addr().unwrap().port(),
+            ..config_in(dir.path())
+        };
+        let report = validate(&config, true);
+        assert!(report.issues.iter().any(|i| i.key == "LATTE_PORT" && i.severity == Severity::Error));
+        assert!(validate(&config, false).valid);
+    }
+}
@@ -0,0 +1,323 @@
+//! 配置热重载
+//! 收到 SIGHUP 或 POST /api/admin/config/reload 时重新读取环境变量与配置文件，
+//! 把可在运行中修改的设置（缩略图质量、内存缓存上限、磁盘空间阈值、进度广播间隔、扫描 cron）
+//! 应用到对应的服务上，无需重启。监听地址、数据库路径等需要重启的设置只报告变化、不生效。
+//! 重新加载后通过 WebSocket 推送一条 `type: "config"` 消息，设置页据此刷新。
+//! AppState::config 始终是启动时的配置；可重载的值须从各服务读取（如 FileService::thumbnail_quality）。
+
+use crate::config::{Config, ConfigError};
+use crate::services::scheduler::parse_cron;
+use crate::services::{CacheService, FileService, Scheduler};
+use crate::websocket::{ScanStateManager, ThumbnailProgress, WarningBroadcaster};
+use serde::Serialize;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+use tracing::{info, warn};
+
+/// WebSocket message type of configuration events
+pub const CONFIG_EVENT: &str = "config";
+
+/// Event code sent after a successful reload
+pub const CONFIG_RELOADED: &str = "config_reloaded";
+
+/// Warning code sent when a reload fails
+pub const CONFIG_RELOAD_FAILED_WARNING: &str = "config_reload_failed";
+
+/// What a reload changed
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReloadOutcome {
+    /// Settings whose new value is now in effect
+    pub applied: Vec<&'static str>,
+    /// Changed settings that only take effect after a restart
+    pub restart_required: Vec<&'static str>,
+}
+
+/// Re-reads the configuration and applies the settings that are safe to change at runtime
+pub struct ConfigReloader {
+    /// Configuration the server was started with
+    startup: Config,
+    /// Last applied configuration; reloads are serialized by this lock
+    current: Mutex<Config>,
+    file_service: Arc<FileService>,
+    cache_service: Arc<CacheService>,
+    scan_state: Arc<ScanStateManager>,
+    thumbnail_progress: Arc<ThumbnailProgress>,
+    scheduler: Arc<Scheduler>,
+    warnings: Arc<WarningBroadcaster>,
+}
+
+impl ConfigReloader {
+    pub fn new(
+        config: Config,
+        file_service: Arc<FileService>,
+        cache_service: Arc<CacheService>,
+        scan_state: Arc<ScanStateManager>,
+        thumbnail_progress: Arc<ThumbnailProgress>,
+        scheduler: Arc<Scheduler>,
+        warnings: Arc<WarningBroadcaster>,
+    ) -> Self {
+        Self {
+            startup: config.clone(),
+            current: Mutex::new(config),
+            file_service,
+            cache_service,
+            scan_state,
+            thumbnail_progress,
+            scheduler,
+            warnings,
+        }
+    }
+
+    /// Last successfully applied configuration (the startup configuration until a reload)
+    pub async fn current(&self) -> Config {
+        self.current.lock().await.clone()
+    }
+
+    /// Read the configuration again and apply it: the `.env` file and the LATTE_CONFIG file are
+    /// read again (see Config::from_env); the process environment itself cannot change at runtime.
+    /// Failures leave the running configuration unchanged and are pushed as a warning.
+    pub async fn reload(&self) -> Result<ReloadOutcome, ConfigError> {
+        let result = match Config::from_env() {
+            Ok(config) => self.apply(config).await,
+            Err(e) => Err(e),
+        };
+        if let Err(e) = &result {
+            warn!("Configuration reload failed: {}", e);
+            self.warnings.warn(CONFIG_RELOAD_FAILED_WARNING, e.to_string());
+        }
+        result
+    }
+
+    /// Apply the reloadable settings of `config`. Checked before anything is applied, so an
+    /// invalid value rejects the whole reload.
+    pub async fn apply(&self, config: Config) -> Result<ReloadOutcome, ConfigError> {
+        if parse_cron(&config.scan_cron).is_err() {
+            return Err(ConfigError::InvalidValue("LATTE_SCAN_CRON".to_string(), config.scan_cron));
+        }
+
+        let mut current = self.current.lock().await;
+        let mut applied = Vec::new();
+
+        if config.thumbnail_quality != current.thumbnail_quality {
+            self.file_service.set_thumbnail_quality(config.thumbnail_quality);
+            applied.push("LATTE_THUMBNAIL_QUALITY");
+        }
+        if config.cache_max_capacity != current.cache_max_capacity || config.cache_ttl_seconds != current.cache_ttl_seconds {
+            self.cache_service.set_memory_limits(config.cache_max_capacity, config.cache_ttl_seconds);
+            if config.cache_max_capacity != current.cache_max_capacity {
+                applied.push("LATTE_CACHE_MAX_CAPACITY");
+            }
+            if config.cache_ttl_seconds != current.cache_ttl_seconds {
+                applied.push("LATTE_CACHE_TTL_SECONDS");
+            }
+        }
+        if config.cache_min_free_mb != current.cache_min_free_mb {
+            if let Some(guard) = self.file_service.disk_guard() {
+                guard.set_min_free_mb(config.cache_min_free_mb);
+            }
+            applied.push("LATTE_CACHE_MIN_FREE_MB");
+        }
+        if config.ws_progress_broadcast_interval != current.ws_progress_broadcast_interval {
+            self.scan_state.set_broadcast_interval(config.ws_progress_broadcast_interval);
+            self.thumbnail_progress.set_broadcast_interval(config.ws_progress_broadcast_interval);
+            applied.push("LATTE_WS_PROGRESS_INTERVAL");
+        }
+        if config.scan_cron != current.scan_cron {
+            // 表达式已在上面校验过
+            if let Err(e) = self.scheduler.reschedule(&config.scan_cron).await {
+                warn!("Failed to reschedule scans: {}", e);
+            }
+            applied.push("LATTE_SCAN_CRON");
+        }
+
+        let outcome = ReloadOutcome {
+            applied,
+            restart_required: restart_required(&self.startup, &config),
+        };
+        *current = config;
+        drop(current);
+
+        info!(
+            "Configuration reloaded; applied: [{}], restart required: [{}]",
+            outcome.applied.join(", "),
+            outcome.restart_required.join(", ")
+        );
+        let mut message = format!("Configuration reloaded, applied: {}", list_or_none(&outcome.applied));
+        if !outcome.restart_required.is_empty() {
+            message.push_str(&format!("; restart required for: {}", outcome.restart_required.join(", ")));
+        }
+        self.warnings.notify(CONFIG_EVENT, CONFIG_RELOADED, message);
+        Ok(outcome)
+    }
+
+    /// Reload on every SIGHUP until the process exits
+    #[cfg(unix)]
+    pub async fn reload_on_sighup(self: Arc<Self>) {
+        use tokio::signal::unix::{signal, SignalKind};
+
+        let mut hangups = match signal(SignalKind::hangup()) {
+            Ok(hangups) => hangups,
+            Err(e) => {
+                warn!("Failed to listen for SIGHUP, configuration reload only via the API: {}", e);
+                return;
+            }
+        };
+        while hangups.recv().await.is_some() {
+            info!("SIGHUP received, reloading configuration");
+            // 失败已记录日志并推送警告
+            let _ = self.reload().await;
+        }
+    }
+}
+
+/// Settings `apply` changes on the running services; every other change needs a restart
+const RELOADABLE: [&str; 6] = [
+    "LATTE_THUMBNAIL_QUALITY",
+    "LATTE_CACHE_MAX_CAPACITY",
+    "LATTE_CACHE_TTL_SECONDS",
+    "LATTE_CACHE_MIN_FREE_MB",
+    "LATTE_WS_PROGRESS_INTERVAL",
+    "LATTE_SCAN_CRON",
+];
+
+/// Changed settings that only take effect after a restart: everything that differs from
+/// the startup configuration and is not reloadable
+fn restart_required(startup: &Config, config: &Config) -> Vec<&'static str> {
+    changed_settings(startup, config)
+        .into_iter()
+        .filter(|key| !RELOADABLE.contains(key))
+        .collect()
+}
+
+/// Environment variables of every setting that differs between `a` and `b`
+fn changed_settings(a: &Config, b: &Config) -> Vec<&'static str> {
+    [
+        ("LATTE_HOST", a.host != b.host),
+        ("LATTE_PORT", a.port != b.port),
+        ("LATTE_BASE_PATH", a.base_path != b.base_path),
+        ("LATTE_EXTRA_LIBRARY_ROOTS", a.extra_library_roots != b.extra_library_roots),
+        ("LATTE_PATH_PREFIX_MAP", a.path_prefix_map != b.path_prefix_map),
+        ("LATTE_DB_PATH", a.db_path != b.db_path),
+        ("LATTE_DB_READ_REPLICA", a.db_read_replica != b.db_read_replica),
+        ("LATTE_CACHE_DIR", a.cache_dir != b.cache_dir),
+        ("LATTE_STATIC_DIR", a.static_dir != b.static_dir),
+        ("LATTE_STATIC_ASSETS_MAX_AGE", a.static_assets_max_age != b.static_assets_max_age),
+        ("LATTE_BACKUP_DIR", a.backup_dir != b.backup_dir),
+        ("LATTE_EXPORT_DIR", a.export_dir != b.export_dir),
+        ("LATTE_THUMBNAIL_SMALL", a.thumbnail_small != b.thumbnail_small),
+        ("LATTE_THUMBNAIL_MEDIUM", a.thumbnail_medium != b.thumbnail_medium),
+        ("LATTE_THUMBNAIL_LARGE", a.thumbnail_large != b.thumbnail_large),
+        ("LATTE_THUMBNAIL_QUALITY", a.thumbnail_quality != b.thumbnail_quality),
+        ("LATTE_THUMBNAIL_FORMATS", a.thumbnail_formats != b.thumbnail_formats),
+        ("LATTE_MAX_DECODE_PIXELS", a.max_decode_pixels != b.max_decode_pixels),
+        ("LATTE_THUMBNAIL_ACCEL_REDIRECT", a.thumbnail_accel_redirect != b.thumbnail_accel_redirect),
+        ("LATTE_THUMBNAIL_FAILURE_TTL_SECONDS", a.thumbnail_failure_ttl_seconds != b.thumbnail_failure_ttl_seconds),
+        ("LATTE_SCAN_WORKER_COUNT", a.scan_worker_count != b.scan_worker_count),
+        ("LATTE_SCAN_CRON", a.scan_cron != b.scan_cron),
+        ("LATTE_SCAN_ON_FIRST_RUN", a.scan_on_first_run != b.scan_on_first_run),
+        ("LATTE_SCAN_BATCH_SIZE", a.scan_batch_size != b.scan_batch_size),
+        ("LATTE_RAW_JPEG_PAIRING", a.raw_jpeg_pairing != b.raw_jpeg_pairing),
+        ("LATTE_TAKEOUT_SIDECARS", a.takeout_sidecars != b.takeout_sidecars),
+        ("LATTE_SCAN_MIN_FILE_SIZE", a.scan_min_file_size != b.scan_min_file_size),
+        ("LATTE_SCAN_IGNORE_PATTERNS", a.scan_ignore_patterns != b.scan_ignore_patterns),
+        ("LATTE_EXTRA_IMAGE_EXTS", a.extra_image_extensions != b.extra_image_extensions),
+        ("LATTE_EXTRA_VIDEO_EXTS", a.extra_video_extensions != b.extra_video_extensions),
+        ("LATTE_SCAN_STABILITY_WINDOW_SECONDS", a.scan_stability_window_seconds != b.scan_stability_window_seconds),
+        ("LATTE_QUIET_HOURS", a.quiet_hours != b.quiet_hours),
+        ("LATTE_QUIET_HOURS_CONCURRENCY", a.quiet_hours_concurrency != b.quiet_hours_concurrency),
+        ("LATTE_QUIET_HOURS_PAUSE", a.quiet_hours_pause != b.quiet_hours_pause),
+        ("LATTE_SCAN_IO_BYTES_PER_SECOND", a.scan_io_bytes_per_second != b.scan_io_bytes_per_second),
+        ("LATTE_SCAN_CHANGE_DETECTION", a.scan_change_detection != b.scan_change_detection),
+        ("LATTE_TOMBSTONE_CHECK_INTERVAL_SECONDS", a.tombstone_check_interval_seconds != b.tombstone_check_interval_seconds),
+        ("LATTE_TOMBSTONE_CHECK_SAMPLE_SIZE", a.tombstone_check_sample_size != b.tombstone_check_sample_size),
+        ("LATTE_VIDEO_FFMPEG_PATH", a.ffmpeg_path != b.ffmpeg_path),
+        ("LATTE_VIDEO_FFPROBE_PATH", a.ffprobe_path != b.ffprobe_path),
+        ("LATTE_VIDEO_THUMBNAIL_OFFSET", a.video_thumbnail_offset != b.video_thumbnail_offset),
+        ("LATTE_VIDEO_TIMELINE_MAX_KEYFRAMES", a.video_timeline_max_keyframes != b.video_timeline_max_keyframes),
+        ("LATTE_VIDEO_FRAME_MAX_WIDTH", a.video_frame_max_width != b.video_frame_max_width),
+        ("LATTE_VIDEO_FRAME_CONCURRENCY", a.video_frame_concurrency != b.video_frame_concurrency),
+        ("LATTE_VIDEO_THUMBNAIL_DURATION", a.video_thumbnail_duration != b.video_thumbnail_duration),
+        ("LATTE_CACHE_MAX_CAPACITY", a.cache_max_capacity != b.cache_max_capacity),
+        ("LATTE_CACHE_TTL_SECONDS", a.cache_ttl_seconds != b.cache_ttl_seconds),
+        ("LATTE_CACHE_MIN_FREE_MB", a.cache_min_free_mb != b.cache_min_free_mb),
+        ("LATTE_DB_BATCH_CHECK_SIZE", a.db_batch_check_size != b.db_batch_check_size),
+        ("LATTE_DB_BATCH_WRITE_SIZE", a.db_batch_write_size != b.db_batch_write_size),
+        ("LATTE_WS_PROGRESS_INTERVAL", a.ws_progress_broadcast_interval != b.ws_progress_broadcast_interval),
+        ("LATTE_WS_CHANNEL_CAPACITY", a.ws_channel_capacity != b.ws_channel_capacity),
+        ("LATTE_WS_SEND_BUFFER", a.ws_send_buffer != b.ws_send_buffer),
+        ("LATTE_API_DEFAULT_PAGE_SIZE", a.api_default_page_size != b.api_default_page_size),
+        ("LATTE_API_MAX_PAGE_SIZE", a.api_max_page_size != b.api_max_page_size),
+        ("LATTE_TRANSCODING_THREADS", a.transcoding_threads != b.transcoding_threads),
+        ("LATTE_BACKGROUND_NICE", a.background_priority.nice != b.background_priority.nice),
+        ("LATTE_BACKGROUND_IO_CLASS", a.background_priority.io_class != b.background_priority.io_class),
+        ("LATTE_DISABLED_PROCESSORS", a.disabled_processors != b.disabled_processors),
+        ("LATTE_THUMBNAIL_WARM_QUEUE_SIZE", a.thumbnail_warm_queue_size != b.thumbnail_warm_queue_size),
+        ("LATTE_THUMBNAIL_WARM_WORKERS", a.thumbnail_warm_workers != b.thumbnail_warm_workers),
+        ("LATTE_BACKUP_KEEP", a.backup_keep != b.backup_keep),
+        ("LATTE_REMOTE_LIBRARY_URL", a.remote_library_url != b.remote_library_url),
+        ("LATTE_REMOTE_LIBRARY_NAME", a.remote_library_name != b.remote_library_name),
+        ("LATTE_TAGGING_URL", a.tagging_url != b.tagging_url),
+        ("LATTE_TAGGING_TEXT_URL", a.tagging_text_url != b.tagging_text_url),
+        ("LATTE_TAGGING_BATCH_SIZE", a.tagging_batch_size != b.tagging_batch_size),
+        ("LATTE_TAGGING_MAX_RETRIES", a.tagging_max_retries != b.tagging_max_retries),
+        ("LATTE_TAGGING_POLL_INTERVAL_SECONDS", a.tagging_poll_interval_seconds != b.tagging_poll_interval_seconds),
+        ("LATTE_WEBHOOK_LOW_DISK_MB", a.webhook_low_disk_mb != b.webhook_low_disk_mb),
+        ("LATTE_SYNC_RETENTION_DAYS", a.sync_retention_days != b.sync_retention_days),
+        ("LATTE_SCAN_SUMMARY_NTFY_URL", a.scan_summary_ntfy_url != b.scan_summary_ntfy_url),
+        ("LATTE_SCAN_SUMMARY_NTFY_TOKEN", a.scan_summary_ntfy_token != b.scan_summary_ntfy_token),
+        ("LATTE_SCAN_SUMMARY_SMTP_URL", a.scan_summary_smtp_url != b.scan_summary_smtp_url),
+        ("LATTE_SCAN_SUMMARY_EMAIL_FROM", a.scan_summary_email_from != b.scan_summary_email_from),
+        ("LATTE_SCAN_SUMMARY_EMAIL_TO", a.scan_summary_email_to != b.scan_summary_email_to),
+        ("LATTE_SCAN_SUMMARY_TEMPLATE", a.scan_summary_template != b.scan_summary_template),
+        ("LATTE_WEEK_START", a.week_start != b.week_start),
+        ("LATTE_DATE_LOCALE", a.date_locale != b.date_locale),
+        ("LATTE_READ_ONLY", a.read_only != b.read_only),
+        ("LATTE_BLUR_DETECTION", a.blur_detection != b.blur_detection),
+        ("LATTE_BLUR_THRESHOLD", a.blur_threshold != b.blur_threshold),
+        ("LATTE_MAKER_NOTES", a.maker_notes != b.maker_notes),
+    ]
+    .into_iter()
+    .filter(|(_, changed)| *changed)
+    .map(|(key, _)| key)
+    .collect()
+}
+
+fn list_or_none(keys: &[&str]) -> String {
+    if keys.is_empty() {
+        "none".to_string()
+    } else {
+        keys.join(", ")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_restart_required() {
+        let startup = Config::default();
+        assert!(restart_required(&startup, &startup.clone()).is_empty());
+
+        let config = Config {
+            port: 9090,
+            db_path: "/tmp/other.db".into(),
+            thumbnail_quality: 0.5,
+            ..Config::default()
+        };
+        assert_eq!(restart_required(&startup, &config), vec!["LATTE_PORT", "LATTE_DB_PATH"]);
+
+        // 不可重载的设置即使不属于路径或线程池也要重启才生效
+        let config = Config {
+            api_max_page_size: 500,
+            blur_threshold: 50.0,
+            scan_cron: "0 0 3 * * ?".to_string(),
+            ..Config::default()
+        };
+        assert_eq!(
+            restart_required(&startup, &config),
+            vec!["LATTE_API_MAX_PAGE_SIZE", "LATTE_BLUR_THRESHOLD"]
+        );
+    }
+}
@@ -0,0 +1,146 @@
+//! 一致性检查
+//! 按 base_path 下的顶层目录分别统计数据库中的文件数和磁盘上可被扫描收录的文件数，
+//! 列出两者不一致的目录，帮助判断是否需要重新扫描（GET /api/maintenance/consistency）。
+//! 只读：不写数据库、不触发扫描。磁盘统计直接复用扫描收集文件的遍历（ScanService::collect_file_paths）。
+
+use crate::config::Config;
+use crate::db::{DatabasePool, MediaFileRepository};
+use crate::services::ScanService;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+/// Group name of files directly inside base_path
+pub const ROOT_GROUP: &str = ".";
+
+/// File counts of one top-level directory
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DirectoryConsistency {
+    /// Top-level directory name relative to base_path ("." for files directly inside it)
+    pub directory: String,
+    pub db_count: u64,
+    pub fs_count: u64,
+    /// fs_count - db_count: positive when files are not indexed yet, negative when indexed files are gone
+    pub difference: i64,
+}
+
+/// Result of a consistency check
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConsistencyReport {
+    pub db_total: u64,
+    pub fs_total: u64,
+    /// Directories whose counts differ, largest difference first
+    pub discrepancies: Vec<DirectoryConsistency>,
+    /// Number of top-level directories compared
+    pub directories_checked: usize,
+    pub rescan_recommended: bool,
+}
+
+impl ConsistencyReport {
+    /// Compare per-directory counts of the database and the filesystem
+    pub fn compare(db_counts: &BTreeMap<String, u64>, fs_counts: &BTreeMap<String, u64>) -> Self {
+        let mut directories: Vec<&String> = db_counts.keys().chain(fs_counts.keys()).collect();
+        directories.sort();
+        directories.dedup();
+
+        let mut discrepancies: Vec<DirectoryConsistency> = directories
+            .iter()
+            .map(|directory| {
+                let db_count = db_counts.get(*directory).copied().unwrap_or(0);
+                let fs_count = fs_counts.get(*directory).copied().unwrap_or(0);
+                DirectoryConsistency {
+                    directory: directory.to_string(),
+                    db_count,
+                    fs_count,
+                    difference: fs_count as i64 - db_count as i64,
+                }
+            })
+            .filter(|d| d.difference != 0)
+            .collect();
+        discrepancies.sort_by_key(|d| std::cmp::Reverse(d.difference.unsigned_abs()));
+
+        Self {
+            db_total: db_counts.values().sum(),
+            fs_total: fs_counts.values().sum(),
+            rescan_recommended: !discrepancies.is_empty(),
+            directories_checked: directories.len(),
+            discrepancies,
+        }
+    }
+}
+
+/// Run the comparison for the configured library
+pub async fn check(db: &DatabasePool, config: &Config, scan_service: &ScanService) -> std::io::Result<ConsistencyReport> {
+    let paths = MediaFileRepository::new(db)
+        .find_all_paths()
+        .await
+        .map_err(std::io::Error::other)?;
+    let db_counts = count_by_top_directory(&config.base_path, paths.iter().map(Path::new));
+    let (files, _, _) = scan_service.collect_file_paths(false).await?;
+    let fs_counts = count_by_top_directory(&config.base_path, files.iter().map(PathBuf::as_path));
+    Ok(ConsistencyReport::compare(&db_counts, &fs_counts))
+}
+
+/// Group name of `path`: its first component below `base`; None outside base
+/// (e.g. files of a remote library)
+fn top_directory(base: &Path, path: &Path) -> Option<String> {
+    let relative = path.strip_prefix(base).ok()?;
+    let mut components = relative.components();
+    let first = components.next()?;
+    Some(match components.next() {
+        Some(_) => first.as_os_str().to_string_lossy().to_string(),
+        None => ROOT_GROUP.to_string(),
+    })
+}
+
+/// Count paths per top-level directory of `base`
+pub fn count_by_top_directory<'p>(base: &Path, paths: impl IntoIterator<Item = &'p Path>) -> BTreeMap<String, u64> {
+    let mut counts = BTreeMap::new();
+    for path in paths {
+        if let Some(directory) = top_directory(base, path) {
+            *counts.entry(directory).or_insert(0) += 1;
+        }
+    }
+    counts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn counts(entries: &[(&str, u64)]) -> BTreeMap<String, u64> {
+        entries.iter().map(|(k, v)| (k.to_string(), *v)).collect()
+    }
+
+    #[test]
+    fn test_count_by_top_directory() {
+        let base = Path::new("/photos");
+        let paths = ["/photos/a.jpg", "/photos/2023/x.jpg", "/photos/2023/trip/y.jpg", "/other/z.jpg"];
+        let result = count_by_top_directory(base, paths.iter().map(Path::new));
+        assert_eq!(result, counts(&[(".", 1), ("2023", 2)]));
+    }
+
+    #[test]
+    fn test_compare_reports_only_discrepancies() {
+        let db = counts(&[("2022", 10), ("2023", 5), ("old", 3)]);
+        let fs = counts(&[("2022", 10), ("2023", 7), ("new", 1)]);
+        let report = ConsistencyReport::compare(&db, &fs);
+
+        assert_eq!(report.db_total, 18);
+        assert_eq!(report.fs_total, 18);
+        assert_eq!(report.directories_checked, 4);
+        assert!(report.rescan_recommended);
+        let found: Vec<(&str, i64)> = report.discrepancies.iter().map(|d| (d.directory.as_str(), d.difference)).collect();
+        assert_eq!(found, [("old", -3), ("2023", 2), ("new", 1)]);
+    }
+
+    #[test]
+    fn test_compare_consistent() {
+        let both = counts(&[("2023", 4)]);
+        let report = ConsistencyReport::compare(&both, &both);
+        assert!(!report.rescan_recommended);
+        assert!(report.discrepancies.is_empty());
+    }
+}
@@ -0,0 +1,170 @@
+//! 启动时检查可选的本地依赖
+//! HEIC 依赖 libheif（及其 HEVC 解码插件），视频依赖 FFmpeg 库或 ffmpeg/ffprobe 可执行文件。
+//! 缺失时各文件只会逐个处理失败，用户很难看出原因；这里在启动时统一探测一次，
+//! 输出带处理建议的警告，并通过 /api/system/info 返回结果。
+
+use crate::config::Config;
+use serde::Serialize;
+use std::path::Path;
+use std::process::Command;
+use tracing::{info, warn};
+
+/// Result of probing one native dependency
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DependencyStatus {
+    pub name: String,
+    pub available: bool,
+    pub version: Option<String>,
+    /// What stops working without it and how to fix it; None when available
+    pub message: Option<String>,
+}
+
+impl DependencyStatus {
+    fn available(name: &str, version: Option<String>) -> Self {
+        Self { name: name.to_string(), available: true, version, message: None }
+    }
+
+    fn missing(name: &str, message: String) -> Self {
+        Self { name: name.to_string(), available: false, version: None, message: Some(message) }
+    }
+}
+
+/// Probe all optional dependencies and log a warning for each one that is missing.
+/// Runs external commands, so call it off the async runtime.
+pub fn check_dependencies(config: &Config) -> Vec<DependencyStatus> {
+    let mut results = vec![check_libheif()];
+    #[cfg(feature = "video-processing")]
+    results.push(check_ffmpeg_library());
+    results.push(check_cli(
+        "ffmpeg",
+        &config.ffmpeg_path,
+        "LATTE_VIDEO_FFMPEG_PATH",
+        if cfg!(feature = "video-processing") {
+            "video thumbnails have no fallback when the FFmpeg libraries fail"
+        } else {
+            "video thumbnails cannot be generated"
+        },
+    ));
+    results.push(check_cli(
+        "ffprobe",
+        &config.ffprobe_path,
+        "LATTE_VIDEO_FFPROBE_PATH",
+        "video timelines and (without the video-processing feature) video metadata are unavailable",
+    ));
+
+    for status in &results {
+        match (status.available, &status.message) {
+            (true, _) => info!(
+                "Dependency {} available ({})",
+                status.name,
+                status.version.as_deref().unwrap_or("unknown version")
+            ),
+            (false, Some(message)) => warn!("Dependency {} unavailable: {}", status.name, message),
+            (false, None) => warn!("Dependency {} unavailable", status.name),
+        }
+    }
+    results
+}
+
+fn check_libheif() -> DependencyStatus {
+    let lib_heif = libheif_rs::LibHeif::new();
+    let [major, minor, patch] = lib_heif.version();
+    let version = format!("{}.{}.{}", major, minor, patch);
+
+    // libheif 本身总是链接进来的，但 HEVC 解码来自 libde265 等插件，发行版常常没有安装
+    if lib_heif
+        .decoder_descriptors(1, Some(libheif_rs::CompressionFormat::Hevc))
+        .is_empty()
+    {
+        return DependencyStatus {
+            version: Some(version),
+            ..DependencyStatus::missing(
+                "libheif",
+                "libheif has no HEVC decoder; HEIC files cannot be decoded or thumbnailed. \
+                 Install the libde265 plugin (e.g. `apt install libheif-plugin-libde265`)"
+                    .to_string(),
+            )
+        };
+    }
+    DependencyStatus::available("libheif", Some(version))
+}
+
+#[cfg(feature = "video-processing")]
+fn check_ffmpeg_library() -> DependencyStatus {
+    match ffmpeg_next::init() {
+        Ok(()) => {
+            let v = ffmpeg_next::util::version();
+            let version = format!("libavutil {}.{}.{}", v >> 16, (v >> 8) & 0xff, v & 0xff);
+            DependencyStatus::available("ffmpeg-library", Some(version))
+        }
+        Err(e) => DependencyStatus::missing(
+            "ffmpeg-library",
+            format!("Failed to initialize FFmpeg libraries ({}); video processing falls back to the CLI", e),
+        ),
+    }
+}
+
+/// Run `<binary> -version` and read the version from the first line
+fn check_cli(name: &str, path: &Path, env_key: &str, impact: &str) -> DependencyStatus {
+    match Command::new(path).arg("-version").output() {
+        Ok(output) if output.status.success() => {
+            let stdout = String::from_utf8_lossy(&output.stdout);
+            DependencyStatus::available(name, parse_version_line(&stdout))
+        }
+        Ok(output) => DependencyStatus::missing(
+            name,
+            format!(
+                "{} exited with {} ({}); {}",
+                path.display(),
+                output.status,
+                String::from_utf8_lossy(&output.stderr).trim(),
+                impact
+            ),
+        ),
+        Err(e) => DependencyStatus::missing(
+            name,
+            format!(
+                "Cannot run {} ({}); {}. Install FFmpeg or set {}",
+                path.display(),
+                e,
+                impact,
+                env_key
+            ),
+        ),
+    }
+}
+
+/// Version from the first line of `ffmpeg -version` ("ffmpeg version 6.1.1-3ubuntu5 Copyright ...")
+fn parse_version_line(output: &str) -> Option<String> {
+    let line = output.lines().next()?;
+    let rest = &line[line.find(" version ")? + " version ".len()..];
+    rest.split_whitespace().next().map(str::to_string)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_version_line() {
+        let output = "ffmpeg version 6.1.1-3ubuntu5 Copyright (c) 2000-2023 the FFmpeg developers\nbuilt with gcc 13";
+        assert_eq!(parse_version_line(output).as_deref(), Some("6.1.1-3ubuntu5"));
+        assert_eq!(parse_version_line("ffprobe version n7.0 Copyright").as_deref(), Some("n7.0"));
+        assert_eq!(parse_version_line("garbage"), None);
+        assert_eq!(parse_version_line(""), None);
+    }
+
+    #[test]
+    fn test_missing_cli_reports_fix() {
+        let status = check_cli(
+            "ffmpeg",
+            Path::new("/nonexistent/ffmpeg"),
+            "LATTE_VIDEO_FFMPEG_PATH",
+            "video thumbnails cannot be generated",
+        );
+        assert!(!status.available);
+        assert!(status.version.is_none());
+        assert!(status.message.unwrap().contains("LATTE_VIDEO_FFMPEG_PATH"));
+    }
+}
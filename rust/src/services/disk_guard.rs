@@ -0,0 +1,204 @@
+//! 缓存磁盘空间保护
+//! 生成缩略图、视频帧等缓存内容前检查缓存目录所在卷的剩余空间，低于 LATTE_CACHE_MIN_FREE_MB 时拒绝生成
+//! （API 返回 507），避免写满磁盘后缓存文件和同一卷上的数据库被写坏。
+//! df 需要启动子进程，查询结果缓存 CHECK_INTERVAL；空间从充足变为不足时通过 WebSocket 推送一次警告。
+
+use crate::services::disk_usage::{self, DiskUsage};
+use crate::websocket::WarningBroadcaster;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// How long a free-space reading is reused
+const CHECK_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Warning code pushed when the cache volume runs low
+pub const DISK_SPACE_LOW_WARNING: &str = "disk_space_low";
+
+/// Generation refused: the cache volume has less free space than configured
+#[derive(Debug, Clone, Copy, PartialEq, Eq, thiserror::Error)]
+#[error("Low disk space on the cache volume: {} MB free, at least {} MB required", .free_bytes / MB, .min_free_bytes / MB)]
+pub struct LowDiskSpace {
+    pub free_bytes: u64,
+    pub min_free_bytes: u64,
+}
+
+const MB: u64 = 1024 * 1024;
+
+#[derive(Debug, Default)]
+struct GuardState {
+    checked_at: Option<Instant>,
+    free_bytes: Option<u64>,
+    /// A warning was sent for the current low-space period
+    warned: bool,
+}
+
+/// Free-space check of the cache volume
+pub struct DiskSpaceGuard {
+    path: PathBuf,
+    min_free_bytes: AtomicU64,
+    warnings: Option<Arc<WarningBroadcaster>>,
+    state: Mutex<GuardState>,
+}
+
+impl DiskSpaceGuard {
+    /// # Arguments
+    ///
+    /// * `path` - Directory on the guarded volume (the cache directory)
+    /// * `min_free_mb` - Required free space in MB; 0 disables the guard
+    pub fn new(path: PathBuf, min_free_mb: u64) -> Self {
+        Self {
+            path,
+            min_free_bytes: AtomicU64::new(min_free_mb.saturating_mul(MB)),
+            warnings: None,
+            state: Mutex::new(GuardState::default()),
+        }
+    }
+
+    /// Push a WebSocket warning when free space drops below the threshold
+    pub fn with_warnings(mut self, warnings: Arc<WarningBroadcaster>) -> Self {
+        self.warnings = Some(warnings);
+        self
+    }
+
+    /// Change the required free space (configuration reload); 0 disables the guard
+    pub fn set_min_free_mb(&self, min_free_mb: u64) {
+        self.min_free_bytes.store(min_free_mb.saturating_mul(MB), Ordering::Relaxed);
+    }
+
+    /// Ok when there is enough free space, or when it cannot be determined (no `df`)
+    pub async fn check(&self) -> Result<(), LowDiskSpace> {
+        let min_free_bytes = self.min_free_bytes.load(Ordering::Relaxed);
+        if min_free_bytes == 0 {
+            return Ok(());
+        }
+
+        let cached = {
+            let state = self.state.lock().unwrap();
+            state
+                .checked_at
+                .filter(|at| at.elapsed() < CHECK_INTERVAL)
+                .map(|_| state.free_bytes)
+        };
+        let free_bytes = match cached {
+            Some(free_bytes) => free_bytes,
+            None => {
+                let path = self.path.clone();
+                let usage = tokio::task::spawn_blocking(move || disk_usage::disk_usage(&path))
+                    .await
+                    .ok()
+                    .flatten();
+                self.record(usage)
+            }
+        };
+
+        match free_bytes {
+            Some(free_bytes) if free_bytes < min_free_bytes => Err(LowDiskSpace {
+                free_bytes,
+                min_free_bytes,
+            }),
+            _ => Ok(()),
+        }
+    }
+
+    /// Store a reading; warns once per low-space period
+    fn record(&self, usage: Option<DiskUsage>) -> Option<u64> {
+        let free_bytes = usage.map(|u| u.free_bytes);
+        let min_free_bytes = self.min_free_bytes.load(Ordering::Relaxed);
+        let mut state = self.state.lock().unwrap();
+        state.checked_at = Some(Instant::now());
+        state.free_bytes = free_bytes;
+
+        let low = free_bytes.is_some_and(|free| free < min_free_bytes);
+        if low && !state.warned {
+            let error = LowDiskSpace {
+                free_bytes: free_bytes.unwrap_or_default(),
+                min_free_bytes,
+            };
+            tracing::warn!("{}; thumbnail generation paused", error);
+            if let Some(warnings) = &self.warnings {
+                warnings.warn(DISK_SPACE_LOW_WARNING, error.to_string());
+            }
+        }
+        state.warned = low;
+        free_bytes
+    }
+}
+
+/// The LowDiskSpace refusal inside an error returned by generation, if that is the cause
+pub fn find_low_disk_space<'a>(error: &'a (dyn std::error::Error + 'static)) -> Option<&'a LowDiskSpace> {
+    let mut current = Some(error);
+    while let Some(error) = current {
+        if let Some(low) = error.downcast_ref::<LowDiskSpace>() {
+            return Some(low);
+        }
+        // io::Error::other 包装的错误不会出现在 source() 链中
+        if let Some(low) = error
+            .downcast_ref::<std::io::Error>()
+            .and_then(|e| e.get_ref())
+            .and_then(|inner| inner.downcast_ref::<LowDiskSpace>())
+        {
+            return Some(low);
+        }
+        current = error.source();
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn usage(free_mb: u64) -> Option<DiskUsage> {
+        Some(DiskUsage { total_bytes: 100_000 * MB, free_bytes: free_mb * MB })
+    }
+
+    #[test]
+    fn test_warns_once_per_low_period() {
+        let warnings = Arc::new(WarningBroadcaster::new(8));
+        let mut rx = warnings.subscribe();
+        let guard = DiskSpaceGuard::new(PathBuf::from("/cache"), 100).with_warnings(warnings);
+
+        guard.record(usage(500));
+        assert!(rx.try_recv().is_err());
+        guard.record(usage(50));
+        assert_eq!(rx.try_recv().unwrap().code, DISK_SPACE_LOW_WARNING);
+        guard.record(usage(40));
+        assert!(rx.try_recv().is_err());
+
+        // 空间恢复后再次不足时重新警告
+        guard.record(usage(200));
+        guard.record(usage(10));
+        assert!(rx.try_recv().is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_check_uses_recent_reading() {
+        let guard = DiskSpaceGuard::new(PathBuf::from("/cache"), 100);
+        guard.record(usage(50));
+        let error = guard.check().await.unwrap_err();
+        assert_eq!(error.free_bytes, 50 * MB);
+        // 重新加载配置后按新阈值判断
+        guard.set_min_free_mb(10);
+        assert!(guard.check().await.is_ok());
+        guard.set_min_free_mb(100);
+
+        guard.record(None);
+        assert!(guard.check().await.is_ok());
+        assert!(DiskSpaceGuard::new(PathBuf::from("/cache"), 0).check().await.is_ok());
+    }
+
+    #[test]
+    fn test_find_low_disk_space() {
+        let low = LowDiskSpace { free_bytes: 1, min_free_bytes: 2 };
+        let boxed: Box<dyn std::error::Error> = Box::new(low);
+        assert_eq!(find_low_disk_space(boxed.as_ref()), Some(&low));
+
+        let wrapped = crate::processors::ProcessingError::IoError(std::io::Error::other(low));
+        assert_eq!(find_low_disk_space(&wrapped), Some(&low));
+
+        let other = std::io::Error::other("disk on fire");
+        assert_eq!(find_low_disk_space(&other), None);
+    }
+}
@@ -0,0 +1,64 @@
+//! 磁盘空间查询
+//! 通过 `df -Pk` 读取路径所在文件系统的总容量与可用空间（POSIX 输出格式，Linux/macOS 通用），
+//! 避免为此引入平台相关的系统调用依赖。
+
+use std::path::Path;
+use std::process::Command;
+
+/// Capacity of the filesystem holding a path
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DiskUsage {
+    pub total_bytes: u64,
+    pub free_bytes: u64,
+}
+
+/// Query the filesystem holding `path`; None when `df` is unavailable or the path does not exist
+pub fn disk_usage(path: &Path) -> Option<DiskUsage> {
+    let output = Command::new("df").arg("-Pk").arg(path).output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    parse_df_output(&String::from_utf8_lossy(&output.stdout))
+}
+
+/// Parse `df -Pk` output: a header line, then
+/// `Filesystem 1024-blocks Used Available Capacity Mounted-on`
+fn parse_df_output(output: &str) -> Option<DiskUsage> {
+    let line = output.lines().nth(1)?;
+    let fields: Vec<&str> = line.split_whitespace().collect();
+    // 文件系统名与挂载点都可能含空格，以 Capacity 列（"40%"）为锚点定位数值列
+    let capacity = fields.iter().skip(4).position(|f| f.ends_with('%'))? + 4;
+    let total_kb: u64 = fields[capacity - 3].parse().ok()?;
+    let free_kb: u64 = fields[capacity - 1].parse().ok()?;
+    Some(DiskUsage {
+        total_bytes: total_kb * 1024,
+        free_bytes: free_kb * 1024,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_df_output() {
+        let output = "Filesystem     1024-blocks      Used Available Capacity Mounted on\n\
+                      /dev/sda1        102400000  40000000  62400000      40% /volume1\n";
+        assert_eq!(
+            parse_df_output(output),
+            Some(DiskUsage { total_bytes: 102400000 * 1024, free_bytes: 62400000 * 1024 })
+        );
+        let spaced = "Filesystem 1024-blocks Used Available Capacity Mounted on\n\
+                      //nas/My Share 2048 1024 1024 50% /mnt/My Photos\n";
+        assert_eq!(parse_df_output(spaced), Some(DiskUsage { total_bytes: 2048 * 1024, free_bytes: 1024 * 1024 }));
+        assert_eq!(parse_df_output("Filesystem 1024-blocks Used Available Capacity Mounted on\n"), None);
+    }
+
+    #[test]
+    fn test_disk_usage_of_temp_dir() {
+        if let Some(usage) = disk_usage(&std::env::temp_dir()) {
+            assert!(usage.total_bytes >= usage.free_bytes);
+        }
+    }
+}
@@ -1,9 +1,23 @@
 use crate::config::Config;
-use crate::db::{DatabasePool, MediaFileRepository};
-use crate::processors::ProcessorRegistry;
-use crate::services::CacheService;
+use crate::db::{DatabasePool, FailedFileRepository, MediaFileRepository};
+use crate::processors::exposure;
+use crate::processors::panorama;
+use crate::processors::thumbnail_crop::{self, CropMode};
+use crate::processors::video_cli;
+use crate::processors::processor_trait::run_cpu_bound;
+use crate::processors::{MediaProcessor, ProcessingError, ProcessorRegistry};
+use crate::services::disk_guard::{DiskSpaceGuard, LowDiskSpace};
+use crate::services::{CacheService, TombstoneChecker};
+use crate::utils::library_path::{LibraryRoots, PathCheckError};
+use crate::utils::placeholder;
+use crate::utils::{ThumbnailFormat, ThumbnailFormats, ThumbnailOptions, ThumbnailPipeline};
 use bytes::Bytes;
+use moka::future::Cache;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU32, Ordering};
 use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Semaphore;
 use tracing::{debug, warn};
 
 /// Service for file operations
@@ -12,23 +26,145 @@ pub struct FileService {
     db: DatabasePool,
     cache: Arc<CacheService>,
     processors: Arc<ProcessorRegistry>,
-    thumbnail_quality: f32,
+    tombstones: Arc<TombstoneChecker>,
+    /// Files outside these roots are never read (see utils::library_path)
+    library_roots: LibraryRoots,
+    /// Recently failed "{file_id}_{size_label}" thumbnails; not retried until the entry expires
+    failures: Cache<String, ()>,
+    /// JPEG/WebP quality as f32 bits; changed by configuration reloads, shared by clones
+    thumbnail_quality: Arc<AtomicU32>,
+    /// Output format per size label (LATTE_THUMBNAIL_FORMATS)
+    thumbnail_formats: ThumbnailFormats,
+    /// ffmpeg binary used for video frames at arbitrary timestamps
+    ffmpeg_path: PathBuf,
+    /// Slots for concurrent frame extractions (LATTE_VIDEO_FRAME_CONCURRENCY)
+    frame_permits: Arc<Semaphore>,
+    /// Refuses generation when the cache volume is nearly full
+    disk_guard: Option<Arc<DiskSpaceGuard>>,
 }
 
+/// Stage name of thumbnail failures in the failed-file registry
+pub const THUMBNAIL_STAGE: &str = "thumbnail";
+
+/// Video frame timestamps are rounded to this step (seconds) so that scrubbing hits the cache
+pub const FRAME_TIME_STEP: f64 = 0.5;
+
 impl FileService {
     pub fn new(
         db: DatabasePool,
         cache: Arc<CacheService>,
         processors: Arc<ProcessorRegistry>,
+        tombstones: Arc<TombstoneChecker>,
         config: &Config,
     ) -> Self {
         Self {
             db,
             cache,
             processors,
-            thumbnail_quality: config.thumbnail_quality,
+            tombstones,
+            library_roots: LibraryRoots::from_config(config),
+            failures: Cache::builder()
+                .max_capacity(100_000)
+                .time_to_live(Duration::from_secs(config.thumbnail_failure_ttl_seconds.max(1)))
+                .build(),
+            thumbnail_quality: Arc::new(AtomicU32::new(config.thumbnail_quality.to_bits())),
+            thumbnail_formats: config.thumbnail_formats.clone(),
+            ffmpeg_path: config.ffmpeg_path.clone(),
+            frame_permits: Arc::new(Semaphore::new(config.video_frame_concurrency.max(1))),
+            disk_guard: None,
+        }
+    }
+
+    /// Check free space on the cache volume before generating thumbnails and frames
+    pub fn with_disk_guard(mut self, disk_guard: Arc<DiskSpaceGuard>) -> Self {
+        self.disk_guard = Some(disk_guard);
+        self
+    }
+
+    /// Quality used for newly generated thumbnails
+    pub fn thumbnail_quality(&self) -> f32 {
+        f32::from_bits(self.thumbnail_quality.load(Ordering::Relaxed))
+    }
+
+    /// Change the quality of thumbnails generated from now on; cached ones are kept
+    pub fn set_thumbnail_quality(&self, quality: f32) {
+        self.thumbnail_quality.store(quality.to_bits(), Ordering::Relaxed);
+    }
+
+    pub fn disk_guard(&self) -> Option<&Arc<DiskSpaceGuard>> {
+        self.disk_guard.as_ref()
+    }
+
+    /// Err when the cache volume is below the free-space threshold; generation must not start
+    async fn check_disk_space(&self) -> Result<(), LowDiskSpace> {
+        match &self.disk_guard {
+            Some(guard) => guard.check().await,
+            None => Ok(()),
         }
     }
+
+    /// Directories media files may be read from
+    pub fn library_roots(&self) -> &LibraryRoots {
+        &self.library_roots
+    }
+
+    /// Resolve the path of a media file for reading; files that no longer exist are
+    /// reported to the tombstone checker
+    pub async fn resolve_source(&self, file_id: &str, file_path: &str) -> Result<std::path::PathBuf, PathCheckError> {
+        let resolved = self.library_roots.resolve(std::path::Path::new(file_path)).await;
+        if resolved == Err(PathCheckError::NotFound) {
+            debug!("File not found: {}", file_path);
+            self.tombstones.report_missing(file_id);
+        }
+        resolved
+    }
+
+    /// Remember a failed thumbnail so it is not regenerated until the failure TTL expires.
+    /// Decode errors (`error`) are also written to the failed-file registry for a later retry.
+    async fn record_failure(&self, file_id: &str, size_label: &str, error: Option<&str>) {
+        self.failures.insert(format!("{}_{}", file_id, size_label), ()).await;
+        if let Some(error) = error {
+            if let Err(e) = FailedFileRepository::new(&self.db).record(file_id, THUMBNAIL_STAGE, error).await {
+                warn!("Failed to record thumbnail failure of {}: {}", file_id, e);
+            }
+        }
+    }
+
+    /// Forget remembered thumbnail failures so the next request decodes again
+    pub fn clear_failures(&self) {
+        self.failures.invalidate_all();
+    }
+
+    /// Drop the cached thumbnails and remembered failures of one file so they are regenerated
+    /// from the current file content
+    pub async fn invalidate_thumbnails(&self, file_id: &str) -> std::io::Result<()> {
+        let prefix = format!("{}_", file_id);
+        let failed: Vec<Arc<String>> = self
+            .failures
+            .iter()
+            .map(|(key, _)| key)
+            .filter(|key| key.starts_with(&prefix))
+            .collect();
+        for key in failed {
+            self.failures.invalidate(key.as_str()).await;
+        }
+        if let Err(e) = FailedFileRepository::new(&self.db).clear(file_id, THUMBNAIL_STAGE).await {
+            warn!("Failed to clear thumbnail failure of {}: {}", file_id, e);
+        }
+
+        let removed = self.cache.invalidate(file_id).await?;
+        debug!("Invalidated {} cached thumbnails of {}", removed, file_id);
+        Ok(())
+    }
+
+    /// SVG placeholder for a file whose thumbnail cannot be generated; None if the file is unknown
+    pub async fn thumbnail_placeholder(&self, file_id: &str) -> Result<Option<String>, sqlx::Error> {
+        let repo = MediaFileRepository::new(&self.db);
+        Ok(repo
+            .find_by_id(file_id)
+            .await?
+            .map(|file| placeholder::placeholder_svg(&file.file_type, &file.file_name)))
+    }
 }
 
 /// Service for file operations - methods
@@ -36,7 +172,7 @@ impl FileService {
     /// Get thumbnail for a file
     /// For "full" size, browser-native formats are served directly without transcoding
     /// (JPEG, PNG, GIF, WebP, AVIF, SVG). Other formats like HEIC/HEIF will be transcoded.
-    /// Returns (data, mime_type) tuple. For thumbnails, mime_type is "image/jpeg".
+    /// Returns (data, mime_type) tuple. For thumbnails, mime_type is that of the format configured for the size.
     ///
     /// Parameters:
     /// - `size_label`: Cache key ("small", "medium", "large", "full")
@@ -51,26 +187,37 @@ impl FileService {
     ) -> Result<Option<(Vec<u8>, String)>, Box<dyn std::error::Error>> {
         // Check if this is a full-size request
         let is_full_size = size_label == "full";
+        let format = self.thumbnail_formats.for_size(size_label);
 
         // For all sizes including full, check disk cache first
         if let Some(data) = self.cache.get_thumbnail(file_id, size_label).await {
-            // Thumbnails are always JPEG; full-size cache uses original format
+            // Thumbnails use the configured format; full-size cache uses original format
             let mime_type = if is_full_size {
                 guess_mime_type_from_path(file_id)
             } else {
-                "image/jpeg".to_string()
+                format.mime_type().to_string()
             };
             // Convert Bytes to Vec<u8> for API compatibility
             return Ok(Some((data.to_vec(), mime_type)));
         }
 
+        // 近期失败过的不再重复解码，由调用方返回占位图
+        if self.failures.contains_key(&format!("{}_{}", file_id, size_label)) {
+            return Ok(None);
+        }
+
+        // 磁盘空间不足时不生成，由调用方返回 507
+        self.check_disk_space().await?;
+
         // Not in cache, generate thumbnail
         let repo = MediaFileRepository::new(&self.db);
+        // Some(error) when decoding failed; stays None when the file is missing or unsupported
+        let mut decode_error = None;
 
         match repo.find_by_id(file_id).await {
             Ok(Some(file)) => {
-                let path = std::path::Path::new(&file.file_path);
-                if path.exists() {
+                if let Ok(path) = self.resolve_source(file_id, &file.file_path).await {
+                    let path = path.as_path();
                     // For full-size requests with browser-native formats, serve original file directly (no transcoding)
                     if is_full_size && is_browser_native_format(&file.file_name) {
                         if let Ok(data) = tokio::fs::read(path).await {
@@ -84,42 +231,265 @@ impl FileService {
 
                     // Generate thumbnail using processor (which uses transcoding_pool internally)
                     if let Some(processor) = self.processors.find_processor(path) {
-                        match processor.generate_thumbnail(path, target_size, self.thumbnail_quality, fit_to_height).await {
+                        // 网格尺寸的全景图缩略图裁剪为固定宽高比，避免显示成一条细线
+                        let panorama_crop = !is_full_size && !fit_to_height
+                            && file.file_type == "image" && file.projection.is_some()
+                            && file.width > file.height;
+                        let generated = if panorama_crop {
+                            self.generate_panorama_grid_thumbnail(processor.as_ref(), path, target_size, format).await
+                        } else {
+                            processor
+                                .generate_thumbnail(path, target_size, self.thumbnail_quality(), format, fit_to_height)
+                                .await
+                        };
+                        match generated {
                             Ok(Some(thumbnail_data)) => {
                                 // Cache the generated thumbnail (all sizes including full)
                                 // Clone for caching since we need to return the original data
                                 let cache_data = Bytes::from(thumbnail_data.clone());
                                 let _ = self.cache.put_thumbnail_bytes(file_id, size_label, cache_data).await;
-                                return Ok(Some((thumbnail_data, "image/jpeg".to_string())));
+                                let _ = FailedFileRepository::new(&self.db).clear(file_id, THUMBNAIL_STAGE).await;
+                                if file.file_type == "image" && !is_full_size && file.mean_luminance.is_none() {
+                                    self.record_exposure(file_id, &thumbnail_data).await;
+                                }
+                                return Ok(Some((thumbnail_data, format.mime_type().to_string())));
                             }
                             Ok(None) => {
                                 debug!("Processor returned no thumbnail for {}", file_id);
                             }
                             Err(e) => {
                                 warn!("Failed to generate thumbnail for {}: {}", file_id, e);
+                                decode_error = Some(e.to_string());
                             }
                         }
                     }
-                } else {
-                    debug!("File not found: {}", file.file_path);
                 }
             }
             Ok(None) => {
                 debug!("File not found in database: {}", file_id);
+                return Ok(None);
             }
             Err(e) => {
                 warn!("Database error when looking up file {}: {}", file_id, e);
+                return Err(Box::new(e));
             }
         }
 
         // Fallback: try to read original file as thumbnail for images
         if !is_full_size {
-            self.generate_fallback_thumbnail(file_id).await
-        } else {
-            Ok(None)
+            match self.generate_fallback_thumbnail(file_id).await {
+                Ok(Some(fallback)) => return Ok(Some(fallback)),
+                Ok(None) => {}
+                Err(e) => debug!("Fallback thumbnail failed for {}: {}", file_id, e),
+            }
+        }
+
+        self.record_failure(file_id, size_label, decode_error.as_deref()).await;
+        Ok(None)
+    }
+
+    /// Compute exposure statistics from a generated thumbnail and store them.
+    /// The thumbnail is already small, so this costs one extra decode of a few hundred pixels.
+    async fn record_exposure(&self, file_id: &str, thumbnail: &[u8]) {
+        let data = thumbnail.to_vec();
+        let stats = match run_cpu_bound(self.processors.transcoding_pool(), move || {
+            exposure::thumbnail_exposure_stats(&data)
+        })
+        .await
+        {
+            Ok(Ok(Some(stats))) => stats,
+            Ok(Ok(None)) => return,
+            Ok(Err(e)) | Err(e) => {
+                debug!("Failed to compute exposure of {}: {}", file_id, e);
+                return;
+            }
+        };
+
+        let repo = MediaFileRepository::new(&self.db);
+        if let Err(e) = repo
+            .update_exposure(file_id, stats.mean_luminance, stats.clipped_highlights, stats.clipped_shadows)
+            .await
+        {
+            warn!("Failed to store exposure of {}: {}", file_id, e);
         }
     }
 
+    /// Record exposure statistics of images whose thumbnails were cached before the statistics
+    /// existed, from the smallest cached thumbnail. Images without one get them when it is generated.
+    /// Returns the number of images processed.
+    pub async fn backfill_exposure(&self) -> Result<usize, sqlx::Error> {
+        let ids = MediaFileRepository::new(&self.db).find_image_ids_missing_exposure().await?;
+        let mut processed = 0;
+        for id in ids {
+            for size_label in ["small", "medium", "large"] {
+                // 直接读磁盘，避免批量回填挤占内存缓存
+                let Some(path) = self.cache.get_thumbnail_disk_path(&id, size_label).await else {
+                    continue;
+                };
+                if let Ok(data) = tokio::fs::read(&path).await {
+                    self.record_exposure(&id, &data).await;
+                    processed += 1;
+                    break;
+                }
+            }
+        }
+        Ok(processed)
+    }
+
+    /// Thumbnail of a panorama for the grid: scaled to the grid height, then center-cropped
+    /// to panorama::GRID_ASPECT_RATIO so that it is `target_size` wide
+    async fn generate_panorama_grid_thumbnail(
+        &self,
+        processor: &dyn MediaProcessor,
+        path: &std::path::Path,
+        target_size: u32,
+        format: ThumbnailFormat,
+    ) -> Result<Option<Vec<u8>>, ProcessingError> {
+        let target_height = (target_size as f64 / panorama::GRID_ASPECT_RATIO).ceil() as u32;
+        // 中间结果固定为 JPEG，裁剪后再编码为目标格式
+        let Some(jpeg) = processor
+            .generate_thumbnail(path, target_height, self.thumbnail_quality(), ThumbnailFormat::Jpeg, true)
+            .await?
+        else {
+            return Ok(None);
+        };
+
+        let quality = self.thumbnail_quality();
+        run_cpu_bound(self.processors.transcoding_pool(), move || {
+            panorama::crop_grid_thumbnail(&jpeg, target_size, quality, format)
+        })
+        .await?
+        .map(Some)
+    }
+
+    /// Get a square-cropped thumbnail (`?crop=square|smart`) in the format of its size.
+    /// Cached separately from the uncropped thumbnail under "{size_label}_{crop}".
+    pub async fn get_cropped_thumbnail(
+        &self,
+        file_id: &str,
+        size_label: &str,
+        target_size: u32,
+        crop: CropMode,
+    ) -> Result<Option<Vec<u8>>, Box<dyn std::error::Error>> {
+        let cache_label = format!("{}_{}", size_label, crop.as_str());
+        if let Some(data) = self.cache.get_thumbnail(file_id, &cache_label).await {
+            return Ok(Some(data.to_vec()));
+        }
+        self.check_disk_space().await?;
+
+        let repo = MediaFileRepository::new(&self.db);
+        let Some(file) = repo.find_by_id(file_id).await? else {
+            return Ok(None);
+        };
+        let Ok(path) = self.resolve_source(file_id, &file.file_path).await else {
+            return Ok(None);
+        };
+        let path = path.as_path();
+        let Some(processor) = self.processors.find_processor(path) else {
+            return Ok(None);
+        };
+
+        // 先把短边缩放到目标尺寸，再裁成正方形
+        let fit_to_height = file.width > file.height;
+        let Some(jpeg) = processor
+            .generate_thumbnail(path, target_size, self.thumbnail_quality(), ThumbnailFormat::Jpeg, fit_to_height)
+            .await?
+        else {
+            return Ok(None);
+        };
+        let quality = self.thumbnail_quality();
+        let format = self.thumbnail_formats.for_size(size_label);
+        let thumbnail = run_cpu_bound(self.processors.transcoding_pool(), move || {
+            thumbnail_crop::crop_square_thumbnail(&jpeg, target_size, crop, quality, format)
+        })
+        .await??;
+
+        let _ = self.cache.put_thumbnail_bytes(file_id, &cache_label, Bytes::from(thumbnail.clone())).await;
+        Ok(Some(thumbnail))
+    }
+
+    /// JPEG frame of a video at `seconds` (rounded to FRAME_TIME_STEP), scaled to `width`.
+    /// Cached separately from the thumbnail under "frame_{ms}_w{width}".
+    /// Returns None when all extraction slots are busy; the caller should retry later.
+    pub async fn get_video_frame(
+        &self,
+        file_id: &str,
+        path: &Path,
+        seconds: f64,
+        width: u32,
+    ) -> Result<Option<Vec<u8>>, ProcessingError> {
+        let seconds = (seconds / FRAME_TIME_STEP).round() * FRAME_TIME_STEP;
+        let cache_label = format!("frame_{}_w{}", (seconds * 1000.0) as u64, width);
+        if let Some(data) = self.cache.get_thumbnail(file_id, &cache_label).await {
+            return Ok(Some(data.to_vec()));
+        }
+        self.check_disk_space().await.map_err(|e| ProcessingError::IoError(std::io::Error::other(e)))?;
+
+        // 拖动进度条会连续发出大量请求，超出并发上限时直接拒绝而不是排队
+        let Ok(_permit) = self.frame_permits.try_acquire() else {
+            return Ok(None);
+        };
+
+        let ffmpeg_path = self.ffmpeg_path.clone();
+        let path = path.to_path_buf();
+        let options = ThumbnailOptions::new(width, self.thumbnail_quality(), false);
+        let jpeg = tokio::task::spawn_blocking(move || {
+            let frame = video_cli::extract_frame(&ffmpeg_path, &path, seconds, width)?;
+            let frame = image::load_from_memory_with_format(&frame, image::ImageFormat::Png)?;
+            ThumbnailPipeline::new(options).run(frame)
+        })
+        .await
+        .map_err(|e| ProcessingError::Processing(e.to_string()))??;
+
+        let _ = self.cache.put_thumbnail_bytes(file_id, &cache_label, Bytes::from(jpeg.clone())).await;
+        Ok(Some(jpeg))
+    }
+
+    /// Get the thumbnail of one top-level image of a multi-image file (HEIC burst), in the format of its size.
+    /// Cached separately from the primary thumbnail under "{size_label}_item{item}".
+    /// Returns None when the file has no such sub-image.
+    pub async fn get_item_thumbnail(
+        &self,
+        file_id: &str,
+        item: u32,
+        size_label: &str,
+        target_size: u32,
+        fit_to_height: bool,
+    ) -> Result<Option<Vec<u8>>, Box<dyn std::error::Error>> {
+        let cache_label = format!("{}_item{}", size_label, item);
+        if let Some(data) = self.cache.get_thumbnail(file_id, &cache_label).await {
+            return Ok(Some(data.to_vec()));
+        }
+        self.check_disk_space().await?;
+
+        let repo = MediaFileRepository::new(&self.db);
+        let Some(file) = repo.find_by_id(file_id).await? else {
+            return Ok(None);
+        };
+        let Ok(path) = self.resolve_source(file_id, &file.file_path).await else {
+            return Ok(None);
+        };
+        let path = path.as_path();
+        let Some(processor) = self.processors.find_processor(path) else {
+            return Ok(None);
+        };
+
+        let thumbnail = processor
+            .generate_item_thumbnail(
+                path,
+                item,
+                target_size,
+                self.thumbnail_quality(),
+                self.thumbnail_formats.for_size(size_label),
+                fit_to_height,
+            )
+            .await?;
+        if let Some(data) = &thumbnail {
+            let _ = self.cache.put_thumbnail_bytes(file_id, &cache_label, Bytes::from(data.clone())).await;
+        }
+        Ok(thumbnail)
+    }
+
     /// Generate a fallback thumbnail from the original file
     async fn generate_fallback_thumbnail(
         &self,
@@ -128,11 +498,10 @@ impl FileService {
         let repo = MediaFileRepository::new(&self.db);
 
         if let Ok(Some(file)) = repo.find_by_id(file_id).await {
-            let path = std::path::Path::new(&file.file_path);
-            if path.exists() {
+            if let Ok(path) = self.library_roots.resolve(std::path::Path::new(&file.file_path)).await {
                 // For images, try to use the original file directly (scaled)
                 if file.file_type == "image" {
-                    let data = tokio::fs::read(path).await?;
+                    let data = tokio::fs::read(&path).await?;
                     // Basic JPEG/PNG check - if it's not a supported format, we can't serve it as thumbnail
                     let mime_type = if data.starts_with(&[0xFF, 0xD8]) {
                         "image/jpeg".to_string()
@@ -158,9 +527,8 @@ impl FileService {
 
         match repo.find_by_id(file_id).await {
             Ok(Some(file)) => {
-                let path = std::path::Path::new(&file.file_path);
-                if path.exists() {
-                    let data = tokio::fs::read(path).await?;
+                if let Ok(path) = self.library_roots.resolve(std::path::Path::new(&file.file_path)).await {
+                    let data = tokio::fs::read(&path).await?;
                     let mime_type = file.mime_type.unwrap_or_else(|| {
                         guess_mime_type(&file.file_name)
                     });
@@ -175,6 +543,50 @@ impl FileService {
     }
 }
 
+/// Largest dimension accepted for ?maxWidth=/?maxHeight= resizing
+const MAX_RESIZE_DIMENSION: u32 = 8192;
+
+/// Resize target for serving an image within max_width × max_height.
+/// Returns (target_size, fit_to_height) as used by thumbnail generation,
+/// or None when no bound is given or the image already fits (serve the original as-is).
+pub fn fit_within(
+    width: Option<i32>,
+    height: Option<i32>,
+    max_width: Option<u32>,
+    max_height: Option<u32>,
+) -> Option<(u32, bool)> {
+    let bound = |v: Option<u32>| v.filter(|v| *v > 0).map(|v| v.min(MAX_RESIZE_DIMENSION));
+    let (max_width, max_height) = (bound(max_width), bound(max_height));
+
+    let dims = match (width, height) {
+        (Some(w), Some(h)) if w > 0 && h > 0 => Some((w as f64, h as f64)),
+        _ => None,
+    };
+
+    match (max_width, max_height, dims) {
+        (None, None, _) => None,
+        // 尺寸未知时按给出的边界缩放
+        (Some(mw), _, None) => Some((mw, false)),
+        (None, Some(mh), None) => Some((mh, true)),
+        (mw, mh, Some((w, h))) => {
+            let scale_w = mw.map_or(f64::INFINITY, |mw| mw as f64 / w);
+            let scale_h = mh.map_or(f64::INFINITY, |mh| mh as f64 / h);
+            if scale_w.min(scale_h) >= 1.0 {
+                None
+            } else if scale_w <= scale_h {
+                mw.map(|mw| (mw, false))
+            } else {
+                mh.map(|mh| (mh, true))
+            }
+        }
+    }
+}
+
+/// Cache label for a resized original ("w1600" / "h1200")
+pub fn resized_label(target_size: u32, fit_to_height: bool) -> String {
+    format!("{}{}", if fit_to_height { 'h' } else { 'w' }, target_size)
+}
+
 /// Get file extension from file name
 fn get_file_extension(file_name: &str) -> String {
     file_name
@@ -228,3 +640,34 @@ fn get_mime_type_from_extension(ext: &str) -> String {
         _ => "application/octet-stream".to_string(),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fit_within() {
+        // 6000x4000 限制宽 1600：按宽度缩放
+        assert_eq!(fit_within(Some(6000), Some(4000), Some(1600), None), Some((1600, false)));
+        // 同时限制宽高时取更严格的一边
+        assert_eq!(fit_within(Some(6000), Some(4000), Some(3000), Some(1000)), Some((1000, true)));
+        assert_eq!(fit_within(Some(6000), Some(4000), Some(1000), Some(3000)), Some((1000, false)));
+        // 原图已在范围内：不缩放
+        assert_eq!(fit_within(Some(800), Some(600), Some(1600), Some(1600)), None);
+        // 未给出边界
+        assert_eq!(fit_within(Some(6000), Some(4000), None, Some(0)), None);
+    }
+
+    #[test]
+    fn test_fit_within_unknown_dimensions() {
+        assert_eq!(fit_within(None, None, Some(1600), None), Some((1600, false)));
+        assert_eq!(fit_within(None, Some(4000), None, Some(900)), Some((900, true)));
+        assert_eq!(fit_within(None, None, Some(100_000), None), Some((MAX_RESIZE_DIMENSION, false)));
+    }
+
+    #[test]
+    fn test_resized_label() {
+        assert_eq!(resized_label(1600, false), "w1600");
+        assert_eq!(resized_label(1200, true), "h1200");
+    }
+}
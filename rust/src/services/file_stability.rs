@@ -0,0 +1,104 @@
+//! 文件稳定性检查
+//! 新增/修改的文件在处理前确认大小与修改时间在一个时间窗口内不再变化，
+//! 避免复制中途的文件生成截断的缩略图和错误的元数据。
+
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+/// Size and mtime of a file at one point in time
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct FileSnapshot {
+    size: u64,
+    modified: Option<SystemTime>,
+}
+
+async fn snapshot(path: &Path) -> Option<FileSnapshot> {
+    let metadata = tokio::fs::metadata(path).await.ok()?;
+    Some(FileSnapshot {
+        size: metadata.len(),
+        modified: metadata.modified().ok(),
+    })
+}
+
+/// A file not modified for a whole window is stable without waiting
+pub fn is_settled(modified: Option<SystemTime>, now: SystemTime, window: Duration) -> bool {
+    match modified {
+        Some(modified) => now.duration_since(modified).is_ok_and(|age| age >= window),
+        // 无法获取修改时间时无法判断，按稳定处理
+        None => true,
+    }
+}
+
+/// Split files into (stable, unstable).
+/// Recently modified files are sampled, then re-checked once after `window`;
+/// files whose size or mtime changed (or that vanished) are unstable and should be retried later.
+/// Only waits when at least one file was modified within the window.
+pub async fn split_unstable(files: Vec<PathBuf>, window: Duration) -> (Vec<PathBuf>, Vec<PathBuf>) {
+    let now = SystemTime::now();
+    let mut stable = Vec::with_capacity(files.len());
+    let mut recent = Vec::new();
+
+    for path in files {
+        match snapshot(&path).await {
+            Some(snap) if !is_settled(snap.modified, now, window) => recent.push((path, snap)),
+            _ => stable.push(path),
+        }
+    }
+
+    if recent.is_empty() {
+        return (stable, Vec::new());
+    }
+
+    tracing::debug!("Waiting {:?} for {} recently modified files to settle", window, recent.len());
+    tokio::time::sleep(window).await;
+
+    let mut unstable = Vec::new();
+    for (path, before) in recent {
+        if snapshot(&path).await == Some(before) {
+            stable.push(path);
+        } else {
+            unstable.push(path);
+        }
+    }
+    (stable, unstable)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_settled() {
+        let now = SystemTime::now();
+        let window = Duration::from_secs(2);
+        assert!(is_settled(Some(now - Duration::from_secs(5)), now, window));
+        assert!(!is_settled(Some(now - Duration::from_secs(1)), now, window));
+        // 修改时间在未来（时钟偏差）视为不稳定
+        assert!(!is_settled(Some(now + Duration::from_secs(5)), now, window));
+        assert!(is_settled(None, now, window));
+    }
+
+    #[tokio::test]
+    async fn test_split_unstable_growing_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let settled = dir.path().join("settled.jpg");
+        let growing = dir.path().join("growing.jpg");
+        std::fs::write(&settled, b"done").unwrap();
+        std::fs::write(&growing, b"part").unwrap();
+
+        let writer = {
+            let growing = growing.clone();
+            tokio::spawn(async move {
+                tokio::time::sleep(Duration::from_millis(50)).await;
+                std::fs::write(&growing, b"partial-more-data").unwrap();
+            })
+        };
+
+        let (stable, unstable) =
+            split_unstable(vec![settled.clone(), growing.clone()], Duration::from_millis(300)).await;
+        writer.await.unwrap();
+
+        assert_eq!(stable, vec![settled]);
+        assert_eq!(unstable, vec![growing]);
+    }
+}
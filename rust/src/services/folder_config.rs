@@ -0,0 +1,83 @@
+//! 目录级配置
+//! 目录内放置 `.nomedia` 或 `.latteignore` 标记文件时，整个子树不参与扫描；
+//! 可选的 `.latte.json` 为目录设置显示名称和封面，扫描时读取并通过目录 API 返回。
+//!
+//! `.latte.json` 示例：`{"displayName": "2023 京都旅行", "cover": "IMG_0042.jpg"}`
+
+use serde::Deserialize;
+use std::path::Path;
+
+/// Marker files excluding the directory and everything below it from scanning
+pub const IGNORE_MARKERS: &[&str] = &[".nomedia", ".latteignore"];
+
+/// Per-folder config file name
+pub const FOLDER_CONFIG_FILE: &str = ".latte.json";
+
+/// Contents of a `.latte.json` folder config
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FolderConfig {
+    /// Name shown instead of the directory name
+    pub display_name: Option<String>,
+    /// Cover file, relative to the directory
+    pub cover: Option<String>,
+}
+
+/// Whether the directory contains an ignore marker file
+pub async fn has_ignore_marker(dir: &Path) -> bool {
+    for marker in IGNORE_MARKERS {
+        if tokio::fs::try_exists(dir.join(marker)).await.unwrap_or(false) {
+            return true;
+        }
+    }
+    false
+}
+
+/// Read the folder config of a directory; None when absent or invalid
+pub async fn read_folder_config(dir: &Path) -> Option<FolderConfig> {
+    let path = dir.join(FOLDER_CONFIG_FILE);
+    let content = tokio::fs::read(&path).await.ok()?;
+    match parse_folder_config(&content) {
+        Ok(config) => Some(config),
+        Err(e) => {
+            tracing::warn!("Invalid folder config {:?}: {}", path, e);
+            None
+        }
+    }
+}
+
+/// Parse `.latte.json`; empty values are treated as unset
+pub fn parse_folder_config(content: &[u8]) -> Result<FolderConfig, serde_json::Error> {
+    let config: FolderConfig = serde_json::from_slice(content)?;
+    let non_empty = |value: Option<String>| value.map(|v| v.trim().to_string()).filter(|v| !v.is_empty());
+    Ok(FolderConfig {
+        display_name: non_empty(config.display_name),
+        cover: non_empty(config.cover),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_folder_config() {
+        let config = parse_folder_config(br#"{"displayName": "Kyoto 2023", "cover": "IMG_0042.jpg", "extra": 1}"#).unwrap();
+        assert_eq!(config.display_name.as_deref(), Some("Kyoto 2023"));
+        assert_eq!(config.cover.as_deref(), Some("IMG_0042.jpg"));
+
+        let config = parse_folder_config(br#"{"displayName": "  "}"#).unwrap();
+        assert_eq!(config, FolderConfig::default());
+
+        assert!(parse_folder_config(b"not json").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_ignore_markers() {
+        let dir = tempfile::tempdir().unwrap();
+        assert!(!has_ignore_marker(dir.path()).await);
+
+        std::fs::write(dir.path().join(".latteignore"), b"").unwrap();
+        assert!(has_ignore_marker(dir.path()).await);
+    }
+}
@@ -0,0 +1,97 @@
+//! 扫描 I/O 带宽限制
+//! 令牌桶限速：扫描读取文件前先按读取量申请额度，超出速率时等待，
+//! 避免在机械硬盘 NAS 上全速扫描挤占其他服务的磁盘带宽。
+
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Bytes-per-second limiter shared by all scan workers
+#[derive(Debug)]
+pub struct IoThrottle {
+    bytes_per_second: u64,
+    state: Mutex<BucketState>,
+}
+
+#[derive(Debug)]
+struct BucketState {
+    /// Available bytes; negative while reads are borrowed ahead of the rate
+    available: f64,
+    last_refill: Instant,
+}
+
+impl IoThrottle {
+    /// Burst capacity is one second worth of bytes
+    pub fn new(bytes_per_second: u64) -> Self {
+        Self {
+            bytes_per_second: bytes_per_second.max(1),
+            state: Mutex::new(BucketState {
+                available: bytes_per_second as f64,
+                last_refill: Instant::now(),
+            }),
+        }
+    }
+
+    /// Reserve `bytes` and return how long the caller must wait before reading
+    pub fn reserve(&self, bytes: u64) -> Duration {
+        self.reserve_at(bytes, Instant::now())
+    }
+
+    fn reserve_at(&self, bytes: u64, now: Instant) -> Duration {
+        let rate = self.bytes_per_second as f64;
+        let mut state = self.state.lock().unwrap();
+
+        let elapsed = now.saturating_duration_since(state.last_refill).as_secs_f64();
+        state.available = (state.available + elapsed * rate).min(rate);
+        state.last_refill = now;
+
+        // 先扣除额度再等待，后续请求排在本次之后，整体速率不超过限制
+        state.available -= bytes as f64;
+        if state.available >= 0.0 {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64(-state.available / rate)
+        }
+    }
+
+    /// Wait until `bytes` may be read
+    pub async fn consume(&self, bytes: u64) {
+        let wait = self.reserve(bytes);
+        if !wait.is_zero() {
+            tokio::time::sleep(wait).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_burst_within_capacity() {
+        let throttle = IoThrottle::new(1000);
+        let now = Instant::now();
+        assert_eq!(throttle.reserve_at(600, now), Duration::ZERO);
+        assert_eq!(throttle.reserve_at(400, now), Duration::ZERO);
+    }
+
+    #[test]
+    fn test_waits_when_exceeding_rate() {
+        let throttle = IoThrottle::new(1000);
+        let now = Instant::now();
+        assert_eq!(throttle.reserve_at(1000, now), Duration::ZERO);
+        assert_eq!(throttle.reserve_at(500, now), Duration::from_millis(500));
+        // 排队在前一个请求之后
+        assert_eq!(throttle.reserve_at(500, now), Duration::from_secs(1));
+    }
+
+    #[test]
+    fn test_refills_over_time() {
+        let throttle = IoThrottle::new(1000);
+        let now = Instant::now();
+        throttle.reserve_at(1000, now);
+        assert_eq!(throttle.reserve_at(500, now + Duration::from_millis(500)), Duration::ZERO);
+        // 额度不会超过一秒的突发容量
+        let later = now + Duration::from_secs(10);
+        assert_eq!(throttle.reserve_at(1500, later), Duration::from_millis(500));
+    }
+}
@@ -0,0 +1,92 @@
+//! 照片目录写权限检测
+//! 启动时在 base_path 中创建并立即删除一个探测文件：只读挂载或没有写权限时，
+//! 所有在原始文件旁写入的功能都要关闭，能力标志通过 /api/system/info 告知前端隐藏相应操作。
+//! 与 disk_usage 一样不依赖平台相关的系统调用（statvfs 的只读标志）。
+
+use serde::Serialize;
+use std::path::Path;
+
+/// Name of the probe file created in base_path
+const PROBE_FILE: &str = ".latte-write-probe";
+
+/// What the server may do with the library folder
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LibraryCapabilities {
+    /// base_path is mounted read-only, not writable by the service user, or LATTE_READ_ONLY is set
+    pub read_only: bool,
+    /// Why the library is read-only (None when writable)
+    pub read_only_reason: Option<String>,
+    /// Features writing next to originals (sidecars, moves, renames) are available
+    pub modify_originals: bool,
+}
+
+impl LibraryCapabilities {
+    /// Writable library: every feature available
+    pub fn writable() -> Self {
+        Self {
+            read_only: false,
+            read_only_reason: None,
+            modify_originals: true,
+        }
+    }
+
+    /// Read-only library for the given reason
+    pub fn read_only(reason: impl Into<String>) -> Self {
+        Self {
+            read_only: true,
+            read_only_reason: Some(reason.into()),
+            modify_originals: false,
+        }
+    }
+
+    /// Probe base_path; `forced` (LATTE_READ_ONLY) skips the probe
+    pub fn detect(base_path: &Path, forced: bool) -> Self {
+        if forced {
+            return Self::read_only("LATTE_READ_ONLY");
+        }
+        match probe_writable(base_path) {
+            Ok(()) => Self::writable(),
+            Err(e) => Self::read_only(e.to_string()),
+        }
+    }
+}
+
+/// Create and remove a probe file in `dir`
+pub(crate) fn probe_writable(dir: &Path) -> std::io::Result<()> {
+    let probe = dir.join(PROBE_FILE);
+    std::fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(&probe)
+        .or_else(|e| match e.kind() {
+            // 上次进程在删除前退出留下的探测文件
+            std::io::ErrorKind::AlreadyExists => std::fs::OpenOptions::new().write(true).open(&probe),
+            _ => Err(e),
+        })?;
+    std::fs::remove_file(&probe)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_writable_dir() {
+        let dir = tempfile::tempdir().unwrap();
+        assert_eq!(LibraryCapabilities::detect(dir.path(), false), LibraryCapabilities::writable());
+        assert!(!dir.path().join(PROBE_FILE).exists());
+    }
+
+    #[test]
+    fn test_forced_and_missing_dir_are_read_only() {
+        let dir = tempfile::tempdir().unwrap();
+        let forced = LibraryCapabilities::detect(dir.path(), true);
+        assert!(forced.read_only);
+        assert!(!forced.modify_originals);
+
+        let missing = LibraryCapabilities::detect(&dir.path().join("missing"), false);
+        assert!(missing.read_only);
+        assert!(missing.read_only_reason.is_some());
+    }
+}
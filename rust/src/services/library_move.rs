@@ -0,0 +1,376 @@
+//! 在网页端移动/重命名原始文件与目录
+//! 目标路径相对于 base_path，校验后在磁盘上 rename，再更新数据库中的路径；id、元数据和用户编辑保持不变。
+//! 目录移动在一个事务中改写其下所有文件与子目录的路径前缀，不会像重新扫描那样先删除再重新入库。
+//! 缩略图缓存按内容哈希或 id 寻址，与路径无关，移动后继续命中。
+//! 文件连同配对的 RAW 与 Takeout 元数据一起移动；目标已存在时不会被覆盖（renameat2 RENAME_NOREPLACE）。
+//! 数据库更新失败时把文件移回原处，库与磁盘不会出现不一致。
+
+use crate::db::{DatabasePool, DirectoryEntry, MediaFile, MediaFileRepository};
+use crate::processors::{filename_date, takeout};
+use crate::services::scan_filter::ScanFilter;
+use crate::utils::library_path::LibraryRoots;
+use std::io::ErrorKind;
+use std::path::{Component, Path, PathBuf};
+use thiserror::Error;
+use tokio::fs;
+
+#[derive(Debug, Error)]
+pub enum MoveError {
+    #[error("Source not found: {0}")]
+    SourceNotFound(String),
+
+    /// Target is not a plain relative path inside base_path, or is excluded from scanning
+    #[error("Invalid target path: {0}")]
+    InvalidTarget(String),
+
+    #[error("Target already exists: {0}")]
+    TargetExists(String),
+
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("Database error: {0}")]
+    Database(#[from] sqlx::Error),
+}
+
+/// Absolute target of a move: `relative` joined onto `base`. Rejects absolute paths, `..`,
+/// names the scan would ignore (the file would vanish on the next scan) and, for files,
+/// a changed extension (it selects the processor).
+pub fn resolve_target(
+    base: &Path,
+    relative: &str,
+    filter: &ScanFilter,
+    keep_extension_of: Option<&Path>,
+) -> Result<PathBuf, MoveError> {
+    let relative_path = Path::new(relative.trim());
+    let mut target = base.to_path_buf();
+    for component in relative_path.components() {
+        match component {
+            Component::Normal(name) => {
+                if filter.is_ignored_name(Path::new(name)) {
+                    return Err(MoveError::InvalidTarget(relative.to_string()));
+                }
+                target.push(name);
+            }
+            Component::CurDir => {}
+            _ => return Err(MoveError::InvalidTarget(relative.to_string())),
+        }
+    }
+    if target == base {
+        return Err(MoveError::InvalidTarget(relative.to_string()));
+    }
+
+    if let Some(source) = keep_extension_of {
+        let extension = |path: &Path| path.extension().map(|e| e.to_string_lossy().to_lowercase());
+        if extension(source) != extension(&target) {
+            return Err(MoveError::InvalidTarget(relative.to_string()));
+        }
+    }
+    Ok(target)
+}
+
+/// Move `file` to `target` on disk, together with its paired RAW file and Takeout sidecar,
+/// and store the new location. Returns the updated row.
+pub async fn move_file(
+    db: &DatabasePool,
+    roots: &LibraryRoots,
+    file: &MediaFile,
+    target: &Path,
+) -> Result<MediaFile, MoveError> {
+    let source = PathBuf::from(&file.file_path);
+    // 目标目录可能经符号链接指向库外
+    if !roots.contains(target).await {
+        return Err(MoveError::InvalidTarget(target.to_string_lossy().to_string()));
+    }
+    if let Some(parent) = target.parent() {
+        fs::create_dir_all(parent).await?;
+    }
+
+    // 随主文件移动的文件 (原路径, 新路径)：RAW 改用新文件名保持配对，元数据按 Takeout 命名规则改名
+    let raw = match file.raw_path.as_ref().map(PathBuf::from) {
+        Some(raw) if fs::try_exists(&raw).await? => {
+            let raw_target = match raw.extension() {
+                Some(extension) => target.with_extension(extension),
+                None => target.to_path_buf(),
+            };
+            Some((raw, raw_target))
+        }
+        _ => None,
+    };
+    let sidecar = takeout::find_sidecar(&source).map(|sidecar| {
+        let sidecar_target = sidecar_target(&source, &sidecar, target);
+        (sidecar, sidecar_target)
+    });
+
+    rename_no_clobber(&source, target).await?;
+    let mut moved_paths = vec![(source.clone(), target.to_path_buf())];
+    for (from, to) in raw.iter().chain(sidecar.iter()) {
+        if let Err(e) = rename_no_clobber(from, to).await {
+            move_back(&moved_paths).await;
+            return Err(e);
+        }
+        moved_paths.push((from.clone(), to.clone()));
+    }
+
+    let mut moved = file.clone();
+    moved.file_path = target.to_string_lossy().to_string();
+    moved.file_name = target
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default();
+    moved.filename_timestamp = filename_date::parse_filename_date(&moved.file_name);
+    moved.date_source = moved
+        .get_effective_sort_time_with_source()
+        .map(|(_, source)| source.as_str().to_string());
+    moved.raw_path = raw.map(|(_, raw_target)| raw_target.to_string_lossy().to_string());
+
+    let repo = MediaFileRepository::new(db);
+    if let Err(e) = repo.update_location(&moved).await {
+        // 数据库未更新：把文件移回原处
+        move_back(&moved_paths).await;
+        return Err(e.into());
+    }
+
+    tracing::info!("Moved {} -> {}", file.file_path, moved.file_path);
+    Ok(moved)
+}
+
+/// New path of the Takeout sidecar `sidecar` of `source` when `source` moves to `target`:
+/// the part of the sidecar name after the media file name (or stem) is kept
+fn sidecar_target(source: &Path, sidecar: &Path, target: &Path) -> PathBuf {
+    let name = |path: &Path| path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+    let stem = |path: &Path| path.file_stem().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+    let sidecar_name = name(sidecar);
+    let new_name = if let Some(suffix) = sidecar_name.strip_prefix(&name(source)) {
+        format!("{}{}", name(target), suffix)
+    } else if let Some(suffix) = sidecar_name.strip_prefix(&stem(source)) {
+        format!("{}{}", stem(target), suffix)
+    } else {
+        format!("{}.json", name(target))
+    };
+    target.with_file_name(new_name)
+}
+
+/// Undo renames done so far (`(from, to)` pairs, in order)
+async fn move_back(moved_paths: &[(PathBuf, PathBuf)]) {
+    for (from, to) in moved_paths.iter().rev() {
+        if let Err(restore) = rename_no_clobber(to, from).await {
+            tracing::error!("Failed to move {:?} back to {:?}: {}", to, from, restore);
+        }
+    }
+}
+
+/// Rename `from` to `to`, failing with TargetExists instead of replacing an existing `to`
+async fn rename_no_clobber(from: &Path, to: &Path) -> Result<(), MoveError> {
+    let (from, to) = (from.to_path_buf(), to.to_path_buf());
+    let target = to.to_string_lossy().to_string();
+    match tokio::task::spawn_blocking(move || rename_noreplace(&from, &to)).await {
+        Ok(Ok(())) => Ok(()),
+        Ok(Err(e)) if e.kind() == ErrorKind::AlreadyExists => Err(MoveError::TargetExists(target)),
+        Ok(Err(e)) => Err(e.into()),
+        Err(e) => Err(std::io::Error::other(e).into()),
+    }
+}
+
+/// rename(2) that never replaces `to`: renameat2 with RENAME_NOREPLACE, or a hard link plus
+/// removing `from` where that is unsupported. Fails with AlreadyExists when `to` exists.
+fn rename_noreplace(from: &Path, to: &Path) -> std::io::Result<()> {
+    #[cfg(target_os = "linux")]
+    {
+        use std::ffi::CString;
+        use std::os::unix::ffi::OsStrExt;
+
+        let c_from = CString::new(from.as_os_str().as_bytes())?;
+        let c_to = CString::new(to.as_os_str().as_bytes())?;
+        let result = unsafe {
+            libc::syscall(
+                libc::SYS_renameat2,
+                libc::AT_FDCWD,
+                c_from.as_ptr(),
+                libc::AT_FDCWD,
+                c_to.as_ptr(),
+                libc::RENAME_NOREPLACE,
+            )
+        };
+        if result == 0 {
+            return Ok(());
+        }
+        let error = std::io::Error::last_os_error();
+        // 文件系统或内核不支持 RENAME_NOREPLACE 时退回下面的做法
+        if !matches!(error.raw_os_error(), Some(libc::EINVAL) | Some(libc::ENOSYS)) {
+            return Err(error);
+        }
+    }
+
+    // 硬链接在目标存在时失败；目录不能硬链接，只能先检查再 rename
+    if std::fs::symlink_metadata(from)?.is_dir() {
+        if std::fs::symlink_metadata(to).is_ok() {
+            return Err(ErrorKind::AlreadyExists.into());
+        }
+        return std::fs::rename(from, to);
+    }
+    std::fs::hard_link(from, to)?;
+    if let Err(e) = std::fs::remove_file(from) {
+        let _ = std::fs::remove_file(to);
+        return Err(e);
+    }
+    Ok(())
+}
+
+/// Move the directory `source` to `target` on disk and rewrite the stored paths of everything
+/// below it in one transaction, together with the directory rows of `target` and the parents
+/// created for it up to `base`. Returns the number of media files whose path changed.
+pub async fn move_directory(
+    db: &DatabasePool,
+    roots: &LibraryRoots,
+    base: &Path,
+    source: &Path,
+    target: &Path,
+) -> Result<u64, MoveError> {
+    if !fs::metadata(source).await.map(|m| m.is_dir()).unwrap_or(false) {
+        return Err(MoveError::SourceNotFound(source.to_string_lossy().to_string()));
+    }
+    // 不能移动到自身之下，也不能经符号链接移出库外
+    if target.starts_with(source) || !roots.contains(source).await || !roots.contains(target).await {
+        return Err(MoveError::InvalidTarget(target.to_string_lossy().to_string()));
+    }
+    if let Some(parent) = target.parent() {
+        fs::create_dir_all(parent).await?;
+    }
+    rename_no_clobber(source, target).await?;
+
+    // 目标及其上级目录（直到 base）的目录记录，与扫描写入的一致
+    let directories: Vec<DirectoryEntry> = target
+        .ancestors()
+        .take_while(|dir| dir.starts_with(base))
+        .map(|dir| DirectoryEntry {
+            path: dir.to_string_lossy().to_string(),
+            parent_path: dir.parent().map(|p| p.to_string_lossy().to_string()),
+            name: dir.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default(),
+            display_name: None,
+            cover_path: None,
+        })
+        .collect();
+
+    let repo = MediaFileRepository::new(db);
+    match repo
+        .move_directory(&source.to_string_lossy(), &target.to_string_lossy(), &directories)
+        .await
+    {
+        Ok(moved) => Ok(moved),
+        Err(e) => {
+            if let Err(restore) = rename_no_clobber(target, source).await {
+                tracing::error!("Failed to move {:?} back to {:?}: {}", target, source, restore);
+            }
+            Err(e.into())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn filter() -> ScanFilter {
+        ScanFilter::new(1, &[".*".to_string(), "*.tmp".to_string()])
+    }
+
+    #[test]
+    fn test_resolve_target() {
+        let base = Path::new("/photos");
+        let source = Path::new("/photos/a/IMG_1.JPG");
+        assert_eq!(
+            resolve_target(base, "2024/trip/IMG_1.jpg", &filter(), Some(source)).unwrap(),
+            PathBuf::from("/photos/2024/trip/IMG_1.jpg")
+        );
+        assert_eq!(
+            resolve_target(base, "./2024", &filter(), None).unwrap(),
+            PathBuf::from("/photos/2024")
+        );
+    }
+
+    #[test]
+    fn test_resolve_target_rejects_escapes_and_ignored_names() {
+        let base = Path::new("/photos");
+        for target in ["../etc/x.jpg", "/tmp/x.jpg", "a/../../x.jpg", "", ".", ".hidden/x.jpg", "a/x.jpg.tmp"] {
+            assert!(
+                matches!(resolve_target(base, target, &filter(), None), Err(MoveError::InvalidTarget(_))),
+                "{target}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_resolve_target_keeps_extension() {
+        let source = Path::new("/photos/IMG_1.heic");
+        assert!(resolve_target(Path::new("/photos"), "IMG_1.jpg", &filter(), Some(source)).is_err());
+        assert!(resolve_target(Path::new("/photos"), "IMG_1.HEIC", &filter(), Some(source)).is_ok());
+    }
+
+    #[test]
+    fn test_sidecar_target() {
+        let target = Path::new("/photos/2024/trip.jpg");
+        let sidecar_of = |sidecar: &str| sidecar_target(Path::new("/photos/IMG_1.jpg"), Path::new(sidecar), target);
+        assert_eq!(sidecar_of("/photos/IMG_1.jpg.json"), PathBuf::from("/photos/2024/trip.jpg.json"));
+        assert_eq!(
+            sidecar_of("/photos/IMG_1.jpg.supplemental-metadata.json"),
+            PathBuf::from("/photos/2024/trip.jpg.supplemental-metadata.json")
+        );
+        assert_eq!(sidecar_of("/photos/IMG_1.json"), PathBuf::from("/photos/2024/trip.json"));
+    }
+
+    #[tokio::test]
+    async fn test_move_file_with_raw_and_sidecar() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = DatabasePool::new(&dir.path().join("test.db")).await.unwrap();
+        let library = dir.path().join("photos");
+        std::fs::create_dir_all(&library).unwrap();
+        for name in ["IMG_1.jpg", "IMG_1.CR3", "IMG_1.jpg.json", "taken.jpg"] {
+            std::fs::write(library.join(name), name).unwrap();
+        }
+        let mut file = MediaFile::new(
+            library.join("IMG_1.jpg").to_string_lossy().to_string(),
+            "IMG_1.jpg".to_string(),
+            "image".to_string(),
+        );
+        file.raw_path = Some(library.join("IMG_1.CR3").to_string_lossy().to_string());
+        MediaFileRepository::new(&db).upsert(&file).await.unwrap();
+        let roots = LibraryRoots::new([library.clone()]);
+
+        // 已存在的目标不会被覆盖
+        let result = move_file(&db, &roots, &file, &library.join("taken.jpg")).await;
+        assert!(matches!(result, Err(MoveError::TargetExists(_))));
+        assert_eq!(std::fs::read(library.join("taken.jpg")).unwrap(), b"taken.jpg");
+        assert!(library.join("IMG_1.jpg").is_file());
+
+        let target = library.join("2024/trip.jpg");
+        let moved = move_file(&db, &roots, &file, &target).await.unwrap();
+        assert_eq!(std::fs::read(&target).unwrap(), b"IMG_1.jpg");
+        assert_eq!(std::fs::read(library.join("2024/trip.CR3")).unwrap(), b"IMG_1.CR3");
+        assert_eq!(std::fs::read(library.join("2024/trip.jpg.json")).unwrap(), b"IMG_1.jpg.json");
+        assert!(!library.join("IMG_1.CR3").exists());
+        let stored = MediaFileRepository::new(&db).find_by_id(&file.id).await.unwrap().unwrap();
+        assert_eq!(stored.raw_path, moved.raw_path);
+        assert_eq!(stored.raw_path.as_deref(), Some(library.join("2024/trip.CR3").to_string_lossy().as_ref()));
+
+        // 库外的目标被拒绝
+        let outside = dir.path().join("elsewhere.jpg");
+        let result = move_file(&db, &roots, &moved, &outside).await;
+        assert!(matches!(result, Err(MoveError::InvalidTarget(_))));
+    }
+
+    #[tokio::test]
+    async fn test_move_directory_rejects_nested_target() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = DatabasePool::new(&dir.path().join("test.db")).await.unwrap();
+        let source = dir.path().join("2023");
+        std::fs::create_dir_all(&source).unwrap();
+
+        let roots = LibraryRoots::new([dir.path().to_path_buf()]);
+        let result = move_directory(&db, &roots, dir.path(), &source, &source.join("inner")).await;
+        assert!(matches!(result, Err(MoveError::InvalidTarget(_))));
+        let result = move_directory(&db, &roots, dir.path(), &dir.path().join("missing"), &dir.path().join("new")).await;
+        assert!(matches!(result, Err(MoveError::SourceNotFound(_))));
+        assert!(source.is_dir());
+    }
+}
@@ -3,9 +3,46 @@ pub mod scan_service;
 pub mod cache_service;
 pub mod scheduler;
 pub mod transcoding_pool;
+pub mod thumbnail_queue;
+pub mod raw_pairing;
+pub mod scan_filter;
+pub mod folder_config;
+pub mod quiet_hours;
+pub mod io_throttle;
+pub mod file_stability;
+pub mod remote_library;
+pub mod tombstone;
+pub mod tagging;
+pub mod semantic_search;
+pub mod dependency_check;
+pub mod disk_usage;
+pub mod disk_guard;
+pub mod library_access;
+pub mod scan_report;
+pub mod webhooks;
+pub mod scan_summary;
+pub mod static_export;
+pub mod consistency;
+pub mod library_move;
+pub mod background_priority;
+pub mod change_detection;
+pub mod config_check;
+pub mod config_reload;
+pub mod thumbnail_metrics;
 
 pub use file_service::FileService;
-pub use scan_service::ScanService;
+pub use scan_service::{RescanError, ScanMode, ScanService};
 pub use cache_service::CacheService;
+pub use config_reload::ConfigReloader;
 pub use scheduler::Scheduler;
 pub use transcoding_pool::TranscodingPool;
+pub use thumbnail_queue::ThumbnailQueue;
+pub use tombstone::TombstoneChecker;
+pub use quiet_hours::QuietHours;
+pub use tagging::TaggingService;
+pub use dependency_check::DependencyStatus;
+pub use library_access::LibraryCapabilities;
+pub use webhooks::{WebhookEvent, WebhookNotifier};
+pub use scan_summary::ScanSummaryNotifier;
+pub use static_export::StaticExporter;
+pub use thumbnail_metrics::ThumbnailMetrics;
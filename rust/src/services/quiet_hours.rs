@@ -0,0 +1,114 @@
+//! 静默时段限速
+//! 在配置的时段内（例如白天 08:00-23:00）扫描与缩略图预生成以较低并发运行或暂停，
+//! 其余时间全速运行，避免耗时较长的夜间扫描拖到白天时拖慢 NAS。
+
+use chrono::{Local, NaiveTime};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+/// Longest single sleep while paused, so a changed clock is noticed
+const MAX_PAUSE_SLEEP: Duration = Duration::from_secs(60);
+
+/// Time window with reduced background concurrency
+#[derive(Debug)]
+pub struct QuietHours {
+    start: NaiveTime,
+    end: NaiveTime,
+    /// Number of concurrent jobs within the window; 0 pauses jobs until the window ends
+    concurrency: usize,
+    permits: Arc<Semaphore>,
+}
+
+impl QuietHours {
+    pub fn new(start: NaiveTime, end: NaiveTime, concurrency: usize) -> Self {
+        Self {
+            start,
+            end,
+            concurrency,
+            permits: Arc::new(Semaphore::new(concurrency.max(1))),
+        }
+    }
+
+    /// Parse a window like "08:00-23:00"; windows may wrap past midnight ("22:00-07:00")
+    pub fn parse_window(value: &str) -> Option<(NaiveTime, NaiveTime)> {
+        let (start, end) = value.split_once('-')?;
+        let start = NaiveTime::parse_from_str(start.trim(), "%H:%M").ok()?;
+        let end = NaiveTime::parse_from_str(end.trim(), "%H:%M").ok()?;
+        (start != end).then_some((start, end))
+    }
+
+    /// Whether `time` is inside the window (start inclusive, end exclusive)
+    pub fn contains(&self, time: NaiveTime) -> bool {
+        if self.start < self.end {
+            time >= self.start && time < self.end
+        } else {
+            time >= self.start || time < self.end
+        }
+    }
+
+    /// Time from `time` until the window ends
+    fn until_end(&self, time: NaiveTime) -> Duration {
+        let seconds = (self.end - time).num_seconds().rem_euclid(24 * 3600);
+        Duration::from_secs(seconds as u64)
+    }
+
+    /// Wait until a job may run. Inside the window this returns a permit limiting
+    /// concurrency (or waits out the window when paused); outside it returns None immediately.
+    pub async fn acquire(&self) -> Option<OwnedSemaphorePermit> {
+        loop {
+            let now = Local::now().time();
+            if !self.contains(now) {
+                return None;
+            }
+            if self.concurrency > 0 {
+                return self.permits.clone().acquire_owned().await.ok();
+            }
+            let wait = self.until_end(now).clamp(Duration::from_secs(1), MAX_PAUSE_SLEEP);
+            tokio::time::sleep(wait).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn time(h: u32, m: u32) -> NaiveTime {
+        NaiveTime::from_hms_opt(h, m, 0).unwrap()
+    }
+
+    #[test]
+    fn test_parse_window() {
+        assert_eq!(QuietHours::parse_window("08:00-23:00"), Some((time(8, 0), time(23, 0))));
+        assert_eq!(QuietHours::parse_window(" 22:30 - 07:00 "), Some((time(22, 30), time(7, 0))));
+        assert_eq!(QuietHours::parse_window("08:00-08:00"), None);
+        assert_eq!(QuietHours::parse_window("8am-11pm"), None);
+        assert_eq!(QuietHours::parse_window(""), None);
+    }
+
+    #[test]
+    fn test_contains_daytime_window() {
+        let quiet = QuietHours::new(time(8, 0), time(23, 0), 1);
+        assert!(quiet.contains(time(8, 0)));
+        assert!(quiet.contains(time(12, 0)));
+        assert!(!quiet.contains(time(23, 0)));
+        assert!(!quiet.contains(time(3, 0)));
+    }
+
+    #[test]
+    fn test_contains_wrapping_window() {
+        let quiet = QuietHours::new(time(22, 0), time(7, 0), 1);
+        assert!(quiet.contains(time(23, 30)));
+        assert!(quiet.contains(time(6, 59)));
+        assert!(!quiet.contains(time(7, 0)));
+        assert!(!quiet.contains(time(12, 0)));
+    }
+
+    #[test]
+    fn test_until_end() {
+        let quiet = QuietHours::new(time(22, 0), time(7, 0), 0);
+        assert_eq!(quiet.until_end(time(23, 0)), Duration::from_secs(8 * 3600));
+        assert_eq!(quiet.until_end(time(6, 0)), Duration::from_secs(3600));
+    }
+}
@@ -0,0 +1,94 @@
+//! RAW+JPEG 配对
+//! 相机同时输出 RAW 与 JPEG 时，以 JPEG 作为展示文件，RAW 作为其备用版本关联
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// Camera RAW file extensions recognised for pairing
+pub const RAW_EXTENSIONS: &[&str] = &[
+    "dng", "cr2", "cr3", "nef", "nrw", "arw", "srf", "sr2", "raf", "orf", "rw2", "pef", "srw",
+];
+
+/// Display formats a RAW file can be paired with
+const DISPLAY_EXTENSIONS: &[&str] = &["jpg", "jpeg", "heic", "heif"];
+
+/// Check if a path has a RAW extension
+pub fn is_raw_file(path: &Path) -> bool {
+    has_extension(path, RAW_EXTENSIONS)
+}
+
+/// Pair RAW files with display files of the same shot (same directory, same file stem).
+/// Returns (display_path, raw_path) pairs; RAW files without a matching display file are ignored.
+pub fn pair_raw_files(files: &[PathBuf], raw_files: &[PathBuf]) -> Vec<(PathBuf, PathBuf)> {
+    let raw_by_key: HashMap<(PathBuf, String), &PathBuf> = raw_files
+        .iter()
+        .filter_map(|raw| pairing_key(raw).map(|key| (key, raw)))
+        .collect();
+
+    files
+        .iter()
+        .filter(|path| has_extension(path, DISPLAY_EXTENSIONS))
+        .filter_map(|path| {
+            let key = pairing_key(path)?;
+            raw_by_key.get(&key).map(|raw| (path.clone(), (*raw).clone()))
+        })
+        .collect()
+}
+
+/// (parent directory, lowercase file stem)
+fn pairing_key(path: &Path) -> Option<(PathBuf, String)> {
+    let parent = path.parent()?.to_path_buf();
+    let stem = path.file_stem()?.to_str()?.to_lowercase();
+    Some((parent, stem))
+}
+
+fn has_extension(path: &Path, extensions: &[&str]) -> bool {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .map(|e| extensions.contains(&e.to_lowercase().as_str()))
+        .unwrap_or(false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_raw_file() {
+        assert!(is_raw_file(Path::new("/photos/DSC_0001.NEF")));
+        assert!(is_raw_file(Path::new("/photos/IMG_0001.cr3")));
+        assert!(!is_raw_file(Path::new("/photos/IMG_0001.jpg")));
+        assert!(!is_raw_file(Path::new("/photos/noext")));
+    }
+
+    #[test]
+    fn test_pair_raw_files_same_stem() {
+        let files = vec![
+            PathBuf::from("/photos/a/DSC_0001.JPG"),
+            PathBuf::from("/photos/a/DSC_0002.jpg"),
+        ];
+        let raws = vec![PathBuf::from("/photos/a/DSC_0001.ARW")];
+
+        let pairs = pair_raw_files(&files, &raws);
+        assert_eq!(pairs, vec![(
+            PathBuf::from("/photos/a/DSC_0001.JPG"),
+            PathBuf::from("/photos/a/DSC_0001.ARW"),
+        )]);
+    }
+
+    #[test]
+    fn test_pair_raw_files_different_directory() {
+        let files = vec![PathBuf::from("/photos/a/DSC_0001.jpg")];
+        let raws = vec![PathBuf::from("/photos/b/DSC_0001.nef")];
+
+        assert!(pair_raw_files(&files, &raws).is_empty());
+    }
+
+    #[test]
+    fn test_pair_raw_files_ignores_videos() {
+        let files = vec![PathBuf::from("/photos/a/DSC_0001.mp4")];
+        let raws = vec![PathBuf::from("/photos/a/DSC_0001.dng")];
+
+        assert!(pair_raw_files(&files, &raws).is_empty());
+    }
+}
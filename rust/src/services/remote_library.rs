@@ -0,0 +1,130 @@
+//! 远程图库（联邦）代理
+//! 将另一台 LatteAlbum 实例的图库只读合并进本机的列表/时间线，
+//! 远程文件的 id 加上 `remote:` 前缀，缩略图与原图请求由本机转发。
+
+use std::time::Duration;
+use thiserror::Error;
+
+/// Prefix marking a file id that belongs to the remote library
+pub const REMOTE_ID_PREFIX: &str = "remote:";
+
+/// Header sent on requests to the remote so it doesn't merge its own remote again
+pub const FEDERATED_HEADER: &str = "x-latte-federated";
+
+/// Timeout for JSON requests (list/dates/detail); media proxying is not limited
+const JSON_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Error)]
+pub enum RemoteLibraryError {
+    #[error("Remote request failed: {0}")]
+    Request(#[from] reqwest::Error),
+
+    #[error("Remote returned status {0}")]
+    Status(reqwest::StatusCode),
+}
+
+/// Client for a secondary LatteAlbum instance
+pub struct RemoteLibrary {
+    base_url: String,
+    name: String,
+    client: reqwest::Client,
+}
+
+impl RemoteLibrary {
+    /// # Arguments
+    ///
+    /// * `base_url` - Remote instance root, e.g. "http://nas2:8080"
+    /// * `name` - Library name shown as the badge on remote items
+    pub fn new(base_url: &str, name: &str) -> Self {
+        Self {
+            base_url: base_url.trim_end_matches('/').to_string(),
+            name: name.to_string(),
+            client: reqwest::Client::new(),
+        }
+    }
+
+    /// Library badge name
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Strip the remote prefix from a file id, None for local ids
+    pub fn remote_id(id: &str) -> Option<&str> {
+        id.strip_prefix(REMOTE_ID_PREFIX)
+    }
+
+    /// GET a JSON API path (with query string) from the remote
+    pub async fn get_json(&self, path_and_query: &str) -> Result<serde_json::Value, RemoteLibraryError> {
+        let response = self
+            .client
+            .get(format!("{}{}", self.base_url, path_and_query))
+            .header(FEDERATED_HEADER, "1")
+            .timeout(JSON_TIMEOUT)
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            return Err(RemoteLibraryError::Status(response.status()));
+        }
+        Ok(response.json().await?)
+    }
+
+    /// Forward a media request (thumbnail/original) and return the raw response for streaming
+    pub async fn proxy(
+        &self,
+        method: reqwest::Method,
+        path_and_query: &str,
+        range: Option<&str>,
+    ) -> Result<reqwest::Response, RemoteLibraryError> {
+        let mut request = self
+            .client
+            .request(method, format!("{}{}", self.base_url, path_and_query))
+            .header(FEDERATED_HEADER, "1");
+        if let Some(range) = range {
+            request = request.header("range", range);
+        }
+        Ok(request.send().await?)
+    }
+
+    /// Rewrite a remote file JSON object for the merged view: prefix its id and add the library badge
+    pub fn tag_item(&self, mut item: serde_json::Value) -> serde_json::Value {
+        if let Some(obj) = item.as_object_mut() {
+            if let Some(id) = obj.get("id").and_then(|v| v.as_str()).map(str::to_string) {
+                let tagged = format!("{}{}", REMOTE_ID_PREFIX, id);
+                // 缩略图地址指向本机的代理路径
+                if let Some(url) = obj.get("thumbnailUrl").and_then(|v| v.as_str()) {
+                    let url = url.replacen(&format!("/api/files/{}/", id), &format!("/api/files/{}/", tagged), 1);
+                    obj.insert("thumbnailUrl".to_string(), serde_json::Value::String(url));
+                }
+                obj.insert("id".to_string(), serde_json::Value::String(tagged));
+            }
+            obj.insert("library".to_string(), serde_json::Value::String(self.name.clone()));
+        }
+        item
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_remote_id() {
+        assert_eq!(RemoteLibrary::remote_id("remote:abc"), Some("abc"));
+        assert_eq!(RemoteLibrary::remote_id("abc"), None);
+    }
+
+    #[test]
+    fn test_tag_item() {
+        let remote = RemoteLibrary::new("http://nas2:8080/", "nas2");
+        let item = remote.tag_item(serde_json::json!({ "id": "abc", "width": 10 }));
+
+        assert_eq!(item["id"], "remote:abc");
+        assert_eq!(item["library"], "nas2");
+        assert_eq!(item["width"], 10);
+        assert_eq!(remote.base_url, "http://nas2:8080");
+
+        let item = remote.tag_item(serde_json::json!({ "id": "abc", "thumbnailUrl": "/api/files/abc/thumbnail?v=01" }));
+        assert_eq!(item["thumbnailUrl"], "/api/files/remote:abc/thumbnail?v=01");
+    }
+}
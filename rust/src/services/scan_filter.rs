@@ -0,0 +1,113 @@
+//! 扫描排除规则
+//! 跳过过小的文件与临时文件（复制/下载中的半成品、Office 锁文件、隐藏文件），
+//! 避免扫描到尚未写完的文件。被跳过的文件不入库，写完（改名/长到正常大小）后下一次扫描自然会收录。
+
+use std::path::Path;
+
+/// Default file name patterns ignored during scan (`*` matches any sequence)
+pub const DEFAULT_IGNORE_PATTERNS: &[&str] = &["*.tmp", "*.partial", "*.part", "~$*", ".*"];
+
+/// File/directory exclusion rules applied while collecting files
+#[derive(Debug, Clone)]
+pub struct ScanFilter {
+    min_file_size: u64,
+    patterns: Vec<String>,
+}
+
+impl ScanFilter {
+    /// # Arguments
+    ///
+    /// * `min_file_size` - Files smaller than this many bytes are skipped
+    /// * `patterns` - File name patterns to skip, matched case-insensitively
+    pub fn new(min_file_size: u64, patterns: &[String]) -> Self {
+        Self {
+            min_file_size,
+            patterns: patterns.iter().map(|p| p.to_lowercase()).collect(),
+        }
+    }
+
+    /// Whether the name matches an ignore pattern (applies to files and directories)
+    pub fn is_ignored_name(&self, path: &Path) -> bool {
+        let Some(name) = path.file_name().and_then(|n| n.to_str()) else {
+            return false;
+        };
+        let name = name.to_lowercase();
+        self.patterns.iter().any(|pattern| wildcard_match(pattern, &name))
+    }
+
+    /// Whether a file should be skipped, given its size in bytes
+    pub fn should_skip_file(&self, path: &Path, size: u64) -> bool {
+        size < self.min_file_size || self.is_ignored_name(path)
+    }
+}
+
+/// Match `name` against a pattern where `*` matches any (possibly empty) sequence
+fn wildcard_match(pattern: &str, name: &str) -> bool {
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or_default();
+    let Some(mut rest) = name.strip_prefix(first) else {
+        return false;
+    };
+
+    let parts: Vec<&str> = parts.collect();
+    let Some((last, middle)) = parts.split_last() else {
+        // 不含 '*'：完全匹配
+        return rest.is_empty();
+    };
+
+    for part in middle {
+        match rest.find(part) {
+            Some(index) => rest = &rest[index + part.len()..],
+            None => return false,
+        }
+    }
+    rest.ends_with(last)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn default_filter() -> ScanFilter {
+        let patterns: Vec<String> = DEFAULT_IGNORE_PATTERNS.iter().map(|p| p.to_string()).collect();
+        ScanFilter::new(1, &patterns)
+    }
+
+    #[test]
+    fn test_wildcard_match() {
+        assert!(wildcard_match("*.tmp", "photo.tmp"));
+        assert!(wildcard_match("~$*", "~$report.jpg"));
+        assert!(wildcard_match(".*", ".ds_store"));
+        assert!(wildcard_match("*.jpg.*", "a.jpg.download"));
+        assert!(wildcard_match("thumbs.db", "thumbs.db"));
+        assert!(!wildcard_match("thumbs.db", "thumbs.db2"));
+        assert!(!wildcard_match("*.tmp", "photo.jpg"));
+        assert!(!wildcard_match("~$*", "photo~$.jpg"));
+    }
+
+    #[test]
+    fn test_default_patterns() {
+        let filter = default_filter();
+        assert!(filter.is_ignored_name(Path::new("/photos/IMG_0001.JPG.partial")));
+        assert!(filter.is_ignored_name(Path::new("/photos/~$IMG_0001.jpg")));
+        assert!(filter.is_ignored_name(Path::new("/photos/.IMG_0001.jpg.abc123")));
+        assert!(filter.is_ignored_name(Path::new("/photos/.thumbnails")));
+        assert!(filter.is_ignored_name(Path::new("/photos/COPY.TMP")));
+        assert!(!filter.is_ignored_name(Path::new("/photos/IMG_0001.jpg")));
+    }
+
+    #[test]
+    fn test_should_skip_file_size() {
+        let filter = ScanFilter::new(1024, &[]);
+        assert!(filter.should_skip_file(Path::new("a.jpg"), 0));
+        assert!(filter.should_skip_file(Path::new("a.jpg"), 1023));
+        assert!(!filter.should_skip_file(Path::new("a.jpg"), 1024));
+    }
+
+    #[test]
+    fn test_empty_file_skipped_by_default() {
+        let filter = default_filter();
+        assert!(filter.should_skip_file(Path::new("a.jpg"), 0));
+        assert!(!filter.should_skip_file(Path::new("a.jpg"), 1));
+    }
+}
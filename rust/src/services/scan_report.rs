@@ -0,0 +1,259 @@
+//! 扫描报告
+//! 记录最近一次扫描中每个文件的处理耗时，汇总出最慢的文件、文件最多的目录
+//! 以及按扩展名统计的失败原因，便于找出拖慢扫描的异常文件（GET /api/scan/report）。
+//! 耗时在写入时逐个汇总进 TimingSummary，内存只与目录数相关，不随处理的文件数增长。
+
+use crate::services::ScanMode;
+use serde::Serialize;
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// Number of slowest files kept in the report
+const SLOWEST_FILES_LIMIT: usize = 20;
+/// Number of directories kept in the report
+const DIRECTORIES_LIMIT: usize = 20;
+
+/// Processing time of one file during a scan
+#[derive(Debug, Clone)]
+pub struct FileTiming {
+    pub path: PathBuf,
+    pub duration: Duration,
+    /// Extraction error; None on success
+    pub error: Option<String>,
+}
+
+/// Running summary of the file timings of a scan
+#[derive(Debug, Default)]
+pub struct TimingSummary {
+    processed: u64,
+    failed: u64,
+    /// Min-heap of the slowest files seen so far, at most SLOWEST_FILES_LIMIT entries
+    slowest: BinaryHeap<Reverse<(Duration, PathBuf, bool)>>,
+    /// Directory -> (processed files, processing time)
+    directories: HashMap<PathBuf, (u64, Duration)>,
+    failures: HashMap<String, ExtensionFailures>,
+}
+
+impl TimingSummary {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add the timing of one processed file
+    pub fn record(&mut self, timing: FileTiming) {
+        self.processed += 1;
+
+        // 目录已存在时不分配新的 PathBuf
+        let dir = timing.path.parent().unwrap_or(Path::new(""));
+        if let Some(directory) = self.directories.get_mut(dir) {
+            directory.0 += 1;
+            directory.1 += timing.duration;
+        } else {
+            self.directories.insert(dir.to_path_buf(), (1, timing.duration));
+        }
+
+        if let Some(error) = &timing.error {
+            self.failed += 1;
+            let extension = timing
+                .path
+                .extension()
+                .map(|e| e.to_string_lossy().to_lowercase())
+                .unwrap_or_default();
+            self.failures
+                .entry(extension.clone())
+                .or_insert_with(|| ExtensionFailures { extension, count: 0, sample_error: error.clone() })
+                .count += 1;
+        }
+
+        let fastest_kept = self.slowest.peek().map(|Reverse((duration, _, _))| *duration);
+        if self.slowest.len() < SLOWEST_FILES_LIMIT || fastest_kept.is_some_and(|d| timing.duration > d) {
+            self.slowest.push(Reverse((timing.duration, timing.path, timing.error.is_none())));
+            if self.slowest.len() > SLOWEST_FILES_LIMIT {
+                self.slowest.pop();
+            }
+        }
+    }
+
+    /// Number of processed files recorded
+    pub fn processed(&self) -> u64 {
+        self.processed
+    }
+}
+
+impl FromIterator<FileTiming> for TimingSummary {
+    fn from_iter<I: IntoIterator<Item = FileTiming>>(iter: I) -> Self {
+        let mut summary = Self::new();
+        for timing in iter {
+            summary.record(timing);
+        }
+        summary
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SlowFile {
+    pub path: String,
+    pub duration_ms: u64,
+    pub success: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DirectoryStats {
+    pub path: String,
+    /// Media files found in the directory (not including subdirectories)
+    pub file_count: u64,
+    /// Files whose metadata was extracted in this scan
+    pub processed_count: u64,
+    pub processing_ms: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExtensionFailures {
+    pub extension: String,
+    pub count: u64,
+    /// One of the errors, to hint at the cause
+    pub sample_error: String,
+}
+
+/// Report of the last scan
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScanReport {
+    pub mode: ScanMode,
+    /// "completed" or "cancelled"
+    pub status: String,
+    pub start_time: String,
+    pub duration_ms: u64,
+    pub total_files: u64,
+    pub processed_files: u64,
+    pub failed_files: u64,
+    pub slowest_files: Vec<SlowFile>,
+    /// Directories with the most files
+    pub largest_directories: Vec<DirectoryStats>,
+    pub failures_by_extension: Vec<ExtensionFailures>,
+}
+
+impl ScanReport {
+    /// Summarize a scan from all collected files and the timings of the processed ones
+    pub fn build(
+        mode: ScanMode,
+        status: &str,
+        start_time: String,
+        duration: Duration,
+        files: &[PathBuf],
+        timings: &TimingSummary,
+    ) -> Self {
+        let mut slowest: Vec<&(Duration, PathBuf, bool)> = timings.slowest.iter().map(|Reverse(t)| t).collect();
+        slowest.sort_by_key(|b| Reverse(b.0));
+        let slowest_files = slowest
+            .into_iter()
+            .map(|(duration, path, success)| SlowFile {
+                path: path.to_string_lossy().to_string(),
+                duration_ms: duration.as_millis() as u64,
+                success: *success,
+            })
+            .collect();
+
+        let mut directories: HashMap<&Path, DirectoryStats> = HashMap::new();
+        for path in files {
+            let dir = path.parent().unwrap_or(Path::new(""));
+            directories
+                .entry(dir)
+                .or_insert_with(|| DirectoryStats {
+                    path: dir.to_string_lossy().to_string(),
+                    file_count: 0,
+                    processed_count: 0,
+                    processing_ms: 0,
+                })
+                .file_count += 1;
+        }
+        for (dir, (processed, processing)) in &timings.directories {
+            if let Some(stats) = directories.get_mut(dir.as_path()) {
+                stats.processed_count = *processed;
+                stats.processing_ms = processing.as_millis() as u64;
+            }
+        }
+        let mut largest_directories: Vec<DirectoryStats> = directories.into_values().collect();
+        largest_directories.sort_by(|a, b| b.file_count.cmp(&a.file_count).then_with(|| a.path.cmp(&b.path)));
+        largest_directories.truncate(DIRECTORIES_LIMIT);
+
+        let mut failures_by_extension: Vec<ExtensionFailures> = timings.failures.values().cloned().collect();
+        failures_by_extension.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.extension.cmp(&b.extension)));
+
+        Self {
+            mode,
+            status: status.to_string(),
+            start_time,
+            duration_ms: duration.as_millis() as u64,
+            total_files: files.len() as u64,
+            processed_files: timings.processed,
+            failed_files: timings.failed,
+            slowest_files,
+            largest_directories,
+            failures_by_extension,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn timing(path: &str, ms: u64, error: Option<&str>) -> FileTiming {
+        FileTiming {
+            path: PathBuf::from(path),
+            duration: Duration::from_millis(ms),
+            error: error.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn test_build_report() {
+        let files: Vec<PathBuf> = ["/p/a/1.jpg", "/p/a/2.heic", "/p/a/3.HEIC", "/p/b/4.mp4"]
+            .iter()
+            .map(PathBuf::from)
+            .collect();
+        let timings: TimingSummary = vec![
+            timing("/p/a/1.jpg", 10, None),
+            timing("/p/a/2.heic", 900, Some("no decoder")),
+            timing("/p/a/3.HEIC", 50, Some("truncated")),
+            timing("/p/b/4.mp4", 300, None),
+        ]
+        .into_iter()
+        .collect();
+
+        let report = ScanReport::build(ScanMode::Force, "completed", "t".to_string(), Duration::from_secs(2), &files, &timings);
+        assert_eq!((report.total_files, report.processed_files, report.failed_files), (4, 4, 2));
+        assert_eq!(report.duration_ms, 2000);
+
+        let slowest: Vec<&str> = report.slowest_files.iter().map(|f| f.path.as_str()).collect();
+        assert_eq!(slowest, ["/p/a/2.heic", "/p/b/4.mp4", "/p/a/3.HEIC", "/p/a/1.jpg"]);
+        assert!(!report.slowest_files[0].success);
+
+        let top = &report.largest_directories[0];
+        assert_eq!((top.path.as_str(), top.file_count, top.processed_count, top.processing_ms), ("/p/a", 3, 3, 960));
+
+        // 扩展名不区分大小写归并
+        assert_eq!(report.failures_by_extension.len(), 1);
+        assert_eq!(report.failures_by_extension[0].extension, "heic");
+        assert_eq!(report.failures_by_extension[0].count, 2);
+    }
+
+    #[test]
+    fn test_slowest_files_bounded() {
+        let timings: TimingSummary = (0..1000u64)
+            .map(|i| timing(&format!("/p/{}.jpg", i), i, None))
+            .collect();
+        assert_eq!(timings.processed(), 1000);
+
+        let report = ScanReport::build(ScanMode::Incremental, "completed", "t".to_string(), Duration::ZERO, &[], &timings);
+        assert_eq!(report.slowest_files.len(), SLOWEST_FILES_LIMIT);
+        assert_eq!(report.slowest_files[0].path, "/p/999.jpg");
+        assert_eq!(report.slowest_files[SLOWEST_FILES_LIMIT - 1].duration_ms, 980);
+    }
+}
@@ -1,22 +1,94 @@
 use crate::config::Config;
-use crate::db::{DatabasePool, MediaFile, MediaFileRepository};
+use crate::db::{path_key, ChangeLogRepository, DatabasePool, DateSource, DirectoryEntry, DirectoryRepository, MediaAttribute, MediaFile, MediaFileRepository, MediaFileTxRepository, MediaSubImage, MetadataField, RepositoryTx};
+use crate::processors::{file_metadata, maker_notes};
+use crate::processors::takeout::{self, TakeoutMetadata};
 use crate::processors::{MediaMetadata, ProcessorRegistry};
+use crate::services::background_priority::{self, background};
+use crate::services::change_detection::{self, Verdict};
+use crate::services::raw_pairing::{is_raw_file, pair_raw_files};
+use crate::services::file_stability;
+use crate::services::folder_config;
+use crate::services::io_throttle::IoThrottle;
+use crate::services::quiet_hours::QuietHours;
+use crate::services::scan_filter::ScanFilter;
+use crate::services::scan_report::{FileTiming, ScanReport, TimingSummary};
+use crate::services::scan_summary::{ScanSummary, ScanSummaryNotifier};
+use crate::services::webhooks::{WebhookEvent, WebhookNotifier};
 use crate::websocket::{ScanStateManager, ScanPhase};
+use std::collections::HashSet;
 use std::path::{Path, PathBuf};
-use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
-use std::sync::Arc;
-use std::time::Instant;
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 use tokio::fs;
-use tokio::sync::Semaphore;
+use tokio::sync::{mpsc, Semaphore};
 
 /// Result of processing a single file
 #[derive(Debug, Clone)]
 struct ProcessingResult {
     path: PathBuf,
     success: Option<MediaFile>,
+    extras: FileExtras,
     error: Option<String>,
+    /// Time spent extracting metadata (for the scan report)
+    duration: Duration,
 }
 
+/// Rows stored next to a file's media_files row. None leaves the stored rows unchanged.
+#[derive(Debug, Clone, Default)]
+struct FileExtras {
+    /// Top-level images of a multi-image container (see MediaMetadata::sub_images)
+    sub_images: Option<Vec<MediaSubImage>>,
+    /// Maker-note attributes (see MediaMetadata::attributes)
+    attributes: Option<Vec<MediaAttribute>>,
+}
+
+/// Error of re-extracting a single file (see ScanService::rescan_file)
+#[derive(Debug, thiserror::Error)]
+pub enum RescanError {
+    #[error("Metadata extraction failed: {0}")]
+    Extraction(String),
+
+    #[error("Database error: {0}")]
+    Database(#[from] sqlx::Error),
+}
+
+/// Scan mode
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, serde::Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ScanMode {
+    /// Skip files whose modify_time is unchanged since the last scan
+    #[default]
+    Incremental,
+    /// Re-extract metadata for every file, ignoring the modify_time skip list.
+    /// Used to backfill new fields after the extraction logic has been upgraded.
+    Force,
+    /// Only reconcile existence and modify_time, without metadata extraction: new files get
+    /// placeholder rows flagged pending_extraction and modified files are flagged, so a big
+    /// import shows up right away. An incremental scan queued afterwards extracts them.
+    Quick,
+}
+
+impl ScanMode {
+    /// Mode of a queued scan when another request arrives: the more thorough one wins
+    /// (Force > Incremental > Quick)
+    pub fn merge(self, other: ScanMode) -> ScanMode {
+        let rank = |mode: ScanMode| match mode {
+            ScanMode::Quick => 0,
+            ScanMode::Incremental => 1,
+            ScanMode::Force => 2,
+        };
+        if rank(other) > rank(self) { other } else { self }
+    }
+}
+
+/// Maximum consecutive follow-up scans for files that are still being written
+const MAX_STABILITY_RETRIES: u32 = 5;
+
+/// Upper bound of bytes charged per file against the I/O throttle.
+/// Metadata extraction reads headers/containers, not whole large videos.
+const MAX_READ_BYTES_PER_FILE: u64 = 16 * 1024 * 1024;
+
 /// RAII guard that ensures is_scanning flag is always reset, even on panic
 struct ScanGuard {
     is_scanning: Arc<AtomicBool>,
@@ -29,18 +101,37 @@ impl Drop for ScanGuard {
 }
 
 /// Service for scanning media files
+#[derive(Clone)]
 pub struct ScanService {
     config: Config,
     db: DatabasePool,
     processors: Arc<ProcessorRegistry>,
     scan_state: Arc<ScanStateManager>,
+    filter: ScanFilter,
 
     // Scan state
     is_scanning: Arc<AtomicBool>,
+    /// Scan requested while another was running; started when the current one finishes.
+    /// A single slot: repeated requests are merged (see ScanMode::merge).
+    queued_scan: Arc<Mutex<Option<ScanMode>>>,
+    /// Report of the last scan (GET /api/scan/report)
+    last_report: Arc<Mutex<Option<ScanReport>>>,
     is_cancelled: Arc<AtomicBool>,
     total_files: Arc<AtomicU64>,
     success_count: Arc<AtomicU64>,
     failure_count: Arc<AtomicU64>,
+    /// Consecutive follow-up scans scheduled for files that were still being written
+    stability_retries: Arc<AtomicU32>,
+    /// Reduced concurrency during configured quiet hours
+    quiet_hours: Option<Arc<QuietHours>>,
+    /// Read bandwidth limit for metadata extraction
+    io_throttle: Option<Arc<IoThrottle>>,
+    /// Outbound webhooks for scan events
+    webhooks: Option<Arc<WebhookNotifier>>,
+    /// ntfy/email summary of scheduled scans
+    summary: Option<Arc<ScanSummaryNotifier>>,
+    /// Set by scheduled_scan(); the next scan to finish sends a summary
+    summary_pending: Arc<AtomicBool>,
 }
 
 impl ScanService {
@@ -50,19 +141,49 @@ impl ScanService {
         processors: Arc<ProcessorRegistry>,
         scan_state: Arc<ScanStateManager>,
     ) -> Self {
+        let filter = ScanFilter::new(config.scan_min_file_size, &config.scan_ignore_patterns);
+        let io_throttle = (config.scan_io_bytes_per_second > 0)
+            .then(|| Arc::new(IoThrottle::new(config.scan_io_bytes_per_second)));
         Self {
             config,
             db,
             processors,
             scan_state,
+            filter,
             is_scanning: Arc::new(AtomicBool::new(false)),
+            queued_scan: Arc::new(Mutex::new(None)),
+            last_report: Arc::new(Mutex::new(None)),
             is_cancelled: Arc::new(AtomicBool::new(false)),
             total_files: Arc::new(AtomicU64::new(0)),
             success_count: Arc::new(AtomicU64::new(0)),
             failure_count: Arc::new(AtomicU64::new(0)),
+            stability_retries: Arc::new(AtomicU32::new(0)),
+            quiet_hours: None,
+            io_throttle,
+            webhooks: None,
+            summary: None,
+            summary_pending: Arc::new(AtomicBool::new(false)),
         }
     }
 
+    /// Throttle metadata extraction during quiet hours
+    pub fn with_quiet_hours(mut self, quiet_hours: Option<Arc<QuietHours>>) -> Self {
+        self.quiet_hours = quiet_hours;
+        self
+    }
+
+    /// Send scan.completed / scan.failed / files.imported webhook events
+    pub fn with_webhooks(mut self, webhooks: Arc<WebhookNotifier>) -> Self {
+        self.webhooks = Some(webhooks);
+        self
+    }
+
+    /// Send a summary of each scheduled scan via ntfy/email
+    pub fn with_scan_summary(mut self, summary: Option<Arc<ScanSummaryNotifier>>) -> Self {
+        self.summary = summary;
+        self
+    }
+
     /// Get the worker count for scan operations
     fn get_worker_count(&self) -> usize {
         self.config.scan_worker_count.unwrap_or_else(|| {
@@ -74,45 +195,186 @@ impl ScanService {
 
     /// Start a scan operation
     pub async fn scan(&self) {
-        tracing::info!("Scanning media files");
+        self.scan_with_mode(ScanMode::Incremental).await;
+    }
+
+    /// Start a scan triggered by the scheduler; a summary is sent when it finishes.
+    /// If it is queued behind a running scan, the summary covers the scan that finishes next.
+    pub async fn scheduled_scan(&self) {
+        if self.summary.is_some() {
+            self.summary_pending.store(true, Ordering::SeqCst);
+        }
+        self.scan().await;
+    }
+
+    /// Start a scan operation with the given mode.
+    /// If a scan is already running the request is queued and runs after it.
+    pub async fn scan_with_mode(&self, mode: ScanMode) {
+        tracing::info!("Scanning media files (mode: {:?})", mode);
+        {
+            // 与 take_queued_scan 共用队列锁，保证"正在扫描则排队"与"扫描结束取队列"不会交错丢失请求
+            let mut queued = self.queued_scan.lock().unwrap();
+            if self.is_scanning.compare_exchange(false, true, Ordering::SeqCst, Ordering::SeqCst).is_err() {
+                let merged = match *queued {
+                    Some(queued) => queued.merge(mode),
+                    None => mode,
+                };
+                *queued = Some(merged);
+                tracing::info!("Scan already in progress, queued (mode: {:?})", merged);
+                return;
+            }
+        }
+
+        self.run_scans(mode).await;
+    }
+
+    /// Run a scan (is_scanning already set), then any scans queued while it ran
+    async fn run_scans(&self, mut mode: ScanMode) {
+        loop {
+            {
+                // RAII guard: ensures is_scanning is always reset, even on panic
+                let _guard = ScanGuard {
+                    is_scanning: self.is_scanning.clone(),
+                };
+
+                self.is_cancelled.store(false, Ordering::SeqCst);
+                self.total_files.store(0, Ordering::SeqCst);
+                self.success_count.store(0, Ordering::SeqCst);
+                self.failure_count.store(0, Ordering::SeqCst);
+
+                self.perform_scan(mode).await;
+            }
+
+            match self.take_queued_scan() {
+                Some(next) => {
+                    tracing::info!("Starting queued scan (mode: {:?})", next);
+                    mode = next;
+                }
+                None => break,
+            }
+        }
+    }
+
+    /// Take the queued scan and mark a scan as running. Returns None if nothing is
+    /// queued, or if another request started a scan first (it drains the queue itself).
+    fn take_queued_scan(&self) -> Option<ScanMode> {
+        let mut queued = self.queued_scan.lock().unwrap();
+        queued.as_ref()?;
         if self.is_scanning.compare_exchange(false, true, Ordering::SeqCst, Ordering::SeqCst).is_err() {
-            tracing::warn!("Scan already in progress");
-            return;
+            return None;
         }
+        queued.take()
+    }
 
-        // RAII guard: ensures is_scanning is always reset, even on panic
-        let _guard = ScanGuard {
-            is_scanning: self.is_scanning.clone(),
+    /// Report of the last completed or cancelled scan
+    pub fn last_report(&self) -> Option<ScanReport> {
+        self.last_report.lock().unwrap().clone()
+    }
+
+    fn store_report(&self, report: ScanReport) {
+        *self.last_report.lock().unwrap() = Some(report);
+    }
+
+    fn notify(&self, event: WebhookEvent, text: String, data: serde_json::Value) {
+        if let Some(webhooks) = &self.webhooks {
+            webhooks.notify(event, text, data);
+        }
+    }
+
+    /// Send the pending scheduled-scan summary, if any
+    fn send_summary(&self, summary: ScanSummary) {
+        let Some(notifier) = self.summary.clone() else {
+            return;
         };
+        if !self.summary_pending.swap(false, Ordering::SeqCst) {
+            return;
+        }
+        tokio::spawn(async move {
+            notifier.send(&summary).await;
+        });
+    }
 
-        self.is_cancelled.store(false, Ordering::SeqCst);
-        self.total_files.store(0, Ordering::SeqCst);
-        self.success_count.store(0, Ordering::SeqCst);
-        self.failure_count.store(0, Ordering::SeqCst);
+    /// Store the report of a cancelled scan
+    fn finish_cancelled(&self, report: ScanReport, files_added: u64, files_updated: u64, files_deleted: u64) {
+        self.send_summary(ScanSummary::from_report(&report, files_added, files_updated, files_deleted));
+        self.store_report(report);
+    }
 
-        self.perform_scan().await;
+    /// Store the report of a completed scan and send scan.completed (and files.imported for new files)
+    fn finish_completed(&self, report: ScanReport, files_added: u64, files_updated: u64, files_deleted: u64) {
+        let text = format!(
+            "Scan completed: {} files, {} processed, {} failed in {:.1}s",
+            report.total_files,
+            report.processed_files,
+            report.failed_files,
+            report.duration_ms as f64 / 1000.0
+        );
+        self.notify(
+            WebhookEvent::ScanCompleted,
+            text,
+            serde_json::json!({
+                "mode": report.mode,
+                "startTime": report.start_time,
+                "durationMs": report.duration_ms,
+                "totalFiles": report.total_files,
+                "processedFiles": report.processed_files,
+                "failedFiles": report.failed_files,
+                "filesAdded": files_added,
+            }),
+        );
+        if files_added > 0 {
+            self.notify(
+                WebhookEvent::FilesImported,
+                format!("{} new files imported", files_added),
+                serde_json::json!({ "count": files_added }),
+            );
+        }
+        self.send_summary(ScanSummary::from_report(&report, files_added, files_updated, files_deleted));
+        self.store_report(report);
+    }
+
+    /// Scan waiting for the current one to finish
+    pub fn queued_scan(&self) -> Option<ScanMode> {
+        *self.queued_scan.lock().unwrap()
     }
 
     /// Scan implementation
-    async fn perform_scan(&self) {
+    async fn perform_scan(&self, mode: ScanMode) {
         let scan_start = Instant::now();
+        let start_time = chrono::Utc::now().to_rfc3339();
         tracing::info!("Starting scan");
 
         // 重置计数器，确保每次扫描从0开始
         self.scan_state.reset_counters();
 
+        // 挂载点变化后先改写数据库中的旧路径，否则旧记录会被当作缺失删除、再作为新文件重新提取
+        self.remap_path_prefixes().await;
+
         // Phase 1: Collect all file paths (fast, no DB access)
         // 在收集文件之前发送 Collecting 阶段，让前端立即看到扫描状态
         self.scan_state.set_phase(ScanPhase::Collecting);
         let collect_start = Instant::now();
-        let files = match self.collect_file_paths().await {
-            Ok(files) => files,
+        let (files, raw_files, directories) = match self.collect_file_paths(true).await {
+            Ok(collected) => collected,
             Err(e) => {
                 tracing::error!("Failed to collect files: {}", e);
                 self.scan_state.error().await;
+                self.notify(
+                    WebhookEvent::ScanFailed,
+                    format!("Scan failed: {}", e),
+                    serde_json::json!({ "mode": mode, "startTime": start_time, "error": e.to_string() }),
+                );
+                self.send_summary(ScanSummary::failed(mode, scan_start.elapsed(), e.to_string()));
                 return;
             }
         };
+
+        // 取消时目录列表不完整，保留上次的结果
+        if !self.is_cancelled.load(Ordering::SeqCst) {
+            if let Err(e) = DirectoryRepository::new(&self.db).sync(&directories).await {
+                tracing::warn!("Failed to sync directories: {}", e);
+            }
+        }
         let collect_duration = collect_start.elapsed();
         tracing::debug!("Phase 1 (collecting): {} files collected in {:?}", files.len(), collect_duration);
 
@@ -124,6 +386,7 @@ impl ScanService {
             // 设置完成状态
             self.scan_state.set_phase(ScanPhase::Completed);
             self.scan_state.completed().await;
+            self.finish_completed(ScanReport::build(mode, "completed", start_time, scan_start.elapsed(), &files, &TimingSummary::new()), 0, 0, 0);
             tracing::info!("Scan complete (no files) in {:?}", scan_start.elapsed());
             return;
         }
@@ -131,7 +394,13 @@ impl ScanService {
         // Phase 2: Batch check database for existing files
         let count_start = Instant::now();
         self.scan_state.set_phase(ScanPhase::Counting);
-        let (files_to_add, files_to_update, skip_list) = self.batch_check_exists(&files).await;
+        let (files_to_add, mut files_to_update, mut skip_list) = self.batch_check_exists(&files).await;
+
+        // Force mode: every unchanged file is reprocessed as an update
+        if mode == ScanMode::Force {
+            files_to_update += skip_list.len() as u64;
+            skip_list.clear();
+        }
 
         // Count files to delete
         let repo = MediaFileRepository::new(&self.db);
@@ -149,33 +418,47 @@ impl ScanService {
             files_to_add, files_to_update, skip_list.len(), files_to_delete, count_duration);
 
         let processing_count = files_to_add + files_to_update;
+        let mut deferred = 0;
+        let mut timings = TimingSummary::new();
         if processing_count > 0 {
             self.scan_state.set_phase(ScanPhase::Processing);
             self.scan_state.set_total(processing_count);
 
             // Build list of files that need metadata extraction
-            let mut files_to_process: Vec<PathBuf> = Vec::with_capacity(processing_count as usize);
-            for path in &files {
-                let path_str = path.to_string_lossy().to_string();
-                if !skip_list.iter().any(|p| p.to_string_lossy() == path_str) {
-                    files_to_process.push(path.clone());
-                }
+            let files_to_process = files_to_process(&files, &skip_list);
+
+            // 仍在复制中的文件（大小/修改时间在窗口内变化）推迟到后续扫描处理
+            let window = Duration::from_secs(self.config.scan_stability_window_seconds);
+            let (files_to_process, unstable) = file_stability::split_unstable(files_to_process, window).await;
+            if !unstable.is_empty() {
+                tracing::info!("Deferring {} files that are still being written: {:?}", unstable.len(), unstable);
+                deferred = unstable.len();
+                self.scan_state.set_total(files_to_process.len() as u64);
             }
-
-            // Phase 3: Parallel metadata extraction (only for files that need it)
+            // 最新的文件最先提取写入，导入大型归档时最近的照片几分钟内就出现在时间线上；
+            // 逐个 stat 放在阻塞线程中，读取失败时保持收集顺序
+            let unordered = files_to_process.clone();
+            let files_to_process = background_priority::spawn_blocking(move || newest_first(files_to_process))
+                .await
+                .unwrap_or(unordered);
+
+            // Phase 3 + 4: parallel metadata extraction (only for files that need it),
+            // pipelined with batched upserts by the writer task
             let process_start = Instant::now();
-            let results = self.parallel_extract_metadata(&files_to_process).await;
-            let process_duration = process_start.elapsed();
-            let success_results = results.iter().filter(|r| r.success.is_some()).count();
-            let fail_results = results.iter().filter(|r| r.success.is_none()).count();
-            tracing::debug!("Phase 3 (processing): {} processed ({} success, {} failed) in {:?}",
-                results.len(), success_results, fail_results, process_duration);
-
-            // Phase 4: Batch upsert results + update skip_list last_scanned
-            self.scan_state.set_phase(ScanPhase::Writing);
-            let writing_cancelled = self.batch_write_results_with_skip(results, &skip_list, total).await;
-            let write_duration = process_start.elapsed();
-            tracing::debug!("Phase 4 (writing): completed in {:?}", write_duration);
+            let (writing_cancelled, file_timings) = if mode == ScanMode::Quick {
+                (self.quick_write(&files_to_process).await, TimingSummary::new())
+            } else {
+                self.extract_and_write(&files_to_process).await
+            };
+            timings = file_timings;
+            let success_results = self.success_count.load(Ordering::SeqCst);
+            tracing::debug!("Phase 3+4 (processing/writing): {} processed ({} success, {} failed) in {:?}",
+                timings.processed(), success_results, self.failure_count.load(Ordering::SeqCst), process_start.elapsed());
+
+            // Update last_scanned of unchanged files
+            if !writing_cancelled {
+                self.touch_unchanged(&skip_list).await;
+            }
 
             // Check if writing was cancelled
             if writing_cancelled || self.is_cancelled.load(Ordering::SeqCst) {
@@ -184,6 +467,12 @@ impl ScanService {
                 self.delete_missing(&files).await;
                 // 发送取消状态
                 self.scan_state.cancelled().await;
+                self.finish_cancelled(
+                    ScanReport::build(mode, "cancelled", start_time, scan_start.elapsed(), &files, &timings),
+                    files_to_add,
+                    files_to_update,
+                    files_to_delete,
+                );
                 tracing::info!("Scan cancelled after writing {} files", success_results);
                 return;
             }
@@ -193,15 +482,21 @@ impl ScanService {
             self.scan_state.set_file_counts(0, 0, files_to_delete);
 
             let write_start = Instant::now();
-            let writing_cancelled = self.batch_write_results_with_skip(Vec::new(), &skip_list, total).await;
+            self.touch_unchanged(&skip_list).await;
             let write_duration = write_start.elapsed();
             tracing::debug!("Phase 4 (updating): {} files touched in {:?}", skip_list.len(), write_duration);
 
             // Check if writing was cancelled
-            if writing_cancelled || self.is_cancelled.load(Ordering::SeqCst) {
+            if self.is_cancelled.load(Ordering::SeqCst) {
                 self.scan_state.set_phase(ScanPhase::Deleting);
                 self.delete_missing(&files).await;
                 self.scan_state.cancelled().await;
+                self.finish_cancelled(
+                    ScanReport::build(mode, "cancelled", start_time, scan_start.elapsed(), &files, &timings),
+                    0,
+                    0,
+                    files_to_delete,
+                );
                 tracing::info!("Scan cancelled during touch phase");
                 return;
             }
@@ -212,8 +507,21 @@ impl ScanService {
         self.delete_missing(&files).await;
         tracing::debug!("Phase 5 (deleting): completed");
 
+        // RAW+JPEG pairing: refresh links after the file set is final
+        self.sync_raw_pairs(&files, &raw_files).await;
+
         // Scan complete
         self.scan_state.completed().await;
+        self.finish_completed(
+            ScanReport::build(mode, "completed", start_time, scan_start.elapsed(), &files, &timings),
+            files_to_add,
+            files_to_update,
+            files_to_delete,
+        );
+        self.schedule_stability_retry(deferred);
+        if mode == ScanMode::Quick && processing_count > 0 {
+            self.queue_extraction_scan();
+        }
 
         let processed = self.success_count.load(Ordering::SeqCst) + self.failure_count.load(Ordering::SeqCst);
         let total_duration = scan_start.elapsed();
@@ -221,9 +529,161 @@ impl ScanService {
             processed, self.success_count.load(Ordering::SeqCst), self.failure_count.load(Ordering::SeqCst), skip_list.len(), total_duration);
     }
 
-    /// Collect file paths only (fast operation)
-    async fn collect_file_paths(&self) -> std::io::Result<Vec<PathBuf>> {
+    /// Queue the incremental scan that extracts the metadata of files added by a quick scan;
+    /// run_scans starts it as soon as the quick scan has finished
+    fn queue_extraction_scan(&self) {
+        let mut queued = self.queued_scan.lock().unwrap();
+        let merged = match *queued {
+            Some(queued) => queued.merge(ScanMode::Incremental),
+            None => ScanMode::Incremental,
+        };
+        *queued = Some(merged);
+        tracing::info!("Quick scan done, queued metadata extraction (mode: {:?})", merged);
+    }
+
+    /// Schedule a follow-up incremental scan for files deferred by the stability check.
+    /// Gives up after MAX_STABILITY_RETRIES consecutive attempts (e.g. a file that is written continuously).
+    fn schedule_stability_retry(&self, deferred: usize) {
+        if deferred == 0 {
+            self.stability_retries.store(0, Ordering::SeqCst);
+            return;
+        }
+
+        let attempt = self.stability_retries.fetch_add(1, Ordering::SeqCst) + 1;
+        if attempt > MAX_STABILITY_RETRIES {
+            tracing::warn!("{} files still unstable after {} retries, waiting for the next scheduled scan",
+                deferred, MAX_STABILITY_RETRIES);
+            self.stability_retries.store(0, Ordering::SeqCst);
+            return;
+        }
+
+        let service = self.clone();
+        let delay = Duration::from_secs(self.config.scan_stability_window_seconds * 2);
+        tokio::spawn(async move {
+            tokio::time::sleep(delay).await;
+            tracing::info!("Retrying {} deferred files (attempt {})", deferred, attempt);
+            service.scan().await;
+        });
+    }
+
+    /// Reprocess only files whose `field` is missing, instead of a full rescan.
+    /// Returns false if another scan is already in progress.
+    pub async fn backfill(&self, field: MetadataField) -> bool {
+        tracing::info!("Backfilling metadata field {:?}", field);
+        if self.is_scanning.compare_exchange(false, true, Ordering::SeqCst, Ordering::SeqCst).is_err() {
+            tracing::warn!("Scan already in progress");
+            return false;
+        }
+
+        let guard = ScanGuard {
+            is_scanning: self.is_scanning.clone(),
+        };
+
+        self.is_cancelled.store(false, Ordering::SeqCst);
+        self.total_files.store(0, Ordering::SeqCst);
+        self.success_count.store(0, Ordering::SeqCst);
+        self.failure_count.store(0, Ordering::SeqCst);
+
+        self.perform_backfill(field).await;
+        drop(guard);
+
+        // 回填期间排队的扫描
+        if let Some(mode) = self.take_queued_scan() {
+            self.run_scans(mode).await;
+        }
+        true
+    }
+
+    /// Backfill implementation: reuses the processing and writing phases of a scan
+    async fn perform_backfill(&self, field: MetadataField) {
+        let backfill_start = Instant::now();
+        self.scan_state.reset_counters();
+        self.scan_state.set_phase(ScanPhase::Collecting);
+
+        let repo = MediaFileRepository::new(&self.db);
+        let files: Vec<PathBuf> = match repo.find_paths_missing_field(field).await {
+            Ok(paths) => paths
+                .into_iter()
+                .map(PathBuf::from)
+                .filter(|p| p.is_file())
+                .collect(),
+            Err(e) => {
+                tracing::error!("Failed to query files missing {:?}: {}", field, e);
+                self.scan_state.error().await;
+                return;
+            }
+        };
+
+        let total = files.len() as u64;
+        self.total_files.store(total, Ordering::SeqCst);
+        self.scan_state.set_total(total);
+        self.scan_state.set_file_counts(0, total, 0);
+
+        if total > 0 {
+            self.scan_state.set_phase(ScanPhase::Processing);
+            let (writing_cancelled, _) = self.extract_and_write(&files).await;
+
+            if writing_cancelled || self.is_cancelled.load(Ordering::SeqCst) {
+                self.scan_state.cancelled().await;
+                tracing::info!("Backfill of {:?} cancelled", field);
+                return;
+            }
+        }
+
+        self.scan_state.completed().await;
+        tracing::info!("Backfill of {:?} complete: {} files reprocessed ({} success, {} failed) in {:?}",
+            field, total, self.success_count.load(Ordering::SeqCst),
+            self.failure_count.load(Ordering::SeqCst), backfill_start.elapsed());
+    }
+
+    /// Re-extract the metadata of one file right away, outside the scan pipeline (e.g. after
+    /// its EXIF was edited externally). Runs alongside a scan; user edits and the id are kept.
+    /// Returns the stored row.
+    pub async fn rescan_file(&self, path: &Path) -> Result<MediaFile, RescanError> {
+        let (media_file, extras) =
+            Self::extract_single_metadata(path, &self.processors, self.config.takeout_sidecars)
+                .await
+                .map_err(|e| RescanError::Extraction(e.to_string()))?;
+
+        let mut tx = RepositoryTx::begin(&self.db).await?;
+        tx.media_files().upsert(&media_file).await?;
+        Self::write_extras(&mut tx.media_files(), &[(media_file.file_path.clone(), extras)]).await?;
+        tx.commit().await?;
+
+        let repo = MediaFileRepository::new(&self.db);
+
+        tracing::info!("Rescanned {}", path.display());
+        repo.find_by_path(path)
+            .await?
+            .ok_or(RescanError::Database(sqlx::Error::RowNotFound))
+    }
+
+    /// Link RAW files to their display files. With pairing disabled `raw_files` is empty,
+    /// which clears any links left over from a previous configuration.
+    async fn sync_raw_pairs(&self, files: &[PathBuf], raw_files: &[PathBuf]) {
+        let pairs: Vec<(String, String)> = pair_raw_files(files, raw_files)
+            .into_iter()
+            .map(|(display, raw)| (display.to_string_lossy().to_string(), raw.to_string_lossy().to_string()))
+            .collect();
+
+        let repo = MediaFileRepository::new(&self.db);
+        match repo.sync_raw_pairs(&pairs).await {
+            Ok(linked) => tracing::debug!("RAW pairing: {} files linked to a RAW sidecar", linked),
+            Err(e) => tracing::warn!("Failed to update RAW pairs: {}", e),
+        }
+    }
+
+    /// Collect file paths only (fast operation).
+    /// Returns (media files, RAW files); RAW files are only collected when RAW+JPEG pairing is enabled.
+    /// `cancellable` stops the walk when the running scan is cancelled; callers outside a scan
+    /// (the consistency check) pass false, since the flag stays set after a cancelled scan.
+    pub(crate) async fn collect_file_paths(
+        &self,
+        cancellable: bool,
+    ) -> std::io::Result<(Vec<PathBuf>, Vec<PathBuf>, Vec<DirectoryEntry>)> {
         let mut files = Vec::new();
+        let mut raw_files = Vec::new();
+        let mut directories = Vec::new();
         let base_path = &self.config.base_path;
 
         tracing::info!("Scanning directory: {:?}", base_path);
@@ -244,20 +704,26 @@ impl ScanService {
             ));
         }
 
-        // Supported extensions
-        let supported_extensions = [
-            "jpg", "jpeg", "png", "gif", "bmp", "webp", "tiff", "heic", "heif",
-            "mp4", "avi", "mov", "mkv", "wmv", "flv", "webm"
-        ];
+        // Supported extensions, including LATTE_EXTRA_IMAGE_EXTS / LATTE_EXTRA_VIDEO_EXTS;
+        // 停用的处理器（LATTE_DISABLED_PROCESSORS）的扩展名不收集，否则这些文件每次扫描都会失败
+        let mut supported_extensions = crate::processors::extensions::supported_extensions(&self.config);
+        supported_extensions.retain(|ext| self.processors.handles_extension(ext));
 
         // Walk directory recursively using async stack (non-blocking)
         let mut stack = vec![base_path.clone()];
 
         while let Some(current_dir) = stack.pop() {
-            if self.is_cancelled.load(Ordering::SeqCst) {
+            if cancellable && self.is_cancelled.load(Ordering::SeqCst) {
                 break;
             }
 
+            // .nomedia / .latteignore 排除整个子树
+            if folder_config::has_ignore_marker(&current_dir).await {
+                tracing::debug!("Skipping directory with ignore marker: {:?}", current_dir);
+                continue;
+            }
+            directories.push(directory_entry(&current_dir).await);
+
             match fs::read_dir(&current_dir).await {
                 Ok(mut entries) => {
                     while let Some(entry) = entries.next_entry().await? {
@@ -265,11 +731,30 @@ impl ScanService {
 
                         if path.is_file() {
                             if let Some(ext) = path.extension().and_then(|e| e.to_str()) {
-                                if supported_extensions.contains(&ext.to_lowercase().as_str()) {
+                                let is_media = supported_extensions.iter().any(|e| e.eq_ignore_ascii_case(ext));
+                                let is_raw = !is_media && self.config.raw_jpeg_pairing && is_raw_file(&path);
+                                if !is_media && !is_raw {
+                                    continue;
+                                }
+
+                                // 过小/临时文件多半仍在写入，本次跳过，下次扫描再收录
+                                let size = entry.metadata().await.map(|m| m.len()).unwrap_or(0);
+                                if self.filter.should_skip_file(&path, size) {
+                                    tracing::debug!("Skipping small or temporary file: {:?} ({} bytes)", path, size);
+                                    continue;
+                                }
+
+                                if is_media {
                                     files.push(path);
+                                } else {
+                                    raw_files.push(path);
                                 }
                             }
                         } else if path.is_dir() {
+                            if self.filter.is_ignored_name(&path) {
+                                tracing::debug!("Skipping ignored directory: {:?}", path);
+                                continue;
+                            }
                             stack.push(path);
                         }
                     }
@@ -281,18 +766,20 @@ impl ScanService {
         }
 
         tracing::info!("Collected {} files", files.len());
-        Ok(files)
+        Ok((files, raw_files, directories))
     }
 
     /// Batch check which files exist in database (optimized for bulk queries)
-    /// Returns (to_add, to_update, skip_list) - skip_list contains files with unchanged modify_time
-    /// Uses batch_find_by_paths_batch for efficient bulk SELECT queries
-    async fn batch_check_exists(&self, files: &[PathBuf]) -> (u64, u64, Vec<PathBuf>) {
+    /// Returns (to_add, to_update, skip_list) - skip_list contains unchanged files
+    /// Uses find_existing for one bulk SELECT per chunk; how changes are detected
+    /// depends on scan_change_detection (modify time, size, content hash)
+    pub async fn batch_check_exists(&self, files: &[PathBuf]) -> (u64, u64, HashSet<PathBuf>) {
         let batch_size = self.config.db_batch_check_size;
+        let mode = self.config.scan_change_detection;
 
         let mut to_add = 0u64;
         let mut to_update = 0u64;
-        let mut skip_list: Vec<PathBuf> = Vec::new();
+        let mut skip_list: HashSet<PathBuf> = HashSet::new();
         let repo = MediaFileRepository::new(&self.db);
 
         for chunk in files.chunks(batch_size) {
@@ -300,46 +787,34 @@ impl ScanService {
                 break;
             }
 
-            // Use the new batch query method for efficient bulk SELECT
-            match repo.batch_find_by_paths_batch(chunk).await {
-                Ok(existing_files) => {
-                    // Create a HashMap for O(1) lookup
-                    use std::collections::HashMap;
-                    let existing_map: HashMap<String, &MediaFile> = existing_files
-                        .iter()
-                        .map(|f| (f.file_path.clone(), f))
-                        .collect();
-
+            match repo.find_existing(chunk).await {
+                Ok(existing_map) => {
+                    // 修改时间和大小都相同、需要比较内容指纹的文件：(路径, 入库时的指纹)
+                    let mut hash_candidates: Vec<(PathBuf, String)> = Vec::new();
                     for path in chunk {
-                        let path_str = path.to_string_lossy().to_string();
-                        match existing_map.get(&path_str) {
+                        match existing_map.get(&path_key(&path.to_string_lossy())) {
+                            // 快速扫描留下的占位记录无论修改时间如何都要提取元数据
+                            Some(existing) if existing.pending_extraction => {
+                                to_update += 1;
+                            }
                             Some(existing) => {
-                                // File exists - check if modify_time changed
-                                if let Ok(fs_metadata) = path.metadata() {
-                                    if let Ok(fs_modify_time) = fs_metadata.modified() {
-                                        let fs_time = fs_modify_time
-                                            .duration_since(std::time::UNIX_EPOCH)
-                                            .unwrap_or_default()
-                                            .as_secs();
-
-                                        let db_time = existing.modify_time
-                                            .map(|t| t.and_utc().timestamp() as u64)
-                                            .unwrap_or(0);
-
-                                        if fs_time == db_time {
-                                            // Modify time unchanged - skip processing
-                                            skip_list.push(path.clone());
-                                        } else {
-                                            // Modify time changed - needs update
-                                            to_update += 1;
-                                        }
-                                    } else {
-                                        // Failed to get fs modify time - treat as update
-                                        to_update += 1;
-                                    }
-                                } else {
+                                let db_time = existing.modify_time
+                                    .map(|t| t.and_utc().timestamp() as u64)
+                                    .unwrap_or(0);
+                                let verdict = match path.metadata() {
+                                    Ok(fs_metadata) => change_detection::compare_stat(mode, &fs_metadata, db_time, existing.file_size),
                                     // Failed to get metadata - treat as update
-                                    to_update += 1;
+                                    Err(_) => Verdict::Changed,
+                                };
+                                match (verdict, &existing.content_hash) {
+                                    (Verdict::Changed, _) => to_update += 1,
+                                    (Verdict::CompareHash, Some(hash)) => {
+                                        hash_candidates.push((path.clone(), hash.clone()));
+                                    }
+                                    // 没有入库指纹的旧记录无法比较，按未变化处理
+                                    (Verdict::Unchanged | Verdict::CompareHash, _) => {
+                                        skip_list.insert(path.clone());
+                                    }
                                 }
                             }
                             None => {
@@ -348,6 +823,12 @@ impl ScanService {
                             }
                         }
                     }
+
+                    if !hash_candidates.is_empty() {
+                        let (unchanged, changed) = background(Self::compare_content(hash_candidates)).await;
+                        to_update += changed as u64;
+                        skip_list.extend(unchanged);
+                    }
                 }
                 Err(e) => {
                     tracing::error!("Batch check failed: {}", e);
@@ -360,71 +841,223 @@ impl ScanService {
         (to_add, to_update, skip_list)
     }
 
-    /// Parallel metadata extraction using semaphore-controlled concurrency
-    /// Reports results via scan_state for ordered progress updates
-    async fn parallel_extract_metadata(&self, files: &[PathBuf]) -> Vec<ProcessingResult> {
+    /// Split candidates into files whose content hash still matches the stored one and
+    /// the number of changed (or no longer readable) files
+    async fn compare_content(candidates: Vec<(PathBuf, String)>) -> (Vec<PathBuf>, usize) {
+        let count = candidates.len();
+        // 只读取首尾各 64 KiB，整批放在一个阻塞任务中
+        let unchanged: Vec<PathBuf> = background_priority::spawn_blocking(move || {
+            candidates
+                .into_iter()
+                .filter(|(path, stored)| file_metadata::content_hash(path).is_ok_and(|hash| hash == *stored))
+                .map(|(path, _)| path)
+                .collect()
+        })
+        .await
+        .unwrap_or_default();
+        let changed = count - unchanged.len();
+        (unchanged, changed)
+    }
+
+    /// Phases 3 and 4 of a scan: extraction workers send results to a channel drained by a
+    /// single writer task, so DB writes overlap with extraction instead of following it.
+    /// Returns (cancelled during writing, per-file timings)
+    async fn extract_and_write(&self, files: &[PathBuf]) -> (bool, TimingSummary) {
+        let batch_size = self.config.db_batch_write_size.max(1);
+        let (sender, receiver) = mpsc::channel(batch_size * 4);
+        let writer = self.clone();
+        let writer = tokio::spawn(async move { writer.write_results(receiver).await });
+
+        self.parallel_extract_metadata(files, sender).await;
+
+        // 提取已全部完成，剩余结果由 writer 写完
+        self.scan_state.set_phase(ScanPhase::Writing);
+        match writer.await {
+            Ok(outcome) => outcome,
+            Err(e) => {
+                tracing::error!("Scan writer task failed: {}", e);
+                (false, TimingSummary::new())
+            }
+        }
+    }
+
+    /// Quick scan writing (no metadata extraction): new files get placeholder rows with file
+    /// system data only, known files whose modify_time changed are only flagged, both as
+    /// pending_extraction. Returns true if cancelled mid-way
+    async fn quick_write(&self, files: &[PathBuf]) -> bool {
+        let batch_size = self.config.db_batch_write_size.max(1);
+        let repo = MediaFileRepository::new(&self.db);
+        let mut success_count = 0u64;
+        let mut failure_count = 0u64;
+
+        for chunk in files.chunks(batch_size) {
+            if self.is_cancelled.load(Ordering::SeqCst) {
+                tracing::info!("Quick scan cancelled, saved {} files so far", success_count);
+                return true;
+            }
+
+            // 出错时跳过本批：把已有文件当新文件写入会覆盖它们的元数据
+            let existing = match repo.find_existing(chunk).await {
+                Ok(existing) => existing,
+                Err(e) => {
+                    tracing::error!("Batch check failed: {}", e);
+                    failure_count += chunk.len() as u64;
+                    self.failure_count.store(failure_count, Ordering::SeqCst);
+                    continue;
+                }
+            };
+            let (known, new): (Vec<PathBuf>, Vec<PathBuf>) = chunk
+                .iter()
+                .cloned()
+                .partition(|path| existing.contains_key(&path_key(&path.to_string_lossy())));
+
+            match repo.mark_pending_extraction(&known).await {
+                Ok(_) => success_count += known.len() as u64,
+                Err(e) => {
+                    tracing::error!("Failed to flag modified files: {}", e);
+                    failure_count += known.len() as u64;
+                }
+            }
+
+            let placeholders: Vec<MediaFile> = new
+                .iter()
+                .filter_map(|path| Self::placeholder_media_file(path, &self.processors))
+                .collect();
+            failure_count += (new.len() - placeholders.len()) as u64;
+            match repo.batch_upsert(&placeholders).await {
+                Ok(_) => success_count += placeholders.len() as u64,
+                Err(e) => {
+                    tracing::error!("Batch upsert failed: {}", e);
+                    failure_count += placeholders.len() as u64;
+                }
+            }
+
+            for _ in chunk {
+                self.scan_state.increment_success();
+            }
+            self.success_count.store(success_count, Ordering::SeqCst);
+            self.failure_count.store(failure_count, Ordering::SeqCst);
+        }
+
+        false
+    }
+
+    /// Placeholder row of a quick scan: name, type, size and file times only.
+    /// None for files no processor handles (a full scan would fail on them as well)
+    fn placeholder_media_file(path: &Path, processors: &ProcessorRegistry) -> Option<MediaFile> {
+        let processor = processors.find_processor(path)?;
+        let file_type = if processor.media_type() == crate::processors::MediaType::Video {
+            "video"
+        } else {
+            "image"
+        };
+        let file_name = path.file_name()
+            .and_then(|n| n.to_str())
+            .unwrap_or("unknown")
+            .to_string();
+
+        let file_metadata = crate::processors::file_metadata::extract_fs_metadata(path);
+        let mut media_file = Self::build_media_file(
+            path,
+            file_name,
+            file_type,
+            &file_metadata,
+            &MediaMetadata::default(),
+        );
+        media_file.pending_extraction = true;
+        Some(media_file)
+    }
+
+    /// Parallel metadata extraction using semaphore-controlled concurrency.
+    /// Each result is sent to the writer as soon as its file is done;
+    /// progress is reported via scan_state.
+    /// At most worker_count tasks exist at a time and a full channel pauses extraction,
+    /// so memory stays flat regardless of library size.
+    async fn parallel_extract_metadata(&self, files: &[PathBuf], results: mpsc::Sender<ProcessingResult>) {
         let worker_count = self.get_worker_count();
         let semaphore = Arc::new(Semaphore::new(worker_count));
 
-        // Clone files to owned Vec for 'static lifetime
-        let files_owned: Vec<PathBuf> = files.to_vec();
         let processors = self.processors.clone();
         let is_cancelled = self.is_cancelled.clone();
         let scan_state = self.scan_state.clone();
+        let quiet_hours = self.quiet_hours.clone();
+        let io_throttle = self.io_throttle.clone();
 
-        // Use scoped spawn to avoid 'static lifetime requirement
-        let mut handles = Vec::new();
+        let mut tasks = tokio::task::JoinSet::new();
+
+        for path in files {
+            // 先取得许可再创建任务，而不是一次为所有文件创建任务
+            let Ok(permit) = semaphore.clone().acquire_owned().await else {
+                break;
+            };
+            if is_cancelled.load(Ordering::SeqCst) {
+                break;
+            }
+            // 回收已结束的任务
+            while tasks.try_join_next().is_some() {}
 
-        for path in &files_owned {
-            let permit = semaphore.clone().acquire_owned();
             let path = path.clone();
             let processors = processors.clone();
             let is_cancelled = is_cancelled.clone();
             let scan_state = scan_state.clone();
-
-            handles.push(tokio::spawn(async move {
-                let _permit = permit.await;
+            let quiet_hours = quiet_hours.clone();
+            let io_throttle = io_throttle.clone();
+            let results = results.clone();
+            let takeout_sidecars = self.config.takeout_sidecars;
+
+            // 解码等 CPU 密集工作在低优先级线程池中执行
+            tasks.spawn(background(async move {
+                let _permit = permit;
+                // 静默时段内再受一层更小的并发限制（或暂停到时段结束）
+                let _quiet_permit = match &quiet_hours {
+                    Some(quiet_hours) => quiet_hours.acquire().await,
+                    None => None,
+                };
 
                 // Check if cancelled before processing
                 if is_cancelled.load(Ordering::SeqCst) {
-                    // Return None for cancelled files - they won't be counted
-                    return None;
+                    // Cancelled files are not sent - they won't be counted
+                    return;
+                }
+
+                // 按预计读取量限速，并计入扫描进度中的读取速率
+                let read_bytes = estimated_read_bytes(&path).await;
+                if let Some(io_throttle) = &io_throttle {
+                    io_throttle.consume(read_bytes).await;
                 }
+                scan_state.add_bytes_read(read_bytes);
 
                 // Process the file
-                match Self::extract_single_metadata(&path, &processors).await {
-                    Ok(media_file) => {
+                let started = Instant::now();
+                let result = match Self::extract_single_metadata(&path, &processors, takeout_sidecars).await {
+                    Ok((media_file, extras)) => {
                         scan_state.increment_success();
-                        Some(ProcessingResult {
+                        ProcessingResult {
                             path,
                             success: Some(media_file),
+                            extras,
                             error: None,
-                        })
+                            duration: started.elapsed(),
+                        }
                     },
                     Err(e) => {
                         scan_state.increment_failure();
-                        Some(ProcessingResult {
+                        ProcessingResult {
                             path,
                             success: None,
+                            extras: FileExtras::default(),
                             error: Some(e.to_string()),
-                        })
+                            duration: started.elapsed(),
+                        }
                     },
-                }
+                };
+                // writer 因取消提前退出时发送失败，忽略即可
+                let _ = results.send(result).await;
             }));
         }
 
         // Wait for all tasks to complete
-        let mut all_results = Vec::with_capacity(handles.len());
-        for handle in handles {
-            if let Ok(Some(result)) = handle.await {
-                all_results.push(result);
-            }
-        }
-
-        // Sort results to maintain order
-        all_results.sort_by_key(|r| r.path.clone());
-
-        all_results
+        while tasks.join_next().await.is_some() {}
     }
 
     /// Build a MediaFile from metadata extracted from a file.
@@ -465,36 +1098,55 @@ impl ScanService {
         media_file.video_codec = format_metadata.video_codec.clone();
         media_file.gps_latitude = format_metadata.gps_latitude;
         media_file.gps_longitude = format_metadata.gps_longitude;
+        media_file.has_depth_map = format_metadata.has_depth_map;
+        media_file.has_portrait_matte = format_metadata.has_portrait_matte;
+        media_file.projection = format_metadata.projection.clone();
+        media_file.hdr_format = format_metadata.hdr_format.clone();
+        media_file.bit_depth = format_metadata.bit_depth;
+        media_file.color_primaries = format_metadata.color_primaries.clone();
+        media_file.color_profile = format_metadata.color_profile.clone();
+        media_file.content_hash = file_metadata.content_hash.clone();
+        media_file.blur_score = format_metadata.blur_score;
+        media_file.orientation = format_metadata.orientation;
+
+        // Filename date: fallback for files without EXIF (WhatsApp, screenshots, ...)
+        media_file.filename_timestamp =
+            crate::processors::filename_date::parse_filename_date(&media_file.file_name);
+        media_file.date_source = media_file
+            .get_effective_sort_time_with_source()
+            .map(|(_, source)| source.as_str().to_string());
 
         media_file
     }
 
-    /// Extract metadata for a single file
+    /// Extract metadata (plus sub-images and attributes) for a single file
     /// Uses spawn_blocking for synchronous file metadata extraction to avoid blocking async runtime
     async fn extract_single_metadata(
         path: &Path,
         processors: &ProcessorRegistry,
-    ) -> Result<MediaFile, Box<dyn std::error::Error>> {
-        let path_buf = path.to_path_buf();
-        let processors = processors.clone();
-
-        // Clone for spawn_blocking (since path_buf is moved into the closure)
-        let path_for_blocking = path_buf.clone();
+        takeout_sidecars: bool,
+    ) -> Result<(MediaFile, FileExtras), Box<dyn std::error::Error>> {
+        // Owned copy for spawn_blocking (moved into the closure)
+        let path_for_blocking = path.to_path_buf();
         // Run synchronous file metadata extraction in blocking thread pool
-        let file_metadata = tokio::task::spawn_blocking(move || {
+        let file_metadata = background_priority::spawn_blocking(move || {
             crate::processors::file_metadata::extract_file_metadata(&path_for_blocking)
         }).await
         .map_err(|e| Box::new(std::io::Error::other(e.to_string())))?;
 
         // Extract format-specific metadata (async, may contain internal blocking operations)
-        let processor = processors.find_processor(&path_buf).ok_or_else(|| {
+        let processor = processors.find_processor(path).ok_or_else(|| {
             std::io::Error::new(std::io::ErrorKind::Unsupported, "No processor found")
         })?;
 
-        let format_metadata = processor.process(&path_buf).await?;
+        let mut format_metadata = processor.process(path).await?;
+        let extras = FileExtras {
+            sub_images: format_metadata.sub_images.take(),
+            attributes: format_metadata.attributes.take(),
+        };
 
         // Build MediaFile using consolidated helper function
-        let file_name = path_buf.file_name()
+        let file_name = path.file_name()
             .and_then(|n| n.to_str())
             .unwrap_or("unknown")
             .to_string();
@@ -505,43 +1157,60 @@ impl ScanService {
             "image"
         };
 
-        let media_file = Self::build_media_file(
-            &path_buf,
+        let mut media_file = Self::build_media_file(
+            path,
             file_name,
             file_type,
             &file_metadata,
             &format_metadata,
         );
 
-        Ok(media_file)
+        if takeout_sidecars {
+            let path = path.to_path_buf();
+            if let Ok(Some(sidecar)) = tokio::task::spawn_blocking(move || takeout::read_sidecar(&path)).await {
+                apply_takeout_sidecar(&mut media_file, sidecar);
+            }
+        }
+
+        Ok((media_file, extras))
     }
 
-    /// Batch write results to database and update last_scanned for unchanged files
-    /// Returns true if the write was cancelled mid-way
-    async fn batch_write_results_with_skip(
-        &self,
-        results: Vec<ProcessingResult>,
-        skip_list: &[PathBuf],
-        _total: u64
-    ) -> bool {
-        let batch_size = self.config.db_batch_write_size;
-        let repo = MediaFileRepository::new(&self.db);
+    /// Writer task: upserts results in batches of up to db_batch_write_size as they arrive.
+    /// Returns (cancelled mid-way, per-file timings)
+    async fn write_results(&self, mut results: mpsc::Receiver<ProcessingResult>) -> (bool, TimingSummary) {
+        let batch_size = self.config.db_batch_write_size.max(1);
 
         let mut success_count = 0u64;
         let mut failure_count = 0u64;
-        let mut cancelled = false;
+        let mut timings = TimingSummary::new();
+        let mut chunk = Vec::with_capacity(batch_size);
 
-        // Write processed files
-        for chunk in results.chunks(batch_size) {
+        // 有结果就写：一次取走已就绪的结果（最多 batch_size 个），不等待凑满批次
+        while results.recv_many(&mut chunk, batch_size).await > 0 {
             // 检查是否需要取消，但先完成当前批次的处理
             let should_cancel = self.is_cancelled.load(Ordering::SeqCst);
 
-            let files: Vec<MediaFile> = chunk.iter()
-                .filter_map(|r| r.success.clone())
-                .collect();
+            // 结果按值拆开：MediaFile 进入写入批次，路径与错误移入耗时汇总，不再逐个克隆
+            let mut files: Vec<MediaFile> = Vec::with_capacity(chunk.len());
+            let mut extras: Vec<(String, FileExtras)> = Vec::new();
+            for ProcessingResult { path, success, extras: file_extras, error, duration } in chunk.drain(..) {
+                match success {
+                    Some(media_file) => {
+                        if file_extras.sub_images.is_some() || file_extras.attributes.is_some() {
+                            extras.push((media_file.file_path.clone(), file_extras));
+                        }
+                        files.push(media_file);
+                    }
+                    None => {
+                        failure_count += 1;
+                        tracing::warn!("Failed to process {}: {}", path.display(), error.as_deref().unwrap_or_default());
+                    }
+                }
+                timings.record(FileTiming { path, duration, error });
+            }
 
             if !files.is_empty() {
-                match repo.batch_upsert(&files).await {
+                match self.write_batch(&files, &extras).await {
                     Ok(_) => {
                         success_count += files.len() as u64;
                     }
@@ -552,33 +1221,59 @@ impl ScanService {
                 }
             }
 
-            for r in chunk {
-                if r.success.is_none() {
-                    failure_count += 1;
-                    tracing::warn!("Failed to process {}: {}", r.path.display(), r.error.clone().unwrap_or_default());
-                }
-            }
-
             self.success_count.store(success_count, Ordering::SeqCst);
             self.failure_count.store(failure_count, Ordering::SeqCst);
 
             // 在完成当前批次后，如果检测到取消，则退出
             if should_cancel {
-                cancelled = true;
                 tracing::info!("Scan cancelled during writing, saved {} files so far", success_count);
-                break;
+                return (true, timings);
             }
         }
 
-        // Update last_scanned for unchanged files (batch touch)
-        // Even if cancelled, we still update skip_list for files that weren't processed
-        if !skip_list.is_empty() && !cancelled {
-            if let Err(e) = repo.batch_touch(skip_list).await {
-                tracing::error!("Batch touch failed: {}", e);
+        (false, timings)
+    }
+
+    /// Update last_scanned for unchanged files (batch touch)
+    async fn touch_unchanged(&self, skip_list: &HashSet<PathBuf>) {
+        if skip_list.is_empty() {
+            return;
+        }
+        if let Err(e) = MediaFileRepository::new(&self.db).batch_touch(skip_list).await {
+            tracing::error!("Batch touch failed: {}", e);
+        }
+    }
+
+    /// Store a batch of files with their sub-images and attributes in one transaction, so a
+    /// failure leaves neither rows without their extras nor extras of rows that were not written
+    async fn write_batch(&self, files: &[MediaFile], extras: &[(String, FileExtras)]) -> Result<(), sqlx::Error> {
+        let mut tx = RepositoryTx::begin(&self.db).await?;
+        tx.media_files().batch_upsert(files).await?;
+        Self::write_extras(&mut tx.media_files(), extras).await?;
+        tx.commit().await
+    }
+
+    /// Store the sub-images and attributes of the files in a written batch, keyed by file path
+    async fn write_extras(repo: &mut MediaFileTxRepository<'_>, extras: &[(String, FileExtras)]) -> Result<(), sqlx::Error> {
+        for (file_path, extras) in extras {
+            if let Some(images) = &extras.sub_images {
+                repo.replace_sub_images(file_path, images).await?;
+            }
+            if let Some(attributes) = &extras.attributes {
+                repo.replace_attributes(file_path, maker_notes::SOURCE, attributes).await?;
             }
         }
+        Ok(())
+    }
 
-        cancelled
+    /// Apply LATTE_PATH_PREFIX_MAP to the stored paths (a no-op once they are rewritten)
+    async fn remap_path_prefixes(&self) {
+        let repo = MediaFileRepository::new(&self.db);
+        for (from, to) in self.config.path_prefix_map.rules() {
+            if let Err(e) = repo.rewrite_path_prefix(&from.to_string_lossy(), &to.to_string_lossy()).await {
+                tracing::warn!("Failed to rewrite path prefix {:?} -> {:?}: {}", from, to, e);
+            }
+        }
     }
 
     async fn delete_missing(&self, existing_files: &[PathBuf]) {
@@ -597,11 +1292,26 @@ impl ScanService {
         if let Ok(count) = repo.delete_missing(&existing_paths).await {
             tracing::info!("Deleted {} missing files", count);
         }
+
+        // 同步变更日志只保留 LATTE_SYNC_RETENTION_DAYS 天，0 为永久保留
+        if let Some(before) = change_log_cutoff(self.config.sync_retention_days, chrono::Utc::now().naive_utc()) {
+            match ChangeLogRepository::new(&self.db).prune(before).await {
+                Ok(0) => {}
+                Ok(count) => tracing::debug!("Pruned {} media change log entries", count),
+                Err(e) => tracing::warn!("Failed to prune the media change log: {}", e),
+            }
+        }
     }
 
-    /// Cancel the current scan
+    /// Whether a scan (or backfill) is currently running
+    pub fn is_scanning(&self) -> bool {
+        self.is_scanning.load(Ordering::SeqCst)
+    }
+
+    /// Cancel the current scan, dropping any queued scan as well
     pub async fn cancel(&self) -> bool {
         if self.is_scanning.load(Ordering::SeqCst) {
+            self.queued_scan.lock().unwrap().take();
             self.is_cancelled.store(true, Ordering::SeqCst);
             true
         } else {
@@ -609,3 +1319,154 @@ impl ScanService {
         }
     }
 }
+
+/// Files that need metadata extraction: every collected file not in the skip list, in collection order.
+/// O(n) thanks to the HashSet lookups (the scan used to be quadratic here).
+fn files_to_process(files: &[PathBuf], skip_list: &HashSet<PathBuf>) -> Vec<PathBuf> {
+    files
+        .iter()
+        .filter(|path| !skip_list.contains(*path))
+        .cloned()
+        .collect()
+}
+
+/// Order files by modify_time, newest first, so recent photos are extracted and written
+/// before the rest of a large archive. Files whose mtime can't be read go last.
+fn newest_first(mut files: Vec<PathBuf>) -> Vec<PathBuf> {
+    files.sort_by_cached_key(|path| {
+        std::cmp::Reverse(path.metadata().and_then(|m| m.modified()).ok())
+    });
+    files
+}
+
+/// Change log entries older than this are pruned; None keeps the whole history (retention 0)
+fn change_log_cutoff(retention_days: u64, now: chrono::NaiveDateTime) -> Option<chrono::NaiveDateTime> {
+    (retention_days > 0).then(|| now - chrono::Duration::days(retention_days as i64))
+}
+
+/// Fill what the file itself lacks from its Google Takeout sidecar. Embedded metadata always wins;
+/// the description only reaches files without one (see the upsert).
+fn apply_takeout_sidecar(media_file: &mut MediaFile, sidecar: TakeoutMetadata) {
+    if media_file.exif_timestamp.is_none() {
+        if let Some(taken_time) = sidecar.taken_time {
+            media_file.exif_timestamp = Some(taken_time);
+            media_file.exif_timezone_offset = Some("+00:00".to_string());
+            if let Some((_, DateSource::Exif | DateSource::Container)) = media_file.get_effective_sort_time_with_source() {
+                media_file.date_source = Some(DateSource::Sidecar.as_str().to_string());
+            }
+        }
+    }
+    if media_file.gps_latitude.is_none() && media_file.gps_longitude.is_none() {
+        media_file.gps_latitude = sidecar.latitude;
+        media_file.gps_longitude = sidecar.longitude;
+    }
+    if media_file.description.is_none() {
+        media_file.description = sidecar.description;
+    }
+}
+
+/// Directory row for a scanned directory, with overrides from its .latte.json
+async fn directory_entry(dir: &Path) -> DirectoryEntry {
+    let config = folder_config::read_folder_config(dir).await.unwrap_or_default();
+    DirectoryEntry {
+        path: dir.to_string_lossy().to_string(),
+        parent_path: dir.parent().map(|p| p.to_string_lossy().to_string()),
+        name: dir
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_default(),
+        display_name: config.display_name,
+        cover_path: config.cover.map(|cover| dir.join(cover).to_string_lossy().to_string()),
+    }
+}
+
+/// Bytes metadata extraction is expected to read from a file
+async fn estimated_read_bytes(path: &Path) -> u64 {
+    fs::metadata(path)
+        .await
+        .map(|m| m.len().min(MAX_READ_BYTES_PER_FILE))
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scan_mode_merge_keeps_most_thorough() {
+        assert_eq!(ScanMode::Quick.merge(ScanMode::Incremental), ScanMode::Incremental);
+        assert_eq!(ScanMode::Incremental.merge(ScanMode::Quick), ScanMode::Incremental);
+        assert_eq!(ScanMode::Force.merge(ScanMode::Quick), ScanMode::Force);
+        assert_eq!(ScanMode::Quick.merge(ScanMode::Force), ScanMode::Force);
+    }
+
+    #[test]
+    fn test_change_log_cutoff() {
+        let now = chrono::NaiveDate::from_ymd_opt(2024, 3, 31).unwrap().and_hms_opt(12, 0, 0).unwrap();
+        assert_eq!(change_log_cutoff(0, now), None);
+        assert_eq!(
+            change_log_cutoff(30, now),
+            Some(chrono::NaiveDate::from_ymd_opt(2024, 3, 1).unwrap().and_hms_opt(12, 0, 0).unwrap())
+        );
+    }
+
+    #[test]
+    fn test_takeout_sidecar_never_overrides_embedded_metadata() {
+        let exif_time = chrono::NaiveDate::from_ymd_opt(2020, 5, 1).unwrap().and_hms_opt(9, 0, 0).unwrap();
+        let mut file = MediaFile::new("/photos/a.jpg".to_string(), "a.jpg".to_string(), "image".to_string());
+        file.exif_timestamp = Some(exif_time);
+        file.gps_latitude = Some(1.0);
+        file.gps_longitude = Some(2.0);
+
+        apply_takeout_sidecar(
+            &mut file,
+            TakeoutMetadata {
+                taken_time: Some(exif_time - chrono::Duration::hours(3)),
+                latitude: Some(48.0),
+                longitude: Some(2.3),
+                description: Some("Paris".to_string()),
+            },
+        );
+        assert_eq!(file.exif_timestamp, Some(exif_time));
+        assert_eq!(file.gps_latitude, Some(1.0));
+        assert_eq!(file.description.as_deref(), Some("Paris"));
+    }
+
+    #[test]
+    fn test_newest_first_orders_by_modify_time() {
+        let dir = tempfile::tempdir().unwrap();
+        let now = std::time::SystemTime::now();
+        let mut files = Vec::new();
+        for (name, age_days) in [("old.jpg", 30), ("new.jpg", 0), ("mid.jpg", 7)] {
+            let path = dir.path().join(name);
+            let file = std::fs::File::create(&path).unwrap();
+            file.set_modified(now - Duration::from_secs(age_days * 86400)).unwrap();
+            files.push(path);
+        }
+        files.push(dir.path().join("missing.jpg"));
+
+        let names: Vec<_> = newest_first(files)
+            .iter()
+            .map(|p| p.file_name().unwrap().to_string_lossy().to_string())
+            .collect();
+        assert_eq!(names, ["new.jpg", "mid.jpg", "old.jpg", "missing.jpg"]);
+    }
+
+    /// 10 万个文件、一半未变化：线性时间内完成（原先逐个 iter().any() 需要数十亿次比较）
+    #[test]
+    fn test_files_to_process_large_library() {
+        let files: Vec<PathBuf> = (0..100_000)
+            .map(|i| PathBuf::from(format!("/photos/{:03}/IMG_{:06}.jpg", i % 500, i)))
+            .collect();
+        let skip_list: HashSet<PathBuf> = files.iter().step_by(2).cloned().collect();
+
+        let started = Instant::now();
+        let to_process = files_to_process(&files, &skip_list);
+        let elapsed = started.elapsed();
+
+        assert_eq!(to_process.len(), 50_000);
+        assert_eq!(to_process[0], files[1]);
+        assert!(to_process.iter().all(|p| !skip_list.contains(p)));
+        assert!(elapsed < Duration::from_secs(2), "took {:?}", elapsed);
+    }
+}
@@ -0,0 +1,286 @@
+//! 定时扫描摘要通知
+//! 定时扫描结束后，把新增/更新/删除/失败数量和耗时按模板渲染成一段文本，
+//! 通过 ntfy（POST 到主题地址）和/或 SMTP 邮件发出。投递在后台进行，失败只记录日志。
+//! 手动触发的扫描不发送：用户在界面上已经能看到结果。
+
+use crate::config::Config;
+use crate::services::scan_report::ScanReport;
+use crate::services::ScanMode;
+use lettre::message::header::ContentType;
+use lettre::message::Mailbox;
+use lettre::{AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor};
+use std::time::Duration;
+use thiserror::Error;
+
+/// Default message body (LATTE_SCAN_SUMMARY_TEMPLATE)
+pub const DEFAULT_SUMMARY_TEMPLATE: &str =
+    "Scan {status} ({mode}): {added} added, {updated} updated, {deleted} deleted, {failed} failed in {duration}";
+
+/// Timeout of one ntfy request
+const NTFY_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Error)]
+pub enum ScanSummaryError {
+    #[error("Invalid SMTP URL: {0}")]
+    SmtpUrl(#[from] lettre::transport::smtp::Error),
+
+    #[error("Invalid email address {0}: {1}")]
+    Address(String, lettre::address::AddressError),
+
+    #[error("LATTE_SCAN_SUMMARY_EMAIL_TO is empty")]
+    NoRecipients,
+}
+
+/// Outcome of one scan, as rendered into the message
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScanSummary {
+    /// "completed", "cancelled" or "failed"
+    pub status: String,
+    pub mode: ScanMode,
+    pub added: u64,
+    pub updated: u64,
+    pub deleted: u64,
+    pub failed: u64,
+    /// Media files found in the library
+    pub total: u64,
+    pub duration: Duration,
+    pub error: Option<String>,
+}
+
+impl ScanSummary {
+    pub fn from_report(report: &ScanReport, added: u64, updated: u64, deleted: u64) -> Self {
+        Self {
+            status: report.status.clone(),
+            mode: report.mode,
+            added,
+            updated,
+            deleted,
+            failed: report.failed_files,
+            total: report.total_files,
+            duration: Duration::from_millis(report.duration_ms),
+            error: None,
+        }
+    }
+
+    /// A scan that stopped before processing any file
+    pub fn failed(mode: ScanMode, duration: Duration, error: String) -> Self {
+        Self {
+            status: "failed".to_string(),
+            mode,
+            added: 0,
+            updated: 0,
+            deleted: 0,
+            failed: 0,
+            total: 0,
+            duration,
+            error: Some(error),
+        }
+    }
+
+    /// Fill the `{placeholder}`s of `template`; unknown placeholders are left as they are
+    pub fn render(&self, template: &str) -> String {
+        let mode = match self.mode {
+            ScanMode::Incremental => "incremental",
+            ScanMode::Force => "force",
+            ScanMode::Quick => "quick",
+        };
+        [
+            ("{status}", self.status.clone()),
+            ("{mode}", mode.to_string()),
+            ("{added}", self.added.to_string()),
+            ("{updated}", self.updated.to_string()),
+            ("{deleted}", self.deleted.to_string()),
+            ("{failed}", self.failed.to_string()),
+            ("{total}", self.total.to_string()),
+            ("{duration}", format_duration(self.duration)),
+            ("{error}", self.error.clone().unwrap_or_default()),
+        ]
+        .iter()
+        .fold(template.to_string(), |text, (placeholder, value)| text.replace(placeholder, value))
+    }
+
+    fn title(&self) -> String {
+        format!("LatteAlbum scan {}", self.status)
+    }
+}
+
+/// "42.3s", "3m 05s", "1h 02m"
+fn format_duration(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    if secs < 60 {
+        format!("{:.1}s", duration.as_secs_f64())
+    } else if secs < 3600 {
+        format!("{}m {:02}s", secs / 60, secs % 60)
+    } else {
+        format!("{}h {:02}m", secs / 3600, secs % 3600 / 60)
+    }
+}
+
+struct EmailTarget {
+    transport: AsyncSmtpTransport<Tokio1Executor>,
+    from: Mailbox,
+    to: Vec<Mailbox>,
+}
+
+/// Sends scan summaries to ntfy and/or by email
+pub struct ScanSummaryNotifier {
+    template: String,
+    ntfy_url: Option<String>,
+    ntfy_token: Option<String>,
+    email: Option<EmailTarget>,
+    client: reqwest::Client,
+}
+
+impl ScanSummaryNotifier {
+    /// Build from the LATTE_SCAN_SUMMARY_* settings; None when neither ntfy nor SMTP is configured
+    pub fn from_config(config: &Config) -> Result<Option<Self>, ScanSummaryError> {
+        let email = match config.scan_summary_smtp_url.as_deref() {
+            Some(url) => {
+                let parse = |address: &str| {
+                    address
+                        .parse::<Mailbox>()
+                        .map_err(|e| ScanSummaryError::Address(address.to_string(), e))
+                };
+                let to = config
+                    .scan_summary_email_to
+                    .iter()
+                    .map(|address| parse(address))
+                    .collect::<Result<Vec<_>, _>>()?;
+                if to.is_empty() {
+                    return Err(ScanSummaryError::NoRecipients);
+                }
+                Some(EmailTarget {
+                    transport: AsyncSmtpTransport::<Tokio1Executor>::from_url(url)?.build(),
+                    from: parse(&config.scan_summary_email_from)?,
+                    to,
+                })
+            }
+            None => None,
+        };
+
+        if email.is_none() && config.scan_summary_ntfy_url.is_none() {
+            return Ok(None);
+        }
+        Ok(Some(Self {
+            template: config.scan_summary_template.clone(),
+            ntfy_url: config.scan_summary_ntfy_url.clone(),
+            ntfy_token: config.scan_summary_ntfy_token.clone(),
+            email,
+            client: reqwest::Client::new(),
+        }))
+    }
+
+    /// Send `summary` to every configured target
+    pub async fn send(&self, summary: &ScanSummary) {
+        let title = summary.title();
+        let text = summary.render(&self.template);
+
+        if let Some(url) = &self.ntfy_url {
+            if let Err(e) = self.send_ntfy(url, &title, &text, summary.status != "completed").await {
+                tracing::warn!("Failed to send scan summary to ntfy: {}", e);
+            }
+        }
+        if let Some(email) = &self.email {
+            if let Err(e) = send_email(email, &title, &text).await {
+                tracing::warn!("Failed to send scan summary email: {}", e);
+            }
+        }
+    }
+
+    async fn send_ntfy(&self, url: &str, title: &str, text: &str, warning: bool) -> Result<(), reqwest::Error> {
+        let mut request = self
+            .client
+            .post(url)
+            .header("Title", title)
+            .timeout(NTFY_TIMEOUT)
+            .body(text.to_string());
+        if warning {
+            request = request.header("Tags", "warning").header("Priority", "high");
+        }
+        if let Some(token) = &self.ntfy_token {
+            request = request.bearer_auth(token);
+        }
+        request.send().await?.error_for_status()?;
+        Ok(())
+    }
+}
+
+async fn send_email(email: &EmailTarget, subject: &str, text: &str) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let mut builder = Message::builder()
+        .from(email.from.clone())
+        .subject(subject)
+        .header(ContentType::TEXT_PLAIN);
+    for to in &email.to {
+        builder = builder.to(to.clone());
+    }
+    let message = builder.body(text.to_string())?;
+    email.transport.send(message).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn summary() -> ScanSummary {
+        ScanSummary {
+            status: "completed".to_string(),
+            mode: ScanMode::Incremental,
+            added: 12,
+            updated: 3,
+            deleted: 1,
+            failed: 2,
+            total: 1500,
+            duration: Duration::from_secs(185),
+            error: None,
+        }
+    }
+
+    #[test]
+    fn test_render_default_template() {
+        assert_eq!(
+            summary().render(DEFAULT_SUMMARY_TEMPLATE),
+            "Scan completed (incremental): 12 added, 3 updated, 1 deleted, 2 failed in 3m 05s"
+        );
+    }
+
+    #[test]
+    fn test_render_custom_template() {
+        let failed = ScanSummary::failed(ScanMode::Force, Duration::from_millis(1500), "Directory not found".to_string());
+        assert_eq!(
+            failed.render("[{status}/{mode}] {total} files, {duration} {error} {unknown}"),
+            "[failed/force] 0 files, 1.5s Directory not found {unknown}"
+        );
+    }
+
+    #[test]
+    fn test_format_duration() {
+        assert_eq!(format_duration(Duration::from_millis(42_300)), "42.3s");
+        assert_eq!(format_duration(Duration::from_secs(61)), "1m 01s");
+        assert_eq!(format_duration(Duration::from_secs(3720)), "1h 02m");
+    }
+
+    #[test]
+    fn test_from_config() {
+        assert!(ScanSummaryNotifier::from_config(&Config::default()).unwrap().is_none());
+
+        let config = Config {
+            scan_summary_ntfy_url: Some("https://ntfy.sh/album".to_string()),
+            ..Config::default()
+        };
+        assert!(ScanSummaryNotifier::from_config(&config).unwrap().is_some());
+
+        let config = Config {
+            scan_summary_smtp_url: Some("smtp://localhost:25".to_string()),
+            ..Config::default()
+        };
+        assert!(matches!(ScanSummaryNotifier::from_config(&config), Err(ScanSummaryError::NoRecipients)));
+
+        let config = Config {
+            scan_summary_smtp_url: Some("smtp://localhost:25".to_string()),
+            scan_summary_email_to: vec!["not an address".to_string()],
+            ..Config::default()
+        };
+        assert!(matches!(ScanSummaryNotifier::from_config(&config), Err(ScanSummaryError::Address(..))));
+    }
+}
@@ -1,29 +1,104 @@
+//! 定时扫描
+//! 按 LATTE_SCAN_CRON 在本地时间触发增量扫描。表达式为带秒的 6 段格式（Quartz 风格，
+//! 如 "0 0 2 * * ?"），也接受标准 5 段 crontab 格式（秒取 0）；"?" 视同 "*"。
+
 use crate::services::ScanService;
-use std::sync::Arc;
-use tracing::info;
+use cron::Schedule;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use tokio::task::AbortHandle;
+use tracing::{info, warn};
 
-/// Scheduler for periodic tasks (simplified)
-pub struct Scheduler;
+/// Scheduler for periodic scans
+pub struct Scheduler {
+    scan_service: Arc<ScanService>,
+    schedule: Mutex<Option<Schedule>>,
+    task: Mutex<Option<AbortHandle>>,
+    /// start() was called (also when there was no valid schedule to run)
+    started: AtomicBool,
+}
 
 impl Scheduler {
-    pub fn new(_scan_service: Arc<ScanService>, _cron_expr: &str) -> Self {
-        Self
+    pub fn new(scan_service: Arc<ScanService>, cron_expr: &str) -> Self {
+        let schedule = match parse_cron(cron_expr) {
+            Ok(schedule) => Some(schedule),
+            Err(e) => {
+                warn!("Invalid scan cron expression {:?}, scheduled scans disabled: {}", cron_expr, e);
+                None
+            }
+        };
+        Self {
+            scan_service,
+            schedule: Mutex::new(schedule),
+            task: Mutex::new(None),
+            started: AtomicBool::new(false),
+        }
     }
 
     /// Start the scheduler
     pub async fn start(&self) {
-        info!("Scheduler started (no-op - scheduled scans not implemented)");
+        self.started.store(true, Ordering::SeqCst);
+        let Some(schedule) = self.schedule.lock().unwrap().clone() else {
+            return;
+        };
+        let scan_service = self.scan_service.clone();
+        let task = tokio::spawn(async move {
+            while let Some(next) = schedule.upcoming(chrono::Local).next() {
+                info!("Next scheduled scan at {}", next);
+                let wait = (next - chrono::Local::now()).to_std().unwrap_or_default();
+                tokio::time::sleep(wait).await;
+                info!("Starting scheduled scan");
+                // 扫描在独立任务中运行，reschedule 中止调度任务时不会打断进行中的扫描
+                let scan_service = scan_service.clone();
+                let _ = tokio::spawn(async move { scan_service.scheduled_scan().await }).await;
+            }
+        });
+        if let Some(previous) = self.task.lock().unwrap().replace(task.abort_handle()) {
+            previous.abort();
+        }
+        info!("Scheduler started");
+    }
+
+    /// Switch to a new cron expression (configuration reload). A started scheduler restarts
+    /// with it; an invalid expression is rejected and the current schedule kept.
+    pub async fn reschedule(&self, cron_expr: &str) -> Result<(), cron::error::Error> {
+        let schedule = parse_cron(cron_expr)?;
+        *self.schedule.lock().unwrap() = Some(schedule);
+        if self.started.load(Ordering::SeqCst) {
+            self.start().await;
+        }
+        Ok(())
     }
 
     /// Stop the scheduler
     pub async fn stop(&self) {
+        self.started.store(false, Ordering::SeqCst);
+        if let Some(task) = self.task.lock().unwrap().take() {
+            task.abort();
+        }
         info!("Scheduler stopped");
     }
 }
 
+/// Parse a 5-field (crontab) or 6/7-field (with seconds/year) cron expression
+pub fn parse_cron(expr: &str) -> Result<Schedule, cron::error::Error> {
+    let fields: Vec<&str> = expr
+        .split_whitespace()
+        .map(|field| if field == "?" { "*" } else { field })
+        .collect();
+    let normalized = if fields.len() == 5 {
+        format!("0 {}", fields.join(" "))
+    } else {
+        fields.join(" ")
+    };
+    Schedule::from_str(&normalized)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use chrono::Timelike;
 
     #[tokio::test]
     async fn test_scheduler_new() {
@@ -63,4 +138,39 @@ mod tests {
         scheduler.start().await;
         scheduler.stop().await;
     }
+
+    #[tokio::test]
+    async fn test_reschedule() {
+        let scheduler = Scheduler::new(Arc::new(ScanService::new(
+            crate::config::Config::default(),
+            crate::db::DatabasePool::new(std::path::Path::new(":memory:")).await.unwrap(),
+            Arc::new(crate::processors::ProcessorRegistry::new(None)),
+            Arc::new(crate::websocket::ScanStateManager::new(tokio::sync::broadcast::channel(100).0)),
+        )), "not a cron");
+        assert!(scheduler.schedule.lock().unwrap().is_none());
+
+        scheduler.start().await;
+        assert!(scheduler.reschedule("also not a cron").await.is_err());
+        assert!(scheduler.task.lock().unwrap().is_none());
+
+        // 启动时表达式无效，重新加载为有效表达式后开始调度
+        scheduler.reschedule("0 30 3 * * ?").await.unwrap();
+        assert!(scheduler.task.lock().unwrap().is_some());
+        scheduler.stop().await;
+    }
+
+    #[test]
+    fn test_parse_cron() {
+        let next = parse_cron("0 0 2 * * ?").unwrap().upcoming(chrono::Local).next().unwrap();
+        assert_eq!((next.hour(), next.minute(), next.second()), (2, 0, 0));
+
+        // 5 段格式：分 时 日 月 周
+        let next = parse_cron("30 */6 * * *").unwrap().upcoming(chrono::Local).next().unwrap();
+        assert_eq!(next.minute(), 30);
+        assert_eq!(next.second(), 0);
+        assert_eq!(next.hour() % 6, 0);
+
+        assert!(parse_cron("not a cron").is_err());
+        assert!(parse_cron("").is_err());
+    }
 }
@@ -0,0 +1,87 @@
+//! 基于向量的语义搜索
+//! 查询文本由外部服务转换为向量，与数据库中存储的图片向量逐一计算余弦相似度（暴力搜索）。
+//! 图库规模在几十万张以内时暴力搜索足够快，无需额外的 ANN 索引。
+
+/// Cosine similarity of two vectors of the same length (0 for zero vectors)
+pub fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
+    dot(a, b) / (norm(a) * norm(b)).max(f32::MIN_POSITIVE)
+}
+
+/// Rank candidates by cosine similarity to `query`, best first.
+/// Candidates whose dimension differs from the query (another model) are ignored.
+pub fn rank(
+    query: &[f32],
+    candidates: impl IntoIterator<Item = (String, Vec<f32>)>,
+    min_score: f32,
+    limit: usize,
+) -> Vec<(String, f32)> {
+    let query_norm = norm(query);
+    if query_norm == 0.0 {
+        return Vec::new();
+    }
+
+    let mut scored: Vec<(String, f32)> = candidates
+        .into_iter()
+        .filter(|(_, vector)| vector.len() == query.len())
+        .filter_map(|(id, vector)| {
+            let vector_norm = norm(&vector);
+            if vector_norm == 0.0 {
+                return None;
+            }
+            let score = dot(query, &vector) / (query_norm * vector_norm);
+            (score >= min_score).then_some((id, score))
+        })
+        .collect();
+
+    scored.sort_by(|a, b| b.1.total_cmp(&a.1));
+    scored.truncate(limit);
+    scored
+}
+
+// 简单的逐元素循环，release 构建下会被自动向量化（SIMD）
+fn dot(a: &[f32], b: &[f32]) -> f32 {
+    a.iter().zip(b).map(|(x, y)| x * y).sum()
+}
+
+fn norm(v: &[f32]) -> f32 {
+    dot(v, v).sqrt()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cosine_similarity() {
+        assert!((cosine_similarity(&[1.0, 0.0], &[2.0, 0.0]) - 1.0).abs() < 1e-6);
+        assert!(cosine_similarity(&[1.0, 0.0], &[0.0, 1.0]).abs() < 1e-6);
+        assert!((cosine_similarity(&[1.0, 0.0], &[-1.0, 0.0]) + 1.0).abs() < 1e-6);
+        assert_eq!(cosine_similarity(&[0.0, 0.0], &[1.0, 0.0]), 0.0);
+    }
+
+    #[test]
+    fn test_rank_orders_and_limits() {
+        let candidates = vec![
+            ("far".to_string(), vec![0.0, 1.0]),
+            ("near".to_string(), vec![1.0, 0.1]),
+            ("exact".to_string(), vec![2.0, 0.0]),
+            ("other-model".to_string(), vec![1.0, 0.0, 0.0]),
+        ];
+        let ranked = rank(&[1.0, 0.0], candidates, 0.0, 2);
+        let ids: Vec<&str> = ranked.iter().map(|(id, _)| id.as_str()).collect();
+        assert_eq!(ids, vec!["exact", "near"]);
+    }
+
+    #[test]
+    fn test_rank_min_score() {
+        let candidates = vec![("a".to_string(), vec![1.0, 1.0]), ("b".to_string(), vec![0.0, 1.0])];
+        let ranked = rank(&[1.0, 0.0], candidates, 0.5, 10);
+        assert_eq!(ranked.len(), 1);
+        assert_eq!(ranked[0].0, "a");
+    }
+
+    #[test]
+    fn test_rank_zero_query() {
+        assert!(rank(&[0.0, 0.0], vec![("a".to_string(), vec![1.0, 0.0])], 0.0, 10).is_empty());
+    }
+}
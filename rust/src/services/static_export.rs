@@ -0,0 +1,395 @@
+//! 静态相册导出
+//! 把筛选出的文件渲染成自包含的静态相册（预生成缩略图 + JSON 索引 + 简单 HTML），
+//! 可以直接拷到 U 盘用浏览器打开，也可以放到任意静态托管上。
+//! 同一时间只运行一个导出任务，进度通过 GET /api/exports/progress 查询。
+
+use crate::config::Config;
+use crate::db::{DatabasePool, FileQuery, MediaFile, MediaFileRepository};
+use crate::services::FileService;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+
+/// Page size used when walking the filtered file list
+const SELECTION_PAGE_SIZE: i32 = 500;
+
+/// Grid thumbnail and viewer image sizes written for every file
+const GRID_SIZE: &str = "medium";
+const VIEWER_SIZE: &str = "large";
+
+/// Files to export: explicit ids, or the same filters as GET /api/files
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExportRequest {
+    /// Output folder name under the export directory
+    pub name: String,
+    /// Export exactly these files (filters are ignored when set)
+    #[serde(default)]
+    pub ids: Vec<String>,
+    pub path: Option<String>,
+    pub file_type: Option<String>,
+    pub camera_model: Option<String>,
+    /// Date prefix ("2024", "2024-05", "2024-05-01")
+    pub date: Option<String>,
+    /// Also copy the original files (much larger export)
+    #[serde(default)]
+    pub include_originals: bool,
+}
+
+/// State of the export job
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportStatus {
+    Idle,
+    Running,
+    Completed,
+    Cancelled,
+    Failed,
+}
+
+/// Progress of the current or last export
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExportProgress {
+    pub status: ExportStatus,
+    pub name: Option<String>,
+    pub total: u64,
+    pub exported: u64,
+    pub failed: u64,
+    pub output_dir: Option<String>,
+    pub error: Option<String>,
+    pub start_time: Option<String>,
+    pub end_time: Option<String>,
+}
+
+impl Default for ExportProgress {
+    fn default() -> Self {
+        Self {
+            status: ExportStatus::Idle,
+            name: None,
+            total: 0,
+            exported: 0,
+            failed: 0,
+            output_dir: None,
+            error: None,
+            start_time: None,
+            end_time: None,
+        }
+    }
+}
+
+/// Entry of index.json
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ExportedItem {
+    id: String,
+    file_name: String,
+    file_type: String,
+    width: Option<i32>,
+    height: Option<i32>,
+    taken_at: Option<String>,
+    title: Option<String>,
+    description: Option<String>,
+    thumbnail: String,
+    image: String,
+    original: Option<String>,
+}
+
+/// Why an export could not be started
+#[derive(Debug, PartialEq, Eq)]
+pub enum ExportStartError {
+    AlreadyRunning,
+    InvalidName,
+}
+
+/// Runs static gallery exports one at a time
+pub struct StaticExporter {
+    config: Config,
+    db: DatabasePool,
+    file_service: Arc<FileService>,
+    progress: Arc<Mutex<ExportProgress>>,
+    is_cancelled: Arc<AtomicBool>,
+}
+
+impl StaticExporter {
+    pub fn new(config: Config, db: DatabasePool, file_service: Arc<FileService>) -> Self {
+        Self {
+            config,
+            db,
+            file_service,
+            progress: Arc::new(Mutex::new(ExportProgress::default())),
+            is_cancelled: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    /// Progress of the current or last export
+    pub fn progress(&self) -> ExportProgress {
+        self.progress.lock().unwrap().clone()
+    }
+
+    /// Start an export in the background. Fails if one is already running or the name
+    /// is not a plain folder name.
+    pub fn start(self: &Arc<Self>, request: ExportRequest) -> Result<(), ExportStartError> {
+        if !is_valid_name(&request.name) {
+            return Err(ExportStartError::InvalidName);
+        }
+        let output_dir = self.config.get_export_dir().join(&request.name);
+        {
+            let mut progress = self.progress.lock().unwrap();
+            if progress.status == ExportStatus::Running {
+                return Err(ExportStartError::AlreadyRunning);
+            }
+            *progress = ExportProgress {
+                status: ExportStatus::Running,
+                name: Some(request.name.clone()),
+                output_dir: Some(output_dir.to_string_lossy().to_string()),
+                start_time: Some(chrono::Utc::now().to_rfc3339()),
+                ..Default::default()
+            };
+        }
+        self.is_cancelled.store(false, Ordering::SeqCst);
+
+        let exporter = self.clone();
+        tokio::spawn(async move {
+            let result = exporter.run(&request, &output_dir).await;
+            let mut progress = exporter.progress.lock().unwrap();
+            progress.status = match result {
+                Ok(()) if exporter.is_cancelled.load(Ordering::SeqCst) => ExportStatus::Cancelled,
+                Ok(()) => ExportStatus::Completed,
+                Err(e) => {
+                    tracing::error!("Export '{}' failed: {}", request.name, e);
+                    progress.error = Some(e.to_string());
+                    ExportStatus::Failed
+                }
+            };
+            progress.end_time = Some(chrono::Utc::now().to_rfc3339());
+            tracing::info!("Export '{}' finished: {:?} ({} files, {} failed)",
+                request.name, progress.status, progress.exported, progress.failed);
+        });
+        Ok(())
+    }
+
+    /// Request cancellation of the running export; returns false if none is running
+    pub fn cancel(&self) -> bool {
+        if self.progress.lock().unwrap().status != ExportStatus::Running {
+            return false;
+        }
+        self.is_cancelled.store(true, Ordering::SeqCst);
+        true
+    }
+
+    async fn run(&self, request: &ExportRequest, output_dir: &Path) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let files = self.select_files(request).await?;
+        self.progress.lock().unwrap().total = files.len() as u64;
+        tracing::info!("Exporting {} files to {:?}", files.len(), output_dir);
+
+        tokio::fs::create_dir_all(output_dir.join("thumbs")).await?;
+        if request.include_originals {
+            tokio::fs::create_dir_all(output_dir.join("originals")).await?;
+        }
+
+        let mut items = Vec::with_capacity(files.len());
+        for file in &files {
+            if self.is_cancelled.load(Ordering::SeqCst) {
+                break;
+            }
+            match self.export_file(file, output_dir, request.include_originals).await {
+                Some(item) => {
+                    items.push(item);
+                    self.progress.lock().unwrap().exported += 1;
+                }
+                None => self.progress.lock().unwrap().failed += 1,
+            }
+        }
+
+        // 取消时也写出索引，已导出的部分仍可浏览
+        let index = serde_json::to_string(&items)?;
+        tokio::fs::write(output_dir.join("index.json"), &index).await?;
+        // file:// 下浏览器不允许 fetch，索引同时以脚本形式内嵌
+        tokio::fs::write(output_dir.join("index.js"), format!("window.LATTE_EXPORT = {};\n", index)).await?;
+        tokio::fs::write(output_dir.join("index.html"), render_html(&request.name)).await?;
+        Ok(())
+    }
+
+    /// Files matching the request, newest first
+    async fn select_files(&self, request: &ExportRequest) -> Result<Vec<MediaFile>, sqlx::Error> {
+        let repo = MediaFileRepository::new(&self.db);
+        if !request.ids.is_empty() {
+            let mut files = Vec::with_capacity(request.ids.len());
+            for id in &request.ids {
+                if let Some(file) = repo.find_by_id(id).await? {
+                    files.push(file);
+                }
+            }
+            return Ok(files);
+        }
+
+        let query = FileQuery::new()
+            .path(request.path.as_deref())
+            .file_type(request.file_type.as_deref())
+            .camera_model(request.camera_model.as_deref())
+            .date(request.date.as_deref());
+        let mut files = Vec::new();
+        for page in 0.. {
+            let batch = repo.find_all(&query.clone().page(page, SELECTION_PAGE_SIZE)).await?;
+            let done = batch.len() < SELECTION_PAGE_SIZE as usize;
+            files.extend(batch);
+            if done {
+                break;
+            }
+        }
+        Ok(files)
+    }
+
+    /// Write the thumbnails (and original) of one file; None if the file could not be exported
+    async fn export_file(&self, file: &MediaFile, output_dir: &Path, include_originals: bool) -> Option<ExportedItem> {
+        let thumbnail = self.write_thumbnail(file, GRID_SIZE, output_dir).await?;
+        let image = self.write_thumbnail(file, VIEWER_SIZE, output_dir).await?;
+
+        let original = if include_originals {
+            let source = match self.file_service.library_roots().resolve(Path::new(&file.file_path)).await {
+                Ok(source) => source,
+                Err(e) => {
+                    tracing::warn!("Skipping original of {} in export: {:?}", file.id, e);
+                    return None;
+                }
+            };
+            let name = format!("originals/{}_{}", file.id, file.file_name);
+            if let Err(e) = tokio::fs::copy(&source, output_dir.join(&name)).await {
+                tracing::warn!("Failed to copy {} into export: {}", file.file_path, e);
+                return None;
+            }
+            Some(name)
+        } else {
+            None
+        };
+
+        Some(ExportedItem {
+            id: file.id.clone(),
+            file_name: file.file_name.clone(),
+            file_type: file.file_type.clone(),
+            width: file.width,
+            height: file.height,
+            taken_at: file.exif_timestamp
+                .or(file.create_time)
+                .or(file.modify_time)
+                .map(|t| t.format("%Y-%m-%dT%H:%M:%S").to_string()),
+            title: file.title.clone(),
+            description: file.description.clone(),
+            thumbnail,
+            image,
+            original,
+        })
+    }
+
+    /// Generate (or take from cache) one thumbnail size and write it into thumbs/
+    async fn write_thumbnail(&self, file: &MediaFile, size: &'static str, output_dir: &Path) -> Option<String> {
+        let target_size = self.config.get_thumbnail_size(size);
+        let (data, mime) = match self.file_service.get_thumbnail(&file.id, size, target_size, size == "large").await {
+            Ok(Some(thumbnail)) => thumbnail,
+            Ok(None) => {
+                tracing::warn!("No {} thumbnail for {} in export", size, file.id);
+                return None;
+            }
+            Err(e) => {
+                tracing::warn!("Failed to generate {} thumbnail for {} in export: {}", size, file.id, e);
+                return None;
+            }
+        };
+        let name = format!("thumbs/{}_{}.{}", file.id, size, extension_for_mime(&mime));
+        if let Err(e) = tokio::fs::write(output_dir.join(&name), &data).await {
+            tracing::warn!("Failed to write export thumbnail {}: {}", name, e);
+            return None;
+        }
+        Some(name)
+    }
+}
+
+/// Export names become folder names: no separators, no leading dot
+fn is_valid_name(name: &str) -> bool {
+    !name.is_empty()
+        && name.len() <= 128
+        && !name.starts_with('.')
+        && name.chars().all(|c| c.is_alphanumeric() || matches!(c, '-' | '_' | ' ' | '.'))
+}
+
+fn extension_for_mime(mime: &str) -> &'static str {
+    match mime {
+        "image/webp" => "webp",
+        "image/avif" => "avif",
+        "image/png" => "png",
+        _ => "jpg",
+    }
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Minimal gallery page: a thumbnail grid built from index.js, each thumbnail links to the
+/// large image (or the original when exported)
+fn render_html(name: &str) -> String {
+    let title = escape_html(name);
+    format!(r#"<!DOCTYPE html>
+<html>
+<head>
+<meta charset="utf-8">
+<meta name="viewport" content="width=device-width, initial-scale=1">
+<title>{title}</title>
+<style>
+body {{ margin: 0; padding: 16px; font-family: sans-serif; background: #111; color: #eee; }}
+h1 {{ font-size: 20px; font-weight: normal; }}
+#grid {{ display: grid; grid-template-columns: repeat(auto-fill, minmax(200px, 1fr)); gap: 8px; }}
+#grid a {{ display: block; aspect-ratio: 1; overflow: hidden; background: #222; }}
+#grid img {{ width: 100%; height: 100%; object-fit: cover; }}
+</style>
+</head>
+<body>
+<h1>{title}</h1>
+<div id="grid"></div>
+<script src="index.js"></script>
+<script>
+var grid = document.getElementById("grid");
+window.LATTE_EXPORT.forEach(function (item) {{
+  var link = document.createElement("a");
+  link.href = item.original || item.image;
+  link.title = item.title || item.fileName;
+  var img = document.createElement("img");
+  img.src = item.thumbnail;
+  img.loading = "lazy";
+  img.alt = item.fileName;
+  link.appendChild(img);
+  grid.appendChild(link);
+}});
+</script>
+</body>
+</html>
+"#)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_export_name_validation() {
+        assert!(is_valid_name("Kyoto 2023"));
+        assert!(is_valid_name("trip_2024-05.v2"));
+        assert!(!is_valid_name(""));
+        assert!(!is_valid_name(".."));
+        assert!(!is_valid_name("../etc"));
+        assert!(!is_valid_name("a/b"));
+        assert!(!is_valid_name("a\\b"));
+    }
+
+    #[test]
+    fn test_html_escapes_title() {
+        let html = render_html("<b>&</b>");
+        assert!(html.contains("<title>&lt;b&gt;&amp;&lt;/b&gt;</title>"));
+        assert!(html.contains(r#"<script src="index.js"></script>"#));
+    }
+}
@@ -0,0 +1,257 @@
+//! 外部打标签服务接入
+//! 定期把尚未打标签的图片分批发给外部 HTTP 服务（例如 CLIP/ONNX 旁路服务），
+//! 将返回的标签与向量存入数据库，本 crate 不内置任何机器学习模型。
+//!
+//! 请求：`POST {url}`，body `{"files": [{"id": "...", "path": "..."}]}`
+//! 响应：`{"results": [{"id": "...", "model": "...", "labels": [{"label": "cat", "score": 0.93}], "embedding": [0.1, ...]}]}`
+//!
+//! 语义搜索的查询文本发往 `LATTE_TAGGING_TEXT_URL`：请求 `{"text": "..."}`，响应 `{"embedding": [...]}`
+
+use crate::db::{DatabasePool, MediaLabel, TaggingRepository};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use thiserror::Error;
+use tokio::task::AbortHandle;
+
+/// Timeout of one tagging request (a whole batch)
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// Timeout of a text embedding request (interactive search)
+const TEXT_REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Delay before the first retry, doubled on each further retry
+const RETRY_BASE_DELAY: Duration = Duration::from_secs(1);
+
+#[derive(Debug, Error)]
+pub enum TaggingError {
+    #[error("Tagging request failed: {0}")]
+    Request(#[from] reqwest::Error),
+
+    #[error("Tagging service returned status {0}")]
+    Status(reqwest::StatusCode),
+
+    #[error("Database error: {0}")]
+    Database(#[from] sqlx::Error),
+}
+
+#[derive(Debug, Serialize)]
+struct TaggingRequest<'a> {
+    files: Vec<TaggingRequestFile<'a>>,
+}
+
+#[derive(Debug, Serialize)]
+struct TaggingRequestFile<'a> {
+    id: &'a str,
+    path: &'a str,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TaggingResponse {
+    #[serde(default)]
+    pub results: Vec<TaggingResult>,
+}
+
+/// Labels/embedding returned for one file
+#[derive(Debug, Deserialize)]
+pub struct TaggingResult {
+    pub id: String,
+    pub model: Option<String>,
+    #[serde(default)]
+    pub labels: Vec<MediaLabel>,
+    pub embedding: Option<Vec<f32>>,
+}
+
+#[derive(Debug, Deserialize)]
+struct TextEmbeddingResponse {
+    embedding: Vec<f32>,
+}
+
+/// Background worker sending new images to an external tagging service
+pub struct TaggingService {
+    text_client: Option<TaggingClient>,
+    _worker_task: AbortHandle,
+}
+
+impl TaggingService {
+    /// 创建并启动后台打标签任务
+    ///
+    /// # Arguments
+    ///
+    /// * `url` - Endpoint of the tagging service
+    /// * `batch_size` - Number of files per request
+    /// * `max_retries` - Retries of a failed request before waiting for the next poll
+    /// * `poll_interval` - Time between checks for untagged files
+    pub fn new(
+        db: DatabasePool,
+        url: &str,
+        batch_size: usize,
+        max_retries: u32,
+        poll_interval: Duration,
+    ) -> Self {
+        let client = TaggingClient::new(url, max_retries);
+        let batch_size = batch_size.max(1);
+
+        let worker_task = tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(poll_interval);
+            loop {
+                ticker.tick().await;
+                match tag_pending(&db, &client, batch_size).await {
+                    Ok(tagged) if tagged > 0 => tracing::info!("Tagged {} files", tagged),
+                    Ok(_) => {}
+                    Err(e) => tracing::warn!("Tagging run stopped: {}", e),
+                }
+            }
+        });
+
+        Self {
+            text_client: None,
+            _worker_task: worker_task.abort_handle(),
+        }
+    }
+
+    /// Enable text embedding (semantic search) through `url`.
+    /// Interactive requests are not retried.
+    pub fn with_text_url(mut self, url: &str) -> Self {
+        self.text_client = Some(TaggingClient::new(url, 0));
+        self
+    }
+
+    /// Embed a search query; None when no text endpoint is configured
+    pub async fn embed_text(&self, text: &str) -> Result<Option<Vec<f32>>, TaggingError> {
+        match &self.text_client {
+            Some(client) => client.embed_text(text).await.map(Some),
+            None => Ok(None),
+        }
+    }
+}
+
+/// HTTP client for the tagging service with retry
+pub struct TaggingClient {
+    url: String,
+    max_retries: u32,
+    client: reqwest::Client,
+}
+
+impl TaggingClient {
+    pub fn new(url: &str, max_retries: u32) -> Self {
+        Self {
+            url: url.to_string(),
+            max_retries,
+            client: reqwest::Client::new(),
+        }
+    }
+
+    /// Tag a batch of (id, path) files, retrying with exponential backoff
+    pub async fn tag(&self, files: &[(String, String)]) -> Result<Vec<TaggingResult>, TaggingError> {
+        let body = TaggingRequest {
+            files: files
+                .iter()
+                .map(|(id, path)| TaggingRequestFile { id, path })
+                .collect(),
+        };
+
+        let mut attempt = 0;
+        loop {
+            match self.send(&body).await {
+                Ok(response) => return Ok(response.results),
+                Err(e) if attempt < self.max_retries => {
+                    let delay = RETRY_BASE_DELAY * 2u32.pow(attempt);
+                    tracing::debug!("Tagging request failed ({}), retrying in {:?}", e, delay);
+                    tokio::time::sleep(delay).await;
+                    attempt += 1;
+                }
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    /// Embed a text query: POST `{"text": "..."}`, response `{"embedding": [...]}`
+    pub async fn embed_text(&self, text: &str) -> Result<Vec<f32>, TaggingError> {
+        let response = self
+            .client
+            .post(&self.url)
+            .json(&serde_json::json!({ "text": text }))
+            .timeout(TEXT_REQUEST_TIMEOUT)
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            return Err(TaggingError::Status(response.status()));
+        }
+        let response: TextEmbeddingResponse = response.json().await?;
+        Ok(response.embedding)
+    }
+
+    async fn send(&self, body: &TaggingRequest<'_>) -> Result<TaggingResponse, TaggingError> {
+        let response = self
+            .client
+            .post(&self.url)
+            .json(body)
+            .timeout(REQUEST_TIMEOUT)
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            return Err(TaggingError::Status(response.status()));
+        }
+        Ok(response.json().await?)
+    }
+}
+
+/// Tag all untagged images batch by batch. Returns the number of tagged files.
+/// Files missing from a response are recorded with no labels so they aren't resent forever.
+pub async fn tag_pending(db: &DatabasePool, client: &TaggingClient, batch_size: usize) -> Result<usize, TaggingError> {
+    let repo = TaggingRepository::new(db);
+    let mut tagged = 0;
+
+    loop {
+        let batch = repo.find_untagged(batch_size).await?;
+        if batch.is_empty() {
+            return Ok(tagged);
+        }
+
+        let mut results = client.tag(&batch).await?;
+        for (id, _) in &batch {
+            let result = results
+                .iter()
+                .position(|r| &r.id == id)
+                .map(|index| results.swap_remove(index));
+            match result {
+                Some(result) => {
+                    repo.save_result(id, result.model.as_deref(), &result.labels, result.embedding.as_deref())
+                        .await?;
+                }
+                None => repo.save_result(id, None, &[], None).await?,
+            }
+            tagged += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_response() {
+        let json = r#"{
+            "results": [
+                { "id": "a", "model": "clip-vit-b32", "labels": [{ "label": "cat", "score": 0.93 }], "embedding": [0.1, 0.2] },
+                { "id": "b", "labels": [{ "label": "beach" }] }
+            ]
+        }"#;
+        let response: TaggingResponse = serde_json::from_str(json).unwrap();
+        assert_eq!(response.results.len(), 2);
+        assert_eq!(response.results[0].model.as_deref(), Some("clip-vit-b32"));
+        assert_eq!(response.results[0].labels[0], MediaLabel { label: "cat".to_string(), score: Some(0.93) });
+        assert_eq!(response.results[0].embedding, Some(vec![0.1, 0.2]));
+        assert_eq!(response.results[1].labels[0].score, None);
+        assert_eq!(response.results[1].embedding, None);
+    }
+
+    #[test]
+    fn test_parse_empty_response() {
+        let response: TaggingResponse = serde_json::from_str("{}").unwrap();
+        assert!(response.results.is_empty());
+    }
+}
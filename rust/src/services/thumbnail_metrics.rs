@@ -0,0 +1,280 @@
+//! 缩略图生成耗时统计
+//! 按源格式（jpeg、png、heic、video 等）、输出格式与阶段（解码、缩放、编码）记录进程内直方图，
+//! 反映实际图库在本机上的表现，用于调整 LATTE_TRANSCODING_THREADS 与选择输出格式，
+//! 不必依赖仓库自带的示例基准。
+//! 通过 GET /metrics（Prometheus 文本格式）与 GET /api/stats/thumbnails（JSON）查看；重启后清零。
+//! 视频由 FFmpeg 在解码循环中同时完成缩放，其 resize 阶段只包含旋转与复制。
+
+use crate::utils::ThumbnailFormat;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Upper bounds of the histogram buckets, in seconds
+const BUCKETS: [f64; 12] = [0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0];
+
+/// Step of thumbnail generation
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Stage {
+    Decode,
+    Resize,
+    Encode,
+}
+
+impl Stage {
+    pub const ALL: [Stage; 3] = [Stage::Decode, Stage::Resize, Stage::Encode];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Stage::Decode => "decode",
+            Stage::Resize => "resize",
+            Stage::Encode => "encode",
+        }
+    }
+}
+
+/// Measures consecutive stages of one thumbnail generation
+#[derive(Debug, Clone)]
+pub struct StageTimer {
+    last: Instant,
+    durations: [Duration; 3],
+}
+
+impl StageTimer {
+    pub fn start() -> Self {
+        Self {
+            last: Instant::now(),
+            durations: [Duration::ZERO; 3],
+        }
+    }
+
+    /// Attribute the time since the previous lap (or the start) to `stage`
+    pub fn lap(&mut self, stage: Stage) {
+        let now = Instant::now();
+        self.durations[stage as usize] += now - self.last;
+        self.last = now;
+    }
+
+    pub fn duration(&self, stage: Stage) -> Duration {
+        self.durations[stage as usize]
+    }
+}
+
+/// Source label of a file: its lower-case extension, with the aliases of a format merged
+pub fn source_format(path: &Path) -> String {
+    let ext = path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .map(str::to_ascii_lowercase)
+        .unwrap_or_default();
+    match ext.as_str() {
+        "jpg" | "jpeg" | "jpe" => "jpeg".to_string(),
+        "tif" | "tiff" => "tiff".to_string(),
+        "heic" | "heif" | "hif" => "heic".to_string(),
+        "" => "other".to_string(),
+        _ => ext,
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+struct Histogram {
+    /// Observations per bucket (not cumulative); the last entry is +Inf
+    counts: [u64; BUCKETS.len() + 1],
+    count: u64,
+    sum: f64,
+    max: f64,
+}
+
+impl Histogram {
+    fn observe(&mut self, seconds: f64) {
+        let bucket = BUCKETS.iter().position(|&bound| seconds <= bound).unwrap_or(BUCKETS.len());
+        self.counts[bucket] += 1;
+        self.count += 1;
+        self.sum += seconds;
+        self.max = self.max.max(seconds);
+    }
+
+    /// Upper bound of the bucket holding the `q` quantile; the maximum for the +Inf bucket
+    fn quantile(&self, q: f64) -> f64 {
+        let rank = (q * self.count as f64).ceil().max(1.0) as u64;
+        let mut seen = 0;
+        for (bucket, count) in self.counts.iter().enumerate() {
+            seen += count;
+            if seen >= rank {
+                return BUCKETS.get(bucket).map_or(self.max, |bound| bound.min(self.max));
+            }
+        }
+        self.max
+    }
+}
+
+/// (source format, output format, stage)
+type SeriesKey = (String, &'static str, Stage);
+
+/// Timing summary of one stage for one source/output format pair
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StageTiming {
+    pub source: String,
+    pub output: &'static str,
+    pub stage: &'static str,
+    /// Thumbnails measured
+    pub count: u64,
+    pub mean_ms: f64,
+    /// Estimated from the histogram buckets (bucket upper bound)
+    pub p50_ms: f64,
+    pub p95_ms: f64,
+    pub max_ms: f64,
+}
+
+/// Response of GET /api/stats/thumbnails
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ThumbnailStats {
+    pub transcoding_threads: usize,
+    pub timings: Vec<StageTiming>,
+}
+
+/// Per-format stage timings of successful thumbnail generations
+#[derive(Debug, Default)]
+pub struct ThumbnailMetrics {
+    histograms: Mutex<BTreeMap<SeriesKey, Histogram>>,
+}
+
+impl ThumbnailMetrics {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record the stages of one generated thumbnail
+    pub fn record(&self, source: &str, output: ThumbnailFormat, timer: &StageTimer) {
+        let mut histograms = self.histograms.lock().unwrap();
+        for stage in Stage::ALL {
+            histograms
+                .entry((source.to_string(), output.name(), stage))
+                .or_default()
+                .observe(timer.duration(stage).as_secs_f64());
+        }
+    }
+
+    /// Summaries sorted by source, output format and stage
+    pub fn stats(&self, transcoding_threads: usize) -> ThumbnailStats {
+        let histograms = self.histograms.lock().unwrap();
+        let ms = |seconds: f64| (seconds * 1_000_000.0).round() / 1000.0;
+        let timings = histograms
+            .iter()
+            .map(|((source, output, stage), histogram)| StageTiming {
+                source: source.clone(),
+                output,
+                stage: stage.name(),
+                count: histogram.count,
+                mean_ms: ms(histogram.sum / histogram.count.max(1) as f64),
+                p50_ms: ms(histogram.quantile(0.5)),
+                p95_ms: ms(histogram.quantile(0.95)),
+                max_ms: ms(histogram.max),
+            })
+            .collect();
+        ThumbnailStats {
+            transcoding_threads,
+            timings,
+        }
+    }
+
+    /// Histograms in the Prometheus text exposition format
+    pub fn render_prometheus(&self) -> String {
+        const NAME: &str = "latte_thumbnail_stage_seconds";
+        let histograms = self.histograms.lock().unwrap();
+        let mut out = String::new();
+        let _ = writeln!(out, "# HELP {NAME} Time spent per thumbnail generation stage.");
+        let _ = writeln!(out, "# TYPE {NAME} histogram");
+        for ((source, output, stage), histogram) in histograms.iter() {
+            let labels = format!("source=\"{}\",output=\"{}\",stage=\"{}\"", source, output, stage.name());
+            let mut cumulative = 0;
+            for (bound, count) in BUCKETS.iter().zip(histogram.counts.iter()) {
+                cumulative += count;
+                let _ = writeln!(out, "{NAME}_bucket{{{labels},le=\"{bound}\"}} {cumulative}");
+            }
+            let _ = writeln!(out, "{NAME}_bucket{{{labels},le=\"+Inf\"}} {}", histogram.count);
+            let _ = writeln!(out, "{NAME}_sum{{{labels}}} {}", histogram.sum);
+            let _ = writeln!(out, "{NAME}_count{{{labels}}} {}", histogram.count);
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn timer(decode_ms: u64, resize_ms: u64, encode_ms: u64) -> StageTimer {
+        let mut timer = StageTimer::start();
+        timer.durations = [
+            Duration::from_millis(decode_ms),
+            Duration::from_millis(resize_ms),
+            Duration::from_millis(encode_ms),
+        ];
+        timer
+    }
+
+    #[test]
+    fn test_source_format() {
+        assert_eq!(source_format(Path::new("/p/IMG_1.JPG")), "jpeg");
+        assert_eq!(source_format(Path::new("/p/IMG_1.heif")), "heic");
+        assert_eq!(source_format(Path::new("/p/a.png")), "png");
+        assert_eq!(source_format(Path::new("/p/README")), "other");
+    }
+
+    #[test]
+    fn test_stage_timer_accumulates() {
+        let mut timer = StageTimer::start();
+        std::thread::sleep(Duration::from_millis(2));
+        timer.lap(Stage::Decode);
+        timer.lap(Stage::Resize);
+        std::thread::sleep(Duration::from_millis(2));
+        timer.lap(Stage::Decode);
+        assert!(timer.duration(Stage::Decode) >= Duration::from_millis(4));
+        assert!(timer.duration(Stage::Resize) < Duration::from_millis(2));
+        assert_eq!(timer.duration(Stage::Encode), Duration::ZERO);
+    }
+
+    #[test]
+    fn test_stats() {
+        let metrics = ThumbnailMetrics::new();
+        for _ in 0..19 {
+            metrics.record("jpeg", ThumbnailFormat::WebP, &timer(20, 5, 8));
+        }
+        metrics.record("jpeg", ThumbnailFormat::WebP, &timer(400, 5, 8));
+        metrics.record("heic", ThumbnailFormat::Jpeg, &timer(120, 10, 4));
+
+        let stats = metrics.stats(4);
+        assert_eq!(stats.transcoding_threads, 4);
+        assert_eq!(stats.timings.len(), 6);
+        assert_eq!((stats.timings[0].source.as_str(), stats.timings[0].stage), ("heic", "decode"));
+
+        let decode = stats.timings.iter().find(|t| t.source == "jpeg" && t.stage == "decode").unwrap();
+        assert_eq!(decode.output, "webp");
+        assert_eq!(decode.count, 20);
+        assert_eq!(decode.mean_ms, 39.0);
+        assert_eq!(decode.p50_ms, 25.0);
+        assert_eq!(decode.p95_ms, 25.0);
+        assert_eq!(decode.max_ms, 400.0);
+    }
+
+    #[test]
+    fn test_render_prometheus() {
+        let metrics = ThumbnailMetrics::new();
+        assert!(!metrics.render_prometheus().contains("_count"));
+
+        metrics.record("video", ThumbnailFormat::Jpeg, &timer(60, 1, 3));
+        let text = metrics.render_prometheus();
+        assert!(text.contains("# TYPE latte_thumbnail_stage_seconds histogram"));
+        let labels = "source=\"video\",output=\"jpeg\",stage=\"decode\"";
+        assert!(text.contains(&format!("latte_thumbnail_stage_seconds_bucket{{{labels},le=\"0.05\"}} 0")));
+        assert!(text.contains(&format!("latte_thumbnail_stage_seconds_bucket{{{labels},le=\"0.1\"}} 1")));
+        assert!(text.contains(&format!("latte_thumbnail_stage_seconds_bucket{{{labels},le=\"+Inf\"}} 1")));
+        assert!(text.contains(&format!("latte_thumbnail_stage_seconds_count{{{labels}}} 1")));
+    }
+}
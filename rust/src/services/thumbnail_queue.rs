@@ -0,0 +1,153 @@
+//! 缩略图后台预生成队列
+//! 前端在浏览当前页时提前提交下一页的文件 id，由后台 worker 逐个生成缩略图写入缓存，
+//! 进度通过 ThumbnailProgress 广播
+
+use crate::services::background_priority::background;
+use crate::services::{CacheService, FileService, QuietHours};
+use crate::websocket::ThumbnailProgress;
+use std::collections::HashSet;
+use std::sync::{Arc, Mutex};
+use tokio::sync::{mpsc, Semaphore};
+use tokio::task::AbortHandle;
+
+/// 单个缩略图预生成任务
+#[derive(Debug, Clone)]
+pub struct ThumbnailJob {
+    pub file_id: String,
+    pub size_label: &'static str,
+    pub target_size: u32,
+    pub fit_to_height: bool,
+}
+
+impl ThumbnailJob {
+    fn key(&self) -> String {
+        format!("{}_{}", self.file_id, self.size_label)
+    }
+}
+
+/// 提交任务的结果
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EnqueueResult {
+    /// 已加入队列
+    Queued,
+    /// 同一任务已在队列中或缓存已存在
+    Skipped,
+    /// 队列已满，任务被丢弃
+    Full,
+}
+
+/// 缩略图后台生成队列
+pub struct ThumbnailQueue {
+    sender: mpsc::Sender<ThumbnailJob>,
+    cache: Arc<CacheService>,
+    pending: Arc<Mutex<HashSet<String>>>,
+    progress: Arc<ThumbnailProgress>,
+    _worker_task: AbortHandle,
+}
+
+impl ThumbnailQueue {
+    /// 创建队列并启动 worker
+    ///
+    /// # Arguments
+    ///
+    /// * `queue_size` - 队列容量，超出后新任务被丢弃
+    /// * `workers` - 同时生成缩略图的最大任务数
+    /// * `quiet_hours` - 静默时段内进一步限制并发或暂停
+    /// * `progress` - 每个任务入队与完成时更新的进度
+    pub fn new(
+        file_service: Arc<FileService>,
+        cache: Arc<CacheService>,
+        queue_size: usize,
+        workers: usize,
+        quiet_hours: Option<Arc<QuietHours>>,
+        progress: Arc<ThumbnailProgress>,
+    ) -> Self {
+        let (tx, mut rx) = mpsc::channel::<ThumbnailJob>(queue_size.max(1));
+        let pending: Arc<Mutex<HashSet<String>>> = Arc::new(Mutex::new(HashSet::new()));
+        let semaphore = Arc::new(Semaphore::new(workers.max(1)));
+
+        let worker_pending = pending.clone();
+        let worker_progress = progress.clone();
+        let worker_task = tokio::spawn(async move {
+            while let Some(job) = rx.recv().await {
+                let permit = match semaphore.clone().acquire_owned().await {
+                    Ok(permit) => permit,
+                    Err(_) => break,
+                };
+                let file_service = file_service.clone();
+                let pending = worker_pending.clone();
+                let quiet_hours = quiet_hours.clone();
+                let progress = worker_progress.clone();
+
+                // 预生成属于后台任务，解码与编码在低优先级线程池中执行
+                tokio::spawn(background(async move {
+                    let _permit = permit;
+                    let _quiet_permit = match &quiet_hours {
+                        Some(quiet_hours) => quiet_hours.acquire().await,
+                        None => None,
+                    };
+                    let success = match file_service
+                        .get_thumbnail(&job.file_id, job.size_label, job.target_size, job.fit_to_height)
+                        .await
+                    {
+                        Ok(Some(_)) => {
+                            tracing::debug!("Warmed thumbnail {} ({})", job.file_id, job.size_label);
+                            true
+                        }
+                        Ok(None) => {
+                            tracing::debug!("No thumbnail generated for {} ({})", job.file_id, job.size_label);
+                            false
+                        }
+                        Err(e) => {
+                            tracing::warn!("Failed to warm thumbnail for {}: {}", job.file_id, e);
+                            false
+                        }
+                    };
+                    pending.lock().unwrap().remove(&job.key());
+                    progress.job_finished(success);
+                }));
+            }
+        });
+
+        Self {
+            sender: tx,
+            cache,
+            pending,
+            progress,
+            _worker_task: worker_task.abort_handle(),
+        }
+    }
+
+    /// 提交一个预生成任务（不等待生成完成）
+    pub async fn enqueue(&self, job: ThumbnailJob) -> EnqueueResult {
+        if self.cache.get_thumbnail_disk_path(&job.file_id, job.size_label).await.is_some() {
+            return EnqueueResult::Skipped;
+        }
+
+        let key = job.key();
+        if !self.pending.lock().unwrap().insert(key.clone()) {
+            return EnqueueResult::Skipped;
+        }
+
+        // 先计入进度再发送，避免 worker 在计数前完成任务导致批次提前结束
+        self.progress.job_queued();
+        match self.sender.try_send(job) {
+            Ok(()) => EnqueueResult::Queued,
+            Err(_) => {
+                self.pending.lock().unwrap().remove(&key);
+                self.progress.job_dropped();
+                EnqueueResult::Full
+            }
+        }
+    }
+
+    /// 当前排队或生成中的任务数
+    pub fn pending_count(&self) -> usize {
+        self.pending.lock().unwrap().len()
+    }
+
+    /// 预生成进度
+    pub fn progress(&self) -> &Arc<ThumbnailProgress> {
+        &self.progress
+    }
+}
@@ -0,0 +1,103 @@
+//! 已删除文件检测（全量扫描之间的自愈）
+//! 后台定时抽查一批随机数据库记录对应的文件是否仍存在，
+//! 并即时检查在原图/缩略图请求中发现缺失的文件；缺失记录直接删除，与全量扫描的删除阶段一致。
+
+use crate::db::{DatabasePool, MediaFileRepository};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tokio::task::AbortHandle;
+
+/// Capacity of the queue of file ids reported missing while serving
+const REPORT_QUEUE_SIZE: usize = 256;
+
+/// Background job removing database entries whose files no longer exist
+pub struct TombstoneChecker {
+    sender: mpsc::Sender<String>,
+    _worker_task: AbortHandle,
+}
+
+impl TombstoneChecker {
+    /// 创建并启动后台检查任务
+    ///
+    /// # Arguments
+    ///
+    /// * `base_path` - Library root; checks are skipped while it is unavailable (e.g. NAS unmounted)
+    /// * `interval` - Time between random sample checks
+    /// * `sample_size` - Number of random entries checked per run
+    pub fn new(db: DatabasePool, base_path: PathBuf, interval: Duration, sample_size: usize) -> Self {
+        let (tx, mut rx) = mpsc::channel::<String>(REPORT_QUEUE_SIZE);
+
+        let worker_task = tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            // 第一次 tick 立即触发，跳过以免与启动扫描同时进行
+            ticker.tick().await;
+
+            loop {
+                tokio::select! {
+                    _ = ticker.tick() => {
+                        match check_sample(&db, &base_path, sample_size).await {
+                            Ok(removed) if removed > 0 => {
+                                tracing::info!("Tombstone check removed {} missing files", removed);
+                            }
+                            Ok(_) => {}
+                            Err(e) => tracing::warn!("Tombstone check failed: {}", e),
+                        }
+                    }
+                    Some(file_id) = rx.recv() => {
+                        if let Err(e) = check_file(&db, &base_path, &file_id).await {
+                            tracing::warn!("Failed to check reported file {}: {}", file_id, e);
+                        }
+                    }
+                }
+            }
+        });
+
+        Self {
+            sender: tx,
+            _worker_task: worker_task.abort_handle(),
+        }
+    }
+
+    /// Report a file whose path was missing while serving it; checked in the background
+    pub fn report_missing(&self, file_id: &str) {
+        if self.sender.try_send(file_id.to_string()).is_err() {
+            tracing::debug!("Tombstone report queue full, dropping {}", file_id);
+        }
+    }
+}
+
+/// Check a random sample of entries, removing those whose file is gone.
+/// Returns the number of removed entries.
+pub async fn check_sample(db: &DatabasePool, base_path: &Path, sample_size: usize) -> Result<u64, sqlx::Error> {
+    if !base_path.is_dir() {
+        tracing::warn!("Base path {:?} unavailable, skipping tombstone check", base_path);
+        return Ok(0);
+    }
+
+    let repo = MediaFileRepository::new(db);
+    let mut removed = 0;
+    for (id, file_path) in repo.sample_paths(sample_size).await? {
+        if !Path::new(&file_path).exists() && repo.delete_by_id(&id).await? {
+            tracing::debug!("Removed missing file {}", file_path);
+            removed += 1;
+        }
+    }
+    Ok(removed)
+}
+
+/// Check a single entry, removing it if its file is gone. Returns true if removed.
+pub async fn check_file(db: &DatabasePool, base_path: &Path, file_id: &str) -> Result<bool, sqlx::Error> {
+    if !base_path.is_dir() {
+        return Ok(false);
+    }
+
+    let repo = MediaFileRepository::new(db);
+    match repo.find_by_id(file_id).await? {
+        Some(file) if !Path::new(&file.file_path).exists() => {
+            tracing::info!("Removing missing file {}", file.file_path);
+            repo.delete_by_id(file_id).await
+        }
+        _ => Ok(false),
+    }
+}
@@ -1,6 +1,8 @@
 //! 图片转码专用线程池服务
 //! 使用 rayon 为 CPU 密集型的图片转码任务创建独立线程池
+//! 可选的低优先级线程池承担扫描、预生成等后台任务（见 `background_priority`）
 
+use crate::services::background_priority::{self, BackgroundPriority};
 use rayon::ThreadPool;
 use std::sync::Arc;
 
@@ -13,6 +15,8 @@ use std::sync::Arc;
 #[derive(Clone)]
 pub struct TranscodingPool {
     inner: Arc<ThreadPool>,
+    /// Low-priority threads for tasks marked with `background_priority::background`
+    background: Option<Arc<ThreadPool>>,
 }
 
 impl TranscodingPool {
@@ -29,6 +33,37 @@ impl TranscodingPool {
 
         Self {
             inner: Arc::new(pool),
+            background: None,
+        }
+    }
+
+    /// 为后台任务创建单独的低优先级线程池
+    ///
+    /// # Arguments
+    ///
+    /// * `num_threads` - 线程数量
+    /// * `priority` - 线程启动时设置的 nice 值与 I/O 调度类
+    pub fn with_background(mut self, num_threads: usize, priority: BackgroundPriority) -> Self {
+        background_priority::configure(priority);
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(num_threads)
+            .thread_name(|i| format!("latte-background-{}", i))
+            .start_handler(move |_| {
+                if let Err(e) = priority.apply_to_current_thread() {
+                    tracing::warn!("Failed to lower background thread priority: {}", e);
+                }
+            })
+            .build()
+            .expect("Failed to build background transcoding thread pool");
+        self.background = Some(Arc::new(pool));
+        self
+    }
+
+    /// Pool for the current task: the low-priority one inside `background_priority::background`
+    fn pool_for_current_task(&self) -> &ThreadPool {
+        match &self.background {
+            Some(background) if background_priority::is_background() => background,
+            _ => &self.inner,
         }
     }
 
@@ -49,6 +84,27 @@ impl TranscodingPool {
         self.inner.scope(f)
     }
 
+    /// 在转码线程池中执行任务，异步等待结果（不阻塞 Tokio 工作线程）
+    ///
+    /// `scope` 会同步阻塞调用线程，在 async 上下文中应使用本方法。
+    /// 在后台任务中调用时使用低优先级线程池（如已创建）。
+    ///
+    /// # Returns
+    ///
+    /// 闭包的返回值；闭包 panic 时返回 None（panic 被捕获，不会导致线程池中止进程）
+    pub async fn run<F, R>(&self, f: F) -> Option<R>
+    where
+        F: FnOnce() -> R + Send + 'static,
+        R: Send + 'static,
+    {
+        let (tx, rx) = tokio::sync::oneshot::channel();
+        self.pool_for_current_task().spawn(move || {
+            let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(f));
+            let _ = tx.send(result.ok());
+        });
+        rx.await.ok().flatten()
+    }
+
     /// 在转码线程池中异步执行任务（不等待结果）
     ///
     /// 注意：由于 rayon 的 spawn 不返回 JoinHandle，
@@ -125,6 +181,21 @@ mod tests {
         assert_eq!(result2, "pool2");
     }
 
+    #[tokio::test]
+    async fn test_transcoding_pool_run() {
+        let pool = TranscodingPool::new(2);
+        let worker = pool.run(|| std::thread::current().id()).await.unwrap();
+        assert_ne!(worker, std::thread::current().id());
+    }
+
+    #[tokio::test]
+    async fn test_transcoding_pool_run_panic() {
+        let pool = TranscodingPool::new(1);
+        assert_eq!(pool.run(|| -> i32 { panic!("boom") }).await, None);
+        // 线程池在 panic 后仍可用
+        assert_eq!(pool.run(|| 7).await, Some(7));
+    }
+
     #[test]
     fn test_transcoding_pool_spawn() {
         let pool = TranscodingPool::new(2);
@@ -140,4 +211,18 @@ mod tests {
             assert!(executed.load(std::sync::atomic::Ordering::SeqCst));
         });
     }
+
+    #[tokio::test]
+    async fn test_background_tasks_use_background_pool() {
+        use crate::services::background_priority::{background, IoPriorityClass};
+
+        let priority = BackgroundPriority { nice: 0, io_class: IoPriorityClass::Unchanged };
+        let pool = TranscodingPool::new(1).with_background(1, priority);
+        let thread_name = || std::thread::current().name().map(str::to_string);
+
+        let foreground = pool.run(thread_name).await.unwrap();
+        assert_ne!(foreground.as_deref(), Some("latte-background-0"));
+        let worker = background(pool.run(thread_name)).await.unwrap();
+        assert_eq!(worker.as_deref(), Some("latte-background-0"));
+    }
 }
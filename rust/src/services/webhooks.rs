@@ -0,0 +1,261 @@
+//! 出站 Webhook 通知
+//! 扫描完成/失败、导入新文件、磁盘空间不足时，向通过 /api/webhooks 注册的地址 POST JSON，
+//! 便于接入 Home Assistant、ntfy、Slack 等。投递在后台进行，失败按指数退避重试，不影响扫描本身。
+//!
+//! 请求体：`{"event": "scan.completed", "timestamp": "...", "text": "...", "data": {...}}`
+//! （`text` 是一句可读摘要，Slack 等只认 text 字段的服务可直接显示）
+//! 请求头：`X-Latte-Event`，配置了 secret 时附带 `X-Latte-Signature: sha256=<hex>`（对请求体的 HMAC-SHA256）
+
+use crate::db::{DatabasePool, Webhook, WebhookRepository};
+use crate::services::disk_usage::disk_usage;
+use hmac::{Hmac, Mac};
+use serde::Serialize;
+use sha2::Sha256;
+use std::path::PathBuf;
+use std::time::Duration;
+use thiserror::Error;
+use tokio::task::AbortHandle;
+
+/// Timeout of one delivery attempt
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Retries of a failed delivery
+const MAX_RETRIES: u32 = 3;
+
+/// Delay before the first retry, doubled on each further retry
+const RETRY_BASE_DELAY: Duration = Duration::from_secs(2);
+
+/// How often the free space of the library and cache volumes is checked
+const DISK_CHECK_INTERVAL: Duration = Duration::from_secs(600);
+
+#[derive(Debug, Error)]
+pub enum WebhookError {
+    #[error("Webhook request failed: {0}")]
+    Request(#[from] reqwest::Error),
+
+    #[error("Webhook endpoint returned status {0}")]
+    Status(reqwest::StatusCode),
+}
+
+/// Events a webhook can subscribe to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WebhookEvent {
+    ScanCompleted,
+    ScanFailed,
+    /// A scan found new files
+    FilesImported,
+    /// Free space of the library or cache volume dropped below the threshold
+    DiskLow,
+    /// Sent by POST /api/webhooks/{id}/test
+    Test,
+}
+
+impl WebhookEvent {
+    /// Events that can be subscribed to (Test is always delivered to the tested webhook)
+    pub const SUBSCRIBABLE: [WebhookEvent; 4] = [
+        Self::ScanCompleted,
+        Self::ScanFailed,
+        Self::FilesImported,
+        Self::DiskLow,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::ScanCompleted => "scan.completed",
+            Self::ScanFailed => "scan.failed",
+            Self::FilesImported => "files.imported",
+            Self::DiskLow => "disk.low",
+            Self::Test => "webhook.test",
+        }
+    }
+
+    /// Parse a subscribable event name
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::SUBSCRIBABLE.into_iter().find(|event| event.as_str() == name)
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct WebhookPayload<'a> {
+    event: &'static str,
+    timestamp: String,
+    text: &'a str,
+    data: &'a serde_json::Value,
+}
+
+/// Sends events to the registered webhooks
+pub struct WebhookNotifier {
+    db: DatabasePool,
+    client: reqwest::Client,
+    _disk_monitor: Option<AbortHandle>,
+}
+
+impl WebhookNotifier {
+    pub fn new(db: DatabasePool) -> Self {
+        Self {
+            db,
+            client: reqwest::Client::new(),
+            _disk_monitor: None,
+        }
+    }
+
+    /// Check the free space of `paths` periodically and send `disk.low` when one of them drops
+    /// below `threshold_bytes`. Sent once per drop; re-armed when the space recovers.
+    pub fn with_disk_monitor(mut self, paths: Vec<PathBuf>, threshold_bytes: u64) -> Self {
+        if threshold_bytes == 0 {
+            return self;
+        }
+        let db = self.db.clone();
+        let client = self.client.clone();
+        let task = tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(DISK_CHECK_INTERVAL);
+            let mut low = vec![false; paths.len()];
+            loop {
+                ticker.tick().await;
+                for (path, was_low) in paths.iter().zip(low.iter_mut()) {
+                    let checked = path.clone();
+                    let Ok(Some(usage)) = tokio::task::spawn_blocking(move || disk_usage(&checked)).await else {
+                        continue;
+                    };
+                    let is_low = usage.free_bytes < threshold_bytes;
+                    if is_low && !*was_low {
+                        tracing::warn!("Low disk space on {:?}: {} bytes free", path, usage.free_bytes);
+                        let text = format!(
+                            "Low disk space on {}: {} MB free",
+                            path.display(),
+                            usage.free_bytes / 1024 / 1024
+                        );
+                        let data = serde_json::json!({
+                            "path": path,
+                            "freeBytes": usage.free_bytes,
+                            "totalBytes": usage.total_bytes,
+                            "thresholdBytes": threshold_bytes,
+                        });
+                        dispatch(&db, &client, WebhookEvent::DiskLow, &text, &data).await;
+                    }
+                    *was_low = is_low;
+                }
+            }
+        });
+        self._disk_monitor = Some(task.abort_handle());
+        self
+    }
+
+    /// Send `event` to every enabled webhook subscribed to it. Returns immediately;
+    /// delivery (with retries) runs in the background.
+    pub fn notify(&self, event: WebhookEvent, text: String, data: serde_json::Value) {
+        let db = self.db.clone();
+        let client = self.client.clone();
+        tokio::spawn(async move {
+            dispatch(&db, &client, event, &text, &data).await;
+        });
+    }
+
+    /// Deliver a test event to one webhook, without retries
+    pub async fn send_test(&self, webhook: &Webhook) -> Result<(), WebhookError> {
+        let data = serde_json::json!({ "webhookId": webhook.id });
+        let body = encode_payload(WebhookEvent::Test, "LatteAlbum webhook test", &data);
+        deliver(&self.client, webhook, WebhookEvent::Test, &body).await
+    }
+}
+
+async fn dispatch(
+    db: &DatabasePool,
+    client: &reqwest::Client,
+    event: WebhookEvent,
+    text: &str,
+    data: &serde_json::Value,
+) {
+    let webhooks = match WebhookRepository::new(db).find_all().await {
+        Ok(webhooks) => webhooks,
+        Err(e) => {
+            tracing::warn!("Failed to load webhooks: {}", e);
+            return;
+        }
+    };
+
+    let body = encode_payload(event, text, data);
+    let deliveries = webhooks
+        .iter()
+        .filter(|webhook| webhook.enabled && webhook.accepts(event.as_str()))
+        .map(|webhook| deliver_with_retry(client, webhook, event, &body));
+    futures_util::future::join_all(deliveries).await;
+}
+
+fn encode_payload(event: WebhookEvent, text: &str, data: &serde_json::Value) -> Vec<u8> {
+    let payload = WebhookPayload {
+        event: event.as_str(),
+        timestamp: chrono::Utc::now().to_rfc3339(),
+        text,
+        data,
+    };
+    serde_json::to_vec(&payload).unwrap_or_default()
+}
+
+async fn deliver_with_retry(client: &reqwest::Client, webhook: &Webhook, event: WebhookEvent, body: &[u8]) {
+    let mut attempt = 0;
+    loop {
+        match deliver(client, webhook, event, body).await {
+            Ok(()) => return,
+            Err(e) if attempt < MAX_RETRIES => {
+                let delay = RETRY_BASE_DELAY * 2u32.pow(attempt);
+                tracing::debug!("Webhook {} failed ({}), retrying in {:?}", webhook.url, e, delay);
+                tokio::time::sleep(delay).await;
+                attempt += 1;
+            }
+            Err(e) => {
+                tracing::warn!("Webhook {} for {} failed: {}", webhook.url, event.as_str(), e);
+                return;
+            }
+        }
+    }
+}
+
+async fn deliver(client: &reqwest::Client, webhook: &Webhook, event: WebhookEvent, body: &[u8]) -> Result<(), WebhookError> {
+    let mut request = client
+        .post(&webhook.url)
+        .header(reqwest::header::CONTENT_TYPE, "application/json")
+        .header("X-Latte-Event", event.as_str())
+        .timeout(REQUEST_TIMEOUT)
+        .body(body.to_vec());
+    if let Some(secret) = webhook.secret.as_deref().filter(|s| !s.is_empty()) {
+        request = request.header("X-Latte-Signature", sign(secret, body));
+    }
+
+    let response = request.send().await?;
+    if !response.status().is_success() {
+        return Err(WebhookError::Status(response.status()));
+    }
+    Ok(())
+}
+
+/// `sha256=<hex HMAC-SHA256 of body>`, the same scheme GitHub uses for X-Hub-Signature-256
+pub fn sign(secret: &str, body: &[u8]) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any length");
+    mac.update(body);
+    let hex: String = mac.finalize().into_bytes().iter().map(|b| format!("{:02x}", b)).collect();
+    format!("sha256={}", hex)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sign() {
+        // RFC 4231 test case 2
+        assert_eq!(
+            sign("Jefe", b"what do ya want for nothing?"),
+            "sha256=5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+    }
+
+    #[test]
+    fn test_event_names() {
+        for event in WebhookEvent::SUBSCRIBABLE {
+            assert_eq!(WebhookEvent::from_name(event.as_str()), Some(event));
+        }
+        assert_eq!(WebhookEvent::from_name("webhook.test"), None);
+        assert_eq!(WebhookEvent::from_name("scan"), None);
+    }
+}
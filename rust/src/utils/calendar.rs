@@ -0,0 +1,206 @@
+//! 日历分组
+//! 时间线按日/周/月/年分桶（在 SQL 中完成），每个桶以其第一天（YYYY-MM-DD）为键。
+//! 按周分组时每周从哪天开始（LATTE_WEEK_START）因地区而异：中国/欧洲习惯周一，美国周日，
+//! 中东部分地区周六。这里集中周起始日的计算（Rust 侧与 SQL 侧一致）以及分组标签的本地化格式。
+
+use crate::api::i18n::Locale;
+use chrono::{Datelike, Duration, NaiveDate, Weekday};
+
+/// Parse a week start: full or abbreviated English weekday name ("monday", "sun", ...)
+pub fn parse_week_start(value: &str) -> Option<Weekday> {
+    value.trim().parse::<Weekday>().ok()
+}
+
+/// Lower-case weekday name, as accepted by `parse_week_start`
+pub fn weekday_name(weekday: Weekday) -> &'static str {
+    match weekday {
+        Weekday::Mon => "monday",
+        Weekday::Tue => "tuesday",
+        Weekday::Wed => "wednesday",
+        Weekday::Thu => "thursday",
+        Weekday::Fri => "friday",
+        Weekday::Sat => "saturday",
+        Weekday::Sun => "sunday",
+    }
+}
+
+/// First day of the week containing `date`
+pub fn week_start_of(date: NaiveDate, week_start: Weekday) -> NaiveDate {
+    let offset = (7 + date.weekday().num_days_from_monday() - week_start.num_days_from_monday()) % 7;
+    date - Duration::days(offset as i64)
+}
+
+/// SQLite expression mapping the date column/expression `day` ("YYYY-MM-DD") to the first day
+/// of its week. Binds one parameter: `week_start.num_days_from_sunday()`.
+pub fn sql_week_start(day: &str) -> String {
+    format!(
+        "date({day}, '-' || ((CAST(strftime('%w', {day}) AS INTEGER) - ? + 7) % 7) || ' days')",
+        day = day
+    )
+}
+
+/// Bucket size of the timeline
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Granularity {
+    Day,
+    Week,
+    Month,
+    Year,
+}
+
+impl Granularity {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "day" => Some(Self::Day),
+            "week" => Some(Self::Week),
+            "month" => Some(Self::Month),
+            "year" => Some(Self::Year),
+            _ => None,
+        }
+    }
+
+    /// SQLite expression mapping the timestamp expression `ts` to the first day of its bucket.
+    /// The week bucket binds one parameter (see `sql_week_start`).
+    pub fn sql_bucket(&self, ts: &str) -> String {
+        match self {
+            Self::Day => format!("date({})", ts),
+            Self::Week => sql_week_start(&format!("date({})", ts)),
+            Self::Month => format!("strftime('%Y-%m-01', {})", ts),
+            Self::Year => format!("strftime('%Y-01-01', {})", ts),
+        }
+    }
+
+    /// Last day of the bucket starting at `start`
+    pub fn end_of(&self, start: NaiveDate) -> NaiveDate {
+        match self {
+            Self::Day => start,
+            Self::Week => start + Duration::days(6),
+            Self::Month => {
+                let next = if start.month() == 12 {
+                    NaiveDate::from_ymd_opt(start.year() + 1, 1, 1)
+                } else {
+                    NaiveDate::from_ymd_opt(start.year(), start.month() + 1, 1)
+                };
+                next.map_or(start, |next| next - Duration::days(1))
+            }
+            Self::Year => NaiveDate::from_ymd_opt(start.year(), 12, 31).unwrap_or(start),
+        }
+    }
+
+    /// Localized label of the bucket starting at `start`
+    pub fn label(&self, start: NaiveDate, locale: Locale) -> String {
+        match self {
+            Self::Day => format_day(start, locale),
+            Self::Week => format_week(start, locale),
+            Self::Month => format_month(start, locale),
+            Self::Year => match locale {
+                Locale::En => start.year().to_string(),
+                Locale::ZhCn => format!("{}年", start.year()),
+            },
+        }
+    }
+}
+
+/// "Mar 3, 2024" / "2024年3月3日"
+pub fn format_day(date: NaiveDate, locale: Locale) -> String {
+    match locale {
+        Locale::En => date.format("%b %-d, %Y").to_string(),
+        Locale::ZhCn => format!("{}年{}月{}日", date.year(), date.month(), date.day()),
+    }
+}
+
+/// "March 2024" / "2024年3月"
+pub fn format_month(date: NaiveDate, locale: Locale) -> String {
+    match locale {
+        Locale::En => date.format("%B %Y").to_string(),
+        Locale::ZhCn => format!("{}年{}月", date.year(), date.month()),
+    }
+}
+
+/// Label of the week starting at `start`: "Mar 3 – 9, 2024" / "2024年3月3日–9日".
+/// The month and year are repeated only when the week spans two of them.
+pub fn format_week(start: NaiveDate, locale: Locale) -> String {
+    let end = start + Duration::days(6);
+    let same_year = start.year() == end.year();
+    let same_month = same_year && start.month() == end.month();
+    match locale {
+        Locale::En => {
+            if same_month {
+                format!("{} – {}, {}", start.format("%b %-d"), end.day(), end.year())
+            } else if same_year {
+                format!("{} – {}, {}", start.format("%b %-d"), end.format("%b %-d"), end.year())
+            } else {
+                format!("{} – {}", format_day(start, locale), format_day(end, locale))
+            }
+        }
+        Locale::ZhCn => {
+            if same_month {
+                format!("{}–{}日", format_day(start, locale), end.day())
+            } else if same_year {
+                format!("{}–{}月{}日", format_day(start, locale), end.month(), end.day())
+            } else {
+                format!("{}–{}", format_day(start, locale), format_day(end, locale))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(y: i32, m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, d).unwrap()
+    }
+
+    #[test]
+    fn test_parse_week_start() {
+        assert_eq!(parse_week_start("monday"), Some(Weekday::Mon));
+        assert_eq!(parse_week_start("Sun"), Some(Weekday::Sun));
+        assert_eq!(parse_week_start(" saturday "), Some(Weekday::Sat));
+        assert_eq!(parse_week_start("someday"), None);
+        assert_eq!(parse_week_start(weekday_name(Weekday::Thu)), Some(Weekday::Thu));
+    }
+
+    #[test]
+    fn test_week_start_of() {
+        // 2024-03-06 是周三
+        let wednesday = date(2024, 3, 6);
+        assert_eq!(week_start_of(wednesday, Weekday::Mon), date(2024, 3, 4));
+        assert_eq!(week_start_of(wednesday, Weekday::Sun), date(2024, 3, 3));
+        assert_eq!(week_start_of(wednesday, Weekday::Sat), date(2024, 3, 2));
+        assert_eq!(week_start_of(wednesday, Weekday::Wed), wednesday);
+        // 跨年
+        assert_eq!(week_start_of(date(2025, 1, 1), Weekday::Mon), date(2024, 12, 30));
+    }
+
+    #[test]
+    fn test_format_week() {
+        assert_eq!(format_week(date(2024, 3, 3), Locale::En), "Mar 3 – 9, 2024");
+        assert_eq!(format_week(date(2024, 2, 26), Locale::En), "Feb 26 – Mar 3, 2024");
+        assert_eq!(format_week(date(2024, 12, 30), Locale::En), "Dec 30, 2024 – Jan 5, 2025");
+        assert_eq!(format_week(date(2024, 3, 3), Locale::ZhCn), "2024年3月3日–9日");
+        assert_eq!(format_week(date(2024, 2, 26), Locale::ZhCn), "2024年2月26日–3月3日");
+        assert_eq!(format_week(date(2024, 12, 30), Locale::ZhCn), "2024年12月30日–2025年1月5日");
+    }
+
+    #[test]
+    fn test_granularity() {
+        assert_eq!(Granularity::parse("month"), Some(Granularity::Month));
+        assert_eq!(Granularity::parse("decade"), None);
+        assert_eq!(Granularity::Day.end_of(date(2024, 2, 28)), date(2024, 2, 28));
+        assert_eq!(Granularity::Week.end_of(date(2024, 2, 26)), date(2024, 3, 3));
+        assert_eq!(Granularity::Month.end_of(date(2024, 2, 1)), date(2024, 2, 29));
+        assert_eq!(Granularity::Month.end_of(date(2024, 12, 1)), date(2024, 12, 31));
+        assert_eq!(Granularity::Year.end_of(date(2024, 1, 1)), date(2024, 12, 31));
+        assert_eq!(Granularity::Year.label(date(2024, 1, 1), Locale::ZhCn), "2024年");
+        assert_eq!(Granularity::Month.label(date(2024, 3, 1), Locale::En), "March 2024");
+    }
+
+    #[test]
+    fn test_format_day_and_month() {
+        assert_eq!(format_day(date(2024, 3, 3), Locale::En), "Mar 3, 2024");
+        assert_eq!(format_month(date(2024, 3, 3), Locale::En), "March 2024");
+        assert_eq!(format_month(date(2024, 3, 3), Locale::ZhCn), "2024年3月");
+    }
+}
@@ -0,0 +1,109 @@
+//! ICC 色彩配置文件
+//! 缩略图以不带配置文件的 JPEG 输出，浏览器按 sRGB 显示。Display P3 / Adobe RGB 照片如果直接
+//! 丢弃配置文件，广色域像素会被当作 sRGB 解释，画面偏灰、饱和度不足；这里用 lcms2 把缩放后的
+//! 帧转换到 sRGB。扫描时顺带读取配置文件名称保存到元数据（MediaFile::color_profile）。
+
+use image::DynamicImage;
+use lcms2::{ColorSpaceSignature, InfoType, Intent, Locale, PixelFormat, Profile, Transform};
+
+/// Human readable profile name ("Display P3", "Adobe RGB (1998)", ...)
+pub fn profile_description(icc: &[u8]) -> Option<String> {
+    let profile = Profile::new_icc(icc).ok()?;
+    profile
+        .info(InfoType::Description, Locale::none())
+        .map(|description| description.trim().to_string())
+        .filter(|description| !description.is_empty())
+}
+
+/// sRGB profiles need no conversion; matching by name avoids an identity transform on every thumbnail
+fn is_srgb(profile: &Profile) -> bool {
+    profile
+        .info(InfoType::Description, Locale::none())
+        .is_some_and(|description| description.to_ascii_lowercase().contains("srgb"))
+}
+
+/// Convert a frame from its embedded ICC profile to sRGB. Alpha is dropped, as the thumbnail
+/// encoder drops it anyway. Unparseable, non-RGB (CMYK/gray) and sRGB profiles leave the frame
+/// unchanged.
+pub fn convert_to_srgb(image: DynamicImage, icc: Option<&[u8]>) -> DynamicImage {
+    let Some(icc) = icc else {
+        return image;
+    };
+    let source = match Profile::new_icc(icc) {
+        Ok(profile) => profile,
+        Err(e) => {
+            tracing::debug!("Ignoring invalid ICC profile: {}", e);
+            return image;
+        }
+    };
+    // image crate 已把 CMYK/灰度解码成 RGB，对应的配置文件不能直接套用
+    if source.color_space() != ColorSpaceSignature::RgbData || is_srgb(&source) {
+        return image;
+    }
+
+    let transform: Transform<[u8; 3], [u8; 3]> = match Transform::new(
+        &source,
+        PixelFormat::RGB_8,
+        &Profile::new_srgb(),
+        PixelFormat::RGB_8,
+        Intent::Perceptual,
+    ) {
+        Ok(transform) => transform,
+        Err(e) => {
+            tracing::debug!("Cannot build ICC transform: {}", e);
+            return image;
+        }
+    };
+
+    let rgb = image.to_rgb8();
+    let (width, height) = rgb.dimensions();
+    let mut pixels: Vec<[u8; 3]> = rgb.as_raw().chunks_exact(3).map(|p| [p[0], p[1], p[2]]).collect();
+    transform.transform_in_place(&mut pixels);
+
+    match image::RgbImage::from_raw(width, height, pixels.into_iter().flatten().collect()) {
+        Some(converted) => DynamicImage::ImageRgb8(converted),
+        None => DynamicImage::ImageRgb8(rgb),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frame() -> DynamicImage {
+        DynamicImage::ImageRgb8(image::RgbImage::from_pixel(4, 4, image::Rgb([200, 100, 100])))
+    }
+
+    #[test]
+    fn test_no_or_invalid_profile_keeps_frame() {
+        assert_eq!(convert_to_srgb(frame(), None), frame());
+        assert_eq!(convert_to_srgb(frame(), Some(b"not an icc profile")), frame());
+        assert_eq!(profile_description(b"not an icc profile"), None);
+    }
+
+    #[test]
+    fn test_srgb_profile_is_identity() {
+        let srgb = Profile::new_srgb().icc().unwrap();
+        assert!(profile_description(&srgb).unwrap().contains("sRGB"));
+        assert_eq!(convert_to_srgb(frame(), Some(&srgb)), frame());
+    }
+
+    #[test]
+    fn test_wide_gamut_is_converted() {
+        // 以 BT.2020 原色构造的广色域配置文件：同样的数值在 sRGB 中对应更饱和的颜色
+        let d65 = lcms2::CIExyY { x: 0.3127, y: 0.3290, Y: 1.0 };
+        let primaries = lcms2::CIExyYTRIPLE {
+            Red: lcms2::CIExyY { x: 0.708, y: 0.292, Y: 1.0 },
+            Green: lcms2::CIExyY { x: 0.170, y: 0.797, Y: 1.0 },
+            Blue: lcms2::CIExyY { x: 0.131, y: 0.046, Y: 1.0 },
+        };
+        let curve = lcms2::ToneCurve::new(2.2);
+        let wide = Profile::new_rgb(&d65, &primaries, &[&curve, &curve, &curve]).unwrap();
+        let icc = wide.icc().unwrap();
+
+        let converted = convert_to_srgb(frame(), Some(&icc)).to_rgb8();
+        let pixel = converted.get_pixel(0, 0);
+        assert!(pixel[0] > 220, "{:?}", pixel);
+        assert!(pixel[1] < 95, "{:?}", pixel);
+    }
+}
@@ -0,0 +1,178 @@
+//! 媒体文件路径校验
+//! 数据库中的路径在读取前先规范化（解析 `..` 与符号链接），结果必须仍位于图库根目录
+//! （LATTE_BASE_PATH 与 LATTE_EXTRA_LIBRARY_ROOTS）之内，防止篡改过的记录或指向库外的符号链接读取任意文件。
+//! 规范化之前先按 LATTE_PATH_PREFIX_MAP 替换路径前缀（挂载点变化后的旧路径）。
+
+use crate::config::Config;
+use crate::utils::path_prefix::PathPrefixMap;
+use std::path::{Path, PathBuf};
+
+/// Why a media path was refused
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PathCheckError {
+    /// The file does not exist (or cannot be resolved)
+    NotFound,
+    /// The file resolves outside every library root
+    OutsideLibrary,
+}
+
+/// Directories media files are allowed to resolve into
+#[derive(Debug, Clone)]
+pub struct LibraryRoots {
+    roots: Vec<PathBuf>,
+    /// Applied to stored paths before they are resolved
+    prefix_map: PathPrefixMap,
+}
+
+impl LibraryRoots {
+    /// Canonicalize the roots once; a root that does not exist yet is kept as given
+    pub fn new(roots: impl IntoIterator<Item = PathBuf>) -> Self {
+        let roots = roots
+            .into_iter()
+            .map(|root| std::fs::canonicalize(&root).unwrap_or(root))
+            .collect();
+        Self { roots, prefix_map: PathPrefixMap::default() }
+    }
+
+    /// Rewrite stored path prefixes before resolving (LATTE_PATH_PREFIX_MAP)
+    pub fn with_prefix_map(mut self, prefix_map: PathPrefixMap) -> Self {
+        self.prefix_map = prefix_map;
+        self
+    }
+
+    /// base_path plus LATTE_EXTRA_LIBRARY_ROOTS
+    pub fn from_config(config: &Config) -> Self {
+        Self::new(std::iter::once(config.base_path.clone()).chain(config.extra_library_roots.iter().cloned()))
+            .with_prefix_map(config.path_prefix_map.clone())
+    }
+
+    /// Whether `path`, which need not exist yet, lies inside a library root: its nearest existing
+    /// ancestor is canonicalized, so a symlinked directory pointing elsewhere is outside
+    pub async fn contains(&self, path: &Path) -> bool {
+        let mut existing = path;
+        let mut missing = Vec::new();
+        loop {
+            if let Ok(resolved) = tokio::fs::canonicalize(existing).await {
+                let full = missing.iter().rev().fold(resolved, |full, name| full.join(name));
+                return self.roots.iter().any(|root| full.starts_with(root));
+            }
+            // 不存在的部分只能是普通名称，`..` 无法在规范化之前判断
+            match (existing.parent(), existing.file_name()) {
+                (Some(parent), Some(name)) => {
+                    missing.push(name);
+                    existing = parent;
+                }
+                _ => return false,
+            }
+        }
+    }
+
+    /// Canonical path of `path` if it is a regular file inside a library root
+    pub async fn resolve(&self, path: &Path) -> Result<PathBuf, PathCheckError> {
+        let resolved = tokio::fs::canonicalize(self.prefix_map.apply(path))
+            .await
+            .map_err(|_| PathCheckError::NotFound)?;
+
+        if !self.roots.iter().any(|root| resolved.starts_with(root)) {
+            tracing::warn!(
+                "Refusing to serve {} outside the library: resolves to {}",
+                path.display(),
+                resolved.display()
+            );
+            return Err(PathCheckError::OutsideLibrary);
+        }
+
+        match tokio::fs::metadata(&resolved).await {
+            Ok(meta) if meta.is_file() => Ok(resolved),
+            _ => Err(PathCheckError::NotFound),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_resolve() {
+        let dir = tempfile::tempdir().unwrap();
+        let library = dir.path().join("photos");
+        let outside = dir.path().join("private");
+        std::fs::create_dir_all(library.join("2024")).unwrap();
+        std::fs::create_dir_all(&outside).unwrap();
+        std::fs::write(library.join("2024/a.jpg"), b"a").unwrap();
+        std::fs::write(outside.join("secret.txt"), b"s").unwrap();
+
+        let roots = LibraryRoots::new([library.clone()]);
+        assert!(roots.resolve(&library.join("2024/a.jpg")).await.is_ok());
+        assert_eq!(roots.resolve(&library.join("2024/missing.jpg")).await, Err(PathCheckError::NotFound));
+        // 目录不是可发送的文件
+        assert_eq!(roots.resolve(&library.join("2024")).await, Err(PathCheckError::NotFound));
+        // `..` 逃逸
+        assert_eq!(
+            roots.resolve(&library.join("2024/../../private/secret.txt")).await,
+            Err(PathCheckError::OutsideLibrary)
+        );
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_resolve_symlinks() {
+        let dir = tempfile::tempdir().unwrap();
+        let library = dir.path().join("photos");
+        let external = dir.path().join("external");
+        std::fs::create_dir_all(&library).unwrap();
+        std::fs::create_dir_all(&external).unwrap();
+        std::fs::write(external.join("b.jpg"), b"b").unwrap();
+        std::os::unix::fs::symlink(external.join("b.jpg"), library.join("link.jpg")).unwrap();
+        std::os::unix::fs::symlink(&external, library.join("linked-dir")).unwrap();
+
+        // 指向库外的文件/目录符号链接被拒绝
+        let roots = LibraryRoots::new([library.clone()]);
+        assert_eq!(roots.resolve(&library.join("link.jpg")).await, Err(PathCheckError::OutsideLibrary));
+        assert_eq!(
+            roots.resolve(&library.join("linked-dir/b.jpg")).await,
+            Err(PathCheckError::OutsideLibrary)
+        );
+
+        // 目标目录登记为额外根目录后允许
+        let roots = LibraryRoots::new([library.clone(), external.clone()]);
+        assert_eq!(
+            roots.resolve(&library.join("link.jpg")).await,
+            Ok(std::fs::canonicalize(external.join("b.jpg")).unwrap())
+        );
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_contains_new_paths() {
+        let dir = tempfile::tempdir().unwrap();
+        let library = dir.path().join("photos");
+        let external = dir.path().join("external");
+        std::fs::create_dir_all(&library).unwrap();
+        std::fs::create_dir_all(&external).unwrap();
+        std::os::unix::fs::symlink(&external, library.join("linked-dir")).unwrap();
+
+        let roots = LibraryRoots::new([library.clone()]);
+        assert!(roots.contains(&library.join("2024/new/a.jpg")).await);
+        assert!(!roots.contains(&library.join("linked-dir/new/a.jpg")).await);
+        assert!(!roots.contains(&dir.path().join("elsewhere/a.jpg")).await);
+        assert!(!roots.contains(&library.join("missing/../../a.jpg")).await);
+    }
+
+    #[tokio::test]
+    async fn test_resolve_applies_prefix_map() {
+        let dir = tempfile::tempdir().unwrap();
+        let library = dir.path().join("mnt-photos");
+        std::fs::create_dir_all(&library).unwrap();
+        std::fs::write(library.join("a.jpg"), b"a").unwrap();
+
+        // 数据库中仍是旧挂载点 /photos 下的路径
+        let map = PathPrefixMap::parse(&[format!("/photos={}", library.display())]).unwrap();
+        let roots = LibraryRoots::new([library.clone()]).with_prefix_map(map);
+        assert_eq!(
+            roots.resolve(Path::new("/photos/a.jpg")).await,
+            Ok(std::fs::canonicalize(library.join("a.jpg")).unwrap())
+        );
+    }
+}
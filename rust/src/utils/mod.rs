@@ -0,0 +1,10 @@
+//! 跨模块共享的工具
+
+pub mod color_profile; // ICC profile names and conversion to sRGB for thumbnails
+pub mod calendar; // Week start and localized labels for date grouping
+pub mod library_path; // Served media must resolve inside base_path / extra library roots
+pub mod path_prefix; // Stored path prefix remapping after the library mount point moved
+pub mod placeholder; // SVG served when a thumbnail cannot be generated
+pub mod thumbnail; // Shared resize/sharpen/encode pipeline used by all processors
+
+pub use thumbnail::{ThumbnailFit, ThumbnailFormat, ThumbnailFormats, ThumbnailOptions, ThumbnailPipeline};
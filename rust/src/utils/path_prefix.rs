@@ -0,0 +1,76 @@
+//! 路径前缀映射
+//! 容器挂载点变化（/photos → /mnt/photos）后，数据库里保存的绝对路径全部失效。
+//! LATTE_PATH_PREFIX_MAP 在读取文件时把旧前缀换成新前缀，`latte-album remap-paths`
+//! （以及之后的每次扫描）把数据库中的路径改写为新前缀，无需全量重扫。
+
+use std::borrow::Cow;
+use std::path::{Path, PathBuf};
+
+/// Ordered `from → to` prefix rules; the longest matching prefix wins
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PathPrefixMap {
+    rules: Vec<(PathBuf, PathBuf)>,
+}
+
+impl PathPrefixMap {
+    /// Parse `from=to` entries (LATTE_PATH_PREFIX_MAP, comma separated); Err with the bad entry
+    pub fn parse(entries: &[String]) -> Result<Self, String> {
+        let mut rules = Vec::with_capacity(entries.len());
+        for entry in entries {
+            let (from, to) = entry.split_once('=').ok_or_else(|| entry.clone())?;
+            let (from, to) = (from.trim(), to.trim());
+            if from.is_empty() || to.is_empty() {
+                return Err(entry.clone());
+            }
+            rules.push((PathBuf::from(from), PathBuf::from(to)));
+        }
+        // 更长的前缀优先，/photos/2024=... 先于 /photos=...
+        rules.sort_by_key(|(from, _)| std::cmp::Reverse(from.components().count()));
+        Ok(Self { rules })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    /// The `(from, to)` rules, longest prefix first
+    pub fn rules(&self) -> &[(PathBuf, PathBuf)] {
+        &self.rules
+    }
+
+    /// Stored path with its prefix replaced; unchanged if no rule matches.
+    /// Matches whole components only: /photos does not match /photos-old.
+    pub fn apply<'a>(&self, path: &'a Path) -> Cow<'a, Path> {
+        for (from, to) in &self.rules {
+            if let Ok(rest) = path.strip_prefix(from) {
+                return Cow::Owned(to.join(rest));
+            }
+        }
+        Cow::Borrowed(path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_apply_longest_prefix() {
+        let map = PathPrefixMap::parse(&[
+            "/photos=/mnt/photos".to_string(),
+            "/photos/archive = /mnt/archive".to_string(),
+        ])
+        .unwrap();
+        assert_eq!(map.apply(Path::new("/photos/2024/a.jpg")), Path::new("/mnt/photos/2024/a.jpg"));
+        assert_eq!(map.apply(Path::new("/photos/archive/b.jpg")), Path::new("/mnt/archive/b.jpg"));
+        assert_eq!(map.apply(Path::new("/photos-old/c.jpg")), Path::new("/photos-old/c.jpg"));
+        assert!(matches!(map.apply(Path::new("/other/d.jpg")), Cow::Borrowed(_)));
+    }
+
+    #[test]
+    fn test_parse_rejects_malformed_entries() {
+        assert_eq!(PathPrefixMap::parse(&["/photos".to_string()]), Err("/photos".to_string()));
+        assert_eq!(PathPrefixMap::parse(&["=/mnt".to_string()]), Err("=/mnt".to_string()));
+        assert!(PathPrefixMap::parse(&[]).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,53 @@
+//! 缩略图占位图
+//! 文件缺失或解码失败时返回按类型区分图标的 SVG，而不是 500，前端网格照常显示。
+
+/// MIME type of the generated placeholder
+pub const PLACEHOLDER_MIME: &str = "image/svg+xml";
+
+/// Square SVG placeholder with an icon for `file_type` ("image"/"video") and the file extension
+pub fn placeholder_svg(file_type: &str, file_name: &str) -> String {
+    let icon = match file_type {
+        // 山与太阳
+        "image" => r##"<circle cx="118" cy="88" r="14" fill="#9ca3af"/><path d="M56 152l36-48 26 32 18-20 28 36z" fill="#9ca3af"/>"##,
+        // 播放按钮
+        "video" => r##"<circle cx="100" cy="108" r="40" fill="none" stroke="#9ca3af" stroke-width="8"/><path d="M88 88v40l32-20z" fill="#9ca3af"/>"##,
+        // 文档
+        _ => r##"<path d="M72 64h40l20 20v68H72z" fill="none" stroke="#9ca3af" stroke-width="8" stroke-linejoin="round"/>"##,
+    };
+    let label = extension_label(file_name);
+
+    format!(
+        r##"<svg xmlns="http://www.w3.org/2000/svg" width="200" height="200" viewBox="0 0 200 200"><rect width="200" height="200" fill="#e5e7eb"/>{}<text x="100" y="184" text-anchor="middle" font-family="sans-serif" font-size="16" fill="#6b7280">{}</text></svg>"##,
+        icon, label
+    )
+}
+
+/// Upper-case extension, restricted to ASCII alphanumerics so it can be embedded in SVG as-is
+fn extension_label(file_name: &str) -> String {
+    std::path::Path::new(file_name)
+        .extension()
+        .and_then(|e| e.to_str())
+        .unwrap_or_default()
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .take(8)
+        .collect::<String>()
+        .to_ascii_uppercase()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_placeholder_svg() {
+        let image = placeholder_svg("image", "IMG_0001.heic");
+        assert!(image.starts_with("<svg"));
+        assert!(image.contains(">HEIC</text>"));
+        assert_ne!(image, placeholder_svg("video", "IMG_0001.heic"));
+
+        // 扩展名中的特殊字符不会进入 SVG
+        assert!(placeholder_svg("image", "a.<b>&c").contains(">BC</text>"));
+        assert!(placeholder_svg("other", "noext").contains("></text>"));
+    }
+}
@@ -0,0 +1,330 @@
+//! 缩略图公共流水线
+//! 各处理器只负责把文件解码成图像（JPEG/PNG 等由 image crate 解码，HEIC 由 libheif，
+//! 视频由 FFmpeg），之后的缩放、锐化与编码都交给 `ThumbnailPipeline`，
+//! 新的输出格式或选项只需要在这里实现一次。
+//! 输出格式按尺寸配置（LATTE_THUMBNAIL_FORMATS，见 `ThumbnailFormats`），例如网格缩略图用 WebP、
+//! 大图用 JPEG 以兼容旧浏览器；格式同时决定缓存键和 Content-Type。
+
+use crate::processors::ProcessingError;
+use image::DynamicImage;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+
+/// Part of every thumbnail ETag. Bump when the pipeline produces different output for the
+/// same options (resampling, sharpening, color handling) so browsers refetch.
+pub const PIPELINE_VERSION: u32 = 1;
+
+/// Which dimension `ThumbnailOptions::size` constrains
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ThumbnailFit {
+    /// Fixed width, height follows the aspect ratio (grid thumbnails)
+    Width,
+    /// Fixed height, width follows the aspect ratio ("large" size)
+    Height,
+}
+
+/// Output encoding
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ThumbnailFormat {
+    Jpeg,
+    Png,
+    /// Lossy WebP, noticeably smaller than JPEG at the same quality
+    WebP,
+}
+
+impl ThumbnailFormat {
+    /// Parse a format name as used in LATTE_THUMBNAIL_FORMATS
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "jpeg" | "jpg" => Some(Self::Jpeg),
+            "png" => Some(Self::Png),
+            "webp" => Some(Self::WebP),
+            _ => None,
+        }
+    }
+
+    /// Short name, part of cache keys
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Jpeg => "jpeg",
+            Self::Png => "png",
+            Self::WebP => "webp",
+        }
+    }
+
+    pub fn mime_type(&self) -> &'static str {
+        match self {
+            Self::Jpeg => "image/jpeg",
+            Self::Png => "image/png",
+            Self::WebP => "image/webp",
+        }
+    }
+}
+
+/// Output format per thumbnail size (LATTE_THUMBNAIL_FORMATS); sizes without an entry are JPEG
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ThumbnailFormats {
+    by_size: HashMap<String, ThumbnailFormat>,
+}
+
+impl ThumbnailFormats {
+    /// Parse `size=format` entries (comma separated, e.g. `small=webp,medium=webp`);
+    /// Err with the bad entry. `full` serves the original file and has no format of its own
+    pub fn parse(entries: &[String]) -> Result<Self, String> {
+        let mut by_size = HashMap::with_capacity(entries.len());
+        for entry in entries {
+            let (size, format) = entry.split_once('=').ok_or_else(|| entry.clone())?;
+            let size = size.trim().to_ascii_lowercase();
+            let format = ThumbnailFormat::parse(format).ok_or_else(|| entry.clone())?;
+            if !matches!(size.as_str(), "small" | "medium" | "large") {
+                return Err(entry.clone());
+            }
+            by_size.insert(size, format);
+        }
+        Ok(Self { by_size })
+    }
+
+    /// Format of a size label. Derived labels ("small_square", "large_item2") follow their size.
+    pub fn for_size(&self, size_label: &str) -> ThumbnailFormat {
+        let size = size_label.split('_').next().unwrap_or(size_label);
+        self.by_size.get(size).copied().unwrap_or(ThumbnailFormat::Jpeg)
+    }
+}
+
+/// Options for producing a thumbnail from a decoded frame
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ThumbnailOptions {
+    /// Target width or height in pixels (see `fit`); 0 keeps the original size
+    pub size: u32,
+    pub fit: ThumbnailFit,
+    pub format: ThumbnailFormat,
+    /// Encoder quality in 0.0-1.0 (JPEG and WebP)
+    pub quality: f32,
+    /// Unsharp mask sigma applied after downscaling; None disables sharpening
+    pub sharpen: Option<f32>,
+}
+
+impl ThumbnailOptions {
+    /// JPEG thumbnail options in the form processors receive them; see `with_format`
+    pub fn new(size: u32, quality: f32, fit_to_height: bool) -> Self {
+        Self {
+            size,
+            fit: if fit_to_height { ThumbnailFit::Height } else { ThumbnailFit::Width },
+            format: ThumbnailFormat::Jpeg,
+            quality,
+            sharpen: None,
+        }
+    }
+
+    pub fn with_format(mut self, format: ThumbnailFormat) -> Self {
+        self.format = format;
+        self
+    }
+
+    pub fn with_sharpen(mut self, sigma: f32) -> Self {
+        self.sharpen = Some(sigma).filter(|s| *s > 0.0);
+        self
+    }
+
+    /// Strong ETag (quoted) of the thumbnail of a source file at `source_version` (content hash,
+    /// or size and modification time). Changes with the source, the options and PIPELINE_VERSION.
+    pub fn etag(&self, source_version: &str, size_label: &str) -> String {
+        let input = format!(
+            "{}|{}|{}|{}|{:?}|{:?}|{}|{:?}",
+            PIPELINE_VERSION, source_version, size_label, self.size, self.fit, self.format, self.quality, self.sharpen
+        );
+        format!("\"{}\"", short_digest(&input))
+    }
+}
+
+/// Version (`?v=`) of versioned thumbnail URLs: a digest of the ETags of every size of the file
+/// at `source_version`, so it changes whenever one of them does (source, options, PIPELINE_VERSION).
+/// Responses to a URL carrying the current version can be cached as immutable.
+pub fn url_version(source_version: &str, sizes: &[(&str, ThumbnailOptions)]) -> String {
+    let etags: Vec<String> = sizes
+        .iter()
+        .map(|(label, options)| options.etag(source_version, label))
+        .collect();
+    short_digest(&etags.join("|"))
+}
+
+/// Hex of the first 8 bytes of the SHA-256 of `input`
+fn short_digest(input: &str) -> String {
+    let digest = Sha256::digest(input.as_bytes());
+    digest[..8].iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Resize + sharpen + encode, shared by all processors
+#[derive(Debug, Clone, Copy)]
+pub struct ThumbnailPipeline {
+    options: ThumbnailOptions,
+}
+
+impl ThumbnailPipeline {
+    pub fn new(options: ThumbnailOptions) -> Self {
+        Self { options }
+    }
+
+    pub fn options(&self) -> &ThumbnailOptions {
+        &self.options
+    }
+
+    /// Output size for a frame of `width`x`height`. Never upscales; processors with a
+    /// native scaler (libheif, FFmpeg) use this to scale before handing the frame over.
+    pub fn target_dimensions(&self, width: u32, height: u32) -> (u32, u32) {
+        let size = self.options.size;
+        if size == 0 || width == 0 || height == 0 {
+            return (width, height);
+        }
+        let (w, h) = match self.options.fit {
+            ThumbnailFit::Width => (size, (size as f64 * height as f64 / width as f64).round() as u32),
+            ThumbnailFit::Height => ((size as f64 * width as f64 / height as f64).round() as u32, size),
+        };
+        if w >= width || h >= height {
+            (width, height)
+        } else {
+            (w.max(1), h.max(1))
+        }
+    }
+
+    /// Produce the encoded thumbnail from a decoded (and orientation-corrected) frame
+    pub fn run(&self, image: DynamicImage) -> Result<Vec<u8>, ProcessingError> {
+        self.finish(self.resize(image))
+    }
+
+    /// Scale a frame to the target size; callers that post-process the scaled frame
+    /// (e.g. color conversion) use this with `finish` instead of `run`
+    pub fn resize(&self, image: DynamicImage) -> DynamicImage {
+        let (width, height) = self.target_dimensions(image.width(), image.height());
+        if image.width() != width || image.height() != height {
+            image.thumbnail_exact(width, height)
+        } else {
+            image
+        }
+    }
+
+    /// Sharpen and encode a frame that is already at its target size
+    /// (scaled by a native decoder, or cropped by the caller)
+    pub fn finish(&self, image: DynamicImage) -> Result<Vec<u8>, ProcessingError> {
+        let image = match self.options.sharpen {
+            Some(sigma) if self.options.size > 0 => image.unsharpen(sigma, 1),
+            _ => image,
+        };
+        self.encode(&image)
+    }
+
+    fn encode(&self, image: &DynamicImage) -> Result<Vec<u8>, ProcessingError> {
+        // JPEG 不支持 alpha：先转 RGBA8 再转 RGB8，丢弃透明通道（各格式输出一致）
+        let rgb = DynamicImage::ImageRgba8(image.to_rgba8()).to_rgb8();
+
+        let mut bytes = Vec::new();
+        match self.options.format {
+            ThumbnailFormat::Jpeg => {
+                let quality = (self.options.quality * 100.0).clamp(1.0, 100.0) as u8;
+                let mut encoder = image::codecs::jpeg::JpegEncoder::new_with_quality(&mut bytes, quality);
+                encoder.encode_image(&rgb)?;
+            }
+            ThumbnailFormat::Png => {
+                rgb.write_to(&mut std::io::Cursor::new(&mut bytes), image::ImageFormat::Png)?;
+            }
+            ThumbnailFormat::WebP => {
+                // image crate 只能编码无损 WebP，缩略图用 libwebp 的有损编码
+                let quality = (self.options.quality * 100.0).clamp(1.0, 100.0);
+                let encoded = webp::Encoder::from_rgb(rgb.as_raw(), rgb.width(), rgb.height()).encode(quality);
+                bytes.extend_from_slice(&encoded);
+            }
+        }
+        Ok(bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frame(width: u32, height: u32) -> DynamicImage {
+        DynamicImage::ImageRgb8(image::RgbImage::from_pixel(width, height, image::Rgb([200, 100, 50])))
+    }
+
+    #[test]
+    fn test_target_dimensions() {
+        let by_width = ThumbnailPipeline::new(ThumbnailOptions::new(300, 0.85, false));
+        assert_eq!(by_width.target_dimensions(4000, 3000), (300, 225));
+        // 不放大小图
+        assert_eq!(by_width.target_dimensions(200, 100), (200, 100));
+
+        let by_height = ThumbnailPipeline::new(ThumbnailOptions::new(900, 0.85, true));
+        assert_eq!(by_height.target_dimensions(4000, 3000), (1200, 900));
+
+        let original = ThumbnailPipeline::new(ThumbnailOptions::new(0, 0.85, false));
+        assert_eq!(original.target_dimensions(4000, 3000), (4000, 3000));
+    }
+
+    #[test]
+    fn test_run_resizes_and_encodes_jpeg() {
+        let pipeline = ThumbnailPipeline::new(ThumbnailOptions::new(100, 0.85, false).with_sharpen(0.5));
+        let bytes = pipeline.run(frame(400, 200)).unwrap();
+        let decoded = image::load_from_memory_with_format(&bytes, image::ImageFormat::Jpeg).unwrap();
+        assert_eq!((decoded.width(), decoded.height()), (100, 50));
+    }
+
+    #[test]
+    fn test_etag_follows_source_and_options() {
+        let options = ThumbnailOptions::new(300, 0.8, false);
+        let etag = options.etag("abc", "small");
+        assert!(etag.starts_with('"') && etag.ends_with('"') && etag.len() == 18);
+        assert_eq!(etag, ThumbnailOptions::new(300, 0.8, false).etag("abc", "small"));
+
+        assert_ne!(etag, options.etag("abd", "small"));
+        assert_ne!(etag, options.etag("abc", "medium"));
+        assert_ne!(etag, ThumbnailOptions::new(300, 0.9, false).etag("abc", "small"));
+        assert_ne!(etag, ThumbnailOptions::new(400, 0.8, false).etag("abc", "small"));
+        assert_ne!(etag, options.with_format(ThumbnailFormat::Png).etag("abc", "small"));
+    }
+
+    #[test]
+    fn test_url_version_follows_every_size() {
+        let small = ThumbnailOptions::new(300, 0.8, false);
+        let large = ThumbnailOptions::new(1200, 0.8, true);
+        let version = url_version("abc", &[("small", small), ("large", large)]);
+        assert_eq!(version.len(), 16);
+        assert_eq!(version, url_version("abc", &[("small", small), ("large", large)]));
+
+        assert_ne!(version, url_version("abd", &[("small", small), ("large", large)]));
+        assert_ne!(version, url_version("abc", &[("small", small), ("large", large.with_format(ThumbnailFormat::WebP))]));
+        assert_ne!(version, url_version("abc", &[("small", ThumbnailOptions::new(300, 0.9, false)), ("large", large)]));
+    }
+
+    #[test]
+    fn test_png_output_drops_alpha() {
+        let options = ThumbnailOptions::new(0, 0.85, false).with_format(ThumbnailFormat::Png);
+        let rgba = DynamicImage::ImageRgba8(image::RgbaImage::from_pixel(10, 10, image::Rgba([1, 2, 3, 0])));
+        let bytes = ThumbnailPipeline::new(options).run(rgba).unwrap();
+        let decoded = image::load_from_memory_with_format(&bytes, image::ImageFormat::Png).unwrap();
+        assert_eq!(decoded.color(), image::ColorType::Rgb8);
+        assert_eq!(options.format.mime_type(), "image/png");
+    }
+
+    #[test]
+    fn test_webp_output() {
+        let options = ThumbnailOptions::new(100, 0.8, false).with_format(ThumbnailFormat::WebP);
+        let bytes = ThumbnailPipeline::new(options).run(frame(400, 200)).unwrap();
+        let decoded = image::load_from_memory_with_format(&bytes, image::ImageFormat::WebP).unwrap();
+        assert_eq!((decoded.width(), decoded.height()), (100, 50));
+    }
+
+    #[test]
+    fn test_formats_per_size() {
+        let formats = ThumbnailFormats::parse(&["small=webp".to_string(), " medium = WEBP".to_string()]).unwrap();
+        assert_eq!(formats.for_size("small"), ThumbnailFormat::WebP);
+        assert_eq!(formats.for_size("medium_square"), ThumbnailFormat::WebP);
+        assert_eq!(formats.for_size("large"), ThumbnailFormat::Jpeg);
+        assert_eq!(formats.for_size("frame_1500_w300"), ThumbnailFormat::Jpeg);
+        assert_eq!(ThumbnailFormats::default().for_size("small"), ThumbnailFormat::Jpeg);
+
+        assert_eq!(ThumbnailFormats::parse(&["small=gif".to_string()]), Err("small=gif".to_string()));
+        assert!(ThumbnailFormats::parse(&["tiny=webp".to_string()]).is_err());
+        assert!(ThumbnailFormats::parse(&["webp".to_string()]).is_err());
+        assert_eq!(ThumbnailFormats::parse(&["full=webp".to_string()]), Err("full=webp".to_string()));
+    }
+}
@@ -2,10 +2,16 @@ use tokio::sync::broadcast;
 use std::sync::Arc;
 use crate::websocket::ScanStateManager;
 
+/// WebSocket message type of scan progress events
+pub const SCAN_EVENT: &str = "scan";
+
 /// Scan progress message
 #[derive(Debug, Clone, serde::Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct ScanProgressMessage {
+    /// Event type, distinguishes scan progress from other jobs on the same WebSocket
+    #[serde(rename = "type")]
+    pub kind: &'static str,
     pub scanning: bool,
     pub phase: Option<String>,
     // phase_message 字段已移除，由前端根据 phase 值显示中文文本
@@ -18,11 +24,14 @@ pub struct ScanProgressMessage {
     pub files_to_update: u64,
     pub files_to_delete: u64,
     pub start_time: Option<String>, // ISO timestamp for scan start
+    pub bytes_read: u64,
+    pub throughput_bytes_per_sec: u64, // read rate since the previous message
 }
 
 impl Default for ScanProgressMessage {
     fn default() -> Self {
         Self {
+            kind: SCAN_EVENT,
             scanning: false,
             phase: None,
             total_files: 0,
@@ -34,6 +43,8 @@ impl Default for ScanProgressMessage {
             files_to_update: 0,
             files_to_delete: 0,
             start_time: None,
+            bytes_read: 0,
+            throughput_bytes_per_sec: 0,
         }
     }
 }
@@ -48,7 +59,12 @@ pub struct ScanProgressBroadcaster {
 impl ScanProgressBroadcaster {
     /// Create a new broadcaster
     pub fn new() -> Self {
-        let (tx, _) = broadcast::channel(100);
+        Self::with_capacity(100)
+    }
+
+    /// Create a broadcaster keeping up to `capacity` messages for slow receivers
+    pub fn with_capacity(capacity: usize) -> Self {
+        let (tx, _) = broadcast::channel(capacity.max(1));
         Self { tx, scan_state: None }
     }
 
@@ -118,6 +134,7 @@ mod tests {
     #[tokio::test]
     async fn test_scan_progress_message_serde() {
         let msg = ScanProgressMessage {
+            kind: SCAN_EVENT,
             scanning: true,
             phase: Some("processing".to_string()),
             total_files: 100,
@@ -129,10 +146,14 @@ mod tests {
             files_to_update: 20,
             files_to_delete: 5,
             start_time: Some("2024-06-15T10:00:00Z".to_string()),
+            bytes_read: 1_048_576,
+            throughput_bytes_per_sec: 524_288,
         };
 
         let json = serde_json::to_string(&msg).unwrap();
+        assert!(json.contains("\"type\":\"scan\""));
         assert!(json.contains("\"scanning\":true"));
+        assert!(json.contains("\"throughputBytesPerSec\":524288"));
         assert!(json.contains("\"phase\":\"processing\""));
         assert!(json.contains("\"status\":\"progress\""));
     }
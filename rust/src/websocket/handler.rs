@@ -1,29 +1,67 @@
 use axum::extract::ws::{Message, WebSocket};
 use crate::websocket::broadcast::ScanProgressBroadcaster;
+use crate::websocket::{ThumbnailProgress, WarningBroadcaster};
 use futures_util::{sink::SinkExt, stream::StreamExt};
+use std::future::Future;
 use std::sync::Arc;
+use tokio::sync::broadcast::{self, error::RecvError};
 use tokio::sync::mpsc;
 
-/// Handle WebSocket connection for scan progress
-pub async fn handle_websocket(ws: WebSocket, broadcaster: Arc<ScanProgressBroadcaster>) {
+/// Handle WebSocket connection for scan and thumbnail pregeneration progress.
+/// Messages carry a `type` field ("scan" / "thumbnails" / "warning").
+/// Up to `send_buffer` messages are queued per client; a client that falls further behind
+/// than the broadcast channel capacity receives a fresh state snapshot.
+pub async fn handle_websocket(
+    ws: WebSocket,
+    broadcaster: Arc<ScanProgressBroadcaster>,
+    thumbnail_progress: Arc<ThumbnailProgress>,
+    warnings: Arc<WarningBroadcaster>,
+    send_buffer: usize,
+) {
     let (mut sender, mut receiver) = ws.split();
 
     // Create channel for progress updates
-    let (tx, mut rx) = mpsc::channel::<String>(100);
+    let (tx, mut rx) = mpsc::channel::<String>(send_buffer.max(1));
+
+    // Subscribe to progress updates before sending the current state, so that no update falls
+    // between the snapshot and the subscription; a client may start a scan once it has the state
+    let mut progress_rx = broadcaster.subscribe();
+    let mut thumbnail_rx = thumbnail_progress.subscribe();
+    let mut warning_rx = warnings.subscribe();
 
     // Send current scan state immediately on connection (for page refresh recovery)
     let current_progress = broadcaster.get_current_progress().await;
     if let Ok(json) = serde_json::to_string(&current_progress) {
         let _ = sender.send(Message::Text(json.into())).await;
     }
+    let current_thumbnails = thumbnail_progress.current();
+    if current_thumbnails.running {
+        if let Ok(json) = serde_json::to_string(&current_thumbnails) {
+            let _ = sender.send(Message::Text(json.into())).await;
+        }
+    }
 
-    // Subscribe to progress updates
-    let mut progress_rx = broadcaster.subscribe();
-
-    // Task 1: Forward progress updates to channel
+    // Task 1: Forward progress updates of both jobs to channel
+    // 客户端过慢时 tx.send 会阻塞，广播接收端随之滞后（Lagged）；此时补发当前状态快照
     let forward_task = tokio::spawn(async move {
-        while let Ok(progress) = progress_rx.recv().await {
-            if let Ok(json) = serde_json::to_string(&progress) {
+        loop {
+            let json = tokio::select! {
+                progress = recv_or_snapshot(&mut progress_rx, || broadcaster.get_current_progress()) => match progress {
+                    Some(progress) => serde_json::to_string(&progress),
+                    None => break,
+                },
+                progress = recv_or_snapshot(&mut thumbnail_rx, || async { thumbnail_progress.current() }) => match progress {
+                    Some(progress) => serde_json::to_string(&progress),
+                    None => break,
+                },
+                // 警告是一次性事件，没有可补发的状态：滞后时跳过
+                warning = warning_rx.recv() => match warning {
+                    Ok(warning) => serde_json::to_string(&warning),
+                    Err(RecvError::Lagged(_)) => continue,
+                    Err(RecvError::Closed) => break,
+                },
+            };
+            if let Ok(json) = json {
                 if tx.send(json).await.is_err() {
                     break;
                 }
@@ -70,3 +108,42 @@ pub async fn handle_websocket(ws: WebSocket, broadcaster: Arc<ScanProgressBroadc
         _ = receive_task => {},
     }
 }
+
+/// Next message of a progress channel. A receiver that lagged behind has lost messages,
+/// so it gets the current state from `snapshot` instead of silently skipping ahead.
+/// None once the channel is closed.
+async fn recv_or_snapshot<T, F, Fut>(rx: &mut broadcast::Receiver<T>, snapshot: F) -> Option<T>
+where
+    T: Clone,
+    F: FnOnce() -> Fut,
+    Fut: Future<Output = T>,
+{
+    match rx.recv().await {
+        Ok(message) => Some(message),
+        Err(RecvError::Lagged(skipped)) => {
+            tracing::debug!("WebSocket client lagged behind by {} messages, sending state snapshot", skipped);
+            Some(snapshot().await)
+        }
+        Err(RecvError::Closed) => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_recv_or_snapshot_lagged_sends_snapshot() {
+        let (tx, mut rx) = broadcast::channel(2);
+        for i in 0..5 {
+            tx.send(i).unwrap();
+        }
+        // 容量 2：前 3 条已被覆盖，先得到快照，之后继续接收剩余消息
+        assert_eq!(recv_or_snapshot(&mut rx, || async { 100 }).await, Some(100));
+        assert_eq!(recv_or_snapshot(&mut rx, || async { 100 }).await, Some(3));
+        assert_eq!(recv_or_snapshot(&mut rx, || async { 100 }).await, Some(4));
+
+        drop(tx);
+        assert_eq!(recv_or_snapshot(&mut rx, || async { 100 }).await, None);
+    }
+}
@@ -1,7 +1,11 @@
 pub mod broadcast;
 pub mod handler;
 pub mod scan_state;
+pub mod thumbnail_progress;
+pub mod warnings;
 
-pub use broadcast::ScanProgressBroadcaster;
+pub use broadcast::{ScanProgressBroadcaster, ScanProgressMessage};
 pub use handler::handle_websocket;
 pub use scan_state::{ScanStateManager, ScanPhase};
+pub use thumbnail_progress::{ThumbnailProgress, ThumbnailProgressMessage};
+pub use warnings::{WarningBroadcaster, WarningMessage};
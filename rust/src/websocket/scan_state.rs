@@ -1,8 +1,9 @@
 use tokio::sync::{broadcast, mpsc};
 use std::sync::{Arc, RwLock};
 use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Instant;
 use tokio::task::AbortHandle;
-use crate::websocket::broadcast::ScanProgressMessage;
+use crate::websocket::broadcast::{ScanProgressMessage, SCAN_EVENT};
 
 /// 扫描阶段
 #[derive(Debug, Clone, PartialEq, serde::Serialize)]
@@ -34,6 +35,10 @@ pub struct ScanState {
     pub files_to_update: u64,
     pub files_to_delete: u64,
     pub start_time: Option<String>,
+    /// 本次扫描读取的字节数
+    pub bytes_read: u64,
+    /// 最近一个广播周期内的读取速率（字节/秒）
+    pub throughput_bytes_per_sec: u64,
 }
 
 /// 进度更新消息（业务逻辑发送的消息）
@@ -43,6 +48,7 @@ pub enum ProgressUpdate {
     SetTotal(u64),
     IncrementSuccess,
     IncrementFailure,
+    AddBytesRead(u64),
     SetFileCounts(u64, u64, u64), // add, update, delete
     ResetCounters,  // 仅重置计数器，不发送广播
     Completed,
@@ -78,15 +84,19 @@ impl ScanStateManager {
         // Worker 任务：接收更新消息，更新状态，广播进度
         let worker_task = tokio::spawn(async move {
             let mut last_progress_reported: u64 = 0;
-            let interval = worker_interval.load(Ordering::Relaxed);
+            let mut last_bytes_reported: u64 = 0;
+            let mut last_broadcast_at = Instant::now();
 
             while let Some(update) = progress_rx.recv().await {
+                // 每次读取，set_broadcast_interval（配置重新加载）立即生效
+                let interval = worker_interval.load(Ordering::Relaxed);
                 {
                     let mut current_state = worker_state.write().unwrap();
 
                     match update {
                         ProgressUpdate::SetPhase(ref phase) => {
                             current_state.phase = phase.clone();
+                            current_state.scanning = Self::is_active(phase);
                         }
                         ProgressUpdate::SetTotal(total) => {
                             current_state.total_files = total;
@@ -97,6 +107,9 @@ impl ScanStateManager {
                         ProgressUpdate::IncrementFailure => {
                             current_state.failure_count += 1;
                         }
+                        ProgressUpdate::AddBytesRead(bytes) => {
+                            current_state.bytes_read += bytes;
+                        }
                         ProgressUpdate::SetFileCounts(add, update, delete) => {
                             current_state.files_to_add = add;
                             current_state.files_to_update = update;
@@ -106,6 +119,10 @@ impl ScanStateManager {
                             // 仅重置计数器，不发送广播消息
                             current_state.success_count = 0;
                             current_state.failure_count = 0;
+                            current_state.bytes_read = 0;
+                            current_state.throughput_bytes_per_sec = 0;
+                            last_bytes_reported = 0;
+                            last_broadcast_at = Instant::now();
                         }
                         ProgressUpdate::Completed => {
                             current_state.scanning = false;
@@ -140,12 +157,22 @@ impl ScanStateManager {
                     ) || processed.saturating_sub(last_progress_reported) >= interval;
 
                     if should_send {
+                        // 读取速率按两次广播之间的增量计算
+                        let elapsed = last_broadcast_at.elapsed().as_secs_f64();
+                        if elapsed > 0.0 {
+                            let bytes = current_state.bytes_read.saturating_sub(last_bytes_reported);
+                            current_state.throughput_bytes_per_sec = (bytes as f64 / elapsed) as u64;
+                        }
+                        last_bytes_reported = current_state.bytes_read;
+                        last_broadcast_at = Instant::now();
+
                         // 对于完成/错误/取消状态，先保存要广播的 phase
                         let broadcast_phase = current_state.phase.clone();
 
                         let phase_str = format!("{:?}", broadcast_phase);
                         let scanning = current_state.scanning;
                         let msg = ScanProgressMessage {
+                            kind: SCAN_EVENT,
                             scanning,
                             phase: Some(phase_str.clone()),
                             total_files: current_state.total_files,
@@ -157,6 +184,8 @@ impl ScanStateManager {
                             files_to_update: current_state.files_to_update,
                             files_to_delete: current_state.files_to_delete,
                             start_time: current_state.start_time.clone(),
+                            bytes_read: current_state.bytes_read,
+                            throughput_bytes_per_sec: current_state.throughput_bytes_per_sec,
                         };
                         let _ = tx_clone.send(msg);
                         last_progress_reported = processed;
@@ -173,6 +202,8 @@ impl ScanStateManager {
                             current_state.files_to_update = 0;
                             current_state.files_to_delete = 0;
                             current_state.start_time = None;
+                            current_state.bytes_read = 0;
+                            current_state.throughput_bytes_per_sec = 0;
                         }
                     }
                 }
@@ -209,6 +240,10 @@ impl ScanStateManager {
         let _ = self.progress_sender.try_send(ProgressUpdate::IncrementFailure);
     }
 
+    pub fn add_bytes_read(&self, bytes: u64) {
+        let _ = self.progress_sender.try_send(ProgressUpdate::AddBytesRead(bytes));
+    }
+
     pub fn set_file_counts(&self, add: u64, update: u64, delete: u64) {
         let _ = self.progress_sender.try_send(ProgressUpdate::SetFileCounts(add, update, delete));
     }
@@ -245,6 +280,7 @@ impl ScanStateManager {
             "0.00".to_string()
         };
         ScanProgressMessage {
+            kind: SCAN_EVENT,
             scanning: state.scanning,
             phase: Some(format!("{:?}", state.phase)),
             total_files: state.total_files,
@@ -256,9 +292,19 @@ impl ScanStateManager {
             files_to_update: state.files_to_update,
             files_to_delete: state.files_to_delete,
             start_time: state.start_time.clone(),
+            bytes_read: state.bytes_read,
+            throughput_bytes_per_sec: state.throughput_bytes_per_sec,
         }
     }
 
+    /// Whether a scan is running in this phase
+    fn is_active(phase: &ScanPhase) -> bool {
+        matches!(
+            phase,
+            ScanPhase::Collecting | ScanPhase::Counting | ScanPhase::Processing | ScanPhase::Writing | ScanPhase::Deleting
+        )
+    }
+
     fn status_from_phase(phase: &ScanPhase) -> String {
         match phase {
             ScanPhase::Idle => "idle".to_string(),
@@ -355,6 +401,7 @@ mod tests {
 
         let state = manager.get_state();
         assert_eq!(state.phase, ScanPhase::Collecting);
+        assert!(state.scanning);
     }
 
     #[tokio::test]
@@ -0,0 +1,204 @@
+//! 缩略图预生成进度
+//! 与扫描进度（scan_state.rs）同样的思路：共享状态 + broadcast 广播，
+//! 通过同一条 WebSocket 连接以 `type: "thumbnails"` 消息推送，HTTP 端点读取同一份状态。
+//! 队列从空闲开始接收任务即为一批，批内任务全部完成后广播 completed 并回到 idle。
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use tokio::sync::broadcast;
+
+/// WebSocket message type of thumbnail pregeneration events
+pub const THUMBNAILS_EVENT: &str = "thumbnails";
+
+/// Thumbnail pregeneration progress message
+#[derive(Debug, Clone, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ThumbnailProgressMessage {
+    #[serde(rename = "type")]
+    pub kind: &'static str,
+    pub running: bool,
+    pub status: String, // idle, started, progress, completed
+    pub total: u64,
+    pub success_count: u64,
+    pub failure_count: u64,
+    pub progress_percentage: String,
+    pub start_time: Option<String>,
+}
+
+#[derive(Debug, Default)]
+struct ThumbnailProgressState {
+    running: bool,
+    total: u64,
+    success_count: u64,
+    failure_count: u64,
+    start_time: Option<String>,
+}
+
+impl ThumbnailProgressState {
+    fn to_message(&self, status: &str) -> ThumbnailProgressMessage {
+        let done = self.success_count + self.failure_count;
+        let percentage = if self.total > 0 {
+            format!("{:.2}", done as f64 / self.total as f64 * 100.0)
+        } else {
+            "0.00".to_string()
+        };
+        ThumbnailProgressMessage {
+            kind: THUMBNAILS_EVENT,
+            running: self.running,
+            status: status.to_string(),
+            total: self.total,
+            success_count: self.success_count,
+            failure_count: self.failure_count,
+            progress_percentage: percentage,
+            start_time: self.start_time.clone(),
+        }
+    }
+}
+
+/// Progress tracker of the thumbnail pregeneration queue
+pub struct ThumbnailProgress {
+    state: Mutex<ThumbnailProgressState>,
+    tx: broadcast::Sender<ThumbnailProgressMessage>,
+    /// Broadcast every N finished jobs (start and completion are always broadcast)
+    broadcast_interval: AtomicU64,
+}
+
+impl ThumbnailProgress {
+    pub fn new(broadcast_interval: u64) -> Self {
+        Self::with_capacity(broadcast_interval, 100)
+    }
+
+    /// Tracker whose broadcast channel keeps up to `capacity` messages for slow receivers
+    pub fn with_capacity(broadcast_interval: u64, capacity: usize) -> Self {
+        let (tx, _) = broadcast::channel(capacity.max(1));
+        Self {
+            state: Mutex::new(ThumbnailProgressState::default()),
+            tx,
+            broadcast_interval: AtomicU64::new(broadcast_interval.max(1)),
+        }
+    }
+
+    /// Change the broadcast interval (configuration reload)
+    pub fn set_broadcast_interval(&self, interval: u64) {
+        self.broadcast_interval.store(interval.max(1), Ordering::Relaxed);
+    }
+
+    /// Subscribe to progress updates
+    pub fn subscribe(&self) -> broadcast::Receiver<ThumbnailProgressMessage> {
+        self.tx.subscribe()
+    }
+
+    /// Current state (for GET /api/thumbnails/progress and new WebSocket connections)
+    pub fn current(&self) -> ThumbnailProgressMessage {
+        let state = self.state.lock().unwrap();
+        let status = if state.running { "progress" } else { "idle" };
+        state.to_message(status)
+    }
+
+    /// A job was accepted; the first job after idle starts a new batch
+    pub fn job_queued(&self) {
+        let mut state = self.state.lock().unwrap();
+        if state.running {
+            state.total += 1;
+            return;
+        }
+        *state = ThumbnailProgressState {
+            running: true,
+            total: 1,
+            start_time: Some(chrono::Utc::now().to_rfc3339()),
+            ..Default::default()
+        };
+        let _ = self.tx.send(state.to_message("started"));
+    }
+
+    /// A job counted by `job_queued` was rejected before reaching the queue
+    pub fn job_dropped(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.total = state.total.saturating_sub(1);
+        self.finish_batch_if_done(&mut state);
+    }
+
+    /// A queued job finished
+    pub fn job_finished(&self, success: bool) {
+        let mut state = self.state.lock().unwrap();
+        if success {
+            state.success_count += 1;
+        } else {
+            state.failure_count += 1;
+        }
+        if self.finish_batch_if_done(&mut state) {
+            return;
+        }
+        if (state.success_count + state.failure_count).is_multiple_of(self.broadcast_interval.load(Ordering::Relaxed)) {
+            let _ = self.tx.send(state.to_message("progress"));
+        }
+    }
+
+    fn finish_batch_if_done(&self, state: &mut ThumbnailProgressState) -> bool {
+        if !state.running || state.success_count + state.failure_count < state.total {
+            return false;
+        }
+        state.running = false;
+        let _ = self.tx.send(state.to_message("completed"));
+        // 与扫描一致：完成后回到 idle，新连接不会收到上一批的计数
+        *state = ThumbnailProgressState::default();
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_batch_lifecycle() {
+        let progress = ThumbnailProgress::new(2);
+        let mut rx = progress.subscribe();
+        assert_eq!(progress.current().status, "idle");
+
+        progress.job_queued();
+        progress.job_queued();
+        progress.job_queued();
+        let started = rx.try_recv().unwrap();
+        assert_eq!(started.status, "started");
+        assert_eq!(started.kind, THUMBNAILS_EVENT);
+
+        let current = progress.current();
+        assert!(current.running);
+        assert_eq!(current.total, 3);
+
+        progress.job_finished(true);
+        assert!(rx.try_recv().is_err());
+        progress.job_finished(false);
+        let update = rx.try_recv().unwrap();
+        assert_eq!((update.status.as_str(), update.success_count, update.failure_count), ("progress", 1, 1));
+
+        progress.job_finished(true);
+        let completed = rx.try_recv().unwrap();
+        assert_eq!(completed.status, "completed");
+        assert_eq!(completed.progress_percentage, "100.00");
+        assert!(!progress.current().running);
+        assert_eq!(progress.current().total, 0);
+    }
+
+    #[test]
+    fn test_dropped_job_completes_batch() {
+        let progress = ThumbnailProgress::new(10);
+        let mut rx = progress.subscribe();
+        progress.job_queued();
+        progress.job_queued();
+        progress.job_finished(true);
+        progress.job_dropped();
+
+        assert_eq!(rx.try_recv().unwrap().status, "started");
+        assert_eq!(rx.try_recv().unwrap().status, "completed");
+        assert!(!progress.current().running);
+    }
+
+    #[test]
+    fn test_message_serde() {
+        let json = serde_json::to_string(&ThumbnailProgress::new(1).current()).unwrap();
+        assert!(json.contains("\"type\":\"thumbnails\""));
+        assert!(json.contains("\"successCount\":0"));
+    }
+}
@@ -0,0 +1,68 @@
+//! 系统警告事件
+//! 磁盘空间不足等需要用户处理的情况，通过同一条 WebSocket 连接以 `type: "warning"` 消息推送。
+//! 警告是一次性事件而不是状态，新连接不补发，滞后的客户端直接跳过丢失的警告。
+
+use tokio::sync::broadcast;
+
+/// WebSocket message type of warnings
+pub const WARNING_EVENT: &str = "warning";
+
+/// Warning pushed to WebSocket clients
+#[derive(Debug, Clone, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WarningMessage {
+    #[serde(rename = "type")]
+    pub kind: &'static str,
+    /// Stable event key, e.g. "disk_space_low"
+    pub code: &'static str,
+    pub message: String,
+    pub timestamp: String,
+}
+
+/// Broadcast channel of warnings
+pub struct WarningBroadcaster {
+    tx: broadcast::Sender<WarningMessage>,
+}
+
+impl WarningBroadcaster {
+    pub fn new(capacity: usize) -> Self {
+        let (tx, _) = broadcast::channel(capacity.max(1));
+        Self { tx }
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<WarningMessage> {
+        self.tx.subscribe()
+    }
+
+    /// Send a warning to every connected client (dropped when nobody is connected)
+    pub fn warn(&self, code: &'static str, message: impl Into<String>) {
+        self.notify(WARNING_EVENT, code, message);
+    }
+
+    /// Send a one-off event of another message type over the same channel,
+    /// e.g. `type: "config"` after a configuration reload
+    pub fn notify(&self, kind: &'static str, code: &'static str, message: impl Into<String>) {
+        let _ = self.tx.send(WarningMessage {
+            kind,
+            code,
+            message: message.into(),
+            timestamp: chrono::Utc::now().to_rfc3339(),
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_warning_message() {
+        let warnings = WarningBroadcaster::new(4);
+        let mut rx = warnings.subscribe();
+        warnings.warn("disk_space_low", "only 10 MB free");
+
+        let json = serde_json::to_string(&rx.try_recv().unwrap()).unwrap();
+        assert!(json.contains("\"type\":\"warning\""));
+        assert!(json.contains("\"code\":\"disk_space_low\""));
+    }
+}
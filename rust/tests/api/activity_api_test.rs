@@ -0,0 +1,58 @@
+//! Activity feed API integration tests
+
+#[cfg(test)]
+mod tests {
+    use reqwest::StatusCode;
+    use latte_album::helpers::start_test_server;
+    use latte_album::config::Config;
+    use latte_album::app::App;
+    use latte_album::db::{DatabasePool, MediaFileRepository, MediaNote, NoteRepository};
+    use tempfile::TempDir;
+
+    /// Create a test configuration with file-based database for isolation
+    async fn test_config() -> (Config, TempDir) {
+        let temp_dir = tempfile::Builder::new()
+            .prefix("latte_test_activity_")
+            .tempdir()
+            .expect("Failed to create temp dir");
+        let db_path = temp_dir.path().join("test.db");
+
+        let config = Config {
+            db_path,
+            ..Config::default()
+        };
+
+        (config, temp_dir)
+    }
+
+    #[tokio::test]
+    async fn test_activity_feed() {
+        let (config, _temp_dir) = test_config().await;
+        let app = App::new(config.clone()).await.expect("Failed to create app");
+        let (addr, _shutdown) = start_test_server(&app).await;
+
+        let db = DatabasePool::new(&config.db_path).await.expect("open db");
+        let first = latte_album::fixtures::create_test_media_file("a.jpg");
+        let second = latte_album::fixtures::create_test_media_file("b.jpg");
+        MediaFileRepository::new(&db).batch_upsert(&[first.clone(), second]).await.expect("upsert");
+        let note = MediaNote::new(first.id.clone(), None, Some("Dad".to_string()), "Nice one".to_string());
+        NoteRepository::new(&db).insert(&note).await.expect("insert note");
+
+        let client = reqwest::Client::new();
+        let response = client.get(format!("http://{}/api/activity", addr)).send().await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body: serde_json::Value = response.json().await.unwrap();
+        assert_eq!(body["total"], 2);
+
+        let items = body["items"].as_array().unwrap();
+        let added = items.iter().find(|e| e["kind"] == "files_added").expect("files_added event");
+        assert_eq!(added["count"], 2);
+        let note_event = items.iter().find(|e| e["kind"] == "note").expect("note event");
+        assert_eq!(note_event["fileId"], first.id.as_str());
+        assert_eq!(note_event["author"], "Dad");
+        assert_eq!(note_event["text"], "Nice one");
+
+        let response = client.get(format!("http://{}/api/activity?page=-1", addr)).send().await.unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+}
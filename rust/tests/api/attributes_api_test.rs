@@ -0,0 +1,91 @@
+//! Attributes API integration tests
+
+#[cfg(test)]
+mod tests {
+    use reqwest::StatusCode;
+    use latte_album::helpers::start_test_server;
+    use latte_album::config::Config;
+    use latte_album::app::App;
+    use latte_album::db::{DatabasePool, MediaAttribute, MediaFileRepository};
+    use tempfile::TempDir;
+
+    /// Create a test configuration with file-based database for isolation
+    async fn test_config() -> (Config, TempDir) {
+        let temp_dir = tempfile::Builder::new()
+            .prefix("latte_test_attributes_")
+            .tempdir()
+            .expect("Failed to create temp dir");
+        let db_path = temp_dir.path().join("test.db");
+
+        let config = Config {
+            db_path,
+            ..Config::default()
+        };
+
+        (config, temp_dir)
+    }
+
+    #[tokio::test]
+    async fn test_attributes_crud() {
+        let (config, _temp_dir) = test_config().await;
+        let app = App::new(config.clone()).await.expect("Failed to create app");
+        let (addr, _shutdown) = start_test_server(&app).await;
+
+        let db = DatabasePool::new(&config.db_path).await.expect("open db");
+        let file = latte_album::fixtures::create_test_media_file("beach.jpg");
+        let repo = MediaFileRepository::new(&db);
+        repo.upsert(&file).await.expect("upsert");
+        let shot_mode = MediaAttribute {
+            key: "shot_mode".to_string(),
+            value: "Beach".to_string(),
+            source: "exif".to_string(),
+        };
+        repo.replace_attributes(&file.file_path, "exif", &[shot_mode]).await.expect("attributes");
+
+        let client = reqwest::Client::new();
+        let attributes_url = format!("http://{}/api/files/{}/attributes", addr, file.id);
+
+        let response = client
+            .put(format!("{}/scene", attributes_url))
+            .json(&serde_json::json!({ "value": "seaside", "source": "classifier" }))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let created: serde_json::Value = response.json().await.unwrap();
+        assert_eq!(created["source"], "classifier");
+
+        let response = client
+            .put(format!("{}/bad%20key", attributes_url))
+            .json(&serde_json::json!({ "value": "x" }))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+        let attributes: Vec<serde_json::Value> = client.get(&attributes_url).send().await.unwrap().json().await.unwrap();
+        let keys: Vec<&str> = attributes.iter().map(|a| a["key"].as_str().unwrap()).collect();
+        assert_eq!(keys, ["scene", "shot_mode"]);
+
+        let detail: serde_json::Value = client
+            .get(format!("http://{}/api/files/{}", addr, file.id))
+            .send()
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+        assert_eq!(detail["attributes"].as_array().unwrap().len(), 2);
+
+        // 默认来源是 api，classifier 写入的值不受影响
+        let response = client.delete(format!("{}/scene", attributes_url)).send().await.unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        let response = client.delete(format!("{}/scene?source=classifier", attributes_url)).send().await.unwrap();
+        assert_eq!(response.status(), StatusCode::NO_CONTENT);
+        let response = client.delete(format!("{}/scene?source=classifier", attributes_url)).send().await.unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+        let response = client.get(format!("http://{}/api/files/missing/attributes", addr)).send().await.unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+}
@@ -41,4 +41,99 @@ mod tests {
         let body: Vec<serde_json::Value> = response.json().await.unwrap();
         assert!(body.is_empty());
     }
+
+    /// 目录移动改写其下文件的路径，id 保持不变；目录记录随之挂到新的上级目录下
+    #[tokio::test]
+    async fn test_move_directory_rewrites_paths() {
+        use latte_album::db::{DatabasePool, DirectoryEntry, DirectoryRepository, MediaFileRepository};
+
+        let (mut config, temp_dir) = test_config().await;
+        let photos = temp_dir.path().join("photos");
+        let source = photos.join("2023").join("trip");
+        std::fs::create_dir_all(&source).unwrap();
+        std::fs::write(source.join("a.jpg"), [0u8; 64]).unwrap();
+        config.base_path = photos.clone();
+        config.scan_on_first_run = false;
+
+        let db = DatabasePool::new(&config.db_path).await.expect("open db");
+        let repo = MediaFileRepository::new(&db);
+        let mut file = latte_album::fixtures::create_test_media_file("a.jpg");
+        file.file_path = source.join("a.jpg").to_string_lossy().to_string();
+        repo.upsert(&file).await.expect("upsert");
+        let entries: Vec<DirectoryEntry> = [photos.clone(), photos.join("2023"), source.clone()]
+            .iter()
+            .map(|dir| DirectoryEntry {
+                path: dir.to_string_lossy().to_string(),
+                parent_path: dir.parent().map(|p| p.to_string_lossy().to_string()),
+                name: dir.file_name().unwrap().to_string_lossy().to_string(),
+                display_name: None,
+                cover_path: None,
+            })
+            .collect();
+        DirectoryRepository::new(&db).sync(&entries).await.expect("sync directories");
+
+        let app = App::new(config).await.expect("Failed to create app");
+        let (addr, _shutdown) = start_test_server(&app).await;
+
+        let client = reqwest::Client::new();
+        let url = format!("http://{}/api/directories/move", addr);
+        let response = client
+            .post(&url)
+            .json(&serde_json::json!({ "from": "2023/trip", "to": "2023/kyoto" }))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body: serde_json::Value = response.json().await.unwrap();
+        assert_eq!(body["filesMoved"], 1);
+
+        let moved = photos.join("2023").join("kyoto").join("a.jpg");
+        assert!(moved.exists());
+        let stored = repo.find_by_id(&file.id).await.unwrap().unwrap();
+        assert_eq!(stored.file_path, moved.to_string_lossy());
+
+        // 移到新建的上级目录下：目录记录的 parentPath 与新上级目录的记录都要跟上
+        let response = client
+            .post(&url)
+            .json(&serde_json::json!({ "from": "2023/kyoto", "to": "2024/kyoto" }))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let directories: Vec<serde_json::Value> = client
+            .get(format!("http://{}/api/directories", addr))
+            .send()
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+        let find = |path: &std::path::Path| {
+            directories
+                .iter()
+                .find(|dir| dir["path"] == path.to_string_lossy().as_ref())
+                .cloned()
+                .unwrap_or_else(|| panic!("no directory {:?} in {:?}", path, directories))
+        };
+        let kyoto = find(&photos.join("2024").join("kyoto"));
+        assert_eq!(kyoto["name"], "kyoto");
+        assert_eq!(kyoto["parentPath"], photos.join("2024").to_string_lossy().as_ref());
+        assert_eq!(find(&photos.join("2024"))["parentPath"], photos.to_string_lossy().as_ref());
+
+        let response = client
+            .post(&url)
+            .json(&serde_json::json!({ "from": "2023/trip", "to": "2025" }))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+        let response = client
+            .post(&url)
+            .json(&serde_json::json!({ "from": "2023", "to": "../elsewhere" }))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
 }
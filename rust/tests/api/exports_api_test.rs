@@ -0,0 +1,106 @@
+//! Static export API integration tests
+
+#[cfg(test)]
+mod tests {
+    use reqwest::StatusCode;
+    use latte_album::helpers::start_test_server;
+    use latte_album::config::Config;
+    use latte_album::app::App;
+    use latte_album::db::{DatabasePool, MediaFileRepository};
+    use tempfile::TempDir;
+
+    /// Create a test configuration with file-based database for isolation
+    async fn test_config() -> (Config, TempDir) {
+        let temp_dir = tempfile::Builder::new()
+            .prefix("latte_test_exports_")
+            .tempdir()
+            .expect("Failed to create temp dir");
+        let db_path = temp_dir.path().join("test.db");
+        let cache_dir = temp_dir.path().join("cache");
+
+        let config = Config {
+            db_path,
+            cache_dir,
+            base_path: temp_dir.path().to_path_buf(),
+            ..Config::default()
+        };
+
+        (config, temp_dir)
+    }
+
+    /// 导出结束后目录里有缩略图、索引和页面
+    #[tokio::test]
+    async fn test_export_writes_static_gallery() {
+        let (config, temp_dir) = test_config().await;
+        let path = temp_dir.path().join("kyoto.png");
+        image::RgbImage::new(64, 48).save(&path).expect("Failed to write test image");
+
+        let app = App::new(config.clone()).await.expect("Failed to create app");
+        let (addr, _shutdown) = start_test_server(&app).await;
+
+        let db = DatabasePool::new(&config.db_path).await.expect("open db");
+        let mut file = latte_album::fixtures::create_test_media_file("kyoto.png");
+        file.file_path = path.to_string_lossy().to_string();
+        MediaFileRepository::new(&db).upsert(&file).await.expect("upsert");
+
+        let client = reqwest::Client::new();
+        let response = client
+            .post(format!("http://{}/api/exports", addr))
+            .json(&serde_json::json!({"name": "Kyoto 2023", "ids": [file.id]}))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::ACCEPTED);
+
+        let mut progress = serde_json::Value::Null;
+        for _ in 0..100 {
+            progress = reqwest::get(format!("http://{}/api/exports/progress", addr))
+                .await
+                .unwrap()
+                .json()
+                .await
+                .unwrap();
+            if progress["status"] != "running" {
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+        }
+        assert_eq!(progress["status"], "completed");
+        assert_eq!(progress["total"], 1);
+        assert_eq!(progress["exported"], 1);
+
+        let output_dir = config.get_export_dir().join("Kyoto 2023");
+        assert!(output_dir.join("index.html").is_file());
+        let index: serde_json::Value =
+            serde_json::from_slice(&std::fs::read(output_dir.join("index.json")).unwrap()).unwrap();
+        assert_eq!(index.as_array().unwrap().len(), 1);
+        assert_eq!(index[0]["fileName"], "kyoto.png");
+        assert!(output_dir.join(index[0]["thumbnail"].as_str().unwrap()).is_file());
+        assert!(output_dir.join(index[0]["image"].as_str().unwrap()).is_file());
+    }
+
+    #[tokio::test]
+    async fn test_export_rejects_path_names() {
+        let (config, _temp_dir) = test_config().await;
+        let app = App::new(config).await.expect("Failed to create app");
+        let (addr, _shutdown) = start_test_server(&app).await;
+
+        let response = reqwest::Client::new()
+            .post(format!("http://{}/api/exports", addr))
+            .json(&serde_json::json!({"name": "../outside"}))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let body: serde_json::Value = response.json().await.unwrap();
+        assert_eq!(body["code"], "invalid_export_name");
+
+        let response = reqwest::Client::new()
+            .post(format!("http://{}/api/exports/cancel", addr))
+            .send()
+            .await
+            .unwrap();
+        let body: serde_json::Value = response.json().await.unwrap();
+        assert_eq!(body["code"], "no_export_in_progress");
+    }
+}
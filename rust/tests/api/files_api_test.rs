@@ -19,6 +19,7 @@ mod tests {
 
         let config = Config {
             db_path,
+            base_path: temp_dir.path().to_path_buf(),
             ..Config::default()
         };
 
@@ -71,6 +72,55 @@ mod tests {
         assert_eq!(body.size, 10);
     }
 
+    #[tokio::test]
+    async fn test_list_files_rejects_invalid_params() {
+        let (config, _temp_dir) = test_config().await;
+        let app = App::new(config).await.expect("Failed to create app");
+        let (addr, _shutdown) = start_test_server(&app).await;
+
+        let client = reqwest::Client::new();
+        let cases = [
+            ("size=100000", "invalid_page_size"),
+            ("size=0", "invalid_page_size"),
+            ("size=-5", "invalid_page_size"),
+            ("page=-1", "invalid_page"),
+            ("page=2147483647&size=200", "invalid_page"),
+            ("sortBy=file_path", "invalid_sort_field"),
+            ("order=random", "invalid_sort_order"),
+            ("exposure=dark", "invalid_exposure_filter"),
+        ];
+        for (query, code) in cases {
+            let response = client
+                .get(format!("http://{}/api/files?{}", addr, query))
+                .send()
+                .await
+                .unwrap();
+            assert_eq!(response.status(), StatusCode::BAD_REQUEST, "{}", query);
+            let body: serde_json::Value = response.json().await.unwrap();
+            assert_eq!(body["code"], code, "{}", query);
+        }
+
+        // 边界值与默认值
+        let response = client
+            .get(format!("http://{}/api/files?size=200&sortBy=fileName&order=asc", addr))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body: FilesResponse = response.json().await.unwrap();
+        assert_eq!(body.size, 200);
+
+        let body: FilesResponse = client
+            .get(format!("http://{}/api/files", addr))
+            .send()
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+        assert_eq!(body.size, 50);
+    }
+
     #[tokio::test]
     async fn test_get_file_details_not_found() {
         let (config, _temp_dir) = test_config().await;
@@ -87,6 +137,40 @@ mod tests {
         assert_eq!(response.status(), StatusCode::NOT_FOUND);
     }
 
+    #[tokio::test]
+    async fn test_error_messages_follow_accept_language() {
+        let (config, _temp_dir) = test_config().await;
+        let app = App::new(config).await.expect("Failed to create app");
+        let (addr, _shutdown) = start_test_server(&app).await;
+
+        let client = reqwest::Client::new();
+        let url = format!("http://{}/api/files/non-existent-id", addr);
+        let response = client
+            .get(&url)
+            .header("Accept-Language", "zh-CN,zh;q=0.9,en;q=0.8")
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        assert_eq!(response.headers()["content-language"], "zh-CN");
+        let body: serde_json::Value = response.json().await.unwrap();
+        assert_eq!(body["code"], "file_not_found");
+        assert_eq!(body["message"], "文件不存在");
+
+        // 未指定或不支持的语言回退到英文，code 不变
+        let body: serde_json::Value = client
+            .get(&url)
+            .header("Accept-Language", "fr-FR")
+            .send()
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+        assert_eq!(body["code"], "file_not_found");
+        assert_eq!(body["message"], "File not found");
+    }
+
     #[tokio::test]
     async fn test_get_dates_empty() {
         let (config, _temp_dir) = test_config().await;
@@ -171,6 +255,288 @@ mod tests {
         );
     }
 
+    /// 两个客户端基于同一版本编辑：后提交的收到 409 与当前状态，而不是静默覆盖
+    #[tokio::test]
+    async fn test_update_file_optimistic_concurrency() {
+        use latte_album::db::{DatabasePool, MediaFileRepository};
+
+        let (config, _temp_dir) = test_config().await;
+        let app = App::new(config.clone()).await.expect("Failed to create app");
+        let (addr, _shutdown) = start_test_server(&app).await;
+
+        let db = DatabasePool::new(&config.db_path).await.expect("open db");
+        let file = latte_album::fixtures::create_test_media_file("edit.jpg");
+        MediaFileRepository::new(&db).upsert(&file).await.expect("upsert");
+
+        let client = reqwest::Client::new();
+        let url = format!("http://{}/api/files/{}", addr, file.id);
+
+        // 缺少版本
+        let response = client.patch(&url).json(&serde_json::json!({ "title": "x" })).send().await.unwrap();
+        assert_eq!(response.status(), StatusCode::PRECONDITION_REQUIRED);
+
+        let response = client
+            .patch(&url)
+            .header("If-Match", "\"1\"")
+            .json(&serde_json::json!({ "title": "Sunset", "description": "Kyoto" }))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()["etag"], "\"2\"");
+        let body: serde_json::Value = response.json().await.unwrap();
+        assert_eq!(body["title"], "Sunset");
+        assert_eq!(body["version"], 2);
+
+        // 过期版本：409 并返回当前状态
+        let response = client
+            .patch(&url)
+            .json(&serde_json::json!({ "title": "Stale", "version": 1 }))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::CONFLICT);
+        let body: serde_json::Value = response.json().await.unwrap();
+        assert_eq!(body["title"], "Sunset");
+        assert_eq!(body["version"], 2);
+
+        // null 清除字段，未提供的字段保持不变
+        let response = client
+            .patch(&url)
+            .json(&serde_json::json!({ "title": null, "version": 2 }))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body: serde_json::Value = response.json().await.unwrap();
+        assert!(body.get("title").is_none());
+        assert_eq!(body["description"], "Kyoto");
+        assert_eq!(body["version"], 3);
+    }
+
+    /// fields 参数只返回请求的字段（id 始终保留），且不能用来选出 GPS。
+    #[tokio::test]
+    async fn test_list_files_fields_projection() {
+        use latte_album::db::{DatabasePool, MediaFileRepository};
+
+        let (config, _temp_dir) = test_config().await;
+        let app = App::new(config.clone()).await.expect("Failed to create app");
+        let (addr, _shutdown) = start_test_server(&app).await;
+
+        let db = DatabasePool::new(&config.db_path).await.expect("open db");
+        let repo = MediaFileRepository::new(&db);
+        let mut file = latte_album::fixtures::create_test_media_file("projection.jpg");
+        file.gps_latitude = Some(39.903333);
+        file.gps_longitude = Some(116.391667);
+        repo.upsert(&file).await.expect("upsert");
+
+        let client = reqwest::Client::new();
+        let response = client
+            .get(format!(
+                "http://{}/api/files?fields=width,height,thumbnailGenerated,gpsLatitude",
+                addr
+            ))
+            .send()
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        let body: FilesResponse = response.json().await.unwrap();
+        assert_eq!(body.total, 1);
+
+        let item = body.items[0].as_object().unwrap();
+        let mut keys: Vec<&str> = item.keys().map(|k| k.as_str()).collect();
+        keys.sort();
+        assert_eq!(keys, vec!["height", "id", "thumbnailGenerated", "width"]);
+        assert_eq!(item["id"], serde_json::json!(file.id));
+        assert_eq!(item["width"], serde_json::json!(1920));
+    }
+
+    /// 写入一个真实文件并登记到数据库，返回文件 id
+    async fn insert_original(config: &Config, dir: &std::path::Path, file_name: &str, data: &[u8]) -> String {
+        use latte_album::db::{DatabasePool, MediaFileRepository};
+
+        let path = dir.join(file_name);
+        std::fs::write(&path, data).expect("write original");
+
+        let db = DatabasePool::new(&config.db_path).await.expect("open db");
+        let repo = MediaFileRepository::new(&db);
+        let mut file = latte_album::fixtures::create_test_media_file(file_name);
+        file.file_path = path.to_string_lossy().to_string();
+        repo.upsert(&file).await.expect("upsert");
+        file.id
+    }
+
+    /// HEAD 只返回头部（长度、类型），不返回文件内容
+    #[tokio::test]
+    async fn test_head_original() {
+        let (config, temp_dir) = test_config().await;
+        let app = App::new(config.clone()).await.expect("Failed to create app");
+        let (addr, _shutdown) = start_test_server(&app).await;
+        let id = insert_original(&config, temp_dir.path(), "head.jpg", &[0u8; 1234]).await;
+
+        let client = reqwest::Client::new();
+        let response = client
+            .head(format!("http://{}/api/files/{}/original", addr, id))
+            .send()
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()["content-length"], "1234");
+        assert_eq!(response.headers()["accept-ranges"], "bytes");
+        assert!(response.headers().get("content-disposition").is_none());
+        assert!(response.bytes().await.unwrap().is_empty());
+    }
+
+    /// download=true 时带上原始文件名（中文名按 RFC 5987 编码）
+    #[tokio::test]
+    async fn test_download_original_content_disposition() {
+        let (config, temp_dir) = test_config().await;
+        let app = App::new(config.clone()).await.expect("Failed to create app");
+        let (addr, _shutdown) = start_test_server(&app).await;
+        let id = insert_original(&config, temp_dir.path(), "旅行 1.jpg", b"jpeg-bytes").await;
+
+        let client = reqwest::Client::new();
+        let response = client
+            .get(format!("http://{}/api/files/{}/original?download=true", addr, id))
+            .send()
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(
+            response.headers()["content-disposition"],
+            "attachment; filename=\"__ 1.jpg\"; filename*=UTF-8''%E6%97%85%E8%A1%8C%201.jpg"
+        );
+        assert_eq!(response.bytes().await.unwrap().as_ref(), b"jpeg-bytes");
+    }
+
+    /// 开放区间与后缀区间只返回请求的字节窗口
+    #[tokio::test]
+    async fn test_original_range_requests() {
+        let (config, temp_dir) = test_config().await;
+        let app = App::new(config.clone()).await.expect("Failed to create app");
+        let (addr, _shutdown) = start_test_server(&app).await;
+        let data: Vec<u8> = (0..100u8).collect();
+        let id = insert_original(&config, temp_dir.path(), "range.jpg", &data).await;
+        let url = format!("http://{}/api/files/{}/original", addr, id);
+
+        let client = reqwest::Client::new();
+
+        let response = client.get(&url).header("Range", "bytes=90-").send().await.unwrap();
+        assert_eq!(response.status(), StatusCode::PARTIAL_CONTENT);
+        assert_eq!(response.headers()["content-range"], "bytes 90-99/100");
+        assert_eq!(response.bytes().await.unwrap().as_ref(), &data[90..]);
+
+        let response = client.get(&url).header("Range", "bytes=-5").send().await.unwrap();
+        assert_eq!(response.status(), StatusCode::PARTIAL_CONTENT);
+        assert_eq!(response.bytes().await.unwrap().as_ref(), &data[95..]);
+
+        let response = client.get(&url).header("Range", "bytes=10-19").send().await.unwrap();
+        assert_eq!(response.headers()["content-length"], "10");
+        assert_eq!(response.bytes().await.unwrap().as_ref(), &data[10..20]);
+
+        let response = client.get(&url).header("Range", "bytes=200-").send().await.unwrap();
+        assert_eq!(response.status(), StatusCode::RANGE_NOT_SATISFIABLE);
+        assert_eq!(response.headers()["content-range"], "bytes */100");
+    }
+
+    /// 指向图库外的符号链接与 `..` 路径一律拒绝（403），不泄露库外文件
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_original_refuses_paths_outside_library() {
+        use latte_album::db::{DatabasePool, MediaFileRepository};
+
+        let (config, temp_dir) = test_config().await;
+        let outside = tempfile::tempdir().unwrap();
+        std::fs::write(outside.path().join("secret.jpg"), b"secret").unwrap();
+        std::os::unix::fs::symlink(outside.path().join("secret.jpg"), temp_dir.path().join("link.jpg")).unwrap();
+
+        let app = App::new(config.clone()).await.expect("Failed to create app");
+        let (addr, _shutdown) = start_test_server(&app).await;
+
+        let db = DatabasePool::new(&config.db_path).await.expect("open db");
+        let repo = MediaFileRepository::new(&db);
+        let mut link = latte_album::fixtures::create_test_media_file("link.jpg");
+        link.file_path = temp_dir.path().join("link.jpg").to_string_lossy().to_string();
+        repo.upsert(&link).await.expect("upsert");
+        let mut dotdot = latte_album::fixtures::create_test_media_file("dotdot.jpg");
+        dotdot.file_path = temp_dir.path().join("..").join(outside.path().file_name().unwrap()).join("secret.jpg")
+            .to_string_lossy().to_string();
+        repo.upsert(&dotdot).await.expect("upsert");
+
+        let client = reqwest::Client::new();
+        for id in [&link.id, &dotdot.id] {
+            let response = client
+                .get(format!("http://{}/api/files/{}/original", addr, id))
+                .send()
+                .await
+                .unwrap();
+            assert_eq!(response.status(), StatusCode::FORBIDDEN);
+            let body: serde_json::Value = response.json().await.unwrap();
+            assert_eq!(body["code"], "file_outside_library");
+        }
+    }
+
+    /// 配置远程图库后，远程文件以只读方式合并进列表，带 library 标记，缩略图/原图经本机转发
+    #[tokio::test]
+    async fn test_remote_library_merged() {
+        // 远程实例
+        let (remote_config, remote_dir) = test_config().await;
+        let remote_app = App::new(remote_config.clone()).await.expect("Failed to create remote app");
+        let (remote_addr, _remote_shutdown) = start_test_server(&remote_app).await;
+        let remote_id = insert_original(&remote_config, remote_dir.path(), "remote.jpg", b"remote-bytes").await;
+
+        // 本机实例
+        let (mut config, temp_dir) = test_config().await;
+        config.remote_library_url = Some(format!("http://{}", remote_addr));
+        config.remote_library_name = "nas2".to_string();
+        let app = App::new(config.clone()).await.expect("Failed to create app");
+        let (addr, _shutdown) = start_test_server(&app).await;
+        insert_original(&config, temp_dir.path(), "local.jpg", b"local-bytes").await;
+
+        let client = reqwest::Client::new();
+        let body: FilesResponse = client
+            .get(format!("http://{}/api/files", addr))
+            .send()
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+        assert_eq!(body.total, 2);
+
+        let remote_item = body
+            .items
+            .iter()
+            .find(|item| item.get("library").is_some())
+            .expect("remote item should be merged");
+        assert_eq!(remote_item["library"], "nas2");
+        let merged_id = format!("remote:{}", remote_id);
+        assert_eq!(remote_item["id"], serde_json::json!(merged_id));
+
+        // 原图经本机代理
+        let response = client
+            .get(format!("http://{}/api/files/{}/original", addr, merged_id))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.bytes().await.unwrap().as_ref(), b"remote-bytes");
+
+        // 详情接口
+        let detail: serde_json::Value = client
+            .get(format!("http://{}/api/files/{}", addr, merged_id))
+            .send()
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+        assert_eq!(detail["library"], "nas2");
+    }
+
     /// 对不存在的 ID 请求 GPS 应返回 404。
     #[tokio::test]
     async fn test_get_gps_not_found() {
@@ -247,4 +613,387 @@ mod tests {
         assert_eq!(body.get("latitude").and_then(|v| v.as_f64()), Some(39.903333));
         assert_eq!(body.get("longitude").and_then(|v| v.as_f64()), Some(116.391667));
     }
+
+    /// ?maxWidth= 服务端缩小后返回 JPEG；原图已在范围内时返回原图
+    #[tokio::test]
+    async fn test_original_max_width_resize() {
+        let (config, temp_dir) = test_config().await;
+        let app = App::new(config.clone()).await.expect("Failed to create app");
+        let (addr, _shutdown) = start_test_server(&app).await;
+
+        let mut png = std::io::Cursor::new(Vec::new());
+        image::RgbImage::new(400, 200)
+            .write_to(&mut png, image::ImageFormat::Png)
+            .unwrap();
+        let png = png.into_inner();
+        // fixture 记录的尺寸为 1920x1080
+        let id = insert_original(&config, temp_dir.path(), "resize.png", &png).await;
+
+        let client = reqwest::Client::new();
+        let response = client
+            .get(format!("http://{}/api/files/{}/original?maxWidth=100", addr, id))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()["content-type"], "image/jpeg");
+        let resized = image::load_from_memory(&response.bytes().await.unwrap()).unwrap();
+        assert_eq!((resized.width(), resized.height()), (100, 50));
+
+        let response = client
+            .get(format!("http://{}/api/files/{}/original?maxWidth=4000", addr, id))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.bytes().await.unwrap().as_ref(), png.as_slice());
+    }
+
+    /// 缩略图 ETag 随源文件内容变化，If-None-Match 命中时返回 304
+    #[tokio::test]
+    async fn test_thumbnail_etag_follows_source() {
+        use latte_album::db::{DatabasePool, MediaFileRepository};
+
+        let (config, temp_dir) = test_config().await;
+        let app = App::new(config.clone()).await.expect("Failed to create app");
+        let (addr, _shutdown) = start_test_server(&app).await;
+
+        let mut png = std::io::Cursor::new(Vec::new());
+        image::RgbImage::new(400, 200)
+            .write_to(&mut png, image::ImageFormat::Png)
+            .unwrap();
+        let id = insert_original(&config, temp_dir.path(), "etag.png", &png.into_inner()).await;
+
+        let client = reqwest::Client::new();
+        let url = format!("http://{}/api/files/{}/thumbnail?size=small", addr, id);
+        let response = client.get(&url).send().await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let etag = response.headers()["etag"].to_str().unwrap().to_string();
+        assert!(!etag.contains(&id));
+
+        let response = client.get(&url).header("If-None-Match", &etag).send().await.unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+        assert_eq!(response.headers()["etag"], etag.as_str());
+
+        // 源文件内容变化（扫描写入新的哈希）后 ETag 随之改变
+        let db = DatabasePool::new(&config.db_path).await.expect("open db");
+        let repo = MediaFileRepository::new(&db);
+        let mut file = repo.find_by_id(&id).await.unwrap().unwrap();
+        file.content_hash = Some("rewritten".to_string());
+        repo.upsert(&file).await.expect("upsert");
+
+        let response = client.get(&url).header("If-None-Match", &etag).send().await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_ne!(response.headers()["etag"], etag.as_str());
+    }
+
+    /// 按尺寸配置的输出格式：small 为 WebP，large 仍为 JPEG，Content-Type 与缓存命中保持一致
+    #[tokio::test]
+    async fn test_thumbnail_format_per_size() {
+        use latte_album::utils::ThumbnailFormats;
+
+        let (mut config, temp_dir) = test_config().await;
+        config.thumbnail_formats = ThumbnailFormats::parse(&["small=webp".to_string()]).unwrap();
+        let app = App::new(config.clone()).await.expect("Failed to create app");
+        let (addr, _shutdown) = start_test_server(&app).await;
+
+        let mut png = std::io::Cursor::new(Vec::new());
+        image::RgbImage::new(400, 200)
+            .write_to(&mut png, image::ImageFormat::Png)
+            .unwrap();
+        let id = insert_original(&config, temp_dir.path(), "formats.png", &png.into_inner()).await;
+
+        let client = reqwest::Client::new();
+        let url = format!("http://{}/api/files/{}/thumbnail?size=small", addr, id);
+        // 第二次请求来自缓存
+        for _ in 0..2 {
+            let response = client.get(&url).send().await.unwrap();
+            assert_eq!(response.status(), StatusCode::OK);
+            assert_eq!(response.headers()["content-type"], "image/webp");
+            let bytes = response.bytes().await.unwrap();
+            image::load_from_memory_with_format(&bytes, image::ImageFormat::WebP).expect("webp thumbnail");
+        }
+
+        let response = client
+            .get(format!("http://{}/api/files/{}/thumbnail?size=large", addr, id))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.headers()["content-type"], "image/jpeg");
+        let bytes = response.bytes().await.unwrap();
+        image::load_from_memory_with_format(&bytes, image::ImageFormat::Jpeg).expect("jpeg thumbnail");
+    }
+
+    /// 单文件重扫按当前文件内容更新元数据，id 保持不变
+    #[tokio::test]
+    async fn test_rescan_file_updates_metadata() {
+        let (config, temp_dir) = test_config().await;
+        let app = App::new(config.clone()).await.expect("Failed to create app");
+        let (addr, _shutdown) = start_test_server(&app).await;
+
+        let mut png = std::io::Cursor::new(Vec::new());
+        image::RgbImage::new(400, 200)
+            .write_to(&mut png, image::ImageFormat::Png)
+            .unwrap();
+        // fixture 记录的尺寸为 1920x1080
+        let id = insert_original(&config, temp_dir.path(), "rescan.png", &png.into_inner()).await;
+
+        let client = reqwest::Client::new();
+        let response = client
+            .post(format!("http://{}/api/files/{}/rescan", addr, id))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body: serde_json::Value = response.json().await.unwrap();
+        assert_eq!(body["id"], id.as_str());
+        assert_eq!(body["width"], 400);
+        assert_eq!(body["height"], 200);
+
+        let response = client
+            .post(format!("http://{}/api/files/unknown/rescan", addr))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    /// 在 base_path 内移动文件：磁盘与数据库路径一起更新，id 不变；越出 base_path 的目标被拒绝
+    #[tokio::test]
+    async fn test_move_file() {
+        let (config, temp_dir) = test_config().await;
+        let app = App::new(config.clone()).await.expect("Failed to create app");
+        let (addr, _shutdown) = start_test_server(&app).await;
+        let id = insert_original(&config, temp_dir.path(), "move.jpg", &[0u8; 64]).await;
+
+        let client = reqwest::Client::new();
+        let url = format!("http://{}/api/files/{}/path", addr, id);
+        let response = client
+            .patch(&url)
+            .json(&serde_json::json!({ "path": "2024/renamed.jpg" }))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body: serde_json::Value = response.json().await.unwrap();
+        assert_eq!(body["id"], id.as_str());
+        assert_eq!(body["fileName"], "renamed.jpg");
+        assert!(!temp_dir.path().join("move.jpg").exists());
+        assert!(temp_dir.path().join("2024/renamed.jpg").exists());
+
+        for target in ["../outside.jpg", "2024/renamed.png"] {
+            let response = client
+                .patch(&url)
+                .json(&serde_json::json!({ "path": target }))
+                .send()
+                .await
+                .unwrap();
+            assert_eq!(response.status(), StatusCode::BAD_REQUEST, "{}", target);
+        }
+
+        insert_original(&config, temp_dir.path(), "taken.jpg", &[0u8; 64]).await;
+        let response = client
+            .patch(&url)
+            .json(&serde_json::json!({ "path": "taken.jpg" }))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::CONFLICT);
+    }
+
+    /// 列表返回带内容版本的 thumbnailUrl，版本匹配时缩略图按 immutable 缓存
+    #[tokio::test]
+    async fn test_versioned_thumbnail_url_is_immutable() {
+        use latte_album::db::{DatabasePool, MediaFileRepository};
+
+        let (config, temp_dir) = test_config().await;
+        let app = App::new(config.clone()).await.expect("Failed to create app");
+        let (addr, _shutdown) = start_test_server(&app).await;
+
+        let mut png = std::io::Cursor::new(Vec::new());
+        image::RgbImage::new(400, 200)
+            .write_to(&mut png, image::ImageFormat::Png)
+            .unwrap();
+        let id = insert_original(&config, temp_dir.path(), "versioned.png", &png.into_inner()).await;
+        let db = DatabasePool::new(&config.db_path).await.expect("open db");
+        let repo = MediaFileRepository::new(&db);
+        let mut file = repo.find_by_id(&id).await.unwrap().unwrap();
+        file.content_hash = Some("0123456789abcdef".to_string());
+        repo.upsert(&file).await.expect("upsert");
+
+        let client = reqwest::Client::new();
+        let list: FilesResponse = client
+            .get(format!("http://{}/api/files", addr))
+            .send()
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+        let url = list.items[0]["thumbnailUrl"].as_str().unwrap().to_string();
+        let prefix = format!("/api/files/{}/thumbnail?v=", id);
+        assert!(url.starts_with(&prefix) && url.len() == prefix.len() + 16, "{}", url);
+        assert_eq!(list.items[0]["aspectRatio"], 1.7778);
+        assert_eq!(list.items[0]["availableThumbSizes"], serde_json::json!([]));
+
+        let response = client.get(format!("http://{}{}&size=small", addr, url)).send().await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()["cache-control"], "public, max-age=31536000, immutable");
+
+        let list: FilesResponse = client
+            .get(format!("http://{}/api/files", addr))
+            .send()
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+        assert_eq!(list.items[0]["availableThumbSizes"], serde_json::json!(["small"]));
+
+        // 过期版本不能被永久缓存
+        let response = client
+            .get(format!("http://{}/api/files/{}/thumbnail?v=ffffffffffff&size=small", addr, id))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.headers()["cache-control"], "public, max-age=86400");
+
+        // 文件被改写后（缓存服务仍记着旧哈希）旧 URL 不再是 immutable，新 URL 是
+        file.content_hash = Some("fedcba9876543210".to_string());
+        repo.upsert(&file).await.expect("upsert");
+        let response = client.get(format!("http://{}{}&size=small", addr, url)).send().await.unwrap();
+        assert_eq!(response.headers()["cache-control"], "public, max-age=86400");
+
+        let list: FilesResponse = client
+            .get(format!("http://{}/api/files", addr))
+            .send()
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+        let new_url = list.items[0]["thumbnailUrl"].as_str().unwrap().to_string();
+        assert_ne!(new_url, url);
+        let response = client.get(format!("http://{}{}&size=small", addr, new_url)).send().await.unwrap();
+        assert_eq!(response.headers()["cache-control"], "public, max-age=31536000, immutable");
+    }
+
+    /// 生成缩略图时记录曝光统计，之后可按 exposure 筛选
+    #[tokio::test]
+    async fn test_list_files_exposure_filter() {
+        let (config, temp_dir) = test_config().await;
+        let app = App::new(config.clone()).await.expect("Failed to create app");
+        let (addr, _shutdown) = start_test_server(&app).await;
+
+        let mut png = std::io::Cursor::new(Vec::new());
+        image::RgbImage::new(400, 200)
+            .write_to(&mut png, image::ImageFormat::Png)
+            .unwrap();
+        let id = insert_original(&config, temp_dir.path(), "night.png", &png.into_inner()).await;
+
+        let client = reqwest::Client::new();
+        let list = |exposure: &'static str| {
+            let client = client.clone();
+            async move {
+                client
+                    .get(format!("http://{}/api/files?exposure={}", addr, exposure))
+                    .send()
+                    .await
+                    .unwrap()
+                    .json::<FilesResponse>()
+                    .await
+                    .unwrap()
+            }
+        };
+
+        // 还没有缩略图，没有统计数据
+        assert_eq!(list("underexposed").await.total, 0);
+
+        let response = client
+            .get(format!("http://{}/api/files/{}/thumbnail?size=small", addr, id))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let body = list("underexposed").await;
+        assert_eq!(body.total, 1);
+        assert_eq!(body.items[0]["id"], id.as_str());
+        assert!(body.items[0]["meanLuminance"].as_f64().unwrap() < 0.01);
+        assert!(body.items[0]["clippedShadows"].as_f64().unwrap() > 0.99);
+        assert_eq!(list("lowLight").await.total, 1);
+        assert_eq!(list("overexposed").await.total, 0);
+    }
+
+    /// 视频帧接口只接受视频，时间必须在 0 与时长之间
+    #[tokio::test]
+    async fn test_video_frame_validation() {
+        use latte_album::db::{DatabasePool, MediaFileRepository};
+
+        let (config, _temp_dir) = test_config().await;
+        let app = App::new(config.clone()).await.expect("Failed to create app");
+        let (addr, _shutdown) = start_test_server(&app).await;
+
+        let db = DatabasePool::new(&config.db_path).await.expect("open db");
+        let repo = MediaFileRepository::new(&db);
+        let mut video = latte_album::fixtures::create_test_media_file_with("clip.mp4", "video", None);
+        video.duration = Some(10.0);
+        let photo = latte_album::fixtures::create_test_media_file("photo.jpg");
+        repo.batch_upsert(&[video.clone(), photo.clone()]).await.unwrap();
+
+        let client = reqwest::Client::new();
+        let cases = [
+            (&video.id, "t=-1", StatusCode::BAD_REQUEST, "invalid_frame_time"),
+            (&video.id, "t=10.5", StatusCode::BAD_REQUEST, "invalid_frame_time"),
+            (&video.id, "width=100", StatusCode::BAD_REQUEST, "invalid_frame_time"),
+            (&photo.id, "t=1", StatusCode::NOT_FOUND, "not_a_video"),
+        ];
+        for (id, query, status, code) in cases {
+            let response = client
+                .get(format!("http://{}/api/files/{}/frame?{}", addr, id, query))
+                .send()
+                .await
+                .unwrap();
+            assert_eq!(response.status(), status, "{}", query);
+            let body: serde_json::Value = response.json().await.unwrap();
+            assert_eq!(body["code"], code, "{}", query);
+        }
+    }
+
+    /// 人像标记出现在文件详情中；没有深度图的文件 /depth 返回 404
+    #[tokio::test]
+    async fn test_portrait_flags_and_missing_depth_map() {
+        use latte_album::db::{DatabasePool, MediaFileRepository};
+
+        let (config, _temp_dir) = test_config().await;
+        let app = App::new(config.clone()).await.expect("Failed to create app");
+        let (addr, _shutdown) = start_test_server(&app).await;
+
+        let db = DatabasePool::new(&config.db_path).await.expect("open db");
+        let repo = MediaFileRepository::new(&db);
+        let mut portrait = latte_album::fixtures::create_test_media_file("portrait.heic");
+        portrait.has_portrait_matte = true;
+        let plain = latte_album::fixtures::create_test_media_file("plain.jpg");
+        repo.upsert(&portrait).await.expect("upsert");
+        repo.upsert(&plain).await.expect("upsert");
+
+        let client = reqwest::Client::new();
+        let body: serde_json::Value = client
+            .get(format!("http://{}/api/files/{}", addr, portrait.id))
+            .send()
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+        assert_eq!(body.get("hasPortraitMatte").and_then(|v| v.as_bool()), Some(true));
+        assert!(body.get("hasDepthMap").is_none());
+
+        let response = client
+            .get(format!("http://{}/api/files/{}/depth", addr, plain.id))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
 }
@@ -0,0 +1,143 @@
+//! Maintenance API integration tests
+
+#[cfg(test)]
+mod tests {
+    use reqwest::StatusCode;
+    use latte_album::helpers::start_test_server;
+    use latte_album::config::Config;
+    use latte_album::app::App;
+    use tempfile::TempDir;
+
+    /// Create a test configuration with file-based database for isolation
+    async fn test_config() -> (Config, TempDir) {
+        let temp_dir = tempfile::Builder::new()
+            .prefix("latte_test_maint_")
+            .tempdir()
+            .expect("Failed to create temp dir");
+        let db_path = temp_dir.path().join("test.db");
+
+        let config = Config {
+            db_path,
+            ..Config::default()
+        };
+
+        (config, temp_dir)
+    }
+
+    #[tokio::test]
+    async fn test_backfill_started() {
+        let (config, _temp_dir) = test_config().await;
+        let app = App::new(config).await.expect("Failed to create app");
+        let (addr, _shutdown) = start_test_server(&app).await;
+
+        let client = reqwest::Client::new();
+        let response = client
+            .post(format!("http://{}/api/maintenance/backfill", addr))
+            .json(&serde_json::json!({ "field": "gps" }))
+            .send()
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::ACCEPTED);
+        let body: serde_json::Value = response.json().await.unwrap();
+        assert_eq!(body["success"], true);
+    }
+
+    #[tokio::test]
+    async fn test_backfill_unsupported_field() {
+        let (config, _temp_dir) = test_config().await;
+        let app = App::new(config).await.expect("Failed to create app");
+        let (addr, _shutdown) = start_test_server(&app).await;
+
+        let client = reqwest::Client::new();
+        let response = client
+            .post(format!("http://{}/api/maintenance/backfill", addr))
+            .json(&serde_json::json!({ "field": "filePath" }))
+            .send()
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_backup_and_list() {
+        let (mut config, temp_dir) = test_config().await;
+        config.backup_dir = Some(temp_dir.path().join("backups"));
+        let app = App::new(config).await.expect("Failed to create app");
+        let (addr, _shutdown) = start_test_server(&app).await;
+
+        let client = reqwest::Client::new();
+        let response = client
+            .post(format!("http://{}/api/maintenance/backup", addr))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::CREATED);
+
+        let response = client
+            .get(format!("http://{}/api/maintenance/backups", addr))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let backups: Vec<serde_json::Value> = response.json().await.unwrap();
+        assert_eq!(backups.len(), 1);
+        let name = backups[0]["name"].as_str().unwrap().to_string();
+        assert!(name.starts_with("album-"));
+
+        // 暂存恢复，重启时生效
+        let response = client
+            .post(format!("http://{}/api/maintenance/restore", addr))
+            .json(&serde_json::json!({ "name": name }))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::ACCEPTED);
+        assert!(temp_dir.path().join("test.db.restore").exists());
+    }
+
+    #[tokio::test]
+    async fn test_restore_unknown_backup() {
+        let (mut config, temp_dir) = test_config().await;
+        config.backup_dir = Some(temp_dir.path().join("backups"));
+        let app = App::new(config).await.expect("Failed to create app");
+        let (addr, _shutdown) = start_test_server(&app).await;
+
+        let client = reqwest::Client::new();
+        let response = client
+            .post(format!("http://{}/api/maintenance/restore", addr))
+            .json(&serde_json::json!({ "name": "../test.db" }))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_consistency_reports_unindexed_directory() {
+        let (mut config, temp_dir) = test_config().await;
+        let photos = temp_dir.path().join("photos");
+        latte_album::fixtures::SyntheticLibrary::with_root(1, &photos).write_jpegs(3).unwrap();
+        // 非媒体文件不计入
+        std::fs::write(photos.join("000").join("notes.txt"), b"not media").unwrap();
+        config.base_path = photos;
+        config.scan_on_first_run = false;
+        let app = App::new(config).await.expect("Failed to create app");
+        let (addr, _shutdown) = start_test_server(&app).await;
+
+        let response = reqwest::Client::new()
+            .get(format!("http://{}/api/maintenance/consistency", addr))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let body: serde_json::Value = response.json().await.unwrap();
+        assert_eq!(body["dbTotal"], 0);
+        assert_eq!(body["fsTotal"], 3);
+        assert_eq!(body["rescanRecommended"], true);
+        assert_eq!(body["discrepancies"][0]["directory"], "000");
+        assert_eq!(body["discrepancies"][0]["difference"], 3);
+    }
+}
@@ -4,3 +4,13 @@ pub mod files_api_test;
 pub mod directories_api_test;
 pub mod system_api_test;
 pub mod websocket_test;
+pub mod thumbnails_api_test;
+pub mod maintenance_api_test;
+pub mod static_files_test;
+pub mod search_api_test;
+pub mod webhooks_api_test;
+pub mod exports_api_test;
+pub mod notes_api_test;
+pub mod activity_api_test;
+pub mod attributes_api_test;
+pub mod sync_api_test;
@@ -0,0 +1,121 @@
+//! Notes API integration tests
+
+#[cfg(test)]
+mod tests {
+    use reqwest::StatusCode;
+    use latte_album::helpers::start_test_server;
+    use latte_album::config::Config;
+    use latte_album::app::App;
+    use latte_album::db::{DatabasePool, MediaFileRepository};
+    use tempfile::TempDir;
+
+    /// Create a test configuration with file-based database for isolation
+    async fn test_config() -> (Config, TempDir) {
+        let temp_dir = tempfile::Builder::new()
+            .prefix("latte_test_notes_")
+            .tempdir()
+            .expect("Failed to create temp dir");
+        let db_path = temp_dir.path().join("test.db");
+
+        let config = Config {
+            db_path,
+            ..Config::default()
+        };
+
+        (config, temp_dir)
+    }
+
+    #[tokio::test]
+    async fn test_notes_crud_with_replies() {
+        let (config, _temp_dir) = test_config().await;
+        let app = App::new(config.clone()).await.expect("Failed to create app");
+        let (addr, _shutdown) = start_test_server(&app).await;
+
+        let db = DatabasePool::new(&config.db_path).await.expect("open db");
+        let file = latte_album::fixtures::create_test_media_file("family.jpg");
+        MediaFileRepository::new(&db).upsert(&file).await.expect("upsert");
+
+        let client = reqwest::Client::new();
+        let notes_url = format!("http://{}/api/files/{}/notes", addr, file.id);
+
+        let response = client.post(&notes_url).json(&serde_json::json!({ "body": "  " })).send().await.unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+        let response = client
+            .post(&notes_url)
+            .json(&serde_json::json!({ "body": "Grandma's birthday", "author": "Mum" }))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::CREATED);
+        let note: serde_json::Value = response.json().await.unwrap();
+        let note_id = note["id"].as_str().unwrap().to_string();
+        assert_eq!(note["author"], "Mum");
+        assert!(note["parentId"].is_null());
+
+        let response = client
+            .post(&notes_url)
+            .json(&serde_json::json!({ "body": "It was 2019", "parentId": note_id }))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::CREATED);
+        let reply: serde_json::Value = response.json().await.unwrap();
+        assert_eq!(reply["parentId"], note_id.as_str());
+
+        // 上级备注必须属于同一文件
+        let response = client
+            .post(&notes_url)
+            .json(&serde_json::json!({ "body": "x", "parentId": "missing" }))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+        let detail: serde_json::Value = client
+            .get(format!("http://{}/api/files/{}", addr, file.id))
+            .send()
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+        assert_eq!(detail["noteCount"], 2);
+
+        let response = client
+            .patch(format!("http://{}/api/notes/{}", addr, note_id))
+            .json(&serde_json::json!({ "body": "Grandma's 80th birthday" }))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let updated: serde_json::Value = response.json().await.unwrap();
+        assert_eq!(updated["body"], "Grandma's 80th birthday");
+        assert!(!updated["updatedAt"].is_null());
+
+        // 删除备注时连同回复一起删除
+        let response = client.delete(format!("http://{}/api/notes/{}", addr, note_id)).send().await.unwrap();
+        assert_eq!(response.status(), StatusCode::NO_CONTENT);
+        let notes: Vec<serde_json::Value> = client.get(&notes_url).send().await.unwrap().json().await.unwrap();
+        assert!(notes.is_empty());
+
+        let response = client.delete(format!("http://{}/api/notes/{}", addr, note_id)).send().await.unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_notes_unknown_file() {
+        let (config, _temp_dir) = test_config().await;
+        let app = App::new(config).await.expect("Failed to create app");
+        let (addr, _shutdown) = start_test_server(&app).await;
+
+        let client = reqwest::Client::new();
+        let response = client
+            .post(format!("http://{}/api/files/missing/notes", addr))
+            .json(&serde_json::json!({ "body": "hello" }))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+}
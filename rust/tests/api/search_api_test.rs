@@ -0,0 +1,93 @@
+//! Full-text and semantic search API integration tests
+
+#[cfg(test)]
+mod tests {
+    use reqwest::StatusCode;
+    use latte_album::helpers::start_test_server;
+    use latte_album::config::Config;
+    use latte_album::app::App;
+    use tempfile::TempDir;
+
+    /// Create a test configuration with file-based database for isolation
+    async fn test_config() -> (Config, TempDir) {
+        let temp_dir = tempfile::Builder::new()
+            .prefix("latte_test_search_")
+            .tempdir()
+            .expect("Failed to create temp dir");
+        let db_path = temp_dir.path().join("test.db");
+
+        let config = Config {
+            db_path,
+            ..Config::default()
+        };
+
+        (config, temp_dir)
+    }
+
+    #[tokio::test]
+    async fn test_text_search_requires_query() {
+        let (config, _temp_dir) = test_config().await;
+        let app = App::new(config).await.expect("Failed to create app");
+        let (addr, _shutdown) = start_test_server(&app).await;
+
+        let client = reqwest::Client::new();
+        let response = client
+            .get(format!("http://{}/api/search?q=", addr))
+            .send()
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_text_search_empty_library() {
+        let (config, _temp_dir) = test_config().await;
+        let app = App::new(config).await.expect("Failed to create app");
+        let (addr, _shutdown) = start_test_server(&app).await;
+
+        let client = reqwest::Client::new();
+        let response = client
+            .get(format!("http://{}/api/search?q=beach", addr))
+            .send()
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        let body: serde_json::Value = response.json().await.unwrap();
+        assert_eq!(body["total"], 0);
+        assert!(body["items"].as_array().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_semantic_search_requires_query() {
+        let (config, _temp_dir) = test_config().await;
+        let app = App::new(config).await.expect("Failed to create app");
+        let (addr, _shutdown) = start_test_server(&app).await;
+
+        let client = reqwest::Client::new();
+        let response = client
+            .get(format!("http://{}/api/search/semantic?q=%20", addr))
+            .send()
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_semantic_search_not_configured() {
+        let (config, _temp_dir) = test_config().await;
+        let app = App::new(config).await.expect("Failed to create app");
+        let (addr, _shutdown) = start_test_server(&app).await;
+
+        let client = reqwest::Client::new();
+        let response = client
+            .get(format!("http://{}/api/search/semantic?q=cat", addr))
+            .send()
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+    }
+}
@@ -0,0 +1,106 @@
+//! Static file serving integration tests (SPA fallback, precompressed assets)
+
+#[cfg(test)]
+mod tests {
+    use reqwest::StatusCode;
+    use latte_album::helpers::start_test_server;
+    use latte_album::config::Config;
+    use latte_album::app::App;
+    use tempfile::TempDir;
+
+    /// Create a config whose static_dir contains a minimal built frontend
+    async fn test_config() -> (Config, TempDir) {
+        let temp_dir = tempfile::Builder::new()
+            .prefix("latte_test_static_")
+            .tempdir()
+            .expect("Failed to create temp dir");
+
+        let static_dir = temp_dir.path().join("dist");
+        std::fs::create_dir_all(static_dir.join("assets")).unwrap();
+        std::fs::write(static_dir.join("index.html"), "<html>spa</html>").unwrap();
+        std::fs::write(static_dir.join("favicon.ico"), b"icon").unwrap();
+        std::fs::write(static_dir.join("assets/app.js"), "console.log('plain')").unwrap();
+        std::fs::write(static_dir.join("assets/app.js.br"), b"brotli-bytes").unwrap();
+        std::fs::write(static_dir.join("assets/app.js.gz"), b"gzip-bytes").unwrap();
+
+        let config = Config {
+            db_path: temp_dir.path().join("test.db"),
+            static_dir,
+            ..Config::default()
+        };
+
+        (config, temp_dir)
+    }
+
+    #[tokio::test]
+    async fn test_spa_fallback_serves_index() {
+        let (config, _temp_dir) = test_config().await;
+        let app = App::new(config).await.expect("Failed to create app");
+        let (addr, _shutdown) = start_test_server(&app).await;
+
+        let client = reqwest::Client::new();
+        let response = client
+            .get(format!("http://{}/albums/2024/some-view", addr))
+            .send()
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()["cache-control"], "no-cache");
+        assert_eq!(response.text().await.unwrap(), "<html>spa</html>");
+
+        // 根目录下的真实文件直接返回
+        let response = client
+            .get(format!("http://{}/favicon.ico", addr))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.bytes().await.unwrap().as_ref(), b"icon");
+    }
+
+    #[tokio::test]
+    async fn test_unknown_api_path_not_found() {
+        let (config, _temp_dir) = test_config().await;
+        let app = App::new(config).await.expect("Failed to create app");
+        let (addr, _shutdown) = start_test_server(&app).await;
+
+        let client = reqwest::Client::new();
+        let response = client
+            .get(format!("http://{}/api/does-not-exist", addr))
+            .send()
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_precompressed_assets() {
+        let (config, _temp_dir) = test_config().await;
+        let app = App::new(config).await.expect("Failed to create app");
+        let (addr, _shutdown) = start_test_server(&app).await;
+        let url = format!("http://{}/assets/app.js", addr);
+
+        let client = reqwest::Client::new();
+
+        let response = client.get(&url).header("Accept-Encoding", "gzip, br").send().await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()["content-encoding"], "br");
+        assert_eq!(response.headers()["vary"], "Accept-Encoding");
+        assert!(response.headers()["content-type"].to_str().unwrap().contains("javascript"));
+        assert_eq!(
+            response.headers()["cache-control"],
+            "public, max-age=31536000, immutable"
+        );
+        assert_eq!(response.bytes().await.unwrap().as_ref(), b"brotli-bytes");
+
+        let response = client.get(&url).header("Accept-Encoding", "gzip, br;q=0").send().await.unwrap();
+        assert_eq!(response.headers()["content-encoding"], "gzip");
+        assert_eq!(response.bytes().await.unwrap().as_ref(), b"gzip-bytes");
+
+        let response = client.get(&url).send().await.unwrap();
+        assert!(response.headers().get("content-encoding").is_none());
+        assert_eq!(response.text().await.unwrap(), "console.log('plain')");
+    }
+}
@@ -0,0 +1,94 @@
+//! Incremental sync API integration tests
+
+#[cfg(test)]
+mod tests {
+    use reqwest::StatusCode;
+    use latte_album::helpers::start_test_server;
+    use latte_album::config::Config;
+    use latte_album::app::App;
+    use latte_album::db::{DatabasePool, MediaFileRepository};
+    use tempfile::TempDir;
+
+    /// Create a test configuration with file-based database for isolation
+    async fn test_config() -> (Config, TempDir) {
+        let temp_dir = tempfile::Builder::new()
+            .prefix("latte_test_sync_")
+            .tempdir()
+            .expect("Failed to create temp dir");
+        let db_path = temp_dir.path().join("test.db");
+
+        let config = Config {
+            db_path,
+            ..Config::default()
+        };
+
+        (config, temp_dir)
+    }
+
+    #[tokio::test]
+    async fn test_sync_changes() {
+        let (config, _temp_dir) = test_config().await;
+        let app = App::new(config.clone()).await.expect("Failed to create app");
+        let (addr, _shutdown) = start_test_server(&app).await;
+        let client = reqwest::Client::new();
+        let changes = |since: &str| {
+            let url = format!("http://{}/api/sync/changes?since={}", addr, since);
+            let client = client.clone();
+            async move {
+                let response = client.get(url).send().await.unwrap();
+                assert_eq!(response.status(), StatusCode::OK);
+                response.json::<serde_json::Value>().await.unwrap()
+            }
+        };
+
+        // 首次调用只返回游标
+        let response = client.get(format!("http://{}/api/sync/changes", addr)).send().await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body: serde_json::Value = response.json().await.unwrap();
+        assert_eq!(body["changes"].as_array().unwrap().len(), 0);
+        let cursor = body["cursor"].as_str().unwrap().to_string();
+
+        let db = DatabasePool::new(&config.db_path).await.expect("open db");
+        let repo = MediaFileRepository::new(&db);
+        let first = latte_album::fixtures::create_test_media_file("a.jpg");
+        let second = latte_album::fixtures::create_test_media_file("b.jpg");
+        repo.batch_upsert(&[first.clone(), second.clone()]).await.expect("upsert");
+
+        let body = changes(&cursor).await;
+        let items = body["changes"].as_array().unwrap();
+        assert_eq!(items.len(), 2);
+        assert_eq!(items[0]["id"], first.id.as_str());
+        assert_eq!(items[0]["change"], "created");
+        assert_eq!(items[0]["file"]["fileName"], "a.jpg");
+        assert_eq!(body["hasMore"], false);
+        assert_eq!(body["resetRequired"], false);
+        let cursor = body["cursor"].as_str().unwrap().to_string();
+
+        repo.delete_by_id(&second.id).await.expect("delete");
+        let body = changes(&cursor).await;
+        let items = body["changes"].as_array().unwrap();
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0]["id"], second.id.as_str());
+        assert_eq!(items[0]["change"], "deleted");
+        assert!(items[0].get("file").is_none());
+
+        // 分页
+        let response = client
+            .get(format!("http://{}/api/sync/changes?since=0&limit=1", addr))
+            .send()
+            .await
+            .unwrap();
+        let body: serde_json::Value = response.json().await.unwrap();
+        assert_eq!(body["changes"].as_array().unwrap().len(), 1);
+        assert_eq!(body["hasMore"], true);
+
+        for since in ["abc", "-1", "999"] {
+            let response = client
+                .get(format!("http://{}/api/sync/changes?since={}", addr, since))
+                .send()
+                .await
+                .unwrap();
+            assert_eq!(response.status(), StatusCode::BAD_REQUEST, "{since}");
+        }
+    }
+}
@@ -91,6 +91,25 @@ mod tests {
         assert!(response.status() == StatusCode::OK || response.status() == StatusCode::ACCEPTED);
     }
 
+    #[tokio::test]
+    async fn test_trigger_force_scan() {
+        let (config, _temp_dir) = test_config().await;
+        let app = App::new(config).await.expect("Failed to create app");
+        let (addr, _shutdown) = start_test_server(&app).await;
+
+        let client = reqwest::Client::new();
+        let response = client
+            .post(format!("http://{}/api/scan?force=true", addr))
+            .send()
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        let body: serde_json::Value = response.json().await.unwrap();
+        assert_eq!(body["success"], true);
+        assert_eq!(body["message"], "Force scan started");
+    }
+
     #[tokio::test]
     async fn test_get_scan_progress_idle() {
         let (config, _temp_dir) = test_config().await;
@@ -112,6 +131,43 @@ mod tests {
         assert_eq!(body.total_files, 0);
     }
 
+    #[tokio::test]
+    async fn test_get_scan_status_includes_last_scan_when_idle() {
+        let photos = TempDir::new().unwrap();
+        latte_album::fixtures::SyntheticLibrary::with_root(1, photos.path()).write_jpegs(2).unwrap();
+        let (config, _temp_dir) = test_config().await;
+        let config = Config {
+            base_path: photos.path().to_path_buf(),
+            scan_stability_window_seconds: 0,
+            ..config
+        };
+        let app = App::new(config).await.expect("Failed to create app");
+        let (addr, _shutdown) = start_test_server(&app).await;
+        let client = reqwest::Client::new();
+        let url = format!("http://{}/api/scan/status", addr);
+
+        let body: serde_json::Value = client.get(&url).send().await.unwrap().json().await.unwrap();
+        assert_eq!(body["type"], "scan");
+        assert_eq!(body["status"], "idle");
+        assert_eq!(body["scanning"], false);
+        assert!(body.get("lastScan").is_none());
+
+        client.post(format!("http://{}/api/scan", addr)).send().await.unwrap();
+        let mut body = serde_json::Value::Null;
+        for _ in 0..100 {
+            tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+            body = client.get(&url).send().await.unwrap().json().await.unwrap();
+            if body.get("lastScan").is_some() {
+                break;
+            }
+        }
+        assert_eq!(body["status"], "idle");
+        assert_eq!(body["lastScan"]["status"], "completed");
+        assert_eq!(body["lastScan"]["mode"], "incremental");
+        assert_eq!(body["lastScan"]["totalFiles"], 2);
+        assert_eq!(body["lastScan"]["processedFiles"], 2);
+    }
+
     #[tokio::test]
     async fn test_cancel_scan_not_scanning() {
         let (config, _temp_dir) = test_config().await;
@@ -128,4 +184,166 @@ mod tests {
         // Cancel when not scanning should return success (idempotent)
         assert_eq!(response.status(), StatusCode::OK);
     }
+
+    #[tokio::test]
+    async fn test_list_processors_with_disabled() {
+        let (mut config, _temp_dir) = test_config().await;
+        config.disabled_processors = vec!["video".to_string()];
+        let app = App::new(config).await.expect("Failed to create app");
+        let (addr, _shutdown) = start_test_server(&app).await;
+
+        let client = reqwest::Client::new();
+        let response = client
+            .get(format!("http://{}/api/system/processors", addr))
+            .send()
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        let body: serde_json::Value = response.json().await.unwrap();
+        let processors = body["processors"].as_array().unwrap();
+        assert_eq!(processors.len(), 3);
+        // 按优先级排序，HEIF 在最前
+        assert_eq!(processors[0]["name"], "heif");
+
+        let video = processors.iter().find(|p| p["name"] == "video").unwrap();
+        assert_eq!(video["enabled"], false);
+        assert!(video["extensions"].as_array().unwrap().contains(&serde_json::json!("mp4")));
+        let image = processors.iter().find(|p| p["name"] == "image").unwrap();
+        assert_eq!(image["enabled"], true);
+        assert_eq!(body["features"]["heif"], true);
+    }
+
+    #[tokio::test]
+    async fn test_system_info() {
+        let (mut config, _temp_dir) = test_config().await;
+        config.ffprobe_path = "/nonexistent/ffprobe".into();
+        config.read_only = true;
+        let app = App::new(config).await.expect("Failed to create app");
+        let (addr, _shutdown) = start_test_server(&app).await;
+
+        let client = reqwest::Client::new();
+        let response = client
+            .get(format!("http://{}/api/system/info", addr))
+            .send()
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        let body: serde_json::Value = response.json().await.unwrap();
+        assert_eq!(body["version"], env!("CARGO_PKG_VERSION"));
+        assert!(body["gitHash"].is_string());
+        assert!(body["uptimeSeconds"].is_u64());
+        assert_eq!(body["library"]["totalFiles"], 0);
+        assert!(body["dbSizeBytes"].as_u64().unwrap() > 0);
+        let dependencies = body["dependencies"].as_array().unwrap();
+        assert!(dependencies.iter().any(|d| d["name"] == "libheif"));
+
+        // 缺失的依赖给出原因与配置项
+        let ffprobe = dependencies.iter().find(|d| d["name"] == "ffprobe").unwrap();
+        assert_eq!(ffprobe["available"], false);
+        assert!(ffprobe["message"].as_str().unwrap().contains("LATTE_VIDEO_FFPROBE_PATH"));
+
+        // 只读时前端隐藏修改原始文件的操作
+        assert_eq!(body["capabilities"]["readOnly"], true);
+        assert_eq!(body["capabilities"]["modifyOriginals"], false);
+    }
+
+    #[tokio::test]
+    async fn test_validate_config() {
+        let (mut config, temp_dir) = test_config().await;
+        config.cache_dir = temp_dir.path().join("cache");
+        config.base_path = temp_dir.path().join("missing-photos");
+        config.scan_cron = "every night".to_string();
+        let app = App::new(config).await.expect("Failed to create app");
+        let (addr, _shutdown) = start_test_server(&app).await;
+
+        let client = reqwest::Client::new();
+        let response = client
+            .get(format!("http://{}/api/admin/config/validate", addr))
+            .send()
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        let body: serde_json::Value = response.json().await.unwrap();
+        assert_eq!(body["valid"], false);
+        let issues = body["issues"].as_array().unwrap();
+        let severity = |key: &str| issues.iter().find(|i| i["key"] == key).map(|i| i["severity"].clone());
+        assert_eq!(severity("LATTE_BASE_PATH"), Some(serde_json::json!("error")));
+        assert_eq!(severity("LATTE_SCAN_CRON"), Some(serde_json::json!("error")));
+        // 运行中的服务占用着端口，不报告端口问题
+        assert_eq!(severity("LATTE_PORT"), None);
+        assert!(body["summary"].as_array().unwrap().iter().any(|e| e["key"] == "LATTE_DB_PATH"));
+
+        // 热重载修正后，校验的是新的配置而不是启动时的配置
+        let reloader = &app.state().config_reloader;
+        let reloaded = Config {
+            scan_cron: "0 30 4 * * ?".to_string(),
+            ..reloader.current().await
+        };
+        reloader.apply(reloaded).await.unwrap();
+        let body: serde_json::Value = client
+            .get(format!("http://{}/api/admin/config/validate", addr))
+            .send()
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+        let issues = body["issues"].as_array().unwrap();
+        assert!(issues.iter().all(|i| i["key"] != "LATTE_SCAN_CRON"));
+        assert!(issues.iter().any(|i| i["key"] == "LATTE_BASE_PATH"));
+    }
+
+    #[tokio::test]
+    async fn test_apply_reloaded_config() {
+        let (config, _temp_dir) = test_config().await;
+        let app = App::new(config.clone()).await.expect("Failed to create app");
+        let state = app.state();
+        let mut events = state.warnings.subscribe();
+
+        let reloaded = Config {
+            thumbnail_quality: 0.5,
+            scan_cron: "0 30 4 * * ?".to_string(),
+            port: config.port + 1,
+            ..config.clone()
+        };
+        let outcome = state.config_reloader.apply(reloaded).await.unwrap();
+        assert_eq!(outcome.applied, vec!["LATTE_THUMBNAIL_QUALITY", "LATTE_SCAN_CRON"]);
+        assert_eq!(outcome.restart_required, vec!["LATTE_PORT"]);
+        assert_eq!(state.file_service.thumbnail_quality(), 0.5);
+
+        let event = events.try_recv().unwrap();
+        assert_eq!(event.kind, "config");
+        assert_eq!(event.code, "config_reloaded");
+
+        // 非法值拒绝整次重新加载
+        let invalid = Config {
+            thumbnail_quality: 0.9,
+            scan_cron: "every night".to_string(),
+            ..config
+        };
+        assert!(state.config_reloader.apply(invalid).await.is_err());
+        assert_eq!(state.file_service.thumbnail_quality(), 0.5);
+    }
+
+    #[tokio::test]
+    async fn test_reload_config_endpoint() {
+        let (config, _temp_dir) = test_config().await;
+        let app = App::new(config).await.expect("Failed to create app");
+        let (addr, _shutdown) = start_test_server(&app).await;
+
+        let response = reqwest::Client::new()
+            .post(format!("http://{}/api/admin/config/reload", addr))
+            .send()
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        let body: serde_json::Value = response.json().await.unwrap();
+        assert!(body["applied"].is_array());
+        // 测试使用临时数据库，重新读取的默认路径与之不同，需要重启才能生效
+        assert!(body["restartRequired"].as_array().unwrap().iter().any(|key| key == "LATTE_DB_PATH"));
+    }
 }
@@ -0,0 +1,268 @@
+//! Thumbnails API integration tests
+
+#[cfg(test)]
+mod tests {
+    use reqwest::StatusCode;
+    use latte_album::helpers::start_test_server;
+    use latte_album::config::Config;
+    use latte_album::app::App;
+    use tempfile::TempDir;
+
+    /// Create a test configuration with file-based database for isolation
+    async fn test_config() -> (Config, TempDir) {
+        let temp_dir = tempfile::Builder::new()
+            .prefix("latte_test_thumbs_")
+            .tempdir()
+            .expect("Failed to create temp dir");
+        let db_path = temp_dir.path().join("test.db");
+        let cache_dir = temp_dir.path().join("cache");
+
+        let config = Config {
+            db_path,
+            cache_dir,
+            base_path: temp_dir.path().to_path_buf(),
+            ..Config::default()
+        };
+
+        (config, temp_dir)
+    }
+
+    #[tokio::test]
+    async fn test_disk_cached_thumbnail_uses_accel_redirect() {
+        let (config, _temp_dir) = test_config().await;
+        // 直接写入磁盘缓存（不经过内存缓存）
+        let shard = config.cache_dir.join("ab").join("cd");
+        std::fs::create_dir_all(&shard).unwrap();
+        std::fs::write(shard.join("abcd1234_small"), b"jpeg").unwrap();
+
+        let config = Config {
+            thumbnail_accel_redirect: Some("/_thumbs/".to_string()),
+            ..config
+        };
+        let app = App::new(config).await.expect("Failed to create app");
+        let (addr, _shutdown) = start_test_server(&app).await;
+
+        let response = reqwest::get(format!("http://{}/api/files/abcd1234/thumbnail?size=small", addr))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()["x-accel-redirect"], "/_thumbs/ab/cd/abcd1234_small");
+        assert_eq!(response.headers()["content-type"], "image/jpeg");
+        assert!(response.bytes().await.unwrap().is_empty());
+    }
+
+    /// 无法解码的文件返回占位图并登记失败；重试接口清除记录
+    #[tokio::test]
+    async fn test_corrupt_file_gets_placeholder() {
+        use latte_album::db::{DatabasePool, MediaFileRepository};
+
+        let (config, temp_dir) = test_config().await;
+        let path = temp_dir.path().join("broken.png");
+        std::fs::write(&path, b"definitely not a png").unwrap();
+
+        let app = App::new(config.clone()).await.expect("Failed to create app");
+        let (addr, _shutdown) = start_test_server(&app).await;
+
+        let db = DatabasePool::new(&config.db_path).await.expect("open db");
+        let mut file = latte_album::fixtures::create_test_media_file("broken.png");
+        file.file_path = path.to_string_lossy().to_string();
+        MediaFileRepository::new(&db).upsert(&file).await.expect("upsert");
+
+        let url = format!("http://{}/api/files/{}/thumbnail?size=small", addr, file.id);
+        // 第二次命中失败缓存，结果相同
+        for _ in 0..2 {
+            let response = reqwest::get(&url).await.unwrap();
+            assert_eq!(response.status(), StatusCode::OK);
+            assert_eq!(response.headers()["content-type"], "image/svg+xml");
+            assert_eq!(response.headers()["cache-control"], "no-cache");
+            assert!(response.text().await.unwrap().contains(">PNG</text>"));
+        }
+
+        let failed: Vec<serde_json::Value> = reqwest::get(format!("http://{}/api/maintenance/failed-files", addr))
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+        assert_eq!(failed.len(), 1);
+        assert_eq!(failed[0]["fileId"], serde_json::json!(file.id));
+        assert_eq!(failed[0]["stage"], "thumbnail");
+
+        let response = reqwest::Client::new()
+            .post(format!("http://{}/api/maintenance/failed-files/retry", addr))
+            .send()
+            .await
+            .unwrap();
+        let body: serde_json::Value = response.json().await.unwrap();
+        assert_eq!(body["code"], "failures_cleared");
+
+        // 数据库中没有的文件仍然是 404
+        let response = reqwest::get(format!("http://{}/api/files/unknown/thumbnail", addr)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_warm_thumbnails_accepted() {
+        let (config, _temp_dir) = test_config().await;
+        let app = App::new(config).await.expect("Failed to create app");
+        let (addr, _shutdown) = start_test_server(&app).await;
+
+        let client = reqwest::Client::new();
+        let response = client
+            .post(format!("http://{}/api/thumbnails/warm", addr))
+            .json(&serde_json::json!({ "ids": ["a", "b"], "sizes": ["small", "medium"] }))
+            .send()
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::ACCEPTED);
+        let body: serde_json::Value = response.json().await.unwrap();
+        let queued = body["queued"].as_u64().unwrap();
+        let skipped = body["skipped"].as_u64().unwrap();
+        let dropped = body["dropped"].as_u64().unwrap();
+        assert_eq!(queued + skipped + dropped, 4);
+    }
+
+    #[tokio::test]
+    async fn test_thumbnail_progress_returns_to_idle() {
+        let (config, _temp_dir) = test_config().await;
+        let app = App::new(config).await.expect("Failed to create app");
+        let (addr, _shutdown) = start_test_server(&app).await;
+
+        let client = reqwest::Client::new();
+        client
+            .post(format!("http://{}/api/thumbnails/warm", addr))
+            .json(&serde_json::json!({ "ids": ["missing-1", "missing-2"] }))
+            .send()
+            .await
+            .unwrap();
+
+        // 不存在的文件很快失败，批次结束后回到 idle
+        let mut body = serde_json::Value::Null;
+        for _ in 0..50 {
+            let response = client
+                .get(format!("http://{}/api/thumbnails/progress", addr))
+                .send()
+                .await
+                .unwrap();
+            assert_eq!(response.status(), StatusCode::OK);
+            body = response.json().await.unwrap();
+            if body["running"] == false {
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+        }
+        assert_eq!(body["type"], "thumbnails");
+        assert_eq!(body["running"], false);
+        assert_eq!(body["status"], "idle");
+    }
+
+    #[tokio::test]
+    async fn test_warm_thumbnails_invalid_size() {
+        let (config, _temp_dir) = test_config().await;
+        let app = App::new(config).await.expect("Failed to create app");
+        let (addr, _shutdown) = start_test_server(&app).await;
+
+        let client = reqwest::Client::new();
+        let response = client
+            .post(format!("http://{}/api/thumbnails/warm", addr))
+            .json(&serde_json::json!({ "ids": ["a"], "sizes": ["huge"] }))
+            .send()
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_warm_thumbnails_too_many_ids() {
+        let (config, _temp_dir) = test_config().await;
+        let app = App::new(config).await.expect("Failed to create app");
+        let (addr, _shutdown) = start_test_server(&app).await;
+
+        let ids: Vec<String> = (0..501).map(|i| format!("id-{}", i)).collect();
+        let client = reqwest::Client::new();
+        let response = client
+            .post(format!("http://{}/api/thumbnails/warm", addr))
+            .json(&serde_json::json!({ "ids": ids }))
+            .send()
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_thumbnail_invalid_crop_mode() {
+        let (config, _temp_dir) = test_config().await;
+        let app = App::new(config).await.expect("Failed to create app");
+        let (addr, _shutdown) = start_test_server(&app).await;
+
+        let client = reqwest::Client::new();
+        let response = client
+            .get(format!("http://{}/api/files/missing/thumbnail?size=small&crop=cover", addr))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+        let response = client
+            .get(format!("http://{}/api/files/missing/thumbnail?size=small&crop=smart", addr))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    /// 生成缩略图后可在 JSON 统计与 /metrics 中看到各阶段耗时
+    #[tokio::test]
+    async fn test_thumbnail_stats_after_generation() {
+        use latte_album::db::{DatabasePool, MediaFileRepository};
+
+        let (config, temp_dir) = test_config().await;
+        let path = temp_dir.path().join("photo.jpg");
+        image::RgbImage::from_pixel(640, 480, image::Rgb([200, 120, 40])).save(&path).unwrap();
+
+        let app = App::new(config.clone()).await.expect("Failed to create app");
+        let (addr, _shutdown) = start_test_server(&app).await;
+
+        let stats: serde_json::Value = reqwest::get(format!("http://{}/api/stats/thumbnails", addr))
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+        assert_eq!(stats["transcodingThreads"], serde_json::json!(config.transcoding_threads));
+        assert_eq!(stats["timings"], serde_json::json!([]));
+
+        let db = DatabasePool::new(&config.db_path).await.expect("open db");
+        let mut file = latte_album::fixtures::create_test_media_file("photo.jpg");
+        file.file_path = path.to_string_lossy().to_string();
+        MediaFileRepository::new(&db).upsert(&file).await.expect("upsert");
+
+        let response = reqwest::get(format!("http://{}/api/files/{}/thumbnail?size=small", addr, file.id))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()["content-type"], "image/jpeg");
+
+        let stats: serde_json::Value = reqwest::get(format!("http://{}/api/stats/thumbnails", addr))
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+        let timings = stats["timings"].as_array().unwrap();
+        let stages: Vec<&str> = timings.iter().map(|t| t["stage"].as_str().unwrap()).collect();
+        assert_eq!(stages, ["decode", "resize", "encode"]);
+        assert!(timings.iter().all(|t| t["source"] == "jpeg" && t["output"] == "jpeg" && t["count"] == 1));
+
+        let response = reqwest::get(format!("http://{}/metrics", addr)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert!(response.headers()["content-type"].to_str().unwrap().starts_with("text/plain"));
+        let text = response.text().await.unwrap();
+        assert!(text.contains(
+            "latte_thumbnail_stage_seconds_count{source=\"jpeg\",output=\"jpeg\",stage=\"decode\"} 1"
+        ));
+    }
+}
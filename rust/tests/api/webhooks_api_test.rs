@@ -0,0 +1,156 @@
+//! Webhook API integration tests
+
+#[cfg(test)]
+mod tests {
+    use reqwest::StatusCode;
+    use latte_album::helpers::start_test_server;
+    use latte_album::config::Config;
+    use latte_album::app::App;
+    use latte_album::services::webhooks::sign;
+    use tempfile::TempDir;
+    use tokio::sync::mpsc;
+
+    /// Create a test configuration with file-based database for isolation
+    async fn test_config() -> (Config, TempDir) {
+        let temp_dir = tempfile::Builder::new()
+            .prefix("latte_test_webhooks_")
+            .tempdir()
+            .expect("Failed to create temp dir");
+        let db_path = temp_dir.path().join("test.db");
+
+        let config = Config {
+            db_path,
+            webhook_low_disk_mb: 0,
+            ..Config::default()
+        };
+
+        (config, temp_dir)
+    }
+
+    /// Start a server recording (X-Latte-Event, X-Latte-Signature, body) of every POST
+    async fn start_receiver() -> (std::net::SocketAddr, mpsc::UnboundedReceiver<(String, String, Vec<u8>)>) {
+        let (tx, rx) = mpsc::unbounded_channel();
+        let router = axum::Router::new().route(
+            "/hook",
+            axum::routing::post(move |headers: axum::http::HeaderMap, body: axum::body::Bytes| {
+                let tx = tx.clone();
+                async move {
+                    let header = |name: &str| {
+                        headers.get(name).and_then(|v| v.to_str().ok()).unwrap_or_default().to_string()
+                    };
+                    let _ = tx.send((header("x-latte-event"), header("x-latte-signature"), body.to_vec()));
+                    StatusCode::OK
+                }
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            axum::serve(listener, router).await.unwrap();
+        });
+        (addr, rx)
+    }
+
+    #[tokio::test]
+    async fn test_create_validates_input() {
+        let (config, _temp_dir) = test_config().await;
+        let app = App::new(config).await.expect("Failed to create app");
+        let (addr, _shutdown) = start_test_server(&app).await;
+        let client = reqwest::Client::new();
+        let url = format!("http://{}/api/webhooks", addr);
+
+        let response = client
+            .post(&url)
+            .json(&serde_json::json!({ "url": "ftp://example.com/hook" }))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+        let response = client
+            .post(&url)
+            .json(&serde_json::json!({ "url": "https://example.com/hook", "events": ["scan.exploded"] }))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+        let response = client.delete(format!("{}/missing", url)).send().await.unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_register_test_and_delete_webhook() {
+        let (config, _temp_dir) = test_config().await;
+        let app = App::new(config).await.expect("Failed to create app");
+        let (addr, _shutdown) = start_test_server(&app).await;
+        let (receiver_addr, mut deliveries) = start_receiver().await;
+        let client = reqwest::Client::new();
+        let url = format!("http://{}/api/webhooks", addr);
+
+        let response = client
+            .post(&url)
+            .json(&serde_json::json!({
+                "url": format!("http://{}/hook", receiver_addr),
+                "events": ["scan.completed", "disk.low"],
+                "secret": "s3cret",
+            }))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::CREATED);
+        let created: serde_json::Value = response.json().await.unwrap();
+        let id = created["id"].as_str().unwrap().to_string();
+        assert_eq!(created["events"], serde_json::json!(["scan.completed", "disk.low"]));
+        assert_eq!(created["hasSecret"], true);
+        // secret 不会被返回
+        assert!(created.get("secret").is_none());
+
+        let listed: Vec<serde_json::Value> = client.get(&url).send().await.unwrap().json().await.unwrap();
+        assert_eq!(listed.len(), 1);
+        assert_eq!(listed[0]["id"], id.as_str());
+
+        let response = client.post(format!("{}/{}/test", url, id)).send().await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let (event, signature, body) = deliveries.recv().await.unwrap();
+        assert_eq!(event, "webhook.test");
+        assert_eq!(signature, sign("s3cret", &body));
+        let payload: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(payload["event"], "webhook.test");
+        assert_eq!(payload["data"]["webhookId"], id.as_str());
+
+        let response = client.delete(format!("{}/{}", url, id)).send().await.unwrap();
+        assert_eq!(response.status(), StatusCode::NO_CONTENT);
+        let listed: Vec<serde_json::Value> = client.get(&url).send().await.unwrap().json().await.unwrap();
+        assert!(listed.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_unreachable_webhook_reports_failure() {
+        let (config, _temp_dir) = test_config().await;
+        let app = App::new(config).await.expect("Failed to create app");
+        let (addr, _shutdown) = start_test_server(&app).await;
+        let client = reqwest::Client::new();
+
+        // 绑定后立即释放的端口，连接会被拒绝
+        let closed = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap().local_addr().unwrap();
+        let created: serde_json::Value = client
+            .post(format!("http://{}/api/webhooks", addr))
+            .json(&serde_json::json!({ "url": format!("http://{}/hook", closed) }))
+            .send()
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+
+        let response = client
+            .post(format!("http://{}/api/webhooks/{}/test", addr, created["id"].as_str().unwrap()))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_GATEWAY);
+        let body: serde_json::Value = response.json().await.unwrap();
+        assert_eq!(body["success"], false);
+    }
+}
@@ -4,8 +4,16 @@
 mod tests {
     use latte_album::config::Config;
     use latte_album::app::App;
+    use latte_album::fixtures::SyntheticLibrary;
     use latte_album::helpers::start_test_server;
+    use futures_util::StreamExt;
+    use serde_json::Value;
+    use std::net::SocketAddr;
     use tempfile::TempDir;
+    use tokio::time::{timeout, Duration};
+    use tokio_tungstenite::tungstenite::Message;
+
+    type WsStream = tokio_tungstenite::WebSocketStream<tokio_tungstenite::MaybeTlsStream<tokio::net::TcpStream>>;
 
     /// Create a test configuration with file-based database for isolation
     async fn test_config() -> (Config, TempDir) {
@@ -53,4 +61,177 @@ mod tests {
         assert!(result2.is_ok(), "Client 2 should connect");
         assert!(result3.is_ok(), "Client 3 should connect");
     }
+
+    /// Config scanning `photos_dir`, with every progress update broadcast.
+    /// 稳定性窗口设为 0，否则刚写入的测试文件会被推迟到下一次扫描
+    async fn scan_config(photos_dir: &std::path::Path) -> (Config, TempDir) {
+        let (config, temp_dir) = test_config().await;
+        let config = Config {
+            base_path: photos_dir.to_path_buf(),
+            cache_dir: temp_dir.path().join("cache"),
+            scan_stability_window_seconds: 0,
+            ws_progress_broadcast_interval: 1,
+            ..config
+        };
+        (config, temp_dir)
+    }
+
+    /// Connect and consume the state message sent on connection.
+    /// 服务端先订阅广播再发送当前状态，收到该消息后即可触发扫描
+    async fn connect(addr: SocketAddr) -> (WsStream, Value) {
+        let url = format!("ws://{}/ws/scan", addr);
+        let (mut ws, _) = timeout(Duration::from_secs(30), tokio_tungstenite::connect_async(url))
+            .await
+            .expect("Timed out connecting to the WebSocket")
+            .expect("WebSocket connection should succeed");
+        let initial = next_scan_message(&mut ws).await;
+        (ws, initial)
+    }
+
+    /// Next scan progress message (thumbnail progress on the same socket is skipped)
+    async fn next_scan_message(ws: &mut WsStream) -> Value {
+        loop {
+            let message = timeout(Duration::from_secs(30), ws.next())
+                .await
+                .expect("Timed out waiting for a WebSocket message")
+                .expect("WebSocket closed")
+                .expect("WebSocket error");
+            if let Message::Text(text) = message {
+                let value: Value = serde_json::from_str(&text).expect("Message should be JSON");
+                if value["type"] == "scan" {
+                    return value;
+                }
+            }
+        }
+    }
+
+    /// Messages up to and including the one with a terminal status
+    async fn messages_until_finished(ws: &mut WsStream) -> Vec<Value> {
+        let mut messages = Vec::new();
+        loop {
+            let message = next_scan_message(ws).await;
+            let finished = matches!(message["status"].as_str(), Some("completed" | "cancelled" | "error"));
+            messages.push(message);
+            if finished {
+                return messages;
+            }
+        }
+    }
+
+    /// Phases in order of appearance, consecutive progress messages of a phase collapsed
+    fn phase_sequence(messages: &[Value]) -> Vec<String> {
+        let mut phases: Vec<String> = Vec::new();
+        for message in messages {
+            let phase = message["phase"].as_str().unwrap_or_default().to_string();
+            if phases.last() != Some(&phase) {
+                phases.push(phase);
+            }
+        }
+        phases
+    }
+
+    async fn post(addr: SocketAddr, path: &str) -> Value {
+        reqwest::Client::new()
+            .post(format!("http://{}{}", addr, path))
+            .send()
+            .await
+            .expect("Request failed")
+            .json()
+            .await
+            .expect("Response should be JSON")
+    }
+
+    #[tokio::test]
+    async fn test_websocket_scan_phase_sequence() {
+        let photos = TempDir::new().unwrap();
+        SyntheticLibrary::with_root(1, photos.path()).write_jpegs(5).unwrap();
+        let (config, _temp_dir) = scan_config(photos.path()).await;
+        let app = App::new(config).await.expect("Failed to create app");
+        let (addr, _shutdown) = start_test_server(&app).await;
+
+        let (mut ws, initial) = connect(addr).await;
+        assert_eq!(initial["status"], "idle");
+        assert_eq!(initial["scanning"], false);
+
+        post(addr, "/api/scan").await;
+        let messages = messages_until_finished(&mut ws).await;
+
+        assert_eq!(phase_sequence(&messages), ["Collecting", "Counting", "Processing", "Deleting", "Completed"]);
+        let statuses: Vec<&str> = messages.iter().filter_map(|m| m["status"].as_str()).collect();
+        assert!(statuses[..statuses.len() - 1].iter().all(|s| *s == "progress"));
+
+        let last = messages.last().unwrap();
+        assert_eq!(last["status"], "completed");
+        assert_eq!(last["filesToAdd"], 5);
+        assert_eq!(last["successCount"], 5);
+        assert_eq!(last["progressPercentage"], "100.00");
+
+        // Processing 阶段每个文件都广播一次，成功数单调递增
+        let counts: Vec<u64> = messages
+            .iter()
+            .filter(|m| m["phase"] == "Processing")
+            .filter_map(|m| m["successCount"].as_u64())
+            .collect();
+        assert!(counts.windows(2).all(|w| w[0] <= w[1]));
+        assert_eq!(counts.last(), Some(&5));
+    }
+
+    #[tokio::test]
+    async fn test_websocket_rescan_unchanged_files_skips_processing() {
+        let photos = TempDir::new().unwrap();
+        SyntheticLibrary::with_root(2, photos.path()).write_jpegs(3).unwrap();
+        let (config, _temp_dir) = scan_config(photos.path()).await;
+        let app = App::new(config).await.expect("Failed to create app");
+        let (addr, _shutdown) = start_test_server(&app).await;
+
+        let (mut ws, _) = connect(addr).await;
+        post(addr, "/api/scan").await;
+        messages_until_finished(&mut ws).await;
+
+        post(addr, "/api/scan").await;
+        let messages = messages_until_finished(&mut ws).await;
+        assert_eq!(phase_sequence(&messages), ["Collecting", "Counting", "Writing", "Deleting", "Completed"]);
+        assert_eq!(messages.last().unwrap()["filesToAdd"], 0);
+    }
+
+    #[tokio::test]
+    async fn test_websocket_scan_cancelled_mid_scan() {
+        let photos = TempDir::new().unwrap();
+        SyntheticLibrary::with_root(3, photos.path()).write_jpegs(40).unwrap();
+        let (config, _temp_dir) = scan_config(photos.path()).await;
+        // 限速到每秒约 3 个文件，保证取消请求到达时扫描仍在 Processing 阶段
+        let config = Config {
+            scan_io_bytes_per_second: 2_000,
+            scan_worker_count: Some(2),
+            ..config
+        };
+        let app = App::new(config).await.expect("Failed to create app");
+        let (addr, _shutdown) = start_test_server(&app).await;
+
+        let (mut ws, _) = connect(addr).await;
+        post(addr, "/api/scan").await;
+
+        let mut messages = Vec::new();
+        loop {
+            let message = next_scan_message(&mut ws).await;
+            let processing = message["phase"] == "Processing";
+            messages.push(message);
+            if processing {
+                break;
+            }
+        }
+        let response = post(addr, "/api/system/scan/cancel").await;
+        assert_eq!(response["success"], true);
+        messages.extend(messages_until_finished(&mut ws).await);
+
+        assert_eq!(phase_sequence(&messages), ["Collecting", "Counting", "Processing", "Deleting", "Cancelled"]);
+        let last = messages.last().unwrap();
+        assert_eq!(last["status"], "cancelled");
+        assert_eq!(last["scanning"], false);
+        assert!(last["successCount"].as_u64().unwrap() < 40);
+
+        // 取消后状态回到 idle，新连接不会收到历史的取消消息
+        let (_ws, initial) = connect(addr).await;
+        assert_eq!(initial["status"], "idle");
+    }
 }
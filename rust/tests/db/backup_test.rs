@@ -0,0 +1,103 @@
+//! Database backup/restore integration tests
+
+#[cfg(test)]
+mod tests {
+    use latte_album::db::{backup, DatabasePool, MediaFileRepository};
+    use latte_album::fixtures::create_test_media_file;
+    use std::path::Path;
+
+    async fn test_db(dir: &Path) -> DatabasePool {
+        let pool = DatabasePool::new(&dir.join("album.db"))
+            .await
+            .expect("Failed to create database pool");
+        pool.migrate(Path::new("./src/db/migrations"))
+            .await
+            .expect("Failed to run migrations");
+        pool
+    }
+
+    #[tokio::test]
+    async fn test_backup_and_rotation() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let db = test_db(temp_dir.path()).await;
+        let backup_dir = temp_dir.path().join("backups");
+
+        MediaFileRepository::new(&db)
+            .upsert(&create_test_media_file("backup.jpg"))
+            .await
+            .unwrap();
+
+        let mut created = Vec::new();
+        for _ in 0..3 {
+            created.push(backup::create_backup(&db, &backup_dir, 2).await.unwrap());
+            tokio::time::sleep(std::time::Duration::from_millis(5)).await;
+        }
+
+        // 只保留最新的 2 份
+        let backups = backup::list_backups(&backup_dir).unwrap();
+        assert_eq!(backups.len(), 2);
+        assert!(!created[0].exists());
+        assert_eq!(backups[0].name, created[2].file_name().unwrap().to_string_lossy());
+
+        backup::validate_backup(&created[2]).await.expect("backup should be valid");
+    }
+
+    #[tokio::test]
+    async fn test_staged_restore_applied() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let db_path = temp_dir.path().join("album.db");
+        let backup_dir = temp_dir.path().join("backups");
+
+        let db = test_db(temp_dir.path()).await;
+        MediaFileRepository::new(&db)
+            .upsert(&create_test_media_file("before.jpg"))
+            .await
+            .unwrap();
+        let backup_path = backup::create_backup(&db, &backup_dir, 0).await.unwrap();
+
+        // 备份之后写入的数据应在恢复后消失
+        MediaFileRepository::new(&db)
+            .upsert(&create_test_media_file("after.jpg"))
+            .await
+            .unwrap();
+        db.get_pool().close().await;
+
+        backup::stage_restore(&backup_path, &db_path).await.unwrap();
+        assert!(backup::pending_restore_path(&db_path).exists());
+
+        assert!(backup::apply_pending_restore(&db_path).await.unwrap());
+        assert!(!backup::pending_restore_path(&db_path).exists());
+
+        let db = DatabasePool::new(&db_path).await.unwrap();
+        let repo = MediaFileRepository::new(&db);
+        assert!(repo.find_by_path(Path::new("/test/photos/before.jpg")).await.unwrap().is_some());
+        assert!(repo.find_by_path(Path::new("/test/photos/after.jpg")).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_invalid_backup_rejected() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let db_path = temp_dir.path().join("album.db");
+        let bogus = temp_dir.path().join("album-bogus.db");
+        std::fs::write(&bogus, b"not a database").unwrap();
+
+        assert!(backup::stage_restore(&bogus, &db_path).await.is_err());
+        assert!(!backup::pending_restore_path(&db_path).exists());
+
+        // 暂存文件损坏时启动不应失败，也不替换数据库
+        std::fs::copy(&bogus, backup::pending_restore_path(&db_path)).unwrap();
+        assert!(!backup::apply_pending_restore(&db_path).await.unwrap());
+        assert!(!backup::pending_restore_path(&db_path).exists());
+    }
+
+    #[test]
+    fn test_resolve_backup_rejects_traversal() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        std::fs::write(temp_dir.path().join("album-20240101-000000.000.db"), b"x").unwrap();
+
+        assert!(backup::resolve_backup(temp_dir.path(), "album-20240101-000000.000.db").is_some());
+        assert!(backup::resolve_backup(temp_dir.path(), "../album-x.db").is_none());
+        assert!(backup::resolve_backup(temp_dir.path(), "other.db").is_none());
+        assert!(backup::resolve_backup(temp_dir.path(), "album-missing.db").is_none());
+    }
+}
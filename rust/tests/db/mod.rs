@@ -1,3 +1,5 @@
 //! Database integration tests
 
 pub mod repository_test;
+pub mod backup_test;
+pub mod synthetic_library_test;
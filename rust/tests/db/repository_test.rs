@@ -3,7 +3,8 @@
 #[cfg(test)]
 mod tests {
     use latte_album::fixtures::{create_test_media_file, create_test_media_file_with};
-    use latte_album::db::{DatabasePool, MediaFileRepository};
+    use latte_album::db::{AttributeRepository, ChangeLogRepository, DatabasePool, ExposureFilter, FileQuery, MediaAttribute, MediaFileEdit, MediaFileRepository, MediaSubImage, RepositoryTx, SortField, SortOrder, TimelineBucket};
+    use latte_album::utils::calendar::Granularity;
     use chrono::{Utc, TimeZone};
 
     /// Wrapper that holds the database pool and keeps the temp dir alive
@@ -49,7 +50,7 @@ mod tests {
         repo.batch_upsert(&files).await.unwrap();
 
         let result = repo
-            .find_all(None, None, None, None, "exif_timestamp", "desc", 0, 50)
+            .find_all(&FileQuery::new().page(0, 50))
             .await
             .unwrap();
 
@@ -70,14 +71,14 @@ mod tests {
 
         // Get first page
         let result = repo
-            .find_all(None, None, None, None, "exif_timestamp", "desc", 0, 5)
+            .find_all(&FileQuery::new().page(0, 5))
             .await
             .unwrap();
         assert_eq!(result.len(), 5);
 
         // Get second page
         let result = repo
-            .find_all(None, None, None, None, "exif_timestamp", "desc", 1, 5)
+            .find_all(&FileQuery::new().page(1, 5))
             .await
             .unwrap();
         assert_eq!(result.len(), 5);
@@ -99,19 +100,78 @@ mod tests {
 
         // Filter by image type
         let result = repo
-            .find_all(None, Some("image"), None, None, "exif_timestamp", "desc", 0, 50)
+            .find_all(&FileQuery::new().file_type(Some("image")))
             .await
             .unwrap();
         assert_eq!(result.len(), 2);
 
         // Filter by video type
         let result = repo
-            .find_all(None, Some("video"), None, None, "exif_timestamp", "desc", 0, 50)
+            .find_all(&FileQuery::new().file_type(Some("video")))
             .await
             .unwrap();
         assert_eq!(result.len(), 1);
     }
 
+    #[tokio::test]
+    async fn test_count_matches_find_all_filters() {
+        let db = test_db_pool().await;
+        let pool = get_pool(&db);
+        let repo = MediaFileRepository::new(pool);
+
+        let mut files = vec![
+            create_test_media_file("a.jpg"),
+            create_test_media_file("b.jpg"),
+            create_test_media_file("c.jpg"),
+        ];
+        files[0].camera_model = Some("X100V".to_string());
+        files[0].exif_timestamp = Some(Utc.with_ymd_and_hms(2024, 5, 1, 8, 0, 0).unwrap().naive_utc());
+        files[1].camera_model = Some("X100V".to_string());
+        files[1].exif_timestamp = Some(Utc.with_ymd_and_hms(2023, 5, 1, 8, 0, 0).unwrap().naive_utc());
+        repo.batch_upsert(&files).await.unwrap();
+
+        let query = FileQuery::new().camera_model(Some("X100V")).date(Some("2024"));
+        assert_eq!(repo.find_all(&query).await.unwrap().len(), 1);
+        assert_eq!(repo.count(&query).await.unwrap(), 1);
+        assert_eq!(repo.count(&FileQuery::new().camera_model(Some("X100V"))).await.unwrap(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_find_all_blurry_filter_and_sort() {
+        let db = test_db_pool().await;
+        let pool = get_pool(&db);
+        let repo = MediaFileRepository::new(pool);
+
+        let mut files = vec![
+            create_test_media_file("sharp.jpg"),
+            create_test_media_file("soft.jpg"),
+            create_test_media_file("blurry.jpg"),
+            create_test_media_file("unscored.jpg"),
+        ];
+        files[0].blur_score = Some(850.0);
+        files[1].blur_score = Some(60.5);
+        files[2].blur_score = Some(12.25);
+        repo.batch_upsert(&files).await.unwrap();
+
+        // Only scored files below the threshold, blurriest first
+        let result = repo
+            .find_all(&FileQuery::new().max_blur_score(Some(100.0)).sort(SortField::BlurScore, SortOrder::Asc))
+            .await
+            .unwrap();
+        let names: Vec<&str> = result.iter().map(|f| f.file_name.as_str()).collect();
+        assert_eq!(names, vec!["blurry.jpg", "soft.jpg"]);
+        assert_eq!(result[0].blur_score, Some(12.25));
+        assert_eq!(repo.count(&FileQuery::new().max_blur_score(Some(100.0))).await.unwrap(), 2);
+
+        // Unscored files sort last
+        let result = repo
+            .find_all(&FileQuery::new().sort(SortField::BlurScore, SortOrder::Desc))
+            .await
+            .unwrap();
+        assert_eq!(result.first().unwrap().file_name, "sharp.jpg");
+        assert_eq!(result.last().unwrap().file_name, "unscored.jpg");
+    }
+
     #[tokio::test]
     async fn test_find_by_id() {
         let db = test_db_pool().await;
@@ -128,6 +188,67 @@ mod tests {
         assert_eq!(result.unwrap().file_name, "test.jpg");
     }
 
+    #[tokio::test]
+    async fn test_search_text_matches_names_and_captions() {
+        let db = test_db_pool().await;
+        let pool = get_pool(&db);
+        let repo = MediaFileRepository::new(pool);
+
+        let beach = create_test_media_file("beach_sunset.jpg");
+        let other = create_test_media_file("IMG_0001.jpg");
+        let other_id = other.id.clone();
+        repo.batch_upsert(&[beach, other]).await.unwrap();
+
+        let (files, total) = repo.search_text("sunset", 0, 10).await.unwrap();
+        assert_eq!(total, 1);
+        assert_eq!(files[0].file_name, "beach_sunset.jpg");
+
+        // 编辑描述后立即可搜索（触发器同步索引）
+        let edit = MediaFileEdit {
+            title: None,
+            description: Some(Some("在西湖边散步".to_string())),
+        };
+        repo.update_edits(&other_id, 1, &edit).await.unwrap();
+
+        let (files, total) = repo.search_text("西湖边", 0, 10).await.unwrap();
+        assert_eq!(total, 1);
+        assert_eq!(files[0].id, other_id);
+
+        // 两个字的词走 LIKE 回退
+        let (files, total) = repo.search_text("西湖", 0, 10).await.unwrap();
+        assert_eq!(total, 1);
+        assert_eq!(files[0].id, other_id);
+
+        let (_, total) = repo.search_text("sunset 西湖", 0, 10).await.unwrap();
+        assert_eq!(total, 0);
+    }
+
+    #[tokio::test]
+    async fn test_video_color_round_trip() {
+        let db = test_db_pool().await;
+        let repo = MediaFileRepository::new(get_pool(&db));
+
+        let mut single = create_test_media_file_with("hdr.mov", "video", None);
+        single.hdr_format = Some("hlg".to_string());
+        single.bit_depth = Some(10);
+        single.color_primaries = Some("bt2020".to_string());
+        let mut batched = create_test_media_file_with("dv.mp4", "video", None);
+        batched.hdr_format = Some("dolby_vision".to_string());
+        batched.bit_depth = Some(12);
+
+        repo.upsert(&single).await.unwrap();
+        repo.batch_upsert(std::slice::from_ref(&batched)).await.unwrap();
+
+        let stored = repo.find_by_id(&single.id).await.unwrap().unwrap();
+        assert_eq!(
+            (stored.hdr_format.as_deref(), stored.bit_depth, stored.color_primaries.as_deref()),
+            (Some("hlg"), Some(10), Some("bt2020"))
+        );
+        let stored = repo.find_by_id(&batched.id).await.unwrap().unwrap();
+        assert_eq!((stored.hdr_format.as_deref(), stored.bit_depth), (Some("dolby_vision"), Some(12)));
+        assert_eq!(stored.color_primaries, None);
+    }
+
     #[tokio::test]
     async fn test_find_by_id_not_found() {
         let db = test_db_pool().await;
@@ -162,6 +283,55 @@ mod tests {
         assert_eq!(dates.len(), 3);
     }
 
+    #[tokio::test]
+    async fn test_timeline() {
+        let db = test_db_pool().await;
+        let pool = get_pool(&db);
+        let repo = MediaFileRepository::new(pool);
+
+        let at = |month: u32, day: u32| chrono::NaiveDate::from_ymd_opt(2024, month, day).unwrap().and_hms_opt(12, 0, 0);
+        // 2024-03-03 周日，03-04 周一，03-06 周三
+        let files = vec![
+            create_test_media_file_with("sunday.jpg", "image", at(3, 3)),
+            create_test_media_file_with("monday.jpg", "image", at(3, 4)),
+            create_test_media_file_with("wednesday.mp4", "video", at(3, 6)),
+            create_test_media_file_with("april.jpg", "image", at(4, 1)),
+        ];
+        repo.batch_upsert(&files).await.unwrap();
+
+        let starts = |buckets: Vec<TimelineBucket>| -> Vec<(String, i64)> {
+            buckets.into_iter().map(|b| (b.start, b.count)).collect()
+        };
+
+        let weeks = repo.timeline(Granularity::Week, &FileQuery::new(), chrono::Weekday::Mon).await.unwrap();
+        assert_eq!(
+            starts(weeks),
+            vec![("2024-04-01".to_string(), 1), ("2024-03-04".to_string(), 2), ("2024-02-26".to_string(), 1)]
+        );
+
+        let weeks = repo.timeline(Granularity::Week, &FileQuery::new(), chrono::Weekday::Sun).await.unwrap();
+        assert_eq!(starts(weeks), vec![("2024-03-31".to_string(), 1), ("2024-03-03".to_string(), 3)]);
+
+        let weeks = repo.timeline(Granularity::Week, &FileQuery::new().file_type(Some("video")), chrono::Weekday::Sat).await.unwrap();
+        assert_eq!(starts(weeks), vec![("2024-03-02".to_string(), 1)]);
+
+        let days = repo.timeline(Granularity::Day, &FileQuery::new().file_type(Some("image")), chrono::Weekday::Mon).await.unwrap();
+        assert_eq!(days.len(), 3);
+
+        // 每个桶带最早/最晚文件的 id
+        let months = repo.timeline(Granularity::Month, &FileQuery::new(), chrono::Weekday::Mon).await.unwrap();
+        assert_eq!(months.len(), 2);
+        assert_eq!((months[0].start.as_str(), months[0].count), ("2024-04-01", 1));
+        assert_eq!(months[0].first_id, files[3].id);
+        assert_eq!(months[0].last_id, files[3].id);
+        assert_eq!((months[1].start.as_str(), months[1].count), ("2024-03-01", 3));
+        assert_eq!(months[1].first_id, files[0].id);
+        assert_eq!(months[1].last_id, files[2].id);
+
+        let years = repo.timeline(Granularity::Year, &FileQuery::new(), chrono::Weekday::Mon).await.unwrap();
+        assert_eq!(starts(years), vec![("2024-01-01".to_string(), 4)]);
+    }
+
     #[tokio::test]
     async fn test_delete_missing() {
         let db = test_db_pool().await;
@@ -188,4 +358,432 @@ mod tests {
         let result = repo.find_by_id(&files[0].id).await.unwrap();
         assert!(result.is_some());
     }
+
+    /// 扫描的存在性检查按 NFC 路径 key 匹配：分解形式的路径也能找到已入库的文件
+    #[tokio::test]
+    async fn test_find_existing_normalizes_paths() {
+        use std::path::PathBuf;
+
+        let db = test_db_pool().await;
+        let pool = get_pool(&db);
+        let repo = MediaFileRepository::new(pool);
+
+        let mut file = create_test_media_file("caf\u{e9}.jpg");
+        file.file_path = "/test/photos/caf\u{e9}.jpg".to_string();
+        repo.batch_upsert(&[file.clone()]).await.unwrap();
+
+        let existing = repo
+            .find_existing(&[
+                PathBuf::from("/test/photos/cafe\u{301}.jpg"),
+                PathBuf::from("/test/photos/new.jpg"),
+            ])
+            .await
+            .unwrap();
+        assert_eq!(existing.len(), 1);
+        let found = &existing["/test/photos/caf\u{e9}.jpg"];
+        // 扫描按 LATTE_SCAN_CHANGE_DETECTION 比较大小和指纹
+        assert_eq!(found.file_size, Some(1024));
+        assert_eq!(found.content_hash, file.content_hash);
+
+        // touch / 删除同样按 key 匹配，不会误删
+        assert_eq!(repo.batch_touch(&[PathBuf::from("/test/photos/cafe\u{301}.jpg")]).await.unwrap(), 1);
+        repo.delete_missing(&["/test/photos/cafe\u{301}.jpg".to_string()]).await.unwrap();
+        assert!(repo.find_by_id(&file.id).await.unwrap().is_some());
+    }
+
+    /// 同一文件以另一种 Unicode 形式再次入库时更新原记录，不会新增一行
+    #[tokio::test]
+    async fn test_upsert_matches_path_key() {
+        let db = test_db_pool().await;
+        let pool = get_pool(&db);
+        let repo = MediaFileRepository::new(pool);
+
+        let mut file = create_test_media_file("caf\u{e9}.jpg");
+        file.file_path = "/test/photos/caf\u{e9}.jpg".to_string();
+        repo.upsert(&file).await.unwrap();
+
+        let mut decomposed = create_test_media_file("cafe\u{301}.jpg");
+        decomposed.file_path = "/test/photos/cafe\u{301}.jpg".to_string();
+        decomposed.width = Some(640);
+        repo.upsert(&decomposed).await.unwrap();
+        decomposed.width = Some(800);
+        repo.batch_upsert(&[decomposed.clone()]).await.unwrap();
+
+        assert_eq!(repo.count(&Default::default()).await.unwrap(), 1);
+        let stored = repo.find_by_id(&file.id).await.unwrap().unwrap();
+        assert_eq!(stored.width, Some(800));
+        assert!(repo.find_by_id(&decomposed.id).await.unwrap().is_none());
+    }
+
+    /// 重新扫描时，内容未变的文件保留曝光统计，内容变化后清空
+    #[tokio::test]
+    async fn test_exposure_kept_until_content_changes() {
+        let db = test_db_pool().await;
+        let pool = get_pool(&db);
+        let repo = MediaFileRepository::new(pool);
+
+        let mut file = create_test_media_file("dark.jpg");
+        file.content_hash = Some("hash-a".to_string());
+        repo.batch_upsert(&[file.clone()]).await.unwrap();
+        assert_eq!(repo.find_image_ids_missing_exposure().await.unwrap(), vec![file.id.clone()]);
+        repo.update_exposure(&file.id, 0.1, 0.0, 0.4).await.unwrap();
+        assert!(repo.find_image_ids_missing_exposure().await.unwrap().is_empty());
+
+        let underexposed = repo
+            .find_all(&FileQuery::new().exposure(Some(ExposureFilter::Underexposed)))
+            .await
+            .unwrap();
+        assert_eq!(underexposed.len(), 1);
+        assert_eq!(underexposed[0].mean_luminance, Some(0.1));
+        assert_eq!(repo.count(&FileQuery::new().exposure(Some(ExposureFilter::Overexposed))).await.unwrap(), 0);
+
+        repo.batch_upsert(&[file.clone()]).await.unwrap();
+        assert_eq!(repo.find_by_id(&file.id).await.unwrap().unwrap().clipped_shadows, Some(0.4));
+
+        file.content_hash = Some("hash-b".to_string());
+        repo.upsert(&file).await.unwrap();
+        assert_eq!(repo.find_by_id(&file.id).await.unwrap().unwrap().mean_luminance, None);
+    }
+
+    /// 挂载点变化后改写路径前缀：只匹配完整的路径段，已按新路径重新入库的记录让位给旧记录
+    #[tokio::test]
+    async fn test_rewrite_path_prefix() {
+        let db = test_db_pool().await;
+        let pool = get_pool(&db);
+        let repo = MediaFileRepository::new(pool);
+
+        let moved = create_test_media_file("a.jpg");
+        let mut sibling = create_test_media_file("b.jpg");
+        sibling.file_path = "/test/photos-old/b.jpg".to_string();
+        let mut readded = create_test_media_file("a.jpg");
+        readded.id = "readded".to_string();
+        readded.file_path = "/mnt/photos/a.jpg".to_string();
+        repo.batch_upsert(&[moved.clone(), sibling.clone(), readded]).await.unwrap();
+
+        assert_eq!(repo.rewrite_path_prefix("/test/photos", "/mnt/photos/").await.unwrap(), 1);
+
+        let file = repo.find_by_id(&moved.id).await.unwrap().unwrap();
+        assert_eq!(file.file_path, "/mnt/photos/a.jpg");
+        assert!(repo.find_existing(&[std::path::PathBuf::from("/mnt/photos/a.jpg")]).await.unwrap()
+            .contains_key("/mnt/photos/a.jpg"));
+        assert!(repo.find_by_id("readded").await.unwrap().is_none());
+        let file = repo.find_by_id(&sibling.id).await.unwrap().unwrap();
+        assert_eq!(file.file_path, "/test/photos-old/b.jpg");
+
+        // 再次执行不做任何改动
+        assert_eq!(repo.rewrite_path_prefix("/test/photos", "/mnt/photos").await.unwrap(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_find_paths_missing_field() {
+        use latte_album::db::MetadataField;
+
+        let db = test_db_pool().await;
+        let pool = get_pool(&db);
+        let repo = MediaFileRepository::new(pool);
+
+        let with_gps = {
+            let mut f = create_test_media_file("gps.jpg");
+            f.gps_latitude = Some(39.9);
+            f.gps_longitude = Some(116.4);
+            f
+        };
+        let without_gps = create_test_media_file("nogps.jpg");
+        let video = create_test_media_file_with("clip.mp4", "video", None);
+        repo.batch_upsert(&[with_gps, without_gps, video]).await.unwrap();
+
+        // GPS 只对图片回填，视频不应出现在结果中
+        let paths = repo.find_paths_missing_field(MetadataField::Gps).await.unwrap();
+        assert_eq!(paths, vec!["/test/photos/nogps.jpg".to_string()]);
+
+        let paths = repo.find_paths_missing_field(MetadataField::Width).await.unwrap();
+        assert!(paths.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_sync_raw_pairs() {
+        use std::path::Path;
+
+        let db = test_db_pool().await;
+        let pool = get_pool(&db);
+        let repo = MediaFileRepository::new(pool);
+
+        repo.batch_upsert(&[
+            create_test_media_file("DSC_0001.jpg"),
+            create_test_media_file("DSC_0002.jpg"),
+        ]).await.unwrap();
+
+        let pairs = vec![(
+            "/test/photos/DSC_0001.jpg".to_string(),
+            "/test/photos/DSC_0001.nef".to_string(),
+        )];
+        assert_eq!(repo.sync_raw_pairs(&pairs).await.unwrap(), 1);
+
+        let paired = repo.find_by_path(Path::new("/test/photos/DSC_0001.jpg")).await.unwrap().unwrap();
+        assert_eq!(paired.raw_path.as_deref(), Some("/test/photos/DSC_0001.nef"));
+        let unpaired = repo.find_by_path(Path::new("/test/photos/DSC_0002.jpg")).await.unwrap().unwrap();
+        assert!(unpaired.raw_path.is_none());
+
+        // 再次扫描时 upsert 不应覆盖配对关系
+        repo.upsert(&create_test_media_file("DSC_0001.jpg")).await.unwrap();
+        let paired = repo.find_by_path(Path::new("/test/photos/DSC_0001.jpg")).await.unwrap().unwrap();
+        assert!(paired.raw_path.is_some());
+
+        // 空配对列表清除所有关联（配对关闭或 RAW 被删除）
+        assert_eq!(repo.sync_raw_pairs(&[]).await.unwrap(), 0);
+        let paired = repo.find_by_path(Path::new("/test/photos/DSC_0001.jpg")).await.unwrap().unwrap();
+        assert!(paired.raw_path.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_tagging_results() {
+        use latte_album::db::{MediaLabel, TaggingRepository};
+
+        let db = test_db_pool().await;
+        let pool = get_pool(&db);
+        let repo = MediaFileRepository::new(pool);
+        let tagging = TaggingRepository::new(pool);
+
+        let image = create_test_media_file("cat.jpg");
+        let video = create_test_media_file_with("clip.mp4", "video", None);
+        repo.batch_upsert(&[image.clone(), video]).await.unwrap();
+
+        // 只有图片会发给打标签服务
+        let untagged = tagging.find_untagged(10).await.unwrap();
+        assert_eq!(untagged, vec![(image.id.clone(), image.file_path.clone())]);
+
+        let labels = vec![
+            MediaLabel { label: "sofa".to_string(), score: Some(0.4) },
+            MediaLabel { label: "cat".to_string(), score: Some(0.9) },
+        ];
+        tagging
+            .save_result(&image.id, Some("clip"), &labels, Some(&[0.1, 0.2, 0.3]))
+            .await
+            .unwrap();
+
+        assert!(tagging.find_untagged(10).await.unwrap().is_empty());
+        let stored = tagging.find_labels(&image.id).await.unwrap();
+        assert_eq!(stored.iter().map(|l| l.label.as_str()).collect::<Vec<_>>(), vec!["cat", "sofa"]);
+
+        // 删除文件时级联清理标签
+        repo.delete_by_id(&image.id).await.unwrap();
+        assert!(tagging.find_labels(&image.id).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_video_timeline_versioned_by_modify_time() {
+        let db = test_db_pool().await;
+        let pool = get_pool(&db);
+        let repo = MediaFileRepository::new(pool);
+
+        let video = create_test_media_file_with("clip.mp4", "video", None);
+        repo.batch_upsert(std::slice::from_ref(&video)).await.unwrap();
+
+        let timeline = r#"{"chapters":[],"keyframes":[0.0,2.0]}"#;
+        repo.save_timeline(&video.id, video.modify_time, timeline).await.unwrap();
+        assert_eq!(
+            repo.find_timeline(&video.id, video.modify_time).await.unwrap().as_deref(),
+            Some(timeline)
+        );
+
+        // 文件被修改后缓存的时间线失效
+        let modified = Utc.timestamp_opt(1800000000, 0).unwrap().naive_utc();
+        assert!(repo.find_timeline(&video.id, Some(modified)).await.unwrap().is_none());
+
+        repo.delete_by_id(&video.id).await.unwrap();
+        assert!(repo.find_timeline(&video.id, video.modify_time).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_sub_images_replaced_by_path() {
+        let db = test_db_pool().await;
+        let pool = get_pool(&db);
+        let repo = MediaFileRepository::new(pool);
+
+        let burst = create_test_media_file_with("burst.heic", "image", None);
+        repo.batch_upsert(std::slice::from_ref(&burst)).await.unwrap();
+
+        let items: Vec<MediaSubImage> = (0..3)
+            .map(|index| MediaSubImage {
+                item_index: index,
+                width: Some(4032),
+                height: Some(3024),
+                is_primary: index == 0,
+            })
+            .collect();
+        repo.replace_sub_images(&burst.file_path, &items).await.unwrap();
+        assert_eq!(repo.find_sub_images(&burst.id).await.unwrap(), items);
+
+        // 重新扫描为单图后清空
+        repo.replace_sub_images(&burst.file_path, &[]).await.unwrap();
+        assert!(repo.find_sub_images(&burst.id).await.unwrap().is_empty());
+
+        repo.replace_sub_images(&burst.file_path, &items).await.unwrap();
+        repo.delete_by_id(&burst.id).await.unwrap();
+        assert!(repo.find_sub_images(&burst.id).await.unwrap().is_empty());
+    }
+
+    fn attribute(key: &str, value: &str, source: &str) -> MediaAttribute {
+        MediaAttribute { key: key.to_string(), value: value.to_string(), source: source.to_string() }
+    }
+
+    #[tokio::test]
+    async fn test_attributes_replaced_per_source() {
+        let db = test_db_pool().await;
+        let pool = get_pool(&db);
+        let repo = MediaFileRepository::new(pool);
+        let attributes = AttributeRepository::new(pool);
+
+        let photo = create_test_media_file_with("canon.jpg", "image", None);
+        repo.batch_upsert(std::slice::from_ref(&photo)).await.unwrap();
+
+        attributes.set(&photo.id, &attribute("scene", "beach", "classifier")).await.unwrap();
+        repo.replace_attributes(
+            &photo.file_path,
+            "exif",
+            &[attribute("shot_mode", "Portrait", "exif"), attribute("serial_number", "0000123456", "exif")],
+        )
+        .await
+        .unwrap();
+        let keys: Vec<String> = attributes.find_by_file(&photo.id).await.unwrap().into_iter().map(|a| a.key).collect();
+        assert_eq!(keys, ["scene", "serial_number", "shot_mode"]);
+
+        // 重新扫描只替换同一来源的属性
+        repo.replace_attributes(&photo.file_path, "exif", &[attribute("shot_mode", "Night", "exif")])
+            .await
+            .unwrap();
+        let stored = attributes.find_by_file(&photo.id).await.unwrap();
+        assert_eq!(stored, [attribute("scene", "beach", "classifier"), attribute("shot_mode", "Night", "exif")]);
+
+        // 同名属性按来源分别保存，扫描不会覆盖或删除其他来源的值
+        attributes.set(&photo.id, &attribute("shot_mode", "Portrait", "classifier")).await.unwrap();
+        repo.replace_attributes(&photo.file_path, "exif", &[attribute("shot_mode", "Sports", "exif")])
+            .await
+            .unwrap();
+        repo.replace_attributes(&photo.file_path, "exif", &[]).await.unwrap();
+        assert_eq!(
+            attributes.find(&photo.id, "classifier", "shot_mode").await.unwrap(),
+            Some(attribute("shot_mode", "Portrait", "classifier"))
+        );
+
+        attributes.set(&photo.id, &attribute("scene", "forest", "classifier")).await.unwrap();
+        assert_eq!(attributes.find(&photo.id, "classifier", "scene").await.unwrap().unwrap().value, "forest");
+        assert!(attributes.delete(&photo.id, "classifier", "scene").await.unwrap());
+        assert!(!attributes.delete(&photo.id, "classifier", "scene").await.unwrap());
+
+        repo.delete_by_id(&photo.id).await.unwrap();
+        assert!(attributes.find_by_file(&photo.id).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_repository_tx_commit_and_rollback() {
+        let db = test_db_pool().await;
+        let pool = get_pool(&db);
+        let repo = MediaFileRepository::new(pool);
+
+        let burst = create_test_media_file_with("burst.heic", "image", None);
+        let items = vec![MediaSubImage { item_index: 0, width: Some(10), height: Some(10), is_primary: true }];
+
+        // 回滚：文件与子图都不写入
+        let mut tx = RepositoryTx::begin(pool).await.unwrap();
+        tx.media_files().upsert(&burst).await.unwrap();
+        tx.media_files().replace_sub_images(&burst.file_path, &items).await.unwrap();
+        tx.rollback().await.unwrap();
+        assert!(repo.find_by_id(&burst.id).await.unwrap().is_none());
+
+        // 未提交就丢弃等同回滚
+        {
+            let mut tx = RepositoryTx::begin(pool).await.unwrap();
+            tx.media_files().upsert(&burst).await.unwrap();
+        }
+        assert!(repo.find_by_id(&burst.id).await.unwrap().is_none());
+
+        let mut tx = RepositoryTx::begin(pool).await.unwrap();
+        tx.media_files().upsert(&burst).await.unwrap();
+        tx.media_files().replace_sub_images(&burst.file_path, &items).await.unwrap();
+        tx.media_files().update_thumbnail_status(&burst.id, true).await.unwrap();
+        tx.commit().await.unwrap();
+
+        let stored = repo.find_by_id(&burst.id).await.unwrap().expect("committed file");
+        assert!(stored.thumbnail_generated);
+        assert_eq!(repo.find_sub_images(&burst.id).await.unwrap(), items);
+    }
+
+    /// 写入媒体文件的仓储方法同时追加变更日志；每个文件只返回游标之后的最新一次变化
+    #[tokio::test]
+    async fn test_change_log() {
+        let db = test_db_pool().await;
+        let pool = get_pool(&db);
+        let repo = MediaFileRepository::new(pool);
+        let changes = ChangeLogRepository::new(pool);
+        assert_eq!(changes.latest_seq().await.unwrap(), 0);
+
+        let files = vec![create_test_media_file("a.jpg"), create_test_media_file("b.jpg")];
+        repo.batch_upsert(&files).await.unwrap();
+        let after_insert = changes.latest_seq().await.unwrap();
+        assert_eq!(after_insert, 2);
+        let kinds = |entries: Vec<latte_album::db::MediaChange>| {
+            entries.into_iter().map(|e| (e.file_id, e.change)).collect::<Vec<_>>()
+        };
+        assert_eq!(
+            kinds(changes.find_since(0, 10).await.unwrap()),
+            vec![(files[0].id.clone(), "created".to_string()), (files[1].id.clone(), "created".to_string())]
+        );
+
+        // 重新扫描保留原 id，记为 updated；删除记为 deleted
+        let mut rescanned = create_test_media_file("a.jpg");
+        rescanned.width = Some(100);
+        repo.upsert(&rescanned).await.unwrap();
+        repo.delete_by_id(&files[1].id).await.unwrap();
+        assert_eq!(
+            kinds(changes.find_since(after_insert, 10).await.unwrap()),
+            vec![(files[0].id.clone(), "updated".to_string()), (files[1].id.clone(), "deleted".to_string())]
+        );
+        assert_eq!(changes.find_since(0, 10).await.unwrap().len(), 2);
+        assert_eq!(changes.find_since(0, 1).await.unwrap()[0].seq, 3);
+
+        // 只更新 last_scanned 不算变化
+        repo.batch_touch([std::path::PathBuf::from(&files[0].file_path)].iter()).await.unwrap();
+        assert_eq!(changes.latest_seq().await.unwrap(), 4);
+
+        // 缩略图状态、曝光统计与 RAW 配对变化时记录，值不变时不记录
+        repo.update_thumbnail_status(&files[0].id, true).await.unwrap();
+        repo.update_thumbnail_status(&files[0].id, true).await.unwrap();
+        repo.update_exposure(&files[0].id, 0.5, 0.01, 0.02).await.unwrap();
+        repo.update_exposure(&files[0].id, 0.5, 0.01, 0.02).await.unwrap();
+        let raw = (files[0].file_path.clone(), "/test/photos/a.dng".to_string());
+        repo.sync_raw_pairs(std::slice::from_ref(&raw)).await.unwrap();
+        repo.sync_raw_pairs(std::slice::from_ref(&raw)).await.unwrap();
+        assert_eq!(changes.latest_seq().await.unwrap(), 7);
+        repo.sync_raw_pairs(&[]).await.unwrap();
+        assert_eq!(changes.latest_seq().await.unwrap(), 8);
+
+        // 清理后游标仍然递增
+        let future = Utc::now().naive_utc() + chrono::Duration::days(1);
+        assert_eq!(changes.prune(future).await.unwrap(), 8);
+        assert_eq!(changes.oldest_seq().await.unwrap(), None);
+        assert_eq!(changes.latest_seq().await.unwrap(), 8);
+        repo.upsert(&create_test_media_file("c.jpg")).await.unwrap();
+        assert_eq!(changes.oldest_seq().await.unwrap(), Some(9));
+    }
+
+    /// 只读副本：浏览查询读取副本，写入仍走主连接池，副本连接拒绝写入
+    #[tokio::test]
+    async fn test_read_replica() {
+        let db = test_db_pool().await;
+        let db_path = db._temp_dir.path().join("test.db");
+        let pool = get_pool(&db).clone().with_read_replica(&db_path).await.unwrap();
+        assert!(pool.has_read_replica());
+        assert!(!get_pool(&db).has_read_replica());
+
+        let repo = MediaFileRepository::new(&pool);
+        repo.upsert(&create_test_media_file("a.jpg")).await.unwrap();
+        assert_eq!(repo.count(&FileQuery::new()).await.unwrap(), 1);
+
+        let write = sqlx::query("DELETE FROM media_files").execute(pool.read_pool()).await;
+        assert!(write.is_err());
+
+        let missing = db._temp_dir.path().join("missing.db");
+        assert!(get_pool(&db).clone().with_read_replica(&missing).await.is_err());
+    }
 }
@@ -0,0 +1,57 @@
+//! Regression tests over a seeded synthetic library
+
+#[cfg(test)]
+mod tests {
+    use latte_album::db::{DatabasePool, FileQuery, MediaFileRepository, SortField, SortOrder};
+    use latte_album::fixtures::SyntheticLibrary;
+
+    const LIBRARY_SIZE: usize = 2_000;
+
+    async fn seeded_db(dir: &tempfile::TempDir) -> DatabasePool {
+        let pool = DatabasePool::new(&dir.path().join("test.db"))
+            .await
+            .expect("Failed to create database pool");
+        pool.migrate(std::path::Path::new("./src/db/migrations"))
+            .await
+            .expect("Failed to run migrations");
+        SyntheticLibrary::new(42)
+            .seed_database(&pool, LIBRARY_SIZE)
+            .await
+            .expect("Failed to seed database");
+        pool
+    }
+
+    #[tokio::test]
+    async fn test_pages_cover_library_once() {
+        let dir = tempfile::tempdir().unwrap();
+        let pool = seeded_db(&dir).await;
+        let repo = MediaFileRepository::new(&pool);
+
+        let mut ids = std::collections::HashSet::new();
+        for page in 0..(LIBRARY_SIZE as i32 / 100) {
+            let query = FileQuery::new().sort(SortField::FileName, SortOrder::Asc).page(page, 100);
+            for file in repo.find_all(&query).await.unwrap() {
+                assert!(ids.insert(file.id));
+            }
+        }
+        assert_eq!(ids.len(), LIBRARY_SIZE);
+        assert_eq!(repo.count(&FileQuery::new()).await.unwrap(), LIBRARY_SIZE as i64);
+    }
+
+    #[tokio::test]
+    async fn test_count_agrees_with_filtered_listing() {
+        let dir = tempfile::tempdir().unwrap();
+        let pool = seeded_db(&dir).await;
+        let repo = MediaFileRepository::new(&pool);
+
+        let query = FileQuery::new()
+            .file_type(Some("image"))
+            .camera_model(Some("X100V"))
+            .date(Some("2020"))
+            .max_blur_score(Some(500.0))
+            .page(0, LIBRARY_SIZE as i32);
+        let files = repo.find_all(&query).await.unwrap();
+        assert!(!files.is_empty());
+        assert_eq!(repo.count(&query).await.unwrap(), files.len() as i64);
+    }
+}
@@ -71,6 +71,8 @@ pub fn create_test_media_file(file_name: &str) -> latte_album::db::MediaFile {
         height: Some(1080),
         exif_timestamp: Some(timestamp.naive_utc()),
         exif_timezone_offset: Some("+08:00".to_string()),
+        filename_timestamp: None,
+        date_source: Some("exif".to_string()),
         create_time: Some(timestamp.naive_utc()),
         modify_time: Some(timestamp.naive_utc()),
         last_scanned: Some(Utc::now().naive_utc()),
@@ -83,7 +85,26 @@ pub fn create_test_media_file(file_name: &str) -> latte_album::db::MediaFile {
         focal_length: Some("50mm".to_string()),
         duration: None,
         video_codec: None,
+        raw_path: None,
+        title: None,
+        description: None,
+        version: 1,
+        updated_at: None,
         thumbnail_generated: false,
+        has_depth_map: false,
+        has_portrait_matte: false,
+        projection: None,
+        hdr_format: None,
+        bit_depth: None,
+        color_primaries: None,
+        color_profile: None,
+        content_hash: None,
+        pending_extraction: false,
+        blur_score: None,
+        orientation: None,
+        mean_luminance: None,
+        clipped_highlights: None,
+        clipped_shadows: None,
         gps_latitude: None,
         gps_longitude: None,
     }
@@ -114,6 +135,8 @@ pub fn create_test_media_file_with(
         height: Some(1080),
         exif_timestamp: Some(timestamp),
         exif_timezone_offset: Some("+08:00".to_string()),
+        filename_timestamp: None,
+        date_source: Some("exif".to_string()),
         create_time: Some(timestamp),
         modify_time: Some(timestamp),
         last_scanned: Some(Utc::now().naive_utc()),
@@ -126,7 +149,26 @@ pub fn create_test_media_file_with(
         focal_length: Some("50mm".to_string()),
         duration: if file_type == "video" { Some(10.0) } else { None },
         video_codec: if file_type == "video" { Some("H264".to_string()) } else { None },
+        raw_path: None,
+        title: None,
+        description: None,
+        version: 1,
+        updated_at: None,
         thumbnail_generated: false,
+        has_depth_map: false,
+        has_portrait_matte: false,
+        projection: None,
+        hdr_format: None,
+        bit_depth: None,
+        color_primaries: None,
+        color_profile: None,
+        content_hash: None,
+        pending_extraction: false,
+        blur_score: None,
+        orientation: None,
+        mean_luminance: None,
+        clipped_highlights: None,
+        clipped_shadows: None,
         gps_latitude: None,
         gps_longitude: None,
     }
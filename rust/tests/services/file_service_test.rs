@@ -5,8 +5,8 @@ mod tests {
     use std::path::PathBuf;
     use bytes::Bytes;
     use tempfile::Builder;
-    use latte_album::fixtures::TestFixtures;
-    use latte_album::db::DatabasePool;
+    use latte_album::fixtures::{create_test_media_file, TestFixtures};
+    use latte_album::db::{DatabasePool, MediaFileRepository};
     use latte_album::services::CacheService;
     use latte_album::config::Config;
 
@@ -43,6 +43,93 @@ mod tests {
         assert!(missed.is_none());
     }
 
+    #[tokio::test]
+    async fn test_cache_sharded_layout_and_migration() {
+        let cache_dir = Builder::new()
+            .prefix("latte_test_cache_")
+            .tempdir()
+            .expect("Failed to create cache dir");
+        let cache_dir_path = PathBuf::from(cache_dir.path());
+
+        // 旧版平铺布局的缓存文件与不相关的子目录
+        std::fs::write(cache_dir_path.join("abcd1234_small"), b"legacy").unwrap();
+        std::fs::create_dir_all(cache_dir_path.join("backups")).unwrap();
+
+        let config = Config::default();
+        let cache = CacheService::new(
+            &cache_dir_path,
+            config.cache_max_capacity,
+            config.cache_ttl_seconds,
+        ).await.expect("Failed to create cache service");
+
+        // 迁移前仍可从旧路径读取
+        assert_eq!(
+            cache.get_thumbnail_disk_path("abcd1234", "small").await,
+            Some(cache_dir_path.join("abcd1234_small"))
+        );
+
+        assert_eq!(cache.migrate_flat_layout().await.unwrap(), 1);
+        assert!(!cache_dir_path.join("abcd1234_small").exists());
+        assert_eq!(
+            cache.get_thumbnail_disk_path("abcd1234", "small").await,
+            Some(cache_dir_path.join("ab").join("cd").join("abcd1234_small"))
+        );
+        assert!(cache_dir_path.join("backups").is_dir());
+
+        // 新写入直接进入分片目录，临时目录不留残余
+        cache.put_thumbnail_bytes("ef012345", "medium", Bytes::from_static(b"new")).await.unwrap();
+        assert_eq!(
+            std::fs::read(cache_dir_path.join("ef").join("01").join("ef012345_medium")).unwrap(),
+            b"new"
+        );
+        assert_eq!(std::fs::read_dir(cache_dir_path.join("tmp")).unwrap().count(), 0);
+        assert_eq!(cache.get_cache_size_bytes().await.unwrap(), 9);
+    }
+
+    #[tokio::test]
+    async fn test_cache_keyed_by_content_hash() {
+        let db_dir = Builder::new().prefix("latte_test_db_").tempdir().unwrap();
+        let pool = DatabasePool::new(&db_dir.path().join("test.db")).await.unwrap();
+        pool.migrate(std::path::Path::new("./src/db/migrations")).await.unwrap();
+
+        // 同一内容的两个文件（如移动后重新导入），以及一个尚未计算哈希的文件
+        let mut original = create_test_media_file("original.jpg");
+        original.content_hash = Some("9f86d081884c7d659a2feaa0c55ad015a3bf4f1b2b0b822cd15d6c15b0f00a08".to_string());
+        let mut reimported = create_test_media_file("reimported.jpg");
+        reimported.content_hash = original.content_hash.clone();
+        let unhashed = create_test_media_file("unhashed.jpg");
+        MediaFileRepository::new(&pool)
+            .batch_upsert(&[original.clone(), reimported.clone(), unhashed.clone()])
+            .await
+            .unwrap();
+
+        let cache_dir = Builder::new()
+            .prefix("latte_test_cache_")
+            .tempdir()
+            .expect("Failed to create cache dir");
+        let cache_dir_path = PathBuf::from(cache_dir.path());
+        let config = Config::default();
+        let cache = CacheService::new(
+            &cache_dir_path,
+            config.cache_max_capacity,
+            config.cache_ttl_seconds,
+        ).await.expect("Failed to create cache service").with_content_hashes(pool.clone());
+
+        cache.put_thumbnail_bytes(&original.id, "small", Bytes::from_static(b"thumb")).await.unwrap();
+        let path = cache.get_thumbnail_disk_path(&reimported.id, "small").await.unwrap();
+        assert_eq!(
+            path,
+            cache_dir_path.join("9f").join("86")
+                .join("9f86d081884c7d659a2feaa0c55ad015a3bf4f1b2b0b822cd15d6c15b0f00a08_small_jpeg")
+        );
+        assert_eq!(cache.get_thumbnail(&reimported.id, "small").await.unwrap(), Bytes::from_static(b"thumb"));
+
+        // 没有哈希时按 id 存放
+        cache.put_thumbnail_bytes(&unhashed.id, "small", Bytes::from_static(b"by id")).await.unwrap();
+        let path = cache.get_thumbnail_disk_path(&unhashed.id, "small").await.unwrap();
+        assert!(path.ends_with(format!("{}_small", unhashed.id)));
+    }
+
     #[tokio::test]
     async fn test_cache_size_calculation() {
         let (_fixtures, _photos_dir) = TestFixtures::new();
@@ -2,3 +2,4 @@
 
 pub mod scan_service_test;
 pub mod file_service_test;
+pub mod tombstone_test;
@@ -4,9 +4,9 @@
 mod tests {
     use tokio::time::Duration;
     use latte_album::fixtures::TestFixtures;
-    use latte_album::db::{DatabasePool, MediaFileRepository};
+    use latte_album::db::{DatabasePool, FileQuery, MediaFileRepository};
     use latte_album::processors::ProcessorRegistry;
-    use latte_album::services::ScanService;
+    use latte_album::services::{ScanMode, ScanService};
     use latte_album::config::Config;
     use latte_album::websocket::ScanStateManager;
     use tempfile::TempDir;
@@ -41,7 +41,11 @@ mod tests {
 
         let (tx, _rx) = tokio::sync::broadcast::channel(100);
         let scan_state = std::sync::Arc::new(ScanStateManager::new(tx));
-        let processors = std::sync::Arc::new(ProcessorRegistry::new(None));
+        let mut processors = ProcessorRegistry::new(None);
+        processors.register(std::sync::Arc::new(
+            latte_album::processors::image_processor::StandardImageProcessor::new(),
+        ));
+        let processors = std::sync::Arc::new(processors);
 
         let scan_service = ScanService::new(
             config,
@@ -83,7 +87,7 @@ mod tests {
 
         // Verify completed with 0 files
         let repo = MediaFileRepository::new(&db);
-        let files = repo.find_all(None, None, None, None, "exif_timestamp", "desc", 0, 100)
+        let files = repo.find_all(&FileQuery::new().page(0, 100))
             .await
             .unwrap();
         assert_eq!(files.len(), 0);
@@ -101,7 +105,7 @@ mod tests {
 
         // Get initial file count
         let repo = MediaFileRepository::new(&db);
-        let initial_count = repo.find_all(None, None, None, None, "exif_timestamp", "desc", 0, 1000)
+        let initial_count = repo.find_all(&FileQuery::new().page(0, 1000))
             .await
             .unwrap()
             .len();
@@ -111,7 +115,7 @@ mod tests {
         tokio::time::sleep(Duration::from_millis(500)).await;
 
         // Get file count after second scan
-        let final_count = repo.find_all(None, None, None, None, "exif_timestamp", "desc", 0, 1000)
+        let final_count = repo.find_all(&FileQuery::new().page(0, 1000))
             .await
             .unwrap()
             .len();
@@ -119,4 +123,212 @@ mod tests {
         // Count should be the same
         assert_eq!(initial_count, final_count);
     }
+
+    #[tokio::test]
+    async fn test_concurrent_scans_are_queued_and_drained() {
+        let (_fixtures, photos_dir) = TestFixtures::new();
+        let (scan_service, db, _, _) = create_test_scan_service(&photos_dir).await;
+
+        // 并发请求：未能开始的请求进入队列，由正在运行的扫描结束后接着执行
+        tokio::join!(
+            scan_service.scan(),
+            scan_service.scan_with_mode(ScanMode::Force),
+            scan_service.scan(),
+        );
+
+        assert!(!scan_service.is_scanning());
+        assert_eq!(scan_service.queued_scan(), None);
+
+        let repo = MediaFileRepository::new(&db);
+        let files = repo.find_all(&FileQuery::new().page(0, 1000))
+            .await
+            .unwrap();
+        assert!(!files.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_scan_report_records_processed_files() {
+        let (_fixtures, photos_dir) = TestFixtures::new();
+        let (scan_service, _db, _, _) = create_test_scan_service(&photos_dir).await;
+        assert!(scan_service.last_report().is_none());
+
+        scan_service.scan_with_mode(ScanMode::Force).await;
+
+        let report = scan_service.last_report().expect("report after scan");
+        assert_eq!(report.status, "completed");
+        assert_eq!(report.mode, ScanMode::Force);
+        assert!(report.total_files > 0);
+        assert_eq!(report.slowest_files.len(), (report.processed_files as usize).min(20));
+        assert!(!report.largest_directories.is_empty());
+        let listed: u64 = report.largest_directories.iter().map(|d| d.file_count).sum();
+        assert!(listed <= report.total_files);
+    }
+
+    #[tokio::test]
+    async fn test_scan_force_mode_reprocesses_unchanged_files() {
+        let (_fixtures, photos_dir) = TestFixtures::new();
+        image::RgbImage::new(4, 4)
+            .save(photos_dir.join("force.png"))
+            .expect("Failed to write test image");
+
+        let (scan_service, db, _, _) = create_test_scan_service(&photos_dir).await;
+        let repo = MediaFileRepository::new(&db);
+
+        scan_service.scan().await;
+        let first = repo.find_by_path(&photos_dir.join("force.png"))
+            .await
+            .unwrap()
+            .expect("File should be indexed after first scan");
+        assert_eq!(first.width, Some(4));
+
+        // 模拟旧版本提取逻辑遗漏的字段，force 模式应重新提取并回填
+        sqlx::query("UPDATE media_files SET width = NULL WHERE id = ?")
+            .bind(&first.id)
+            .execute(db.get_pool())
+            .await
+            .unwrap();
+
+        scan_service.scan_with_mode(ScanMode::Force).await;
+        let second = repo.find_by_path(&photos_dir.join("force.png"))
+            .await
+            .unwrap()
+            .expect("File should still be indexed after force scan");
+
+        // id 保持稳定，缺失字段被回填
+        assert_eq!(second.id, first.id);
+        assert_eq!(second.width, Some(4));
+    }
+
+    #[tokio::test]
+    async fn test_quick_scan_defers_extraction_to_follow_up_scan() {
+        let (_fixtures, photos_dir) = TestFixtures::new();
+        image::RgbImage::new(4, 4)
+            .save(photos_dir.join("quick.png"))
+            .expect("Failed to write test image");
+
+        let (scan_service, db, _, _) = create_test_scan_service(&photos_dir).await;
+        let repo = MediaFileRepository::new(&db);
+
+        // 快速扫描结束后自动排队一次增量扫描，由它补全元数据
+        scan_service.scan_with_mode(ScanMode::Quick).await;
+        assert_eq!(scan_service.queued_scan(), None);
+        assert_eq!(scan_service.last_report().unwrap().mode, ScanMode::Incremental);
+
+        let file = repo.find_by_path(&photos_dir.join("quick.png"))
+            .await
+            .unwrap()
+            .expect("File should be indexed after quick scan");
+        assert_eq!(file.width, Some(4));
+        assert!(!file.pending_extraction);
+        assert_eq!(repo.count_pending_extraction().await.unwrap(), 0);
+
+        // 待提取的记录即使修改时间未变也要重新提取
+        sqlx::query("UPDATE media_files SET width = NULL, pending_extraction = 1 WHERE id = ?")
+            .bind(&file.id)
+            .execute(db.get_pool())
+            .await
+            .unwrap();
+        scan_service.scan().await;
+        let file = repo.find_by_path(&photos_dir.join("quick.png"))
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(file.width, Some(4));
+        assert!(!file.pending_extraction);
+    }
+
+    #[tokio::test]
+    async fn test_scan_skips_small_and_temporary_files() {
+        let (_fixtures, photos_dir) = TestFixtures::new();
+        let image = image::RgbImage::new(4, 4);
+        image.save(photos_dir.join("keep.png")).unwrap();
+        image.save(photos_dir.join(".hidden.png")).unwrap();
+        image.save(photos_dir.join("~$locked.png")).unwrap();
+        std::fs::write(photos_dir.join("empty.png"), b"").unwrap();
+        std::fs::create_dir(photos_dir.join(".cache")).unwrap();
+        image.save(photos_dir.join(".cache").join("inner.png")).unwrap();
+
+        let (scan_service, db, _, _) = create_test_scan_service(&photos_dir).await;
+        scan_service.scan().await;
+
+        let repo = MediaFileRepository::new(&db);
+        let files = repo.find_all(&FileQuery::new().page(0, 100))
+            .await
+            .unwrap();
+        let names: Vec<&str> = files.iter().map(|f| f.file_name.as_str()).collect();
+        assert_eq!(names, vec!["keep.png"]);
+    }
+
+    #[tokio::test]
+    async fn test_scan_respects_ignore_markers_and_folder_config() {
+        use latte_album::db::DirectoryRepository;
+
+        let (_fixtures, photos_dir) = TestFixtures::new();
+        let image = image::RgbImage::new(4, 4);
+        let trip = photos_dir.join("trip");
+        let private = photos_dir.join("private");
+        std::fs::create_dir_all(private.join("nested")).unwrap();
+        std::fs::create_dir(&trip).unwrap();
+        image.save(trip.join("cover.png")).unwrap();
+        image.save(private.join("nested").join("secret.png")).unwrap();
+        std::fs::write(private.join(".nomedia"), b"").unwrap();
+        std::fs::write(trip.join(".latte.json"), br#"{"displayName": "Kyoto", "cover": "cover.png"}"#).unwrap();
+
+        let (scan_service, db, _, _) = create_test_scan_service(&photos_dir).await;
+        scan_service.scan().await;
+
+        let repo = MediaFileRepository::new(&db);
+        let files = repo.find_all(&FileQuery::new().page(0, 100))
+            .await
+            .unwrap();
+        let names: Vec<&str> = files.iter().map(|f| f.file_name.as_str()).collect();
+        assert_eq!(names, vec!["cover.png"]);
+
+        let directories = DirectoryRepository::new(&db).find_all().await.unwrap();
+        assert!(directories.iter().all(|d| !d.path.contains("private")));
+        let trip_dir = directories.iter().find(|d| d.name == "trip").expect("trip directory");
+        assert_eq!(trip_dir.display_name.as_deref(), Some("Kyoto"));
+        assert_eq!(trip_dir.cover_id.as_deref(), Some(files[0].id.as_str()));
+    }
+
+    /// Google Takeout 旁车文件补全没有 EXIF 的照片的时间、位置和描述
+    #[tokio::test]
+    async fn test_scan_reads_takeout_sidecar() {
+        let (_fixtures, photos_dir) = TestFixtures::new();
+        image::RgbImage::new(4, 4).save(photos_dir.join("IMG_1234.png")).unwrap();
+        std::fs::write(
+            photos_dir.join("IMG_1234.png.json"),
+            br#"{
+                "title": "IMG_1234.png",
+                "description": "Eiffel Tower",
+                "photoTakenTime": { "timestamp": "1500000000" },
+                "geoData": { "latitude": 48.8584, "longitude": 2.2945 }
+            }"#,
+        )
+        .unwrap();
+
+        let (scan_service, db, _, _) = create_test_scan_service(&photos_dir).await;
+        scan_service.scan().await;
+
+        let repo = MediaFileRepository::new(&db);
+        let file = repo
+            .find_by_path(&photos_dir.join("IMG_1234.png"))
+            .await
+            .unwrap()
+            .expect("scanned file");
+        assert_eq!(
+            file.exif_timestamp,
+            chrono::DateTime::from_timestamp(1_500_000_000, 0).map(|t| t.naive_utc())
+        );
+        assert_eq!(file.exif_timezone_offset.as_deref(), Some("+00:00"));
+        assert_eq!(file.date_source.as_deref(), Some("sidecar"));
+        assert_eq!(file.gps_latitude, Some(48.8584));
+        assert_eq!(file.description.as_deref(), Some("Eiffel Tower"));
+
+        // 旁车文件本身不是媒体文件
+        let files = repo.find_all(&FileQuery::new().page(0, 100))
+            .await
+            .unwrap();
+        assert_eq!(files.len(), 1);
+    }
 }
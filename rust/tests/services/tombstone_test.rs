@@ -0,0 +1,62 @@
+//! Tombstone (deleted file) check integration tests
+
+#[cfg(test)]
+mod tests {
+    use latte_album::db::{DatabasePool, MediaFileRepository};
+    use latte_album::fixtures::{create_test_media_file, TestFixtures};
+    use latte_album::services::tombstone::{check_file, check_sample};
+
+    async fn create_test_db(dir: &std::path::Path) -> DatabasePool {
+        let db = DatabasePool::new(&dir.join("test.db"))
+            .await
+            .expect("Failed to create database pool");
+        db.migrate(std::path::Path::new("./src/db/migrations"))
+            .await
+            .expect("Failed to run migrations");
+        db
+    }
+
+    /// Insert an entry pointing at `photos_dir/name`, creating the file if `exists`
+    async fn insert_file(repo: &MediaFileRepository<'_>, photos_dir: &std::path::Path, name: &str, exists: bool) -> String {
+        let path = photos_dir.join(name);
+        if exists {
+            std::fs::write(&path, b"data").unwrap();
+        }
+        let mut file = create_test_media_file(name);
+        file.file_path = path.to_string_lossy().to_string();
+        repo.upsert(&file).await.unwrap();
+        file.id
+    }
+
+    #[tokio::test]
+    async fn test_check_sample_removes_missing_files() {
+        let (fixtures, photos_dir) = TestFixtures::new();
+        let db = create_test_db(fixtures.photos_dir().parent().unwrap()).await;
+        let repo = MediaFileRepository::new(&db);
+
+        let kept = insert_file(&repo, &photos_dir, "kept.jpg", true).await;
+        let gone = insert_file(&repo, &photos_dir, "gone.jpg", false).await;
+
+        assert_eq!(check_sample(&db, &photos_dir, 100).await.unwrap(), 1);
+        assert!(repo.find_by_id(&kept).await.unwrap().is_some());
+        assert!(repo.find_by_id(&gone).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_check_skipped_when_base_path_unavailable() {
+        let (fixtures, photos_dir) = TestFixtures::new();
+        let db = create_test_db(fixtures.photos_dir().parent().unwrap()).await;
+        let repo = MediaFileRepository::new(&db);
+
+        let gone = insert_file(&repo, &photos_dir, "gone.jpg", false).await;
+
+        // 图库目录不可用（例如 NAS 未挂载）时不能把所有记录当作已删除
+        let unmounted = photos_dir.join("unmounted");
+        assert_eq!(check_sample(&db, &unmounted, 100).await.unwrap(), 0);
+        assert!(!check_file(&db, &unmounted, &gone).await.unwrap());
+        assert!(repo.find_by_id(&gone).await.unwrap().is_some());
+
+        assert!(check_file(&db, &photos_dir, &gone).await.unwrap());
+        assert!(repo.find_by_id(&gone).await.unwrap().is_none());
+    }
+}